@@ -45,9 +45,14 @@ if (( $(echo "$BALANCE < 5" | bc -l) )); then
     fi
 fi
 
-# Build the program
+# Build the program without the localnet-only `verbose-logs` (see verbose_msg!).
+# Mainnet builds also set `mainnet`, which refuses to compile with the `mock` test doubles
 echo "🔨 Building the program..."
-anchor build
+if [ "$CLUSTER" == "mainnet-beta" ]; then
+    anchor build -p subly_program -- --features mainnet
+else
+    anchor build -p subly_program
+fi
 
 # Get program ID
 PROGRAM_ID=$(anchor keys list | grep "subly_program" | cut -d':' -f2 | tr -d ' ')
@@ -55,7 +60,7 @@ echo "📝 Program ID: $PROGRAM_ID"
 
 # Deploy the program
 echo "🚀 Deploying the program..."
-anchor deploy --provider.cluster $CLUSTER
+anchor deploy -p subly_program --provider.cluster $CLUSTER
 
 # Verify deployment
 echo "✅ Verifying deployment..."
@@ -236,7 +236,7 @@ export class SublyIntegration {
     );
 
     const tx = await this.program.methods
-      .deposit(new anchor.BN(amount))
+      .deposit(new anchor.BN(amount), false, null)
       .accounts({
         user: user.publicKey,
         userAccount: userPda,
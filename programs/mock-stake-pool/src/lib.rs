@@ -0,0 +1,227 @@
+//! Localnet stand-in for the SPL stake pool program, the CPI target Subly's
+//! staking instructions are pointed at when built with the `mock` feature.
+//! It answers DepositSol and WithdrawSol at a fixed 1:1 rate without fees, so
+//! stake and unstake flows complete deterministically. The pool account holds
+//! the deposited lamports itself and doubles as the reserve stake account.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount};
+
+declare_id!("9qvANchPf1q3MzkiM2pe99VDnWU8hXF51SjtowC1Hxff");
+
+pub const POOL_SEED: &str = "pool";
+/// Same derivation as the SPL stake pool: [stake_pool, "withdraw"]
+pub const WITHDRAW_AUTHORITY_SEED: &str = "withdraw";
+
+/// One-byte tags of the SPL stake pool instructions answered here, see
+/// spl_stake_pool::instruction::StakePoolInstruction
+pub const DEPOSIT_SOL_TAG: u8 = 14;
+pub const WITHDRAW_SOL_TAG: u8 = 16;
+
+#[program]
+pub mod mock_stake_pool {
+    use super::*;
+
+    pub fn initialize_pool(ctx: Context<InitializePool>) -> Result<()> {
+        ctx.accounts.initialize_pool(&ctx.bumps)
+    }
+
+    /// SPL stake pool instructions carry a one-byte tag rather than an Anchor
+    /// discriminator, so they all arrive here
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        let (tag, amount) = parse_instruction(data)?;
+        match tag {
+            DEPOSIT_SOL_TAG => deposit_sol(program_id, accounts, amount),
+            WITHDRAW_SOL_TAG => withdraw_sol(program_id, accounts, amount),
+            _ => err!(MockStakePoolError::UnsupportedInstruction),
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MockStakePool {
+    pub pool_mint: Pubkey,
+    pub withdraw_authority_bump: u8,
+    pub bump: u8,
+}
+
+#[error_code]
+pub enum MockStakePoolError {
+    #[msg("Only DepositSol and WithdrawSol are supported")]
+    UnsupportedInstruction,
+    #[msg("Account does not belong to this pool")]
+    InvalidPoolAccount,
+    #[msg("Pool mint must be minted by the pool's withdraw authority")]
+    InvalidMintAuthority,
+    #[msg("Pool reserve cannot cover the withdrawal")]
+    InsufficientReserve,
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + MockStakePool::INIT_SPACE,
+        seeds = [POOL_SEED.as_bytes(), pool_mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, MockStakePool>,
+
+    /// CHECK: PDA with no data, only used as the pool mint's authority
+    #[account(
+        seeds = [pool.key().as_ref(), WITHDRAW_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub withdraw_authority: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = pool_mint.mint_authority == Some(withdraw_authority.key()).into()
+            @ MockStakePoolError::InvalidMintAuthority
+    )]
+    pub pool_mint: Account<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializePool<'info> {
+    pub fn initialize_pool(&mut self, bumps: &InitializePoolBumps) -> Result<()> {
+        self.pool.set_inner(MockStakePool {
+            pool_mint: self.pool_mint.key(),
+            withdraw_authority_bump: bumps.withdraw_authority,
+            bump: bumps.pool,
+        });
+        Ok(())
+    }
+}
+
+/// Split SPL stake pool instruction data into its tag and u64 argument
+pub fn parse_instruction(data: &[u8]) -> Result<(u8, u64)> {
+    match data {
+        [tag, amount @ ..] if amount.len() == 8 => {
+            Ok((*tag, u64::from_le_bytes(amount.try_into().unwrap())))
+        }
+        _ => err!(MockStakePoolError::UnsupportedInstruction),
+    }
+}
+
+/// Load the pool and check the accounts every instruction shares with it
+fn load_pool<'info>(
+    program_id: &Pubkey,
+    pool_info: &'info AccountInfo<'info>,
+    withdraw_authority: &AccountInfo<'info>,
+    reserve: &AccountInfo<'info>,
+    pool_mint: &AccountInfo<'info>,
+) -> Result<Account<'info, MockStakePool>> {
+    let pool = Account::<MockStakePool>::try_from(pool_info)?;
+    let expected_authority = Pubkey::create_program_address(
+        &[
+            pool_info.key.as_ref(),
+            WITHDRAW_AUTHORITY_SEED.as_bytes(),
+            &[pool.withdraw_authority_bump],
+        ],
+        program_id,
+    )
+    .map_err(|_| MockStakePoolError::InvalidPoolAccount)?;
+    require!(
+        *withdraw_authority.key == expected_authority
+            && reserve.key == pool_info.key
+            && *pool_mint.key == pool.pool_mint,
+        MockStakePoolError::InvalidPoolAccount
+    );
+    Ok(pool)
+}
+
+/// Accounts, in SPL order: stake pool, withdraw authority, reserve, lamports
+/// from (signer), pool tokens to, manager fee, referrer fee, pool mint, system
+/// program, token program. Mints one pool token per lamport
+fn deposit_sol<'info>(
+    program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    lamports: u64,
+) -> Result<()> {
+    let [pool_info, withdraw_authority, reserve, lamports_from, pool_tokens_to, _manager_fee, _referrer_fee, pool_mint, system_program, token_program, ..] =
+        accounts
+    else {
+        return err!(ErrorCode::AccountNotEnoughKeys);
+    };
+    let pool = load_pool(program_id, pool_info, withdraw_authority, reserve, pool_mint)?;
+
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: lamports_from.clone(),
+                to: reserve.clone(),
+            },
+        ),
+        lamports,
+    )?;
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            MintTo {
+                mint: pool_mint.clone(),
+                to: pool_tokens_to.clone(),
+                authority: withdraw_authority.clone(),
+            },
+            &[&[
+                pool_info.key.as_ref(),
+                WITHDRAW_AUTHORITY_SEED.as_bytes(),
+                &[pool.withdraw_authority_bump],
+            ]],
+        ),
+        lamports,
+    )
+}
+
+/// Accounts, in SPL order: stake pool, withdraw authority, user transfer
+/// authority (signer), pool tokens from, reserve, lamports to, manager fee,
+/// pool mint, clock, stake history, stake program, token program. Burns the
+/// pool tokens and pays out the same number of lamports from the reserve
+fn withdraw_sol<'info>(
+    program_id: &Pubkey,
+    accounts: &'info [AccountInfo<'info>],
+    pool_tokens: u64,
+) -> Result<()> {
+    let [pool_info, withdraw_authority, user_transfer_authority, pool_tokens_from, reserve, lamports_to, _manager_fee, pool_mint, _clock, _stake_history, _stake_program, token_program, ..] =
+        accounts
+    else {
+        return err!(ErrorCode::AccountNotEnoughKeys);
+    };
+    load_pool(program_id, pool_info, withdraw_authority, reserve, pool_mint)?;
+    Account::<TokenAccount>::try_from(pool_tokens_from)?;
+    Program::<Token>::try_from(token_program)?;
+
+    token::burn(
+        CpiContext::new(
+            token_program.clone(),
+            Burn {
+                mint: pool_mint.clone(),
+                from: pool_tokens_from.clone(),
+                authority: user_transfer_authority.clone(),
+            },
+        ),
+        pool_tokens,
+    )?;
+
+    // The pool account owns the reserve lamports, so they move without a CPI
+    let rent_floor = Rent::get()?.minimum_balance(reserve.data_len());
+    let available = reserve.lamports().saturating_sub(rent_floor);
+    require!(
+        available >= pool_tokens,
+        MockStakePoolError::InsufficientReserve
+    );
+    **reserve.try_borrow_mut_lamports()? -= pool_tokens;
+    **lamports_to.try_borrow_mut_lamports()? += pool_tokens;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::Pubkey;
+use mock_stake_pool::{parse_instruction, DEPOSIT_SOL_TAG, WITHDRAW_SOL_TAG};
+use spl_stake_pool::instruction as spl_instruction;
+
+fn key() -> Pubkey {
+    Pubkey::new_unique()
+}
+
+#[test]
+fn parses_what_subly_sends_to_the_stake_pool() {
+    let deposit = spl_instruction::deposit_sol(
+        &mock_stake_pool::ID,
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        1_500_000_000,
+    );
+    assert_eq!(
+        parse_instruction(&deposit.data).unwrap(),
+        (DEPOSIT_SOL_TAG, 1_500_000_000)
+    );
+    assert_eq!(deposit.accounts.len(), 10);
+
+    let withdraw = spl_instruction::withdraw_sol(
+        &mock_stake_pool::ID,
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        &key(),
+        250_000_000,
+    );
+    assert_eq!(
+        parse_instruction(&withdraw.data).unwrap(),
+        (WITHDRAW_SOL_TAG, 250_000_000)
+    );
+    assert_eq!(withdraw.accounts.len(), 12);
+}
+
+#[test]
+fn rejects_other_instruction_layouts() {
+    assert!(parse_instruction(&[]).is_err());
+    assert!(parse_instruction(&[DEPOSIT_SOL_TAG, 1, 2, 3]).is_err());
+    // DepositSolWithSlippage carries a second u64
+    assert!(parse_instruction(&[DEPOSIT_SOL_TAG; 17]).is_err());
+}
@@ -10,28 +10,310 @@ pub const TREASURY_SEED: &str = "treasury";
 // Provider related seeds
 pub const PROVIDER_SEED: &str = "provider";
 pub const SUBSCRIPTION_SERVICE_SEED: &str = "subscription_service";
+pub const PROVIDER_EARNINGS_SEED: &str = "provider_earnings"; // Per provider, USDC owed until settlement
 
 // User related seeds
 pub const USER_SEED: &str = "user";
 pub const USER_SUBSCRIPTION_SEED: &str = "user_subscription";
 pub const PAYMENT_RECORD_SEED: &str = "payment_record";
 pub const STAKE_ACCOUNT_SEED: &str = "stake_account";
+pub const WITHDRAWAL_ALLOWLIST_SEED: &str = "withdrawal_allowlist";
 
 // Vault seeds
 pub const SOL_VAULT_SEED: &str = "vault";
 pub const JITO_VAULT_SEED: &str = "jito_vault";
+pub const WSOL_TEMP_SEED: &str = "wsol_temp"; // Per-user wSOL account, opened and closed within deposit_wsol
 
-// Maximum string lengths
+// Certificate seeds
+pub const CERTIFICATE_SEED: &str = "certificate";
+pub const CERTIFICATE_AUTHORITY_SEED: &str = "certificate_authority";
+pub const CERTIFICATE_ATTRIBUTES_SEED: &str = "certificate_attributes";
+pub const CERTIFICATE_COLLECTION_SEED: &str = "certificate_collection";
+pub const RENT_SPONSOR_SEED: &str = "rent_sponsor";
+pub const SPONSOR_ESCROW_SEED: &str = "sponsor_escrow";
+pub const KEEPER_SEED: &str = "keeper"; // Per keeper wallet, allowed to bill in allowlist mode
+pub const CONFIG_CHANGE_SEED: &str = "config_change"; // Per CONFIG_FIELD_* code, a timelocked change
+
+// Promo seeds
+pub const PROMO_CLAIM_SEED: &str = "promo_claim"; // Per provider and nonce, marks a signed grant as used
+
+// Moderation seeds
+pub const REPORT_SEED: &str = "report"; // Per service and reporter, see report_service
+
+// Service registry seeds
+pub const SERVICE_REGISTRY_SEED: &str = "service_registry"; // Per page of service ids, see ServiceRegistryPage
+
+// Localnet test doubles, only used by the `mock` feature
+pub const MOCK_PRICE_FEED_SEED: &str = "mock_price_feed";
+
+// Maximum string lengths. Raising one grows the Provider/SubscriptionService
+// layout; existing accounts are brought up to it with resize_provider/resize_service
 pub const MAX_NAME_LENGTH: usize = 64;
 pub const MAX_DESCRIPTION_LENGTH: usize = 200;
 pub const MAX_URL_LENGTH: usize = 200;
 
+// Certificate metadata (Token Metadata program limits)
+pub const CERTIFICATE_SYMBOL: &str = "SUBLY";
+pub const MAX_CERTIFICATE_NAME_LENGTH: usize = 32;
+pub const MAX_METADATA_ACCOUNT_LENGTH: usize = 679; // Token Metadata MAX_METADATA_LEN
+pub const MAX_CERTIFICATE_ROYALTY_BPS: u16 = 1000; // 10%
+// Fallback metadata JSON: {base}{provider}/{service_id}.json
+pub const DEFAULT_CERTIFICATE_URI_BASE: &str = "https://subly.app/certificates/";
+
+// ServiceUpdated.changed_fields_bitmap flags
+#[constant]
+pub const SERVICE_FIELD_NAME: u8 = 1 << 0;
+#[constant]
+pub const SERVICE_FIELD_DESCRIPTION: u8 = 1 << 1;
+#[constant]
+pub const SERVICE_FIELD_FEE_USD: u8 = 1 << 2;
+#[constant]
+pub const SERVICE_FIELD_BILLING_FREQUENCY: u8 = 1 << 3;
+#[constant]
+pub const SERVICE_FIELD_IMAGE_URL: u8 = 1 << 4;
+#[constant]
+pub const SERVICE_FIELD_CERTIFICATE_MODE: u8 = 1 << 5;
+#[constant]
+pub const SERVICE_FIELD_ROYALTY: u8 = 1 << 6;
+#[constant]
+pub const SERVICE_FIELD_CERTIFICATE_URI: u8 = 1 << 7;
+
+// ProviderUpdated.changed_fields_bitmap flags
+#[constant]
+pub const PROVIDER_FIELD_NAME: u8 = 1 << 0;
+#[constant]
+pub const PROVIDER_FIELD_DESCRIPTION: u8 = 1 << 1;
+
+// SubscriptionService.certificate_mode values
+#[constant]
+pub const CERTIFICATE_MODE_TOKEN: u8 = 0; // Mint + token account + metadata per subscription
+#[constant]
+pub const CERTIFICATE_MODE_COMPRESSED: u8 = 1; // Bubblegum compressed NFT
+
+// GlobalState.execution_mode values: who may trigger billing
+#[constant]
+pub const EXECUTION_MODE_AUTHORITY_ONLY: u8 = 0; // Only the protocol authority
+#[constant]
+pub const EXECUTION_MODE_ALLOWLIST: u8 = 1; // The authority or a registered keeper
+#[constant]
+pub const EXECUTION_MODE_PERMISSIONLESS: u8 = 2; // Anyone; due date and amount checks still apply
+
+// GlobalState.pause_flags bits: each halts one area while the rest keeps running.
+// GlobalState.is_paused stays the master switch over all of them
+#[constant]
+pub const PAUSE_DEPOSITS: u8 = 1 << 0;
+#[constant]
+pub const PAUSE_SUBSCRIPTIONS: u8 = 1 << 1;
+#[constant]
+pub const PAUSE_PAYMENTS: u8 = 1 << 2;
+#[constant]
+pub const PAUSE_STAKING: u8 = 1 << 3;
+pub const PAUSE_FLAGS_ALL: u8 =
+    PAUSE_DEPOSITS | PAUSE_SUBSCRIPTIONS | PAUSE_PAYMENTS | PAUSE_STAKING;
+
+// ConfigChanged.field codes (borsh-serialized values are hashed into the event)
+#[constant]
+pub const CONFIG_FIELD_PROTOCOL_FEE: u8 = 0;
+#[constant]
+pub const CONFIG_FIELD_PAUSED: u8 = 1;
+#[constant]
+pub const CONFIG_FIELD_PRICE_FEED: u8 = 2;
+#[constant]
+pub const CONFIG_FIELD_JITO_STAKE_POOL: u8 = 3;
+#[constant]
+pub const CONFIG_FIELD_JITO_SOL_MINT: u8 = 4;
+#[constant]
+pub const CONFIG_FIELD_SPL_STAKE_POOL_PROGRAM: u8 = 5;
+#[constant]
+pub const CONFIG_FIELD_USDC_MINT: u8 = 6;
+#[constant]
+pub const CONFIG_FIELD_KEEPER_ADDED: u8 = 7;
+#[constant]
+pub const CONFIG_FIELD_KEEPER_REMOVED: u8 = 8;
+#[constant]
+pub const CONFIG_FIELD_SOULBOUND_CERTIFICATES: u8 = 9;
+#[constant]
+pub const CONFIG_FIELD_CERTIFICATE_TREE: u8 = 10;
+#[constant]
+pub const CONFIG_FIELD_SPONSOR_CERTIFICATE_RENT: u8 = 11;
+#[constant]
+pub const CONFIG_FIELD_PAYMENT_RECORD_RETENTION: u8 = 12;
+#[constant]
+pub const CONFIG_FIELD_MIN_DEPOSIT: u8 = 13;
+#[constant]
+pub const CONFIG_FIELD_LIQUID_RESERVE: u8 = 14;
+#[constant]
+pub const CONFIG_FIELD_MAX_DEPOSIT_PER_USER: u8 = 15;
+#[constant]
+pub const CONFIG_FIELD_EXECUTION_MODE: u8 = 16;
+#[constant]
+pub const CONFIG_FIELD_MAX_SERVICES_PER_WINDOW: u8 = 17;
+#[constant]
+pub const CONFIG_FIELD_MAX_SERVICE_FEE: u8 = 18;
+#[constant]
+pub const CONFIG_FIELD_REQUIRE_VERIFIED_PROVIDERS: u8 = 19;
+#[constant]
+pub const CONFIG_FIELD_AUTHORITY: u8 = 20;
+#[constant]
+pub const CONFIG_FIELD_PENDING_AUTHORITY: u8 = 21;
+#[constant]
+pub const CONFIG_FIELD_PAUSE_FLAGS: u8 = 22;
+#[constant]
+pub const CONFIG_FIELD_OPERATOR: u8 = 23;
+#[constant]
+pub const CONFIG_FIELD_CONFIG_TIMELOCK: u8 = 24;
+#[constant]
+pub const CONFIG_FIELD_EMERGENCY_MODE: u8 = 25;
+#[constant]
+pub const CONFIG_FIELD_MAX_PRICE_AGE: u8 = 26;
+#[constant]
+pub const CONFIG_FIELD_SOL_PRICE_BOUNDS: u8 = 27;
+#[constant]
+pub const CONFIG_FIELD_LOCK_PERIODS: u8 = 28;
+#[constant]
+pub const CONFIG_FIELD_FEE_RECIPIENT: u8 = 29;
+#[constant]
+pub const CONFIG_FIELD_RENT_COLLECTOR: u8 = 30;
+#[constant]
+pub const CONFIG_FIELD_VERIFIED_FEE: u8 = 31;
+#[constant]
+pub const CONFIG_FIELD_MAX_PRICE_DEVIATION: u8 = 32;
+#[constant]
+pub const CONFIG_FIELD_PRICE_OVERRIDE: u8 = 33;
+
+// Billing failure reason codes, shared by BillingSkipped, BatchCompleted and PaymentFailed
+#[constant]
+pub const BILLING_REASON_NONE: u8 = 0;
+#[constant]
+pub const BILLING_REASON_INVALID_ACCOUNT: u8 = 1;
+#[constant]
+pub const BILLING_REASON_SUBSCRIPTION_INACTIVE: u8 = 2;
+#[constant]
+pub const BILLING_REASON_SERVICE_INACTIVE: u8 = 3;
+#[constant]
+pub const BILLING_REASON_INSUFFICIENT_BALANCE: u8 = 4;
+#[constant]
+pub const BILLING_REASON_PRICE_UNAVAILABLE: u8 = 5;
+#[constant]
+pub const BILLING_REASON_CERTIFICATE_MISSING: u8 = 6;
+#[constant]
+pub const BILLING_REASON_SERVICE_FROZEN: u8 = 7;
+
+// Why unsubscribe_batch left an entry alone, reported per entry in its summary
+#[constant]
+pub const UNSUBSCRIBE_REASON_NONE: u8 = 0; // Cancelled
+#[constant]
+pub const UNSUBSCRIBE_REASON_INVALID_ACCOUNTS: u8 = 1;
+#[constant]
+pub const UNSUBSCRIBE_REASON_NOT_ACTIVE: u8 = 2;
+#[constant]
+pub const UNSUBSCRIBE_REASON_SPONSORED: u8 = 3; // Escrow refund needs unsubscribe_from_service
+#[constant]
+pub const UNSUBSCRIBE_REASON_COMPRESSED_CERTIFICATE: u8 = 4;
+#[constant]
+pub const UNSUBSCRIBE_REASON_OUT_OF_COMPUTE: u8 = 5;
+
+// Report reason codes for report_service
+#[constant]
+pub const REPORT_REASON_SCAM: u8 = 1;
+#[constant]
+pub const REPORT_REASON_NOT_DELIVERED: u8 = 2;
+#[constant]
+pub const REPORT_REASON_IMPERSONATION: u8 = 3;
+#[constant]
+pub const REPORT_REASON_OTHER: u8 = 4;
+
+// Account layout versions, stored as the first field after the discriminator.
+// Bump a version together with a migration step in utils::migration
+#[constant]
+pub const GLOBAL_STATE_VERSION: u8 = 1;
+// GlobalState size when version 1 shipped; fields appended since are zero-filled by migrate
+pub const GLOBAL_STATE_V1_SPACE: usize = 344;
+#[constant]
+pub const PROVIDER_VERSION: u8 = 1;
+#[constant]
+pub const SUBSCRIPTION_SERVICE_VERSION: u8 = 1;
+#[constant]
+pub const USER_VERSION: u8 = 1;
+#[constant]
+pub const USER_SUBSCRIPTION_VERSION: u8 = 1;
+#[constant]
+pub const STAKE_ACCOUNT_VERSION: u8 = 1;
+#[constant]
+pub const WITHDRAWAL_ALLOWLIST_VERSION: u8 = 1;
+
 // Protocol configuration
 pub const DEFAULT_PROTOCOL_FEE_BPS: u16 = 100; // 1%
 pub const MAX_PROTOCOL_FEE_BPS: u16 = 1000; // 10%
 pub const MIN_SUBSCRIPTION_PERIOD_DAYS: u64 = 7;
 pub const MAX_SUBSCRIPTION_PERIOD_DAYS: u64 = 365;
+pub const PAYMENT_GRACE_PERIOD_SECONDS: i64 = 7 * 86400; // Delinquent access window after the due date
+pub const DEFAULT_PAYMENT_RECORD_RETENTION_DAYS: u64 = 400;
+pub const MIN_PAYMENT_RECORD_RETENTION_DAYS: u64 = 90;
+pub const MAX_PAYMENT_RECORDS_PER_CLOSE: usize = 20;
+pub const DEFAULT_MIN_DEPOSIT_LAMPORTS: u64 = 10_000_000; // 0.01 SOL
+pub const MAX_MIN_DEPOSIT_LAMPORTS: u64 = 10_000_000_000; // 10 SOL, highest minimum the authority may set
+pub const DEFAULT_LIQUID_RESERVE_LAMPORTS: u64 = 100_000_000; // 0.1 SOL
+pub const MAX_WITHDRAWAL_DESTINATIONS: usize = 5;
+pub const WITHDRAWAL_ALLOWLIST_COOLDOWN_SECONDS: i64 = 86400; // After a change while enabled
+pub const MAX_WITHDRAWAL_DELAY_SECONDS: i64 = 30 * 86400;
+pub const MAX_SPONSORED_PERIODS: u64 = 24; // Billing periods one sponsor_subscription call may prepay
+pub const MAX_PROMO_DISCOUNT_BPS: u16 = 10000; // 100%, a free subscription
+pub const PROMO_MESSAGE_DOMAIN: &[u8] = b"subly:promo:v1"; // Prefix of the message a provider signs
+pub const SERVICE_REGISTRATION_WINDOW_SECONDS: i64 = 86400; // Fixed windows aligned to the Unix epoch
+pub const DEFAULT_MAX_SERVICES_PER_WINDOW: u32 = 10; // Unverified providers only
+pub const MAX_KEEPERS: u8 = 16; // Registered keepers at any one time
+pub const MAX_CONFIG_TIMELOCK_SECS: i64 = 30 * 86400;
+
+// Oracle configuration
+// Default sanity range, used while GlobalState's min/max_sol_price_cents are 0
+pub const MIN_SOL_USD_PRICE_CENTS: u64 = 1_000; // $10
+pub const MAX_SOL_USD_PRICE_CENTS: u64 = 100_000; // $1000
+pub const DEFAULT_MAX_PRICE_AGE_SECS: u32 = 300; // Also applies while max_price_age_secs is 0
+pub const MAX_PRICE_AGE_LIMIT_SECS: u32 = 86_400; // Highest max_price_age_secs the authority may set
+pub const MAX_PRICE_OVERRIDE_SECS: i64 = 86_400; // Longest a manual price override may stay valid
+
+// Subscription lock and fee bounds. The bound keeps the lock representable:
+// MAX_SERVICE_FEE_USD_CENTS at MIN_SOL_USD_PRICE_CENTS, times the lock periods, fits in u64
+// Default lock, used while GlobalState's lock_periods is 0 and for subscriptions
+// opened before the lock was recorded on them
+pub const SUBSCRIPTION_LOCK_PERIODS: u64 = 12;
+pub const MAX_SUBSCRIPTION_LOCK_PERIODS: u8 = 24; // Highest lock_periods the authority may set
+pub const MAX_SERVICE_FEE_USD_CENTS: u64 = 1_000_000; // $10,000 per billing period
+// Largest USD amount converted in one go: a sponsorship prepaying the most periods of the largest fee
+pub const MAX_CONVERTIBLE_USD_CENTS: u64 = MAX_SERVICE_FEE_USD_CENTS * MAX_SPONSORED_PERIODS;
 
 // Staking configuration
 pub const MIN_STAKE_AMOUNT: u64 = 1_000_000_000; // 1 SOL in lamports
+pub const MAX_WITHDRAW_UNSTAKE_ROUNDS: usize = 3; // Unstake attempts before a withdrawal gives up
+pub const MAX_DUST_LAMPORTS: u64 = 100_000; // 0.0001 SOL, largest residue sweep_dust clears
+pub const MAX_DUST_JITO_SOL: u64 = 10_000_000; // 0.01 JitoSOL, largest stake sweep_dust redeems
 pub const YIELD_CALCULATION_PERIOD: i64 = 86400; // 24 hours in seconds
+pub const YIELD_APY_BPS: u64 = 500; // 5% APY used by claim_yield
+
+// Service ids per ServiceRegistryPage; must match the length of its services array
+pub const SERVICE_REGISTRY_PAGE_CAPACITY: u64 = 64;
+
+// View return layouts
+pub const PROTOCOL_STATS_VERSION: u8 = 3;
+// Return data is capped at 1024 bytes: 4-byte scanned count + 4-byte vec length
+// + 66 bytes per SubscribableServiceInfo
+pub const MAX_SUBSCRIBABLE_SERVICES_PER_PAGE: usize = 15;
+// 4-byte counters + vec length + 72 bytes per DuePaymentKey
+pub const MAX_DUE_PAYMENTS_PER_PAGE: usize = 13;
+// 4-byte page + vec length + 40 bytes per RegisteredService + 7 bytes of cursors
+pub const MAX_REGISTRY_SERVICES_PER_VIEW: usize = 25;
+// Batch billing emits an event per skipped subscription, so it is capped separately
+pub const MAX_SUBSCRIPTIONS_PER_BATCH: usize = 32;
+// Batch loops stop before an account once fewer compute units than this remain,
+// leaving enough to serialize the result instead of failing mid-account
+pub const MIN_COMPUTE_UNITS_PER_SCAN: u64 = 15_000;
+// unsubscribe_batch entries: (user_subscription, subscription_service, provider_account,
+// certificate mint, certificate token account, certificate attributes)
+pub const UNSUBSCRIBE_BATCH_STRIDE: usize = 6;
+// A transaction locks at most 64 accounts and unsubscribe_batch names 10 itself,
+// leaving room for (64 - 10) / UNSUBSCRIBE_BATCH_STRIDE entries
+pub const MAX_UNSUBSCRIBES_PER_BATCH: usize = 9;
+// Certificate thaw, burn and closes plus the cancellation event, with headroom.
+// MAX_UNSUBSCRIBES_PER_BATCH entries fit in a 1.4M compute unit budget
+pub const MIN_COMPUTE_UNITS_PER_UNSUBSCRIBE: u64 = 60_000;
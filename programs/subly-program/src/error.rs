@@ -12,6 +12,12 @@ pub enum ErrorCode {
     DescriptionTooLong,
     #[msg("Image URL is too long")]
     UrlTooLong,
+    #[msg("Text contains control or invisible characters")]
+    InvalidCharacters,
+    #[msg("Text has leading or trailing whitespace")]
+    UntrimmedText,
+    #[msg("URL must start with https:// or ipfs://")]
+    InvalidUrlScheme,
     #[msg("Invalid fee amount")]
     InvalidFeeAmount,
     #[msg("Invalid billing frequency")]
@@ -34,6 +40,20 @@ pub enum ErrorCode {
     InsufficientAvailableBalance,
     #[msg("Insufficient staked funds")]
     InsufficientStakedFunds,
+    #[msg("Invalid payment record account")]
+    InvalidPaymentRecord,
+    #[msg("Payment record is still within the retention window")]
+    PaymentRecordRetentionActive,
+    #[msg("Payment record is disputed or has a refund pending")]
+    PaymentRecordDisputed,
+    #[msg("Retention period is below the minimum")]
+    InvalidRetentionPeriod,
+    #[msg("Provider has no pending earnings to settle")]
+    NoPendingEarnings,
+    #[msg("USDC treasury does not cover pending provider payouts")]
+    PayoutsUnderfunded,
+    #[msg("Provider earnings are held while one of their services is frozen")]
+    EarningsHeld,
 
     // Subscription errors
     #[msg("Invalid subscription ID")]
@@ -56,14 +76,38 @@ pub enum ErrorCode {
     InvalidProvider,
     #[msg("Invalid service ID")]
     InvalidServiceId,
+    #[msg("Too many accounts passed in one page")]
+    PageTooLarge,
+    #[msg("Service is frozen pending review")]
+    ServiceFrozen,
+    #[msg("Service is not frozen")]
+    ServiceNotFrozen,
+    #[msg("Unknown report reason code")]
+    InvalidReportReason,
 
     // NFT and certificate errors
     #[msg("No certificate to destroy")]
     NoCertificateToDestroy,
+    #[msg("Certificate token program does not match the protocol setting")]
+    InvalidCertificateTokenProgram,
+    #[msg("Service does not issue this kind of certificate")]
+    InvalidCertificateMode,
+    #[msg("Compressed certificate tree is not configured")]
+    CertificateTreeNotConfigured,
+    #[msg("Certificate royalty exceeds the maximum")]
+    RoyaltyTooHigh,
+    #[msg("Certificate metadata URI must use https or ipfs")]
+    InvalidCertificateUri,
+    #[msg("Certificate mint must have a supply of exactly 1 and zero decimals")]
+    InvalidCertificateSupply,
+    #[msg("Certificate mint authority must be the certificate authority PDA")]
+    InvalidCertificateMintAuthority,
 
     // Price feed errors
     #[msg("Invalid price feed")]
     InvalidPriceFeed,
+    #[msg("Settlement mint does not match the configured mint")]
+    InvalidSettlementMint,
     #[msg("Price not available")]
     PriceNotAvailable,
     #[msg("Invalid price")]
@@ -98,4 +142,208 @@ pub enum ErrorCode {
     ArithmeticOverflow,
     #[msg("Arithmetic underflow")]
     ArithmeticUnderflow,
+
+    // Account versioning errors
+    #[msg("Account layout version is not supported by this program; migrate older accounts first")]
+    UnsupportedAccountVersion,
+    #[msg("Account is already on the current layout version")]
+    AccountAlreadyMigrated,
+    #[msg("Account cannot be migrated as this account type")]
+    InvalidMigrationAccount,
+
+    // Accounting invariant errors
+    #[msg("Locked SOL exceeds deposited SOL; user accounting is inconsistent")]
+    LockedExceedsDeposited,
+
+    // Account layout errors
+    #[msg("Account predates the current size limits; resize it before storing longer text")]
+    AccountResizeRequired,
+    #[msg("Stored PDA bump does not match the canonical bump")]
+    InvalidStoredBump,
+
+    // Deposit errors
+    #[msg("First deposit is below the protocol minimum")]
+    DepositTooSmall,
+
+    // Withdrawal errors
+    #[msg("Withdrawal destination is not on the user's allowlist")]
+    WithdrawalDestinationNotAllowed,
+    #[msg("Withdrawal allowlist was changed recently; withdrawals are paused until the cooldown ends")]
+    WithdrawalAllowlistCooldown,
+    #[msg("Too many withdrawal destinations")]
+    TooManyWithdrawalDestinations,
+    #[msg("Withdrawal delay is out of range")]
+    InvalidWithdrawalDelay,
+    #[msg("A withdrawal delay is set; request the withdrawal and execute it after the delay")]
+    WithdrawalDelayActive,
+    #[msg("No withdrawal delay is set; withdraw directly instead")]
+    WithdrawalDelayNotSet,
+    #[msg("A withdrawal is already pending")]
+    WithdrawalAlreadyPending,
+    #[msg("No withdrawal is pending")]
+    NoPendingWithdrawal,
+    #[msg("Pending withdrawal is still within its delay")]
+    WithdrawalStillLocked,
+
+    // Staking errors
+    #[msg("auto_stake requires the stake pool accounts")]
+    AutoStakeAccountsMissing,
+    #[msg("Unstaking did not free enough SOL to cover the withdrawal")]
+    UnstakeShortfall,
+    #[msg("Balance is above the dust threshold or still locked; withdraw or unstake it first")]
+    BalanceNotDust,
+
+    // Deposit cap errors
+    #[msg("Deposit would exceed the per-user deposit cap")]
+    DepositCapExceeded,
+
+    // Sponsorship errors
+    #[msg("Subscription escrow is funded by another sponsor")]
+    SponsorEscrowInUse,
+    #[msg("Sponsor account does not match the subscription's sponsor")]
+    InvalidSponsor,
+    #[msg("Sponsor account is required to refund the subscription's escrow")]
+    SponsorAccountRequired,
+    #[msg("Sponsored periods out of range")]
+    InvalidSponsoredPeriods,
+
+    // Billing execution errors
+    #[msg("Invalid billing execution mode")]
+    InvalidExecutionMode,
+    #[msg("Caller is not a registered keeper")]
+    KeeperNotRegistered,
+
+    // Promo errors
+    #[msg("Promo claim needs an ed25519 signature instruction right before it")]
+    PromoSignatureMissing,
+    #[msg("Promo signature instruction is malformed or signs a different grant")]
+    InvalidPromoSignature,
+    #[msg("Promo grant was not signed by the provider's wallet")]
+    PromoSignerMismatch,
+    #[msg("Promo grant has expired")]
+    PromoExpired,
+    #[msg("Promo discount out of range")]
+    InvalidPromoDiscount,
+
+    // Conversion errors
+    #[msg("USDC received would be below the requested minimum")]
+    SlippageExceeded,
+
+    // Referral errors
+    #[msg("Users cannot refer themselves")]
+    SelfReferral,
+    #[msg("Referrer has no user account")]
+    ReferrerNotRegistered,
+    #[msg("Referrer account does not match the referrer")]
+    InvalidReferrer,
+    #[msg("Referrer is already recorded and cannot be changed")]
+    ReferrerAlreadySet,
+    #[msg("Referrer account is required to credit a referred deposit")]
+    ReferrerAccountRequired,
+
+    // Rate limit errors
+    #[msg("Service registration limit reached for the current window; try again later")]
+    RateLimited,
+
+    // Provider standing errors
+    #[msg("Provider is banned")]
+    ProviderBanned,
+    #[msg("Only verified providers' services can be subscribed to")]
+    ProviderNotVerified,
+    #[msg("Provider account does not belong to the service's provider")]
+    ProviderAccountMismatch,
+    #[msg("Service is not registered to the given provider")]
+    ServiceProviderMismatch,
+
+    // Batch errors
+    #[msg("Batch accounts must come in complete groups")]
+    InvalidBatchLayout,
+    #[msg("Crank page must continue after the crank cursor in ascending key order")]
+    CrankCursorMismatch,
+
+    // Configuration errors
+    #[msg("Configuration address cannot be the default pubkey")]
+    InvalidConfigAddress,
+
+    // Authority transfer errors
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthorityTransfer,
+
+    // Pause errors
+    #[msg("Unknown pause flag bits")]
+    InvalidPauseFlags,
+
+    // Keeper registry errors
+    #[msg("Keeper registry is full; remove a keeper first")]
+    KeeperRegistryFull,
+
+    // Config timelock errors
+    #[msg("This change is timelocked; queue it with queue_config_change")]
+    ConfigTimelocked,
+    #[msg("Config timelock out of range")]
+    InvalidConfigTimelock,
+    #[msg("Queued config change is still inside its timelock")]
+    ConfigChangeNotExecutable,
+
+    // Emergency mode errors
+    #[msg("Emergency mode is not active")]
+    EmergencyModeNotActive,
+    #[msg("Emergency mode can only be active while the protocol is paused")]
+    EmergencyModeRequiresPause,
+
+    // Oracle configuration errors
+    #[msg("Maximum price age out of range")]
+    InvalidMaxPriceAge,
+    #[msg("SOL price bounds must satisfy 1 <= min <= max")]
+    InvalidPriceBounds,
+
+    // Subscription lock errors
+    #[msg("Lock periods out of range")]
+    InvalidLockPeriods,
+
+    // Fee recipient errors
+    #[msg("Fee recipient accounts are required while a fee recipient is set")]
+    FeeRecipientAccountMissing,
+    #[msg("Account is not the configured fee recipient")]
+    InvalidFeeRecipient,
+
+    // Account close errors
+    #[msg("Only inactive subscriptions can be closed")]
+    SubscriptionStillActive,
+    #[msg("Rent collector account is missing or not the configured one")]
+    InvalidRentCollector,
+
+    // Service registry errors
+    #[msg("Service registry slot belongs to another service")]
+    ServiceRegistrySlotTaken,
+
+    // Price circuit breaker errors
+    #[msg("SOL/USD price moved too far from the last observed price")]
+    PriceDeviationTooLarge,
+
+    // Price override errors
+    #[msg("Price override must expire in the future and within MAX_PRICE_OVERRIDE_SECS")]
+    InvalidPriceOverride,
+
+    // Admin deactivation errors
+    #[msg("Service was deactivated by the authority")]
+    ServiceDeactivatedByAdmin,
+
+    // Minimum deposit errors
+    #[msg("Minimum deposit is above MAX_MIN_DEPOSIT_LAMPORTS or the per-user deposit cap")]
+    InvalidMinDeposit,
+
+    // Protocol fee change errors
+    #[msg("The last protocol fee change is still pending for payments due before it")]
+    ProtocolFeeChangePending,
+
+    // Fee recipient errors
+    #[msg("Fee recipient must be rent exempt and hold a USDC account for the settlement mint")]
+    FeeRecipientNotPayable,
+
+    // Subscription expiry errors
+    #[msg("Only delinquent subscriptions can expire")]
+    SubscriptionNotDelinquent,
+    #[msg("The subscription's grace period has not ended")]
+    GracePeriodNotOver,
 }
@@ -0,0 +1,393 @@
+use crate::state::ConfigChange;
+use anchor_lang::{prelude::*, solana_program::hash::hash};
+
+// Vault events
+#[event]
+pub struct Deposited {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub new_deposited_total: u64,
+}
+
+#[event]
+pub struct Withdrawn {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub unstaked_first: bool,
+    pub new_deposited_total: u64,
+    pub destination: Pubkey, // The user's wallet unless another destination was passed
+}
+
+#[event]
+pub struct EmergencyWithdrawn {
+    pub user: Pubkey,
+    pub amount: u64,            // Every lamport the vault held
+    pub deposited_cleared: u64, // deposited_sol zeroed, locked funds included
+    pub subscriptions_deactivated: u32,
+}
+
+#[event]
+pub struct WithdrawalAllowlistUpdated {
+    pub user: Pubkey,
+    pub enabled: bool,
+    pub destinations: Vec<Pubkey>,
+    pub locked_until: i64,
+}
+
+#[event]
+pub struct WithdrawalDelayUpdated {
+    pub user: Pubkey,
+    pub withdrawal_delay_secs: i64, // Delay in force now
+    pub pending_delay_secs: i64,    // Lower delay waiting to take effect, if effective_at is set
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct WithdrawalRequested {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub unlocks_at: i64,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct DustSwept {
+    pub user: Pubkey,
+    pub jito_sol_redeemed: u64,
+    pub redeemed_lamports: u64,     // Reached the vault from the pool
+    pub lamports_paid_out: u64,     // Vault lamports above the rent floor, sent to the user
+    pub deposited_written_off: u64, // deposited_sol no lamports backed
+    pub staked_written_off: u64,    // staked_sol left with no JitoSOL behind it
+}
+
+#[event]
+pub struct ReferralRecorded {
+    pub user: Pubkey,
+    pub referrer: Pubkey,
+    pub first_deposit: u64,
+}
+
+#[event]
+pub struct UsdcDeposited {
+    pub user: Pubkey,
+    pub amount: u64, // Base units credited, after any transfer fee
+    pub new_usdc_balance: u64,
+}
+
+#[event]
+pub struct UsdcWithdrawn {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub new_usdc_balance: u64,
+}
+
+#[event]
+pub struct SolWithdrawnAsUsdc {
+    pub user: Pubkey,
+    pub lamports: u64,    // SOL value withdrawn
+    pub usdc_amount: u64, // USDC base units sent at the oracle price
+    pub usdc_received: u64, // After any transfer fee withheld by the mint
+    pub sol_usd_price_cents: u64,
+}
+
+// Subscription events
+#[event]
+pub struct SubscriptionCreated {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub fee_usd: u64, // USD cents per billing period
+    pub next_payment_due: i64,
+    pub certificate_mint: Pubkey,
+}
+
+#[event]
+pub struct SubscriptionCancelled {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub prorated_refund_lamports: u64,
+    pub unlocked_lamports: u64,
+    pub effective_at: i64, // Access ends at this timestamp
+}
+
+#[event]
+pub struct SubscriptionSponsored {
+    pub sponsor: Pubkey,
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub periods: u64,
+    pub amount: u64,         // Lamports added to the escrow
+    pub escrow_balance: u64, // Escrow available for billing afterwards
+}
+
+#[event]
+pub struct SponsorEscrowRefunded {
+    pub sponsor: Pubkey,
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub amount: u64, // Unused escrow plus the escrow account's rent
+}
+
+#[event]
+pub struct PromoClaimed {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub discount_bps: u16,
+    pub nonce: u64,
+}
+
+/// Emitted when a billing attempt first marks a subscription delinquent.
+/// Providers should degrade access until grace_ends_at.
+#[event]
+pub struct SubscriptionDelinquent {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub failed_attempts: u8,
+    pub grace_ends_at: i64,
+    pub reason: u8, // BILLING_REASON_* code
+}
+
+/// Emitted by expire_subscription when a delinquent subscription outlives its
+/// grace period and access must be cut off
+#[event]
+pub struct SubscriptionExpired {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub unlocked_lamports: u64,
+}
+
+// Keeper telemetry
+/// Emitted once per batch run. `failed` entries are skipped deliberately and
+/// each one that decodes to a subscription also gets a BillingSkipped event.
+#[event]
+pub struct BatchCompleted {
+    pub processed: u32,
+    pub skipped: u32, // not yet due; expected, not a failure
+    pub failed: u32,
+    pub first_failure_reason: u8, // BILLING_REASON_* code, NONE if nothing failed
+}
+
+#[event]
+pub struct BillingSkipped {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub reason: u8, // BILLING_REASON_* code
+}
+
+/// Emitted when a provider's pending earnings are delivered
+#[event]
+pub struct ProviderEarningsSettled {
+    pub provider: Pubkey,
+    pub amount: u64,          // Sent from the USDC treasury
+    pub received_amount: u64, // After any transfer fee withheld by the mint
+    pub settled_by: Pubkey,
+}
+
+/// Emitted by close_payment_record once per transaction
+#[event]
+pub struct PaymentRecordsClosed {
+    pub user: Pubkey,
+    pub closed_by: Pubkey,
+    pub closed_count: u32,
+    pub lamports_returned: u64,
+}
+
+#[event]
+pub struct UserSubscriptionClosed {
+    pub user: Pubkey,
+    pub subscription: Pubkey,
+    pub closed_by: Pubkey,
+    pub rent_destination: Pubkey, // The user, or the rent collector when someone else closed it
+    pub lamports_returned: u64,
+}
+
+// Service lifecycle events
+#[event]
+pub struct ServiceRegistered {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub fee_usd: u64,
+    pub billing_frequency_days: u64,
+}
+
+#[event]
+pub struct ServiceUpdated {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub changed_fields_bitmap: u8, // SERVICE_FIELD_* flags
+}
+
+#[event]
+pub struct ServiceStatusChanged {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub is_active: bool,
+}
+
+#[event]
+pub struct ServiceRegistryBackfilled {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub page: u32,
+    pub is_active: bool,
+}
+
+#[event]
+pub struct ServiceReported {
+    pub reporter: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub reason_code: u8,   // REPORT_REASON_* code
+    pub report_count: u32, // Reports by this reporter against the service
+}
+
+#[event]
+pub struct ServiceFreezeChanged {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub frozen: bool,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ServiceAdminDeactivated {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub reason_code: u8, // REPORT_REASON_* code
+    pub authority: Pubkey,
+}
+
+// Provider events
+#[event]
+pub struct ProviderRegistered {
+    pub wallet: Pubkey,
+    pub name_hash: [u8; 32], // sha256 of normalize_name(name), equal for lookalike names; the account holds the full string
+    pub nft_mint: Pubkey,
+    pub created_at: i64,
+}
+
+#[event]
+pub struct ProviderUpdated {
+    pub wallet: Pubkey,
+    pub changed_fields_bitmap: u8, // PROVIDER_FIELD_* flags
+}
+
+/// Emitted by the authority's provider verification path
+#[event]
+pub struct ProviderVerificationChanged {
+    pub wallet: Pubkey,
+    pub is_verified: bool,
+    pub by: Pubkey,
+}
+
+#[event]
+pub struct ProviderBanChanged {
+    pub wallet: Pubkey,
+    pub is_banned: bool,
+    pub by: Pubkey,
+}
+
+// Staking events
+#[event]
+pub struct YieldClaimed {
+    pub user: Pubkey,
+    pub gross_lamports: u64,
+    pub protocol_fee_lamports: u64,
+    pub net_lamports: u64,
+    pub pool_rate_used: u64, // APY in basis points applied to the period
+    pub period_start: i64,
+    pub period_end: i64,
+}
+
+// Treasury events
+#[event]
+pub struct TreasuryInitialized {
+    pub treasury: Pubkey,
+    pub usdc_treasury: Pubkey,
+    pub funded_lamports: u64, // 0 when the treasury was already at its rent floor
+    pub by: Pubkey,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub remaining_lamports: u64, // Treasury balance left, including the rent floor
+    pub by: Pubkey,
+}
+
+// Governance events
+/// Audit trail for authority configuration changes.
+/// `field` is one of the CONFIG_FIELD_* constants; values are hashed so one
+/// event shape covers every setter regardless of the value's type.
+#[event]
+pub struct ConfigChanged {
+    pub field: u8,
+    pub old_value_hash: [u8; 32],
+    pub new_value_hash: [u8; 32],
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+impl ConfigChanged {
+    pub fn new<T: AnchorSerialize>(field: u8, old_value: &T, new_value: &T, by: Pubkey) -> Result<Self> {
+        Ok(Self {
+            field,
+            old_value_hash: Self::value_hash(old_value)?,
+            new_value_hash: Self::value_hash(new_value)?,
+            by,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+
+    fn value_hash<T: AnchorSerialize>(value: &T) -> Result<[u8; 32]> {
+        let mut data = Vec::new();
+        value
+            .serialize(&mut data)
+            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+        Ok(hash(&data).to_bytes())
+    }
+}
+
+// Timelocked config changes carry the value itself, so indexers can warn
+// subscribers before it lands
+#[event]
+pub struct ConfigChangeQueued {
+    pub field: u8,
+    pub change: ConfigChange,
+    pub executable_at: i64,
+    pub by: Pubkey,
+}
+
+#[event]
+pub struct ConfigChangeExecuted {
+    pub field: u8,
+    pub change: ConfigChange,
+    pub by: Pubkey,
+}
+
+#[event]
+pub struct ConfigChangeCancelled {
+    pub field: u8,
+    pub change: ConfigChange,
+    pub by: Pubkey,
+}
+
+// Oracle events
+#[event]
+pub struct PriceBreakerReset {
+    pub previous_price_cents: u64, // Reference the tripped breaker compared against
+    pub by: Pubkey,
+}
@@ -0,0 +1,93 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Authority takedown of a malicious service. Where freeze_service holds a
+/// service pending review, this ends it: the service stops taking subscribers
+/// and being billed, and its provider cannot reactivate it. Subscribers
+/// unsubscribe as usual, even after passing their certificate on
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct AdminDeactivateService<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    /// Registry page of the service, created here for services registered
+    /// before the registry existed
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ServiceRegistryPage::INIT_SPACE,
+        seeds = [
+            SERVICE_REGISTRY_SEED.as_bytes(),
+            ServiceRegistryPage::page_of(service_id).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub service_registry_page: Box<Account<'info, ServiceRegistryPage>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AdminDeactivateService<'info> {
+    /// Deactivate the service for `reason_code`, a REPORT_REASON_* code, and
+    /// tombstone it in the service registry
+    pub fn admin_deactivate_service(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        reason_code: u8,
+        bumps: &AdminDeactivateServiceBumps,
+    ) -> Result<()> {
+        require_full_layout(
+            &self.subscription_service.to_account_info(),
+            8 + SubscriptionService::INIT_SPACE,
+        )?;
+
+        self.subscription_service.deactivate_by_admin(reason_code)?;
+        self.service_registry_page.record(
+            bumps.service_registry_page,
+            service_id,
+            self.subscription_service.key(),
+            false,
+        )?;
+
+        msg!(
+            "Subscription service {} by provider {} deactivated by the authority (reason {})",
+            service_id,
+            provider,
+            reason_code
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceAdminDeactivated {
+                provider,
+                service_id,
+                reason_code,
+                authority: self.authority.key(),
+            },
+        )
+    }
+}
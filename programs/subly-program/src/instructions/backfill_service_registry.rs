@@ -0,0 +1,75 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Write a service registered before the service registry existed into its
+/// registry page. Anyone may run it, paying for the page if it is the first on
+/// it. A service's slot is fixed by its id, so backfills can run in any order
+/// and repeating one only refreshes the tombstone
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct BackfillServiceRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ServiceRegistryPage::INIT_SPACE,
+        seeds = [
+            SERVICE_REGISTRY_SEED.as_bytes(),
+            ServiceRegistryPage::page_of(service_id).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub service_registry_page: Box<Account<'info, ServiceRegistryPage>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> BackfillServiceRegistry<'info> {
+    pub fn backfill_service_registry(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        bumps: &BackfillServiceRegistryBumps,
+    ) -> Result<()> {
+        let is_active = self.subscription_service.is_active;
+        self.service_registry_page.record(
+            bumps.service_registry_page,
+            service_id,
+            self.subscription_service.key(),
+            is_active,
+        )?;
+        let page = self.service_registry_page.page;
+
+        msg!(
+            "Service {} by provider {} recorded on registry page {}",
+            service_id,
+            provider,
+            page
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceRegistryBackfilled {
+                provider,
+                service_id,
+                page,
+                is_active,
+            },
+        )
+    }
+}
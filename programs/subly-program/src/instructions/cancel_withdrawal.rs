@@ -0,0 +1,40 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+}
+
+impl<'info> CancelWithdrawal<'info> {
+    /// Abort a pending withdrawal, returning the reserved SOL to the available balance
+    pub fn cancel_withdrawal(&mut self, bumps: &CancelWithdrawalBumps) -> Result<()> {
+        let amount = self.user_account.cancel_pending_withdrawal()?;
+
+        msg!(
+            "Pending withdrawal of {} SOL cancelled",
+            lamports_to_sol_string(amount)
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            WithdrawalCancelled {
+                user: self.user.key(),
+                amount,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -1,22 +1,25 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, state::*, utils::*};
 use anchor_lang::prelude::*;
-use pyth_sdk_solana::state::SolanaPriceAccount;
+use anchor_spl::token_interface::Mint;
 
 #[derive(Accounts)]
 pub struct CheckSubscribableServices<'info> {
     pub user: Signer<'info>,
 
+    /// User's account (optional - visitors who never deposited have none)
     #[account(
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
-    pub user_account: Account<'info, User>,
+    pub user_account: Option<Account<'info, User>>,
 
     /// Global state for reading Jito configuration and Pyth price feed
     #[account(
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
@@ -27,26 +30,42 @@ pub struct CheckSubscribableServices<'info> {
     /// Jito stake pool account for fetching real APY data
     /// CHECK: Jito stake pool account
     pub jito_stake_pool: AccountInfo<'info>,
+
+    /// Settlement mint (optional - lets the user's USDC balance count toward coverage)
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint
+    )]
+    pub usdc_mint: Option<InterfaceAccount<'info, Mint>>,
 }
 
+/// Fixed-size service summary so a full page stays under Solana's 1024-byte
+/// return data limit. Clients read names/descriptions from the service accounts.
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct SubscribableServiceInfo {
     pub provider: Pubkey,
     pub service_id: u64,
-    pub name: String,
-    pub description: String,
     pub fee_usd: u64,
     pub billing_frequency_days: u64,
     pub monthly_fee_sol: u64, // Calculated monthly fee in SOL lamports
     pub can_afford: bool,
+    pub provider_verified: bool, // False unless the provider's account was passed and is verified
+}
+
+/// One page of the view. `scanned` is how many remaining accounts were examined;
+/// when it is below the number passed, the compute budget ran out and the client
+/// should resubmit the rest.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SubscribableServicesPage {
+    pub scanned: u32,
+    pub services: Vec<SubscribableServiceInfo>,
 }
 
 impl<'info> CheckSubscribableServices<'info> {
     pub fn check_subscribable_services(
         ctx: Context<'_, '_, '_, 'info, CheckSubscribableServices<'info>>,
         jito_apy_bps: u16, // Jito APY in basis points (e.g., 700 = 7%)
-    ) -> Result<Vec<SubscribableServiceInfo>> {
-        let user_account = &ctx.accounts.user_account;
+        assumed_deposit_lamports: Option<u64>, // Hypothetical extra deposit for "what if" pricing
+    ) -> Result<SubscribableServicesPage> {
         let global_state = &ctx.accounts.global_state;
 
         // Verify the Pyth price feed account matches the one in GlobalState
@@ -61,13 +80,29 @@ impl<'info> CheckSubscribableServices<'info> {
             ErrorCode::InvalidJitoStakePool
         );
 
-        // Get user's deposited lamports (available for staking)
-        let deposited_lamports = user_account
-            .deposited_sol
-            .checked_sub(user_account.locked_sol)
-            .unwrap_or(0);
+        // Every entry is fixed-size, so the page size bounds the return data size.
+        // Provider accounts ride along with the services, one per service at most
+        require!(
+            ctx.remaining_accounts.len() <= 2 * MAX_SUBSCRIBABLE_SERVICES_PER_PAGE,
+            ErrorCode::PageTooLarge
+        );
+
+        // Get user's deposited lamports (available for staking); zero for users without an account
+        let available_lamports = match &ctx.accounts.user_account {
+            Some(user_account) => user_account.available_sol()?,
+            None => 0,
+        };
 
-        msg!("User deposited SOL (available): {} lamports", deposited_lamports);
+        // Price affordability as if the user deposited assumed_deposit_lamports on top
+        let deposited_lamports = available_lamports
+            .checked_add(assumed_deposit_lamports.unwrap_or(0))
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        msg!(
+            "User deposited SOL (available): {} lamports, assumed deposit: {} lamports",
+            available_lamports,
+            assumed_deposit_lamports.unwrap_or(0)
+        );
 
         // Step 1: Calculate expected yield per month from Jito staking
         let expected_yield_per_month = Self::calculate_expected_monthly_yield(
@@ -78,54 +113,107 @@ impl<'info> CheckSubscribableServices<'info> {
         msg!("Expected monthly yield: {} lamports", expected_yield_per_month);
 
         // Step 2: Get SOL/USD price from Pyth
-        let sol_usd_price = Self::get_sol_usd_price_from_pyth(&ctx.accounts.sol_usd_price_feed)?;
-        msg!("SOL/USD price from Pyth: ${:.2}", sol_usd_price as f64 / 100.0);
+        let sol_usd_price =
+            get_sol_usd_price_cents(&ctx.accounts.sol_usd_price_feed, &ctx.accounts.global_state)?;
+        msg!("SOL/USD price from Pyth: ${}", cents_to_usd_string(sol_usd_price));
+
+        // Unlocked USDC covers a service outright when it pays for the whole lock
+        let usdc_coverage = match (&ctx.accounts.user_account, &ctx.accounts.usdc_mint) {
+            (Some(user_account), Some(usdc_mint)) => {
+                Some((user_account.available_usdc()?, usdc_mint.decimals))
+            }
+            _ => None,
+        };
 
-        // Step 3: Process subscription service PDAs from remaining accounts
+        // Step 3: Process subscription service PDAs from remaining accounts, as
+        // listed by ServiceRegistryPage::live_services. Provider PDAs passed
+        // alongside them, in any order, mark their services' entries verified
         let mut affordable_services = Vec::new();
-        
+        let mut verified_providers = Vec::new();
+        let mut skipped_accounts: u32 = 0;
+        let mut scanned: u32 = 0;
+
         for account_info in ctx.remaining_accounts {
-            // Deserialize account data directly
-            let mut data = &account_info.data.borrow()[8..]; // Skip discriminator
-            let service_account = SubscriptionService::try_deserialize(&mut data)?;
-            
-            // Skip inactive services
-            if !service_account.is_active {
+            if !has_compute_for_next_scan() {
+                msg!("Compute budget low, stopping after {} accounts", scanned);
+                break;
+            }
+            scanned += 1;
+
+            // Only accept canonical SubscriptionService PDAs owned by this program.
+            // The summary skips the service's strings instead of deserializing them
+            let service_account = match load_subscription_service_summary(account_info) {
+                Some(service) => service,
+                None => {
+                    match load_provider(account_info) {
+                        Some(provider) if provider.is_verified => {
+                            verified_providers.push(provider.wallet)
+                        }
+                        Some(_) => {}
+                        None => skipped_accounts += 1,
+                    }
+                    continue;
+                }
+            };
+
+            // Skip inactive and frozen services
+            if !service_account.is_active || service_account.frozen {
                 continue;
             }
 
             // Convert USD fee to SOL lamports using real Pyth price
-            let monthly_fee_sol = Self::convert_usd_to_sol_lamports(
-                service_account.fee_usd, 
-                sol_usd_price
-            )?;
+            let monthly_fee_sol = convert_usd_to_sol_lamports(service_account.fee_usd, sol_usd_price)?;
             
-            // Check if user can afford this service with expected yield
-            let can_afford = expected_yield_per_month >= monthly_fee_sol;
+            // Check if user can afford this service with expected yield or held USDC
+            let covered_by_usdc = match usdc_coverage {
+                Some((available_usdc, decimals)) => {
+                    available_usdc
+                        >= subscription_lock_token_amount(
+                            service_account.fee_usd,
+                            decimals,
+                            ctx.accounts.global_state.lock_periods(),
+                        )?
+                }
+                None => false,
+            };
+            let can_afford = expected_yield_per_month >= monthly_fee_sol || covered_by_usdc;
 
             let service_info = SubscribableServiceInfo {
                 provider: service_account.provider,
                 service_id: service_account.service_id,
-                name: service_account.name.clone(),
-                description: service_account.description.clone(),
                 fee_usd: service_account.fee_usd,
                 billing_frequency_days: service_account.billing_frequency_days,
                 monthly_fee_sol,
                 can_afford,
+                provider_verified: false,
             };
 
+            require!(
+                affordable_services.len() < MAX_SUBSCRIBABLE_SERVICES_PER_PAGE,
+                ErrorCode::PageTooLarge
+            );
             affordable_services.push(service_info);
 
             msg!(
-                "Service: {}, Fee: ${:.2}, Monthly SOL: {} lamports, Affordable: {}",
-                service_account.name,
-                service_account.fee_usd as f64 / 100.0,
+                "Service: {}#{}, Fee: ${}, Monthly SOL: {} lamports, Affordable: {}",
+                service_account.provider,
+                service_account.service_id,
+                cents_to_usd_string(service_account.fee_usd),
                 monthly_fee_sol,
                 can_afford
             );
         }
         
-        msg!("Processed {} subscription service PDAs", ctx.remaining_accounts.len());
+        msg!(
+            "Processed {} of {} subscription service PDAs ({} invalid accounts skipped)",
+            scanned,
+            ctx.remaining_accounts.len(),
+            skipped_accounts
+        );
+
+        for service_info in affordable_services.iter_mut() {
+            service_info.provider_verified = verified_providers.contains(&service_info.provider);
+        }
 
         // Sort by affordability (affordable services first), then by price (cheaper first)
         affordable_services.sort_by(|a, b| {
@@ -141,7 +229,10 @@ impl<'info> CheckSubscribableServices<'info> {
             affordable_services.iter().filter(|s| s.can_afford).count()
         );
 
-        Ok(affordable_services)
+        Ok(SubscribableServicesPage {
+            scanned,
+            services: affordable_services,
+        })
     }
 
     /// Calculate expected yield per month from Jito staking
@@ -150,91 +241,14 @@ impl<'info> CheckSubscribableServices<'info> {
         deposited_lamports: u64,
         jito_apy_bps: u16, // Jito APY in basis points received as parameter
     ) -> Result<u64> {
-        msg!("Using provided Jito APY: {}bps ({}%)", jito_apy_bps, jito_apy_bps as f64 / 100.0);
-        
-        // Calculate annual yield
-        let annual_yield = deposited_lamports
-            .checked_mul(jito_apy_bps as u64)
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(10000) // Convert basis points to percentage
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-        // Convert to monthly yield
-        let monthly_yield = annual_yield
-            .checked_div(12)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-        Ok(monthly_yield)
-    }
-
-    /// Get SOL/USD price from Pyth Network - REAL IMPLEMENTATION
-    fn get_sol_usd_price_from_pyth(price_feed_account: &AccountInfo) -> Result<u64> {
-        // Load price feed from Pyth account using the correct API
-        let price_feed = SolanaPriceAccount::account_info_to_feed(price_feed_account)
-            .map_err(|_| ErrorCode::InvalidPriceFeed)?;
-        
-        // Get current price with staleness check
-        let current_time = Clock::get()?.unix_timestamp;
-        let max_age = 3600; // 1 hour in seconds
-        
-        let price = price_feed
-            .get_price_no_older_than(current_time, max_age)
-            .ok_or(ErrorCode::PriceNotAvailable)?;
-        
-        // Validate price data
-        require!(price.price > 0, ErrorCode::InvalidPrice);
-        
         msg!(
-            "Pyth price data: price={}, conf={}, expo={}, timestamp={}",
-            price.price,
-            price.conf,
-            price.expo,
-            price.publish_time
-        );
-
-        // Convert price to USD cents
-        // Pyth SOL/USD typically has exponent of -8, meaning price is in units of 10^-8 USD
-        let price_cents = if price.expo >= 0 {
-            // Positive exponent: multiply
-            (price.price as u64)
-                .checked_mul(10_u64.pow(price.expo as u32))
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_mul(100) // Convert to cents
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-        } else {
-            // Negative exponent: divide
-            let divisor = 10_u64.pow((-price.expo) as u32);
-            (price.price as u64)
-                .checked_mul(100) // Convert to cents first
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(divisor)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-        };
-
-        // Validate reasonable price range ($10 - $1000 per SOL)
-        require!(
-            price_cents >= 1000 && price_cents <= 100000,
-            ErrorCode::InvalidPrice
-        );
-
-        msg!(
-            "Real SOL/USD price from Pyth: ${:.2} (account: {})",
-            price_cents as f64 / 100.0,
-            price_feed_account.key()
+            "Using provided Jito APY: {}bps ({}%)",
+            jito_apy_bps,
+            bps_to_percent_string(u64::from(jito_apy_bps))
         );
 
-        Ok(price_cents)
-    }
-
-    /// Convert USD cents to SOL lamports
-    fn convert_usd_to_sol_lamports(usd_cents: u64, sol_usd_cents: u64) -> Result<u64> {
-        // lamports = (usd_cents * LAMPORTS_PER_SOL) / sol_usd_cents
-        let lamports = (usd_cents as u128)
-            .checked_mul(1_000_000_000) // LAMPORTS_PER_SOL
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(sol_usd_cents as u128)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let monthly_yield = monthly_yield_lamports(deposited_lamports, jito_apy_bps);
 
-        Ok(u64::try_from(lamports).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+        Ok(monthly_yield)
     }
 }
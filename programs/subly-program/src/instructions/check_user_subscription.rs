@@ -1,4 +1,4 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, state::*, utils::load_token_account};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -10,7 +10,8 @@ pub struct CheckUserSubscription<'info> {
     #[account(
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
@@ -22,13 +23,29 @@ pub struct CheckUserSubscription<'info> {
             provider.as_ref(),
             &service_id.to_le_bytes(),
         ],
-        bump,
+        bump = user_subscription.bump,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_subscription: Option<Account<'info, UserSubscription>>,
+
+    /// Token account expected to hold the subscription certificate (optional).
+    /// When provided, an active subscription also requires the certificate to be held.
+    /// CHECK: May be closed; parsed as a token account in the handler
+    pub certificate_token_account: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SubscriptionStatus {
+    pub is_active: bool,
+    pub certificate_held: bool, // Always false when no certificate account is passed
 }
 
 impl<'info> CheckUserSubscription<'info> {
-    pub fn check_user_subscription(&self, provider: Pubkey, service_id: u64) -> Result<bool> {
+    pub fn check_user_subscription(
+        &self,
+        provider: Pubkey,
+        service_id: u64,
+    ) -> Result<SubscriptionStatus> {
         // Check if user has a subscription account for this provider and service
         if let Some(subscription) = &self.user_subscription {
             // Verify the subscription matches the requested provider and service
@@ -45,9 +62,13 @@ impl<'info> CheckUserSubscription<'info> {
                 ErrorCode::UnauthorizedUser
             );
 
-            // Check if subscription is active
-            let is_active = subscription.is_active;
-            
+            let certificate_held = self.certificate_held(provider, service_id)?;
+
+            // Check if subscription is active; a burned or transferred certificate
+            // only counts against it when the caller asked for the certificate check
+            let is_active = subscription.is_active
+                && (self.certificate_token_account.is_none() || certificate_held);
+
             msg!(
                 "User {} subscription to provider {} service {}: {}",
                 self.user.key(),
@@ -56,7 +77,10 @@ impl<'info> CheckUserSubscription<'info> {
                 if is_active { "ACTIVE" } else { "INACTIVE" }
             );
 
-            Ok(is_active)
+            Ok(SubscriptionStatus {
+                is_active,
+                certificate_held,
+            })
         } else {
             // No subscription account exists
             msg!(
@@ -65,7 +89,36 @@ impl<'info> CheckUserSubscription<'info> {
                 provider,
                 service_id
             );
-            Ok(false)
+            Ok(SubscriptionStatus {
+                is_active: false,
+                certificate_held: false,
+            })
         }
     }
+
+    /// Whether the passed token account holds exactly one certificate for this
+    /// subscription and belongs to the queried wallet
+    fn certificate_held(&self, provider: Pubkey, service_id: u64) -> Result<bool> {
+        let Some(account) = &self.certificate_token_account else {
+            return Ok(false);
+        };
+        let Some(token_account) = load_token_account(account)? else {
+            return Ok(false);
+        };
+
+        let user = self.user.key();
+        let (certificate_mint, _) = Pubkey::find_program_address(
+            &[
+                CERTIFICATE_SEED.as_bytes(),
+                user.as_ref(),
+                provider.as_ref(),
+                &service_id.to_le_bytes(),
+            ],
+            &crate::ID,
+        );
+
+        Ok(token_account.mint == certificate_mint
+            && token_account.amount == 1
+            && token_account.owner == user)
+    }
 }
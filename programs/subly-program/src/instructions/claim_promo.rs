@@ -0,0 +1,119 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::{prelude::*, solana_program::sysvar};
+
+/// Apply a promo grant the provider signed off-chain. The transaction must put an
+/// ed25519 program instruction verifying the provider wallet's signature over
+/// promo_claim_message right before this one; the nonce's PromoClaim account makes
+/// each grant claimable once
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64, discount_bps: u16, expiry: i64, nonce: u64)]
+pub struct ClaimPromo<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user.key().as_ref(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = user_subscription.bump,
+        constraint = user_subscription.user == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_subscription.is_active @ ErrorCode::SubscriptionNotActive,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_subscription: Account<'info, UserSubscription>,
+
+    /// Replay protection: creating it fails if the nonce was already claimed
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PromoClaim::INIT_SPACE,
+        seeds = [PROMO_CLAIM_SEED.as_bytes(), provider.as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub promo_claim: Account<'info, PromoClaim>,
+
+    /// CHECK: Instructions sysvar, read to find the ed25519 signature check
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimPromo<'info> {
+    pub fn claim_promo(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        discount_bps: u16,
+        expiry: i64,
+        nonce: u64,
+        bumps: &ClaimPromoBumps,
+    ) -> Result<()> {
+        self.global_state.require_not_paused_for(PAUSE_SUBSCRIPTIONS)?;
+        require!(
+            (1..=MAX_PROMO_DISCOUNT_BPS).contains(&discount_bps),
+            ErrorCode::InvalidPromoDiscount
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time <= expiry, ErrorCode::PromoExpired);
+
+        let message = promo_claim_message(&self.user.key(), service_id, discount_bps, expiry, nonce);
+        verify_preceding_ed25519(
+            &self.instructions_sysvar,
+            &self.provider_account.wallet,
+            &message,
+        )?;
+
+        self.user_subscription.discount_bps = discount_bps;
+        self.promo_claim.set_inner(PromoClaim {
+            provider,
+            nonce,
+            user: self.user.key(),
+            service_id,
+            discount_bps,
+            claimed_at: current_time,
+            bump: bumps.promo_claim,
+        });
+
+        msg!(
+            "User {} claimed a {}% promo on service {} of provider {}",
+            self.user.key(),
+            bps_to_percent_string(u64::from(discount_bps)),
+            service_id,
+            provider
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            PromoClaimed {
+                user: self.user.key(),
+                provider,
+                service_id,
+                discount_bps,
+                nonce,
+            },
+        )?;
+
+        Ok(())
+    }
+}
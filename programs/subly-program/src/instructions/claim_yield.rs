@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ClaimYield<'info> {
     #[account(mut)]
@@ -10,10 +11,18 @@ pub struct ClaimYield<'info> {
         mut,
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
     #[account(
         mut,
         seeds = [
@@ -22,14 +31,15 @@ pub struct ClaimYield<'info> {
         ],
         bump = stake_account.bump,
         constraint = stake_account.user == user.key() @ ErrorCode::UnauthorizedUser,
-        constraint = stake_account.is_active @ ErrorCode::StakingNotAvailable
+        constraint = stake_account.is_active @ ErrorCode::StakingNotAvailable,
+        constraint = stake_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub stake_account: Account<'info, StakeAccount>,
 
     #[account(
         mut,
         seeds = [b"vault", user.key().as_ref()],
-        bump,
+        bump = user_account.vault_bump,
     )]
     pub sol_vault: SystemAccount<'info>,
 
@@ -46,11 +56,16 @@ pub struct ClaimYield<'info> {
 
 impl<'info> ClaimYield<'info> {
     pub fn claim_yield(&mut self, bumps: &ClaimYieldBumps) -> Result<()> {
+        self.global_state.require_not_paused_for(PAUSE_STAKING)?;
+
         let user_account = &mut self.user_account;
         let stake_account = &mut self.stake_account;
 
         let current_time = Clock::get()?.unix_timestamp;
-        let time_since_last_claim = current_time - stake_account.last_yield_claim;
+        let period_start = stake_account.last_yield_claim;
+        let time_since_last_claim = current_time
+            .checked_sub(stake_account.last_yield_claim)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
 
         // Only allow claiming if enough time has passed (24 hours)
         require!(
@@ -60,12 +75,11 @@ impl<'info> ClaimYield<'info> {
 
         // Calculate yield (simplified - 5% APY)
         // yield = staked_amount * 0.05 * (time_since_last_claim / 31536000) // seconds in a year
-        let yield_amount = stake_account.staked_amount
-            .checked_mul(5)
-            .and_then(|x| x.checked_div(100))
-            .and_then(|x| x.checked_mul(time_since_last_claim as u64))
-            .and_then(|x| x.checked_div(31536000)) // seconds in a year
-            .unwrap_or(0);
+        let yield_amount = accrued_yield_lamports(
+            stake_account.staked_amount,
+            YIELD_APY_BPS,
+            time_since_last_claim,
+        )?;
 
         if yield_amount > 0 {
             // Transfer yield from Jito vault to user vault (simplified)
@@ -90,25 +104,38 @@ impl<'info> ClaimYield<'info> {
             )?;
 
             // Update accounts
-            stake_account.total_yield_earned = stake_account.total_yield_earned
-                .checked_add(yield_amount)
-                .unwrap();
-            stake_account.last_yield_claim = current_time;
+            stake_account.record_yield(yield_amount, current_time)?;
 
             user_account.deposited_sol = user_account.deposited_sol
                 .checked_add(yield_amount)
-                .unwrap();
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
 
-            msg!(
+            verbose_msg!(
                 "User {} claimed {} SOL yield (total earned: {} SOL)",
                 self.user.key(),
-                yield_amount as f64 / 1_000_000_000.0,
-                stake_account.total_yield_earned as f64 / 1_000_000_000.0
+                lamports_to_sol_string(yield_amount),
+                lamports_to_sol_string(stake_account.total_yield_earned)
             );
         } else {
-            msg!("No yield available to claim");
+            verbose_msg!("No yield available to claim");
         }
 
+        // Emitted for zero-yield claims too, so "claimed nothing" is distinguishable from a failure.
+        // No protocol fee is taken on yield yet, so gross == net.
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            YieldClaimed {
+                user: self.user.key(),
+                gross_lamports: yield_amount,
+                protocol_fee_lamports: 0,
+                net_lamports: yield_amount,
+                pool_rate_used: YIELD_APY_BPS,
+                period_start,
+                period_end: current_time,
+            },
+        )?;
+
         Ok(())
     }
 }
@@ -0,0 +1,117 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Prune audit records once they leave the retention window. Further records of
+/// the same user can be passed via remaining accounts to close them in one transaction.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClosePaymentRecord<'info> {
+    /// The records' user or the protocol authority
+    pub closer: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut, close = user)]
+    pub payment_record: Account<'info, PaymentRecord>,
+
+    /// Receives the reclaimed rent of every closed record
+    #[account(
+        mut,
+        address = payment_record.user @ ErrorCode::UnauthorizedUser
+    )]
+    pub user: SystemAccount<'info>,
+}
+
+impl<'info> ClosePaymentRecord<'info> {
+    pub fn close_payment_record(
+        ctx: Context<'_, '_, 'info, 'info, ClosePaymentRecord<'info>>,
+    ) -> Result<()> {
+        require!(
+            ctx.remaining_accounts.len() < MAX_PAYMENT_RECORDS_PER_CLOSE,
+            ErrorCode::PageTooLarge
+        );
+
+        let closer = ctx.accounts.closer.key();
+        let user = ctx.accounts.user.key();
+        require!(
+            closer == user || closer == ctx.accounts.global_state.authority,
+            ErrorCode::UnauthorizedUser
+        );
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let retention_seconds = ctx
+            .accounts
+            .global_state
+            .payment_record_retention_days
+            .checked_mul(86400)
+            .and_then(|seconds| i64::try_from(seconds).ok())
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        // The named record is closed by its `close` constraint when the instruction exits
+        Self::check_closable(
+            &ctx.accounts.payment_record,
+            current_time,
+            retention_seconds,
+        )?;
+
+        let mut closed_count: u32 = 1;
+        let mut lamports_returned = ctx.accounts.payment_record.to_account_info().lamports();
+
+        for account_info in ctx.remaining_accounts {
+            require!(account_info.is_writable, ErrorCode::InvalidPaymentRecord);
+            let record = Account::<PaymentRecord>::try_from(account_info)
+                .map_err(|_| ErrorCode::InvalidPaymentRecord)?;
+            require_keys_eq!(record.user, user, ErrorCode::UnauthorizedUser);
+            Self::check_closable(&record, current_time, retention_seconds)?;
+
+            lamports_returned = lamports_returned
+                .checked_add(account_info.lamports())
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            record.close(ctx.accounts.user.to_account_info())?;
+            closed_count += 1;
+        }
+
+        msg!(
+            "Closed {} payment records of user {}, returned {} lamports",
+            closed_count,
+            user,
+            lamports_returned
+        );
+
+        emit_cpi_event(
+            &ctx.accounts.event_authority,
+            ctx.bumps.event_authority,
+            PaymentRecordsClosed {
+                user,
+                closed_by: closer,
+                closed_count,
+                lamports_returned,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// A record can be closed once it is past the retention window and not under dispute
+    fn check_closable(
+        record: &PaymentRecord,
+        current_time: i64,
+        retention_seconds: i64,
+    ) -> Result<()> {
+        require!(!record.disputed, ErrorCode::PaymentRecordDisputed);
+        let closable_at = record
+            .payment_date
+            .checked_add(retention_seconds)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(
+            current_time >= closable_at,
+            ErrorCode::PaymentRecordRetentionActive
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Close an inactive subscription. The rent goes back to the user when they
+/// close it, and to the rent collector when the authority cleans it up
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseUserSubscription<'info> {
+    /// The subscription's user or the protocol authority
+    pub closer: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user_subscription.user.as_ref(),
+            user_subscription.provider.as_ref(),
+            user_subscription.service_id.to_le_bytes().as_ref()
+        ],
+        bump = user_subscription.bump,
+        constraint = !user_subscription.is_active @ ErrorCode::SubscriptionStillActive,
+        // A sponsor's escrow is refunded through unsubscribe, not orphaned here
+        constraint = user_subscription.sponsor == Pubkey::default() @ ErrorCode::SponsorEscrowInUse
+    )]
+    pub user_subscription: Account<'info, UserSubscription>,
+
+    /// Paid the subscription's rent when subscribing
+    #[account(
+        mut,
+        address = user_subscription.user @ ErrorCode::UnauthorizedUser
+    )]
+    pub user: SystemAccount<'info>,
+
+    /// GlobalState::rent_collector, required when the user is not the closer
+    #[account(mut)]
+    pub rent_collector: Option<SystemAccount<'info>>,
+}
+
+impl<'info> CloseUserSubscription<'info> {
+    pub fn close_user_subscription(&mut self, bumps: &CloseUserSubscriptionBumps) -> Result<()> {
+        let closer = self.closer.key();
+        let user = self.user.key();
+        require!(
+            closer == user || closer == self.global_state.authority,
+            ErrorCode::UnauthorizedUser
+        );
+
+        let subscription = self.user_subscription.key();
+        let (rent_destination, lamports_returned) = close_with_rent_policy(
+            &self.user_subscription,
+            &self.global_state,
+            &closer,
+            &self.user.to_account_info(),
+            self.rent_collector
+                .as_ref()
+                .map(|rent_collector| rent_collector.as_ref()),
+        )?;
+
+        msg!(
+            "Closed subscription {} of user {}, {} lamports to {}",
+            subscription,
+            user,
+            lamports_returned,
+            rent_destination
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            UserSubscriptionClosed {
+                user,
+                subscription,
+                closed_by: closer,
+                rent_destination,
+                lamports_returned,
+            },
+        )
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetConfigTimelock<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(change: ConfigChange)]
+pub struct QueueConfigChange<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// One per field: a second change to the same field needs the first cancelled
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PendingConfigChange::INIT_SPACE,
+        seeds = [CONFIG_CHANGE_SEED.as_bytes(), &[change.field()]],
+        bump
+    )]
+    pub pending_config_change: Account<'info, PendingConfigChange>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(field: u8)]
+pub struct ExecuteConfigChange<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [CONFIG_CHANGE_SEED.as_bytes(), &[field]],
+        bump = pending_config_change.bump
+    )]
+    pub pending_config_change: Account<'info, PendingConfigChange>,
+
+    /// Wallet a queued ConfigChange::FeeRecipient pays fees to
+    /// CHECK: Only its address and balance are read, see require_payable_fee_recipient
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
+
+    /// That wallet's USDC account
+    /// CHECK: Deserialized and checked by require_payable_fee_recipient
+    pub fee_recipient_usdc_account: Option<UncheckedAccount<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(field: u8)]
+pub struct CancelConfigChange<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [CONFIG_CHANGE_SEED.as_bytes(), &[field]],
+        bump = pending_config_change.bump
+    )]
+    pub pending_config_change: Account<'info, PendingConfigChange>,
+}
+
+impl<'info> SetConfigTimelock<'info> {
+    /// Turn on or lengthen the config timelock. Lowering it goes through
+    /// queue_config_change with ConfigChange::ConfigTimelock
+    pub fn set_config_timelock(&mut self, secs: i64, bumps: &SetConfigTimelockBumps) -> Result<()> {
+        let old_value = self.global_state.raise_config_timelock(secs)?;
+
+        msg!("Config timelock raised from {}s to {}s", old_value, secs);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_CONFIG_TIMELOCK,
+                &old_value,
+                &secs,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
+
+impl<'info> QueueConfigChange<'info> {
+    /// Record `change` to be applied once the current config timelock has passed
+    pub fn queue_config_change(
+        &mut self,
+        change: ConfigChange,
+        bumps: &QueueConfigChangeBumps,
+    ) -> Result<()> {
+        change.validate()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        let executable_at = self
+            .global_state
+            .config_change_executable_at(current_time)?;
+
+        self.pending_config_change.set_inner(PendingConfigChange {
+            change,
+            queued_by: self.authority.key(),
+            queued_at: current_time,
+            executable_at,
+            bump: bumps.pending_config_change,
+        });
+
+        msg!(
+            "Config field {} change queued, executable at {}",
+            change.field(),
+            executable_at
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChangeQueued {
+                field: change.field(),
+                change,
+                executable_at,
+                by: self.authority.key(),
+            },
+        )
+    }
+}
+
+impl<'info> ExecuteConfigChange<'info> {
+    /// Apply the change queued for `field` once its delay is over. The rent of
+    /// the queued change returns to the authority
+    pub fn execute_config_change(
+        &mut self,
+        field: u8,
+        bumps: &ExecuteConfigChangeBumps,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        self.pending_config_change
+            .require_executable(current_time)?;
+
+        let change = self.pending_config_change.change;
+        let by = self.authority.key();
+        let config_changed = match change {
+            ConfigChange::ProtocolFee(fee_bps) => {
+                let old_value = self.global_state.set_protocol_fee(fee_bps, current_time)?;
+                ConfigChanged::new(field, &old_value, &fee_bps, by)?
+            }
+            ConfigChange::Address { address, .. } => {
+                let old_value = self.global_state.set_config_address(field, address)?;
+                ConfigChanged::new(field, &old_value, &address, by)?
+            }
+            ConfigChange::ConfigTimelock(secs) => {
+                let old_value =
+                    std::mem::replace(&mut self.global_state.config_timelock_secs, secs);
+                ConfigChanged::new(field, &old_value, &secs, by)?
+            }
+            ConfigChange::VerifiedFee(fee_bps) => {
+                let old_value = self.global_state.set_verified_fee(fee_bps)?;
+                ConfigChanged::new(field, &old_value, &fee_bps, by)?
+            }
+            ConfigChange::FeeRecipient(fee_recipient) => {
+                require_payable_fee_recipient(
+                    &self.global_state,
+                    fee_recipient,
+                    self.fee_recipient.as_deref(),
+                    self.fee_recipient_usdc_account.as_deref(),
+                )?;
+                let old_value =
+                    std::mem::replace(&mut self.global_state.fee_recipient, fee_recipient);
+                ConfigChanged::new(field, &old_value, &fee_recipient, by)?
+            }
+            ConfigChange::MaxPriceAge(secs) => {
+                let old_value = self.global_state.set_max_price_age(secs)?;
+                ConfigChanged::new(field, &old_value, &secs, by)?
+            }
+            ConfigChange::SolPriceBounds {
+                min_cents,
+                max_cents,
+            } => {
+                let old_value = self
+                    .global_state
+                    .set_sol_price_bounds(min_cents, max_cents)?;
+                ConfigChanged::new(field, &old_value, &(min_cents, max_cents), by)?
+            }
+            ConfigChange::MaxPriceDeviation(max_bps) => {
+                let old_value =
+                    std::mem::replace(&mut self.global_state.max_price_deviation_bps, max_bps);
+                ConfigChanged::new(field, &old_value, &max_bps, by)?
+            }
+        };
+
+        msg!("Config field {} change executed", field);
+
+        emit_cpi_event(&self.event_authority, bumps.event_authority, config_changed)?;
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChangeExecuted { field, change, by },
+        )
+    }
+}
+
+impl<'info> CancelConfigChange<'info> {
+    /// Drop the change queued for `field`
+    pub fn cancel_config_change(
+        &mut self,
+        field: u8,
+        bumps: &CancelConfigChangeBumps,
+    ) -> Result<()> {
+        let change = self.pending_config_change.change;
+
+        msg!("Config field {} change cancelled", field);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChangeCancelled {
+                field,
+                change,
+                by: self.authority.key(),
+            },
+        )
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    instructions::{scan_billing_page, split_billing_accounts},
+    state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+
+/// Billing scan for automation networks (Clockwork-style threads, Tuktuk).
+/// The account list is fixed and small; a page of UserSubscription PDAs follows
+/// in remaining accounts, sorted by key and starting after
+/// global_state.crank_cursor, and may be followed by their SubscriptionService
+/// accounts so due subscriptions of frozen services are reported as skipped.
+/// The thread signs with its own PDA, which the
+/// authority registers with add_keeper; in authority-only mode it cannot crank.
+/// Due subscriptions are still charged through execute_subscription_payment
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CrankPayments<'info> {
+    /// The automation thread's PDA, allowed by global_state.execution_mode
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// The thread's keeper registration, needed in allowlist mode
+    #[account(
+        seeds = [KEEPER_SEED.as_bytes(), authority.key().as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Option<Account<'info, Keeper>>,
+
+    /// Pyth SOL/USD price feed account
+    /// CHECK: Pyth price feed account
+    #[account(
+        constraint = sol_usd_price_feed.key() == global_state.sol_usd_price_feed @ ErrorCode::InvalidPriceFeed
+    )]
+    pub sol_usd_price_feed: AccountInfo<'info>,
+}
+
+/// What one crank did, and whether the thread should run again right away
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CrankPaymentsResult {
+    pub scanned: u32,
+    pub due: u32,
+    pub more_remaining: bool,
+    pub cursor: Pubkey, // Where the next crank starts, default = a new pass
+}
+
+impl<'info> CrankPayments<'info> {
+    pub fn crank_payments(
+        ctx: Context<'_, '_, '_, 'info, CrankPayments<'info>>,
+    ) -> Result<CrankPaymentsResult> {
+        let accounts = ctx.accounts;
+        accounts.global_state.require_not_paused_for(PAUSE_PAYMENTS)?;
+        accounts.global_state.check_billing_caller(
+            &accounts.authority.key(),
+            accounts.keeper_registration.is_some(),
+        )?;
+
+        let billing_accounts = split_billing_accounts(ctx.remaining_accounts)?;
+        let page: Vec<Pubkey> = billing_accounts
+            .subscriptions
+            .iter()
+            .map(|a| a.key())
+            .collect();
+        accounts.global_state.check_crank_page(&page)?;
+
+        // A stale price would mark the whole page as failing later, so stop here
+        observe_sol_usd_price_cents(&accounts.sol_usd_price_feed, &mut accounts.global_state)?;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let scan = scan_billing_page(
+            billing_accounts.subscriptions,
+            &billing_accounts.frozen_services,
+            current_time,
+            &accounts.event_authority,
+            ctx.bumps.event_authority,
+        )?;
+
+        let more_remaining = accounts
+            .global_state
+            .advance_crank_cursor(&page, scan.scanned as usize);
+        accounts.global_state.last_payment_processed = current_time;
+
+        emit_cpi_event(
+            &accounts.event_authority,
+            ctx.bumps.event_authority,
+            scan.summary(),
+        )?;
+
+        verbose_msg!(
+            "Crank scanned {} subscriptions, {} due, more remaining: {}",
+            scan.scanned,
+            scan.processed,
+            more_remaining
+        );
+
+        Ok(CrankPaymentsResult {
+            scanned: scan.scanned,
+            due: scan.processed,
+            more_remaining,
+            cursor: accounts.global_state.crank_cursor,
+        })
+    }
+}
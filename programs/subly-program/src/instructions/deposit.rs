@@ -1,9 +1,18 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::*,
+    instructions::{stake_vault_sol, StakePoolDepositAccounts},
+    state::*,
+    utils::*,
+};
 use anchor_lang::{
     prelude::*,
     system_program::{transfer, Transfer},
 };
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
@@ -12,7 +21,7 @@ pub struct Deposit<'info> {
     #[account(
         init_if_needed,
         payer = user,
-        space = User::INIT_SPACE,
+        space = 8 + User::INIT_SPACE,
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump
     )]
@@ -21,7 +30,8 @@ pub struct Deposit<'info> {
     #[account(
         mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
@@ -29,53 +39,305 @@ pub struct Deposit<'info> {
     #[account(
         mut,
         seeds = [b"vault", user.key().as_ref()],
-        bump,
+        bump = user_account.vault_bump_for(&user.key()),
     )]
     pub sol_vault: SystemAccount<'info>,
 
+    /// The referrer's user account. Required when `referrer` is passed on the first
+    /// deposit and on every later deposit of a referred user
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump,
+        constraint = referrer_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub referrer_account: Option<Box<Account<'info, User>>>,
+
     pub system_program: Program<'info, System>,
+
+    // ===== Optional Jito/SPL Stake Pool Accounts for auto_stake =====
+    /// User's stake account - required for auto_stake
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + StakeAccount::INIT_SPACE,
+        seeds = [
+            STAKE_ACCOUNT_SEED.as_bytes(),
+            user.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stake_account: Option<Account<'info, StakeAccount>>,
+
+    /// Protocol's JitoSOL vault (ATA owned by protocol PDA) - required for auto_stake
+    #[account(
+        mut,
+        associated_token::mint = jito_sol_mint,
+        associated_token::authority = protocol_authority
+    )]
+    pub protocol_jito_vault: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: Protocol authority PDA that owns JitoSOL vault - required for auto_stake
+    #[account(
+        seeds = [b"protocol_authority"],
+        bump
+    )]
+    pub protocol_authority: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Stake Pool program (read from GlobalState) - required for auto_stake
+    #[account(address = global_state.spl_stake_pool_program)]
+    pub stake_pool_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Jito Stake Pool account (read from GlobalState) - required for auto_stake
+    #[account(
+        mut,
+        address = global_state.jito_stake_pool
+    )]
+    pub jito_stake_pool: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Stake pool withdraw authority (PDA derived from stake pool) - required for auto_stake
+    pub stake_pool_withdraw_authority: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Jito reserve stake account - required for auto_stake
+    #[account(mut)]
+    pub reserve_stake: Option<UncheckedAccount<'info>>,
+
+    /// JitoSOL mint (read from GlobalState) - required for auto_stake
+    #[account(
+        mut,
+        address = global_state.jito_sol_mint
+    )]
+    pub jito_sol_mint: Option<Account<'info, Mint>>,
+
+    /// CHECK: Jito manager fee account - required for auto_stake
+    #[account(mut)]
+    pub manager_fee_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Referrer pool tokens (can be same as protocol vault) - required for auto_stake
+    #[account(mut)]
+    pub referrer_pool_tokens: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+/// Deposit bookkeeping shared by the SOL and wrapped SOL paths: initializes or
+/// verifies the user account, enforces the minimum on its first deposit, moves `amount` (plus any
+/// vault rent top-up) from the user's wallet into the vault and credits it, along
+/// with the user's referrer. Returns whether this was the user's first deposit
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn deposit_sol<'info>(
+    user: &AccountInfo<'info>,
+    user_account: &mut User,
+    global_state: &mut GlobalState,
+    sol_vault: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    referrer_account: Option<&mut User>,
+    referrer: Option<Pubkey>,
+    amount: u64,
+    vault_bump: u8,
+    user_account_bump: u8,
+) -> Result<bool> {
+    global_state.require_not_paused_for(PAUSE_DEPOSITS)?;
+    require!(amount > 0, ErrorCode::InvalidAmount);
+
+    // Checked against the current cap, so raising it unblocks users right away
+    require!(
+        amount <= user_account.deposit_headroom(global_state.max_deposit_per_user_lamports)?,
+        ErrorCode::DepositCapExceeded
+    );
+
+    // Initialize the user account on first deposit; later deposits verify it instead
+    let is_new_user = user_account.initialize_or_verify(
+        user.key(),
+        Clock::get()?.unix_timestamp,
+        vault_bump,
+        user_account_bump,
+    )?;
+    // The minimum keeps dust from opening rent-paying accounts and vaults
+    global_state.require_min_deposit(amount, is_new_user)?;
+    if is_new_user {
+        global_state.record_user_joined()?;
+    }
+
+    // Keep the vault rent exempt: the first deposit (or one into a vault that
+    // predates the floor) also pays the rent minimum, which is not credited
+    let rent_floor = vault_rent_floor()?;
+    let rent_top_up = rent_floor
+        .saturating_add(user_account.deposited_sol)
+        .saturating_sub(sol_vault.lamports())
+        .min(rent_floor);
+
+    // Transfer SOL from user to vault
+    let ctx = CpiContext::new(
+        system_program.clone(),
+        Transfer {
+            from: user.clone(),
+            to: sol_vault.clone(),
+        },
+    );
+    transfer(
+        ctx,
+        amount
+            .checked_add(rent_top_up)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+    )?;
+
+    // Update user account
+    user_account.deposited_sol = user_account
+        .deposited_sol
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    global_state.record_deposit(amount)?;
+
+    credit_referrer(user_account, referrer_account, referrer, amount, is_new_user)?;
+
+    Ok(is_new_user)
+}
+
+/// Record `referrer` on a user's first deposit and credit every deposit of a
+/// referred user to the referrer's tally. Later deposits may repeat the stored
+/// referrer but not change it, and must pass its account so no volume is skipped
+fn credit_referrer(
+    user_account: &mut User,
+    referrer_account: Option<&mut User>,
+    referrer: Option<Pubkey>,
+    amount: u64,
+    is_first_deposit: bool,
+) -> Result<()> {
+    match referrer {
+        Some(referrer) if is_first_deposit => user_account.set_referrer(referrer)?,
+        Some(referrer) => require_keys_eq!(
+            referrer,
+            user_account.referrer,
+            ErrorCode::ReferrerAlreadySet
+        ),
+        None => {}
+    }
+    if user_account.referrer == Pubkey::default() {
+        return Ok(());
+    }
+
+    let referrer_account = referrer_account.ok_or(if is_first_deposit {
+        ErrorCode::ReferrerNotRegistered
+    } else {
+        ErrorCode::ReferrerAccountRequired
+    })?;
+    require_keys_eq!(
+        referrer_account.wallet,
+        user_account.referrer,
+        ErrorCode::InvalidReferrer
+    );
+    referrer_account.record_referred_deposit(amount, is_first_deposit)
 }
 
 impl<'info> Deposit<'info> {
-    pub fn deposit(&mut self, amount: u64, bumps: &DepositBumps) -> Result<()> {
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
-        require!(amount > 0, ErrorCode::InvalidAmount);
-
-        let user_account = &mut self.user_account;
-
-        // Initialize user account if this is the first time
-        if user_account.wallet == Pubkey::default() {
-            user_account.wallet = self.user.key();
-            user_account.deposited_sol = 0;
-            user_account.locked_sol = 0;
-            user_account.staked_sol = 0;
-            user_account.created_at = Clock::get()?.unix_timestamp;
-            user_account.bump = bumps.user_account;
+    pub fn deposit(
+        &mut self,
+        amount: u64,
+        auto_stake: bool,
+        referrer: Option<Pubkey>,
+        bumps: &DepositBumps,
+    ) -> Result<()> {
+        let vault_bump = self.user_account.vault_bump_for(&self.user.key());
+        let is_first_deposit = deposit_sol(
+            &self.user.to_account_info(),
+            &mut self.user_account,
+            &mut self.global_state,
+            &self.sol_vault.to_account_info(),
+            &self.system_program.to_account_info(),
+            self.referrer_account.as_deref_mut().map(|account| &mut **account),
+            referrer,
+            amount,
+            vault_bump,
+            bumps.user_account,
+        )?;
+
+        if is_first_deposit && self.user_account.referrer != Pubkey::default() {
+            emit_cpi_event(
+                &self.event_authority,
+                bumps.event_authority,
+                ReferralRecorded {
+                    user: self.user.key(),
+                    referrer: self.user_account.referrer,
+                    first_deposit: amount,
+                },
+            )?;
         }
 
-        // Transfer SOL from user to vault
-        let ctx = CpiContext::new(
-            self.system_program.to_account_info(),
-            Transfer {
-                from: self.user.to_account_info(),
-                to: self.sol_vault.to_account_info(),
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            Deposited {
+                user: self.user.key(),
+                amount,
+                new_deposited_total: self.user_account.deposited_sol,
             },
-        );
-        transfer(ctx, amount)?;
-
-        // Update user account
-        user_account.deposited_sol = user_account
-            .deposited_sol
-            .checked_add(amount)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        )?;
 
-        msg!(
-            "User {} deposited {} SOL (total: {} SOL)",
-            self.user.key(),
-            amount as f64 / 1_000_000_000.0,
-            user_account.deposited_sol as f64 / 1_000_000_000.0
-        );
+        if auto_stake {
+            self.stake_above_reserve(bumps)?;
+        }
 
         Ok(())
     }
+
+    /// Stake everything above the protocol's liquid reserve through the same path
+    /// as stake_sol. Every stake pool account must be present so a client that
+    /// asked for auto_stake never silently ends up unstaked
+    fn stake_above_reserve(&mut self, bumps: &DepositBumps) -> Result<()> {
+        self.global_state.require_not_paused_for(PAUSE_STAKING)?;
+        let stake_account_bump = bumps
+            .stake_account
+            .ok_or(ErrorCode::AutoStakeAccountsMissing)?;
+        let pool = StakePoolDepositAccounts {
+            stake_pool_program: required_account(&self.stake_pool_program)?,
+            jito_stake_pool: required_account(&self.jito_stake_pool)?,
+            stake_pool_withdraw_authority: required_account(&self.stake_pool_withdraw_authority)?,
+            reserve_stake: required_account(&self.reserve_stake)?,
+            protocol_jito_vault: required_account(&self.protocol_jito_vault)?,
+            manager_fee_account: required_account(&self.manager_fee_account)?,
+            referrer_pool_tokens: required_account(&self.referrer_pool_tokens)?,
+            jito_sol_mint: required_account(&self.jito_sol_mint)?,
+            token_program: required_account(&self.token_program)?,
+            system_program: self.system_program.to_account_info(),
+        };
+        // Not part of the CPI, but it is what ties protocol_jito_vault to the protocol
+        required_account(&self.protocol_authority)?;
+        let stake_account = self
+            .stake_account
+            .as_mut()
+            .ok_or(ErrorCode::AutoStakeAccountsMissing)?;
+
+        let amount = self
+            .user_account
+            .stakeable_above_reserve(self.global_state.liquid_reserve_lamports)?
+            .min(spendable_vault_balance(&self.sol_vault)?);
+        if amount < MIN_STAKE_AMOUNT {
+            msg!(
+                "Auto-stake skipped: {} SOL above the liquid reserve is below the minimum stake",
+                lamports_to_sol_string(amount)
+            );
+            return Ok(());
+        }
+
+        stake_vault_sol(
+            self.user.key(),
+            &mut self.user_account,
+            stake_account,
+            &mut self.global_state,
+            &self.sol_vault.to_account_info(),
+            &pool,
+            amount,
+            stake_account_bump,
+        )
+    }
+}
+
+/// Account info of an optional auto_stake account, or AutoStakeAccountsMissing
+fn required_account<'info, T: ToAccountInfo<'info>>(account: &Option<T>) -> Result<AccountInfo<'info>> {
+    account
+        .as_ref()
+        .map(|account| account.to_account_info())
+        .ok_or(ErrorCode::AutoStakeAccountsMissing.into())
 }
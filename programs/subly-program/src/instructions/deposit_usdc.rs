@@ -0,0 +1,125 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+/// Fund subscriptions with the settlement stablecoin instead of SOL. Tokens are held
+/// in an ATA owned by the user's SOL vault PDA, which signs for billing and withdrawals
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DepositUsdc<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + User::INIT_SPACE,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump
+    )]
+    pub user_account: Box<Account<'info, User>>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// The user's SOL vault PDA, authority of the USDC vault
+    #[account(
+        seeds = [b"vault", user.key().as_ref()],
+        bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// USDC mint (classic SPL or Token-2022)
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
+    )]
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Token account the USDC is taken from
+    #[account(
+        mut,
+        token::mint = usdc_mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_usdc_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user's USDC vault
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = sol_vault,
+        associated_token::token_program = token_program
+    )]
+    pub usdc_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DepositUsdc<'info> {
+    pub fn deposit_usdc(&mut self, amount: u64, bumps: &DepositUsdcBumps) -> Result<()> {
+        self.global_state.require_not_paused_for(PAUSE_DEPOSITS)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        // Same first-deposit handling as the SOL path
+        let is_new_user = self.user_account.initialize_or_verify(
+            self.user.key(),
+            Clock::get()?.unix_timestamp,
+            bumps.sol_vault,
+            bumps.user_account,
+        )?;
+        if is_new_user {
+            self.global_state.record_user_joined()?;
+        }
+
+        // Only what actually reaches the vault is credited
+        let transfer_fee = transfer_fee_amount(&self.usdc_mint.to_account_info(), amount)?;
+        let received_amount = amount
+            .checked_sub(transfer_fee)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.user_usdc_account.to_account_info(),
+                    mint: self.usdc_mint.to_account_info(),
+                    to: self.usdc_vault.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+            self.usdc_mint.decimals,
+        )?;
+
+        self.user_account.usdc_balance = self
+            .user_account
+            .usdc_balance
+            .checked_add(received_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            UsdcDeposited {
+                user: self.user.key(),
+                amount: received_amount,
+                new_usdc_balance: self.user_account.usdc_balance,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    constants::*, error::ErrorCode, events::*, instructions::deposit_sol, state::*, utils::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{
+    close_account, spl_token::native_mint, transfer_checked, CloseAccount, Mint, Token,
+    TokenAccount, TransferChecked,
+};
+
+/// Deposit wrapped SOL. `amount` is moved into a temporary wSOL account, which is
+/// closed back to the user's wallet to unwrap it, and the SOL then follows the
+/// normal deposit path into the vault. The temporary account is created and closed
+/// in the same instruction, so any failure reverts its creation as well
+#[event_cpi]
+#[derive(Accounts)]
+pub struct DepositWsol<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + User::INIT_SPACE,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump
+    )]
+    pub user_account: Box<Account<'info, User>>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref()],
+        bump = user_account.vault_bump_for(&user.key()),
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// The referrer's user account, required when the user was referred so the
+    /// deposit is credited to them
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), referrer_account.wallet.as_ref()],
+        bump = referrer_account.bump,
+        constraint = referrer_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub referrer_account: Option<Box<Account<'info, User>>>,
+
+    #[account(address = native_mint::ID)]
+    pub wsol_mint: Box<Account<'info, Mint>>,
+
+    /// The user's wSOL token account
+    #[account(
+        mut,
+        token::mint = wsol_mint,
+        token::authority = user
+    )]
+    pub user_wsol_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [WSOL_TEMP_SEED.as_bytes(), user.key().as_ref()],
+        bump,
+        token::mint = wsol_mint,
+        token::authority = user
+    )]
+    pub temp_wsol_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DepositWsol<'info> {
+    pub fn deposit_wsol(&mut self, amount: u64, bumps: &DepositWsolBumps) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            self.user_wsol_account.amount >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        // Move exactly `amount` so the rest of the user's wSOL stays wrapped
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.user_wsol_account.to_account_info(),
+                    mint: self.wsol_mint.to_account_info(),
+                    to: self.temp_wsol_account.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            amount,
+            self.wsol_mint.decimals,
+        )?;
+
+        // Closing unwraps: the user gets back `amount` plus the rent they paid for the account
+        close_account(CpiContext::new(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.temp_wsol_account.to_account_info(),
+                destination: self.user.to_account_info(),
+                authority: self.user.to_account_info(),
+            },
+        ))?;
+
+        let vault_bump = self.user_account.vault_bump_for(&self.user.key());
+        deposit_sol(
+            &self.user.to_account_info(),
+            &mut self.user_account,
+            &mut self.global_state,
+            &self.sol_vault.to_account_info(),
+            &self.system_program.to_account_info(),
+            self.referrer_account.as_deref_mut().map(|account| &mut **account),
+            None,
+            amount,
+            vault_bump,
+            bumps.user_account,
+        )?;
+
+        msg!("Unwrapped and deposited {} SOL", lamports_to_sol_string(amount));
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            Deposited {
+                user: self.user.key(),
+                amount,
+                new_deposited_total: self.user_account.deposited_sol,
+            },
+        )?;
+
+        Ok(())
+    }
+}
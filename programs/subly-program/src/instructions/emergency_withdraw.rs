@@ -0,0 +1,190 @@
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::*,
+    instructions::{deactivate_subscription, load_batch_entry, release_certificate},
+    state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenInterface;
+
+/// Wind-down exit: empties the user's SOL vault while emergency mode is on,
+/// ignoring subscription locks. The user's subscriptions are passed as remaining
+/// accounts laid out as in unsubscribe_batch, groups of UNSUBSCRIBE_BATCH_STRIDE,
+/// and cancelled like an unsubscribe: counters updated and certificate released.
+/// Sponsored subscriptions are left for unsubscribe to refund their escrow, and
+/// compressed-certificate ones for unsubscribe_from_service_compressed. Staked SOL
+/// and USDC stay on the books and leave through their usual withdrawals
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref()],
+        bump = user_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// Close authority of Token-2022 certificate mints
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+
+    /// Receives the certificate rent back when it paid for the certificate accounts
+    #[account(
+        mut,
+        seeds = [RENT_SPONSOR_SEED.as_bytes()],
+        bump
+    )]
+    pub rent_sponsor: SystemAccount<'info>,
+
+    /// Every certificate passed must belong to this token program
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> EmergencyWithdraw<'info> {
+    pub fn emergency_withdraw(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyWithdraw<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.global_state.require_emergency_mode()?;
+
+        let accounts = ctx.accounts;
+        let user_key = accounts.user.key();
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let entries = ctx.remaining_accounts;
+        require!(
+            entries
+                .chunks_exact(UNSUBSCRIBE_BATCH_STRIDE)
+                .remainder()
+                .is_empty(),
+            ErrorCode::InvalidBatchLayout
+        );
+
+        // Deactivate first, so a subscription lock can never outlive the balance
+        let mut subscriptions_deactivated = 0u32;
+        for group in entries.chunks_exact(UNSUBSCRIBE_BATCH_STRIDE) {
+            let mut entry = match load_batch_entry(user_key, accounts.token_program.key(), group) {
+                Ok(entry) => entry,
+                Err(reason) => {
+                    msg!(
+                        "Subscription {} left as is (reason {}); unsubscribe handles it",
+                        group[0].key(),
+                        reason
+                    );
+                    continue;
+                }
+            };
+
+            let user_subscription = &mut entry.user_subscription;
+            if user_subscription.locked_usdc > 0 {
+                accounts
+                    .user_account
+                    .release_locked_usdc(user_subscription.locked_usdc)?;
+                user_subscription.locked_usdc = 0;
+            }
+            // The SOL lock goes with the vault below
+            user_subscription.locked_sol = 0;
+            let access_ends_at = deactivate_subscription(
+                &mut entry.subscription_service,
+                &mut entry.provider_account,
+                user_subscription,
+                &mut accounts.global_state,
+                current_time,
+            )?;
+
+            // Access ends with the period already paid for, as on unsubscribe
+            let rent_sponsored = entry.certificate_attributes.rent_sponsored;
+            entry.certificate_attributes.paid_through = access_ends_at;
+            entry.certificate_attributes.updated_at = current_time;
+            let rent_destination = if rent_sponsored {
+                accounts.rent_sponsor.to_account_info()
+            } else {
+                accounts.user.to_account_info()
+            };
+            release_certificate(
+                &accounts.user,
+                entry.certificate_nft_mint,
+                entry.certificate_nft_token_account,
+                &accounts.certificate_authority,
+                rent_destination,
+                &accounts.token_program,
+                ctx.bumps.certificate_authority,
+                entry.subscription_service.deactivated_by_admin,
+            )?;
+
+            entry.user_subscription.exit(&crate::ID)?;
+            entry.subscription_service.exit(&crate::ID)?;
+            entry.provider_account.exit(&crate::ID)?;
+            entry.certificate_attributes.exit(&crate::ID)?;
+            subscriptions_deactivated += 1;
+        }
+
+        // Everything in the vault goes back, rent included: the vault holds no
+        // data and is recreated by the next deposit
+        let amount = accounts.sol_vault.lamports();
+        if amount > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new_with_signer(
+                    accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: accounts.sol_vault.to_account_info(),
+                        to: accounts.user.to_account_info(),
+                    },
+                    &[&[
+                        b"vault",
+                        user_key.as_ref(),
+                        &[accounts.user_account.vault_bump],
+                    ]],
+                ),
+                amount,
+            )?;
+        }
+
+        let deposited_cleared = accounts.user_account.clear_sol_for_emergency_withdraw();
+        accounts.global_state.record_withdrawal(deposited_cleared);
+
+        msg!(
+            "Emergency withdrawal of {} lamports for {}, {} subscriptions deactivated",
+            amount,
+            user_key,
+            subscriptions_deactivated
+        );
+
+        emit_cpi_event(
+            &accounts.event_authority,
+            ctx.bumps.event_authority,
+            EmergencyWithdrawn {
+                user: user_key,
+                amount,
+                deposited_cleared,
+                subscriptions_deactivated,
+            },
+        )
+    }
+}
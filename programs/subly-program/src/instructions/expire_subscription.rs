@@ -0,0 +1,156 @@
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::*,
+    instructions::{close_subscription, refund_sponsor_escrow},
+    state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+
+/// End a delinquent subscription once its grace period is over, releasing the
+/// collateral it locked. Anyone may send it. The certificate stays with the
+/// subscriber, frozen since the subscription went delinquent
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExpireSubscription<'info> {
+    /// Anyone, typically the provider or a keeper
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user_subscription.user.as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user_subscription.user.as_ref(),
+            user_subscription.provider.as_ref(),
+            user_subscription.service_id.to_le_bytes().as_ref()
+        ],
+        bump = user_subscription.bump,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_subscription: Account<'info, UserSubscription>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            user_subscription.provider.as_ref(),
+            user_subscription.service_id.to_le_bytes().as_ref()
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), user_subscription.provider.as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    // Pyth price feed for SOL/USD conversion
+    /// CHECK: This account is validated in close_subscription to match the price feed in GlobalState
+    pub sol_usd_price_feed: AccountInfo<'info>,
+
+    /// Sponsor escrow for this subscription, refunded on expiry
+    #[account(
+        mut,
+        seeds = [SPONSOR_ESCROW_SEED.as_bytes(), user_subscription.key().as_ref()],
+        bump
+    )]
+    pub sponsor_escrow: SystemAccount<'info>,
+
+    /// CHECK: Receives the escrow refund; required when the subscription is sponsored
+    /// and checked against user_subscription.sponsor
+    #[account(mut)]
+    pub sponsor: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ExpireSubscription<'info> {
+    pub fn expire_subscription(&mut self, bumps: &ExpireSubscriptionBumps) -> Result<()> {
+        require!(
+            self.user_subscription.delinquent_since.is_some(),
+            ErrorCode::SubscriptionNotDelinquent
+        );
+        let grace_ends_at = self.user_subscription.grace_ends_at()?;
+        require!(
+            Clock::get()?.unix_timestamp >= grace_ends_at,
+            ErrorCode::GracePeriodNotOver
+        );
+
+        let user = self.user_subscription.user;
+        let (_, unlocked_lamports, _) = close_subscription(
+            user,
+            &mut self.user_account,
+            &mut self.subscription_service,
+            &mut self.provider_account,
+            &mut self.user_subscription,
+            &mut self.global_state,
+            &self.sol_usd_price_feed,
+        )?;
+
+        // Unused sponsor funds go back to the sponsor, never to the subscriber
+        let sponsor_key = self.user_subscription.sponsor;
+        let subscription_key = self.user_subscription.key();
+        let refunded = refund_sponsor_escrow(
+            &mut self.user_subscription,
+            &self.sponsor_escrow.to_account_info(),
+            self.sponsor.as_ref().map(|sponsor| sponsor.as_ref()),
+            &self.system_program.to_account_info(),
+            subscription_key,
+            bumps.sponsor_escrow,
+        )?;
+
+        msg!(
+            "Subscription {} of user {} expired, grace period ended at {}",
+            subscription_key,
+            user,
+            grace_ends_at
+        );
+
+        if refunded > 0 {
+            emit_cpi_event(
+                &self.event_authority,
+                bumps.event_authority,
+                SponsorEscrowRefunded {
+                    sponsor: sponsor_key,
+                    user,
+                    provider: self.user_subscription.provider,
+                    service_id: self.user_subscription.service_id,
+                    amount: refunded,
+                },
+            )?;
+        }
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionExpired {
+                user,
+                provider: self.user_subscription.provider,
+                service_id: self.user_subscription.service_id,
+                unlocked_lamports,
+            },
+        )
+    }
+}
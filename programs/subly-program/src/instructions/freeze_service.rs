@@ -0,0 +1,153 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Authority kill switch for a single service, used instead of pausing the whole
+/// protocol when a service is reported as fraudulent
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct FreezeService<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    /// The provider's earnings ledger, held while the service is frozen
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ProviderEarnings::INIT_SPACE,
+        seeds = [PROVIDER_EARNINGS_SEED.as_bytes(), provider.as_ref()],
+        bump
+    )]
+    pub provider_earnings: Box<Account<'info, ProviderEarnings>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct UnfreezeService<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_EARNINGS_SEED.as_bytes(), provider.as_ref()],
+        bump = provider_earnings.bump
+    )]
+    pub provider_earnings: Box<Account<'info, ProviderEarnings>>,
+}
+
+impl<'info> FreezeService<'info> {
+    /// Freeze the service: no new subscribers or sponsorships, billing skipped
+    /// with BILLING_REASON_SERVICE_FROZEN and the provider's earnings held
+    pub fn freeze_service(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        bumps: &FreezeServiceBumps,
+    ) -> Result<()> {
+        require!(!self.subscription_service.frozen, ErrorCode::ServiceFrozen);
+        require_full_layout(
+            &self.subscription_service.to_account_info(),
+            8 + SubscriptionService::INIT_SPACE,
+        )?;
+
+        self.subscription_service.frozen = true;
+        self.provider_earnings
+            .set_provider_if_new(provider, bumps.provider_earnings);
+        self.provider_earnings.hold()?;
+
+        msg!(
+            "Subscription service {} by provider {} frozen, {} frozen services held",
+            service_id,
+            provider,
+            self.provider_earnings.frozen_services
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceFreezeChanged {
+                provider,
+                service_id,
+                frozen: true,
+                authority: self.authority.key(),
+            },
+        )
+    }
+}
+
+impl<'info> UnfreezeService<'info> {
+    /// Lift a freeze: subscribing, billing and settlement resume as before
+    pub fn unfreeze_service(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        bumps: &UnfreezeServiceBumps,
+    ) -> Result<()> {
+        require!(
+            self.subscription_service.frozen,
+            ErrorCode::ServiceNotFrozen
+        );
+
+        self.subscription_service.frozen = false;
+        self.provider_earnings.release()?;
+
+        msg!(
+            "Subscription service {} by provider {} unfrozen",
+            service_id,
+            provider
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceFreezeChanged {
+                provider,
+                service_id,
+                frozen: false,
+                authority: self.authority.key(),
+            },
+        )
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{constants::*, error::ErrorCode, state::*};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+#[derive(Accounts)]
+pub struct FundRentSponsor<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [RENT_SPONSOR_SEED.as_bytes()],
+        bump
+    )]
+    pub rent_sponsor: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FundRentSponsor<'info> {
+    /// Top up the PDA that pays certificate rent when sponsorship is enabled
+    pub fn fund_rent_sponsor(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.authority.to_account_info(),
+                    to: self.rent_sponsor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        msg!(
+            "Rent sponsor funded with {} lamports, balance {}",
+            amount,
+            self.rent_sponsor.lamports()
+        );
+
+        Ok(())
+    }
+}
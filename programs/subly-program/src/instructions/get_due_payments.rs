@@ -0,0 +1,102 @@
+use crate::{
+    constants::*, error::ErrorCode, instructions::ProcessSubscriptionPayments, state::*, utils::*,
+};
+use anchor_lang::prelude::*;
+
+/// Read-only keeper view: evaluates a page of UserSubscription PDAs passed via
+/// remaining accounts and reports which ones are due for payment.
+/// Mutates nothing, so it is safe to simulate off-chain repeatedly.
+#[derive(Accounts)]
+pub struct GetDuePayments<'info> {
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Identifies a subscription whose payment can be executed now
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DuePaymentKey {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DuePaymentsSummary {
+    pub scanned: u32,
+    pub due_count: u32,
+    pub not_due_count: u32,
+    pub inactive_count: u32,
+    pub invalid_count: u32,
+    pub due: Vec<DuePaymentKey>,
+}
+
+impl<'info> GetDuePayments<'info> {
+    pub fn get_due_payments(
+        ctx: Context<'_, '_, '_, 'info, GetDuePayments<'info>>,
+    ) -> Result<DuePaymentsSummary> {
+        // Every due entry is 72 bytes, so the page size bounds the return data size
+        require!(
+            ctx.remaining_accounts.len() <= MAX_DUE_PAYMENTS_PER_PAGE,
+            ErrorCode::PageTooLarge
+        );
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let mut summary = DuePaymentsSummary {
+            scanned: 0,
+            due_count: 0,
+            not_due_count: 0,
+            inactive_count: 0,
+            invalid_count: 0,
+            due: Vec::new(),
+        };
+
+        for account_info in ctx.remaining_accounts {
+            // `scanned` below the page size tells the keeper where to resume
+            if !has_compute_for_next_scan() {
+                msg!("Compute budget low, stopping after {} accounts", summary.scanned);
+                break;
+            }
+            summary.scanned += 1;
+
+            let subscription = match load_user_subscription(account_info) {
+                Some(subscription) => subscription,
+                None => {
+                    summary.invalid_count += 1;
+                    continue;
+                }
+            };
+
+            if !subscription.is_active {
+                summary.inactive_count += 1;
+                continue;
+            }
+
+            // Same due-date rule the execution path enforces
+            if ProcessSubscriptionPayments::check_payment_due(&subscription, current_time)? {
+                summary.due_count += 1;
+                summary.due.push(DuePaymentKey {
+                    user: subscription.user,
+                    provider: subscription.provider,
+                    service_id: subscription.service_id,
+                });
+            } else {
+                summary.not_due_count += 1;
+            }
+        }
+
+        msg!(
+            "Due payments scan: {} scanned, {} due, {} not due, {} inactive, {} invalid",
+            summary.scanned,
+            summary.due_count,
+            summary.not_due_count,
+            summary.inactive_count,
+            summary.invalid_count
+        );
+
+        Ok(summary)
+    }
+}
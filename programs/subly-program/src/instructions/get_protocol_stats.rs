@@ -0,0 +1,64 @@
+use crate::{constants::*, error::ErrorCode, state::*};
+use anchor_lang::prelude::*;
+
+/// Read-only view of protocol-wide statistics for dashboards
+#[derive(Accounts)]
+pub struct GetProtocolStats<'info> {
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+/// Protocol statistics returned via return data.
+/// New fields must only be appended so existing decoders keep working;
+/// bump PROTOCOL_STATS_VERSION whenever the layout grows.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProtocolStats {
+    pub version: u8,
+    pub total_providers: u64,
+    pub total_services: u64,
+    pub total_users: u64,
+    pub total_active_subscriptions: u64,
+    pub total_deposited_lamports: u64,
+    pub total_staked_lamports: u64,
+    pub tvl_lamports: u64, // Approximation: deposited + staked (staked valued at cost)
+    pub total_volume_lamports: u64,
+    pub total_protocol_fees_lamports: u64,
+    pub protocol_fee_bps: u16,
+    pub is_paused: bool,
+    pub last_payment_processed: i64,
+    pub pause_flags: u8, // PAUSE_* areas halted on their own, added in version 2
+    pub total_protocol_fees_usdc: u64, // USDC base units, added in version 3
+}
+
+impl<'info> GetProtocolStats<'info> {
+    pub fn get_protocol_stats(&self) -> Result<ProtocolStats> {
+        let global_state = &self.global_state;
+
+        // Saturate rather than fail: this is a display figure, not accounting
+        let tvl_lamports = global_state
+            .total_deposited_lamports
+            .saturating_add(global_state.total_staked_lamports);
+
+        Ok(ProtocolStats {
+            version: PROTOCOL_STATS_VERSION,
+            total_providers: global_state.total_providers,
+            total_services: global_state.total_services,
+            total_users: global_state.total_users,
+            total_active_subscriptions: global_state.total_active_subscriptions,
+            total_deposited_lamports: global_state.total_deposited_lamports,
+            total_staked_lamports: global_state.total_staked_lamports,
+            tvl_lamports,
+            total_volume_lamports: global_state.total_volume_lamports,
+            total_protocol_fees_lamports: global_state.total_protocol_fees_lamports,
+            protocol_fee_bps: global_state.protocol_fee_bps,
+            is_paused: global_state.is_paused,
+            last_payment_processed: global_state.last_payment_processed,
+            pause_flags: global_state.pause_flags,
+            total_protocol_fees_usdc: global_state.total_protocol_fees_usdc,
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{constants::*, error::ErrorCode, state::*};
+use anchor_lang::prelude::*;
+
+/// Read-only view of one service registry page, for clients walking the registry
+/// instead of fetching and decoding pages themselves. A full page does not fit in
+/// return data, so each call lists up to MAX_REGISTRY_SERVICES_PER_VIEW live
+/// services from `start_slot` and says where to continue
+#[derive(Accounts)]
+#[instruction(page: u32)]
+pub struct GetRegistryPage<'info> {
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: The registry page PDA for `page`, read as empty while no service on
+    /// it has been recorded
+    #[account(
+        seeds = [SERVICE_REGISTRY_SEED.as_bytes(), page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub service_registry_page: UncheckedAccount<'info>,
+}
+
+/// A live service and the id it was registered under
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RegisteredService {
+    pub service_id: u64,
+    pub service: Pubkey, // SubscriptionService PDA
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RegistryPage {
+    pub page: u32,
+    pub services: Vec<RegisteredService>,
+    pub next_slot: Option<u8>, // start_slot continuing this page, None = page done
+    pub next_page: Option<u32>, // Next page holding service ids, None = last page
+}
+
+impl<'info> GetRegistryPage<'info> {
+    pub fn get_registry_page(&self, page: u32, start_slot: u8) -> Result<RegistryPage> {
+        let (services, next_slot) = if self.service_registry_page.data_is_empty() {
+            (Vec::new(), None)
+        } else {
+            let data = self.service_registry_page.try_borrow_data()?;
+            ServiceRegistryPage::try_deserialize(&mut &data[..])?
+                .live_services_from(start_slot, MAX_REGISTRY_SERVICES_PER_VIEW)
+        };
+
+        // Service ids run from 0 to total_services - 1
+        let next_page = self
+            .global_state
+            .total_services
+            .checked_sub(1)
+            .map(ServiceRegistryPage::page_of)
+            .filter(|&last_page| page < last_page)
+            .map(|_| page + 1);
+
+        Ok(RegistryPage {
+            page,
+            services: services
+                .into_iter()
+                .map(|(service_id, service)| RegisteredService {
+                    service_id,
+                    service,
+                })
+                .collect(),
+            next_slot,
+            next_page,
+        })
+    }
+}
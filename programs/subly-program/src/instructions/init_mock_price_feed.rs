@@ -0,0 +1,57 @@
+use crate::{constants::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Localnet stand-in for the Pyth SOL/USD account, see the `mock` feature
+#[derive(Accounts)]
+pub struct InitMockPriceFeed<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Pyth-layout price account, created and written by this instruction
+    #[account(
+        mut,
+        seeds = [MOCK_PRICE_FEED_SEED.as_bytes()],
+        bump
+    )]
+    pub price_feed: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitMockPriceFeed<'info> {
+    /// Create the mock feed on first use, then (re)publish `price_cents` at
+    /// `expo` with `publish_time`, so tests can move the price or let it go stale
+    pub fn init_mock_price_feed(
+        &mut self,
+        price_cents: u64,
+        expo: i32,
+        publish_time: i64,
+        bumps: &InitMockPriceFeedBumps,
+    ) -> Result<()> {
+        let data = mock_price_account_data(price_cents, expo, publish_time)?;
+
+        if self.price_feed.owner != &crate::ID {
+            create_pda_account(
+                &self.payer.to_account_info(),
+                &self.price_feed.to_account_info(),
+                &self.system_program.to_account_info(),
+                MOCK_PRICE_FEED_SPACE,
+                &crate::ID,
+                &[&[MOCK_PRICE_FEED_SEED.as_bytes(), &[bumps.price_feed]]],
+            )?;
+        }
+        self.price_feed
+            .try_borrow_mut_data()?
+            .copy_from_slice(&data);
+
+        msg!(
+            "Mock price feed {}: {} cents at expo {}, published {}",
+            self.price_feed.key(),
+            price_cents,
+            expo,
+            publish_time
+        );
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::state::*;
+use crate::{constants::*, state::*};
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -51,7 +51,7 @@ impl<'info> Initialize<'info> {
         let global_state = &mut self.global_state;
 
         global_state.authority = self.authority.key();
-        global_state.protocol_fee_bps = 100; // 1% protocol fee
+        global_state.protocol_fee_bps = DEFAULT_PROTOCOL_FEE_BPS;
         global_state.is_paused = false;
         
         // Set Jito configuration (can be mainnet or devnet)
@@ -68,7 +68,37 @@ impl<'info> Initialize<'info> {
         // Initialize counters and timestamps
         global_state.total_services = 0;
         global_state.last_payment_processed = 0;
-        
+        global_state.total_providers = 0;
+        global_state.total_users = 0;
+        global_state.total_active_subscriptions = 0;
+        global_state.total_deposited_lamports = 0;
+        global_state.total_staked_lamports = 0;
+        global_state.total_volume_lamports = 0;
+        global_state.total_protocol_fees_lamports = 0;
+
+        // Certificates stay classic SPL tokens until the authority opts in
+        global_state.soulbound_certificates = false;
+        global_state.certificate_merkle_tree = Pubkey::default();
+        global_state.certificate_collection = Pubkey::default();
+        global_state.sponsor_certificate_rent = false;
+        global_state.payment_record_retention_days = DEFAULT_PAYMENT_RECORD_RETENTION_DAYS;
+        global_state.min_deposit_lamports = DEFAULT_MIN_DEPOSIT_LAMPORTS;
+        global_state.liquid_reserve_lamports = DEFAULT_LIQUID_RESERVE_LAMPORTS;
+        global_state.max_deposit_per_user_lamports = 0;
+        global_state.execution_mode = EXECUTION_MODE_AUTHORITY_ONLY;
+        global_state.max_services_per_window = DEFAULT_MAX_SERVICES_PER_WINDOW;
+        global_state.max_service_fee_usd_cents = MAX_SERVICE_FEE_USD_CENTS;
+        global_state.total_pending_payouts_usdc = 0;
+        global_state.require_verified_providers = false;
+        global_state.max_price_age_secs = DEFAULT_MAX_PRICE_AGE_SECS;
+        global_state.min_sol_price_cents = MIN_SOL_USD_PRICE_CENTS;
+        global_state.max_sol_price_cents = MAX_SOL_USD_PRICE_CENTS;
+        global_state.lock_periods = SUBSCRIPTION_LOCK_PERIODS as u8;
+
+        // Stored so fee transfers can sign for the treasury without re-deriving it
+        global_state.treasury_bump =
+            Pubkey::find_program_address(&[TREASURY_SEED.as_bytes()], &crate::ID).1;
+        global_state.version = GLOBAL_STATE_VERSION;
         global_state.bump = bumps.global_state;
 
         msg!(
@@ -0,0 +1,89 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+/// Create what billing expects to exist: the treasury PDA funded to its rent
+/// floor and its USDC associated token account. Safe to run again, so a
+/// partially failed setup can simply be retried
+#[event_cpi]
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// Protocol treasury, at the bump initialize recorded
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED.as_bytes()],
+        bump = global_state.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
+    )]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    /// Protocol's USDC treasury account, as execute_subscription_payment expects it
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub protocol_usdc_treasury: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeTreasury<'info> {
+    pub fn initialize_treasury(&mut self, bumps: &InitializeTreasuryBumps) -> Result<()> {
+        // Top up only what is missing, so a rerun funds nothing
+        let funded_lamports = vault_rent_floor()?.saturating_sub(self.treasury.lamports());
+        if funded_lamports > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.authority.to_account_info(),
+                        to: self.treasury.to_account_info(),
+                    },
+                ),
+                funded_lamports,
+            )?;
+        }
+
+        msg!(
+            "Treasury {} funded with {} lamports, USDC account {}",
+            self.treasury.key(),
+            funded_lamports,
+            self.protocol_usdc_treasury.key()
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            TreasuryInitialized {
+                treasury: self.treasury.key(),
+                usdc_treasury: self.protocol_usdc_treasury.key(),
+                funded_lamports,
+                by: self.authority.key(),
+            },
+        )
+    }
+}
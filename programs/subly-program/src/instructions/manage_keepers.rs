@@ -0,0 +1,106 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(keeper: Pubkey)]
+pub struct AddKeeper<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Keeper::INIT_SPACE,
+        seeds = [KEEPER_SEED.as_bytes(), keeper.as_ref()],
+        bump
+    )]
+    pub keeper_registration: Account<'info, Keeper>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(keeper: Pubkey)]
+pub struct RemoveKeeper<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [KEEPER_SEED.as_bytes(), keeper.as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Account<'info, Keeper>,
+}
+
+impl<'info> AddKeeper<'info> {
+    /// Allow `keeper` to trigger billing while the execution mode is allowlist,
+    /// up to MAX_KEEPERS at a time
+    pub fn add_keeper(&mut self, keeper: Pubkey, bumps: &AddKeeperBumps) -> Result<()> {
+        self.global_state.register_keeper()?;
+        self.keeper_registration.set_inner(Keeper {
+            keeper,
+            added_at: Clock::get()?.unix_timestamp,
+            bump: bumps.keeper_registration,
+        });
+
+        msg!("Keeper {} registered", keeper);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_KEEPER_ADDED,
+                &Pubkey::default(),
+                &keeper,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<'info> RemoveKeeper<'info> {
+    /// Revoke `keeper`; its registration rent returns to the authority. Billing
+    /// needs the registration account, so the keeper is locked out from the
+    /// next transaction on
+    pub fn remove_keeper(&mut self, keeper: Pubkey, bumps: &RemoveKeeperBumps) -> Result<()> {
+        self.global_state.unregister_keeper();
+        msg!("Keeper {} removed", keeper);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_KEEPER_REMOVED,
+                &keeper,
+                &Pubkey::default(),
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{error::ErrorCode, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Accounts for the migrate_* instructions. Migration only changes an account's
+/// layout, never its contents, so anyone may run it; the caller pays any extra rent.
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Owner, discriminator and version are checked by migrate_account
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// GlobalState migration also sets defaults for the configuration it adds, so
+/// only the authority may run it, paying any extra rent
+#[derive(Accounts)]
+pub struct MigrateGlobalState<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: Older layouts do not deserialize; the stored authority,
+    /// discriminator and version are checked by migrate_global_state
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump
+    )]
+    pub global_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateAccount<'info> {
+    pub fn migrate<T>(&mut self) -> Result<()>
+    where
+        T: AccountDeserialize + AccountSerialize + Discriminator + Owner + Space + Versioned,
+    {
+        let from_version = migrate_account::<T>(
+            &self.account.to_account_info(),
+            &self.payer.to_account_info(),
+            &self.system_program.to_account_info(),
+        )?;
+
+        msg!(
+            "Account {} migrated from version {} to {}",
+            self.account.key(),
+            from_version,
+            T::CURRENT_VERSION
+        );
+
+        Ok(())
+    }
+}
+
+impl<'info> MigrateGlobalState<'info> {
+    /// Bring GlobalState to the current layout. Once it is there, further calls
+    /// succeed without touching the account
+    pub fn migrate_global_state(&mut self) -> Result<()> {
+        let global_state = self.global_state.to_account_info();
+        {
+            let data = global_state.try_borrow_data()?;
+            require_keys_eq!(
+                stored_global_state_authority(&data)?,
+                self.authority.key(),
+                ErrorCode::UnauthorizedAuthority
+            );
+            if !needs_migration::<GlobalState>(&data) {
+                msg!(
+                    "GlobalState is already on version {}",
+                    GlobalState::CURRENT_VERSION
+                );
+                return Ok(());
+            }
+        }
+
+        let from_version = migrate_account::<GlobalState>(
+            &global_state,
+            &self.authority.to_account_info(),
+            &self.system_program.to_account_info(),
+        )?;
+
+        msg!(
+            "GlobalState migrated from version {} to {}",
+            from_version,
+            GlobalState::CURRENT_VERSION
+        );
+
+        Ok(())
+    }
+}
@@ -1,27 +1,161 @@
+pub mod admin_deactivate_service;
+pub mod backfill_service_registry;
+pub mod cancel_withdrawal;
 pub mod check_subscribable_services;
 pub mod check_user_subscription;
+pub mod claim_promo;
 pub mod claim_yield;
+pub mod close_payment_record;
+pub mod close_user_subscription;
+pub mod config_timelock;
+pub mod crank_payments;
 pub mod deposit;
+pub mod deposit_usdc;
+pub mod deposit_wsol;
+pub mod emergency_withdraw;
+pub mod expire_subscription;
+pub mod freeze_service;
+pub mod fund_rent_sponsor;
+pub mod get_due_payments;
+pub mod get_protocol_stats;
+pub mod get_registry_page;
 pub mod initialize;
+pub mod initialize_treasury;
+#[cfg(feature = "mock")]
+pub mod init_mock_price_feed;
+pub mod manage_keepers;
+pub mod migrate_account;
 pub mod process_payments;
 pub mod register_provider;
 pub mod register_subscription_service;
+pub mod report_service;
+pub mod request_withdrawal;
+pub mod reset_price_breaker;
+pub mod resize_account;
+pub mod set_certificate_tree;
+pub mod set_execution_mode;
+pub mod set_fee_recipient;
+pub mod set_liquid_reserve;
+pub mod set_lock_periods;
+pub mod set_max_deposit_per_user;
+pub mod set_max_price_age;
+pub mod set_max_price_deviation;
+pub mod set_max_service_fee;
+pub mod set_min_deposit;
+pub mod set_operator;
+pub mod set_paused;
+pub mod set_payment_record_disputed;
+pub mod set_payment_record_retention;
+pub mod set_price_override;
+pub mod set_protocol_fee;
+pub mod set_provider_standing;
+pub mod set_rent_collector;
+pub mod set_require_verified_providers;
+pub mod set_service_registration_limit;
+pub mod set_service_status;
+pub mod set_sol_price_bounds;
+pub mod set_soulbound_certificates;
+pub mod set_sponsor_certificate_rent;
+pub mod set_verified_fee;
+pub mod set_withdrawal_allowlist;
+pub mod set_withdrawal_delay;
+pub mod settle_provider_earnings;
+pub mod sponsor_subscription;
 pub mod stake_sol;
+pub mod sweep_dust;
 pub mod subscribe_to_service;
+pub mod subscribe_to_service_compressed;
+pub mod transfer_authority;
 pub mod unstake_sol;
+pub mod unsubscribe_batch;
 pub mod unsubscribe_from_service;
+pub mod unsubscribe_from_service_compressed;
+pub mod update_config;
+pub mod update_provider;
+pub mod update_subscription_service;
 pub mod withdraw;
+pub mod withdraw_sol_as_usdc;
+pub mod withdraw_treasury;
+pub mod withdraw_usdc;
+pub mod withdraw_with_unstake;
 
+pub use admin_deactivate_service::*;
+pub use backfill_service_registry::*;
+pub use cancel_withdrawal::*;
 pub use check_subscribable_services::*;
 pub use check_user_subscription::*;
+pub use claim_promo::*;
 pub use claim_yield::*;
+pub use close_payment_record::*;
+pub use close_user_subscription::*;
+pub use config_timelock::*;
+pub use crank_payments::*;
 pub use deposit::*;
+pub use deposit_usdc::*;
+pub use deposit_wsol::*;
+pub use emergency_withdraw::*;
+pub use expire_subscription::*;
+pub use freeze_service::*;
+pub use fund_rent_sponsor::*;
+pub use get_due_payments::*;
+pub use get_protocol_stats::*;
+pub use get_registry_page::*;
 pub use initialize::*;
+pub use initialize_treasury::*;
+#[cfg(feature = "mock")]
+pub use init_mock_price_feed::*;
+pub use manage_keepers::*;
+pub use migrate_account::*;
 pub use process_payments::*;
 pub use register_provider::*;
 pub use register_subscription_service::*;
+pub use report_service::*;
+pub use request_withdrawal::*;
+pub use reset_price_breaker::*;
+pub use resize_account::*;
+pub use set_certificate_tree::*;
+pub use set_execution_mode::*;
+pub use set_fee_recipient::*;
+pub use set_liquid_reserve::*;
+pub use set_lock_periods::*;
+pub use set_max_deposit_per_user::*;
+pub use set_max_price_age::*;
+pub use set_max_price_deviation::*;
+pub use set_max_service_fee::*;
+pub use set_min_deposit::*;
+pub use set_operator::*;
+pub use set_paused::*;
+pub use set_payment_record_disputed::*;
+pub use set_payment_record_retention::*;
+pub use set_price_override::*;
+pub use set_protocol_fee::*;
+pub use set_provider_standing::*;
+pub use set_rent_collector::*;
+pub use set_require_verified_providers::*;
+pub use set_service_registration_limit::*;
+pub use set_service_status::*;
+pub use set_sol_price_bounds::*;
+pub use set_soulbound_certificates::*;
+pub use set_sponsor_certificate_rent::*;
+pub use set_verified_fee::*;
+pub use set_withdrawal_allowlist::*;
+pub use set_withdrawal_delay::*;
+pub use settle_provider_earnings::*;
+pub use sponsor_subscription::*;
 pub use stake_sol::*;
+pub use sweep_dust::*;
 pub use subscribe_to_service::*;
+pub use subscribe_to_service_compressed::*;
+pub use transfer_authority::*;
 pub use unstake_sol::*;
+pub use unsubscribe_batch::*;
 pub use unsubscribe_from_service::*;
+pub use unsubscribe_from_service_compressed::*;
+pub use update_config::*;
+pub use update_provider::*;
+pub use update_subscription_service::*;
 pub use withdraw::*;
+pub use withdraw_sol_as_usdc::*;
+pub use withdraw_treasury::*;
+pub use withdraw_usdc::*;
+pub use withdraw_with_unstake::*;
@@ -1,15 +1,22 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_2022,
+    token_interface::{
+        freeze_account, thaw_account, transfer_checked, FreezeAccount, Mint, ThawAccount,
+        TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use pyth_sdk_solana::state::SolanaPriceAccount;
 
 /// Instruction for batch processing subscription payments (Pay Subscription Fee 1)
-/// This is called daily by the Subly System to identify and process due payments
+/// This is called daily by the Subly System to identify and process due payments.
+/// UserSubscription PDAs to scan are passed via remaining accounts, optionally
+/// followed by their SubscriptionService accounts, see split_billing_accounts.
+#[event_cpi]
 #[derive(Accounts)]
 pub struct ProcessSubscriptionPayments<'info> {
+    /// Caller triggering billing, allowed by global_state.execution_mode
     #[account(mut)]
     pub authority: Signer<'info>,
 
@@ -17,63 +24,86 @@ pub struct ProcessSubscriptionPayments<'info> {
         mut,
         seeds = [b"global_state"],
         bump = global_state.bump,
-        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
+    /// The caller's keeper registration, needed in allowlist mode unless the
+    /// caller is the protocol authority or operator
+    #[account(
+        seeds = [KEEPER_SEED.as_bytes(), authority.key().as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Option<Account<'info, Keeper>>,
+
     /// CHECK: Treasury account to collect protocol fees
     #[account(
         mut,
         seeds = [b"treasury"],
-        bump
+        bump = global_state.treasury_bump
     )]
     pub treasury: SystemAccount<'info>,
 
     /// Pyth SOL/USD price feed account
-    /// CHECK: Pyth price feed account
+    /// CHECK: Must be the price feed configured in GlobalState
+    #[account(address = global_state.sol_usd_price_feed @ ErrorCode::InvalidPriceFeed)]
     pub sol_usd_price_feed: AccountInfo<'info>,
 
-    /// USDC mint account
+    /// USDC mint account (classic SPL or Token-2022)
     #[account(
-        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidPriceFeed
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
     )]
-    pub usdc_mint: Account<'info, Mint>,
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
 
     /// Protocol's USDC treasury account
     #[account(
         mut,
         associated_token::mint = usdc_mint,
-        associated_token::authority = treasury
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
     )]
-    pub protocol_usdc_treasury: Account<'info, TokenAccount>,
+    pub protocol_usdc_treasury: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 /// Individual payment execution instruction (Pay Subscription Fee 2)
 /// This is called for each user whose payment is due, handling the complete payment flow
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(user: Pubkey, provider: Pubkey, service_id: u64)]
 pub struct ExecuteSubscriptionPayment<'info> {
-    /// Protocol authority executing the payment
+    /// Caller executing the payment, allowed by global_state.execution_mode.
+    /// Pays the rent of the records this instruction creates
     #[account(mut)]
     pub authority: Signer<'info>,
 
     #[account(
+        mut,
         seeds = [b"global_state"],
         bump = global_state.bump,
-        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
+    /// The caller's keeper registration, needed in allowlist mode unless the
+    /// caller is the protocol authority or operator
+    #[account(
+        seeds = [KEEPER_SEED.as_bytes(), authority.key().as_ref()],
+        bump = keeper_registration.bump
+    )]
+    pub keeper_registration: Option<Account<'info, Keeper>>,
+
     /// User's account
     #[account(
         mut,
         seeds = [USER_SEED.as_bytes(), user.as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
@@ -86,11 +116,12 @@ pub struct ExecuteSubscriptionPayment<'info> {
             provider.as_ref(),
             &service_id.to_le_bytes(),
         ],
-        bump = user_subscription.bumps,
+        bump = user_subscription.bump,
         constraint = user_subscription.user == user @ ErrorCode::UnauthorizedUser,
         constraint = user_subscription.provider == provider @ ErrorCode::InvalidProvider,
         constraint = user_subscription.service_id == service_id @ ErrorCode::InvalidServiceId,
-        constraint = user_subscription.is_active @ ErrorCode::SubscriptionNotActive
+        constraint = user_subscription.is_active @ ErrorCode::SubscriptionNotActive,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_subscription: Account<'info, UserSubscription>,
 
@@ -101,10 +132,13 @@ pub struct ExecuteSubscriptionPayment<'info> {
             provider.as_ref(),
             &service_id.to_le_bytes(),
         ],
-        bump = subscription_service.bumps,
+        bump = subscription_service.bump,
         constraint = subscription_service.provider == provider @ ErrorCode::InvalidProvider,
         constraint = subscription_service.service_id == service_id @ ErrorCode::InvalidServiceId,
-        constraint = subscription_service.is_active @ ErrorCode::ServiceNotActive
+        constraint = subscription_service.is_active @ ErrorCode::ServiceNotActive,
+        // Checked before the records below are created at the caller's expense
+        constraint = !subscription_service.frozen @ ErrorCode::ServiceFrozen,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub subscription_service: Account<'info, SubscriptionService>,
 
@@ -113,7 +147,8 @@ pub struct ExecuteSubscriptionPayment<'info> {
         mut,
         seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
         bump = provider_account.bump,
-        constraint = provider_account.wallet == provider @ ErrorCode::InvalidProvider
+        constraint = provider_account.wallet == provider @ ErrorCode::InvalidProvider,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub provider_account: Account<'info, Provider>,
 
@@ -121,34 +156,153 @@ pub struct ExecuteSubscriptionPayment<'info> {
     #[account(
         mut,
         seeds = [b"vault", user.as_ref()],
-        bump,
+        bump = user_account.vault_bump,
     )]
     pub user_sol_vault: SystemAccount<'info>,
 
-    /// Provider's USDC account for receiving payments
+    /// Provider's earnings ledger, credited with the provider's share of each payment.
+    /// The USDC stays in the treasury until settle_provider_earnings, so billing never
+    /// depends on the provider's token account
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ProviderEarnings::INIT_SPACE,
+        seeds = [PROVIDER_EARNINGS_SEED.as_bytes(), provider.as_ref()],
+        bump
+    )]
+    pub provider_earnings: Box<Account<'info, ProviderEarnings>>,
+
+    /// Protocol treasury: receives the SOL and holds provider earnings in its USDC
+    /// account. Keeps the protocol fee unless a fee recipient is set
     #[account(
         mut,
-        associated_token::mint = usdc_mint,
-        associated_token::authority = provider
+        seeds = [b"treasury"],
+        bump = global_state.treasury_bump
     )]
-    pub provider_usdc_account: Account<'info, TokenAccount>,
+    pub treasury: SystemAccount<'info>,
 
     /// Protocol's USDC treasury token account
     #[account(
         mut,
-        seeds = [b"treasury"],
+        associated_token::mint = usdc_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub protocol_usdc_treasury: InterfaceAccount<'info, TokenAccount>,
+
+    /// GlobalState::fee_recipient, paid the protocol fee on SOL payments. Required
+    /// while a fee recipient is set
+    /// CHECK: Only receives lamports; the address is checked against GlobalState
+    #[account(
+        mut,
+        address = global_state.fee_recipient @ ErrorCode::InvalidFeeRecipient
+    )]
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
+
+    /// Fee recipient's USDC account, paid the protocol fee on USDC payments.
+    /// Required while a fee recipient is set
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = global_state.fee_recipient,
+        associated_token::token_program = token_program
+    )]
+    pub fee_recipient_usdc_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// USDC mint (classic SPL or Token-2022)
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
+    )]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    /// User's USDC vault, billed before the SOL vault when it covers the fee
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = user_sol_vault,
+        associated_token::token_program = token_program
+    )]
+    pub user_usdc_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Sponsor escrow for this subscription, billed before the user's own funds
+    #[account(
+        mut,
+        seeds = [SPONSOR_ESCROW_SEED.as_bytes(), user_subscription.key().as_ref()],
         bump
     )]
-    pub treasury: SystemAccount<'info>,
+    pub sponsor_escrow: SystemAccount<'info>,
+
+    /// Certificate mint for this subscription
+    /// CHECK: Address is checked by seeds; may not exist for compressed certificates
+    #[account(
+        seeds = [
+            CERTIFICATE_SEED.as_bytes(),
+            user.as_ref(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub certificate_nft_mint: AccountInfo<'info>,
+
+    /// Subscriber's certificate token account, frozen while the subscription is delinquent
+    /// CHECK: Address is checked against the certificate token program; may be closed
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &user,
+            &certificate_nft_mint.key(),
+            &certificate_token_program.key()
+        )
+    )]
+    pub certificate_nft_token_account: AccountInfo<'info>,
+
+    /// Freeze authority of the certificate mint
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+
+    /// Paid-through attributes of the certificate, created here for subscriptions
+    /// that predate them
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + CertificateAttributes::INIT_SPACE,
+        seeds = [
+            CERTIFICATE_ATTRIBUTES_SEED.as_bytes(),
+            certificate_nft_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    pub certificate_attributes: Box<Account<'info, CertificateAttributes>>,
 
-    /// USDC mint
-    pub usdc_mint: Account<'info, Mint>,
+    /// Audit record for this payment, one per billing cycle. Created on the cycle's
+    /// first attempt and filled in once the payment clears
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + PaymentRecord::INIT_SPACE,
+        seeds = [
+            PAYMENT_RECORD_SEED.as_bytes(),
+            user_subscription.key().as_ref(),
+            &user_subscription.total_payments_made.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment_record: Account<'info, PaymentRecord>,
 
     /// Pyth SOL/USD price feed
-    /// CHECK: Pyth price feed account
+    /// CHECK: Must be the price feed configured in GlobalState
+    #[account(address = global_state.sol_usd_price_feed @ ErrorCode::InvalidPriceFeed)]
     pub sol_usd_price_feed: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the certificate mint, which may differ from the settlement mint's
+    pub certificate_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
@@ -156,28 +310,57 @@ pub struct ExecuteSubscriptionPayment<'info> {
 impl<'info> ProcessSubscriptionPayments<'info> {
     /// Main entry point for daily batch processing of subscription payments
     /// Implements "Pay Subscription Fee 1" flow from the diagram
-    pub fn process_subscription_payments(&mut self) -> Result<()> {
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
+    pub fn process_subscription_payments(
+        ctx: Context<'_, '_, '_, 'info, ProcessSubscriptionPayments<'info>>,
+    ) -> Result<()> {
+        let accounts = ctx.accounts;
+        accounts.global_state.require_not_paused_for(PAUSE_PAYMENTS)?;
+        accounts.global_state.check_billing_caller(
+            &accounts.authority.key(),
+            accounts.keeper_registration.is_some(),
+        )?;
 
         let current_time = Clock::get()?.unix_timestamp;
 
-        msg!(
+        verbose_msg!(
             "Starting subscription payment processing batch at timestamp: {}",
             current_time
         );
 
+        let billing_accounts = split_billing_accounts(ctx.remaining_accounts)?;
+
         // Validate Pyth price feed is accessible
-        let sol_usd_price = Self::get_sol_usd_price_from_pyth(&self.sol_usd_price_feed)?;
-        msg!(
-            "Current SOL/USD price: ${:.2}",
-            sol_usd_price as f64 / 100.0
+        let sol_usd_price =
+            observe_sol_usd_price_cents(&accounts.sol_usd_price_feed, &mut accounts.global_state)?;
+        verbose_msg!(
+            "Current SOL/USD price: ${}",
+            cents_to_usd_string(sol_usd_price)
         );
 
+        let scan = scan_billing_page(
+            billing_accounts.subscriptions,
+            &billing_accounts.frozen_services,
+            current_time,
+            &accounts.event_authority,
+            ctx.bumps.event_authority,
+        )?;
+
         // Update the last payment processing timestamp
-        self.global_state.last_payment_processed = current_time;
+        accounts.global_state.last_payment_processed = current_time;
 
-        msg!(
-            "Subscription payment batch processing completed. Ready to execute individual payments."
+        emit_cpi_event(
+            &accounts.event_authority,
+            ctx.bumps.event_authority,
+            scan.summary(),
+        )?;
+
+        verbose_msg!(
+            "Subscription payment batch processing completed: {} of {} scanned, {} due, {} not due, {} failed. Ready to execute individual payments.",
+            scan.scanned,
+            billing_accounts.subscriptions.len(),
+            scan.processed,
+            scan.skipped,
+            scan.failed
         );
 
         Ok(())
@@ -189,7 +372,7 @@ impl<'info> ProcessSubscriptionPayments<'info> {
         // Payment is due if current time >= next_payment_due
         let is_due = current_time >= subscription.next_payment_due;
 
-        msg!(
+        verbose_msg!(
             "Payment due check for user {} provider {} service {}: {} (due: {}, current: {})",
             subscription.user,
             subscription.provider,
@@ -201,55 +384,151 @@ impl<'info> ProcessSubscriptionPayments<'info> {
 
         Ok(is_due)
     }
+}
 
-    /// Get real-time SOL/USD price from Pyth Network
-    fn get_sol_usd_price_from_pyth(price_feed_account: &AccountInfo) -> Result<u64> {
-        let price_feed = SolanaPriceAccount::account_info_to_feed(price_feed_account)
-            .map_err(|_| ErrorCode::InvalidPriceFeed)?;
+/// Tally of one billing scan over a page of UserSubscription accounts
+pub(crate) struct BillingScan {
+    pub scanned: u32,
+    pub processed: u32,
+    pub skipped: u32,
+    pub failed: u32,
+    pub first_failure_reason: u8,
+}
 
-        let current_time = Clock::get()?.unix_timestamp;
-        let max_age = 300; // 5 minutes for production
+impl BillingScan {
+    fn record_failure(&mut self, reason: u8) {
+        self.failed += 1;
+        if self.first_failure_reason == BILLING_REASON_NONE {
+            self.first_failure_reason = reason;
+        }
+    }
 
-        let price = price_feed
-            .get_price_no_older_than(current_time, max_age)
-            .ok_or(ErrorCode::PriceNotAvailable)?;
+    pub fn summary(&self) -> BatchCompleted {
+        BatchCompleted {
+            processed: self.processed,
+            skipped: self.skipped,
+            failed: self.failed,
+            first_failure_reason: self.first_failure_reason,
+        }
+    }
+}
 
-        require!(price.price > 0, ErrorCode::InvalidPrice);
+/// Billing remaining accounts: the page of UserSubscription accounts and the
+/// SubscriptionService accounts that may follow it
+pub(crate) struct BillingAccounts<'a, 'info> {
+    pub subscriptions: &'a [AccountInfo<'info>],
+    pub frozen_services: Vec<(Pubkey, u64)>, // (provider, service_id) of each frozen service passed
+}
 
-        // Convert to USD cents with proper precision handling
-        let price_cents = if price.expo >= 0 {
-            (price.price as u64)
-                .checked_mul(10_u64.pow(price.expo as u32))
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_mul(100)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-        } else {
-            let divisor = 10_u64.pow((-price.expo) as u32);
-            (price.price as u64)
-                .checked_mul(100)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(divisor)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
+/// Split billing remaining accounts at the first SubscriptionService
+pub(crate) fn split_billing_accounts<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<BillingAccounts<'a, 'info>> {
+    let page_len = accounts
+        .iter()
+        .position(|account_info| load_subscription_service_summary(account_info).is_some())
+        .unwrap_or(accounts.len());
+    let (subscriptions, services) = accounts.split_at(page_len);
+    require!(
+        subscriptions.len() <= MAX_SUBSCRIPTIONS_PER_BATCH
+            && services.len() <= MAX_SUBSCRIPTIONS_PER_BATCH,
+        ErrorCode::PageTooLarge
+    );
+
+    let frozen_services = services
+        .iter()
+        .filter_map(load_subscription_service_summary)
+        .filter(|service| service.frozen)
+        .map(|service| (service.provider, service.service_id))
+        .collect();
+    Ok(BillingAccounts {
+        subscriptions,
+        frozen_services,
+    })
+}
+
+/// Classify each subscription in `subscriptions` as due, not yet due or failed,
+/// emitting BillingSkipped for the failures. A due subscription of a service in
+/// `frozen_services` fails with BILLING_REASON_SERVICE_FROZEN, since
+/// execute_subscription_payment refuses it. Stops early when compute runs low,
+/// leaving `scanned` short of the page
+pub(crate) fn scan_billing_page<'info>(
+    subscriptions: &[AccountInfo<'info>],
+    frozen_services: &[(Pubkey, u64)],
+    current_time: i64,
+    event_authority: &AccountInfo<'info>,
+    event_authority_bump: u8,
+) -> Result<BillingScan> {
+    let mut scan = BillingScan {
+        scanned: 0,
+        processed: 0,
+        skipped: 0,
+        failed: 0,
+        first_failure_reason: BILLING_REASON_NONE,
+    };
+
+    for account_info in subscriptions {
+        if !has_compute_for_next_scan() {
+            msg!("Compute budget low, stopping after {} accounts", scan.scanned);
+            break;
+        }
+        scan.scanned += 1;
+
+        let subscription = match load_user_subscription(account_info) {
+            Some(subscription) => subscription,
+            None => {
+                // Nothing trustworthy to attribute a BillingSkipped event to
+                msg!("Skipping invalid subscription account {}", account_info.key());
+                scan.record_failure(BILLING_REASON_INVALID_ACCOUNT);
+                continue;
+            }
         };
 
-        // Validate reasonable SOL price range ($10 - $1000)
-        require!(
-            price_cents >= 1000 && price_cents <= 100000,
-            ErrorCode::InvalidPrice
-        );
+        let reason = if !subscription.is_active {
+            BILLING_REASON_SUBSCRIPTION_INACTIVE
+        } else if !ProcessSubscriptionPayments::check_payment_due(&subscription, current_time)? {
+            scan.skipped += 1;
+            continue;
+        } else if frozen_services.contains(&(subscription.provider, subscription.service_id)) {
+            BILLING_REASON_SERVICE_FROZEN
+        } else {
+            scan.processed += 1;
+            continue;
+        };
 
-        Ok(price_cents)
+        scan.record_failure(reason);
+        emit_cpi_event(
+            event_authority,
+            event_authority_bump,
+            BillingSkipped {
+                user: subscription.user,
+                provider: subscription.provider,
+                service_id: subscription.service_id,
+                reason,
+            },
+        )?;
     }
+
+    Ok(scan)
 }
 
 impl<'info> ExecuteSubscriptionPayment<'info> {
     /// Execute payment for a specific subscription - Production Implementation
     /// This implements the complete "Pay Subscription Fee 2" flow from the diagram
+    ///
+    /// CPI ordering rule: funds only move through the System and SPL Token programs,
+    /// which cannot call back into this program, and the treasury is reloaded and
+    /// checked after those transfers. Every account write is finished before the
+    /// end-of-billing marker; a CPI into any other program belongs below it, so a
+    /// failing callee rolls back a fully consistent state. `tests/billing_rollback.rs`
+    /// checks that rollback against the compiled program
     pub fn execute_payment(&mut self, bumps: &ExecuteSubscriptionPaymentBumps) -> Result<()> {
         let current_time = Clock::get()?.unix_timestamp;
 
-        // 1. Validate protocol state
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
+        // 1. Validate protocol state and that the caller may bill under the current mode
+        self.global_state.require_not_paused_for(PAUSE_PAYMENTS)?;
+        self.global_state
+            .check_billing_caller(&self.authority.key(), self.keeper_registration.is_some())?;
 
         // 2. Verify payment is actually due (critical validation)
         require!(
@@ -263,85 +542,381 @@ impl<'info> ExecuteSubscriptionPayment<'info> {
             ErrorCode::SubscriptionNotActive
         );
 
-        // 4. Verify service is still active
+        // 4. Verify service is still active and not frozen pending review. The scan
+        // reports a frozen service's subscriptions as skipped rather than due
         require!(
             self.subscription_service.is_active,
             ErrorCode::ServiceNotActive
         );
-
-        // 5. Get real-time pricing from Pyth
-        let sol_usd_price = Self::get_sol_usd_price_from_pyth(&self.sol_usd_price_feed)?;
-        msg!(
-            "Current SOL/USD price: ${:.2}",
-            sol_usd_price as f64 / 100.0
-        );
+        require!(!self.subscription_service.frozen, ErrorCode::ServiceFrozen);
+
+        // 5. Billing follows the certificate: a subscriber who no longer holds it is
+        // not charged for access and goes into the delinquency flow instead
+        if !self.certificate_held()? {
+            return self.mark_delinquent(
+                current_time,
+                BILLING_REASON_CERTIFICATE_MISSING,
+                bumps,
+            );
+        }
 
         // 6. Calculate payment amounts
-        let fee_usd = self.subscription_service.fee_usd; // in cents
+        let fee_usd = self
+            .user_subscription
+            .discounted_fee(self.subscription_service.fee_usd)?; // in cents, after any promo
         let billing_frequency_days = self.subscription_service.billing_frequency_days;
 
-        // 7. Convert USD fee to SOL lamports using real-time price
-        let sol_amount_needed = Self::convert_usd_to_sol_lamports(fee_usd, sol_usd_price)?;
-
-        // 8. Verify user has sufficient funds
-        require!(
-            self.user_sol_vault.lamports() >= sol_amount_needed,
-            ErrorCode::InsufficientBalance
-        );
+        // 7. A sponsor escrow for this subscription pays first, as long as it covers the fee.
+        // The oracle is read at most once per payment
+        let mut oracle_price = None;
+        let sponsored_quote = self.sponsor_escrow_quote(fee_usd, &mut oracle_price)?;
+
+        // 8. Then the user's USDC balance: the fee is already in USD, so no oracle is needed
+        let usdc_fee_amount = convert_usd_to_token_amount(fee_usd, self.usdc_mint.decimals)?;
+        let (
+            sol_amount_needed,
+            sol_from_user_vault,
+            protocol_fee_amount,
+            usdc_amount_for_provider,
+            provider_credited_amount,
+        ) = if let Some((sol_usd_price, sol_amount_needed)) = sponsored_quote {
+            self.transfer_sol_from_sponsor_escrow(sol_amount_needed, bumps)?;
+            let (protocol_fee_amount, usdc_amount_for_provider) =
+                self.settle_sol_payment(sol_amount_needed, sol_usd_price)?;
+
+            (
+                sol_amount_needed,
+                0,
+                protocol_fee_amount,
+                usdc_amount_for_provider,
+                usdc_amount_for_provider,
+            )
+        } else if self.usdc_covers(usdc_fee_amount)? {
+            let (provider_share, credited) = self.pay_from_usdc_vault(usdc_fee_amount)?;
+            (0, 0, 0, provider_share, credited)
+        } else {
+            // 9. Get real-time pricing from Pyth
+            let sol_usd_price = self.sol_usd_price(&mut oracle_price)?;
+            verbose_msg!(
+                "Current SOL/USD price: ${}",
+                cents_to_usd_string(sol_usd_price)
+            );
+
+            // 10. Convert USD fee to SOL lamports using real-time price
+            let sol_amount_needed = convert_usd_to_sol_lamports(fee_usd, sol_usd_price)?;
+
+            // 11. A vault that cannot cover the fee above its rent floor and any pending
+            // withdrawal makes the subscription delinquent. Returning Ok keeps the
+            // delinquency state instead of rolling it back
+            let billable_balance = spendable_vault_balance(&self.user_sol_vault)?
+                .saturating_sub(self.user_account.pending_withdrawal_lamports);
+            if billable_balance < sol_amount_needed {
+                return self.mark_delinquent(
+                    current_time,
+                    BILLING_REASON_INSUFFICIENT_BALANCE,
+                    bumps,
+                );
+            }
+
+            // 12. Execute SOL transfers from user vault
+            self.transfer_sol_from_user_vault(sol_amount_needed)?;
+
+            // 13. Take the protocol fee and convert the rest to USDC for the provider
+            let (protocol_fee_amount, usdc_amount_for_provider) =
+                self.settle_sol_payment(sol_amount_needed, sol_usd_price)?;
+
+            (
+                sol_amount_needed,
+                sol_amount_needed,
+                protocol_fee_amount,
+                usdc_amount_for_provider,
+                usdc_amount_for_provider,
+            )
+        };
 
-        // 9. Calculate protocol fee
-        let protocol_fee_bps = self.global_state.protocol_fee_bps;
-        let protocol_fee_amount = sol_amount_needed
-            .checked_mul(protocol_fee_bps as u64)
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        // 14. Credit the provider's share as a pending payout
+        self.credit_provider_earnings(provider_credited_amount, current_time, bumps)?;
 
-        let provider_payment_amount = sol_amount_needed
-            .checked_sub(protocol_fee_amount)
-            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        // 15. Update subscription state, lifting any delinquency
+        self.update_subscription_after_payment(billing_frequency_days, current_time)?;
+        self.clear_delinquency(bumps)?;
 
-        // 10. Execute SOL transfers from user vault
-        self.transfer_sol_from_user_vault(sol_amount_needed, bumps)?;
+        // 16. Extend the certificate's paid-through date to the new due date
+        self.handle_subscription_certificate(current_time, bumps)?;
 
-        // 11. Convert SOL to USDC and pay provider
-        let usdc_amount_for_provider =
-            Self::convert_sol_to_usdc_amount(provider_payment_amount, sol_usd_price)?;
+        // 17. Update user account balances
+        self.update_user_balances(sol_from_user_vault)?;
 
-        // 12. Transfer USDC to provider
-        self.transfer_usdc_to_provider(usdc_amount_for_provider, bumps)?;
+        // 18. Update protocol counters
+        self.update_protocol_counters(sol_amount_needed, sol_from_user_vault, protocol_fee_amount)?;
 
-        // 13. Handle subscription certificate (burn if final payment or update)
-        self.handle_subscription_certificate(current_time, bumps)?;
-
-        // 14. Update subscription state
-        self.update_subscription_after_payment(billing_frequency_days, current_time)?;
+        // 19. Record the payment and what the provider was credited
+        self.record_payment(
+            sol_amount_needed,
+            usdc_amount_for_provider,
+            provider_credited_amount,
+            current_time,
+            bumps,
+        );
 
-        // 15. Update user account balances
-        self.update_user_balances(sol_amount_needed)?;
+        // ---- Billing state is final. CPIs into programs other than System or SPL Token go below ----
 
-        // 16. Log successful payment
-        msg!(
-            "PAYMENT EXECUTED: User {} paid {} SOL (${:.2}) to provider {} for service {} | Protocol fee: {} SOL | Next due: {}",
+        // 20. Log successful payment
+        verbose_msg!(
+            "PAYMENT EXECUTED: User {} paid {} SOL (${}) to provider {} for service {} | Protocol fee: {} SOL | Next due: {}",
             self.user_account.wallet,
-            sol_amount_needed as f64 / 1_000_000_000.0,
-            fee_usd as f64 / 100.0,
+            lamports_to_sol_string(sol_amount_needed),
+            cents_to_usd_string(fee_usd),
             self.subscription_service.provider,
             self.subscription_service.service_id,
-            protocol_fee_amount as f64 / 1_000_000_000.0,
+            lamports_to_sol_string(protocol_fee_amount),
             self.user_subscription.next_payment_due
         );
 
         Ok(())
     }
 
-    /// Transfer SOL from user vault to treasury for conversion
-    fn transfer_sol_from_user_vault(
+    /// Whether the user's USDC vault can pay `usdc_fee_amount`. USDC locked for this
+    /// subscription counts, USDC locked for other subscriptions does not
+    fn usdc_covers(&self, usdc_fee_amount: u64) -> Result<bool> {
+        if self.user_usdc_vault.is_none() {
+            return Ok(false);
+        }
+        let spendable = self
+            .user_account
+            .available_usdc()?
+            .checked_add(self.user_subscription.locked_usdc)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(spendable >= usdc_fee_amount)
+    }
+
+    /// Pay the fee straight from the user's USDC vault. The USDC treasury holds the
+    /// provider's share as a pending payout and keeps the protocol fee, unless a
+    /// fee recipient is set and takes it instead. The fee is drawn from this
+    /// subscription's USDC lock first. Returns (provider's share, amount to credit
+    /// after any transfer fee on the share)
+    fn pay_from_usdc_vault(&mut self, usdc_fee_amount: u64) -> Result<(u64, u64)> {
+        let Some(user_usdc_vault) = self.user_usdc_vault.as_ref() else {
+            return err!(ErrorCode::InsufficientBalance);
+        };
+
+        let (protocol_fee_amount, provider_amount) =
+            split_payment(usdc_fee_amount, self.protocol_fee_bps())?;
+        // Sent on its own, the provider's share arrives less exactly this fee. Sent
+        // with the protocol fee, the fee withheld on the whole transfer never exceeds
+        // the fees on its two shares, so the protocol's share covers any difference
+        let transfer_fee = transfer_fee_amount(&self.usdc_mint.to_account_info(), provider_amount)?;
+        let credited_amount = provider_amount
+            .checked_sub(transfer_fee)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        let treasury_amount = match self.global_state.fee_recipient() {
+            Some(_) if protocol_fee_amount > 0 => {
+                let fee_recipient_usdc_account = self
+                    .fee_recipient_usdc_account
+                    .as_ref()
+                    .ok_or(ErrorCode::FeeRecipientAccountMissing)?;
+                self.transfer_from_usdc_vault(
+                    user_usdc_vault.to_account_info(),
+                    fee_recipient_usdc_account.to_account_info(),
+                    protocol_fee_amount,
+                )?;
+                provider_amount
+            }
+            _ => usdc_fee_amount,
+        };
+        self.transfer_from_usdc_vault(
+            user_usdc_vault.to_account_info(),
+            self.protocol_usdc_treasury.to_account_info(),
+            treasury_amount,
+        )?;
+        // The only transfer that changes the treasury's USDC balance during billing
+        self.protocol_usdc_treasury.reload()?;
+
+        let from_lock = usdc_fee_amount.min(self.user_subscription.locked_usdc);
+        self.user_subscription.locked_usdc -= from_lock;
+        self.user_account.release_locked_usdc(from_lock)?;
+        self.user_account.usdc_balance = self
+            .user_account
+            .usdc_balance
+            .checked_sub(usdc_fee_amount)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        self.global_state.record_usdc_payment(protocol_fee_amount)?;
+
+        verbose_msg!(
+            "Paid {} USDC base units from user vault ({} for provider {}, {} protocol fee)",
+            usdc_fee_amount,
+            provider_amount,
+            self.subscription_service.provider,
+            protocol_fee_amount
+        );
+
+        Ok((provider_amount, credited_amount))
+    }
+
+    /// Transfer `amount` of USDC out of the user's USDC vault, signed by the SOL vault
+    fn transfer_from_usdc_vault(
+        &self,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        let user_key = self.user_account.wallet;
+        let vault_bump = self.user_account.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", user_key.as_ref(), &[vault_bump]];
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from,
+                    mint: self.usdc_mint.to_account_info(),
+                    to,
+                    authority: self.user_sol_vault.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            amount,
+            self.usdc_mint.decimals,
+        )
+    }
+
+    /// The SOL/USD price from Pyth, read once and kept in `oracle_price` for the
+    /// rest of the payment
+    fn sol_usd_price(&mut self, oracle_price: &mut Option<u64>) -> Result<u64> {
+        if let Some(price) = *oracle_price {
+            return Ok(price);
+        }
+        let price = observe_sol_usd_price_cents(&self.sol_usd_price_feed, &mut self.global_state)?;
+        *oracle_price = Some(price);
+        Ok(price)
+    }
+
+    /// The SOL price and fee in lamports when this subscription's sponsor escrow
+    /// covers the whole fee. A short escrow is left alone and billing falls back
+    /// to the user's own balances; the remainder goes back to the sponsor on cancellation
+    fn sponsor_escrow_quote(
+        &mut self,
+        fee_usd: u64,
+        oracle_price: &mut Option<u64>,
+    ) -> Result<Option<(u64, u64)>> {
+        let escrow_lamports = self.user_subscription.sponsor_escrow_lamports;
+        if escrow_lamports == 0 {
+            return Ok(None);
+        }
+
+        let sol_usd_price = self.sol_usd_price(oracle_price)?;
+        let sol_amount_needed = convert_usd_to_sol_lamports(fee_usd, sol_usd_price)?;
+        if escrow_lamports < sol_amount_needed {
+            msg!(
+                "Sponsor escrow ({} SOL) no longer covers the fee, billing the user",
+                lamports_to_sol_string(escrow_lamports)
+            );
+            return Ok(None);
+        }
+        Ok(Some((sol_usd_price, sol_amount_needed)))
+    }
+
+    /// Transfer SOL from the subscription's sponsor escrow to the treasury for conversion
+    fn transfer_sol_from_sponsor_escrow(
         &mut self,
         amount: u64,
         bumps: &ExecuteSubscriptionPaymentBumps,
     ) -> Result<()> {
-        let user_vault_bump = bumps.user_sol_vault;
+        let subscription_key = self.user_subscription.key();
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: self.sponsor_escrow.to_account_info(),
+                    to: self.treasury.to_account_info(),
+                },
+                &[&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    subscription_key.as_ref(),
+                    &[bumps.sponsor_escrow],
+                ]],
+            ),
+            amount,
+        )?;
+
+        self.user_subscription.sponsor_escrow_lamports = self
+            .user_subscription
+            .sponsor_escrow_lamports
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        verbose_msg!(
+            "Transferred {} SOL from sponsor escrow to treasury, {} SOL left",
+            lamports_to_sol_string(amount),
+            lamports_to_sol_string(self.user_subscription.sponsor_escrow_lamports)
+        );
+        Ok(())
+    }
+
+    /// Rate for this payment: verified providers pay verified_fee_bps when set,
+    /// and a protocol fee change only applies to payments that fall due after
+    /// it, not to ones already due and waiting to be charged
+    fn protocol_fee_bps(&self) -> u16 {
+        self.global_state.protocol_fee_bps_for_provider(
+            self.user_subscription.next_payment_due,
+            self.provider_account.is_verified,
+        )
+    }
+
+    /// Split SOL already in the treasury into the protocol fee and the provider's
+    /// share, converted to USDC at the oracle price. The USDC stays in the treasury
+    /// as the provider's pending payout, and the fee goes on to the fee recipient
+    /// when one is set. Returns (protocol fee, USDC for the provider)
+    fn settle_sol_payment(&mut self, sol_amount: u64, sol_usd_price: u64) -> Result<(u64, u64)> {
+        let (protocol_fee_amount, provider_payment_amount) =
+            split_payment(sol_amount, self.protocol_fee_bps())?;
+        self.transfer_protocol_fee_from_treasury(protocol_fee_amount)?;
+
+        let usdc_amount_for_provider = convert_sol_to_token_amount(
+            provider_payment_amount,
+            sol_usd_price,
+            self.usdc_mint.decimals,
+        )?;
+
+        Ok((protocol_fee_amount, usdc_amount_for_provider))
+    }
+
+    /// Forward the SOL protocol fee from the treasury to the fee recipient. Without
+    /// a fee recipient the fee stays in the treasury
+    fn transfer_protocol_fee_from_treasury(&self, amount: u64) -> Result<()> {
+        if self.global_state.fee_recipient().is_none() || amount == 0 {
+            return Ok(());
+        }
+        let fee_recipient = self
+            .fee_recipient
+            .as_ref()
+            .ok_or(ErrorCode::FeeRecipientAccountMissing)?;
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: self.treasury.to_account_info(),
+                    to: fee_recipient.to_account_info(),
+                },
+                &[&[b"treasury", &[self.global_state.treasury_bump]]],
+            ),
+            amount,
+        )?;
+
+        verbose_msg!(
+            "Paid {} SOL protocol fee to {}",
+            lamports_to_sol_string(amount),
+            fee_recipient.key()
+        );
+        Ok(())
+    }
+
+    /// Transfer SOL from user vault to treasury for conversion
+    fn transfer_sol_from_user_vault(&mut self, amount: u64) -> Result<()> {
+        let user_vault_bump = self.user_account.vault_bump;
         let user_key = self.user_account.wallet;
 
         let transfer_ix = anchor_lang::system_program::Transfer {
@@ -358,44 +933,204 @@ impl<'info> ExecuteSubscriptionPayment<'info> {
             amount,
         )?;
 
-        msg!(
+        verbose_msg!(
             "Transferred {} SOL from user vault to treasury",
-            amount as f64 / 1_000_000_000.0
+            lamports_to_sol_string(amount)
         );
         Ok(())
     }
 
-    /// Transfer USDC to provider account (production implementation)
-    fn transfer_usdc_to_provider(
-        &self,
-        usdc_amount: u64,
-        _bumps: &ExecuteSubscriptionPaymentBumps,
+    /// Credit the provider's earnings ledger and the protocol-wide pending total.
+    /// The treasury must hold every pending payout, including this one, so a
+    /// treasury short of USDC fails the payment instead of owing the provider.
+    /// Its balance is current: pay_from_usdc_vault reloads it after paying in
+    fn credit_provider_earnings(
+        &mut self,
+        amount: u64,
+        current_time: i64,
+        bumps: &ExecuteSubscriptionPaymentBumps,
     ) -> Result<()> {
-        // In simplified version, we transfer SOL directly to provider
-        // This is a placeholder for USDC conversion logic
-        msg!(
-            "Payment of {} USDC equivalent sent to provider {}",
-            usdc_amount as f64 / 1_000_000.0, // USDC has 6 decimals
-            self.subscription_service.provider
-        );
+        let earnings = &mut self.provider_earnings;
+        earnings.set_provider_if_new(self.subscription_service.provider, bumps.provider_earnings);
+        earnings.credit(amount, current_time)?;
+
+        self.global_state.total_pending_payouts_usdc = self
+            .global_state
+            .total_pending_payouts_usdc
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.global_state
+            .require_payouts_covered(self.protocol_usdc_treasury.amount, 0)?;
 
+        verbose_msg!(
+            "Credited {} USDC base units to provider {}, {} pending",
+            amount,
+            self.subscription_service.provider,
+            self.provider_earnings.pending_usdc
+        );
         Ok(())
     }
 
-    /// Handle subscription certificate NFT (simplified version)
+    /// Record the new paid-through date on the certificate's attributes account
     fn handle_subscription_certificate(
         &mut self,
         current_time: i64,
-        _bumps: &ExecuteSubscriptionPaymentBumps,
+        bumps: &ExecuteSubscriptionPaymentBumps,
+    ) -> Result<()> {
+        let paid_through = self.user_subscription.next_payment_due;
+
+        let rent_sponsored = self.certificate_attributes.rent_sponsored;
+        self.certificate_attributes.set_inner(CertificateAttributes {
+            mint: self.certificate_nft_mint.key(),
+            paid_through,
+            updated_at: current_time,
+            rent_sponsored,
+            bump: bumps.certificate_attributes,
+        });
+
+        verbose_msg!(
+            "Certificate {} paid through {}",
+            self.certificate_nft_mint.key(),
+            paid_through
+        );
+        Ok(())
+    }
+
+    /// Record a failed billing attempt with its BILLING_REASON_* code. The first
+    /// failure starts the grace period, freezes the certificate and notifies the provider
+    fn mark_delinquent(
+        &mut self,
+        current_time: i64,
+        reason: u8,
+        bumps: &ExecuteSubscriptionPaymentBumps,
     ) -> Result<()> {
-        // In simplified version, no certificate burning
+        let subscription = &mut self.user_subscription;
+        subscription.failed_payment_attempts = subscription
+            .failed_payment_attempts
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let failed_attempts = subscription.failed_payment_attempts;
+
+        if subscription.delinquent_since.is_some() {
+            msg!(
+                "Payment attempt {} failed (reason {}); subscription already delinquent",
+                failed_attempts,
+                reason
+            );
+            return Ok(());
+        }
+
+        subscription.delinquent_since = Some(current_time);
+        let grace_ends_at = subscription.grace_ends_at()?;
+
+        self.set_certificate_frozen(true, bumps)?;
+
         msg!(
-            "Certificate handling completed for timestamp {}",
-            current_time
+            "Subscription delinquent (reason {}), grace ends at {}",
+            reason,
+            grace_ends_at
         );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionDelinquent {
+                user: self.user_subscription.user,
+                provider: self.user_subscription.provider,
+                service_id: self.user_subscription.service_id,
+                failed_attempts,
+                grace_ends_at,
+                reason,
+            },
+        )
+    }
+
+    /// Whether the subscriber still holds exactly one certificate for this subscription.
+    /// Compressed certificates cannot be checked without a Merkle proof and always pass.
+    fn certificate_held(&self) -> Result<bool> {
+        if self.user_subscription.certificate_asset_id != Pubkey::default() {
+            return Ok(true);
+        }
+
+        // A wrong token program would derive a different, empty token account
+        if !self.certificate_nft_mint.data_is_empty() {
+            require!(
+                self.certificate_token_program.key() == *self.certificate_nft_mint.owner,
+                ErrorCode::InvalidCertificateTokenProgram
+            );
+        }
+
+        let Some(token_account) = load_token_account(&self.certificate_nft_token_account)? else {
+            return Ok(false);
+        };
+        Ok(token_account.mint == self.certificate_nft_mint.key()
+            && token_account.amount == 1
+            && token_account.owner == self.user_subscription.user)
+    }
+
+    /// Reset the delinquency state after a payment clears and thaw the certificate
+    fn clear_delinquency(&mut self, bumps: &ExecuteSubscriptionPaymentBumps) -> Result<()> {
+        self.user_subscription.failed_payment_attempts = 0;
+        if self.user_subscription.delinquent_since.take().is_some() {
+            self.set_certificate_frozen(false, bumps)?;
+            verbose_msg!("Subscription delinquency cleared");
+        }
         Ok(())
     }
 
+    /// Freeze or thaw the subscriber's certificate with the certificate authority PDA.
+    /// Only Token-2022 certificates are toggled: classic certificates stay frozen to the
+    /// subscriber for their whole life, and compressed certificates cannot be frozen.
+    /// Closed certificate accounts are skipped.
+    fn set_certificate_frozen(
+        &self,
+        frozen: bool,
+        bumps: &ExecuteSubscriptionPaymentBumps,
+    ) -> Result<()> {
+        if self.certificate_nft_mint.owner != &token_2022::ID {
+            return Ok(());
+        }
+        require!(
+            self.certificate_token_program.key() == token_2022::ID,
+            ErrorCode::InvalidCertificateTokenProgram
+        );
+
+        let Some(token_account) = load_token_account(&self.certificate_nft_token_account)? else {
+            return Ok(());
+        };
+        if token_account.is_frozen() == frozen {
+            return Ok(());
+        }
+
+        let authority_seeds: &[&[u8]] = &[
+            CERTIFICATE_AUTHORITY_SEED.as_bytes(),
+            &[bumps.certificate_authority],
+        ];
+        let signer = &[authority_seeds];
+
+        if frozen {
+            freeze_account(CpiContext::new_with_signer(
+                self.certificate_token_program.to_account_info(),
+                FreezeAccount {
+                    account: self.certificate_nft_token_account.to_account_info(),
+                    mint: self.certificate_nft_mint.to_account_info(),
+                    authority: self.certificate_authority.to_account_info(),
+                },
+                signer,
+            ))
+        } else {
+            thaw_account(CpiContext::new_with_signer(
+                self.certificate_token_program.to_account_info(),
+                ThawAccount {
+                    account: self.certificate_nft_token_account.to_account_info(),
+                    mint: self.certificate_nft_mint.to_account_info(),
+                    authority: self.certificate_authority.to_account_info(),
+                },
+                signer,
+            ))
+        }
+    }
+
     /// Update subscription account after successful payment
     fn update_subscription_after_payment(
         &mut self,
@@ -418,7 +1153,7 @@ impl<'info> ExecuteSubscriptionPayment<'info> {
             .checked_add(billing_period_seconds)
             .ok_or(ErrorCode::ArithmeticOverflow)?;
 
-        msg!(
+        verbose_msg!(
             "Updated subscription: payment #{}, next due at timestamp {}",
             self.user_subscription.total_payments_made,
             self.user_subscription.next_payment_due
@@ -429,132 +1164,49 @@ impl<'info> ExecuteSubscriptionPayment<'info> {
 
     /// Update user account balances after payment
     fn update_user_balances(&mut self, payment_amount: u64) -> Result<()> {
-        // Deduct from deposited SOL
-        self.user_account.deposited_sol = self
-            .user_account
-            .deposited_sol
-            .checked_sub(payment_amount)
-            .ok_or(ErrorCode::InsufficientBalance)?;
+        // Deduct from deposited SOL. The subscription's lock stays until unsubscribe
+        self.user_account.record_sol_payment(payment_amount)?;
 
-        // Update locked SOL for active subscriptions
-        // In production, this would be more sophisticated based on remaining subscription periods
-        msg!(
+        verbose_msg!(
             "Updated user balances: deposited_sol reduced by {} SOL",
-            payment_amount as f64 / 1_000_000_000.0
+            lamports_to_sol_string(payment_amount)
         );
 
         Ok(())
     }
 
-    /// Get SOL/USD price from Pyth Network - Production Implementation
-    fn get_sol_usd_price_from_pyth(price_feed_account: &AccountInfo) -> Result<u64> {
-        let price_feed = SolanaPriceAccount::account_info_to_feed(price_feed_account)
-            .map_err(|_| ErrorCode::InvalidPriceFeed)?;
-
-        let current_time = Clock::get()?.unix_timestamp;
-        let max_age = 300; // 5 minutes max age for production
-
-        let price = price_feed
-            .get_price_no_older_than(current_time, max_age)
-            .ok_or(ErrorCode::PriceNotAvailable)?;
-
-        require!(price.price > 0, ErrorCode::InvalidPrice);
-
-        // Convert to USD cents
-        let price_cents = if price.expo >= 0 {
-            (price.price as u64)
-                .checked_mul(10_u64.pow(price.expo as u32))
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_mul(100)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-        } else {
-            let divisor = 10_u64.pow((-price.expo) as u32);
-            (price.price as u64)
-                .checked_mul(100)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(divisor)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-        };
-
-        require!(
-            price_cents >= 1000 && price_cents <= 100000,
-            ErrorCode::InvalidPrice
-        );
-
-        Ok(price_cents)
-    }
-
-    /// Convert USD cents to SOL lamports
-    fn convert_usd_to_sol_lamports(usd_cents: u64, sol_usd_cents: u64) -> Result<u64> {
-        let lamports = (usd_cents as u128)
-            .checked_mul(1_000_000_000) // LAMPORTS_PER_SOL
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(sol_usd_cents as u128)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-        Ok(u64::try_from(lamports).map_err(|_| ErrorCode::ArithmeticOverflow)?)
-    }
-
-    /// Convert SOL lamports to USDC amount (6 decimals)
-    fn convert_sol_to_usdc_amount(sol_lamports: u64, sol_usd_cents: u64) -> Result<u64> {
-        let usdc_amount = (sol_lamports as u128)
-            .checked_mul(sol_usd_cents as u128)
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(1_000_000_000) // LAMPORTS_PER_SOL
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_mul(10000) // Convert cents to USDC (6 decimals): cents * 10000 = micro-dollars
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
-
-        Ok(u64::try_from(usdc_amount).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+    /// Update protocol-wide counters after payment
+    fn update_protocol_counters(
+        &mut self,
+        payment_amount: u64,
+        paid_from_deposits: u64,
+        protocol_fee: u64,
+    ) -> Result<()> {
+        self.global_state
+            .record_payment(payment_amount, paid_from_deposits, protocol_fee)
     }
-}
-
-/// Payment record creation for audit trail (simplified)
-#[derive(Accounts)]
-pub struct CreatePaymentRecord<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-
-    #[account(
-        seeds = [b"global_state"],
-        bump
-    )]
-    pub global_state: Account<'info, GlobalState>,
-
-    /// Payment record account
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + PaymentRecord::INIT_SPACE,
-        seeds = [b"payment_record", authority.key().as_ref()],
-        bump
-    )]
-    pub payment_record: Account<'info, PaymentRecord>,
-
-    pub system_program: Program<'info, System>,
-}
-
-impl<'info> CreatePaymentRecord<'info> {
-    /// Create a payment record for audit purposes (simplified)
-    pub fn create_payment_record(&mut self, amount: u64) -> Result<()> {
-        let current_time = Clock::get()?.unix_timestamp;
 
+    /// Write the audit record for this billing cycle
+    fn record_payment(
+        &mut self,
+        payment_amount: u64,
+        settlement_amount: u64,
+        provider_credited_amount: u64,
+        current_time: i64,
+        bumps: &ExecuteSubscriptionPaymentBumps,
+    ) {
         self.payment_record.set_inner(PaymentRecord {
-            user: self.authority.key(),
-            provider: self.authority.key(), // Simplified
-            subscription_id: 0,             // Simplified
-            amount,
+            user: self.user_account.wallet,
+            provider: self.subscription_service.provider,
+            subscription_id: self.user_subscription.subscription_id,
+            amount: payment_amount,
             payment_date: current_time,
             payment_type: PaymentType::Subscription,
-            bump: 0, // Will be set by Anchor
+            settlement_mint: self.usdc_mint.key(),
+            settlement_amount,
+            provider_received_amount: provider_credited_amount,
+            disputed: false,
+            bump: bumps.payment_record,
         });
-
-        msg!(
-            "Payment record created: {} lamports at timestamp {}",
-            amount,
-            current_time
-        );
-
-        Ok(())
     }
 }
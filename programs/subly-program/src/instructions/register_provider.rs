@@ -1,10 +1,15 @@
-use crate::{constants::*, error::ErrorCode, state::*};
-use anchor_lang::prelude::*;
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::{prelude::*, solana_program::hash::hash};
 use anchor_spl::{
     associated_token::AssociatedToken,
+    metadata::{
+        create_master_edition_v3, create_metadata_accounts_v3, CreateMasterEditionV3,
+        CreateMetadataAccountsV3, Metadata,
+    },
     token::{mint_to, Mint, MintTo, Token, TokenAccount},
 };
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(name: String)]
 pub struct RegisterProvider<'info> {
@@ -14,14 +19,15 @@ pub struct RegisterProvider<'info> {
     #[account(
         mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
     #[account(
         init,
         payer = provider,
-        space = Provider::INIT_SPACE,
+        space = 8 + Provider::INIT_SPACE,
         seeds = [PROVIDER_SEED.as_bytes(), provider.key().as_ref()],
         bump
     )]
@@ -45,9 +51,67 @@ pub struct RegisterProvider<'info> {
     )]
     pub provider_nft_token_account: Account<'info, TokenAccount>,
 
+    // Collection NFT that the provider's certificates are verified into.
+    // Held and controlled by the certificate authority so only the protocol can verify items
+    #[account(
+        init,
+        payer = provider,
+        seeds = [CERTIFICATE_COLLECTION_SEED.as_bytes(), provider.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = certificate_authority,
+        mint::freeze_authority = certificate_authority,
+    )]
+    pub certificate_collection_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = provider,
+        associated_token::mint = certificate_collection_mint,
+        associated_token::authority = certificate_authority,
+    )]
+    pub certificate_collection_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Address is checked by seeds; the Token Metadata program initializes it
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            certificate_collection_mint.key().as_ref()
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub certificate_collection_metadata: AccountInfo<'info>,
+
+    /// CHECK: Address is checked by seeds; the Token Metadata program initializes it
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            certificate_collection_mint.key().as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub certificate_collection_master_edition: AccountInfo<'info>,
+
+    /// Protocol PDA acting as certificate mint, freeze and metadata update authority
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 impl<'info> RegisterProvider<'info> {
@@ -57,23 +121,31 @@ impl<'info> RegisterProvider<'info> {
         description: String,
         bumps: &RegisterProviderBumps,
     ) -> Result<()> {
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
-        require!(name.len() <= MAX_NAME_LENGTH, ErrorCode::NameTooLong);
-        require!(
-            description.len() <= MAX_DESCRIPTION_LENGTH,
-            ErrorCode::DescriptionTooLong
-        );
+        self.global_state.require_not_paused()?;
+        validate_name(&name)?;
+        validate_description(&description)?;
 
         let provider_account = &mut self.provider_account;
 
+        provider_account.version = PROVIDER_VERSION;
         provider_account.wallet = self.provider.key();
         provider_account.name = name.clone();
         provider_account.description = description;
         provider_account.total_subscribers = 0;
         provider_account.is_verified = false;
         provider_account.created_at = Clock::get()?.unix_timestamp;
+        provider_account.last_service_registered_at = 0;
+        provider_account.services_registered_in_window = 0;
+        provider_account.is_banned = false;
+        provider_account.verified_at = 0;
         provider_account.bump = bumps.provider_account;
 
+        self.global_state.total_providers = self
+            .global_state
+            .total_providers
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
         // Mint provider verification NFT
         let cpi_accounts = MintTo {
             mint: self.provider_nft_mint.to_account_info(),
@@ -84,12 +156,94 @@ impl<'info> RegisterProvider<'info> {
         let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
         mint_to(cpi_ctx, 1)?;
 
+        self.create_certificate_collection(&name, bumps)?;
+
         msg!(
-            "Provider '{}' registered with NFT: {}",
+            "Provider '{}' registered with NFT: {}, certificate collection: {}",
             name,
-            self.provider_nft_mint.key()
+            self.provider_nft_mint.key(),
+            self.certificate_collection_mint.key()
         );
 
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ProviderRegistered {
+                wallet: self.provider.key(),
+                name_hash: hash(normalize_name(&name).as_bytes()).to_bytes(),
+                nft_mint: self.provider_nft_mint.key(),
+                created_at: self.provider_account.created_at,
+            },
+        )?;
+
         Ok(())
     }
+
+    /// Mint the provider's certificate collection NFT and give it metadata and a
+    /// master edition, as Token Metadata requires for collection verification.
+    /// The collection is unsized so certificates can be burned with plain token
+    /// burns without keeping a collection size in sync.
+    fn create_certificate_collection(
+        &self,
+        name: &str,
+        bumps: &RegisterProviderBumps,
+    ) -> Result<()> {
+        let authority_seeds: &[&[u8]] = &[
+            CERTIFICATE_AUTHORITY_SEED.as_bytes(),
+            &[bumps.certificate_authority],
+        ];
+        let signer = &[authority_seeds];
+
+        mint_to(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                MintTo {
+                    mint: self.certificate_collection_mint.to_account_info(),
+                    to: self.certificate_collection_token_account.to_account_info(),
+                    authority: self.certificate_authority.to_account_info(),
+                },
+                signer,
+            ),
+            1,
+        )?;
+
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                self.token_metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: self.certificate_collection_metadata.to_account_info(),
+                    mint: self.certificate_collection_mint.to_account_info(),
+                    mint_authority: self.certificate_authority.to_account_info(),
+                    payer: self.provider.to_account_info(),
+                    update_authority: self.certificate_authority.to_account_info(),
+                    system_program: self.system_program.to_account_info(),
+                    rent: self.rent.to_account_info(),
+                },
+                signer,
+            ),
+            certificate_collection_metadata(name),
+            true, // is_mutable
+            true, // update_authority_is_signer
+            None,
+        )?;
+
+        create_master_edition_v3(
+            CpiContext::new_with_signer(
+                self.token_metadata_program.to_account_info(),
+                CreateMasterEditionV3 {
+                    edition: self.certificate_collection_master_edition.to_account_info(),
+                    mint: self.certificate_collection_mint.to_account_info(),
+                    update_authority: self.certificate_authority.to_account_info(),
+                    mint_authority: self.certificate_authority.to_account_info(),
+                    payer: self.provider.to_account_info(),
+                    metadata: self.certificate_collection_metadata.to_account_info(),
+                    token_program: self.token_program.to_account_info(),
+                    system_program: self.system_program.to_account_info(),
+                    rent: self.rent.to_account_info(),
+                },
+                signer,
+            ),
+            Some(0), // max_supply: no prints
+        )
+    }
 }
@@ -1,7 +1,8 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
 use anchor_lang::prelude::*;
 use anchor_spl::{associated_token::AssociatedToken, token::Token};
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(name: String, description: String)]
 pub struct RegisterSubscriptionService<'info> {
@@ -12,21 +13,23 @@ pub struct RegisterSubscriptionService<'info> {
         mut,
         seeds = [PROVIDER_SEED.as_bytes(), provider.key().as_ref()],
         bump = provider_account.bump,
-        constraint = provider_account.wallet == provider.key() @ ErrorCode::UnauthorizedProvider
+        constraint = provider_account.wallet == provider.key() @ ErrorCode::UnauthorizedProvider,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub provider_account: Account<'info, Provider>,
 
     #[account(
         mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
     #[account(
         init,
         payer = provider,
-        space = SubscriptionService::INIT_SPACE,
+        space = 8 + SubscriptionService::INIT_SPACE,
         seeds = [
             SUBSCRIPTION_SERVICE_SEED.as_bytes(),
             provider.key().as_ref(),
@@ -36,12 +39,26 @@ pub struct RegisterSubscriptionService<'info> {
     )]
     pub subscription_service: Account<'info, SubscriptionService>,
 
+    /// Registry page the new service id falls on, created by its first service
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ServiceRegistryPage::INIT_SPACE,
+        seeds = [
+            SERVICE_REGISTRY_SEED.as_bytes(),
+            ServiceRegistryPage::page_of(global_state.total_services).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub service_registry_page: Box<Account<'info, ServiceRegistryPage>>,
+
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> RegisterSubscriptionService<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn register_subscription_service(
         &mut self,
         name: String,
@@ -49,26 +66,35 @@ impl<'info> RegisterSubscriptionService<'info> {
         fee_usd: u64,
         billing_frequency_days: u64,
         image_url: String,
+        certificate_metadata_uri: String,
         bumps: &RegisterSubscriptionServiceBumps,
     ) -> Result<()> {
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
-        require!(name.len() <= MAX_NAME_LENGTH, ErrorCode::NameTooLong);
-        require!(
-            description.len() <= MAX_DESCRIPTION_LENGTH,
-            ErrorCode::DescriptionTooLong
-        );
-        require!(image_url.len() <= MAX_URL_LENGTH, ErrorCode::UrlTooLong);
-        require!(fee_usd > 0, ErrorCode::InvalidFeeAmount);
+        self.global_state.require_not_paused()?;
+        validate_name(&name)?;
+        validate_description(&description)?;
+        validate_image_url(&image_url)?;
+        validate_certificate_metadata_uri(&certificate_metadata_uri)?;
+        validate_service_fee(fee_usd, self.global_state.service_fee_cap())?;
         require!(
-            billing_frequency_days >= MIN_SUBSCRIPTION_PERIOD_DAYS
-                && billing_frequency_days <= MAX_SUBSCRIPTION_PERIOD_DAYS,
+            (MIN_SUBSCRIPTION_PERIOD_DAYS..=MAX_SUBSCRIPTION_PERIOD_DAYS)
+                .contains(&billing_frequency_days),
             ErrorCode::InvalidBillingFrequency
         );
 
+        // The rate limit fields trail the text fields, so older accounts must be resized first
+        require_full_layout(
+            &self.provider_account.to_account_info(),
+            8 + Provider::INIT_SPACE,
+        )?;
+
+        let current_time = Clock::get()?.unix_timestamp;
         let provider_account = &mut self.provider_account;
         let global_state = &mut self.global_state;
+        provider_account
+            .record_service_registration(current_time, global_state.max_services_per_window)?;
 
         self.subscription_service.set_inner(SubscriptionService {
+            version: SUBSCRIPTION_SERVICE_VERSION,
             provider: self.provider.key(),
             service_id: global_state.total_services,
             name: name.clone(),
@@ -78,21 +104,49 @@ impl<'info> RegisterSubscriptionService<'info> {
             image_url,
             current_subscribers: 0,
             is_active: true,
-            created_at: Clock::get()?.unix_timestamp,
-            bumps: bumps.subscription_service,
+            created_at: current_time,
+            certificate_mode: CERTIFICATE_MODE_TOKEN,
+            royalty_bps: 0,
+            certificate_metadata_uri,
+            bump: bumps.subscription_service,
+            frozen: false,
+            deactivated_by_admin: false,
+            deactivation_reason: 0,
         });
 
+        let service_id = global_state.total_services;
+        self.service_registry_page.record(
+            bumps.service_registry_page,
+            service_id,
+            self.subscription_service.key(),
+            true,
+        )?;
+
         // Update global service count
-        global_state.total_services += 1;
+        global_state.total_services = global_state
+            .total_services
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
 
         msg!(
-            "Subscription service '{}' registered by provider {} with fee ${:.2} per {} days",
+            "Subscription service '{}' registered by provider {} with fee ${} per {} days",
             name,
             self.provider.key(),
-            fee_usd as f64 / 100.0,
+            cents_to_usd_string(fee_usd),
             billing_frequency_days
         );
 
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceRegistered {
+                provider: self.provider.key(),
+                service_id,
+                fee_usd,
+                billing_frequency_days,
+            },
+        )?;
+
         Ok(())
     }
 }
@@ -0,0 +1,82 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Flag a service as fraudulent for the authority to review. Each reporter has
+/// one Report per service, paid for by the reporter, so reports cannot be inflated
+/// by repeating them
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct ReportService<'info> {
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+
+    #[account(
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    #[account(
+        init_if_needed,
+        payer = reporter,
+        space = 8 + Report::INIT_SPACE,
+        seeds = [
+            REPORT_SEED.as_bytes(),
+            subscription_service.key().as_ref(),
+            reporter.key().as_ref(),
+        ],
+        bump
+    )]
+    pub report: Account<'info, Report>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ReportService<'info> {
+    /// Report the service with a REPORT_REASON_* code
+    pub fn report_service(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        reason_code: u8,
+        bumps: &ReportServiceBumps,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let report = &mut self.report;
+        if report.report_count == 0 {
+            report.reporter = self.reporter.key();
+            report.provider = provider;
+            report.service_id = service_id;
+            report.bump = bumps.report;
+        }
+        report.record(reason_code, current_time)?;
+
+        msg!(
+            "Service {} by provider {} reported by {} (reason {}, report #{})",
+            service_id,
+            provider,
+            self.reporter.key(),
+            reason_code,
+            report.report_count
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceReported {
+                reporter: self.reporter.key(),
+                provider,
+                service_id,
+                reason_code,
+                report_count: self.report.report_count,
+            },
+        )
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RequestWithdrawal<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+}
+
+impl<'info> RequestWithdrawal<'info> {
+    /// Reserve SOL for a withdrawal that execute_withdrawal pays out once the
+    /// user's withdrawal delay has passed. Reserved funds cannot be locked for
+    /// subscriptions or billed in the meantime
+    pub fn request_withdrawal(
+        &mut self,
+        amount: u64,
+        bumps: &RequestWithdrawalBumps,
+    ) -> Result<()> {
+        let user_account = &mut self.user_account;
+        user_account.request_withdrawal(amount, Clock::get()?.unix_timestamp)?;
+
+        msg!(
+            "Withdrawal of {} SOL requested, unlocks at {}",
+            lamports_to_sol_string(amount),
+            user_account.withdrawal_unlocks_at
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            WithdrawalRequested {
+                user: self.user.key(),
+                amount,
+                unlocks_at: user_account.withdrawal_unlocks_at,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ResetPriceBreaker<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> ResetPriceBreaker<'info> {
+    /// Acknowledge a tripped price circuit breaker once the feed is trusted
+    /// again. The next price read becomes the new reference, so reset only
+    /// after checking the feed is back to sane prices
+    pub fn reset_price_breaker(&mut self, bumps: &ResetPriceBreakerBumps) -> Result<()> {
+        let previous_price_cents = self.global_state.reset_price_breaker();
+
+        msg!(
+            "Price breaker reset, previous reference {} cents",
+            previous_price_cents
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            PriceBreakerReset {
+                previous_price_cents,
+                by: self.authority.key(),
+            },
+        )
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{constants::*, error::ErrorCode, state::*};
+use anchor_lang::prelude::*;
+
+/// Grow a provider account to the current maximum layout after a text limit
+/// was raised. The owner pays the extra rent; the new tail is zeroed.
+#[derive(Accounts)]
+pub struct ResizeProvider<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), provider.key().as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.wallet == provider.key() @ ErrorCode::UnauthorizedProvider,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion,
+        realloc = 8 + Provider::INIT_SPACE,
+        realloc::payer = provider,
+        realloc::zero = true
+    )]
+    pub provider_account: Account<'info, Provider>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ResizeProvider<'info> {
+    pub fn resize_provider(&mut self) -> Result<()> {
+        msg!(
+            "Provider account {} resized to {} bytes",
+            self.provider_account.key(),
+            8 + Provider::INIT_SPACE
+        );
+
+        Ok(())
+    }
+}
+
+/// Grow a subscription service account to the current maximum layout after a
+/// text limit was raised. The provider pays the extra rent; the new tail is zeroed.
+#[derive(Accounts)]
+#[instruction(service_id: u64)]
+pub struct ResizeSubscriptionService<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.key().as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.provider == provider.key() @ ErrorCode::UnauthorizedProvider,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion,
+        realloc = 8 + SubscriptionService::INIT_SPACE,
+        realloc::payer = provider,
+        realloc::zero = true
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ResizeSubscriptionService<'info> {
+    pub fn resize_service(&mut self, service_id: u64) -> Result<()> {
+        msg!(
+            "Subscription service {} by provider {} resized to {} bytes",
+            service_id,
+            self.provider.key(),
+            8 + SubscriptionService::INIT_SPACE
+        );
+
+        Ok(())
+    }
+}
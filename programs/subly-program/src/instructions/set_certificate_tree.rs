@@ -0,0 +1,81 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetCertificateTree<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// Bubblegum tree config for the new tree; its delegate must be the certificate authority
+    /// CHECK: Address is checked by seeds under the Bubblegum program; data is read manually
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = BUBBLEGUM_PROGRAM_ID
+    )]
+    pub tree_config: AccountInfo<'info>,
+
+    /// CHECK: Concurrent Merkle tree owned by the account compression program
+    #[account(owner = SPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+}
+
+impl<'info> SetCertificateTree<'info> {
+    /// Point compressed certificates at a Bubblegum tree.
+    /// The tree is created off-chain and delegated to the certificate authority PDA,
+    /// so only the program can mint certificates into it.
+    pub fn set_certificate_tree(
+        &mut self,
+        collection: Pubkey,
+        bumps: &SetCertificateTreeBumps,
+    ) -> Result<()> {
+        require!(
+            tree_delegate(&self.tree_config)? == self.certificate_authority.key(),
+            ErrorCode::UnauthorizedAuthority
+        );
+
+        let old_value = (
+            self.global_state.certificate_merkle_tree,
+            self.global_state.certificate_collection,
+        );
+        let new_value = (self.merkle_tree.key(), collection);
+
+        self.global_state.certificate_merkle_tree = self.merkle_tree.key();
+        self.global_state.certificate_collection = collection;
+
+        msg!(
+            "Compressed certificate tree set to {} (collection: {})",
+            self.merkle_tree.key(),
+            collection
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_CERTIFICATE_TREE,
+                &old_value,
+                &new_value,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
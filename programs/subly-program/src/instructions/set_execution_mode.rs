@@ -0,0 +1,49 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetExecutionMode<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetExecutionMode<'info> {
+    /// Choose who may trigger billing. Applies to the very next billing call
+    pub fn set_execution_mode(
+        &mut self,
+        execution_mode: u8,
+        bumps: &SetExecutionModeBumps,
+    ) -> Result<()> {
+        require!(
+            execution_mode <= EXECUTION_MODE_PERMISSIONLESS,
+            ErrorCode::InvalidExecutionMode
+        );
+
+        let old_value = self.global_state.execution_mode;
+        self.global_state.execution_mode = execution_mode;
+
+        msg!("Billing execution mode set to {}", execution_mode);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_EXECUTION_MODE,
+                &old_value,
+                &execution_mode,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetFeeRecipient<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// The new fee recipient. Not needed when fees return to the treasury
+    /// CHECK: Only its address and balance are read, see require_payable_fee_recipient
+    pub fee_recipient: Option<UncheckedAccount<'info>>,
+
+    /// The new fee recipient's USDC account
+    /// CHECK: Deserialized and checked by require_payable_fee_recipient
+    pub fee_recipient_usdc_account: Option<UncheckedAccount<'info>>,
+}
+
+impl<'info> SetFeeRecipient<'info> {
+    /// Pay protocol fees to `fee_recipient` instead of keeping them in the
+    /// treasury, which then only holds funds on their way to providers. The
+    /// wallet and its USDC account have to exist already, or every payment would
+    /// fail. The default pubkey keeps fees in the treasury again. Once a config
+    /// timelock is set the change has to be queued instead
+    pub fn set_fee_recipient(
+        &mut self,
+        fee_recipient: Pubkey,
+        bumps: &SetFeeRecipientBumps,
+    ) -> Result<()> {
+        self.global_state.require_no_config_timelock()?;
+        require_payable_fee_recipient(
+            &self.global_state,
+            fee_recipient,
+            self.fee_recipient.as_deref(),
+            self.fee_recipient_usdc_account.as_deref(),
+        )?;
+        let old_value = std::mem::replace(&mut self.global_state.fee_recipient, fee_recipient);
+
+        msg!(
+            "Fee recipient changed from {} to {}",
+            old_value,
+            fee_recipient
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_FEE_RECIPIENT,
+                &old_value,
+                &fee_recipient,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
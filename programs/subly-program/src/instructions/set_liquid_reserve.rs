@@ -0,0 +1,47 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetLiquidReserve<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetLiquidReserve<'info> {
+    /// Set how much available SOL auto-staking deposits leave liquid for billing
+    pub fn set_liquid_reserve(
+        &mut self,
+        liquid_reserve_lamports: u64,
+        bumps: &SetLiquidReserveBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.liquid_reserve_lamports;
+        self.global_state.liquid_reserve_lamports = liquid_reserve_lamports;
+
+        msg!(
+            "Liquid reserve set to {} SOL",
+            lamports_to_sol_string(liquid_reserve_lamports)
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_LIQUID_RESERVE,
+                &old_value,
+                &liquid_reserve_lamports,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetLockPeriods<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetLockPeriods<'info> {
+    /// Set how many billing periods of fees a new subscription locks. Open
+    /// subscriptions unlock what they recorded when subscribing
+    pub fn set_lock_periods(
+        &mut self,
+        lock_periods: u8,
+        bumps: &SetLockPeriodsBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.set_lock_periods(lock_periods)?;
+
+        msg!(
+            "Subscription lock changed from {} to {} periods",
+            old_value,
+            lock_periods
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_LOCK_PERIODS,
+                &old_value,
+                &lock_periods,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
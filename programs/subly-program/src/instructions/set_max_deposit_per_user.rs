@@ -0,0 +1,47 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMaxDepositPerUser<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetMaxDepositPerUser<'info> {
+    /// Set the cap on deposited plus staked SOL per user, 0 for unlimited
+    pub fn set_max_deposit_per_user(
+        &mut self,
+        max_deposit_per_user_lamports: u64,
+        bumps: &SetMaxDepositPerUserBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.max_deposit_per_user_lamports;
+        self.global_state.max_deposit_per_user_lamports = max_deposit_per_user_lamports;
+
+        msg!(
+            "Per-user deposit cap set to {} SOL",
+            lamports_to_sol_string(max_deposit_per_user_lamports)
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_MAX_DEPOSIT_PER_USER,
+                &old_value,
+                &max_deposit_per_user_lamports,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMaxPriceAge<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetMaxPriceAge<'info> {
+    /// Set how old a Pyth price may be for subscribing, billing, unsubscribing
+    /// and the pricing views. 0 restores DEFAULT_MAX_PRICE_AGE_SECS. A longer age
+    /// lets billing take staler prices, so once a config timelock is set the
+    /// change has to be queued instead
+    pub fn set_max_price_age(
+        &mut self,
+        max_price_age_secs: u32,
+        bumps: &SetMaxPriceAgeBumps,
+    ) -> Result<()> {
+        self.global_state.require_no_config_timelock()?;
+        let old_value = self.global_state.set_max_price_age(max_price_age_secs)?;
+
+        msg!(
+            "Maximum price age set to {}s",
+            self.global_state.max_price_age()
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_MAX_PRICE_AGE,
+                &old_value,
+                &max_price_age_secs,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
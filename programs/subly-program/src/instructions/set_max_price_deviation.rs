@@ -0,0 +1,51 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMaxPriceDeviation<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetMaxPriceDeviation<'info> {
+    /// Arm the price circuit breaker: a SOL/USD price more than `max_bps` away
+    /// from the last observed one stops every pricing instruction, billing
+    /// included, until reset_price_breaker. 0 turns it off. Once a config
+    /// timelock is set only arming a breaker that is off takes effect here;
+    /// any other change has to be queued
+    pub fn set_max_price_deviation(
+        &mut self,
+        max_bps: u16,
+        bumps: &SetMaxPriceDeviationBumps,
+    ) -> Result<()> {
+        self.global_state
+            .require_direct_price_deviation_change(max_bps)?;
+        let old_value = std::mem::replace(&mut self.global_state.max_price_deviation_bps, max_bps);
+
+        msg!(
+            "Max price deviation changed from {} to {} bps",
+            old_value,
+            max_bps
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_MAX_PRICE_DEVIATION,
+                &old_value,
+                &max_bps,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
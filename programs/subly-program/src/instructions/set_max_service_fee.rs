@@ -0,0 +1,53 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMaxServiceFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetMaxServiceFee<'info> {
+    /// Set the highest fee a service may be registered or updated with. Existing
+    /// services above it keep billing; only new fees are checked
+    pub fn set_max_service_fee(
+        &mut self,
+        max_service_fee_usd_cents: u64,
+        bumps: &SetMaxServiceFeeBumps,
+    ) -> Result<()> {
+        require!(
+            (1..=MAX_SERVICE_FEE_USD_CENTS).contains(&max_service_fee_usd_cents),
+            ErrorCode::InvalidFeeAmount
+        );
+
+        let old_value = self.global_state.max_service_fee_usd_cents;
+        self.global_state.max_service_fee_usd_cents = max_service_fee_usd_cents;
+
+        msg!(
+            "Maximum service fee set to ${}",
+            cents_to_usd_string(max_service_fee_usd_cents)
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_MAX_SERVICE_FEE,
+                &old_value,
+                &max_service_fee_usd_cents,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
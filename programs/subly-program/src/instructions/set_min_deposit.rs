@@ -0,0 +1,46 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetMinDeposit<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetMinDeposit<'info> {
+    /// Set the smallest first deposit. Existing users may top up any amount
+    pub fn set_min_deposit(
+        &mut self,
+        min_deposit_lamports: u64,
+        bumps: &SetMinDepositBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.set_min_deposit(min_deposit_lamports)?;
+
+        msg!(
+            "Minimum deposit set to {} SOL",
+            lamports_to_sol_string(min_deposit_lamports)
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_MIN_DEPOSIT,
+                &old_value,
+                &min_deposit_lamports,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetOperator<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetOperator<'info> {
+    /// Name the key that runs billing alongside the authority, so the keeper
+    /// machine does not need the admin key. The default pubkey removes it
+    pub fn set_operator(&mut self, operator: Pubkey, bumps: &SetOperatorBumps) -> Result<()> {
+        let old_value = std::mem::replace(&mut self.global_state.operator, operator);
+
+        msg!("Operator changed from {} to {}", old_value, operator);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_OPERATOR,
+                &old_value,
+                &operator,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetPaused<'info> {
+    /// Stop everything GlobalState::require_not_paused closes
+    pub fn pause_protocol(&mut self, bumps: &SetPausedBumps) -> Result<()> {
+        self.set_paused(true, bumps)
+    }
+
+    /// Lift a pause
+    pub fn unpause_protocol(&mut self, bumps: &SetPausedBumps) -> Result<()> {
+        self.set_paused(false, bumps)
+    }
+
+    /// Halt or reopen single areas (PAUSE_* bits) without a full pause, e.g. stop
+    /// subscriptions and billing during an oracle incident while deposits and
+    /// withdrawals keep working. A full pause still overrides these flags
+    pub fn set_pause_flags(&mut self, pause_flags: u8, bumps: &SetPausedBumps) -> Result<()> {
+        let old_value = self.global_state.set_pause_flags(pause_flags)?;
+
+        msg!(
+            "Pause flags changed from {:#06b} to {:#06b}",
+            old_value,
+            pause_flags
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PAUSE_FLAGS,
+                &old_value,
+                &pause_flags,
+                self.authority.key(),
+            )?,
+        )
+    }
+
+    /// Turn emergency mode on or off. While it is on, emergency_withdraw hands
+    /// users their whole SOL vault regardless of subscription locks, so it is
+    /// meant for winding the protocol down and requires a pause
+    pub fn set_emergency_mode(&mut self, enabled: bool, bumps: &SetPausedBumps) -> Result<()> {
+        let old_value = self.global_state.set_emergency_mode(enabled)?;
+
+        msg!("Emergency mode {}", if enabled { "ON" } else { "OFF" });
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_EMERGENCY_MODE,
+                &old_value,
+                &enabled,
+                self.authority.key(),
+            )?,
+        )
+    }
+
+    /// Pause or resume the protocol. See GlobalState::require_not_paused for
+    /// what a pause closes; withdrawals and unsubscribes stay open
+    pub fn set_paused(&mut self, paused: bool, bumps: &SetPausedBumps) -> Result<()> {
+        // Balances emptied by emergency_withdraw must not be reopened for business
+        require!(
+            paused || !self.global_state.emergency_mode,
+            ErrorCode::EmergencyModeRequiresPause
+        );
+
+        let current_time = Clock::get()?.unix_timestamp;
+        let old_value = self.global_state.apply_pause(paused, current_time);
+
+        msg!(
+            "Protocol {} at {}",
+            if paused { "PAUSED" } else { "RESUMED" },
+            current_time
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PAUSED,
+                &old_value,
+                &paused,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{error::ErrorCode, state::*};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetPaymentRecordDisputed<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(mut)]
+    pub payment_record: Account<'info, PaymentRecord>,
+}
+
+impl<'info> SetPaymentRecordDisputed<'info> {
+    /// Flag a record as disputed or refund-pending so it cannot be pruned
+    pub fn set_payment_record_disputed(&mut self, disputed: bool) -> Result<()> {
+        self.payment_record.disputed = disputed;
+
+        msg!(
+            "Payment record {} dispute flag set to {}",
+            self.payment_record.key(),
+            disputed
+        );
+
+        Ok(())
+    }
+}
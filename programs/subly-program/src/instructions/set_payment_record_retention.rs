@@ -0,0 +1,49 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPaymentRecordRetention<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetPaymentRecordRetention<'info> {
+    /// Set how long payment records must be kept before they can be closed
+    pub fn set_payment_record_retention(
+        &mut self,
+        retention_days: u64,
+        bumps: &SetPaymentRecordRetentionBumps,
+    ) -> Result<()> {
+        require!(
+            retention_days >= MIN_PAYMENT_RECORD_RETENTION_DAYS,
+            ErrorCode::InvalidRetentionPeriod
+        );
+
+        let old_value = self.global_state.payment_record_retention_days;
+        self.global_state.payment_record_retention_days = retention_days;
+
+        msg!("Payment record retention set to {} days", retention_days);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PAYMENT_RECORD_RETENTION,
+                &old_value,
+                &retention_days,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
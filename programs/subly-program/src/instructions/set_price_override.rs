@@ -0,0 +1,60 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetPriceOverride<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetPriceOverride<'info> {
+    /// Oracle outage recovery: serve `price_cents` as the SOL/USD price whenever
+    /// the Pyth feed has no fresh price, until `valid_until`. A fresh price always
+    /// wins. A price of 0 clears the override at any time
+    pub fn set_price_override(
+        &mut self,
+        price_cents: u64,
+        valid_until: i64,
+        bumps: &SetPriceOverrideBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.set_price_override(
+            price_cents,
+            valid_until,
+            Clock::get()?.unix_timestamp,
+        )?;
+        let new_value = (
+            self.global_state.price_override_cents,
+            self.global_state.price_override_valid_until,
+        );
+
+        if price_cents == 0 {
+            msg!("Price override cleared");
+        } else {
+            msg!(
+                "Price override set to ${} until {}",
+                cents_to_usd_string(price_cents),
+                valid_until
+            );
+        }
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PRICE_OVERRIDE,
+                &old_value,
+                &new_value,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
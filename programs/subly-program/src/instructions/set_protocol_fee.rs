@@ -0,0 +1,49 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetProtocolFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetProtocolFee<'info> {
+    /// Change the protocol's cut of each payment, at most MAX_PROTOCOL_FEE_BPS.
+    /// Payments already due keep the old rate, see GlobalState::protocol_fee_bps_for,
+    /// so the next change has to wait until this one settles, unless it goes back
+    /// to the previous rate. Once a config timelock is set the change has to be
+    /// queued instead
+    pub fn set_protocol_fee(&mut self, fee_bps: u16, bumps: &SetProtocolFeeBumps) -> Result<()> {
+        self.global_state.require_no_config_timelock()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        let old_value = self.global_state.set_protocol_fee(fee_bps, current_time)?;
+
+        msg!(
+            "Protocol fee set to {} bps, effective for payments due from {}",
+            fee_bps,
+            current_time
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PROTOCOL_FEE,
+                &old_value,
+                &fee_bps,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
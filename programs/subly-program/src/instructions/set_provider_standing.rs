@@ -0,0 +1,123 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Authority verification of a provider, required to take new subscribers while
+/// GlobalState::require_verified_providers is set. Shared by set_provider_verified,
+/// verify_provider and revoke_provider_verification
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey)]
+pub struct SetProviderVerified<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+}
+
+/// Authority ban of a provider: none of their services accept new subscribers
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey)]
+pub struct SetProviderBanned<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+}
+
+impl<'info> SetProviderVerified<'info> {
+    pub fn set_provider_verified(
+        &mut self,
+        provider: Pubkey,
+        is_verified: bool,
+        bumps: &SetProviderVerifiedBumps,
+    ) -> Result<()> {
+        require_full_layout(
+            &self.provider_account.to_account_info(),
+            8 + Provider::INIT_SPACE,
+        )?;
+
+        self.provider_account
+            .set_verified(is_verified, Clock::get()?.unix_timestamp);
+
+        msg!(
+            "Provider {} {}",
+            provider,
+            if is_verified {
+                "verified"
+            } else {
+                "unverified"
+            }
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ProviderVerificationChanged {
+                wallet: provider,
+                is_verified,
+                by: self.authority.key(),
+            },
+        )
+    }
+}
+
+impl<'info> SetProviderBanned<'info> {
+    /// Ban or reinstate a provider. Existing subscriptions are left to the
+    /// service freeze, which also holds the provider's earnings
+    pub fn set_provider_banned(
+        &mut self,
+        provider: Pubkey,
+        is_banned: bool,
+        bumps: &SetProviderBannedBumps,
+    ) -> Result<()> {
+        require_full_layout(
+            &self.provider_account.to_account_info(),
+            8 + Provider::INIT_SPACE,
+        )?;
+
+        self.provider_account.is_banned = is_banned;
+
+        msg!(
+            "Provider {} {}",
+            provider,
+            if is_banned { "banned" } else { "reinstated" }
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ProviderBanChanged {
+                wallet: provider,
+                is_banned,
+                by: self.authority.key(),
+            },
+        )
+    }
+}
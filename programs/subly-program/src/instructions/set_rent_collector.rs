@@ -0,0 +1,46 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetRentCollector<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetRentCollector<'info> {
+    /// Send the rent of accounts closed by the authority, rather than by their
+    /// payer, to `rent_collector`. The default pubkey sends it to the authority
+    pub fn set_rent_collector(
+        &mut self,
+        rent_collector: Pubkey,
+        bumps: &SetRentCollectorBumps,
+    ) -> Result<()> {
+        let old_value = std::mem::replace(&mut self.global_state.rent_collector, rent_collector);
+
+        msg!(
+            "Rent collector changed from {} to {}",
+            old_value,
+            rent_collector
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_RENT_COLLECTOR,
+                &old_value,
+                &rent_collector,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
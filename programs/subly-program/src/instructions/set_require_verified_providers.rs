@@ -0,0 +1,48 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetRequireVerifiedProviders<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetRequireVerifiedProviders<'info> {
+    /// Toggle curated mode. Existing subscriptions keep billing either way; only
+    /// new subscriptions to unverified providers' services are refused
+    pub fn set_require_verified_providers(
+        &mut self,
+        required: bool,
+        bumps: &SetRequireVerifiedProvidersBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.require_verified_providers;
+        self.global_state.require_verified_providers = required;
+
+        msg!(
+            "Verified providers {} for new subscriptions",
+            if required { "REQUIRED" } else { "NOT REQUIRED" }
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_REQUIRE_VERIFIED_PROVIDERS,
+                &old_value,
+                &required,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
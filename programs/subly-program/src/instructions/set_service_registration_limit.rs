@@ -0,0 +1,49 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetServiceRegistrationLimit<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetServiceRegistrationLimit<'info> {
+    /// Set how many services an unverified provider may register per
+    /// registration window, 0 for unlimited
+    pub fn set_service_registration_limit(
+        &mut self,
+        max_services_per_window: u32,
+        bumps: &SetServiceRegistrationLimitBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.max_services_per_window;
+        self.global_state.max_services_per_window = max_services_per_window;
+
+        msg!(
+            "Service registration limit set to {} per {} seconds",
+            max_services_per_window,
+            SERVICE_REGISTRATION_WINDOW_SECONDS
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_MAX_SERVICES_PER_WINDOW,
+                &old_value,
+                &max_services_per_window,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(service_id: u64)]
+pub struct SetServiceStatus<'info> {
+    #[account(mut)]
+    pub provider: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.key().as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.provider == provider.key() @ ErrorCode::UnauthorizedProvider,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    /// Registry page of the service, created here for services registered
+    /// before the registry existed
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ServiceRegistryPage::INIT_SPACE,
+        seeds = [
+            SERVICE_REGISTRY_SEED.as_bytes(),
+            ServiceRegistryPage::page_of(service_id).to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub service_registry_page: Box<Account<'info, ServiceRegistryPage>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetServiceStatus<'info> {
+    /// Activate or deactivate a service. Inactive services accept no new
+    /// subscribers and are not billed, and are tombstoned in the service registry.
+    /// Services deactivated by the authority stay inactive
+    pub fn set_service_status(
+        &mut self,
+        service_id: u64,
+        is_active: bool,
+        bumps: &SetServiceStatusBumps,
+    ) -> Result<()> {
+        self.subscription_service
+            .require_provider_may_set_status(is_active)?;
+        self.subscription_service.is_active = is_active;
+        self.service_registry_page.record(
+            bumps.service_registry_page,
+            service_id,
+            self.subscription_service.key(),
+            is_active,
+        )?;
+
+        msg!(
+            "Subscription service {} by provider {} is now {}",
+            service_id,
+            self.provider.key(),
+            if is_active { "ACTIVE" } else { "INACTIVE" }
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceStatusChanged {
+                provider: self.provider.key(),
+                service_id,
+                is_active,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetSolPriceBounds<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetSolPriceBounds<'info> {
+    /// Set the SOL/USD range, in cents, that subscribing, billing, unsubscribing
+    /// and the pricing views accept, so a price move past the old bounds does
+    /// not halt the protocol. The bounds decide which prices fees convert at, so
+    /// once a config timelock is set the change has to be queued instead
+    pub fn set_sol_price_bounds(
+        &mut self,
+        min_sol_price_cents: u64,
+        max_sol_price_cents: u64,
+        bumps: &SetSolPriceBoundsBumps,
+    ) -> Result<()> {
+        self.global_state.require_no_config_timelock()?;
+        let old_value = self
+            .global_state
+            .set_sol_price_bounds(min_sol_price_cents, max_sol_price_cents)?;
+
+        msg!(
+            "SOL price bounds set to ${} - ${}",
+            cents_to_usd_string(min_sol_price_cents),
+            cents_to_usd_string(max_sol_price_cents)
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_SOL_PRICE_BOUNDS,
+                &old_value,
+                &(min_sol_price_cents, max_sol_price_cents),
+                self.authority.key(),
+            )?,
+        )
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetSoulboundCertificates<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetSoulboundCertificates<'info> {
+    /// Select the token program for newly minted certificates.
+    /// Existing certificates keep working under the program they were minted with.
+    pub fn set_soulbound_certificates(
+        &mut self,
+        enabled: bool,
+        bumps: &SetSoulboundCertificatesBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.soulbound_certificates;
+        self.global_state.soulbound_certificates = enabled;
+
+        msg!(
+            "Soulbound (Token-2022) certificates {}",
+            if enabled { "ENABLED" } else { "DISABLED" }
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_SOULBOUND_CERTIFICATES,
+                &old_value,
+                &enabled,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
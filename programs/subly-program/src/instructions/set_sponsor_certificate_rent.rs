@@ -0,0 +1,48 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetSponsorCertificateRent<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetSponsorCertificateRent<'info> {
+    /// Let the rent sponsor PDA pay for new certificate accounts.
+    /// Subscribers pay as before whenever the sponsor cannot cover a certificate.
+    pub fn set_sponsor_certificate_rent(
+        &mut self,
+        enabled: bool,
+        bumps: &SetSponsorCertificateRentBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.sponsor_certificate_rent;
+        self.global_state.sponsor_certificate_rent = enabled;
+
+        msg!(
+            "Certificate rent sponsorship {}",
+            if enabled { "ENABLED" } else { "DISABLED" }
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_SPONSOR_CERTIFICATE_RENT,
+                &old_value,
+                &enabled,
+                self.authority.key(),
+            )?,
+        )?;
+
+        Ok(())
+    }
+}
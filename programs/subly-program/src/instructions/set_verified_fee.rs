@@ -0,0 +1,48 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetVerifiedFee<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> SetVerifiedFee<'info> {
+    /// Change the protocol's cut of payments to verified providers, at most
+    /// MAX_PROTOCOL_FEE_BPS, so finishing verification lowers a provider's take
+    /// rate. None charges them protocol_fee_bps like everyone else. Once a config
+    /// timelock is set the change has to be queued instead
+    pub fn set_verified_fee(
+        &mut self,
+        fee_bps: Option<u16>,
+        bumps: &SetVerifiedFeeBumps,
+    ) -> Result<()> {
+        self.global_state.require_no_config_timelock()?;
+        let old_value = self.global_state.set_verified_fee(fee_bps)?;
+
+        match fee_bps {
+            Some(fee_bps) => msg!("Verified provider fee set to {} bps", fee_bps),
+            None => msg!("Verified provider fee cleared, verified providers pay the protocol fee"),
+        }
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_VERIFIED_FEE,
+                &old_value,
+                &fee_bps,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetWithdrawalAllowlist<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + WithdrawalAllowlist::INIT_SPACE,
+        seeds = [WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_allowlist: Account<'info, WithdrawalAllowlist>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetWithdrawalAllowlist<'info> {
+    /// Replace the withdrawal allowlist and turn enforcement on or off. Enabling an
+    /// allowlist applies at once; any change to an enabled one (including disabling
+    /// it) blocks withdrawals for WITHDRAWAL_ALLOWLIST_COOLDOWN_SECONDS
+    pub fn set_withdrawal_allowlist(
+        &mut self,
+        enabled: bool,
+        destinations: Vec<Pubkey>,
+        bumps: &SetWithdrawalAllowlistBumps,
+    ) -> Result<()> {
+        require!(
+            destinations.len() <= MAX_WITHDRAWAL_DESTINATIONS,
+            ErrorCode::TooManyWithdrawalDestinations
+        );
+
+        let allowlist = &mut self.withdrawal_allowlist;
+        if allowlist.version == 0 {
+            allowlist.version = WithdrawalAllowlist::CURRENT_VERSION;
+            allowlist.user = self.user.key();
+            allowlist.bump = bumps.withdrawal_allowlist;
+        }
+        require!(
+            allowlist.is_current_version(),
+            ErrorCode::UnsupportedAccountVersion
+        );
+
+        let current_time = Clock::get()?.unix_timestamp;
+        if allowlist.enabled {
+            allowlist.locked_until = current_time
+                .checked_add(WITHDRAWAL_ALLOWLIST_COOLDOWN_SECONDS)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+        allowlist.enabled = enabled;
+        allowlist.destinations = destinations;
+
+        msg!(
+            "Withdrawal allowlist {} with {} destinations",
+            if enabled { "enabled" } else { "disabled" },
+            allowlist.destinations.len()
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            WithdrawalAllowlistUpdated {
+                user: self.user.key(),
+                enabled,
+                destinations: allowlist.destinations.clone(),
+                locked_until: allowlist.locked_until,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetWithdrawalDelay<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+}
+
+impl<'info> SetWithdrawalDelay<'info> {
+    /// Opt in to (or change) the withdrawal timelock. Raising the delay applies at
+    /// once; lowering it waits out the current delay so a stolen key cannot skip it
+    pub fn set_withdrawal_delay(
+        &mut self,
+        delay_secs: i64,
+        bumps: &SetWithdrawalDelayBumps,
+    ) -> Result<()> {
+        let user_account = &mut self.user_account;
+        user_account.set_withdrawal_delay(delay_secs, Clock::get()?.unix_timestamp)?;
+
+        msg!(
+            "Withdrawal delay {}s, pending {}s from {}",
+            user_account.withdrawal_delay_secs,
+            user_account.pending_withdrawal_delay_secs,
+            user_account.withdrawal_delay_effective_at
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            WithdrawalDelayUpdated {
+                user: self.user.key(),
+                withdrawal_delay_secs: user_account.withdrawal_delay_secs,
+                pending_delay_secs: user_account.pending_withdrawal_delay_secs,
+                effective_at: user_account.withdrawal_delay_effective_at,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+/// Deliver a provider's pending earnings from the USDC treasury to the provider's
+/// USDC associated token account, creating it if it was closed or never opened.
+/// Anyone may pay for the call: the funds can only reach the provider's own account
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SettleProviderEarnings<'info> {
+    /// Pays for the provider's token account if it has to be created
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// CHECK: Provider wallet, only used as the token account authority and
+    /// checked against the ledger
+    pub provider: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_EARNINGS_SEED.as_bytes(), provider.key().as_ref()],
+        bump,
+        constraint = provider_earnings.provider == provider.key() @ ErrorCode::NoPendingEarnings
+    )]
+    pub provider_earnings: Box<Account<'info, ProviderEarnings>>,
+
+    /// The provider's USDC associated token account, created if missing
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = provider,
+        associated_token::token_program = token_program
+    )]
+    pub provider_usdc_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Protocol treasury, owner of the USDC treasury account
+    #[account(
+        seeds = [b"treasury"],
+        bump = global_state.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// Protocol's USDC treasury token account, which holds pending earnings
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub protocol_usdc_treasury: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// USDC mint (classic SPL or Token-2022)
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
+    )]
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SettleProviderEarnings<'info> {
+    /// Send the whole pending balance in one transfer
+    pub fn settle_provider_earnings(&mut self, bumps: &SettleProviderEarningsBumps) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let amount = self.provider_earnings.take_pending(current_time)?;
+
+        self.global_state.total_pending_payouts_usdc = self
+            .global_state
+            .total_pending_payouts_usdc
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        let transfer_fee = transfer_fee_amount(&self.usdc_mint.to_account_info(), amount)?;
+        let received_amount = amount
+            .checked_sub(transfer_fee)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.protocol_usdc_treasury.to_account_info(),
+                    mint: self.usdc_mint.to_account_info(),
+                    to: self.provider_usdc_account.to_account_info(),
+                    authority: self.treasury.to_account_info(),
+                },
+                &[&[b"treasury", &[self.global_state.treasury_bump]]],
+            ),
+            amount,
+            self.usdc_mint.decimals,
+        )?;
+
+        msg!(
+            "Settled {} USDC base units to provider {} ({} received after transfer fee)",
+            amount,
+            self.provider.key(),
+            received_amount
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ProviderEarningsSettled {
+                provider: self.provider.key(),
+                amount,
+                received_amount,
+                settled_by: self.payer.key(),
+            },
+        )
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+/// Fund a dedicated escrow for one subscription. Billing draws from the escrow
+/// before the subscriber's own balances, and whatever is left goes back to the
+/// sponsor when the subscription is cancelled. The subscriber cannot withdraw it.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(user: Pubkey, provider: Pubkey, service_id: u64)]
+pub struct SponsorSubscription<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user.as_ref(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = user_subscription.bump,
+        constraint = user_subscription.user == user @ ErrorCode::UnauthorizedUser,
+        constraint = user_subscription.provider == provider @ ErrorCode::InvalidProvider,
+        constraint = user_subscription.service_id == service_id @ ErrorCode::InvalidServiceId,
+        constraint = user_subscription.is_active @ ErrorCode::SubscriptionNotActive,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_subscription: Account<'info, UserSubscription>,
+
+    #[account(
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            &service_id.to_le_bytes(),
+        ],
+        bump = subscription_service.bump,
+        constraint = !subscription_service.frozen @ ErrorCode::ServiceFrozen,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    /// Holds the sponsored lamports for this subscription
+    #[account(
+        mut,
+        seeds = [SPONSOR_ESCROW_SEED.as_bytes(), user_subscription.key().as_ref()],
+        bump
+    )]
+    pub sponsor_escrow: SystemAccount<'info>,
+
+    /// Pyth SOL/USD price feed account
+    /// CHECK: This account is validated in the instruction method to match the price feed in GlobalState
+    pub sol_usd_price_feed: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SponsorSubscription<'info> {
+    /// Prepay `periods` billing periods of the subscription at the current SOL price
+    pub fn sponsor_subscription(
+        &mut self,
+        periods: u64,
+        bumps: &SponsorSubscriptionBumps,
+    ) -> Result<()> {
+        self.global_state.require_not_paused_for(PAUSE_SUBSCRIPTIONS)?;
+        require!(
+            self.sol_usd_price_feed.key() == self.global_state.sol_usd_price_feed,
+            ErrorCode::InvalidPriceFeed
+        );
+        require!(
+            (1..=MAX_SPONSORED_PERIODS).contains(&periods),
+            ErrorCode::InvalidSponsoredPeriods
+        );
+
+        // One sponsor per escrow, so a refund always has a single rightful recipient
+        let subscription = &mut self.user_subscription;
+        require!(
+            subscription.sponsor_escrow_lamports == 0
+                || subscription.sponsor == self.sponsor.key(),
+            ErrorCode::SponsorEscrowInUse
+        );
+
+        let sol_usd_price = get_sol_usd_price_cents(&self.sol_usd_price_feed, &self.global_state)?;
+        let fee_usd = self
+            .subscription_service
+            .fee_usd
+            .checked_mul(periods)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let amount = convert_usd_to_sol_lamports(fee_usd, sol_usd_price)?;
+
+        // The escrow keeps its own rent floor, paid by the first sponsorship and
+        // never counted as billable
+        let rent_top_up = vault_rent_floor()?.saturating_sub(self.sponsor_escrow.lamports());
+        transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.sponsor.to_account_info(),
+                    to: self.sponsor_escrow.to_account_info(),
+                },
+            ),
+            amount
+                .checked_add(rent_top_up)
+                .ok_or(ErrorCode::ArithmeticOverflow)?,
+        )?;
+
+        subscription.sponsor = self.sponsor.key();
+        subscription.sponsor_escrow_lamports = subscription
+            .sponsor_escrow_lamports
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        msg!(
+            "Sponsor {} prepaid {} periods ({} SOL) for user {}",
+            self.sponsor.key(),
+            periods,
+            lamports_to_sol_string(amount),
+            subscription.user
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionSponsored {
+                sponsor: self.sponsor.key(),
+                user: subscription.user,
+                provider: subscription.provider,
+                service_id: subscription.service_id,
+                periods,
+                amount,
+                escrow_balance: subscription.sponsor_escrow_lamports,
+            },
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Return a cancelled subscription's escrow, rent included, to its sponsor and
+/// clear the sponsorship. Returns the lamports refunded, zero when unsponsored
+pub(crate) fn refund_sponsor_escrow<'info>(
+    user_subscription: &mut UserSubscription,
+    sponsor_escrow: &AccountInfo<'info>,
+    sponsor: Option<&AccountInfo<'info>>,
+    system_program: &AccountInfo<'info>,
+    subscription_key: Pubkey,
+    escrow_bump: u8,
+) -> Result<u64> {
+    if user_subscription.sponsor == Pubkey::default() {
+        return Ok(0);
+    }
+    let sponsor = sponsor.ok_or(ErrorCode::SponsorAccountRequired)?;
+    require_keys_eq!(
+        sponsor.key(),
+        user_subscription.sponsor,
+        ErrorCode::InvalidSponsor
+    );
+
+    let amount = sponsor_escrow.lamports();
+    if amount > 0 {
+        transfer(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                Transfer {
+                    from: sponsor_escrow.clone(),
+                    to: sponsor.clone(),
+                },
+                &[&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    subscription_key.as_ref(),
+                    &[escrow_bump],
+                ]],
+            ),
+            amount,
+        )?;
+    }
+
+    user_subscription.sponsor = Pubkey::default();
+    user_subscription.sponsor_escrow_lamports = 0;
+
+    msg!(
+        "Refunded {} SOL of sponsor escrow to {}",
+        lamports_to_sol_string(amount),
+        sponsor.key()
+    );
+    Ok(amount)
+}
@@ -1,4 +1,4 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, state::*, utils::*};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -16,14 +16,15 @@ pub struct StakeSol<'info> {
         mut,
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
     #[account(
         init_if_needed,
         payer = user,
-        space = StakeAccount::INIT_SPACE,
+        space = 8 + StakeAccount::INIT_SPACE,
         seeds = [
             STAKE_ACCOUNT_SEED.as_bytes(),
             user.key().as_ref(),
@@ -35,14 +36,16 @@ pub struct StakeSol<'info> {
     #[account(
         mut,
         seeds = [b"vault", user.key().as_ref()],
-        bump,
+        bump = user_account.vault_bump,
     )]
     pub sol_vault: SystemAccount<'info>,
 
-    /// Global state for reading Jito configuration
+    /// Global state for reading Jito configuration and updating protocol counters
     #[account(
+        mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
@@ -106,96 +109,150 @@ pub struct StakeSol<'info> {
     pub stake_program: UncheckedAccount<'info>,
 }
 
+/// Stake pool accounts needed to move vault SOL into the Jito pool
+pub(crate) struct StakePoolDepositAccounts<'info> {
+    pub stake_pool_program: AccountInfo<'info>,
+    pub jito_stake_pool: AccountInfo<'info>,
+    pub stake_pool_withdraw_authority: AccountInfo<'info>,
+    pub reserve_stake: AccountInfo<'info>,
+    pub protocol_jito_vault: AccountInfo<'info>,
+    pub manager_fee_account: AccountInfo<'info>,
+    pub referrer_pool_tokens: AccountInfo<'info>,
+    pub jito_sol_mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+}
+
+/// Staking shared by stake_sol and auto-staking deposits: checks the user's
+/// available balance, deposits `amount` from the vault into the Jito pool and
+/// records it on the stake account, user account and protocol counters
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn stake_vault_sol<'info>(
+    user: Pubkey,
+    user_account: &mut User,
+    stake_account: &mut StakeAccount,
+    global_state: &mut GlobalState,
+    sol_vault: &AccountInfo<'info>,
+    pool: &StakePoolDepositAccounts<'info>,
+    amount: u64,
+    stake_account_bump: u8,
+) -> Result<()> {
+    require!(amount >= MIN_STAKE_AMOUNT, ErrorCode::MinimumStakeNotMet);
+    require!(
+        stake_account.user == Pubkey::default() || stake_account.is_current_version(),
+        ErrorCode::UnsupportedAccountVersion
+    );
+
+    // Check if user has sufficient available balance
+    let available_balance = user_account.available_sol()?;
+
+    require!(
+        available_balance >= amount,
+        ErrorCode::InsufficientAvailableBalance
+    );
+    require!(
+        spendable_vault_balance(sol_vault)? >= amount,
+        ErrorCode::InsufficientBalance
+    );
+
+    // Transfer SOL from user vault to Jito stake pool via CPI
+    let vault_bump = user_account.vault_bump;
+    let signer_seeds: &[&[&[u8]]] = &[&[b"vault", user.as_ref(), &[vault_bump]]];
+
+    // ===== REAL JITO INTEGRATION =====
+    // Using actual Jito SPL Stake Pool for liquid staking and yield generation
+
+    // Create the deposit_sol instruction for Jito SPL Stake Pool
+    let deposit_instruction = spl_instruction::deposit_sol(
+        pool.stake_pool_program.key,            // stake pool program
+        pool.jito_stake_pool.key,               // stake pool
+        pool.stake_pool_withdraw_authority.key, // withdraw authority
+        pool.reserve_stake.key,                 // reserve stake
+        sol_vault.key,                          // from (SOL source)
+        pool.protocol_jito_vault.key,           // to (pool token destination)
+        pool.manager_fee_account.key,           // manager fee account
+        pool.referrer_pool_tokens.key,          // referrer pool tokens
+        pool.jito_sol_mint.key,                 // pool mint
+        pool.token_program.key,                 // token program
+        amount,                                 // SOL amount
+    );
+
+    // Execute the Jito stake deposit via CPI
+    anchor_lang::solana_program::program::invoke_signed(
+        &deposit_instruction,
+        &[
+            pool.stake_pool_program.clone(),
+            pool.jito_stake_pool.clone(),
+            pool.stake_pool_withdraw_authority.clone(),
+            pool.reserve_stake.clone(),
+            sol_vault.clone(),
+            pool.protocol_jito_vault.clone(),
+            pool.manager_fee_account.clone(),
+            pool.referrer_pool_tokens.clone(),
+            pool.jito_sol_mint.clone(),
+            pool.token_program.clone(),
+            pool.system_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // Get the JitoSOL token balance after staking
+    // In real implementation, we'd calculate the exact amount based on the pool's exchange rate
+    // For now, we'll use a conservative estimate (Jito typically gives slightly less than 1:1)
+    let estimated_jito_sol = estimate_staked_jito_sol(amount); // ~2% difference
+
+    // Initialize the stake account on first use, then add to it
+    if stake_account.user == Pubkey::default() {
+        stake_account.version = STAKE_ACCOUNT_VERSION;
+        stake_account.user = user;
+        stake_account.bump = stake_account_bump;
+    }
+    stake_account.record_stake(amount, estimated_jito_sol, current_time)?;
+
+    // Update user account
+    user_account.move_to_staked(amount)?;
+
+    global_state.record_stake(amount)?;
+
+    verbose_msg!(
+        "User {} staked {} SOL via Jito SPL Stake Pool ({}), received ~{} JitoSOL",
+        user,
+        lamports_to_sol_string(amount),
+        global_state.jito_stake_pool,
+        lamports_to_sol_string(estimated_jito_sol)
+    );
+
+    Ok(())
+}
+
 impl<'info> StakeSol<'info> {
     pub fn stake_sol(&mut self, amount: u64, bumps: &StakeSolBumps) -> Result<()> {
-        require!(amount >= MIN_STAKE_AMOUNT, ErrorCode::MinimumStakeNotMet);
-
-        let user_account = &mut self.user_account;
-        let stake_account = &mut self.stake_account;
-
-        // Check if user has sufficient available balance
-        let available_balance = user_account
-            .deposited_sol
-            .checked_sub(user_account.locked_sol)
-            .unwrap_or(0);
-
-        require!(
-            available_balance >= amount,
-            ErrorCode::InsufficientAvailableBalance
-        );
-
-        // Transfer SOL from user vault to Jito stake pool via CPI
-        let vault_bump = bumps.sol_vault;
-        let user_key = self.user.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault", user_key.as_ref(), &[vault_bump]]];
-
-        // ===== REAL JITO INTEGRATION =====
-        // Using actual Jito SPL Stake Pool for liquid staking and yield generation
-
-        // Create the deposit_sol instruction for Jito SPL Stake Pool
-        let deposit_instruction = spl_instruction::deposit_sol(
-            &self.stake_pool_program.key(),            // stake pool program
-            &self.jito_stake_pool.key(),               // stake pool
-            &self.stake_pool_withdraw_authority.key(), // withdraw authority
-            &self.reserve_stake.key(),                 // reserve stake
-            &self.sol_vault.key(),                     // from (SOL source)
-            &self.protocol_jito_vault.key(),           // to (pool token destination)
-            &self.manager_fee_account.key(),           // manager fee account
-            &self.referrer_pool_tokens.key(),          // referrer pool tokens
-            &self.jito_sol_mint.key(),                 // pool mint
-            &self.token_program.key(),                 // token program
-            amount,                                    // SOL amount
-        );
-
-        // Execute the Jito stake deposit via CPI
-        anchor_lang::solana_program::program::invoke_signed(
-            &deposit_instruction,
-            &[
-                self.stake_pool_program.to_account_info(),
-                self.jito_stake_pool.to_account_info(),
-                self.stake_pool_withdraw_authority.to_account_info(),
-                self.reserve_stake.to_account_info(),
-                self.sol_vault.to_account_info(),
-                self.protocol_jito_vault.to_account_info(),
-                self.manager_fee_account.to_account_info(),
-                self.referrer_pool_tokens.to_account_info(),
-                self.jito_sol_mint.to_account_info(),
-                self.token_program.to_account_info(),
-                self.system_program.to_account_info(),
-            ],
-            signer_seeds,
-        )?;
-
-        let current_time = Clock::get()?.unix_timestamp;
-
-        // Initialize stake account
-        stake_account.user = self.user.key();
-        stake_account.staked_amount = amount;
-
-        // Get the JitoSOL token balance after staking
-        // In real implementation, we'd calculate the exact amount based on the pool's exchange rate
-        // For now, we'll use a conservative estimate (Jito typically gives slightly less than 1:1)
-        let estimated_jito_sol = amount.saturating_mul(98).saturating_div(100); // ~2% difference
-        stake_account.jito_sol_amount = estimated_jito_sol;
-
-        stake_account.stake_date = current_time;
-        stake_account.last_yield_claim = current_time;
-        stake_account.total_yield_earned = 0;
-        stake_account.is_active = true;
-        stake_account.bump = bumps.stake_account;
-
-        // Update user account
-        user_account.deposited_sol = user_account.deposited_sol.checked_sub(amount).unwrap();
-        user_account.staked_sol = user_account.staked_sol.checked_add(amount).unwrap();
-
-        msg!(
-            "User {} staked {} SOL via Jito SPL Stake Pool ({}), received ~{} JitoSOL",
-            self.user.key(),
-            amount as f64 / 1_000_000_000.0,
-            self.global_state.jito_stake_pool,
-            estimated_jito_sol as f64 / 1_000_000_000.0
-        );
+        self.global_state.require_not_paused_for(PAUSE_STAKING)?;
 
-        Ok(())
+        let pool = StakePoolDepositAccounts {
+            stake_pool_program: self.stake_pool_program.to_account_info(),
+            jito_stake_pool: self.jito_stake_pool.to_account_info(),
+            stake_pool_withdraw_authority: self.stake_pool_withdraw_authority.to_account_info(),
+            reserve_stake: self.reserve_stake.to_account_info(),
+            protocol_jito_vault: self.protocol_jito_vault.to_account_info(),
+            manager_fee_account: self.manager_fee_account.to_account_info(),
+            referrer_pool_tokens: self.referrer_pool_tokens.to_account_info(),
+            jito_sol_mint: self.jito_sol_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
+
+        stake_vault_sol(
+            self.user.key(),
+            &mut self.user_account,
+            &mut self.stake_account,
+            &mut self.global_state,
+            &self.sol_vault.to_account_info(),
+            &pool,
+            amount,
+            bumps.stake_account,
+        )
     }
 }
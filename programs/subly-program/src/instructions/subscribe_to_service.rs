@@ -1,11 +1,24 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{mint_to, Mint, MintTo, Token, TokenAccount},
+    associated_token::{self, AssociatedToken},
+    metadata::{
+        create_metadata_accounts_v3, set_and_verify_collection, sign_metadata,
+        CreateMetadataAccountsV3, Metadata, SetAndVerifyCollection, SignMetadata,
+    },
+    token_2022,
+    token_2022_extensions::{
+        mint_close_authority_initialize, non_transferable_mint_initialize,
+        MintCloseAuthorityInitialize, NonTransferableMintInitialize,
+    },
+    token,
+    token_interface::{
+        freeze_account, initialize_mint2, mint_to, thaw_account, FreezeAccount, InitializeMint2,
+        Mint, MintTo, ThawAccount, TokenInterface,
+    },
 };
-use pyth_sdk_solana::state::SolanaPriceAccount;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(provider: Pubkey, service_id: u64)]
 pub struct SubscribeToService<'info> {
@@ -16,7 +29,8 @@ pub struct SubscribeToService<'info> {
         mut,
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
@@ -27,23 +41,29 @@ pub struct SubscribeToService<'info> {
             provider.as_ref(),
             service_id.to_le_bytes().as_ref()
         ],
-        bump,
+        bump = subscription_service.bump,
         constraint = subscription_service.is_active @ ErrorCode::ServiceNotActive,
-        constraint = subscription_service.provider != user.key() @ ErrorCode::CannotSubscribeToOwnService
+        constraint = !subscription_service.frozen @ ErrorCode::ServiceFrozen,
+        constraint = subscription_service.provider == provider @ ErrorCode::ServiceProviderMismatch,
+        constraint = subscription_service.provider != user.key() @ ErrorCode::CannotSubscribeToOwnService,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub subscription_service: Account<'info, SubscriptionService>,
 
     #[account(
         mut,
         seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
-        bump
+        bump = provider_account.bump,
+        constraint = provider_account.wallet == subscription_service.provider @ ErrorCode::ProviderAccountMismatch,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub provider_account: Account<'info, Provider>,
 
+    // Reused when re-subscribing after a cancellation
     #[account(
-        init,
+        init_if_needed,
         payer = user,
-        space = UserSubscription::INIT_SPACE,
+        space = 8 + UserSubscription::INIT_SPACE,
         seeds = [
             USER_SUBSCRIPTION_SEED.as_bytes(),
             user.key().as_ref(),
@@ -57,7 +77,8 @@ pub struct SubscribeToService<'info> {
     #[account(
         mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
@@ -65,27 +86,248 @@ pub struct SubscribeToService<'info> {
     /// CHECK: Pyth price feed account
     pub sol_usd_price_feed: AccountInfo<'info>,
 
-    // Subscription certificate NFT
+    /// Settlement mint, needed only to lock the subscription in USDC when SOL falls short
     #[account(
-        init,
-        payer = user,
-        mint::decimals = 0,
-        mint::authority = user,
-        mint::freeze_authority = user,
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint
+    )]
+    pub usdc_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    // Subscription certificate NFT, derived from the subscription identity and
+    // created in the handler so Token-2022 extensions can be initialized first
+    /// CHECK: Address is checked by seeds; created or reused by the handler
+    #[account(
+        mut,
+        seeds = [
+            CERTIFICATE_SEED.as_bytes(),
+            user.key().as_ref(),
+            provider.as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub certificate_nft_mint: AccountInfo<'info>,
+
+    /// CHECK: Created through the Associated Token program, which verifies the address
+    #[account(mut)]
+    pub certificate_nft_token_account: AccountInfo<'info>,
+
+    /// Metaplex metadata for the certificate, created by the Token Metadata program
+    /// CHECK: Address is checked by seeds; the Token Metadata program initializes it
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            certificate_nft_mint.key().as_ref()
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
     )]
-    pub certificate_nft_mint: Account<'info, Mint>,
+    pub certificate_metadata: AccountInfo<'info>,
 
+    // Reused when re-subscribing with the same certificate mint
     #[account(
-        init,
+        init_if_needed,
         payer = user,
-        associated_token::mint = certificate_nft_mint,
-        associated_token::authority = user,
+        space = 8 + CertificateAttributes::INIT_SPACE,
+        seeds = [
+            CERTIFICATE_ATTRIBUTES_SEED.as_bytes(),
+            certificate_nft_mint.key().as_ref()
+        ],
+        bump
     )]
-    pub certificate_nft_token_account: Account<'info, TokenAccount>,
+    pub certificate_attributes: Account<'info, CertificateAttributes>,
 
-    pub token_program: Program<'info, Token>,
+    // Provider's certificate collection. Providers registered before collections
+    // existed have no collection metadata; their certificates are left unverified
+    /// CHECK: Address is checked by seeds
+    #[account(
+        seeds = [CERTIFICATE_COLLECTION_SEED.as_bytes(), provider.as_ref()],
+        bump
+    )]
+    pub certificate_collection_mint: AccountInfo<'info>,
+
+    /// CHECK: Address is checked by seeds; verified by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            certificate_collection_mint.key().as_ref()
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub certificate_collection_metadata: AccountInfo<'info>,
+
+    /// CHECK: Address is checked by seeds; verified by the Token Metadata program
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            certificate_collection_mint.key().as_ref(),
+            b"edition"
+        ],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub certificate_collection_master_edition: AccountInfo<'info>,
+
+    /// Protocol PDA acting as certificate mint, freeze and metadata update authority
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+
+    /// Optional provider co-signature; verifies the provider as a creator on new certificates
+    #[account(address = provider @ ErrorCode::InvalidProvider)]
+    pub provider_signer: Option<Signer<'info>>,
+
+    /// Protocol-funded payer for certificate accounts when rent sponsorship is enabled
+    #[account(
+        mut,
+        seeds = [RENT_SPONSOR_SEED.as_bytes()],
+        bump
+    )]
+    pub rent_sponsor: SystemAccount<'info>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Subscription bookkeeping shared by the token and compressed certificate paths:
+/// locks 12 months of fees, records the subscription and updates the counters.
+/// The lock is taken in SOL when available SOL covers it, otherwise in USDC when the
+/// settlement mint's `usdc_decimals` are known. Returns (current_time, next_payment_due).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn open_subscription(
+    user: Pubkey,
+    provider: Pubkey,
+    service_id: u64,
+    user_account: &mut User,
+    subscription_service: &mut SubscriptionService,
+    provider_account: &mut Provider,
+    user_subscription: &mut UserSubscription,
+    user_subscription_bump: u8,
+    global_state: &mut GlobalState,
+    sol_usd_price_feed: &AccountInfo,
+    usdc_decimals: Option<u8>,
+) -> Result<(i64, i64)> {
+    global_state.require_not_paused_for(PAUSE_SUBSCRIPTIONS)?;
+    provider_account.require_in_good_standing(global_state.require_verified_providers)?;
+
+    // Verify the Pyth price feed account matches the one in GlobalState
+    require!(
+        sol_usd_price_feed.key() == global_state.sol_usd_price_feed,
+        ErrorCode::InvalidPriceFeed
+    );
+
+    require!(
+        !user_subscription.is_active,
+        ErrorCode::SubscriptionAlreadyExists
+    );
+    // A reused subscription account must be on the current layout
+    require!(
+        user_subscription.subscribed_at == 0 || user_subscription.is_current_version(),
+        ErrorCode::UnsupportedAccountVersion
+    );
+
+    // Get real SOL/USD price from Pyth
+    let sol_usd_price_cents = observe_sol_usd_price_cents(sol_usd_price_feed, global_state)?;
+
+    // Lock the configured number of billing periods of fees at the real price
+    let lock_periods = global_state.lock_periods();
+    let required_locked_amount = subscription_lock_lamports(
+        subscription_service.fee_usd,
+        sol_usd_price_cents,
+        lock_periods,
+    )?;
+
+    // Check if user has sufficient available balance, falling back to USDC
+    let available_balance = user_account.available_sol()?;
+    let locked_usdc = if available_balance >= required_locked_amount {
+        0
+    } else {
+        let decimals = usdc_decimals.ok_or(ErrorCode::InsufficientAvailableBalance)?;
+        let required_locked_usdc =
+            subscription_lock_token_amount(subscription_service.fee_usd, decimals, lock_periods)?;
+        require!(
+            user_account.available_usdc()? >= required_locked_usdc,
+            ErrorCode::InsufficientAvailableBalance
+        );
+        required_locked_usdc
+    };
+
+    let locked_sol = if locked_usdc > 0 {
+        0
+    } else {
+        required_locked_amount
+    };
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let next_payment_due =
+        current_time + (subscription_service.billing_frequency_days as i64 * 86400);
+
+    // Create subscription
+    *user_subscription = UserSubscription {
+        version: USER_SUBSCRIPTION_VERSION,
+        user,
+        provider,
+        service_id,
+        subscription_id: service_id, // Use service_id as subscription_id for simplicity
+        subscribed_at: current_time,
+        last_payment_at: None,
+        next_payment_due,
+        total_payments_made: 0,
+        is_active: true,
+        unsubscribed_at: None,
+        certificate_asset_id: Pubkey::default(),
+        failed_payment_attempts: 0,
+        delinquent_since: None,
+        bump: user_subscription_bump,
+        locked_usdc,
+        sponsor: Pubkey::default(),
+        sponsor_escrow_lamports: 0,
+        discount_bps: 0,
+        locked_sol,
+    };
+
+    // Lock funds for subscription
+    user_account.locked_usdc = user_account
+        .locked_usdc
+        .checked_add(locked_usdc)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    user_account.locked_sol = user_account
+        .locked_sol
+        .checked_add(locked_sol)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    // Update counters
+    subscription_service.current_subscribers = subscription_service
+        .current_subscribers
+        .checked_add(1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    provider_account.total_subscribers = provider_account
+        .total_subscribers
+        .checked_add(1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    global_state.record_subscription_opened()?;
+
+    verbose_msg!(
+        "User {} subscribed to service '{}' from provider {} (Fee: ${}/{} days)",
+        user,
+        subscription_service.name,
+        provider,
+        cents_to_usd_string(subscription_service.fee_usd),
+        subscription_service.billing_frequency_days
+    );
+
+    Ok((current_time, next_payment_due))
 }
 
 impl<'info> SubscribeToService<'info> {
@@ -95,161 +337,331 @@ impl<'info> SubscribeToService<'info> {
         service_id: u64,
         bumps: &SubscribeToServiceBumps,
     ) -> Result<()> {
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
-
-        // Verify the Pyth price feed account matches the one in GlobalState
         require!(
-            self.sol_usd_price_feed.key() == self.global_state.sol_usd_price_feed,
-            ErrorCode::InvalidPriceFeed
+            self.subscription_service.certificate_mode == CERTIFICATE_MODE_TOKEN,
+            ErrorCode::InvalidCertificateMode
         );
 
-        let subscription_service = &mut self.subscription_service;
-        let user_account = &mut self.user_account;
-        let provider_account = &mut self.provider_account;
+        let (current_time, next_payment_due) = open_subscription(
+            self.user.key(),
+            provider,
+            service_id,
+            &mut self.user_account,
+            &mut self.subscription_service,
+            &mut self.provider_account,
+            &mut self.user_subscription,
+            bumps.user_subscription,
+            &mut self.global_state,
+            &self.sol_usd_price_feed,
+            self.usdc_mint.as_ref().map(|mint| mint.decimals),
+        )?;
+
+        // Mint subscription certificate NFT
+        let rent_sponsored = self.mint_certificate(provider, service_id, bumps)?;
+        self.certificate_attributes.set_inner(CertificateAttributes {
+            mint: self.certificate_nft_mint.key(),
+            paid_through: next_payment_due,
+            updated_at: current_time,
+            rent_sponsored,
+            bump: bumps.certificate_attributes,
+        });
 
-        // Get real SOL/USD price from Pyth
-        let sol_usd_price_cents = Self::get_sol_usd_price_from_pyth(&self.sol_usd_price_feed)?;
+        verbose_msg!(
+            "Subscription certificate NFT minted: {}",
+            self.certificate_nft_mint.key()
+        );
 
-        // Calculate required locked amount (12 months of subscription fees) using real price
-        let monthly_fee_lamports =
-            Self::convert_usd_to_sol_lamports(subscription_service.fee_usd, sol_usd_price_cents)?;
-        let required_locked_amount = monthly_fee_lamports * 12; // Lock 12 months worth
+        // Emitted last so the event only ever reflects fully applied state
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionCreated {
+                user: self.user.key(),
+                provider,
+                service_id,
+                fee_usd: self.subscription_service.fee_usd,
+                next_payment_due,
+                certificate_mint: self.certificate_nft_mint.key(),
+            },
+        )?;
 
-        // Check if user has sufficient available balance
-        let available_balance = user_account
-            .deposited_sol
-            .checked_sub(user_account.locked_sol)
-            .unwrap_or(0);
+        Ok(())
+    }
 
+    /// Mint the certificate for this subscription to the user.
+    /// The mint lives at a PDA of the subscription identity. A mint left over from an
+    /// earlier subscription is reused under the token program it was created with;
+    /// otherwise a new one is created under the program selected in GlobalState.
+    /// Returns whether the rent sponsor paid for the accounts created here.
+    fn mint_certificate(
+        &self,
+        provider: Pubkey,
+        service_id: u64,
+        bumps: &SubscribeToServiceBumps,
+    ) -> Result<bool> {
+        let reuse_mint = !self.certificate_nft_mint.data_is_empty();
+        let expected_token_program = if reuse_mint {
+            *self.certificate_nft_mint.owner
+        } else {
+            self.global_state.certificate_token_program()
+        };
         require!(
-            available_balance >= required_locked_amount,
-            ErrorCode::InsufficientAvailableBalance
+            self.token_program.key() == expected_token_program,
+            ErrorCode::InvalidCertificateTokenProgram
         );
 
-        let current_time = Clock::get()?.unix_timestamp;
-        let next_payment_due =
-            current_time + (subscription_service.billing_frequency_days as i64 * 86400);
-
-        // Create subscription
-        self.user_subscription.set_inner(UserSubscription {
-            user: self.user.key(),
-            provider,
-            service_id,
-            subscription_id: service_id, // Use service_id as subscription_id for simplicity
-            subscribed_at: current_time,
-            last_payment_at: None,
-            next_payment_due,
-            total_payments_made: 0,
-            is_active: true,
-            unsubscribed_at: None,
-            bumps: bumps.user_subscription,
-        });
+        let authority_seeds: &[&[u8]] = &[
+            CERTIFICATE_AUTHORITY_SEED.as_bytes(),
+            &[bumps.certificate_authority],
+        ];
+        let sponsor_seeds: &[&[u8]] = &[RENT_SPONSOR_SEED.as_bytes(), &[bumps.rent_sponsor]];
+
+        // The sponsor PDA signs alongside the certificate authority when it pays
+        let sponsored_signer = [authority_seeds, sponsor_seeds];
+        let user_signer = [authority_seeds];
+        let rent_sponsored = self.rent_sponsor_covers(reuse_mint)?;
+        let (payer, signer): (AccountInfo<'info>, &[&[&[u8]]]) = if rent_sponsored {
+            (self.rent_sponsor.to_account_info(), &sponsored_signer)
+        } else {
+            (self.user.to_account_info(), &user_signer)
+        };
 
-        // Lock funds for subscription
-        user_account.locked_sol = user_account
-            .locked_sol
-            .checked_add(required_locked_amount)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        if !reuse_mint {
+            self.create_certificate_mint(provider, service_id, &payer, signer, bumps)?;
+        }
+
+        // The token account survives a cancellation unless it was closed
+        associated_token::create_idempotent(CpiContext::new_with_signer(
+            self.associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: payer.clone(),
+                associated_token: self.certificate_nft_token_account.to_account_info(),
+                authority: self.user.to_account_info(),
+                mint: self.certificate_nft_mint.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            },
+            signer,
+        ))?;
+
+        // A classic certificate left over from an earlier subscription is still frozen
+        let token_account = load_token_account(&self.certificate_nft_token_account)?
+            .ok_or(ErrorCode::InvalidCertificateTokenProgram)?;
+        if token_account.is_frozen() {
+            thaw_account(CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                ThawAccount {
+                    account: self.certificate_nft_token_account.to_account_info(),
+                    mint: self.certificate_nft_mint.to_account_info(),
+                    authority: self.certificate_authority.to_account_info(),
+                },
+                signer,
+            ))?;
+        }
 
-        // Mint subscription certificate NFT
         let cpi_accounts = MintTo {
             mint: self.certificate_nft_mint.to_account_info(),
             to: self.certificate_nft_token_account.to_account_info(),
-            authority: self.user.to_account_info(),
+            authority: self.certificate_authority.to_account_info(),
         };
         let cpi_program = self.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
         mint_to(cpi_ctx, 1)?;
 
-        // Update counters
-        subscription_service.current_subscribers += 1;
-        provider_account.total_subscribers += 1;
-
-        msg!(
-            "User {} subscribed to service '{}' from provider {} (Fee: ${:.2}/{} days)",
-            self.user.key(),
-            subscription_service.name,
-            provider,
-            subscription_service.fee_usd as f64 / 100.0,
-            subscription_service.billing_frequency_days
-        );
-
-        msg!(
-            "Subscription certificate NFT minted: {}",
-            self.certificate_nft_mint.key()
-        );
+        // Ownership checks compare balances to 1, which only means one certificate
+        // if the mint never holds more than a single whole token
+        require_single_certificate(
+            &self.certificate_nft_mint.try_borrow_data()?,
+            &self.certificate_authority.key(),
+        )?;
+
+        // Certificates are bound to the paying wallet. Token-2022 certificates are
+        // non-transferable by construction; classic ones are frozen instead
+        if self.token_program.key() == token::ID {
+            freeze_account(CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                FreezeAccount {
+                    account: self.certificate_nft_token_account.to_account_info(),
+                    mint: self.certificate_nft_mint.to_account_info(),
+                    authority: self.certificate_authority.to_account_info(),
+                },
+                signer,
+            ))?;
+        }
+
+        // Brand the certificate with the service's name and image
+        if self.certificate_metadata.data_is_empty() {
+            self.create_certificate_metadata(&payer, signer)?;
+            if let Some(provider_signer) = &self.provider_signer {
+                sign_metadata(CpiContext::new(
+                    self.token_metadata_program.to_account_info(),
+                    SignMetadata {
+                        creator: provider_signer.to_account_info(),
+                        metadata: self.certificate_metadata.to_account_info(),
+                    },
+                ))?;
+            }
+            if !self.certificate_collection_metadata.data_is_empty() {
+                self.verify_certificate_collection(&payer, signer)?;
+            }
+        }
+
+        Ok(rent_sponsored)
+    }
 
-        Ok(())
+    /// Whether the rent sponsor should pay for this certificate's accounts.
+    /// An underfunded sponsor falls back to the subscriber paying, as without sponsorship.
+    fn rent_sponsor_covers(&self, reuse_mint: bool) -> Result<bool> {
+        if !self.global_state.sponsor_certificate_rent {
+            return Ok(false);
+        }
+
+        let rent = Rent::get()?;
+        let soulbound_mint = self.token_program.key() == token_2022::ID;
+
+        // The sponsor itself has to stay rent exempt
+        let mut required = rent.minimum_balance(0);
+        if !reuse_mint {
+            required += rent.minimum_balance(certificate_mint_space(soulbound_mint)?);
+        }
+        if self.certificate_nft_token_account.data_is_empty() {
+            required += rent.minimum_balance(certificate_token_account_space(soulbound_mint)?);
+        }
+        if self.certificate_metadata.data_is_empty() {
+            required += rent.minimum_balance(MAX_METADATA_ACCOUNT_LENGTH);
+        }
+
+        let covered = self.rent_sponsor.lamports() >= required;
+        if !covered {
+            msg!("Rent sponsor underfunded; certificate rent is paid by the subscriber");
+        }
+        Ok(covered)
     }
 
-    /// Get SOL/USD price from Pyth Network - REAL IMPLEMENTATION
-    fn get_sol_usd_price_from_pyth(price_feed_account: &AccountInfo) -> Result<u64> {
-        // Load price feed from Pyth account using the correct API
-        let price_feed = SolanaPriceAccount::account_info_to_feed(price_feed_account)
-            .map_err(|_| ErrorCode::InvalidPriceFeed)?;
-
-        // Get current price with staleness check
-        let current_time = Clock::get()?.unix_timestamp;
-        let max_age = 3600; // 1 hour in seconds
-
-        let price = price_feed
-            .get_price_no_older_than(current_time, max_age)
-            .ok_or(ErrorCode::PriceNotAvailable)?;
-
-        // Validate price data
-        require!(price.price > 0, ErrorCode::InvalidPrice);
-
-        msg!(
-            "Pyth price data: price={}, conf={}, expo={}, timestamp={}",
-            price.price,
-            price.conf,
-            price.expo,
-            price.publish_time
-        );
+    /// Create the certificate mint at its PDA, with the certificate authority PDA as
+    /// mint and freeze authority. Under Token-2022 the mint is non-transferable
+    /// (soulbound) and carries a close authority so the protocol can reclaim its rent later.
+    fn create_certificate_mint(
+        &self,
+        provider: Pubkey,
+        service_id: u64,
+        payer: &AccountInfo<'info>,
+        signer: &[&[&[u8]]],
+        bumps: &SubscribeToServiceBumps,
+    ) -> Result<()> {
+        let soulbound = self.global_state.soulbound_certificates;
+        let space = certificate_mint_space(soulbound)?;
+
+        let user_key = self.user.key();
+        let service_id_bytes = service_id.to_le_bytes();
+        let mint_seeds: &[&[u8]] = &[
+            CERTIFICATE_SEED.as_bytes(),
+            user_key.as_ref(),
+            provider.as_ref(),
+            service_id_bytes.as_ref(),
+            &[bumps.certificate_nft_mint],
+        ];
+
+        let mut signers = signer.to_vec();
+        signers.push(mint_seeds);
+        create_pda_account(
+            payer,
+            &self.certificate_nft_mint,
+            &self.system_program.to_account_info(),
+            space,
+            &self.token_program.key(),
+            &signers,
+        )?;
+
+        if soulbound {
+            non_transferable_mint_initialize(CpiContext::new(
+                self.token_program.to_account_info(),
+                NonTransferableMintInitialize {
+                    token_program_id: self.token_program.to_account_info(),
+                    mint: self.certificate_nft_mint.to_account_info(),
+                },
+            ))?;
+            mint_close_authority_initialize(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    MintCloseAuthorityInitialize {
+                        token_program_id: self.token_program.to_account_info(),
+                        mint: self.certificate_nft_mint.to_account_info(),
+                    },
+                ),
+                Some(&self.certificate_authority.key()),
+            )?;
+        }
+
+        initialize_mint2(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                InitializeMint2 {
+                    mint: self.certificate_nft_mint.to_account_info(),
+                },
+            ),
+            0,
+            &self.certificate_authority.key(),
+            Some(&self.certificate_authority.key()),
+        )
+    }
 
-        // Convert price to USD cents
-        // Pyth SOL/USD typically has exponent of -8, meaning price is in units of 10^-8 USD
-        let price_cents = if price.expo >= 0 {
-            // Positive exponent: multiply
-            (price.price as u64)
-                .checked_mul(10_u64.pow(price.expo as u32))
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_mul(100) // Convert to cents
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-        } else {
-            // Negative exponent: divide
-            let divisor = 10_u64.pow((-price.expo) as u32);
-            (price.price as u64)
-                .checked_mul(100) // Convert to cents first
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(divisor)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
+    /// Create Metaplex metadata for the certificate mint.
+    /// The certificate authority PDA signs as mint authority and stays the update
+    /// authority so later payments can refresh the metadata without the user.
+    fn create_certificate_metadata(
+        &self,
+        payer: &AccountInfo<'info>,
+        signer: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_accounts = CreateMetadataAccountsV3 {
+            metadata: self.certificate_metadata.to_account_info(),
+            mint: self.certificate_nft_mint.to_account_info(),
+            mint_authority: self.certificate_authority.to_account_info(),
+            payer: payer.clone(),
+            update_authority: self.certificate_authority.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
         };
-
-        // Validate reasonable price range ($10 - $1000 per SOL)
-        require!(
-            price_cents >= 1000 && price_cents <= 100000,
-            ErrorCode::InvalidPrice
-        );
-
-        msg!(
-            "Real SOL/USD price from Pyth: ${:.2} (account: {})",
-            price_cents as f64 / 100.0,
-            price_feed_account.key()
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_metadata_program.to_account_info(),
+            cpi_accounts,
+            signer,
         );
 
-        Ok(price_cents)
+        create_metadata_accounts_v3(
+            cpi_ctx,
+            certificate_metadata(&self.subscription_service, self.certificate_authority.key()),
+            true, // is_mutable
+            true, // update_authority_is_signer
+            None,
+        )
     }
 
-    /// Convert USD cents to SOL lamports using real Pyth price
-    fn convert_usd_to_sol_lamports(usd_cents: u64, sol_usd_cents: u64) -> Result<u64> {
-        // lamports = (usd_cents * LAMPORTS_PER_SOL) / sol_usd_cents
-        let lamports = (usd_cents as u128)
-            .checked_mul(1_000_000_000) // LAMPORTS_PER_SOL
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(sol_usd_cents as u128)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    /// Add the certificate to the provider's collection and mark it verified.
+    /// The certificate authority is update authority of both the item and the collection.
+    fn verify_certificate_collection(
+        &self,
+        payer: &AccountInfo<'info>,
+        signer: &[&[&[u8]]],
+    ) -> Result<()> {
+        let cpi_accounts = SetAndVerifyCollection {
+            metadata: self.certificate_metadata.to_account_info(),
+            collection_authority: self.certificate_authority.to_account_info(),
+            payer: payer.clone(),
+            update_authority: self.certificate_authority.to_account_info(),
+            collection_mint: self.certificate_collection_mint.to_account_info(),
+            collection_metadata: self.certificate_collection_metadata.to_account_info(),
+            collection_master_edition: self
+                .certificate_collection_master_edition
+                .to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_metadata_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
 
-        Ok(u64::try_from(lamports).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+        set_and_verify_collection(cpi_ctx, None)
     }
 }
@@ -0,0 +1,229 @@
+use crate::{
+    constants::*, error::ErrorCode, events::*, instructions::open_subscription, state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+/// Subscribe to a service that issues compressed (Bubblegum) certificates.
+/// The certificate is a leaf in the protocol tree instead of a mint + token account,
+/// which keeps the per-subscription cost to the transaction fee.
+/// Bubblegum v1 leaves cannot be frozen, so unlike token certificates these can be
+/// transferred; providers should gate on the UserSubscription, not on leaf ownership.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct SubscribeToServiceCompressed<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_active @ ErrorCode::ServiceNotActive,
+        constraint = !subscription_service.frozen @ ErrorCode::ServiceFrozen,
+        constraint = subscription_service.provider == provider @ ErrorCode::ServiceProviderMismatch,
+        constraint = subscription_service.provider != user.key() @ ErrorCode::CannotSubscribeToOwnService,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.wallet == subscription_service.provider @ ErrorCode::ProviderAccountMismatch,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+
+    // Reused when re-subscribing after a cancellation
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserSubscription::INIT_SPACE,
+        seeds = [
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user.key().as_ref(),
+            provider.as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump
+    )]
+    pub user_subscription: Account<'info, UserSubscription>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.certificate_merkle_tree != Pubkey::default()
+            @ ErrorCode::CertificateTreeNotConfigured,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// Pyth SOL/USD price feed account
+    /// CHECK: Pyth price feed account
+    pub sol_usd_price_feed: AccountInfo<'info>,
+
+    /// Settlement mint, needed only to lock the subscription in USDC when SOL falls short
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint
+    )]
+    pub usdc_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// Tree delegate that signs the Bubblegum mint
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum tree config, checked by seeds and by the Bubblegum program
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = BUBBLEGUM_PROGRAM_ID
+    )]
+    pub tree_config: AccountInfo<'info>,
+
+    /// CHECK: Must be the tree configured in GlobalState
+    #[account(
+        mut,
+        address = global_state.certificate_merkle_tree
+    )]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum program
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+
+    /// CHECK: SPL Noop program used by Bubblegum for leaf logs
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = SPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SubscribeToServiceCompressed<'info> {
+    pub fn subscribe_to_service_compressed(
+        &mut self,
+        provider: Pubkey,
+        service_id: u64,
+        bumps: &SubscribeToServiceCompressedBumps,
+    ) -> Result<()> {
+        require!(
+            self.subscription_service.certificate_mode == CERTIFICATE_MODE_COMPRESSED,
+            ErrorCode::InvalidCertificateMode
+        );
+
+        let (_current_time, next_payment_due) = open_subscription(
+            self.user.key(),
+            provider,
+            service_id,
+            &mut self.user_account,
+            &mut self.subscription_service,
+            &mut self.provider_account,
+            &mut self.user_subscription,
+            bumps.user_subscription,
+            &mut self.global_state,
+            &self.sol_usd_price_feed,
+            self.usdc_mint.as_ref().map(|mint| mint.decimals),
+        )?;
+
+        // The leaf minted next gets the asset id derived from the tree's current mint count
+        let asset_id = next_compressed_asset_id(&self.tree_config, &self.merkle_tree.key())?;
+        self.user_subscription.certificate_asset_id = asset_id;
+
+        self.mint_compressed_certificate(bumps)?;
+
+        msg!("Compressed subscription certificate minted: {}", asset_id);
+
+        // Emitted last so the event only ever reflects fully applied state
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionCreated {
+                user: self.user.key(),
+                provider,
+                service_id,
+                fee_usd: self.subscription_service.fee_usd,
+                next_payment_due,
+                certificate_mint: asset_id,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn mint_compressed_certificate(&self, bumps: &SubscribeToServiceCompressedBumps) -> Result<()> {
+        let token_metadata =
+            certificate_metadata(&self.subscription_service, self.certificate_authority.key());
+        let collection = self.global_state.certificate_collection;
+
+        // The collection is recorded unverified; verification needs the collection authority
+        let metadata = BubblegumMetadataArgs {
+            name: token_metadata.name,
+            symbol: token_metadata.symbol,
+            uri: token_metadata.uri,
+            seller_fee_basis_points: token_metadata.seller_fee_basis_points,
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(0),
+            collection: (collection != Pubkey::default()).then_some(BubblegumCollection {
+                verified: false,
+                key: collection,
+            }),
+            uses: None,
+            token_program_version: 0,
+            creators: token_metadata
+                .creators
+                .unwrap_or_default()
+                .into_iter()
+                .map(|creator| BubblegumCreator {
+                    address: creator.address,
+                    verified: creator.verified,
+                    share: creator.share,
+                })
+                .collect(),
+        };
+
+        bubblegum_mint_v1(
+            &self.bubblegum_program,
+            &self.tree_config,
+            &self.user.to_account_info(),
+            &self.merkle_tree,
+            &self.user.to_account_info(),
+            &self.certificate_authority,
+            &self.log_wrapper,
+            &self.compression_program,
+            &self.system_program.to_account_info(),
+            metadata,
+            &[
+                CERTIFICATE_AUTHORITY_SEED.as_bytes(),
+                &[bumps.certificate_authority],
+            ],
+        )
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::*,
+    instructions::{withdraw_pool_sol, StakePoolWithdrawAccounts},
+    state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+/// Clear the residues left after a user has withdrawn and unstaked everything.
+/// Where each residual lamport ends up:
+/// - JitoSOL dust is redeemed through the pool into the vault, below MIN_STAKE_AMOUNT or not
+/// - every vault lamport above the rent floor, including those proceeds, goes to the
+///   user's wallet; the rent floor itself stays in the vault
+/// - deposited_sol and staked_sol dust that no lamports back any more is written off
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Box<Account<'info, User>>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref()],
+        bump = user_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// User's stake account (optional - may not exist if user never staked)
+    #[account(
+        mut,
+        seeds = [
+            STAKE_ACCOUNT_SEED.as_bytes(),
+            user.key().as_ref(),
+        ],
+        bump = stake_account.bump,
+        constraint = stake_account.user == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = stake_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub stake_account: Option<Box<Account<'info, StakeAccount>>>,
+
+    // ===== Optional Jito/SPL Stake Pool Accounts for redeeming JitoSOL dust =====
+    /// Protocol's JitoSOL vault (ATA owned by protocol PDA) - required for redeeming
+    #[account(
+        mut,
+        associated_token::mint = jito_sol_mint,
+        associated_token::authority = protocol_authority
+    )]
+    pub protocol_jito_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// CHECK: Protocol authority PDA that owns JitoSOL vault - required for redeeming
+    #[account(
+        seeds = [b"protocol_authority"],
+        bump
+    )]
+    pub protocol_authority: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SPL Stake Pool program (read from GlobalState) - required for redeeming
+    #[account(address = global_state.spl_stake_pool_program)]
+    pub stake_pool_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Jito Stake Pool account (read from GlobalState) - required for redeeming
+    #[account(
+        mut,
+        address = global_state.jito_stake_pool
+    )]
+    pub jito_stake_pool: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Stake pool withdraw authority (PDA derived from stake pool) - required for redeeming
+    pub stake_pool_withdraw_authority: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Jito reserve stake account, checked by the pool - required for redeeming
+    #[account(mut)]
+    pub reserve_stake: Option<UncheckedAccount<'info>>,
+
+    /// JitoSOL mint (read from GlobalState) - required for redeeming
+    #[account(
+        mut,
+        address = global_state.jito_sol_mint
+    )]
+    pub jito_sol_mint: Option<Box<Account<'info, Mint>>>,
+
+    /// CHECK: Jito manager fee account - required for redeeming
+    #[account(mut)]
+    pub manager_fee_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Clock sysvar, read by the pool - required for redeeming
+    #[account(address = anchor_lang::solana_program::sysvar::clock::ID)]
+    pub clock: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Stake history sysvar, read by the pool - required for redeeming
+    #[account(address = anchor_lang::solana_program::sysvar::stake_history::ID)]
+    pub stake_history: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Stake program - required for redeeming
+    #[account(address = anchor_lang::solana_program::stake::program::ID)]
+    pub stake_program: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SweepDust<'info> {
+    /// Sweep the user's residues, failing with BalanceNotDust if anything left is
+    /// more than dust or still locked, so a real balance is never swept away
+    pub fn sweep_dust(&mut self, bumps: &SweepDustBumps) -> Result<()> {
+        require!(
+            self.user_account.locked_sol == 0
+                && self.user_account.locked_usdc == 0
+                && self.user_account.pending_withdrawal_lamports == 0,
+            ErrorCode::BalanceNotDust
+        );
+        require!(
+            self.user_account.deposited_sol <= MAX_DUST_LAMPORTS,
+            ErrorCode::BalanceNotDust
+        );
+
+        let jito_sol_dust = self
+            .stake_account
+            .as_ref()
+            .map_or(0, |stake_account| stake_account.jito_sol_amount);
+        require!(jito_sol_dust <= MAX_DUST_JITO_SOL, ErrorCode::BalanceNotDust);
+        let redeemed_lamports = if jito_sol_dust > 0 {
+            self.redeem_jito_sol_dust(jito_sol_dust, bumps)?
+        } else {
+            0
+        };
+
+        // Principal rounding can leave staked_sol behind with no JitoSOL to redeem
+        let staked_written_off = self.user_account.staked_sol;
+        require!(
+            staked_written_off <= MAX_DUST_LAMPORTS,
+            ErrorCode::BalanceNotDust
+        );
+        self.user_account.staked_sol = 0;
+        self.global_state.total_staked_lamports = self
+            .global_state
+            .total_staked_lamports
+            .saturating_sub(staked_written_off);
+
+        // Pay out whatever the vault holds above its rent floor; any deposited_sol
+        // beyond that was never backed by lamports and is written off
+        let lamports_paid_out = spendable_vault_balance(&self.sol_vault)?;
+        let deposited_written_off = self
+            .user_account
+            .deposited_sol
+            .saturating_sub(lamports_paid_out);
+        if lamports_paid_out > 0 {
+            let user_key = self.user.key();
+            anchor_lang::system_program::transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: self.sol_vault.to_account_info(),
+                        to: self.user.to_account_info(),
+                    },
+                    &[&[b"vault", user_key.as_ref(), &[self.user_account.vault_bump]]],
+                ),
+                lamports_paid_out,
+            )?;
+        }
+
+        // Counters were introduced after launch, so older deposits may not be reflected
+        self.global_state.total_deposited_lamports = self
+            .global_state
+            .total_deposited_lamports
+            .saturating_sub(self.user_account.deposited_sol);
+        self.user_account.deposited_sol = 0;
+
+        msg!(
+            "Swept dust: redeemed {} JitoSOL base units for {} lamports, paid out {} lamports, wrote off {} deposited and {} staked lamports",
+            jito_sol_dust,
+            redeemed_lamports,
+            lamports_paid_out,
+            deposited_written_off,
+            staked_written_off
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            DustSwept {
+                user: self.user.key(),
+                jito_sol_redeemed: jito_sol_dust,
+                redeemed_lamports,
+                lamports_paid_out,
+                deposited_written_off,
+                staked_written_off,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    /// Redeem all of the user's JitoSOL through the pool, bypassing MIN_STAKE_AMOUNT,
+    /// and book the lamports that actually reached the vault
+    fn redeem_jito_sol_dust(&mut self, jito_sol_amount: u64, bumps: &SweepDustBumps) -> Result<u64> {
+        // Every optional redeeming account must be present; a missing one is a client error
+        let protocol_authority_bump = bumps
+            .protocol_authority
+            .ok_or(ErrorCode::StakingNotAvailable)?;
+        let pool = StakePoolWithdrawAccounts {
+            stake_pool_program: redeeming_account(&self.stake_pool_program)?,
+            jito_stake_pool: redeeming_account(&self.jito_stake_pool)?,
+            stake_pool_withdraw_authority: redeeming_account(&self.stake_pool_withdraw_authority)?,
+            protocol_authority: redeeming_account(&self.protocol_authority)?,
+            protocol_jito_vault: redeeming_account(&self.protocol_jito_vault.as_deref())?,
+            reserve_stake: redeeming_account(&self.reserve_stake)?,
+            manager_fee_account: redeeming_account(&self.manager_fee_account)?,
+            jito_sol_mint: redeeming_account(&self.jito_sol_mint.as_deref())?,
+            clock: redeeming_account(&self.clock)?,
+            stake_history: redeeming_account(&self.stake_history)?,
+            stake_program: redeeming_account(&self.stake_program)?,
+            token_program: redeeming_account(&self.token_program)?,
+        };
+
+        // The pool rate and fee decide what arrives, so measure it on the vault
+        let vault_before = self.sol_vault.lamports();
+
+        withdraw_pool_sol(
+            &pool,
+            &self.sol_vault.to_account_info(),
+            jito_sol_amount,
+            protocol_authority_bump,
+        )?;
+
+        let sol_received = self
+            .sol_vault
+            .lamports()
+            .checked_sub(vault_before)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        // All of the JitoSOL is burned, so all of the principal leaves the stake
+        let stake_account = self
+            .stake_account
+            .as_mut()
+            .ok_or(ErrorCode::StakingNotAvailable)?;
+        let principal = stake_account.principal_for(jito_sol_amount)?;
+        stake_account.record_unstake(jito_sol_amount, principal)?;
+
+        // staked_sol may already be below the principal after earlier rounding
+        let booked_principal = principal.min(self.user_account.staked_sol);
+        self.user_account
+            .record_unstake_proceeds(booked_principal, sol_received)?;
+
+        self.global_state.total_staked_lamports = self
+            .global_state
+            .total_staked_lamports
+            .saturating_sub(booked_principal);
+        self.global_state.total_deposited_lamports = self
+            .global_state
+            .total_deposited_lamports
+            .checked_add(sol_received)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        Ok(sol_received)
+    }
+}
+
+/// Account info of an optional redeeming account, or StakingNotAvailable
+fn redeeming_account<'info, T: ToAccountInfo<'info>>(account: &Option<T>) -> Result<AccountInfo<'info>> {
+    account
+        .as_ref()
+        .map(|account| account.to_account_info())
+        .ok_or(ErrorCode::StakingNotAvailable.into())
+}
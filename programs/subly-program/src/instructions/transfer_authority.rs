@@ -0,0 +1,124 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProposeAuthorityTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelAuthorityTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAuthorityTransfer<'info> {
+    /// The proposed authority, checked against global_state.pending_authority
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> ProposeAuthorityTransfer<'info> {
+    /// First step of an authority rotation. Nothing changes hands until
+    /// `new_authority` signs accept_authority_transfer, so a mistyped key
+    /// can be cancelled or replaced
+    pub fn propose_authority_transfer(
+        &mut self,
+        new_authority: Pubkey,
+        bumps: &ProposeAuthorityTransferBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.propose_authority(new_authority)?;
+
+        msg!("Authority transfer to {} proposed", new_authority);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PENDING_AUTHORITY,
+                &old_value,
+                &new_authority,
+                self.authority.key(),
+            )?,
+        )
+    }
+}
+
+impl<'info> CancelAuthorityTransfer<'info> {
+    /// Drop the pending proposal; the current authority stays in place
+    pub fn cancel_authority_transfer(
+        &mut self,
+        bumps: &CancelAuthorityTransferBumps,
+    ) -> Result<()> {
+        let old_value = self.global_state.cancel_authority_transfer()?;
+
+        msg!("Authority transfer to {} cancelled", old_value);
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_PENDING_AUTHORITY,
+                &old_value,
+                &Pubkey::default(),
+                self.authority.key(),
+            )?,
+        )
+    }
+}
+
+impl<'info> AcceptAuthorityTransfer<'info> {
+    /// Second step: the proposed key takes over as authority
+    pub fn accept_authority_transfer(
+        &mut self,
+        bumps: &AcceptAuthorityTransferBumps,
+    ) -> Result<()> {
+        let new_authority = self.new_authority.key();
+        let old_value = self.global_state.accept_authority(new_authority)?;
+
+        msg!(
+            "Authority transferred from {} to {}",
+            old_value,
+            new_authority
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ConfigChanged::new(
+                CONFIG_FIELD_AUTHORITY,
+                &old_value,
+                &new_authority,
+                new_authority,
+            )?,
+        )
+    }
+}
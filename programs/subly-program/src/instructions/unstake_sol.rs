@@ -1,4 +1,4 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{constants::*, error::ErrorCode, state::*, utils::*};
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -16,7 +16,8 @@ pub struct UnstakeSol<'info> {
         mut,
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
@@ -28,21 +29,24 @@ pub struct UnstakeSol<'info> {
         ],
         bump = stake_account.bump,
         constraint = stake_account.user == user.key() @ ErrorCode::UnauthorizedUser,
-        constraint = stake_account.is_active @ ErrorCode::StakingNotAvailable
+        constraint = stake_account.is_active @ ErrorCode::StakingNotAvailable,
+        constraint = stake_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub stake_account: Account<'info, StakeAccount>,
 
     #[account(
         mut,
         seeds = [b"vault", user.key().as_ref()],
-        bump,
+        bump = user_account.vault_bump,
     )]
     pub sol_vault: SystemAccount<'info>,
 
-    /// Global state for reading Jito configuration
+    /// Global state for reading Jito configuration and updating protocol counters
     #[account(
+        mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
@@ -76,6 +80,10 @@ pub struct UnstakeSol<'info> {
     /// CHECK: Stake pool withdraw authority (PDA derived from stake pool)
     pub stake_pool_withdraw_authority: UncheckedAccount<'info>,
 
+    /// CHECK: Jito reserve stake account the SOL is paid from, checked by the pool
+    #[account(mut)]
+    pub reserve_stake: UncheckedAccount<'info>,
+
     /// JitoSOL mint (read from GlobalState)
     #[account(
         mut,
@@ -87,10 +95,85 @@ pub struct UnstakeSol<'info> {
     #[account(mut)]
     pub manager_fee_account: UncheckedAccount<'info>,
 
+    /// CHECK: Clock sysvar, read by the pool
+    #[account(address = anchor_lang::solana_program::sysvar::clock::ID)]
+    pub clock: UncheckedAccount<'info>,
+
+    /// CHECK: Stake history sysvar, read by the pool
+    #[account(address = anchor_lang::solana_program::sysvar::stake_history::ID)]
+    pub stake_history: UncheckedAccount<'info>,
+
     // ===== Programs =====
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
+
+    /// CHECK: Stake program
+    #[account(address = anchor_lang::solana_program::stake::program::ID)]
+    pub stake_program: UncheckedAccount<'info>,
+}
+
+/// Stake pool accounts needed to redeem the protocol's JitoSOL into a vault
+pub(crate) struct StakePoolWithdrawAccounts<'info> {
+    pub stake_pool_program: AccountInfo<'info>,
+    pub jito_stake_pool: AccountInfo<'info>,
+    pub stake_pool_withdraw_authority: AccountInfo<'info>,
+    pub protocol_authority: AccountInfo<'info>,
+    pub protocol_jito_vault: AccountInfo<'info>,
+    pub reserve_stake: AccountInfo<'info>,
+    pub manager_fee_account: AccountInfo<'info>,
+    pub jito_sol_mint: AccountInfo<'info>,
+    pub clock: AccountInfo<'info>,
+    pub stake_history: AccountInfo<'info>,
+    pub stake_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+/// Burn `jito_sol_amount` from the protocol JitoSOL vault through the pool's
+/// WithdrawSol, signed by the protocol authority, with the SOL paid into `sol_vault`.
+/// Shared by unstake_sol, withdraw_with_unstake and sweep_dust
+pub(crate) fn withdraw_pool_sol<'info>(
+    pool: &StakePoolWithdrawAccounts<'info>,
+    sol_vault: &AccountInfo<'info>,
+    jito_sol_amount: u64,
+    protocol_authority_bump: u8,
+) -> Result<()> {
+    let signer_seeds: &[&[&[u8]]] = &[&[b"protocol_authority", &[protocol_authority_bump]]];
+
+    let withdraw_instruction = spl_instruction::withdraw_sol(
+        pool.stake_pool_program.key,            // stake pool program
+        pool.jito_stake_pool.key,               // stake pool
+        pool.stake_pool_withdraw_authority.key, // withdraw authority
+        pool.protocol_authority.key,            // user transfer authority (protocol)
+        pool.protocol_jito_vault.key,           // burn from (JitoSOL source)
+        pool.reserve_stake.key,                 // reserve stake
+        sol_vault.key,                          // to (SOL destination)
+        pool.manager_fee_account.key,           // manager fee account
+        pool.jito_sol_mint.key,                 // pool mint
+        pool.token_program.key,                 // token program
+        jito_sol_amount,                        // JitoSOL amount to burn
+    );
+
+    anchor_lang::solana_program::program::invoke_signed(
+        &withdraw_instruction,
+        &[
+            pool.stake_pool_program.clone(),
+            pool.jito_stake_pool.clone(),
+            pool.stake_pool_withdraw_authority.clone(),
+            pool.protocol_authority.clone(),
+            pool.protocol_jito_vault.clone(),
+            pool.reserve_stake.clone(),
+            sol_vault.clone(),
+            pool.manager_fee_account.clone(),
+            pool.jito_sol_mint.clone(),
+            pool.clock.clone(),
+            pool.stake_history.clone(),
+            pool.stake_program.clone(),
+            pool.token_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
 }
 
 impl<'info> UnstakeSol<'info> {
@@ -100,95 +183,58 @@ impl<'info> UnstakeSol<'info> {
         jito_apy_bps: u16,
         bumps: &UnstakeSolBumps,
     ) -> Result<()> {
+        self.global_state.require_not_paused_for(PAUSE_STAKING)?;
         require!(jito_sol_amount > 0, ErrorCode::InvalidAmount);
         require!(
             self.stake_account.jito_sol_amount >= jito_sol_amount,
             ErrorCode::InsufficientStakedFunds
         );
 
-        // Prepare signer seeds for protocol authority
-        let protocol_authority_bump = bumps.protocol_authority;
-        let signer_seeds: &[&[&[u8]]] = &[&[b"protocol_authority", &[protocol_authority_bump]]];
-
         // ===== REAL JITO UNSTAKING =====
         // Using actual Jito SPL Stake Pool withdraw_sol instruction
-
-        // Create the withdraw_sol instruction for Jito SPL Stake Pool
-        let withdraw_instruction = spl_instruction::withdraw_sol(
-            &self.stake_pool_program.key(),            // stake pool program
-            &self.jito_stake_pool.key(),               // stake pool
-            &self.stake_pool_withdraw_authority.key(), // withdraw authority
-            &self.protocol_authority.key(),            // user transfer authority (protocol)
-            &self.protocol_jito_vault.key(),           // burn from (JitoSOL source)
-            &self.sol_vault.key(),                     // to (SOL destination)
-            &self.manager_fee_account.key(),           // manager fee account
-            &self.jito_sol_mint.key(),                 // pool mint
-            &self.token_program.key(),                 // token program
-            &self.system_program.key(),                // system program
-            jito_sol_amount,                           // JitoSOL amount to burn
-        );
-
-        // Execute the Jito unstake via CPI
-        anchor_lang::solana_program::program::invoke_signed(
-            &withdraw_instruction,
-            &[
-                self.stake_pool_program.to_account_info(),
-                self.jito_stake_pool.to_account_info(),
-                self.stake_pool_withdraw_authority.to_account_info(),
-                self.protocol_authority.to_account_info(),
-                self.protocol_jito_vault.to_account_info(),
-                self.sol_vault.to_account_info(),
-                self.manager_fee_account.to_account_info(),
-                self.jito_sol_mint.to_account_info(),
-                self.token_program.to_account_info(),
-                self.system_program.to_account_info(),
-            ],
-            signer_seeds,
+        let pool = StakePoolWithdrawAccounts {
+            stake_pool_program: self.stake_pool_program.to_account_info(),
+            jito_stake_pool: self.jito_stake_pool.to_account_info(),
+            stake_pool_withdraw_authority: self.stake_pool_withdraw_authority.to_account_info(),
+            protocol_authority: self.protocol_authority.to_account_info(),
+            protocol_jito_vault: self.protocol_jito_vault.to_account_info(),
+            reserve_stake: self.reserve_stake.to_account_info(),
+            manager_fee_account: self.manager_fee_account.to_account_info(),
+            jito_sol_mint: self.jito_sol_mint.to_account_info(),
+            clock: self.clock.to_account_info(),
+            stake_history: self.stake_history.to_account_info(),
+            stake_program: self.stake_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        };
+        withdraw_pool_sol(
+            &pool,
+            &self.sol_vault.to_account_info(),
+            jito_sol_amount,
+            bumps.protocol_authority,
         )?;
 
         // Calculate estimated SOL received using dynamic APY (reverse of staking calculation)
-        // APY is in basis points (bps): 10000 bps = 100%, so jito_apy_bps + 10000 gives the multiplier
-        let apy_multiplier = 10000_u64 + jito_apy_bps as u64; // e.g., 200 bps = 2% -> 10200
-        let estimated_sol_received = jito_sol_amount
-            .saturating_mul(apy_multiplier)
-            .saturating_div(10000);
-
-        // Update stake account
-        self.stake_account.jito_sol_amount = self
-            .stake_account
-            .jito_sol_amount
-            .checked_sub(jito_sol_amount)
-            .unwrap();
-        self.stake_account.staked_amount = self
-            .stake_account
-            .staked_amount
-            .checked_sub(estimated_sol_received)
-            .unwrap();
-
-        // If no more staked amount, deactivate the stake account
-        if self.stake_account.staked_amount == 0 {
-            self.stake_account.is_active = false;
-        }
+        // e.g., 200 bps = 2% returns 1.02 lamports per JitoSOL base unit
+        let estimated_sol_received = estimate_unstaked_lamports(jito_sol_amount, jito_apy_bps)?;
+
+        // Update stake account; it is deactivated once nothing is left staked
+        self.stake_account
+            .record_unstake(jito_sol_amount, estimated_sol_received)?;
 
         // Update user account
-        self.user_account.staked_sol = self
-            .user_account
-            .staked_sol
-            .checked_sub(estimated_sol_received)
-            .unwrap();
-        self.user_account.deposited_sol = self
-            .user_account
-            .deposited_sol
-            .checked_add(estimated_sol_received)
-            .unwrap();
-
-        msg!(
-            "User {} unstaked {} JitoSOL via pool {} with {:.2}% APY, received ~{} SOL",
+        self.user_account.move_to_deposited(estimated_sol_received)?;
+
+        // move_to_deposited took the same estimate off staked_sol
+        self.global_state
+            .record_unstake(estimated_sol_received, estimated_sol_received)?;
+
+        verbose_msg!(
+            "User {} unstaked {} JitoSOL via pool {} with {}% APY, received ~{} SOL",
             self.user.key(),
-            jito_sol_amount as f64 / 1_000_000_000.0,
+            lamports_to_sol_string(jito_sol_amount),
             self.global_state.jito_stake_pool,
-            jito_apy_bps as f64 / 100.0,
-            estimated_sol_received as f64 / 1_000_000_000.0
+            bps_to_percent_string(u64::from(jito_apy_bps)),
+            lamports_to_sol_string(estimated_sol_received)
         );
 
         Ok(())
@@ -0,0 +1,338 @@
+use crate::{
+    constants::*, error::ErrorCode, events::*, instructions::close_subscription,
+    instructions::release_certificate, state::*, utils::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id, token_interface::TokenInterface,
+};
+
+/// Cancel several token-certificate subscriptions of one user in a single
+/// transaction. Entries are passed as remaining accounts in groups of
+/// UNSUBSCRIBE_BATCH_STRIDE: (user_subscription, subscription_service,
+/// provider_account, certificate mint, certificate token account, certificate
+/// attributes), at most MAX_UNSUBSCRIBES_PER_BATCH of them. Entries that fail
+/// validation are skipped and reported rather than failing the batch.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UnsubscribeBatch<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: Validated against the price feed in GlobalState
+    pub sol_usd_price_feed: AccountInfo<'info>,
+
+    /// Close authority of Token-2022 certificate mints
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
+
+    /// Receives the certificate rent back when it paid for the certificate accounts
+    #[account(
+        mut,
+        seeds = [RENT_SPONSOR_SEED.as_bytes()],
+        bump
+    )]
+    pub rent_sponsor: SystemAccount<'info>,
+
+    /// Every certificate in the batch must belong to this token program
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Outcome of a batch: one UNSUBSCRIBE_REASON_* code per entry, in order, with
+/// UNSUBSCRIBE_REASON_NONE for the entries that were cancelled
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UnsubscribeBatchSummary {
+    pub cancelled: u32,
+    pub skipped: u32,
+    pub unlocked_lamports: u64,
+    pub reasons: Vec<u8>,
+}
+
+/// One validated batch entry
+pub(crate) struct BatchEntry<'info> {
+    pub user_subscription: Account<'info, UserSubscription>,
+    pub subscription_service: Account<'info, SubscriptionService>,
+    pub provider_account: Account<'info, Provider>,
+    pub certificate_attributes: Account<'info, CertificateAttributes>,
+    pub certificate_nft_mint: &'info AccountInfo<'info>,
+    pub certificate_nft_token_account: &'info AccountInfo<'info>,
+}
+
+impl<'info> UnsubscribeBatch<'info> {
+    pub fn unsubscribe_batch(
+        ctx: Context<'_, '_, 'info, 'info, UnsubscribeBatch<'info>>,
+    ) -> Result<UnsubscribeBatchSummary> {
+        let entries = ctx.remaining_accounts;
+        require!(
+            entries
+                .chunks_exact(UNSUBSCRIBE_BATCH_STRIDE)
+                .remainder()
+                .is_empty(),
+            ErrorCode::InvalidBatchLayout
+        );
+        require!(
+            entries.len() <= MAX_UNSUBSCRIBES_PER_BATCH * UNSUBSCRIBE_BATCH_STRIDE,
+            ErrorCode::PageTooLarge
+        );
+
+        let accounts = ctx.accounts;
+        let mut summary = UnsubscribeBatchSummary {
+            cancelled: 0,
+            skipped: 0,
+            unlocked_lamports: 0,
+            reasons: Vec::with_capacity(entries.len() / UNSUBSCRIBE_BATCH_STRIDE),
+        };
+
+        for group in entries.chunks_exact(UNSUBSCRIBE_BATCH_STRIDE) {
+            let reason =
+                if anchor_lang::solana_program::compute_units::sol_remaining_compute_units()
+                    < MIN_COMPUTE_UNITS_PER_UNSUBSCRIBE
+                {
+                    UNSUBSCRIBE_REASON_OUT_OF_COMPUTE
+                } else {
+                    match load_batch_entry(accounts.user.key(), accounts.token_program.key(), group)
+                    {
+                        Ok(mut entry) => {
+                            let unlocked = accounts.cancel_entry(&mut entry, &ctx.bumps)?;
+                            summary.unlocked_lamports = summary
+                                .unlocked_lamports
+                                .checked_add(unlocked)
+                                .ok_or(ErrorCode::ArithmeticOverflow)?;
+                            UNSUBSCRIBE_REASON_NONE
+                        }
+                        Err(reason) => reason,
+                    }
+                };
+
+            if reason == UNSUBSCRIBE_REASON_NONE {
+                summary.cancelled += 1;
+            } else {
+                msg!(
+                    "Skipping subscription {} (reason {})",
+                    group[0].key(),
+                    reason
+                );
+                summary.skipped += 1;
+            }
+            summary.reasons.push(reason);
+        }
+
+        msg!(
+            "Batch unsubscribe: {} cancelled, {} skipped",
+            summary.cancelled,
+            summary.skipped
+        );
+
+        Ok(summary)
+    }
+
+    /// Apply the normal unsubscribe to a validated entry and persist it right away,
+    /// so a later entry sharing its provider sees the updated counters.
+    /// Returns the lamports unlocked
+    fn cancel_entry(
+        &mut self,
+        entry: &mut BatchEntry<'info>,
+        bumps: &UnsubscribeBatchBumps,
+    ) -> Result<u64> {
+        let (current_time, unlocked_lamports, access_ends_at) = close_subscription(
+            self.user.key(),
+            &mut self.user_account,
+            &mut entry.subscription_service,
+            &mut entry.provider_account,
+            &mut entry.user_subscription,
+            &mut self.global_state,
+            &self.sol_usd_price_feed,
+        )?;
+
+        // Access now ends with the current paid period
+        let rent_sponsored = entry.certificate_attributes.rent_sponsored;
+        entry.certificate_attributes.paid_through = access_ends_at;
+        entry.certificate_attributes.updated_at = current_time;
+
+        let rent_destination = if rent_sponsored {
+            self.rent_sponsor.to_account_info()
+        } else {
+            self.user.to_account_info()
+        };
+        release_certificate(
+            &self.user,
+            entry.certificate_nft_mint,
+            entry.certificate_nft_token_account,
+            &self.certificate_authority,
+            rent_destination,
+            &self.token_program,
+            bumps.certificate_authority,
+            entry.subscription_service.deactivated_by_admin,
+        )?;
+
+        entry.user_subscription.exit(&crate::ID)?;
+        entry.subscription_service.exit(&crate::ID)?;
+        entry.provider_account.exit(&crate::ID)?;
+        entry.certificate_attributes.exit(&crate::ID)?;
+
+        // Unsubscribing never refunds already-paid periods, it only unlocks collateral
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionCancelled {
+                user: self.user.key(),
+                provider: entry.user_subscription.provider,
+                service_id: entry.user_subscription.service_id,
+                prorated_refund_lamports: 0,
+                unlocked_lamports,
+                effective_at: access_ends_at,
+            },
+        )?;
+
+        Ok(unlocked_lamports)
+    }
+}
+
+/// Load and validate one entry of `user`'s, laid out as in unsubscribe_batch with a
+/// certificate of `token_program`, or the reason it has to be skipped
+pub(crate) fn load_batch_entry<'info>(
+    user: Pubkey,
+    token_program: Pubkey,
+    group: &'info [AccountInfo<'info>],
+) -> std::result::Result<BatchEntry<'info>, u8> {
+    let [subscription_info, service_info, provider_info, mint_info, token_account_info, attributes_info] =
+        group
+    else {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    };
+    if ![
+        subscription_info,
+        service_info,
+        provider_info,
+        mint_info,
+        token_account_info,
+        attributes_info,
+    ]
+    .iter()
+    .all(|info| info.is_writable)
+    {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    }
+
+    // Canonical, current-version subscription PDA of this user
+    let user_subscription =
+        Account::<UserSubscription>::try_from(subscription_info).map_err(invalid)?;
+    let provider = user_subscription.provider;
+    let service_id_bytes = user_subscription.service_id.to_le_bytes();
+    let subscription_address = Pubkey::create_program_address(
+        &[
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user.as_ref(),
+            provider.as_ref(),
+            service_id_bytes.as_ref(),
+            &[user_subscription.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(invalid)?;
+    if subscription_address != subscription_info.key() || !user_subscription.is_current_version() {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    }
+    match user_subscription.batch_unsubscribe_skip_reason(&user) {
+        UNSUBSCRIBE_REASON_NONE => {}
+        reason => return Err(reason),
+    }
+
+    let subscription_service =
+        Account::<SubscriptionService>::try_from(service_info).map_err(invalid)?;
+    let service_address = Pubkey::create_program_address(
+        &[
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            service_id_bytes.as_ref(),
+            &[subscription_service.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(invalid)?;
+    if service_address != service_info.key() || !subscription_service.is_current_version() {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    }
+
+    let provider_account = Account::<Provider>::try_from(provider_info).map_err(invalid)?;
+    let provider_address = Pubkey::create_program_address(
+        &[
+            PROVIDER_SEED.as_bytes(),
+            provider.as_ref(),
+            &[provider_account.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(invalid)?;
+    if provider_address != provider_info.key() || !provider_account.is_current_version() {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    }
+
+    let (mint_address, _) = Pubkey::find_program_address(
+        &[
+            CERTIFICATE_SEED.as_bytes(),
+            user.as_ref(),
+            provider.as_ref(),
+            service_id_bytes.as_ref(),
+        ],
+        &crate::ID,
+    );
+    let token_account_address =
+        get_associated_token_address_with_program_id(&user, &mint_address, &token_program);
+    if mint_address != mint_info.key() || token_account_address != token_account_info.key() {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    }
+
+    // Subscriptions that predate certificate attributes go through unsubscribe_from_service,
+    // which creates them
+    let certificate_attributes =
+        Account::<CertificateAttributes>::try_from(attributes_info).map_err(invalid)?;
+    let attributes_address = Pubkey::create_program_address(
+        &[
+            CERTIFICATE_ATTRIBUTES_SEED.as_bytes(),
+            mint_address.as_ref(),
+            &[certificate_attributes.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(invalid)?;
+    if attributes_address != attributes_info.key() {
+        return Err(UNSUBSCRIBE_REASON_INVALID_ACCOUNTS);
+    }
+
+    Ok(BatchEntry {
+        user_subscription,
+        subscription_service,
+        provider_account,
+        certificate_attributes,
+        certificate_nft_mint: mint_info,
+        certificate_nft_token_account: token_account_info,
+    })
+}
+
+/// Any failure to load or match an entry account skips the entry
+fn invalid<E>(_: E) -> u8 {
+    UNSUBSCRIBE_REASON_INVALID_ACCOUNTS
+}
@@ -1,11 +1,17 @@
-use crate::{constants::*, error::ErrorCode, state::*};
+use crate::{
+    constants::*, error::ErrorCode, events::*, instructions::refund_sponsor_escrow, state::*,
+    utils::*,
+};
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{burn, Burn, Mint, Token, TokenAccount},
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_2022,
+    token_interface::{
+        burn, close_account, thaw_account, Burn, CloseAccount, ThawAccount, TokenInterface,
+    },
 };
-use pyth_sdk_solana::state::SolanaPriceAccount;
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(provider: Pubkey, service_id: u64)]
 pub struct UnsubscribeFromService<'info> {
@@ -16,7 +22,8 @@ pub struct UnsubscribeFromService<'info> {
         mut,
         seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
         bump = user_account.bump,
-        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_account: Account<'info, User>,
 
@@ -28,10 +35,11 @@ pub struct UnsubscribeFromService<'info> {
             provider.as_ref(),
             service_id.to_le_bytes().as_ref()
         ],
-        bump = user_subscription.bumps,
+        bump = user_subscription.bump,
         constraint = user_subscription.user == user.key() @ ErrorCode::UnauthorizedUser,
         constraint = user_subscription.provider == provider @ ErrorCode::InvalidProvider,
-        constraint = user_subscription.service_id == service_id @ ErrorCode::InvalidServiceId
+        constraint = user_subscription.service_id == service_id @ ErrorCode::InvalidServiceId,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub user_subscription: Account<'info, UserSubscription>,
 
@@ -42,21 +50,24 @@ pub struct UnsubscribeFromService<'info> {
             provider.as_ref(),
             service_id.to_le_bytes().as_ref()
         ],
-        bump
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub subscription_service: Account<'info, SubscriptionService>,
 
     #[account(
         mut,
         seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
-        bump
+        bump = provider_account.bump,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub provider_account: Account<'info, Provider>,
 
     #[account(
         mut,
         seeds = [b"global_state"],
-        bump = global_state.bump
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
     )]
     pub global_state: Account<'info, GlobalState>,
 
@@ -64,214 +75,432 @@ pub struct UnsubscribeFromService<'info> {
     /// CHECK: This account is validated in the instruction method to match the price feed in GlobalState
     pub sol_usd_price_feed: AccountInfo<'info>,
 
-    // Subscription certificate NFT to burn
-    #[account(mut)]
-    pub certificate_nft_mint: Account<'info, Mint>,
-
+    // Subscription certificate NFT to burn. Both accounts may already be closed,
+    // and either token program is accepted: certificates keep the program they were minted with
+    /// CHECK: Address is checked by seeds; owner is checked against token_program when it exists
     #[account(
         mut,
-        associated_token::mint = certificate_nft_mint,
-        associated_token::authority = user,
-        constraint = certificate_nft_token_account.amount > 0 @ ErrorCode::NoCertificateToDestroy
+        seeds = [
+            CERTIFICATE_SEED.as_bytes(),
+            user.key().as_ref(),
+            provider.as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump
     )]
-    pub certificate_nft_token_account: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-}
-
-impl<'info> UnsubscribeFromService<'info> {
-    pub fn unsubscribe_from_service(&mut self, _provider: Pubkey, _service_id: u64) -> Result<()> {
-        require!(!self.global_state.is_paused, ErrorCode::ProtocolPaused);
-
-        // Verify the Pyth price feed account matches the one in GlobalState
-        require!(
-            self.sol_usd_price_feed.key() == self.global_state.sol_usd_price_feed,
-            ErrorCode::InvalidPriceFeed
-        );
-
-        let user_subscription = &mut self.user_subscription;
-        let user_account = &mut self.user_account;
-        let subscription_service = &mut self.subscription_service;
-        let provider_account = &mut self.provider_account;
+    pub certificate_nft_mint: AccountInfo<'info>,
 
-        // Validate that the subscription is active
-        require!(
-            user_subscription.is_active,
-            ErrorCode::SubscriptionNotActive
-        );
-
-        // Calculate the amount to unlock (remaining subscription fees)
-        let current_time = Clock::get()?.unix_timestamp;
-        let subscription_days = subscription_service.billing_frequency_days;
-        let seconds_per_day = 86400i64;
-        let billing_period_seconds = subscription_days as i64 * seconds_per_day;
-
-        // Calculate how much time is left in current billing period
-        let time_since_subscription = current_time - user_subscription.subscribed_at;
-        let _full_periods_passed = time_since_subscription / billing_period_seconds;
-        let time_in_current_period = time_since_subscription % billing_period_seconds;
-        let _remaining_time_in_period = billing_period_seconds - time_in_current_period;
+    /// CHECK: Must be the user's associated token account for the certificate mint
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &user.key(),
+            &certificate_nft_mint.key(),
+            &token_program.key()
+        )
+    )]
+    pub certificate_nft_token_account: AccountInfo<'info>,
 
-        // Get real SOL/USD price from Pyth
-        let sol_usd_price_cents = Self::get_sol_usd_price_from_pyth(&self.sol_usd_price_feed)?;
+    // Created here for subscriptions that predate certificate attributes
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + CertificateAttributes::INIT_SPACE,
+        seeds = [
+            CERTIFICATE_ATTRIBUTES_SEED.as_bytes(),
+            certificate_nft_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub certificate_attributes: Account<'info, CertificateAttributes>,
 
-        // Calculate monthly fee in lamports using real Pyth price
-        let monthly_fee_lamports =
-            Self::convert_usd_to_sol_lamports(subscription_service.fee_usd, sol_usd_price_cents)?;
+    /// Close authority of Token-2022 certificate mints
+    /// CHECK: PDA with no data, only used as an authority
+    #[account(
+        seeds = [CERTIFICATE_AUTHORITY_SEED.as_bytes()],
+        bump
+    )]
+    pub certificate_authority: AccountInfo<'info>,
 
-        // Calculate how much SOL to unlock
-        // Unlock all remaining locked funds for this subscription (since user is canceling)
-        let locked_amount_for_subscription = monthly_fee_lamports
-            .checked_mul(12)
-            .ok_or(ErrorCode::ArithmeticOverflow)?; // We locked 12 months initially
+    /// Receives the certificate rent back when it paid for the certificate accounts
+    #[account(
+        mut,
+        seeds = [RENT_SPONSOR_SEED.as_bytes()],
+        bump
+    )]
+    pub rent_sponsor: SystemAccount<'info>,
 
-        // Free up locked SOL
-        user_account.locked_sol = user_account
-            .locked_sol
-            .checked_sub(locked_amount_for_subscription)
-            .unwrap_or(0);
-
-        // Burn the subscription certificate NFT
-        let cpi_accounts = Burn {
-            mint: self.certificate_nft_mint.to_account_info(),
-            from: self.certificate_nft_token_account.to_account_info(),
-            authority: self.user.to_account_info(),
-        };
-        let cpi_program = self.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        burn(cpi_ctx, 1)?;
+    /// Sponsor escrow for this subscription, refunded on cancellation
+    #[account(
+        mut,
+        seeds = [SPONSOR_ESCROW_SEED.as_bytes(), user_subscription.key().as_ref()],
+        bump
+    )]
+    pub sponsor_escrow: SystemAccount<'info>,
 
-        // Deactivate subscription
-        user_subscription.is_active = false;
-        user_subscription.unsubscribed_at = Some(current_time);
+    /// CHECK: Receives the escrow refund; required when the subscription is sponsored
+    /// and checked against user_subscription.sponsor
+    #[account(mut)]
+    pub sponsor: Option<UncheckedAccount<'info>>,
 
-        // Update counters
-        subscription_service.current_subscribers =
-            subscription_service.current_subscribers.saturating_sub(1);
-        provider_account.total_subscribers = provider_account.total_subscribers.saturating_sub(1);
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
 
+/// Cancellation bookkeeping shared by the token and compressed certificate paths:
+/// unlocks the collateral, deactivates the subscription and updates the counters.
+/// Returns (current_time, unlocked_lamports, access_ends_at).
+pub(crate) fn close_subscription(
+    user: Pubkey,
+    user_account: &mut User,
+    subscription_service: &mut SubscriptionService,
+    provider_account: &mut Provider,
+    user_subscription: &mut UserSubscription,
+    global_state: &mut GlobalState,
+    sol_usd_price_feed: &AccountInfo,
+) -> Result<(i64, u64, i64)> {
+    // Verify the Pyth price feed account matches the one in GlobalState
+    require!(
+        sol_usd_price_feed.key() == global_state.sol_usd_price_feed,
+        ErrorCode::InvalidPriceFeed
+    );
+
+    // Validate that the subscription is active
+    require!(
+        user_subscription.is_active,
+        ErrorCode::SubscriptionNotActive
+    );
+
+    // Calculate the amount to unlock (remaining subscription fees)
+    let current_time = Clock::get()?.unix_timestamp;
+    let subscription_days = subscription_service.billing_frequency_days;
+    let seconds_per_day = 86400i64;
+    let billing_period_seconds = subscription_days as i64 * seconds_per_day;
+
+    // Calculate how much time is left in current billing period
+    let time_since_subscription = current_time
+        .checked_sub(user_subscription.subscribed_at)
+        .ok_or(ErrorCode::ArithmeticUnderflow)?;
+    let _full_periods_passed = time_since_subscription / billing_period_seconds;
+    let time_in_current_period = time_since_subscription % billing_period_seconds;
+    let _remaining_time_in_period = billing_period_seconds - time_in_current_period;
+
+    // Unlock all remaining locked funds for this subscription (since user is canceling).
+    // A USDC lock was recorded on the subscription and is released exactly
+    let locked_before = user_account.locked_sol;
+    if user_subscription.locked_usdc > 0 {
+        user_account.release_locked_usdc(user_subscription.locked_usdc)?;
         msg!(
-            "User {} successfully unsubscribed from service '{}' (Provider: {})",
-            self.user.key(),
-            subscription_service.name,
-            user_subscription.provider
+            "Unlocked {} USDC base units from subscription",
+            user_subscription.locked_usdc
         );
+        user_subscription.locked_usdc = 0;
+    } else if user_subscription.locked_sol > 0 {
+        // Recorded when subscribing, so later lock_periods changes and price moves
+        // do not change what comes back. Capped in case an emergency withdrawal
+        // already cleared the user's locks
+        let locked_sol = std::mem::take(&mut user_subscription.locked_sol);
+        let unlocked = locked_sol.min(user_account.locked_sol);
+        user_account.release_locked(unlocked)?;
+        msg!("Unlocked {} lamports from subscription", unlocked);
+    } else {
+        // Subscribed before the lock was recorded, so recompute it from the
+        // real SOL/USD price from Pyth. A way out skips the circuit breaker and
+        // leaves its reference alone: the unlock is capped at what the user has
+        // locked, so a tripped breaker must not keep them subscribed
+        let sol_usd_price_cents =
+            sol_usd_price_cents_or_override_at(sol_usd_price_feed, global_state, current_time)?;
+
+        // Computed the same way subscribe locked them
+        let locked_amount_for_subscription = subscription_unlock_lamports(
+            subscription_service.fee_usd,
+            sol_usd_price_cents,
+            user_account.locked_sol,
+        )?;
 
+        // Free up locked SOL
+        user_account.release_locked(locked_amount_for_subscription)?;
         msg!(
-            "Unlocked {} lamports from subscription. Certificate NFT burned: {}",
-            locked_amount_for_subscription,
-            self.certificate_nft_mint.key()
+            "Unlocked {} lamports from subscription",
+            locked_amount_for_subscription
         );
-
-        // Check if less than one month has passed since last payment for prorated access
-        if let Some(last_payment) = user_subscription.last_payment_at {
-            let time_since_payment = current_time - last_payment;
-            if time_since_payment < billing_period_seconds {
-                msg!(
-                    "User retains access until next billing cycle ({} days remaining)",
-                    (billing_period_seconds - time_since_payment) / seconds_per_day
-                );
-            }
-        } else {
-            // First billing period - user retains access until next payment would be due
-            let time_until_next_payment = user_subscription.next_payment_due - current_time;
-            if time_until_next_payment > 0 {
-                msg!(
-                    "User retains access until next billing cycle ({} days remaining)",
-                    time_until_next_payment / seconds_per_day
-                );
-            }
-        }
-
-        Ok(())
     }
 
-    /// Get SOL/USD price from Pyth Network - REAL IMPLEMENTATION
-    fn get_sol_usd_price_from_pyth(price_feed_account: &AccountInfo) -> Result<u64> {
-        // Load price feed from Pyth account using the correct API
-        let price_feed = SolanaPriceAccount::account_info_to_feed(price_feed_account)
-            .map_err(|_| ErrorCode::InvalidPriceFeed)?;
-
-        // Get current price with staleness check
-        let current_time = Clock::get()?.unix_timestamp;
-        let max_age = 3600; // 1 hour in seconds
+    let access_ends_at = deactivate_subscription(
+        subscription_service,
+        provider_account,
+        user_subscription,
+        global_state,
+        current_time,
+    )?;
+
+    msg!(
+        "User {} successfully unsubscribed from service '{}' (Provider: {})",
+        user,
+        subscription_service.name,
+        user_subscription.provider
+    );
+
+    let unlocked_lamports = locked_before
+        .checked_sub(user_account.locked_sol)
+        .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+    Ok((current_time, unlocked_lamports, access_ends_at))
+}
 
-        let price = price_feed
-            .get_price_no_older_than(current_time, max_age)
-            .ok_or(ErrorCode::PriceNotAvailable)?;
+/// Deactivate the subscription and take it off the service, provider and protocol
+/// counters, leaving its collateral to the caller. Returns when access ends: the
+/// end of the period already paid for, or `current_time` when none is left
+pub(crate) fn deactivate_subscription(
+    subscription_service: &mut SubscriptionService,
+    provider_account: &mut Provider,
+    user_subscription: &mut UserSubscription,
+    global_state: &mut GlobalState,
+    current_time: i64,
+) -> Result<i64> {
+    let seconds_per_day = 86400i64;
+    let billing_period_seconds =
+        subscription_service.billing_frequency_days as i64 * seconds_per_day;
+
+    // Deactivate subscription
+    user_subscription.is_active = false;
+    user_subscription.unsubscribed_at = Some(current_time);
+
+    // Update counters
+    subscription_service.current_subscribers = subscription_service
+        .current_subscribers
+        .checked_sub(1)
+        .ok_or(ErrorCode::ArithmeticUnderflow)?;
+    provider_account.total_subscribers = provider_account
+        .total_subscribers
+        .checked_sub(1)
+        .ok_or(ErrorCode::ArithmeticUnderflow)?;
+    global_state.record_subscription_closed();
+
+    // Check if less than one month has passed since last payment for prorated access
+    let mut access_ends_at = current_time;
+    if let Some(last_payment) = user_subscription.last_payment_at {
+        let time_since_payment = current_time
+            .checked_sub(last_payment)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        if time_since_payment < billing_period_seconds {
+            access_ends_at = last_payment + billing_period_seconds;
+            msg!(
+                "User retains access until next billing cycle ({} days remaining)",
+                (billing_period_seconds - time_since_payment) / seconds_per_day
+            );
+        }
+    } else {
+        // First billing period - user retains access until next payment would be due
+        let time_until_next_payment = user_subscription.next_payment_due - current_time;
+        if time_until_next_payment > 0 {
+            access_ends_at = user_subscription.next_payment_due;
+            msg!(
+                "User retains access until next billing cycle ({} days remaining)",
+                time_until_next_payment / seconds_per_day
+            );
+        }
+    }
 
-        // Validate price data
-        require!(price.price > 0, ErrorCode::InvalidPrice);
+    Ok(access_ends_at)
+}
 
-        msg!(
-            "Pyth price data: price={}, conf={}, expo={}, timestamp={}",
-            price.price,
-            price.conf,
-            price.expo,
-            price.publish_time
+impl<'info> UnsubscribeFromService<'info> {
+    pub fn unsubscribe_from_service(
+        &mut self,
+        _provider: Pubkey,
+        _service_id: u64,
+        bumps: &UnsubscribeFromServiceBumps,
+    ) -> Result<()> {
+        // Compressed certificates are released through unsubscribe_from_service_compressed
+        require!(
+            self.user_subscription.certificate_asset_id == Pubkey::default(),
+            ErrorCode::InvalidCertificateMode
         );
 
-        // Convert price to USD cents
-        // Pyth SOL/USD typically has exponent of -8, meaning price is in units of 10^-8 USD
-        let price_cents = if price.expo >= 0 {
-            // Positive exponent: multiply
-            (price.price as u64)
-                .checked_mul(10_u64.pow(price.expo as u32))
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_mul(100) // Convert to cents
-                .ok_or(ErrorCode::ArithmeticOverflow)?
+        let (current_time, unlocked_lamports, access_ends_at) = close_subscription(
+            self.user.key(),
+            &mut self.user_account,
+            &mut self.subscription_service,
+            &mut self.provider_account,
+            &mut self.user_subscription,
+            &mut self.global_state,
+            &self.sol_usd_price_feed,
+        )?;
+
+        // Unused sponsor funds go back to the sponsor, never to the subscriber
+        let sponsor_key = self.user_subscription.sponsor;
+        let subscription_key = self.user_subscription.key();
+        let refunded = refund_sponsor_escrow(
+            &mut self.user_subscription,
+            &self.sponsor_escrow.to_account_info(),
+            self.sponsor.as_ref().map(|sponsor| sponsor.as_ref()),
+            &self.system_program.to_account_info(),
+            subscription_key,
+            bumps.sponsor_escrow,
+        )?;
+
+        // Access now ends with the current paid period
+        let rent_sponsored = self.certificate_attributes.rent_sponsored;
+        self.certificate_attributes.set_inner(CertificateAttributes {
+            mint: self.certificate_nft_mint.key(),
+            paid_through: access_ends_at,
+            updated_at: current_time,
+            rent_sponsored,
+            bump: bumps.certificate_attributes,
+        });
+
+        // Burn the subscription certificate NFT and reclaim its rent
+        let rent_destination = if rent_sponsored {
+            self.rent_sponsor.to_account_info()
         } else {
-            // Negative exponent: divide
-            let divisor = 10_u64.pow((-price.expo) as u32);
-            (price.price as u64)
-                .checked_mul(100) // Convert to cents first
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(divisor)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
+            self.user.to_account_info()
         };
+        release_certificate(
+            &self.user,
+            &self.certificate_nft_mint,
+            &self.certificate_nft_token_account,
+            &self.certificate_authority,
+            rent_destination,
+            &self.token_program,
+            bumps.certificate_authority,
+            self.subscription_service.deactivated_by_admin,
+        )?;
+
+        if refunded > 0 {
+            emit_cpi_event(
+                &self.event_authority,
+                bumps.event_authority,
+                SponsorEscrowRefunded {
+                    sponsor: sponsor_key,
+                    user: self.user.key(),
+                    provider: self.user_subscription.provider,
+                    service_id: self.user_subscription.service_id,
+                    amount: refunded,
+                },
+            )?;
+        }
 
-        // Validate reasonable price range ($10 - $1000 per SOL)
-        require!(
-            price_cents >= 1000 && price_cents <= 100000,
-            ErrorCode::InvalidPrice
-        );
+        // Emitted last so the event only ever reflects fully applied state.
+        // Unsubscribing never refunds already-paid periods, it only unlocks collateral.
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SubscriptionCancelled {
+                user: self.user.key(),
+                provider: self.user_subscription.provider,
+                service_id: self.user_subscription.service_id,
+                prorated_refund_lamports: 0,
+                unlocked_lamports,
+                effective_at: access_ends_at,
+            },
+        )?;
+
+        Ok(())
+    }
+}
 
+/// Thaw, burn and close the certificate token account, returning the rent to
+/// `rent_destination`: the rent sponsor for sponsored certificates, otherwise the user.
+/// Token-2022 certificate mints carry the certificate authority as close authority
+/// and are closed too; classic SPL mints cannot be closed and are reused on re-subscription.
+/// Accounts that are already closed are skipped so the flow is safe to retry.
+/// With `allow_missing_certificate`, set for services the authority deactivated,
+/// a subscriber who no longer holds the certificate is released without burning it
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn release_certificate<'info>(
+    user: &AccountInfo<'info>,
+    certificate_nft_mint: &AccountInfo<'info>,
+    certificate_nft_token_account: &AccountInfo<'info>,
+    certificate_authority: &AccountInfo<'info>,
+    rent_destination: AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    certificate_authority_bump: u8,
+    allow_missing_certificate: bool,
+) -> Result<()> {
+    if certificate_nft_mint.data_is_empty() {
+        msg!("Certificate mint already closed");
+        return Ok(());
+    }
+    require!(
+        certificate_nft_mint.owner == token_program.key,
+        ErrorCode::InvalidCertificateTokenProgram
+    );
+
+    let authority_seeds: &[&[u8]] = &[
+        CERTIFICATE_AUTHORITY_SEED.as_bytes(),
+        &[certificate_authority_bump],
+    ];
+
+    let token_account = load_token_account(certificate_nft_token_account)?;
+    // Whoever holds the certificate now keeps it: the burn needs the holder's
+    // signature and a Token-2022 mint only closes once its supply is gone
+    let held = matches!(&token_account, Some(account) if account.amount > 0);
+    if allow_missing_certificate && !held {
         msg!(
-            "Real SOL/USD price from Pyth: ${:.2} (account: {})",
-            price_cents as f64 / 100.0,
-            price_feed_account.key()
+            "Certificate {} no longer held by the subscriber, left in place",
+            certificate_nft_mint.key()
         );
-
-        Ok(price_cents)
+        return Ok(());
     }
 
-    /// Convert USD cents to SOL lamports using real Pyth price
-    fn convert_usd_to_sol_lamports(usd_cents: u64, sol_usd_price_cents: u64) -> Result<u64> {
-        // Calculate SOL lamports needed for the USD amount
-        // usd_cents / (sol_usd_price_cents / 100) * LAMPORTS_PER_SOL
-        // = (usd_cents * 100 * LAMPORTS_PER_SOL) / sol_usd_price_cents
+    if let Some(token_account) = token_account {
+        // Classic certificates are frozen to the subscriber and must be thawed to burn
+        if token_account.is_frozen() {
+            thaw_account(CpiContext::new_with_signer(
+                token_program.clone(),
+                ThawAccount {
+                    account: certificate_nft_token_account.clone(),
+                    mint: certificate_nft_mint.clone(),
+                    authority: certificate_authority.clone(),
+                },
+                &[authority_seeds],
+            ))?;
+        }
 
-        const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+        if token_account.amount > 0 {
+            burn(
+                CpiContext::new(
+                    token_program.clone(),
+                    Burn {
+                        mint: certificate_nft_mint.clone(),
+                        from: certificate_nft_token_account.clone(),
+                        authority: user.clone(),
+                    },
+                ),
+                token_account.amount,
+            )?;
+        }
 
-        let numerator = usd_cents
-            .checked_mul(100)
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_mul(LAMPORTS_PER_SOL)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        close_account(CpiContext::new(
+            token_program.clone(),
+            CloseAccount {
+                account: certificate_nft_token_account.clone(),
+                destination: rent_destination.clone(),
+                authority: user.clone(),
+            },
+        ))?;
+    } else {
+        msg!("Certificate token account already closed");
+    }
 
-        let sol_lamports = numerator
-            .checked_div(sol_usd_price_cents)
-            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    if token_program.key() == token_2022::ID {
+        close_account(CpiContext::new_with_signer(
+            token_program.clone(),
+            CloseAccount {
+                account: certificate_nft_mint.clone(),
+                destination: rent_destination,
+                authority: certificate_authority.clone(),
+            },
+            &[authority_seeds],
+        ))?;
+    }
 
-        msg!(
-            "Converting ${:.2} to {} lamports at ${:.2}/SOL",
-            usd_cents as f64 / 100.0,
-            sol_lamports,
-            sol_usd_price_cents as f64 / 100.0
-        );
+    msg!(
+        "Certificate NFT burned and closed: {}",
+        certificate_nft_mint.key()
+    );
 
-        Ok(sol_lamports)
-    }
+    Ok(())
 }
@@ -0,0 +1,216 @@
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::*,
+    instructions::{close_subscription, refund_sponsor_escrow},
+    state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+
+/// Cancel a subscription whose certificate is a compressed (Bubblegum) NFT.
+/// The Merkle proof nodes for the leaf are passed via remaining accounts.
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(provider: Pubkey, service_id: u64)]
+pub struct UnsubscribeFromServiceCompressed<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            user.key().as_ref(),
+            provider.as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump = user_subscription.bump,
+        constraint = user_subscription.user == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_subscription.certificate_asset_id != Pubkey::default()
+            @ ErrorCode::InvalidCertificateMode,
+        constraint = user_subscription.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_subscription: Account<'info, UserSubscription>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), provider.as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// CHECK: This account is validated in close_subscription to match the price feed in GlobalState
+    pub sol_usd_price_feed: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum tree config, checked by seeds and by the Bubblegum program
+    #[account(
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = BUBBLEGUM_PROGRAM_ID
+    )]
+    pub tree_config: AccountInfo<'info>,
+
+    /// CHECK: Tree holding the certificate leaf, verified by the Bubblegum burn
+    #[account(mut)]
+    pub merkle_tree: AccountInfo<'info>,
+
+    /// CHECK: Bubblegum program
+    #[account(address = BUBBLEGUM_PROGRAM_ID)]
+    pub bubblegum_program: AccountInfo<'info>,
+
+    /// CHECK: SPL Noop program used by Bubblegum for leaf logs
+    #[account(address = SPL_NOOP_PROGRAM_ID)]
+    pub log_wrapper: AccountInfo<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = SPL_ACCOUNT_COMPRESSION_PROGRAM_ID)]
+    pub compression_program: AccountInfo<'info>,
+
+    /// Sponsor escrow for this subscription, refunded on cancellation
+    #[account(
+        mut,
+        seeds = [SPONSOR_ESCROW_SEED.as_bytes(), user_subscription.key().as_ref()],
+        bump
+    )]
+    pub sponsor_escrow: SystemAccount<'info>,
+
+    /// CHECK: Receives the escrow refund; required when the subscription is sponsored
+    /// and checked against user_subscription.sponsor
+    #[account(mut)]
+    pub sponsor: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UnsubscribeFromServiceCompressed<'info> {
+    pub fn unsubscribe_from_service_compressed(
+        ctx: Context<'_, '_, '_, 'info, UnsubscribeFromServiceCompressed<'info>>,
+        leaf: CompressedLeafProof,
+    ) -> Result<()> {
+        let accounts = ctx.accounts;
+
+        // The leaf must be the certificate recorded at subscribe time
+        let (asset_id, _) = Pubkey::find_program_address(
+            &[
+                b"asset",
+                accounts.merkle_tree.key().as_ref(),
+                &leaf.nonce.to_le_bytes(),
+            ],
+            &BUBBLEGUM_PROGRAM_ID,
+        );
+        require_keys_eq!(
+            asset_id,
+            accounts.user_subscription.certificate_asset_id,
+            ErrorCode::InvalidCertificateMode
+        );
+
+        let (_current_time, unlocked_lamports, access_ends_at) = close_subscription(
+            accounts.user.key(),
+            &mut accounts.user_account,
+            &mut accounts.subscription_service,
+            &mut accounts.provider_account,
+            &mut accounts.user_subscription,
+            &mut accounts.global_state,
+            &accounts.sol_usd_price_feed,
+        )?;
+
+        // Unused sponsor funds go back to the sponsor, never to the subscriber
+        let sponsor_key = accounts.user_subscription.sponsor;
+        let subscription_key = accounts.user_subscription.key();
+        let refunded = refund_sponsor_escrow(
+            &mut accounts.user_subscription,
+            &accounts.sponsor_escrow.to_account_info(),
+            accounts.sponsor.as_ref().map(|sponsor| sponsor.as_ref()),
+            &accounts.system_program.to_account_info(),
+            subscription_key,
+            ctx.bumps.sponsor_escrow,
+        )?;
+
+        if accounts.subscription_service.deactivated_by_admin {
+            // Only the current leaf owner can burn, and a subscriber who passed the
+            // certificate on must still get out of a service the authority ended
+            msg!(
+                "Service deactivated by the authority, compressed certificate {} left in place",
+                accounts.user_subscription.certificate_asset_id
+            );
+        } else {
+            bubblegum_burn(
+                &accounts.bubblegum_program,
+                &accounts.tree_config,
+                &accounts.user.to_account_info(),
+                &accounts.merkle_tree,
+                &accounts.log_wrapper,
+                &accounts.compression_program,
+                &accounts.system_program.to_account_info(),
+                ctx.remaining_accounts,
+                &leaf,
+            )?;
+
+            msg!(
+                "Compressed certificate burned: {}",
+                accounts.user_subscription.certificate_asset_id
+            );
+        }
+        accounts.user_subscription.certificate_asset_id = Pubkey::default();
+
+        if refunded > 0 {
+            emit_cpi_event(
+                &accounts.event_authority,
+                ctx.bumps.event_authority,
+                SponsorEscrowRefunded {
+                    sponsor: sponsor_key,
+                    user: accounts.user.key(),
+                    provider: accounts.user_subscription.provider,
+                    service_id: accounts.user_subscription.service_id,
+                    amount: refunded,
+                },
+            )?;
+        }
+
+        emit_cpi_event(
+            &accounts.event_authority,
+            ctx.bumps.event_authority,
+            SubscriptionCancelled {
+                user: accounts.user.key(),
+                provider: accounts.user_subscription.provider,
+                service_id: accounts.user_subscription.service_id,
+                prorated_refund_lamports: 0,
+                unlocked_lamports,
+                effective_at: access_ends_at,
+            },
+        )?;
+
+        Ok(())
+    }
+}
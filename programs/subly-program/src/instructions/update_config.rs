@@ -0,0 +1,66 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    /// Rotate the oracle, Jito and USDC addresses set at initialize, e.g. when
+    /// Pyth migrates a feed or Jito deploys a new pool. Only the fields passed
+    /// are overwritten. Every instruction checks these accounts against
+    /// GlobalState, so the change applies from the next transaction; balances
+    /// already held in the old JitoSOL or USDC mint are not moved. Once a config
+    /// timelock is set each address has to be queued instead
+    pub fn update_config(
+        &mut self,
+        sol_usd_price_feed: Option<Pubkey>,
+        jito_stake_pool: Option<Pubkey>,
+        jito_sol_mint: Option<Pubkey>,
+        spl_stake_pool_program: Option<Pubkey>,
+        usdc_mint: Option<Pubkey>,
+        bumps: &UpdateConfigBumps,
+    ) -> Result<()> {
+        self.global_state.require_no_config_timelock()?;
+        let updates = [
+            (CONFIG_FIELD_PRICE_FEED, sol_usd_price_feed),
+            (CONFIG_FIELD_JITO_STAKE_POOL, jito_stake_pool),
+            (CONFIG_FIELD_JITO_SOL_MINT, jito_sol_mint),
+            (CONFIG_FIELD_SPL_STAKE_POOL_PROGRAM, spl_stake_pool_program),
+            (CONFIG_FIELD_USDC_MINT, usdc_mint),
+        ];
+
+        for (field, address) in updates {
+            let Some(address) = address else {
+                continue;
+            };
+            let old_value = self.global_state.set_config_address(field, address)?;
+
+            msg!(
+                "Config field {} changed from {} to {}",
+                field,
+                old_value,
+                address
+            );
+
+            emit_cpi_event(
+                &self.event_authority,
+                bumps.event_authority,
+                ConfigChanged::new(field, &old_value, &address, self.authority.key())?,
+            )?;
+        }
+
+        Ok(())
+    }
+}
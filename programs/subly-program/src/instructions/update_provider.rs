@@ -0,0 +1,74 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdateProvider<'info> {
+    pub provider: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED.as_bytes(), provider.key().as_ref()],
+        bump = provider_account.bump,
+        constraint = provider_account.wallet == provider.key() @ ErrorCode::UnauthorizedProvider,
+        constraint = provider_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub provider_account: Account<'info, Provider>,
+}
+
+impl<'info> UpdateProvider<'info> {
+    pub fn update_provider(
+        &mut self,
+        name: Option<String>,
+        description: Option<String>,
+        bumps: &UpdateProviderBumps,
+    ) -> Result<()> {
+        self.global_state.require_not_paused()?;
+
+        if name.is_some() || description.is_some() {
+            require_full_layout(
+                &self.provider_account.to_account_info(),
+                8 + Provider::INIT_SPACE,
+            )?;
+        }
+
+        let provider_account = &mut self.provider_account;
+        let mut changed_fields_bitmap: u8 = 0;
+
+        if let Some(name) = name {
+            validate_name(&name)?;
+            provider_account.name = name;
+            changed_fields_bitmap |= PROVIDER_FIELD_NAME;
+        }
+
+        if let Some(description) = description {
+            validate_description(&description)?;
+            provider_account.description = description;
+            changed_fields_bitmap |= PROVIDER_FIELD_DESCRIPTION;
+        }
+
+        msg!(
+            "Provider {} updated (changed fields bitmap: {})",
+            self.provider.key(),
+            changed_fields_bitmap
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ProviderUpdated {
+                wallet: self.provider.key(),
+                changed_fields_bitmap,
+            },
+        )?;
+
+        Ok(())
+    }
+}
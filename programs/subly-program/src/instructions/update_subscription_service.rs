@@ -0,0 +1,146 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(service_id: u64)]
+pub struct UpdateSubscriptionService<'info> {
+    pub provider: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            provider.key().as_ref(),
+            service_id.to_le_bytes().as_ref()
+        ],
+        bump = subscription_service.bump,
+        constraint = subscription_service.provider == provider.key() @ ErrorCode::UnauthorizedProvider,
+        constraint = subscription_service.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub subscription_service: Account<'info, SubscriptionService>,
+}
+
+impl<'info> UpdateSubscriptionService<'info> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_subscription_service(
+        &mut self,
+        service_id: u64,
+        name: Option<String>,
+        description: Option<String>,
+        fee_usd: Option<u64>,
+        billing_frequency_days: Option<u64>,
+        image_url: Option<String>,
+        certificate_mode: Option<u8>,
+        royalty_bps: Option<u16>,
+        certificate_metadata_uri: Option<String>,
+        bumps: &UpdateSubscriptionServiceBumps,
+    ) -> Result<()> {
+        self.global_state.require_not_paused()?;
+
+        if name.is_some()
+            || description.is_some()
+            || image_url.is_some()
+            || certificate_metadata_uri.is_some()
+        {
+            require_full_layout(
+                &self.subscription_service.to_account_info(),
+                8 + SubscriptionService::INIT_SPACE,
+            )?;
+        }
+
+        let subscription_service = &mut self.subscription_service;
+        let mut changed_fields_bitmap: u8 = 0;
+
+        if let Some(name) = name {
+            validate_name(&name)?;
+            subscription_service.name = name;
+            changed_fields_bitmap |= SERVICE_FIELD_NAME;
+        }
+
+        if let Some(description) = description {
+            validate_description(&description)?;
+            subscription_service.description = description;
+            changed_fields_bitmap |= SERVICE_FIELD_DESCRIPTION;
+        }
+
+        if let Some(fee_usd) = fee_usd {
+            validate_service_fee(fee_usd, self.global_state.service_fee_cap())?;
+            subscription_service.fee_usd = fee_usd;
+            changed_fields_bitmap |= SERVICE_FIELD_FEE_USD;
+        }
+
+        if let Some(billing_frequency_days) = billing_frequency_days {
+            require!(
+                (MIN_SUBSCRIPTION_PERIOD_DAYS..=MAX_SUBSCRIPTION_PERIOD_DAYS)
+                    .contains(&billing_frequency_days),
+                ErrorCode::InvalidBillingFrequency
+            );
+            subscription_service.billing_frequency_days = billing_frequency_days;
+            changed_fields_bitmap |= SERVICE_FIELD_BILLING_FREQUENCY;
+        }
+
+        if let Some(image_url) = image_url {
+            validate_image_url(&image_url)?;
+            subscription_service.image_url = image_url;
+            changed_fields_bitmap |= SERVICE_FIELD_IMAGE_URL;
+        }
+
+        // Applies to new subscriptions; existing certificates keep their mode
+        if let Some(certificate_mode) = certificate_mode {
+            match certificate_mode {
+                CERTIFICATE_MODE_TOKEN => {}
+                CERTIFICATE_MODE_COMPRESSED => require!(
+                    self.global_state.certificate_merkle_tree != Pubkey::default(),
+                    ErrorCode::CertificateTreeNotConfigured
+                ),
+                _ => return err!(ErrorCode::InvalidCertificateMode),
+            }
+            subscription_service.certificate_mode = certificate_mode;
+            changed_fields_bitmap |= SERVICE_FIELD_CERTIFICATE_MODE;
+        }
+
+        // Applies to certificates minted from now on
+        if let Some(royalty_bps) = royalty_bps {
+            require!(
+                royalty_bps <= MAX_CERTIFICATE_ROYALTY_BPS,
+                ErrorCode::RoyaltyTooHigh
+            );
+            subscription_service.royalty_bps = royalty_bps;
+            changed_fields_bitmap |= SERVICE_FIELD_ROYALTY;
+        }
+
+        // An empty string reverts to the protocol default URI
+        if let Some(certificate_metadata_uri) = certificate_metadata_uri {
+            validate_certificate_metadata_uri(&certificate_metadata_uri)?;
+            subscription_service.certificate_metadata_uri = certificate_metadata_uri;
+            changed_fields_bitmap |= SERVICE_FIELD_CERTIFICATE_URI;
+        }
+
+        msg!(
+            "Subscription service {} updated by provider {} (changed fields bitmap: {})",
+            service_id,
+            self.provider.key(),
+            changed_fields_bitmap
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            ServiceUpdated {
+                provider: self.provider.key(),
+                service_id,
+                changed_fields_bitmap,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+/// Withdraw SOL value as USDC out of the user's own USDC balance, priced with the
+/// Pyth feed. SOL is never converted: there is no swap route, and the treasury's
+/// USDC belongs to providers and the protocol
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawSolAsUsdc<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Box<Account<'info, User>>,
+
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref()],
+        bump = user_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// CHECK: The user's withdrawal allowlist PDA, which may not exist. Always passed
+    /// so an enabled allowlist cannot be skipped by leaving it out
+    #[account(
+        seeds = [WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_allowlist: UncheckedAccount<'info>,
+
+    /// USDC mint (classic SPL or Token-2022)
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
+    )]
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// User's USDC vault, paid from
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = sol_vault,
+        associated_token::token_program = token_program
+    )]
+    pub user_usdc_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user's USDC associated token account, created if missing
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_usdc_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Pyth SOL/USD price feed account
+    /// CHECK: This account is validated in the instruction method to match the price feed in GlobalState
+    pub sol_usd_price_feed: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WithdrawSolAsUsdc<'info> {
+    /// Withdraw `lamports` worth of USDC, failing unless at least `min_usdc_out`
+    /// base units arrive
+    pub fn withdraw_sol_as_usdc(
+        &mut self,
+        lamports: u64,
+        min_usdc_out: u64,
+        bumps: &WithdrawSolAsUsdcBumps,
+    ) -> Result<()> {
+        require!(lamports > 0, ErrorCode::InvalidAmount);
+        require!(
+            self.sol_usd_price_feed.key() == self.global_state.sol_usd_price_feed,
+            ErrorCode::InvalidPriceFeed
+        );
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            self.user_account.withdrawal_delay_at(current_time) == 0,
+            ErrorCode::WithdrawalDelayActive
+        );
+
+        // USDC always goes to the user's own token account, which an enabled allowlist
+        // must still permit
+        if let Some(allowlist) = load_withdrawal_allowlist(&self.withdrawal_allowlist)? {
+            allowlist.check_destination(&self.user.key(), current_time)?;
+        }
+
+        let sol_usd_price = get_sol_usd_price_cents(&self.sol_usd_price_feed, &self.global_state)?;
+        let usdc_amount =
+            convert_sol_to_token_amount(lamports, sol_usd_price, self.usdc_mint.decimals)?;
+        require!(usdc_amount > 0, ErrorCode::InvalidAmount);
+
+        // The minimum is checked against what lands in the user's account
+        let transfer_fee = transfer_fee_amount(&self.usdc_mint.to_account_info(), usdc_amount)?;
+        let usdc_received = usdc_amount
+            .checked_sub(transfer_fee)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        require!(usdc_received >= min_usdc_out, ErrorCode::SlippageExceeded);
+
+        require!(
+            self.user_account.available_usdc()? >= usdc_amount,
+            ErrorCode::InsufficientAvailableBalance
+        );
+
+        let user_key = self.user.key();
+        let vault_bump = self.user_account.vault_bump;
+        let vault_seeds: &[&[u8]] = &[b"vault", user_key.as_ref(), &[vault_bump]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.user_usdc_vault.to_account_info(),
+                    mint: self.usdc_mint.to_account_info(),
+                    to: self.user_usdc_account.to_account_info(),
+                    authority: self.sol_vault.to_account_info(),
+                },
+                &[vault_seeds],
+            ),
+            usdc_amount,
+            self.usdc_mint.decimals,
+        )?;
+
+        self.user_account.usdc_balance = self
+            .user_account
+            .usdc_balance
+            .checked_sub(usdc_amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        msg!(
+            "Withdrew {} SOL as {} USDC base units ({} received)",
+            lamports_to_sol_string(lamports),
+            usdc_amount,
+            usdc_received
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            SolWithdrawnAsUsdc {
+                user: user_key,
+                lamports,
+                usdc_amount,
+                usdc_received,
+                sol_usd_price_cents: sol_usd_price,
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+
+/// Move protocol fees out of the SOL treasury. USDC held for provider payouts
+/// sits in the treasury's token account and is not touched here
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.authority == authority.key() @ ErrorCode::UnauthorizedAuthority,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    /// Protocol treasury, which collects the SOL side of execute_payment
+    #[account(
+        mut,
+        seeds = [b"treasury"],
+        bump = global_state.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// Where the SOL is sent
+    #[account(mut)]
+    pub destination: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WithdrawTreasury<'info> {
+    /// Send `amount` lamports, or everything above the rent floor for `u64::MAX`,
+    /// so the treasury stays rent exempt and can keep receiving fees
+    pub fn withdraw_treasury(&mut self, amount: u64, bumps: &WithdrawTreasuryBumps) -> Result<()> {
+        let amount = treasury_withdrawal_amount(
+            amount,
+            spendable_vault_balance(&self.treasury.to_account_info())?,
+        )?;
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: self.treasury.to_account_info(),
+                    to: self.destination.to_account_info(),
+                },
+                &[&[b"treasury", &[self.global_state.treasury_bump]]],
+            ),
+            amount,
+        )?;
+
+        msg!(
+            "Withdrew {} lamports from the treasury to {}",
+            amount,
+            self.destination.key()
+        );
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            TreasuryWithdrawn {
+                destination: self.destination.key(),
+                amount,
+                remaining_lamports: self.treasury.lamports(),
+                by: self.authority.key(),
+            },
+        )
+    }
+}
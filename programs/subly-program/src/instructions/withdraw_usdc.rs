@@ -0,0 +1,128 @@
+use crate::{constants::*, error::ErrorCode, events::*, state::*, utils::*};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawUsdc<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Box<Account<'info, User>>,
+
+    #[account(
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Box<Account<'info, GlobalState>>,
+
+    /// The user's SOL vault PDA, authority of the USDC vault
+    #[account(
+        seeds = [b"vault", user.key().as_ref()],
+        bump = user_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// CHECK: The user's withdrawal allowlist PDA, which may not exist. Always passed
+    /// so an enabled allowlist cannot be skipped by leaving it out
+    #[account(
+        seeds = [WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_allowlist: UncheckedAccount<'info>,
+
+    /// USDC mint (classic SPL or Token-2022)
+    #[account(
+        constraint = usdc_mint.key() == global_state.usdc_mint @ ErrorCode::InvalidSettlementMint,
+        mint::token_program = token_program
+    )]
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The user's USDC vault
+    #[account(
+        mut,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = sol_vault,
+        associated_token::token_program = token_program
+    )]
+    pub usdc_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token account receiving the USDC
+    #[account(
+        mut,
+        token::mint = usdc_mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_usdc_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawUsdc<'info> {
+    pub fn withdraw_usdc(&mut self, amount: u64, bumps: &WithdrawUsdcBumps) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            self.user_account.usdc_balance >= amount,
+            ErrorCode::InsufficientBalance
+        );
+
+        // Calculate available balance (deposited - locked for subscriptions)
+        require!(
+            self.user_account.available_usdc()? >= amount,
+            ErrorCode::InsufficientAvailableBalance
+        );
+
+        // USDC always goes to the user's own token account, which an enabled allowlist
+        // must still permit
+        if let Some(allowlist) = load_withdrawal_allowlist(&self.withdrawal_allowlist)? {
+            allowlist.check_destination(&self.user.key(), Clock::get()?.unix_timestamp)?;
+        }
+
+        let user_key = self.user.key();
+        let vault_bump = self.user_account.vault_bump;
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.usdc_vault.to_account_info(),
+                    mint: self.usdc_mint.to_account_info(),
+                    to: self.user_usdc_account.to_account_info(),
+                    authority: self.sol_vault.to_account_info(),
+                },
+                &[&[b"vault", user_key.as_ref(), &[vault_bump]]],
+            ),
+            amount,
+            self.usdc_mint.decimals,
+        )?;
+
+        self.user_account.usdc_balance = self
+            .user_account
+            .usdc_balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            UsdcWithdrawn {
+                user: self.user.key(),
+                amount,
+                new_usdc_balance: self.user_account.usdc_balance,
+            },
+        )?;
+
+        Ok(())
+    }
+}
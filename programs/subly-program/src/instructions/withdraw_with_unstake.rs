@@ -0,0 +1,298 @@
+use crate::{
+    constants::*,
+    error::ErrorCode,
+    events::*,
+    instructions::{withdraw_pool_sol, StakePoolWithdrawAccounts},
+    state::*,
+    utils::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use super::withdraw::pay_out_sol;
+
+/// Withdrawal that first unstakes JitoSOL when the vault's liquid SOL falls short.
+/// Carries the Jito stake pool accounts that a plain Withdraw leaves out
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawWithUnstake<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), user.key().as_ref()],
+        bump = user_account.bump,
+        constraint = user_account.wallet == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = user_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub user_account: Account<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref()],
+        bump = user_account.vault_bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// Where the SOL is sent (optional - defaults to the user's wallet)
+    #[account(mut)]
+    pub destination: Option<SystemAccount<'info>>,
+
+    /// CHECK: The user's withdrawal allowlist PDA, which may not exist. Always passed
+    /// so an enabled allowlist cannot be skipped by leaving it out
+    #[account(
+        seeds = [WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.key().as_ref()],
+        bump
+    )]
+    pub withdrawal_allowlist: UncheckedAccount<'info>,
+
+    /// Global state for reading Jito configuration and updating protocol counters
+    #[account(
+        mut,
+        seeds = [b"global_state"],
+        bump = global_state.bump,
+        constraint = global_state.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub global_state: Account<'info, GlobalState>,
+
+    #[account(
+        mut,
+        seeds = [
+            STAKE_ACCOUNT_SEED.as_bytes(),
+            user.key().as_ref(),
+        ],
+        bump = stake_account.bump,
+        constraint = stake_account.user == user.key() @ ErrorCode::UnauthorizedUser,
+        constraint = stake_account.is_current_version() @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Protocol's JitoSOL vault (ATA owned by protocol PDA)
+    #[account(
+        mut,
+        associated_token::mint = jito_sol_mint,
+        associated_token::authority = protocol_authority
+    )]
+    pub protocol_jito_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Protocol authority PDA that owns JitoSOL vault
+    #[account(
+        seeds = [b"protocol_authority"],
+        bump
+    )]
+    pub protocol_authority: UncheckedAccount<'info>,
+
+    // ===== Jito/SPL Stake Pool Accounts =====
+    /// CHECK: SPL Stake Pool program (read from GlobalState)
+    #[account(address = global_state.spl_stake_pool_program)]
+    pub stake_pool_program: UncheckedAccount<'info>,
+
+    /// CHECK: Jito Stake Pool account (read from GlobalState)
+    #[account(
+        mut,
+        address = global_state.jito_stake_pool
+    )]
+    pub jito_stake_pool: UncheckedAccount<'info>,
+
+    /// CHECK: Stake pool withdraw authority (PDA derived from stake pool), checked by the pool
+    pub stake_pool_withdraw_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Jito reserve stake account the SOL is paid from, checked by the pool
+    #[account(mut)]
+    pub reserve_stake: UncheckedAccount<'info>,
+
+    /// JitoSOL mint (read from GlobalState)
+    #[account(
+        mut,
+        address = global_state.jito_sol_mint
+    )]
+    pub jito_sol_mint: Account<'info, Mint>,
+
+    /// CHECK: Jito manager fee account, checked by the pool
+    #[account(mut)]
+    pub manager_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Clock sysvar, read by the pool
+    #[account(address = anchor_lang::solana_program::sysvar::clock::ID)]
+    pub clock: UncheckedAccount<'info>,
+
+    /// CHECK: Stake history sysvar, read by the pool
+    #[account(address = anchor_lang::solana_program::sysvar::stake_history::ID)]
+    pub stake_history: UncheckedAccount<'info>,
+
+    // ===== Programs =====
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Stake program
+    #[account(address = anchor_lang::solana_program::stake::program::ID)]
+    pub stake_program: UncheckedAccount<'info>,
+}
+
+impl<'info> WithdrawWithUnstake<'info> {
+    /// Direct withdrawals are only allowed while no withdrawal delay is in force
+    pub fn require_no_withdrawal_delay(&self) -> Result<()> {
+        require!(
+            self.user_account
+                .withdrawal_delay_at(Clock::get()?.unix_timestamp)
+                == 0,
+            ErrorCode::WithdrawalDelayActive
+        );
+        Ok(())
+    }
+
+    pub fn withdraw_with_unstake(
+        &mut self,
+        amount: u64,
+        jito_apy_bps: u16,
+        bumps: &WithdrawWithUnstakeBumps,
+    ) -> Result<()> {
+        let unstaked_first = self.unstake_sol_if_needed(amount, jito_apy_bps, bumps)?;
+
+        let destination = pay_out_sol(
+            &self.user,
+            &mut self.user_account,
+            &self.sol_vault,
+            self.destination.as_ref(),
+            &self.withdrawal_allowlist,
+            &mut self.global_state,
+            &self.system_program,
+            amount,
+        )?;
+
+        emit_cpi_event(
+            &self.event_authority,
+            bumps.event_authority,
+            Withdrawn {
+                user: self.user.key(),
+                amount,
+                unstaked_first,
+                new_deposited_total: self.user_account.deposited_sol,
+                destination,
+            },
+        )
+    }
+
+    /// Unstake from Jito into the user's vault.
+    /// Books the SOL that actually reached the vault and returns it
+    fn unstake_from_jito(
+        &mut self,
+        jito_sol_amount: u64,
+        bumps: &WithdrawWithUnstakeBumps,
+    ) -> Result<u64> {
+        // The pool rate and fee decide what arrives, so measure it on the vault
+        let vault_before = self.sol_vault.lamports();
+
+        let pool = StakePoolWithdrawAccounts {
+            stake_pool_program: self.stake_pool_program.to_account_info(),
+            jito_stake_pool: self.jito_stake_pool.to_account_info(),
+            stake_pool_withdraw_authority: self.stake_pool_withdraw_authority.to_account_info(),
+            protocol_authority: self.protocol_authority.to_account_info(),
+            protocol_jito_vault: self.protocol_jito_vault.to_account_info(),
+            reserve_stake: self.reserve_stake.to_account_info(),
+            manager_fee_account: self.manager_fee_account.to_account_info(),
+            jito_sol_mint: self.jito_sol_mint.to_account_info(),
+            clock: self.clock.to_account_info(),
+            stake_history: self.stake_history.to_account_info(),
+            stake_program: self.stake_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        };
+        withdraw_pool_sol(
+            &pool,
+            &self.sol_vault.to_account_info(),
+            jito_sol_amount,
+            bumps.protocol_authority,
+        )?;
+
+        let sol_received = self
+            .sol_vault
+            .lamports()
+            .checked_sub(vault_before)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+        // Update stake account with the principal behind the burned JitoSOL
+        let principal = self.stake_account.principal_for(jito_sol_amount)?;
+        self.stake_account
+            .record_unstake(jito_sol_amount, principal)?;
+
+        // Update user account from the measured proceeds
+        self.user_account
+            .record_unstake_proceeds(principal, sol_received)?;
+
+        self.global_state.record_unstake(principal, sol_received)?;
+
+        msg!(
+            "Unstaked {} JitoSOL base units, vault received {} lamports",
+            jito_sol_amount,
+            sol_received
+        );
+
+        Ok(sol_received)
+    }
+
+    /// Unstakes until the vault covers the withdrawal, re-reading the vault after
+    /// each round since the pool may return less than estimated; gives up after
+    /// MAX_WITHDRAW_UNSTAKE_ROUNDS so the whole withdrawal fails atomically.
+    /// Returns whether JitoSOL had to be unstaked to cover the withdrawal.
+    fn unstake_sol_if_needed(
+        &mut self,
+        withdraw_amount: u64,
+        jito_apy_bps: u16,
+        bumps: &WithdrawWithUnstakeBumps,
+    ) -> Result<bool> {
+        let mut unstaked = false;
+
+        for _ in 0..MAX_WITHDRAW_UNSTAKE_ROUNDS {
+            // Check if we have sufficient unlocked SOL for withdrawal, above the rent floor
+            let vault_balance = spendable_vault_balance(&self.sol_vault)?;
+
+            if vault_balance >= withdraw_amount {
+                if !unstaked {
+                    // Sufficient unlocked SOL, no need to unstake
+                    msg!(
+                        "Sufficient unlocked SOL ({} lamports), no unstaking needed",
+                        vault_balance
+                    );
+                }
+                return Ok(unstaked);
+            }
+
+            let staked_jito_sol = self.stake_account.jito_sol_amount;
+            if staked_jito_sol == 0 {
+                msg!("No staked JitoSOL to unstake");
+                return Err(if unstaked {
+                    ErrorCode::UnstakeShortfall.into()
+                } else {
+                    ErrorCode::InsufficientBalance.into()
+                });
+            }
+
+            // Calculate how much SOL we need to unstake
+            let needed_sol = withdraw_amount
+                .checked_sub(vault_balance)
+                .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+            // Calculate JitoSOL amount needed (reverse of APY calculation), rounded up
+            let jito_sol_needed = jito_sol_needed_for(needed_sol, jito_apy_bps);
+
+            // Use the minimum of what we need and what we have staked
+            let jito_sol_to_unstake = jito_sol_needed.min(staked_jito_sol);
+
+            msg!(
+                "Unstaking {} JitoSOL base units to get ~{} lamports for withdrawal",
+                jito_sol_to_unstake,
+                needed_sol
+            );
+
+            self.unstake_from_jito(jito_sol_to_unstake, bumps)?;
+            unstaked = true;
+        }
+
+        require!(
+            spendable_vault_balance(&self.sol_vault)? >= withdraw_amount,
+            ErrorCode::UnstakeShortfall
+        );
+        Ok(unstaked)
+    }
+}
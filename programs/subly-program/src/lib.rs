@@ -1,13 +1,23 @@
+#[macro_use]
+mod logging;
+
+#[cfg(all(feature = "mock", feature = "mainnet"))]
+compile_error!("the `mock` feature replaces the price oracle with test doubles and must not be built for mainnet");
+
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
+pub mod utils;
 
 use anchor_lang::prelude::*;
 
 pub use constants::*;
+pub use events::*;
 pub use instructions::*;
 pub use state::*;
+pub use utils::CompressedLeafProof;
 
 declare_id!("9MV6eJ5CfimYDv4WSqtyPx1Uc36apP1dzTMpGrobYCnc");
 
@@ -33,18 +43,50 @@ pub mod subly_program {
         )
     }
 
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
+        ctx.accounts.initialize_treasury(&ctx.bumps)
+    }
+
+    #[cfg(feature = "mock")]
+    pub fn init_mock_price_feed(
+        ctx: Context<InitMockPriceFeed>,
+        price_cents: u64,
+        expo: i32,
+        publish_time: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .init_mock_price_feed(price_cents, expo, publish_time, &ctx.bumps)
+    }
+
     pub fn check_subscribable_services<'info>(
         ctx: Context<'_, '_, '_, 'info, CheckSubscribableServices<'info>>,
         jito_apy_bps: u16, // Jito APY in basis points (e.g., 700 = 7%)
-    ) -> Result<Vec<SubscribableServiceInfo>> {
-        CheckSubscribableServices::check_subscribable_services(ctx, jito_apy_bps)
+        assumed_deposit_lamports: Option<u64>,
+    ) -> Result<SubscribableServicesPage> {
+        CheckSubscribableServices::check_subscribable_services(
+            ctx,
+            jito_apy_bps,
+            assumed_deposit_lamports,
+        )
+    }
+
+    pub fn get_protocol_stats(ctx: Context<GetProtocolStats>) -> Result<ProtocolStats> {
+        ctx.accounts.get_protocol_stats()
+    }
+
+    pub fn get_registry_page(
+        ctx: Context<GetRegistryPage>,
+        page: u32,
+        start_slot: u8,
+    ) -> Result<RegistryPage> {
+        ctx.accounts.get_registry_page(page, start_slot)
     }
 
     pub fn check_user_subscription(
         ctx: Context<CheckUserSubscription>,
         provider: Pubkey,
         service_id: u64,
-    ) -> Result<bool> {
+    ) -> Result<SubscriptionStatus> {
         ctx.accounts.check_user_subscription(provider, service_id)
     }
 
@@ -64,6 +106,7 @@ pub mod subly_program {
         fee_usd: u64,
         billing_frequency_days: u64,
         image_url: String,
+        certificate_metadata_uri: String,
     ) -> Result<()> {
         ctx.accounts.register_subscription_service(
             name,
@@ -71,19 +114,445 @@ pub mod subly_program {
             fee_usd,
             billing_frequency_days,
             image_url,
+            certificate_metadata_uri,
+            &ctx.bumps,
+        )
+    }
+
+    pub fn update_provider(
+        ctx: Context<UpdateProvider>,
+        name: Option<String>,
+        description: Option<String>,
+    ) -> Result<()> {
+        ctx.accounts.update_provider(name, description, &ctx.bumps)
+    }
+
+    pub fn resize_provider(ctx: Context<ResizeProvider>) -> Result<()> {
+        ctx.accounts.resize_provider()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_subscription_service(
+        ctx: Context<UpdateSubscriptionService>,
+        service_id: u64,
+        name: Option<String>,
+        description: Option<String>,
+        fee_usd: Option<u64>,
+        billing_frequency_days: Option<u64>,
+        image_url: Option<String>,
+        certificate_mode: Option<u8>,
+        royalty_bps: Option<u16>,
+        certificate_metadata_uri: Option<String>,
+    ) -> Result<()> {
+        ctx.accounts.update_subscription_service(
+            service_id,
+            name,
+            description,
+            fee_usd,
+            billing_frequency_days,
+            image_url,
+            certificate_mode,
+            royalty_bps,
+            certificate_metadata_uri,
+            &ctx.bumps,
+        )
+    }
+
+    pub fn resize_service(
+        ctx: Context<ResizeSubscriptionService>,
+        service_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.resize_service(service_id)
+    }
+
+    pub fn set_service_status(
+        ctx: Context<SetServiceStatus>,
+        service_id: u64,
+        is_active: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_service_status(service_id, is_active, &ctx.bumps)
+    }
+
+    pub fn backfill_service_registry(
+        ctx: Context<BackfillServiceRegistry>,
+        provider: Pubkey,
+        service_id: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .backfill_service_registry(provider, service_id, &ctx.bumps)
+    }
+
+    pub fn report_service(
+        ctx: Context<ReportService>,
+        provider: Pubkey,
+        service_id: u64,
+        reason_code: u8,
+    ) -> Result<()> {
+        ctx.accounts.report_service(provider, service_id, reason_code, &ctx.bumps)
+    }
+
+    pub fn freeze_service(
+        ctx: Context<FreezeService>,
+        provider: Pubkey,
+        service_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.freeze_service(provider, service_id, &ctx.bumps)
+    }
+
+    pub fn unfreeze_service(
+        ctx: Context<UnfreezeService>,
+        provider: Pubkey,
+        service_id: u64,
+    ) -> Result<()> {
+        ctx.accounts.unfreeze_service(provider, service_id, &ctx.bumps)
+    }
+
+    pub fn admin_deactivate_service(
+        ctx: Context<AdminDeactivateService>,
+        provider: Pubkey,
+        service_id: u64,
+        reason_code: u8,
+    ) -> Result<()> {
+        ctx.accounts
+            .admin_deactivate_service(provider, service_id, reason_code, &ctx.bumps)
+    }
+
+    pub fn set_provider_verified(
+        ctx: Context<SetProviderVerified>,
+        provider: Pubkey,
+        is_verified: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_provider_verified(provider, is_verified, &ctx.bumps)
+    }
+
+    pub fn verify_provider(ctx: Context<SetProviderVerified>, provider: Pubkey) -> Result<()> {
+        ctx.accounts
+            .set_provider_verified(provider, true, &ctx.bumps)
+    }
+
+    pub fn revoke_provider_verification(
+        ctx: Context<SetProviderVerified>,
+        provider: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_provider_verified(provider, false, &ctx.bumps)
+    }
+
+    pub fn set_provider_banned(
+        ctx: Context<SetProviderBanned>,
+        provider: Pubkey,
+        is_banned: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_provider_banned(provider, is_banned, &ctx.bumps)
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        ctx.accounts.set_paused(paused, &ctx.bumps)
+    }
+
+    pub fn pause_protocol(ctx: Context<SetPaused>) -> Result<()> {
+        ctx.accounts.pause_protocol(&ctx.bumps)
+    }
+
+    pub fn unpause_protocol(ctx: Context<SetPaused>) -> Result<()> {
+        ctx.accounts.unpause_protocol(&ctx.bumps)
+    }
+
+    pub fn set_pause_flags(ctx: Context<SetPaused>, pause_flags: u8) -> Result<()> {
+        ctx.accounts.set_pause_flags(pause_flags, &ctx.bumps)
+    }
+
+    pub fn set_emergency_mode(ctx: Context<SetPaused>, enabled: bool) -> Result<()> {
+        ctx.accounts.set_emergency_mode(enabled, &ctx.bumps)
+    }
+
+    pub fn set_soulbound_certificates(
+        ctx: Context<SetSoulboundCertificates>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_soulbound_certificates(enabled, &ctx.bumps)
+    }
+
+    pub fn set_sponsor_certificate_rent(
+        ctx: Context<SetSponsorCertificateRent>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_sponsor_certificate_rent(enabled, &ctx.bumps)
+    }
+
+    pub fn fund_rent_sponsor(ctx: Context<FundRentSponsor>, amount: u64) -> Result<()> {
+        ctx.accounts.fund_rent_sponsor(amount)
+    }
+
+    pub fn set_certificate_tree(
+        ctx: Context<SetCertificateTree>,
+        collection: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.set_certificate_tree(collection, &ctx.bumps)
+    }
+
+    pub fn set_min_deposit(ctx: Context<SetMinDeposit>, min_deposit_lamports: u64) -> Result<()> {
+        ctx.accounts
+            .set_min_deposit(min_deposit_lamports, &ctx.bumps)
+    }
+
+    pub fn set_liquid_reserve(
+        ctx: Context<SetLiquidReserve>,
+        liquid_reserve_lamports: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_liquid_reserve(liquid_reserve_lamports, &ctx.bumps)
+    }
+
+    pub fn set_max_deposit_per_user(
+        ctx: Context<SetMaxDepositPerUser>,
+        max_deposit_per_user_lamports: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_max_deposit_per_user(max_deposit_per_user_lamports, &ctx.bumps)
+    }
+
+    pub fn set_max_service_fee(
+        ctx: Context<SetMaxServiceFee>,
+        max_service_fee_usd_cents: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_max_service_fee(max_service_fee_usd_cents, &ctx.bumps)
+    }
+
+    pub fn set_max_price_age(ctx: Context<SetMaxPriceAge>, max_price_age_secs: u32) -> Result<()> {
+        ctx.accounts
+            .set_max_price_age(max_price_age_secs, &ctx.bumps)
+    }
+
+    pub fn set_max_price_deviation(ctx: Context<SetMaxPriceDeviation>, max_bps: u16) -> Result<()> {
+        ctx.accounts.set_max_price_deviation(max_bps, &ctx.bumps)
+    }
+
+    pub fn reset_price_breaker(ctx: Context<ResetPriceBreaker>) -> Result<()> {
+        ctx.accounts.reset_price_breaker(&ctx.bumps)
+    }
+
+    pub fn set_price_override(
+        ctx: Context<SetPriceOverride>,
+        price_cents: u64,
+        valid_until: i64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_price_override(price_cents, valid_until, &ctx.bumps)
+    }
+
+    pub fn set_sol_price_bounds(
+        ctx: Context<SetSolPriceBounds>,
+        min_sol_price_cents: u64,
+        max_sol_price_cents: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_sol_price_bounds(min_sol_price_cents, max_sol_price_cents, &ctx.bumps)
+    }
+
+    pub fn set_lock_periods(ctx: Context<SetLockPeriods>, lock_periods: u8) -> Result<()> {
+        ctx.accounts.set_lock_periods(lock_periods, &ctx.bumps)
+    }
+
+    pub fn set_fee_recipient(ctx: Context<SetFeeRecipient>, fee_recipient: Pubkey) -> Result<()> {
+        ctx.accounts.set_fee_recipient(fee_recipient, &ctx.bumps)
+    }
+
+    pub fn set_rent_collector(
+        ctx: Context<SetRentCollector>,
+        rent_collector: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.set_rent_collector(rent_collector, &ctx.bumps)
+    }
+
+    pub fn set_service_registration_limit(
+        ctx: Context<SetServiceRegistrationLimit>,
+        max_services_per_window: u32,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_service_registration_limit(max_services_per_window, &ctx.bumps)
+    }
+
+    pub fn set_require_verified_providers(
+        ctx: Context<SetRequireVerifiedProviders>,
+        required: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_require_verified_providers(required, &ctx.bumps)
+    }
+
+    pub fn set_protocol_fee(ctx: Context<SetProtocolFee>, fee_bps: u16) -> Result<()> {
+        ctx.accounts.set_protocol_fee(fee_bps, &ctx.bumps)
+    }
+
+    pub fn set_verified_fee(ctx: Context<SetVerifiedFee>, fee_bps: Option<u16>) -> Result<()> {
+        ctx.accounts.set_verified_fee(fee_bps, &ctx.bumps)
+    }
+
+    pub fn set_operator(ctx: Context<SetOperator>, operator: Pubkey) -> Result<()> {
+        ctx.accounts.set_operator(operator, &ctx.bumps)
+    }
+
+    pub fn set_execution_mode(ctx: Context<SetExecutionMode>, execution_mode: u8) -> Result<()> {
+        ctx.accounts.set_execution_mode(execution_mode, &ctx.bumps)
+    }
+
+    pub fn set_config_timelock(ctx: Context<SetConfigTimelock>, secs: i64) -> Result<()> {
+        ctx.accounts.set_config_timelock(secs, &ctx.bumps)
+    }
+
+    pub fn queue_config_change(
+        ctx: Context<QueueConfigChange>,
+        change: ConfigChange,
+    ) -> Result<()> {
+        ctx.accounts.queue_config_change(change, &ctx.bumps)
+    }
+
+    pub fn execute_config_change(ctx: Context<ExecuteConfigChange>, field: u8) -> Result<()> {
+        ctx.accounts.execute_config_change(field, &ctx.bumps)
+    }
+
+    pub fn cancel_config_change(ctx: Context<CancelConfigChange>, field: u8) -> Result<()> {
+        ctx.accounts.cancel_config_change(field, &ctx.bumps)
+    }
+
+    pub fn propose_authority_transfer(
+        ctx: Context<ProposeAuthorityTransfer>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.propose_authority_transfer(new_authority, &ctx.bumps)
+    }
+
+    pub fn accept_authority_transfer(ctx: Context<AcceptAuthorityTransfer>) -> Result<()> {
+        ctx.accounts.accept_authority_transfer(&ctx.bumps)
+    }
+
+    pub fn cancel_authority_transfer(ctx: Context<CancelAuthorityTransfer>) -> Result<()> {
+        ctx.accounts.cancel_authority_transfer(&ctx.bumps)
+    }
+
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        sol_usd_price_feed: Option<Pubkey>,
+        jito_stake_pool: Option<Pubkey>,
+        jito_sol_mint: Option<Pubkey>,
+        spl_stake_pool_program: Option<Pubkey>,
+        usdc_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.update_config(
+            sol_usd_price_feed,
+            jito_stake_pool,
+            jito_sol_mint,
+            spl_stake_pool_program,
+            usdc_mint,
             &ctx.bumps,
         )
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        ctx.accounts.deposit(amount, &ctx.bumps)
+    pub fn add_keeper(ctx: Context<AddKeeper>, keeper: Pubkey) -> Result<()> {
+        ctx.accounts.add_keeper(keeper, &ctx.bumps)
+    }
+
+    pub fn remove_keeper(ctx: Context<RemoveKeeper>, keeper: Pubkey) -> Result<()> {
+        ctx.accounts.remove_keeper(keeper, &ctx.bumps)
+    }
+
+    pub fn deposit(
+        ctx: Context<Deposit>,
+        amount: u64,
+        auto_stake: bool,
+        referrer: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.deposit(amount, auto_stake, referrer, &ctx.bumps)
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64, jito_apy_bps: u16) -> Result<()> {
-        // Sequential: unstake_sol then withdraw
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        ctx.accounts.require_no_withdrawal_delay()?;
+        ctx.accounts.withdraw(amount, &ctx.bumps)
+    }
+
+    pub fn withdraw_with_unstake(
+        ctx: Context<WithdrawWithUnstake>,
+        amount: u64,
+        jito_apy_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.require_no_withdrawal_delay()?;
+        // Sequential: unstake JitoSOL as needed, then withdraw
         ctx.accounts
-            .unstake_sol_if_needed(amount, jito_apy_bps, &ctx.bumps)?;
-        ctx.accounts.withdraw(amount, jito_apy_bps, &ctx.bumps)
+            .withdraw_with_unstake(amount, jito_apy_bps, &ctx.bumps)
+    }
+
+    pub fn set_withdrawal_delay(ctx: Context<SetWithdrawalDelay>, delay_secs: i64) -> Result<()> {
+        ctx.accounts.set_withdrawal_delay(delay_secs, &ctx.bumps)
+    }
+
+    pub fn request_withdrawal(ctx: Context<RequestWithdrawal>, amount: u64) -> Result<()> {
+        ctx.accounts.request_withdrawal(amount, &ctx.bumps)
+    }
+
+    pub fn execute_withdrawal(ctx: Context<Withdraw>) -> Result<()> {
+        // The pending amount was reserved at request time; pay it out like a direct
+        // withdrawal. Staked SOL is unstaked with unstake_sol earlier in the transaction
+        let amount = ctx.accounts.take_pending_withdrawal()?;
+        ctx.accounts.withdraw(amount, &ctx.bumps)
+    }
+
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        ctx.accounts.cancel_withdrawal(&ctx.bumps)
+    }
+
+    pub fn sweep_dust(ctx: Context<SweepDust>) -> Result<()> {
+        ctx.accounts.sweep_dust(&ctx.bumps)
+    }
+
+    pub fn emergency_withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, EmergencyWithdraw<'info>>,
+    ) -> Result<()> {
+        EmergencyWithdraw::emergency_withdraw(ctx)
+    }
+
+    pub fn set_withdrawal_allowlist(
+        ctx: Context<SetWithdrawalAllowlist>,
+        enabled: bool,
+        destinations: Vec<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_withdrawal_allowlist(enabled, destinations, &ctx.bumps)
+    }
+
+    pub fn deposit_wsol(ctx: Context<DepositWsol>, amount: u64) -> Result<()> {
+        ctx.accounts.deposit_wsol(amount, &ctx.bumps)
+    }
+
+    pub fn deposit_usdc(ctx: Context<DepositUsdc>, amount: u64) -> Result<()> {
+        ctx.accounts.deposit_usdc(amount, &ctx.bumps)
+    }
+
+    pub fn withdraw_usdc(ctx: Context<WithdrawUsdc>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_usdc(amount, &ctx.bumps)
+    }
+
+    pub fn withdraw_sol_as_usdc(
+        ctx: Context<WithdrawSolAsUsdc>,
+        lamports: u64,
+        min_usdc_out: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .withdraw_sol_as_usdc(lamports, min_usdc_out, &ctx.bumps)
+    }
+
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_treasury(amount, &ctx.bumps)
+    }
+
+    pub fn settle_provider_earnings(ctx: Context<SettleProviderEarnings>) -> Result<()> {
+        ctx.accounts.settle_provider_earnings(&ctx.bumps)
     }
 
     pub fn subscribe_to_service(
@@ -100,11 +569,44 @@ pub mod subly_program {
         provider: Pubkey,
         service_id: u64,
     ) -> Result<()> {
-        ctx.accounts.unsubscribe_from_service(provider, service_id)
+        ctx.accounts
+            .unsubscribe_from_service(provider, service_id, &ctx.bumps)
     }
 
-    pub fn process_subscription_payments(ctx: Context<ProcessSubscriptionPayments>) -> Result<()> {
-        ctx.accounts.process_subscription_payments()
+    pub fn subscribe_to_service_compressed(
+        ctx: Context<SubscribeToServiceCompressed>,
+        provider: Pubkey,
+        service_id: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .subscribe_to_service_compressed(provider, service_id, &ctx.bumps)
+    }
+
+    pub fn unsubscribe_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UnsubscribeBatch<'info>>,
+    ) -> Result<UnsubscribeBatchSummary> {
+        UnsubscribeBatch::unsubscribe_batch(ctx)
+    }
+
+    pub fn unsubscribe_from_service_compressed<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnsubscribeFromServiceCompressed<'info>>,
+        _provider: Pubkey,
+        _service_id: u64,
+        leaf: CompressedLeafProof,
+    ) -> Result<()> {
+        UnsubscribeFromServiceCompressed::unsubscribe_from_service_compressed(ctx, leaf)
+    }
+
+    pub fn process_subscription_payments<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessSubscriptionPayments<'info>>,
+    ) -> Result<()> {
+        ProcessSubscriptionPayments::process_subscription_payments(ctx)
+    }
+
+    pub fn crank_payments<'info>(
+        ctx: Context<'_, '_, '_, 'info, CrankPayments<'info>>,
+    ) -> Result<CrankPaymentsResult> {
+        CrankPayments::crank_payments(ctx)
     }
 
     pub fn execute_subscription_payment(
@@ -116,11 +618,85 @@ pub mod subly_program {
         ctx.accounts.execute_payment(&ctx.bumps)
     }
 
-    pub fn create_payment_record(
-        ctx: Context<CreatePaymentRecord>,
-        amount: u64,
+    pub fn sponsor_subscription(
+        ctx: Context<SponsorSubscription>,
+        _user: Pubkey,
+        _provider: Pubkey,
+        _service_id: u64,
+        periods: u64,
+    ) -> Result<()> {
+        ctx.accounts.sponsor_subscription(periods, &ctx.bumps)
+    }
+
+    pub fn claim_promo(
+        ctx: Context<ClaimPromo>,
+        provider: Pubkey,
+        service_id: u64,
+        discount_bps: u16,
+        expiry: i64,
+        nonce: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .claim_promo(provider, service_id, discount_bps, expiry, nonce, &ctx.bumps)
+    }
+
+    pub fn get_due_payments<'info>(
+        ctx: Context<'_, '_, '_, 'info, GetDuePayments<'info>>,
+    ) -> Result<DuePaymentsSummary> {
+        GetDuePayments::get_due_payments(ctx)
+    }
+
+    pub fn close_payment_record<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClosePaymentRecord<'info>>,
     ) -> Result<()> {
-        ctx.accounts.create_payment_record(amount)
+        ClosePaymentRecord::close_payment_record(ctx)
+    }
+
+    pub fn expire_subscription(ctx: Context<ExpireSubscription>) -> Result<()> {
+        ctx.accounts.expire_subscription(&ctx.bumps)
+    }
+
+    pub fn close_user_subscription(ctx: Context<CloseUserSubscription>) -> Result<()> {
+        ctx.accounts.close_user_subscription(&ctx.bumps)
+    }
+
+    pub fn set_payment_record_retention(
+        ctx: Context<SetPaymentRecordRetention>,
+        retention_days: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .set_payment_record_retention(retention_days, &ctx.bumps)
+    }
+
+    pub fn set_payment_record_disputed(
+        ctx: Context<SetPaymentRecordDisputed>,
+        disputed: bool,
+    ) -> Result<()> {
+        ctx.accounts.set_payment_record_disputed(disputed)
+    }
+
+    pub fn migrate_global_state(ctx: Context<MigrateGlobalState>) -> Result<()> {
+        ctx.accounts.migrate_global_state()
+    }
+
+    pub fn migrate_provider(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<Provider>()
+    }
+
+    pub fn migrate_subscription_service(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<SubscriptionService>()
+    }
+
+    pub fn migrate_user(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<User>()
+    }
+
+    pub fn migrate_user_subscription(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<UserSubscription>()
+    }
+
+    pub fn migrate_stake_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        ctx.accounts.migrate::<StakeAccount>()
     }
 
     pub fn stake_sol(ctx: Context<StakeSol>, amount: u64) -> Result<()> {
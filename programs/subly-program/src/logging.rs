@@ -0,0 +1,15 @@
+//! Logging that only costs compute units in builds with the `verbose-logs`
+//! feature. Events carry everything indexers need; these logs narrate the happy
+//! path for localnet debugging. Logs next to a failure or a skipped charge stay
+//! on plain `msg!` so they show up in every build.
+
+/// `msg!` that compiles to nothing without the `verbose-logs` feature. The
+/// arguments stay type-checked in both builds, so values used only for logging
+/// don't turn into unused-variable warnings when the feature is off
+macro_rules! verbose_msg {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "verbose-logs") {
+            anchor_lang::prelude::msg!($($arg)*);
+        }
+    };
+}
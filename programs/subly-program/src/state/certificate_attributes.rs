@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+
+/// On-chain attributes for a subscription certificate, keyed by the certificate mint.
+/// Only the program can write it, so providers can gate access on `paid_through`.
+#[account]
+#[derive(InitSpace)]
+pub struct CertificateAttributes {
+    pub mint: Pubkey,
+    pub paid_through: i64, // Access is paid up to this timestamp
+    pub updated_at: i64,
+    pub rent_sponsored: bool, // Certificate account rent returns to the rent sponsor on close
+    pub bump: u8,
+}
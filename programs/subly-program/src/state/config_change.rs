@@ -0,0 +1,103 @@
+use crate::{constants::*, error::ErrorCode};
+use anchor_lang::prelude::*;
+
+/// A sensitive GlobalState change that must wait out config_timelock_secs
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ConfigChange {
+    /// New protocol_fee_bps, applied through GlobalState::set_protocol_fee
+    ProtocolFee(u16),
+    /// New address for one of CONFIG_FIELD_PRICE_FEED through CONFIG_FIELD_USDC_MINT
+    Address { field: u8, address: Pubkey },
+    /// New config_timelock_secs; only lowering it needs the queue
+    ConfigTimelock(i64),
+    /// New verified provider rate or None to clear it, applied through
+    /// GlobalState::set_verified_fee
+    VerifiedFee(Option<u16>),
+    /// New fee_recipient, or the default pubkey to keep fees in the treasury.
+    /// Executing it needs the recipient's accounts, see require_payable_fee_recipient
+    FeeRecipient(Pubkey),
+    /// New max_price_age_secs, applied through GlobalState::set_max_price_age
+    MaxPriceAge(u32),
+    /// New SOL/USD sanity range in cents, applied through
+    /// GlobalState::set_sol_price_bounds
+    SolPriceBounds { min_cents: u64, max_cents: u64 },
+    /// New max_price_deviation_bps, 0 turning the price circuit breaker off
+    MaxPriceDeviation(u16),
+}
+
+impl ConfigChange {
+    /// CONFIG_FIELD_* code of the value this change replaces, also the
+    /// PendingConfigChange seed, so each field has at most one change queued
+    pub fn field(&self) -> u8 {
+        match self {
+            ConfigChange::ProtocolFee(_) => CONFIG_FIELD_PROTOCOL_FEE,
+            ConfigChange::Address { field, .. } => *field,
+            ConfigChange::ConfigTimelock(_) => CONFIG_FIELD_CONFIG_TIMELOCK,
+            ConfigChange::VerifiedFee(_) => CONFIG_FIELD_VERIFIED_FEE,
+            ConfigChange::FeeRecipient(_) => CONFIG_FIELD_FEE_RECIPIENT,
+            ConfigChange::MaxPriceAge(_) => CONFIG_FIELD_MAX_PRICE_AGE,
+            ConfigChange::SolPriceBounds { .. } => CONFIG_FIELD_SOL_PRICE_BOUNDS,
+            ConfigChange::MaxPriceDeviation(_) => CONFIG_FIELD_MAX_PRICE_DEVIATION,
+        }
+    }
+
+    /// Reject values the change could never apply, so a bad proposal fails at
+    /// queue time rather than after the delay
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            ConfigChange::ProtocolFee(fee_bps) | ConfigChange::VerifiedFee(Some(fee_bps)) => {
+                require!(
+                    fee_bps <= MAX_PROTOCOL_FEE_BPS,
+                    ErrorCode::InvalidProtocolFee
+                )
+            }
+            ConfigChange::VerifiedFee(None)
+            | ConfigChange::FeeRecipient(_)
+            | ConfigChange::MaxPriceDeviation(_) => {}
+            ConfigChange::Address { field, address } => require!(
+                (CONFIG_FIELD_PRICE_FEED..=CONFIG_FIELD_USDC_MINT).contains(&field)
+                    && address != Pubkey::default(),
+                ErrorCode::InvalidConfigAddress
+            ),
+            ConfigChange::ConfigTimelock(secs) => require!(
+                (0..=MAX_CONFIG_TIMELOCK_SECS).contains(&secs),
+                ErrorCode::InvalidConfigTimelock
+            ),
+            ConfigChange::MaxPriceAge(secs) => require!(
+                secs <= MAX_PRICE_AGE_LIMIT_SECS,
+                ErrorCode::InvalidMaxPriceAge
+            ),
+            ConfigChange::SolPriceBounds {
+                min_cents,
+                max_cents,
+            } => require!(
+                min_cents >= 1 && min_cents <= max_cents,
+                ErrorCode::InvalidPriceBounds
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// A queued ConfigChange. queue_config_change creates it, and
+/// execute_config_change or cancel_config_change closes it
+#[account]
+#[derive(InitSpace)]
+pub struct PendingConfigChange {
+    pub change: ConfigChange,
+    pub queued_by: Pubkey,
+    pub queued_at: i64,
+    pub executable_at: i64, // queued_at plus the timelock in force when queued
+    pub bump: u8,
+}
+
+impl PendingConfigChange {
+    /// Fails until the change's delay has passed
+    pub fn require_executable(&self, now: i64) -> Result<()> {
+        require!(
+            now >= self.executable_at,
+            ErrorCode::ConfigChangeNotExecutable
+        );
+        Ok(())
+    }
+}
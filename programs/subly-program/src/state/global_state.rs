@@ -1,8 +1,24 @@
+use crate::{
+    constants::{
+        CONFIG_FIELD_JITO_SOL_MINT, CONFIG_FIELD_JITO_STAKE_POOL, CONFIG_FIELD_PRICE_FEED,
+        CONFIG_FIELD_SPL_STAKE_POOL_PROGRAM, CONFIG_FIELD_USDC_MINT,
+        DEFAULT_LIQUID_RESERVE_LAMPORTS, DEFAULT_MAX_PRICE_AGE_SECS,
+        DEFAULT_MAX_SERVICES_PER_WINDOW, DEFAULT_MIN_DEPOSIT_LAMPORTS, EXECUTION_MODE_ALLOWLIST,
+        EXECUTION_MODE_AUTHORITY_ONLY, EXECUTION_MODE_PERMISSIONLESS, GLOBAL_STATE_V1_SPACE,
+        MAX_CONFIG_TIMELOCK_SECS, MAX_KEEPERS, MAX_MIN_DEPOSIT_LAMPORTS, MAX_PRICE_AGE_LIMIT_SECS,
+        MAX_PRICE_OVERRIDE_SECS, MAX_PROTOCOL_FEE_BPS, MAX_SERVICE_FEE_USD_CENTS,
+        MAX_SOL_USD_PRICE_CENTS, MAX_SUBSCRIPTIONS_PER_BATCH, MAX_SUBSCRIPTION_LOCK_PERIODS,
+        MIN_SOL_USD_PRICE_CENTS, PAUSE_FLAGS_ALL, PAYMENT_GRACE_PERIOD_SECONDS,
+        SUBSCRIPTION_LOCK_PERIODS,
+    },
+    error::ErrorCode,
+};
 use anchor_lang::prelude::*;
 
 #[account]
 #[derive(InitSpace)]
 pub struct GlobalState {
+    pub version: u8, // Layout version, see Versioned
     pub authority: Pubkey,
     pub protocol_fee_bps: u16, // Basis points (100 = 1%)
     pub is_paused: bool,
@@ -17,5 +33,690 @@ pub struct GlobalState {
     // Global service counter for unique service IDs
     pub total_services: u64,
     pub last_payment_processed: i64, // Timestamp of last payment processing
+    // Protocol-wide counters for dashboards (see get_protocol_stats)
+    pub total_providers: u64,
+    pub total_users: u64,
+    pub total_active_subscriptions: u64,
+    pub total_deposited_lamports: u64, // Liquid SOL held in user vaults
+    pub total_staked_lamports: u64,    // SOL staked through Jito
+    pub total_volume_lamports: u64,    // Cumulative subscription payments
+    pub total_protocol_fees_lamports: u64,
+    // Mint new certificates as non-transferable Token-2022 mints instead of classic SPL tokens
+    pub soulbound_certificates: bool,
+    // Compressed certificates: Bubblegum tree delegated to the certificate authority PDA
+    pub certificate_merkle_tree: Pubkey, // Default pubkey = not configured
+    pub certificate_collection: Pubkey,  // Optional collection recorded on each leaf
+    // Pay certificate account rent from the rent sponsor PDA instead of the subscriber
+    pub sponsor_certificate_rent: bool,
+    // PaymentRecords can be closed once they are this old
+    pub payment_record_retention_days: u64,
+    pub treasury_bump: u8,
     pub bump: u8,
+    // Fields below were added after version 1 and are appended so that
+    // upgrade_account_data's zero-fill keeps older layouts readable
+    // Smallest first deposit, the one that opens the User account and pays vault rent
+    pub min_deposit_lamports: u64,
+    // Available SOL an auto-staking deposit leaves liquid in the user's vault
+    pub liquid_reserve_lamports: u64,
+    // Cap on deposited plus staked SOL per user, 0 = unlimited
+    pub max_deposit_per_user_lamports: u64,
+    // Who may trigger billing, an EXECUTION_MODE_* value
+    pub execution_mode: u8,
+    // Services an unverified provider may register per registration window, 0 = unlimited
+    pub max_services_per_window: u32,
+    // Highest fee a service may charge, at most MAX_SERVICE_FEE_USD_CENTS; 0 = that ceiling
+    pub max_service_fee_usd_cents: u64,
+    // USDC credited to provider earnings ledgers and not yet settled, held by the USDC treasury
+    pub total_pending_payouts_usdc: u64,
+    // Curated mode: only verified providers' services accept new subscribers
+    pub require_verified_providers: bool,
+    // Last subscription key crank_payments scanned in the current pass, default = start
+    pub crank_cursor: Pubkey,
+    // When the protocol was last paused and unpaused, 0 = never
+    pub paused_at: i64,
+    pub unpaused_at: i64,
+    // Proposed next authority, default = no transfer pending. The current
+    // authority keeps every privilege until the proposed key accepts
+    pub pending_authority: Pubkey,
+    // Rate replaced by the last set_protocol_fee, still charged on payments due
+    // before protocol_fee_effective_from
+    pub previous_protocol_fee_bps: u16,
+    pub protocol_fee_effective_from: i64,
+    // PAUSE_* bits halting single areas; is_paused still halts all of them
+    pub pause_flags: u8,
+    // Hot key that may trigger billing in every execution mode but holds no
+    // admin rights, default = none
+    pub operator: Pubkey,
+    // Keeper registrations currently open, at most MAX_KEEPERS
+    pub keeper_count: u8,
+    // Delay queue_config_change puts on fee, oracle, Jito and USDC changes.
+    // 0 = none, and the direct setters keep working
+    pub config_timelock_secs: i64,
+    // Wind-down switch, only on while paused: users may empty their vaults with
+    // emergency_withdraw regardless of subscription locks
+    pub emergency_mode: bool,
+    // Oldest Pyth price any instruction accepts, 0 = DEFAULT_MAX_PRICE_AGE_SECS
+    pub max_price_age_secs: u32,
+    // SOL/USD sanity range in cents; a price outside it stops pricing instructions.
+    // 0 = MIN_SOL_USD_PRICE_CENTS / MAX_SOL_USD_PRICE_CENTS
+    pub min_sol_price_cents: u64,
+    pub max_sol_price_cents: u64,
+    // Billing periods of fees a new subscription locks, 0 = SUBSCRIPTION_LOCK_PERIODS
+    pub lock_periods: u8,
+    // Wallet paid the protocol fee of each payment, default = fees stay in the treasury
+    pub fee_recipient: Pubkey,
+    // Receives the rent of accounts closed by anyone but their payer, default = the authority
+    pub rent_collector: Pubkey,
+    // Protocol fee on payments to verified providers, only while verified_fee_set;
+    // unset = protocol_fee_bps. See GlobalState::verified_fee_bps
+    pub verified_fee_bps: u16,
+    // Price circuit breaker: largest move from the last observed SOL/USD price
+    // any instruction accepts, 0 = off. The reference is 0 until the first
+    // observation and after reset_price_breaker
+    pub max_price_deviation_bps: u16,
+    pub last_observed_price_cents: u64,
+    // Manual SOL/USD price served only while the Pyth feed has no fresh price, up to
+    // and including price_override_valid_until. 0 = none, see set_price_override
+    pub price_override_cents: u64,
+    pub price_override_valid_until: i64,
+    // Whether verified_fee_bps applies, so a rate of 0 is not mistaken for unset
+    pub verified_fee_set: bool,
+    // Cumulative protocol fees of payments made in USDC, in USDC base units.
+    // total_protocol_fees_lamports only counts SOL payments
+    pub total_protocol_fees_usdc: u64,
+    // Spare bytes so later fields fit without growing the account: new fields
+    // go above this and shrink it, and read as zero on existing accounts. The
+    // first 64 ran out at rent_collector, which grew the account by another 64
+    pub reserved: [u8; 6],
+}
+
+impl GlobalState {
+    /// Give an account migrated from `previous_len` bytes the limits initialize
+    /// would have set, where a zero-filled field would lift the limit. Only
+    /// layouts that ended at version 1's shipped size lack all of them; longer
+    /// accounts already carry their own values
+    pub fn apply_appended_field_defaults(&mut self, previous_len: usize) {
+        if previous_len > 8 + GLOBAL_STATE_V1_SPACE {
+            return;
+        }
+        self.min_deposit_lamports = DEFAULT_MIN_DEPOSIT_LAMPORTS;
+        self.liquid_reserve_lamports = DEFAULT_LIQUID_RESERVE_LAMPORTS;
+        self.max_services_per_window = DEFAULT_MAX_SERVICES_PER_WINDOW;
+        self.max_service_fee_usd_cents = MAX_SERVICE_FEE_USD_CENTS;
+    }
+
+    /// Fails when a deposit that opens a User account is below min_deposit_lamports.
+    /// Top-ups of existing accounts may be any size
+    pub fn require_min_deposit(&self, amount: u64, is_new_user: bool) -> Result<()> {
+        require!(
+            !is_new_user || amount >= self.min_deposit_lamports,
+            ErrorCode::DepositTooSmall
+        );
+        Ok(())
+    }
+
+    /// Set the smallest first deposit. It may not exceed the per-user deposit
+    /// cap, which would leave no first deposit that passes both. Returns the
+    /// previous setting
+    pub fn set_min_deposit(&mut self, lamports: u64) -> Result<u64> {
+        require!(
+            lamports <= MAX_MIN_DEPOSIT_LAMPORTS
+                && (self.max_deposit_per_user_lamports == 0
+                    || lamports <= self.max_deposit_per_user_lamports),
+            ErrorCode::InvalidMinDeposit
+        );
+        Ok(std::mem::replace(&mut self.min_deposit_lamports, lamports))
+    }
+
+    /// Oldest Pyth price, in seconds, that pricing instructions accept
+    pub fn max_price_age(&self) -> u64 {
+        match self.max_price_age_secs {
+            0 => u64::from(DEFAULT_MAX_PRICE_AGE_SECS),
+            secs => u64::from(secs),
+        }
+    }
+
+    /// Set the oldest price instructions accept, 0 restoring the default.
+    /// Returns the previous setting
+    pub fn set_max_price_age(&mut self, secs: u32) -> Result<u32> {
+        require!(
+            secs <= MAX_PRICE_AGE_LIMIT_SECS,
+            ErrorCode::InvalidMaxPriceAge
+        );
+        Ok(std::mem::replace(&mut self.max_price_age_secs, secs))
+    }
+
+    /// SOL/USD prices, in cents, that pricing instructions accept
+    pub fn sol_price_bounds(&self) -> std::ops::RangeInclusive<u64> {
+        let min = match self.min_sol_price_cents {
+            0 => MIN_SOL_USD_PRICE_CENTS,
+            cents => cents,
+        };
+        let max = match self.max_sol_price_cents {
+            0 => MAX_SOL_USD_PRICE_CENTS,
+            cents => cents,
+        };
+        min..=max
+    }
+
+    /// Set the accepted SOL/USD range. A zero price is never accepted, so 1 and
+    /// u64::MAX switch the sanity check off. Returns the previous (min, max)
+    pub fn set_sol_price_bounds(&mut self, min_cents: u64, max_cents: u64) -> Result<(u64, u64)> {
+        require!(
+            min_cents >= 1 && min_cents <= max_cents,
+            ErrorCode::InvalidPriceBounds
+        );
+        Ok((
+            std::mem::replace(&mut self.min_sol_price_cents, min_cents),
+            std::mem::replace(&mut self.max_sol_price_cents, max_cents),
+        ))
+    }
+
+    /// Fails when `price_cents` moved more than max_price_deviation_bps from the
+    /// last observed price. A refused price is never recorded, so the breaker
+    /// stays tripped until the authority resets it
+    pub fn check_price_deviation(&self, price_cents: u64) -> Result<()> {
+        let last = self.last_observed_price_cents;
+        if self.max_price_deviation_bps == 0 || last == 0 {
+            return Ok(());
+        }
+        let deviation_bps = u128::from(price_cents.abs_diff(last)) * 10_000 / u128::from(last);
+        require!(
+            deviation_bps <= u128::from(self.max_price_deviation_bps),
+            ErrorCode::PriceDeviationTooLarge
+        );
+        Ok(())
+    }
+
+    /// Fails once a config timelock is set, unless the change arms a breaker that
+    /// is off. Lowering, raising or disabling a threshold changes which prices
+    /// billing trusts, so that has to go through queue_config_change
+    pub fn require_direct_price_deviation_change(&self, max_bps: u16) -> Result<()> {
+        if self.max_price_deviation_bps == 0 && max_bps != 0 {
+            return Ok(());
+        }
+        self.require_no_config_timelock()
+    }
+
+    /// Make `price_cents` the reference for the next deviation check
+    pub fn record_observed_price(&mut self, price_cents: u64) {
+        self.last_observed_price_cents = price_cents;
+    }
+
+    /// Acknowledge a tripped breaker: the next price read becomes the new
+    /// reference. Returns the reference it cleared
+    pub fn reset_price_breaker(&mut self) -> u64 {
+        std::mem::take(&mut self.last_observed_price_cents)
+    }
+
+    /// Store a manual price of `price_cents` for Pyth outages, served until
+    /// `valid_until` and at most MAX_PRICE_OVERRIDE_SECS from `now`. The price
+    /// must be inside the sanity range. A price of 0 clears the override.
+    /// Returns the previous (price, valid_until)
+    pub fn set_price_override(
+        &mut self,
+        price_cents: u64,
+        valid_until: i64,
+        now: i64,
+    ) -> Result<(u64, i64)> {
+        let valid_until = if price_cents == 0 {
+            0
+        } else {
+            require!(
+                self.sol_price_bounds().contains(&price_cents),
+                ErrorCode::InvalidPrice
+            );
+            require!(
+                valid_until > now && valid_until - now <= MAX_PRICE_OVERRIDE_SECS,
+                ErrorCode::InvalidPriceOverride
+            );
+            valid_until
+        };
+        Ok((
+            std::mem::replace(&mut self.price_override_cents, price_cents),
+            std::mem::replace(&mut self.price_override_valid_until, valid_until),
+        ))
+    }
+
+    /// The override price, if one is set and has not expired at `now`
+    pub fn price_override_at(&self, now: i64) -> Option<u64> {
+        (self.price_override_cents > 0 && now <= self.price_override_valid_until)
+            .then_some(self.price_override_cents)
+    }
+
+    /// Billing periods of fees subscribe_to_service locks
+    pub fn lock_periods(&self) -> u64 {
+        match self.lock_periods {
+            0 => SUBSCRIPTION_LOCK_PERIODS,
+            periods => u64::from(periods),
+        }
+    }
+
+    /// Set the lock taken by new subscriptions. Open subscriptions keep the lock
+    /// recorded when they subscribed. Returns the previous value
+    pub fn set_lock_periods(&mut self, periods: u8) -> Result<u8> {
+        require!(
+            (1..=MAX_SUBSCRIPTION_LOCK_PERIODS).contains(&periods),
+            ErrorCode::InvalidLockPeriods
+        );
+        Ok(std::mem::replace(&mut self.lock_periods, periods))
+    }
+
+    /// Wallet execute_payment pays protocol fees to, or None while they stay in
+    /// the treasury
+    pub fn fee_recipient(&self) -> Option<Pubkey> {
+        (self.fee_recipient != Pubkey::default()).then_some(self.fee_recipient)
+    }
+
+    /// Wallet that collects the rent of protocol accounts closed by someone
+    /// other than their payer
+    pub fn rent_collector(&self) -> Pubkey {
+        if self.rent_collector == Pubkey::default() {
+            self.authority
+        } else {
+            self.rent_collector
+        }
+    }
+
+    /// Fails while the protocol is paused. Pausing closes everything that moves
+    /// value into or through the protocol: deposits, staking, yield, subscribing,
+    /// sponsoring, promos, billing and provider/service registration and updates.
+    /// The ways out stay open so users can always leave: withdrawals (direct,
+    /// delayed, USDC and as-USDC), sweep_dust, unsubscribes, closing payment
+    /// records and settling provider earnings. Authority setters, reports and
+    /// service freezes, views, migrations and resizes are unaffected
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.is_paused, ErrorCode::ProtocolPaused);
+        Ok(())
+    }
+
+    /// Fails while the protocol is paused or the PAUSE_* `area` is. Deposits,
+    /// subscriptions, billing and staking check their own area, so an incident
+    /// in one of them can be contained while withdrawals keep working
+    pub fn require_not_paused_for(&self, area: u8) -> Result<()> {
+        self.require_not_paused()?;
+        require!(self.pause_flags & area == 0, ErrorCode::ProtocolPaused);
+        Ok(())
+    }
+
+    /// Replace the per-area pause flags. Returns the previous flags
+    pub fn set_pause_flags(&mut self, pause_flags: u8) -> Result<u8> {
+        require!(
+            pause_flags & !PAUSE_FLAGS_ALL == 0,
+            ErrorCode::InvalidPauseFlags
+        );
+        Ok(std::mem::replace(&mut self.pause_flags, pause_flags))
+    }
+
+    /// Set the pause flag at `now`, stamping paused_at or unpaused_at only when
+    /// the flag actually changes. Returns the previous flag
+    pub fn apply_pause(&mut self, paused: bool, now: i64) -> bool {
+        let was_paused = self.is_paused;
+        if paused != was_paused {
+            if paused {
+                self.paused_at = now;
+            } else {
+                self.unpaused_at = now;
+            }
+        }
+        self.is_paused = paused;
+        was_paused
+    }
+
+    /// Turn emergency mode on or off. It can only be turned on while the
+    /// protocol is paused. Returns the previous setting
+    pub fn set_emergency_mode(&mut self, enabled: bool) -> Result<bool> {
+        require!(
+            !enabled || self.is_paused,
+            ErrorCode::EmergencyModeRequiresPause
+        );
+        Ok(std::mem::replace(&mut self.emergency_mode, enabled))
+    }
+
+    /// Fails unless the authority has turned on emergency mode
+    pub fn require_emergency_mode(&self) -> Result<()> {
+        require!(self.emergency_mode, ErrorCode::EmergencyModeNotActive);
+        Ok(())
+    }
+
+    /// Switch to `fee_bps` from `now` on, keeping the current rate for payments
+    /// that were already due. Only one previous rate is kept, so a change stays
+    /// pending for a grace period while the payments due before it are billed,
+    /// and a second change in that window is refused rather than repricing them.
+    /// Going back to the previous rate undoes the pending change instead, which
+    /// leaves those payments at the rate they had. Returns the rate it replaced
+    pub fn set_protocol_fee(&mut self, fee_bps: u16, now: i64) -> Result<u16> {
+        require!(
+            fee_bps <= MAX_PROTOCOL_FEE_BPS,
+            ErrorCode::InvalidProtocolFee
+        );
+        if fee_bps == self.previous_protocol_fee_bps && now < self.protocol_fee_settled_at()? {
+            return Ok(std::mem::replace(&mut self.protocol_fee_bps, fee_bps));
+        }
+        require!(
+            now >= self.protocol_fee_settled_at()?,
+            ErrorCode::ProtocolFeeChangePending
+        );
+        self.previous_protocol_fee_bps = self.protocol_fee_bps;
+        self.protocol_fee_effective_from = now;
+        self.protocol_fee_bps = fee_bps;
+        Ok(self.previous_protocol_fee_bps)
+    }
+
+    /// When the last protocol fee change stops being pending: payments due before
+    /// it have had the grace period to be billed at the previous rate
+    pub fn protocol_fee_settled_at(&self) -> Result<i64> {
+        Ok(self
+            .protocol_fee_effective_from
+            .checked_add(PAYMENT_GRACE_PERIOD_SECONDS)
+            .ok_or(ErrorCode::ArithmeticOverflow)?)
+    }
+
+    /// Protocol fee rate for a payment that fell due at `due_at`
+    pub fn protocol_fee_bps_for(&self, due_at: i64) -> u16 {
+        if due_at < self.protocol_fee_effective_from {
+            self.previous_protocol_fee_bps
+        } else {
+            self.protocol_fee_bps
+        }
+    }
+
+    /// Rate verified providers are charged, None while they pay protocol_fee_bps
+    /// like everyone else
+    pub fn verified_fee_bps(&self) -> Option<u16> {
+        self.verified_fee_set.then_some(self.verified_fee_bps)
+    }
+
+    /// Change the rate verified providers are charged, at most MAX_PROTOCOL_FEE_BPS,
+    /// or clear it with None. Unlike set_protocol_fee it applies to every payment
+    /// from now on. Returns the rate it replaced
+    pub fn set_verified_fee(&mut self, fee_bps: Option<u16>) -> Result<Option<u16>> {
+        require!(
+            fee_bps.unwrap_or_default() <= MAX_PROTOCOL_FEE_BPS,
+            ErrorCode::InvalidProtocolFee
+        );
+        let old_value = self.verified_fee_bps();
+        self.verified_fee_set = fee_bps.is_some();
+        self.verified_fee_bps = fee_bps.unwrap_or_default();
+        Ok(old_value)
+    }
+
+    /// Protocol fee rate for a payment that fell due at `due_at` to a provider
+    /// that is or is not verified
+    pub fn protocol_fee_bps_for_provider(&self, due_at: i64, provider_verified: bool) -> u16 {
+        match self.verified_fee_bps() {
+            Some(fee_bps) if provider_verified => fee_bps,
+            _ => self.protocol_fee_bps_for(due_at),
+        }
+    }
+
+    /// Fails once a config timelock is set: fee, oracle, price age and bounds,
+    /// Jito and USDC changes then have to go through queue_config_change
+    pub fn require_no_config_timelock(&self) -> Result<()> {
+        require!(self.config_timelock_secs == 0, ErrorCode::ConfigTimelocked);
+        Ok(())
+    }
+
+    /// Raise the config timelock to `secs` right away. Lowering it would let a
+    /// change skip the delay subscribers were promised, so that is queued too.
+    /// Returns the previous timelock
+    pub fn raise_config_timelock(&mut self, secs: i64) -> Result<i64> {
+        require!(
+            secs <= MAX_CONFIG_TIMELOCK_SECS,
+            ErrorCode::InvalidConfigTimelock
+        );
+        require!(
+            secs >= self.config_timelock_secs,
+            ErrorCode::ConfigTimelocked
+        );
+        Ok(std::mem::replace(&mut self.config_timelock_secs, secs))
+    }
+
+    /// When a change queued at `now` may be executed
+    pub fn config_change_executable_at(&self, now: i64) -> Result<i64> {
+        Ok(now
+            .checked_add(self.config_timelock_secs)
+            .ok_or(ErrorCode::ArithmeticOverflow)?)
+    }
+
+    /// Start handing the authority to `new_authority`, replacing any earlier
+    /// proposal. Returns the proposal it replaced
+    pub fn propose_authority(&mut self, new_authority: Pubkey) -> Result<Pubkey> {
+        require_keys_neq!(
+            new_authority,
+            Pubkey::default(),
+            ErrorCode::InvalidConfigAddress
+        );
+        Ok(std::mem::replace(&mut self.pending_authority, new_authority))
+    }
+
+    /// Complete the pending transfer for `signer`, who must be the proposed key.
+    /// Returns the previous authority
+    pub fn accept_authority(&mut self, signer: Pubkey) -> Result<Pubkey> {
+        require_keys_neq!(
+            self.pending_authority,
+            Pubkey::default(),
+            ErrorCode::NoPendingAuthorityTransfer
+        );
+        require_keys_eq!(
+            signer,
+            self.pending_authority,
+            ErrorCode::UnauthorizedAuthority
+        );
+        self.pending_authority = Pubkey::default();
+        Ok(std::mem::replace(&mut self.authority, signer))
+    }
+
+    /// Withdraw the pending proposal. Returns the key that was proposed
+    pub fn cancel_authority_transfer(&mut self) -> Result<Pubkey> {
+        require_keys_neq!(
+            self.pending_authority,
+            Pubkey::default(),
+            ErrorCode::NoPendingAuthorityTransfer
+        );
+        Ok(std::mem::take(&mut self.pending_authority))
+    }
+
+    /// Whether `caller` may trigger billing under the current execution mode.
+    /// `is_keeper` is whether a keeper registration for the caller was passed.
+    /// The operator is admitted wherever the authority is
+    pub fn check_billing_caller(&self, caller: &Pubkey, is_keeper: bool) -> Result<()> {
+        match self.execution_mode {
+            EXECUTION_MODE_AUTHORITY_ONLY => require!(
+                self.is_billing_admin(caller),
+                ErrorCode::UnauthorizedAuthority
+            ),
+            EXECUTION_MODE_ALLOWLIST => require!(
+                is_keeper || self.is_billing_admin(caller),
+                ErrorCode::KeeperNotRegistered
+            ),
+            EXECUTION_MODE_PERMISSIONLESS => {}
+            _ => return err!(ErrorCode::InvalidExecutionMode),
+        }
+        Ok(())
+    }
+
+    /// Count a new keeper registration, failing once MAX_KEEPERS are registered
+    pub fn register_keeper(&mut self) -> Result<()> {
+        require!(
+            self.keeper_count < MAX_KEEPERS,
+            ErrorCode::KeeperRegistryFull
+        );
+        self.keeper_count += 1;
+        Ok(())
+    }
+
+    /// Release a keeper registration. Keepers registered before the count
+    /// existed were never counted, so it saturates at zero
+    pub fn unregister_keeper(&mut self) {
+        self.keeper_count = self.keeper_count.saturating_sub(1);
+    }
+
+    /// The authority, or the operator once one is set
+    fn is_billing_admin(&self, caller: &Pubkey) -> bool {
+        *caller == self.authority
+            || (self.operator != Pubkey::default() && *caller == self.operator)
+    }
+
+    /// Point one of the external addresses, CONFIG_FIELD_PRICE_FEED through
+    /// CONFIG_FIELD_USDC_MINT, at `address`. Returns the previous value
+    pub fn set_config_address(&mut self, field: u8, address: Pubkey) -> Result<Pubkey> {
+        require_keys_neq!(address, Pubkey::default(), ErrorCode::InvalidConfigAddress);
+        let slot = match field {
+            CONFIG_FIELD_PRICE_FEED => &mut self.sol_usd_price_feed,
+            CONFIG_FIELD_JITO_STAKE_POOL => &mut self.jito_stake_pool,
+            CONFIG_FIELD_JITO_SOL_MINT => &mut self.jito_sol_mint,
+            CONFIG_FIELD_SPL_STAKE_POOL_PROGRAM => &mut self.spl_stake_pool_program,
+            CONFIG_FIELD_USDC_MINT => &mut self.usdc_mint,
+            _ => return err!(ErrorCode::InvalidConfigAddress),
+        };
+        Ok(std::mem::replace(slot, address))
+    }
+
+    /// Fails unless `page` continues the current crank pass: keys strictly
+    /// ascending and all after the cursor, so no subscription is scanned twice
+    pub fn check_crank_page(&self, page: &[Pubkey]) -> Result<()> {
+        let mut previous = self.crank_cursor;
+        for key in page {
+            require!(*key > previous, ErrorCode::CrankCursorMismatch);
+            previous = *key;
+        }
+        Ok(())
+    }
+
+    /// Move the crank cursor past the first `scanned` keys of `page`, or back to
+    /// the start once the pass is complete. A full page may have a successor and
+    /// a short one ends the pass, unless compute ran out before the page did.
+    /// Returns whether more subscriptions remain in the pass
+    pub fn advance_crank_cursor(&mut self, page: &[Pubkey], scanned: usize) -> bool {
+        let more_remaining = scanned < page.len() || page.len() == MAX_SUBSCRIPTIONS_PER_BATCH;
+        if !more_remaining {
+            self.crank_cursor = Pubkey::default();
+        } else if let Some(last) = page[..scanned].last() {
+            self.crank_cursor = *last;
+        }
+        more_remaining
+    }
+
+    /// Fails unless the USDC treasury holds at least the pending provider payouts
+    /// once `outflow` more has left it. Payouts are the providers' money and
+    /// nothing else may spend it
+    pub fn require_payouts_covered(&self, treasury_balance: u64, outflow: u64) -> Result<()> {
+        let remaining = treasury_balance
+            .checked_sub(outflow)
+            .ok_or(ErrorCode::PayoutsUnderfunded)?;
+        require!(
+            remaining >= self.total_pending_payouts_usdc,
+            ErrorCode::PayoutsUnderfunded
+        );
+        Ok(())
+    }
+
+    /// Highest fee register and update accept. Accounts migrated before the
+    /// setting existed read 0 and get the protocol ceiling
+    pub fn service_fee_cap(&self) -> u64 {
+        if self.max_service_fee_usd_cents == 0 {
+            MAX_SERVICE_FEE_USD_CENTS
+        } else {
+            self.max_service_fee_usd_cents.min(MAX_SERVICE_FEE_USD_CENTS)
+        }
+    }
+
+    /// Token program that new subscription certificates are minted under
+    pub fn certificate_token_program(&self) -> Pubkey {
+        if self.soulbound_certificates {
+            anchor_spl::token_2022::ID
+        } else {
+            anchor_spl::token::ID
+        }
+    }
+}
+
+/// Protocol-wide counters read by get_protocol_stats. They mirror the per-user
+/// ledgers: total_deposited_lamports follows User.deposited_sol and
+/// total_staked_lamports follows User.staked_sol. The counters were introduced
+/// after launch, so decrements saturate where older balances may not be reflected
+impl GlobalState {
+    /// A wallet made its first deposit
+    pub fn record_user_joined(&mut self) -> Result<()> {
+        self.total_users = self
+            .total_users
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// `lamports` were credited to a user's deposited_sol
+    pub fn record_deposit(&mut self, lamports: u64) -> Result<()> {
+        self.total_deposited_lamports = self
+            .total_deposited_lamports
+            .checked_add(lamports)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// `lamports` left a user's deposited_sol: a withdrawal, or SOL spent on a
+    /// payment or converted to USDC
+    pub fn record_withdrawal(&mut self, lamports: u64) {
+        self.total_deposited_lamports = self.total_deposited_lamports.saturating_sub(lamports);
+    }
+
+    /// `lamports` of deposited SOL were staked
+    pub fn record_stake(&mut self, lamports: u64) -> Result<()> {
+        self.total_deposited_lamports = self.total_deposited_lamports.saturating_sub(lamports);
+        self.total_staked_lamports = self
+            .total_staked_lamports
+            .checked_add(lamports)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// An unstake took `staked_lamports` off staked_sol and credited
+    /// `lamports_received` to deposited_sol
+    pub fn record_unstake(&mut self, staked_lamports: u64, lamports_received: u64) -> Result<()> {
+        self.total_staked_lamports = self.total_staked_lamports.saturating_sub(staked_lamports);
+        self.record_deposit(lamports_received)
+    }
+
+    pub fn record_subscription_opened(&mut self) -> Result<()> {
+        self.total_active_subscriptions = self
+            .total_active_subscriptions
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_subscription_closed(&mut self) {
+        self.total_active_subscriptions = self.total_active_subscriptions.saturating_sub(1);
+    }
+
+    /// A subscription payment of `payment_amount`, `paid_from_deposits` of it
+    /// from the user's deposited_sol (sponsor escrow was never counted)
+    pub fn record_payment(
+        &mut self,
+        payment_amount: u64,
+        paid_from_deposits: u64,
+        protocol_fee: u64,
+    ) -> Result<()> {
+        self.record_withdrawal(paid_from_deposits);
+        self.total_volume_lamports = self
+            .total_volume_lamports
+            .checked_add(payment_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.total_protocol_fees_lamports = self
+            .total_protocol_fees_lamports
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// A subscription payment made from the user's USDC, whose `protocol_fee` is
+    /// in USDC base units and never touched deposited SOL
+    pub fn record_usdc_payment(&mut self, protocol_fee: u64) -> Result<()> {
+        self.total_protocol_fees_usdc = self
+            .total_protocol_fees_usdc
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
 }
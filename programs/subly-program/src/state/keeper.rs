@@ -0,0 +1,10 @@
+use anchor_lang::prelude::*;
+
+/// Registration of a wallet allowed to trigger billing in allowlist execution mode
+#[account]
+#[derive(InitSpace)]
+pub struct Keeper {
+    pub keeper: Pubkey,
+    pub added_at: i64,
+    pub bump: u8,
+}
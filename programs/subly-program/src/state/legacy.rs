@@ -0,0 +1,194 @@
+use crate::{
+    constants::{
+        MAX_DESCRIPTION_LENGTH, MAX_NAME_LENGTH, MAX_URL_LENGTH, SOL_VAULT_SEED, TREASURY_SEED,
+    },
+    state::*,
+};
+use anchor_lang::prelude::*;
+
+// Layouts the accounts had before they stored a version, field for field as
+// they were written. They are only read, by migration, and each maps its fields
+// onto the current layout explicitly rather than assuming an offset.
+// GlobalState was allocated 8 + INIT_SPACE, the others INIT_SPACE
+
+/// An account layout from before the version field, and where its fields go
+pub trait UnversionedLayout<T>: AnchorDeserialize + Space {
+    /// Copy the fields onto `account`, a zeroed account on the current version
+    fn upgrade(self, account: &mut T);
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct GlobalStateV0 {
+    pub authority: Pubkey,
+    pub protocol_fee_bps: u16,
+    pub is_paused: bool,
+    pub jito_stake_pool: Pubkey,
+    pub jito_sol_mint: Pubkey,
+    pub spl_stake_pool_program: Pubkey,
+    pub sol_usd_price_feed: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub total_services: u64,
+    pub last_payment_processed: i64,
+    pub bump: u8,
+}
+
+impl UnversionedLayout<GlobalState> for GlobalStateV0 {
+    fn upgrade(self, account: &mut GlobalState) {
+        account.authority = self.authority;
+        account.protocol_fee_bps = self.protocol_fee_bps;
+        account.is_paused = self.is_paused;
+        account.jito_stake_pool = self.jito_stake_pool;
+        account.jito_sol_mint = self.jito_sol_mint;
+        account.spl_stake_pool_program = self.spl_stake_pool_program;
+        account.sol_usd_price_feed = self.sol_usd_price_feed;
+        account.usdc_mint = self.usdc_mint;
+        account.total_services = self.total_services;
+        account.last_payment_processed = self.last_payment_processed;
+        account.bump = self.bump;
+        // The treasury came later, its bump is the canonical one initialize stores
+        account.treasury_bump =
+            Pubkey::find_program_address(&[TREASURY_SEED.as_bytes()], &crate::ID).1;
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct ProviderV0 {
+    pub wallet: Pubkey,
+    #[max_len(MAX_NAME_LENGTH)]
+    pub name: String,
+    #[max_len(MAX_DESCRIPTION_LENGTH)]
+    pub description: String,
+    pub total_subscribers: u64,
+    pub is_verified: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl UnversionedLayout<Provider> for ProviderV0 {
+    fn upgrade(self, account: &mut Provider) {
+        account.wallet = self.wallet;
+        account.name = self.name;
+        account.description = self.description;
+        account.total_subscribers = self.total_subscribers;
+        account.is_verified = self.is_verified;
+        account.created_at = self.created_at;
+        account.bump = self.bump;
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct SubscriptionServiceV0 {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    #[max_len(MAX_NAME_LENGTH)]
+    pub name: String,
+    #[max_len(MAX_DESCRIPTION_LENGTH)]
+    pub description: String,
+    pub fee_usd: u64,
+    pub billing_frequency_days: u64,
+    #[max_len(MAX_URL_LENGTH)]
+    pub image_url: String,
+    pub current_subscribers: u64,
+    pub is_active: bool,
+    pub created_at: i64,
+    pub bumps: u8,
+}
+
+impl UnversionedLayout<SubscriptionService> for SubscriptionServiceV0 {
+    fn upgrade(self, account: &mut SubscriptionService) {
+        account.provider = self.provider;
+        account.service_id = self.service_id;
+        account.name = self.name;
+        account.description = self.description;
+        account.fee_usd = self.fee_usd;
+        account.billing_frequency_days = self.billing_frequency_days;
+        account.image_url = self.image_url;
+        account.current_subscribers = self.current_subscribers;
+        account.is_active = self.is_active;
+        account.created_at = self.created_at;
+        account.bump = self.bumps;
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct UserV0 {
+    pub wallet: Pubkey,
+    pub deposited_sol: u64,
+    pub locked_sol: u64,
+    pub staked_sol: u64,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl UnversionedLayout<User> for UserV0 {
+    fn upgrade(self, account: &mut User) {
+        account.wallet = self.wallet;
+        account.deposited_sol = self.deposited_sol;
+        account.locked_sol = self.locked_sol;
+        account.staked_sol = self.staked_sol;
+        account.created_at = self.created_at;
+        account.bump = self.bump;
+        // The vault bump was never stored, instructions that sign for the vault read it
+        account.vault_bump = Pubkey::find_program_address(
+            &[SOL_VAULT_SEED.as_bytes(), self.wallet.as_ref()],
+            &crate::ID,
+        )
+        .1;
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct UserSubscriptionV0 {
+    pub user: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub subscription_id: u64,
+    pub subscribed_at: i64,
+    pub last_payment_at: Option<i64>,
+    pub next_payment_due: i64,
+    pub total_payments_made: u64,
+    pub is_active: bool,
+    pub unsubscribed_at: Option<i64>,
+    pub bumps: u8,
+}
+
+impl UnversionedLayout<UserSubscription> for UserSubscriptionV0 {
+    fn upgrade(self, account: &mut UserSubscription) {
+        account.user = self.user;
+        account.provider = self.provider;
+        account.service_id = self.service_id;
+        account.subscription_id = self.subscription_id;
+        account.subscribed_at = self.subscribed_at;
+        account.last_payment_at = self.last_payment_at;
+        account.next_payment_due = self.next_payment_due;
+        account.total_payments_made = self.total_payments_made;
+        account.is_active = self.is_active;
+        account.unsubscribed_at = self.unsubscribed_at;
+        account.bump = self.bumps;
+    }
+}
+
+#[derive(AnchorDeserialize, InitSpace)]
+pub struct StakeAccountV0 {
+    pub user: Pubkey,
+    pub staked_amount: u64,
+    pub jito_sol_amount: u64,
+    pub stake_date: i64,
+    pub last_yield_claim: i64,
+    pub total_yield_earned: u64,
+    pub is_active: bool,
+    pub bump: u8,
+}
+
+impl UnversionedLayout<StakeAccount> for StakeAccountV0 {
+    fn upgrade(self, account: &mut StakeAccount) {
+        account.user = self.user;
+        account.staked_amount = self.staked_amount;
+        account.jito_sol_amount = self.jito_sol_amount;
+        account.stake_date = self.stake_date;
+        account.last_yield_claim = self.last_yield_claim;
+        account.total_yield_earned = self.total_yield_earned;
+        account.is_active = self.is_active;
+        account.bump = self.bump;
+    }
+}
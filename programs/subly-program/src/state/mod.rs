@@ -1,15 +1,35 @@
+pub mod certificate_attributes;
+pub mod config_change;
 pub mod global_state;
+pub mod keeper;
+pub mod legacy;
 pub mod payment_record;
+pub mod promo_claim;
 pub mod provider;
+pub mod provider_earnings;
+pub mod report;
+pub mod service_registry;
 pub mod stake_account;
 pub mod subscription_service;
 pub mod user;
 pub mod user_subscription;
+pub mod versioned;
+pub mod withdrawal_allowlist;
 
+pub use certificate_attributes::*;
+pub use config_change::*;
 pub use global_state::*;
+pub use keeper::*;
+pub use legacy::*;
 pub use payment_record::*;
+pub use promo_claim::*;
 pub use provider::*;
+pub use provider_earnings::*;
+pub use report::*;
+pub use service_registry::*;
 pub use stake_account::*;
 pub use subscription_service::*;
 pub use user::*;
 pub use user_subscription::*;
+pub use versioned::*;
+pub use withdrawal_allowlist::*;
@@ -19,5 +19,10 @@ pub struct PaymentRecord {
     pub amount: u64, // In lamports
     pub payment_date: i64,
     pub payment_type: PaymentType,
+    // Settlement leg (zero for records without a token transfer)
+    pub settlement_mint: Pubkey,
+    pub settlement_amount: u64,        // Provider's share in the settlement mint
+    pub provider_received_amount: u64, // Credited to ProviderEarnings, after any Token-2022 transfer fee
+    pub disputed: bool,                // Disputed or refund pending; blocks close_payment_record
     pub bump: u8,
 }
@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+/// Marks a provider's promo nonce as used. claim_promo creates it, so a signed
+/// grant can be claimed once no matter how many times the signature is replayed
+#[account]
+#[derive(InitSpace)]
+pub struct PromoClaim {
+    pub provider: Pubkey,
+    pub nonce: u64,
+    pub user: Pubkey,
+    pub service_id: u64,
+    pub discount_bps: u16,
+    pub claimed_at: i64,
+    pub bump: u8,
+}
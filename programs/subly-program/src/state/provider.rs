@@ -1,15 +1,68 @@
+use crate::{constants::*, error::ErrorCode};
 use anchor_lang::prelude::*;
 
 #[account]
 #[derive(InitSpace)]
 pub struct Provider {
+    pub version: u8, // Layout version, see Versioned
     pub wallet: Pubkey,
-    #[max_len(64)]
+    #[max_len(MAX_NAME_LENGTH)]
     pub name: String,
-    #[max_len(200)]
+    #[max_len(MAX_DESCRIPTION_LENGTH)]
     pub description: String,
     pub total_subscribers: u64,
     pub is_verified: bool,
     pub created_at: i64,
     pub bump: u8,
+    // Service registration rate limit, see record_service_registration
+    pub last_service_registered_at: i64,
+    pub services_registered_in_window: u32,
+    // Set by the authority; a banned provider's services cannot take new subscribers
+    pub is_banned: bool,
+    pub verified_at: i64, // When the authority last verified the provider, 0 while unverified
+}
+
+impl Provider {
+    /// Fails unless new subscribers may join this provider's services: banned
+    /// providers never qualify, and unverified ones don't while `require_verified`
+    pub fn require_in_good_standing(&self, require_verified: bool) -> Result<()> {
+        require!(!self.is_banned, ErrorCode::ProviderBanned);
+        require!(
+            self.is_verified || !require_verified,
+            ErrorCode::ProviderNotVerified
+        );
+        Ok(())
+    }
+
+    /// Set the verification flag. Verifying stamps `now` as verified_at, which
+    /// re-verifying refreshes; revoking clears it
+    pub fn set_verified(&mut self, is_verified: bool, now: i64) {
+        self.is_verified = is_verified;
+        self.verified_at = if is_verified { now } else { 0 };
+    }
+
+    /// Count a service registration at `now` against the window limit.
+    /// Windows are SERVICE_REGISTRATION_WINDOW_SECONDS long and aligned to the
+    /// Unix epoch, so the count restarts with the first registration of a new
+    /// window. Verified providers and a limit of 0 are unlimited.
+    pub fn record_service_registration(&mut self, now: i64, max_per_window: u32) -> Result<()> {
+        let current_window = now.div_euclid(SERVICE_REGISTRATION_WINDOW_SECONDS);
+        let last_window = self
+            .last_service_registered_at
+            .div_euclid(SERVICE_REGISTRATION_WINDOW_SECONDS);
+        let registered = if current_window == last_window {
+            self.services_registered_in_window
+        } else {
+            0
+        };
+
+        require!(
+            self.is_verified || max_per_window == 0 || registered < max_per_window,
+            ErrorCode::RateLimited
+        );
+
+        self.services_registered_in_window = registered.saturating_add(1);
+        self.last_service_registered_at = now;
+        Ok(())
+    }
 }
@@ -0,0 +1,77 @@
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+
+/// A provider's earnings ledger. Billing credits the provider's share here and
+/// leaves the USDC in the protocol treasury, so a closed or missing provider
+/// token account never blocks a subscriber's payment. settle_provider_earnings
+/// delivers the pending balance
+#[account]
+#[derive(InitSpace)]
+pub struct ProviderEarnings {
+    pub provider: Pubkey,
+    pub pending_usdc: u64, // Owed to the provider, held by the USDC treasury
+    pub total_credited_usdc: u64, // Cumulative credits from billing
+    pub total_settled_usdc: u64, // Cumulative amount sent by settlement
+    pub last_credited_at: i64,
+    pub last_settled_at: i64,
+    pub bump: u8,
+    // Frozen services of this provider; settlement is held while any remain
+    pub frozen_services: u32,
+}
+
+impl ProviderEarnings {
+    /// Fill in the ledger's identity the first time it is used
+    pub fn set_provider_if_new(&mut self, provider: Pubkey, bump: u8) {
+        if self.provider == Pubkey::default() {
+            self.provider = provider;
+            self.bump = bump;
+        }
+    }
+
+    /// Credit a billing payment's provider share as pending payout
+    pub fn credit(&mut self, amount: u64, now: i64) -> Result<()> {
+        self.pending_usdc = self
+            .pending_usdc
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.total_credited_usdc = self
+            .total_credited_usdc
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.last_credited_at = now;
+        Ok(())
+    }
+
+    /// Clear the pending balance for delivery and return it. Earnings stay held
+    /// while any of the provider's services is frozen
+    pub fn take_pending(&mut self, now: i64) -> Result<u64> {
+        require!(self.frozen_services == 0, ErrorCode::EarningsHeld);
+        let amount = self.pending_usdc;
+        require!(amount > 0, ErrorCode::NoPendingEarnings);
+        self.pending_usdc = 0;
+        self.total_settled_usdc = self
+            .total_settled_usdc
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.last_settled_at = now;
+        Ok(amount)
+    }
+
+    /// Hold settlement while one more of the provider's services is frozen
+    pub fn hold(&mut self) -> Result<()> {
+        self.frozen_services = self
+            .frozen_services
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Lift the hold of one unfrozen service
+    pub fn release(&mut self) -> Result<()> {
+        self.frozen_services = self
+            .frozen_services
+            .checked_sub(1)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{constants::*, error::ErrorCode};
+use anchor_lang::prelude::*;
+
+/// A user's report against a service. There is one per reporter and service, so
+/// repeat reports update it instead of adding accounts
+#[account]
+#[derive(InitSpace)]
+pub struct Report {
+    pub reporter: Pubkey,
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub reason_code: u8, // REPORT_REASON_* code of the latest report
+    pub report_count: u32,
+    pub first_reported_at: i64,
+    pub last_reported_at: i64,
+    pub bump: u8,
+}
+
+impl Report {
+    /// Record a report with `reason_code`, updating the reporter's earlier one
+    pub fn record(&mut self, reason_code: u8, now: i64) -> Result<()> {
+        require!(
+            (REPORT_REASON_SCAM..=REPORT_REASON_OTHER).contains(&reason_code),
+            ErrorCode::InvalidReportReason
+        );
+        if self.report_count == 0 {
+            self.first_reported_at = now;
+        }
+        self.report_count = self
+            .report_count
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.reason_code = reason_code;
+        self.last_reported_at = now;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{constants::*, error::ErrorCode};
+use anchor_lang::prelude::*;
+
+/// One page of the service registry, so clients can enumerate services without
+/// scanning program accounts. Service ids are global and sequential, so service
+/// `id` always lives in slot `id % SERVICE_REGISTRY_PAGE_CAPACITY` of page
+/// `id / SERVICE_REGISTRY_PAGE_CAPACITY`. register_subscription_service fills
+/// the slot and set_service_status keeps its tombstone in step with is_active.
+/// Services registered before the registry have empty slots until
+/// backfill_service_registry or a status change writes them
+#[account]
+#[derive(InitSpace)]
+pub struct ServiceRegistryPage {
+    pub page: u32,
+    pub services: [Pubkey; 64], // SubscriptionService PDAs, default pubkey = empty slot
+    pub tombstones: u64,        // Bit i set = the service in slot i is inactive
+    pub entries: u8,            // Filled slots
+    pub bump: u8,
+}
+
+impl ServiceRegistryPage {
+    /// Page holding `service_id`
+    pub fn page_of(service_id: u64) -> u32 {
+        // Service ids would have to pass 2^38 before this saturates
+        u32::try_from(service_id / SERVICE_REGISTRY_PAGE_CAPACITY).unwrap_or(u32::MAX)
+    }
+
+    fn slot_of(service_id: u64) -> usize {
+        (service_id % SERVICE_REGISTRY_PAGE_CAPACITY) as usize
+    }
+
+    /// First service id on this page
+    pub fn first_service_id(&self) -> u64 {
+        u64::from(self.page) * SERVICE_REGISTRY_PAGE_CAPACITY
+    }
+
+    /// Write `service` into its slot and set its tombstone from `is_active`.
+    /// Rewriting a slot with the same service only updates the tombstone, so
+    /// backfills can be repeated in any order
+    pub fn record(
+        &mut self,
+        page_bump: u8,
+        service_id: u64,
+        service: Pubkey,
+        is_active: bool,
+    ) -> Result<()> {
+        let slot = Self::slot_of(service_id);
+        let entry = &mut self.services[slot];
+        if *entry == Pubkey::default() {
+            *entry = service;
+            self.entries += 1;
+        } else {
+            require_keys_eq!(*entry, service, ErrorCode::ServiceRegistrySlotTaken);
+        }
+
+        self.page = Self::page_of(service_id);
+        self.bump = page_bump;
+        if is_active {
+            self.tombstones &= !(1 << slot);
+        } else {
+            self.tombstones |= 1 << slot;
+        }
+        Ok(())
+    }
+
+    /// Whether `service_id` is registered here and not tombstoned
+    pub fn is_live(&self, service_id: u64) -> bool {
+        Self::page_of(service_id) == self.page
+            && self.services[Self::slot_of(service_id)] != Pubkey::default()
+            && self.tombstones & (1 << Self::slot_of(service_id)) == 0
+    }
+
+    /// Service ids and PDAs of the page's live services, in id order. These are
+    /// the accounts to pass to check_subscribable_services
+    pub fn live_services(&self) -> impl Iterator<Item = (u64, Pubkey)> + '_ {
+        let first_service_id = self.first_service_id();
+        self.services
+            .iter()
+            .enumerate()
+            .filter(move |(slot, service)| {
+                **service != Pubkey::default() && self.tombstones & (1 << slot) == 0
+            })
+            .map(move |(slot, service)| (first_service_id + slot as u64, *service))
+    }
+
+    /// Up to `limit` live services from `start_slot` on, and the slot of the next
+    /// live service when more remain on this page
+    pub fn live_services_from(
+        &self,
+        start_slot: u8,
+        limit: usize,
+    ) -> (Vec<(u64, Pubkey)>, Option<u8>) {
+        let first_service_id = self.first_service_id();
+        let mut remaining = self
+            .live_services()
+            .skip_while(|(service_id, _)| *service_id < first_service_id + u64::from(start_slot));
+        let services = remaining.by_ref().take(limit).collect();
+        let next_slot = remaining
+            .next()
+            .map(|(service_id, _)| (service_id - first_service_id) as u8);
+        (services, next_slot)
+    }
+}
@@ -1,8 +1,10 @@
+use crate::error::ErrorCode;
 use anchor_lang::prelude::*;
 
 #[account]
 #[derive(InitSpace)]
 pub struct StakeAccount {
+    pub version: u8, // Layout version, see Versioned
     pub user: Pubkey,
     pub staked_amount: u64,   // In lamports
     pub jito_sol_amount: u64, // JitoSOL received
@@ -12,3 +14,67 @@ pub struct StakeAccount {
     pub is_active: bool,
     pub bump: u8,
 }
+
+impl StakeAccount {
+    /// Record SOL staked and the JitoSOL it is expected to mint. A stake that
+    /// starts from nothing restarts the stake and yield clocks
+    pub fn record_stake(&mut self, amount: u64, jito_sol_amount: u64, staked_at: i64) -> Result<()> {
+        if !self.is_active {
+            self.stake_date = staked_at;
+            self.last_yield_claim = staked_at;
+            self.is_active = true;
+        }
+        self.staked_amount = self
+            .staked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.jito_sol_amount = self
+            .jito_sol_amount
+            .checked_add(jito_sol_amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Staked lamports backing `jito_sol_amount` of this stake's JitoSOL, pro rata
+    pub fn principal_for(&self, jito_sol_amount: u64) -> Result<u64> {
+        require!(
+            jito_sol_amount <= self.jito_sol_amount,
+            ErrorCode::InsufficientStakedFunds
+        );
+        if jito_sol_amount == self.jito_sol_amount {
+            return Ok(self.staked_amount);
+        }
+        let principal = u128::from(self.staked_amount)
+            .checked_mul(u128::from(jito_sol_amount))
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            / u128::from(self.jito_sol_amount);
+        u64::try_from(principal).map_err(|_| ErrorCode::ArithmeticOverflow.into())
+    }
+
+    /// Record JitoSOL burned by an unstake and the SOL it returned.
+    /// Deactivates the stake once nothing is left staked
+    pub fn record_unstake(&mut self, jito_sol_amount: u64, sol_received: u64) -> Result<()> {
+        self.jito_sol_amount = self
+            .jito_sol_amount
+            .checked_sub(jito_sol_amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        self.staked_amount = self
+            .staked_amount
+            .checked_sub(sol_received)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        if self.staked_amount == 0 {
+            self.is_active = false;
+        }
+        Ok(())
+    }
+
+    /// Record yield paid out to the user's vault
+    pub fn record_yield(&mut self, amount: u64, claimed_at: i64) -> Result<()> {
+        self.total_yield_earned = self
+            .total_yield_earned
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.last_yield_claim = claimed_at;
+        Ok(())
+    }
+}
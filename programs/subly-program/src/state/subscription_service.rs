@@ -1,20 +1,61 @@
+use crate::{constants::*, error::ErrorCode};
 use anchor_lang::prelude::*;
 
 #[account]
 #[derive(InitSpace)]
 pub struct SubscriptionService {
+    pub version: u8, // Layout version, see Versioned
     pub provider: Pubkey,
     pub service_id: u64,
-    #[max_len(64)]
+    #[max_len(MAX_NAME_LENGTH)]
     pub name: String,
-    #[max_len(200)]
+    #[max_len(MAX_DESCRIPTION_LENGTH)]
     pub description: String,
     pub fee_usd: u64, // USD cents
     pub billing_frequency_days: u64,
-    #[max_len(200)]
+    #[max_len(MAX_URL_LENGTH)]
     pub image_url: String,
     pub current_subscribers: u64,
     pub is_active: bool,
     pub created_at: i64,
-    pub bumps: u8,
+    pub certificate_mode: u8, // CERTIFICATE_MODE_* constant
+    pub royalty_bps: u16,     // seller_fee_basis_points on certificate metadata
+    #[max_len(MAX_URL_LENGTH)]
+    pub certificate_metadata_uri: String, // Empty = protocol default URI
+    pub bump: u8,
+    // Held by the authority pending review of user reports, see freeze_service
+    pub frozen: bool,
+    // Set by admin_deactivate_service; the provider can no longer reactivate the service
+    pub deactivated_by_admin: bool,
+    pub deactivation_reason: u8, // REPORT_REASON_* code given by the authority, 0 = none
+}
+
+impl SubscriptionService {
+    /// Deactivate the service on the authority's behalf for `reason_code`.
+    /// Unlike a freeze this is final: new subscriptions and billing stop for good
+    /// and subscribers are left to unsubscribe
+    pub fn deactivate_by_admin(&mut self, reason_code: u8) -> Result<()> {
+        require!(
+            (REPORT_REASON_SCAM..=REPORT_REASON_OTHER).contains(&reason_code),
+            ErrorCode::InvalidReportReason
+        );
+        require!(
+            !self.deactivated_by_admin,
+            ErrorCode::ServiceDeactivatedByAdmin
+        );
+
+        self.is_active = false;
+        self.deactivated_by_admin = true;
+        self.deactivation_reason = reason_code;
+        Ok(())
+    }
+
+    /// Fails when the provider tries to reactivate a service the authority deactivated
+    pub fn require_provider_may_set_status(&self, is_active: bool) -> Result<()> {
+        require!(
+            !is_active || !self.deactivated_by_admin,
+            ErrorCode::ServiceDeactivatedByAdmin
+        );
+        Ok(())
+    }
 }
@@ -1,12 +1,314 @@
+use crate::{
+    constants::{MAX_WITHDRAWAL_DELAY_SECONDS, SOL_VAULT_SEED, USER_VERSION},
+    error::ErrorCode,
+    state::Versioned,
+};
 use anchor_lang::prelude::*;
 
 #[account]
 #[derive(InitSpace)]
 pub struct User {
+    pub version: u8, // Layout version, see Versioned
     pub wallet: Pubkey,
     pub deposited_sol: u64, // lamports
     pub locked_sol: u64,    // lamports locked for active subscriptions
     pub staked_sol: u64,    // lamports staked for yield generation
     pub created_at: i64,
+    pub vault_bump: u8, // SOL vault PDA, stored at first deposit
     pub bump: u8,
+    // Appended after version 1 so older layouts migrate by zero-fill
+    pub usdc_balance: u64, // USDC base units held in the user's USDC vault
+    pub locked_usdc: u64,  // USDC base units locked for active subscriptions
+    // Opt-in withdrawal timelock. Raising the delay applies at once; lowering it
+    // only takes effect once the old delay has passed
+    pub withdrawal_delay_secs: i64,
+    pub pending_withdrawal_delay_secs: i64,
+    pub withdrawal_delay_effective_at: i64, // 0 when no lower delay is pending
+    pub pending_withdrawal_lamports: u64,   // Requested, reserved from subscriptions and billing
+    pub withdrawal_unlocks_at: i64,
+    // Referral attribution. The referrer is fixed by the first deposit
+    pub referrer: Pubkey,     // Default when the user was not referred
+    pub referred_count: u32,  // Users whose first deposit named this user
+    pub referred_volume: u64, // Lamports deposited by those users
+}
+
+impl User {
+    /// Set up a freshly allocated account, or verify an existing one belongs to
+    /// `wallet`. Identity fields are only ever written while still unset, so a
+    /// repeated call cannot move created_at or the stored bumps; a bump that was
+    /// stored and disagrees with the canonical one means the account is corrupt.
+    /// Returns true when the account was initialized by this call.
+    pub fn initialize_or_verify(
+        &mut self,
+        wallet: Pubkey,
+        created_at: i64,
+        vault_bump: u8,
+        bump: u8,
+    ) -> Result<bool> {
+        let is_new = self.wallet == Pubkey::default();
+        if is_new {
+            self.version = USER_VERSION;
+            self.wallet = wallet;
+            self.deposited_sol = 0;
+            self.locked_sol = 0;
+            self.staked_sol = 0;
+        }
+
+        require_keys_eq!(self.wallet, wallet, ErrorCode::UnauthorizedUser);
+        require!(
+            self.is_current_version(),
+            ErrorCode::UnsupportedAccountVersion
+        );
+
+        // Backfill anything a partial initialization may have left unset
+        if self.created_at == 0 {
+            self.created_at = created_at;
+        }
+        if self.vault_bump == 0 {
+            self.vault_bump = vault_bump;
+        }
+        if self.bump == 0 {
+            self.bump = bump;
+        }
+        require!(
+            self.vault_bump == vault_bump && self.bump == bump,
+            ErrorCode::InvalidStoredBump
+        );
+
+        Ok(is_new)
+    }
+
+    /// Bump of `wallet`'s SOL vault: the one stored at the first deposit, or the
+    /// canonical bump while the account has not stored it yet
+    pub fn vault_bump_for(&self, wallet: &Pubkey) -> u8 {
+        match self.vault_bump {
+            0 => {
+                Pubkey::find_program_address(
+                    &[SOL_VAULT_SEED.as_bytes(), wallet.as_ref()],
+                    &crate::ID,
+                )
+                .1
+            }
+            bump => bump,
+        }
+    }
+
+    /// Record who referred this user. Only a user's first deposit may name a
+    /// referrer, and a user cannot refer themselves
+    pub fn set_referrer(&mut self, referrer: Pubkey) -> Result<()> {
+        require!(referrer != Pubkey::default(), ErrorCode::InvalidReferrer);
+        require_keys_neq!(referrer, self.wallet, ErrorCode::SelfReferral);
+        require_keys_eq!(self.referrer, Pubkey::default(), ErrorCode::ReferrerAlreadySet);
+        self.referrer = referrer;
+        Ok(())
+    }
+
+    /// Credit a deposit made by a user this user referred. `is_first_deposit`
+    /// counts the referred user once, on the deposit that recorded the referral
+    pub fn record_referred_deposit(&mut self, amount: u64, is_first_deposit: bool) -> Result<()> {
+        if is_first_deposit {
+            self.referred_count = self
+                .referred_count
+                .checked_add(1)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+        self.referred_volume = self
+            .referred_volume
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Deposited SOL not locked for subscriptions or reserved for a pending
+    /// withdrawal. Saturates on purpose: SOL billing pays from deposited_sol and
+    /// leaves the lock in place until unsubscribe, so after a few payments the
+    /// lock may exceed what is left, and then nothing is available
+    pub fn available_sol(&self) -> Result<u64> {
+        Ok(self
+            .deposited_sol
+            .saturating_sub(self.locked_sol)
+            .saturating_sub(self.pending_withdrawal_lamports))
+    }
+
+    /// Withdrawal delay in force at `current_time`, including a lowered delay
+    /// whose waiting period has passed
+    pub fn withdrawal_delay_at(&self, current_time: i64) -> i64 {
+        if self.withdrawal_delay_effective_at != 0
+            && current_time >= self.withdrawal_delay_effective_at
+        {
+            self.pending_withdrawal_delay_secs
+        } else {
+            self.withdrawal_delay_secs
+        }
+    }
+
+    /// Change the withdrawal delay. An increase applies immediately and drops any
+    /// pending decrease; a decrease is scheduled for when the current delay has passed
+    pub fn set_withdrawal_delay(&mut self, delay_secs: i64, current_time: i64) -> Result<()> {
+        require!(
+            (0..=MAX_WITHDRAWAL_DELAY_SECONDS).contains(&delay_secs),
+            ErrorCode::InvalidWithdrawalDelay
+        );
+
+        let current_delay = self.withdrawal_delay_at(current_time);
+        self.withdrawal_delay_secs = current_delay;
+        if delay_secs >= current_delay {
+            self.withdrawal_delay_secs = delay_secs;
+            self.pending_withdrawal_delay_secs = 0;
+            self.withdrawal_delay_effective_at = 0;
+        } else {
+            self.pending_withdrawal_delay_secs = delay_secs;
+            self.withdrawal_delay_effective_at = current_time
+                .checked_add(current_delay)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+        Ok(())
+    }
+
+    /// Reserve `amount` for a withdrawal that unlocks after the current delay
+    pub fn request_withdrawal(&mut self, amount: u64, current_time: i64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        let delay = self.withdrawal_delay_at(current_time);
+        require!(delay > 0, ErrorCode::WithdrawalDelayNotSet);
+        require!(
+            self.pending_withdrawal_lamports == 0,
+            ErrorCode::WithdrawalAlreadyPending
+        );
+        require!(
+            self.available_sol()? >= amount,
+            ErrorCode::InsufficientAvailableBalance
+        );
+
+        self.pending_withdrawal_lamports = amount;
+        self.withdrawal_unlocks_at = current_time
+            .checked_add(delay)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Clear the pending withdrawal once it has unlocked, returning its amount
+    pub fn take_pending_withdrawal(&mut self, current_time: i64) -> Result<u64> {
+        let amount = self.pending_withdrawal_lamports;
+        require!(amount > 0, ErrorCode::NoPendingWithdrawal);
+        require!(
+            current_time >= self.withdrawal_unlocks_at,
+            ErrorCode::WithdrawalStillLocked
+        );
+        self.pending_withdrawal_lamports = 0;
+        self.withdrawal_unlocks_at = 0;
+        Ok(amount)
+    }
+
+    /// Drop the pending withdrawal, returning its amount to the available balance
+    pub fn cancel_pending_withdrawal(&mut self) -> Result<u64> {
+        let amount = self.pending_withdrawal_lamports;
+        require!(amount > 0, ErrorCode::NoPendingWithdrawal);
+        self.pending_withdrawal_lamports = 0;
+        self.withdrawal_unlocks_at = 0;
+        Ok(amount)
+    }
+
+    /// How much more SOL may be deposited under a per-user `cap` on deposited
+    /// plus staked SOL. A cap of 0 means unlimited
+    pub fn deposit_headroom(&self, cap: u64) -> Result<u64> {
+        if cap == 0 {
+            return Ok(u64::MAX);
+        }
+        let held = self
+            .deposited_sol
+            .checked_add(self.staked_sol)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(cap.saturating_sub(held))
+    }
+
+    /// Available SOL above `liquid_reserve`, which an auto-staking deposit stakes
+    pub fn stakeable_above_reserve(&self, liquid_reserve: u64) -> Result<u64> {
+        Ok(self.available_sol()?.saturating_sub(liquid_reserve))
+    }
+
+    /// USDC balance not locked for subscriptions. USDC billing draws on the lock
+    /// first, so locked_usdc never exceeds usdc_balance unless the accounting is corrupt
+    pub fn available_usdc(&self) -> Result<u64> {
+        self.usdc_balance
+            .checked_sub(self.locked_usdc)
+            .ok_or(ErrorCode::LockedExceedsDeposited.into())
+    }
+
+    /// Move lamports from the deposited balance into the staked balance
+    pub fn move_to_staked(&mut self, amount: u64) -> Result<()> {
+        self.deposited_sol = self
+            .deposited_sol
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        self.staked_sol = self
+            .staked_sol
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Move lamports returned by an unstake from the staked balance back into deposits
+    pub fn move_to_deposited(&mut self, amount: u64) -> Result<()> {
+        self.staked_sol = self
+            .staked_sol
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        self.deposited_sol = self
+            .deposited_sol
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Record an unstake measured on the vault: `principal` leaves the staked
+    /// balance and the `sol_received` that actually arrived is credited, so pool
+    /// fees and yield show up in deposited_sol rather than drifting from the vault
+    pub fn record_unstake_proceeds(&mut self, principal: u64, sol_received: u64) -> Result<()> {
+        self.staked_sol = self
+            .staked_sol
+            .checked_sub(principal)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        self.deposited_sol = self
+            .deposited_sol
+            .checked_add(sol_received)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Take a subscription payment out of the deposited balance. Locks are
+    /// released by unsubscribe, not by billing
+    pub fn record_sol_payment(&mut self, amount: u64) -> Result<()> {
+        self.deposited_sol = self
+            .deposited_sol
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientBalance)?;
+        Ok(())
+    }
+
+    /// Release funds locked for a subscription that has ended
+    pub fn release_locked(&mut self, amount: u64) -> Result<()> {
+        self.locked_sol = self
+            .locked_sol
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        Ok(())
+    }
+
+    /// Drop every SOL claim for emergency_withdraw: deposits, subscription locks
+    /// and any pending withdrawal. Returns the deposited SOL that was cleared
+    pub fn clear_sol_for_emergency_withdraw(&mut self) -> u64 {
+        self.locked_sol = 0;
+        self.pending_withdrawal_lamports = 0;
+        self.withdrawal_unlocks_at = 0;
+        std::mem::take(&mut self.deposited_sol)
+    }
+
+    /// Release USDC locked for a subscription that has ended or been paid from the lock
+    pub fn release_locked_usdc(&mut self, amount: u64) -> Result<()> {
+        self.locked_usdc = self
+            .locked_usdc
+            .checked_sub(amount)
+            .ok_or(ErrorCode::ArithmeticUnderflow)?;
+        Ok(())
+    }
 }
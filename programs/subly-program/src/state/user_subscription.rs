@@ -1,8 +1,10 @@
+use crate::{constants::*, error::ErrorCode};
 use anchor_lang::prelude::*;
 
 #[account]
 #[derive(InitSpace)]
 pub struct UserSubscription {
+    pub version: u8, // Layout version, see Versioned
     pub user: Pubkey,
     pub provider: Pubkey,
     pub service_id: u64,
@@ -13,5 +15,51 @@ pub struct UserSubscription {
     pub total_payments_made: u64,
     pub is_active: bool,
     pub unsubscribed_at: Option<i64>,
-    pub bumps: u8,
+    pub certificate_asset_id: Pubkey, // Compressed certificate leaf; default for token certificates
+    pub failed_payment_attempts: u8,  // Since the last successful payment
+    pub delinquent_since: Option<i64>,
+    pub bump: u8,
+    // Appended after version 1 so older layouts migrate by zero-fill
+    pub locked_usdc: u64, // Lock held in USDC base units; zero when the lock is in SOL
+    pub sponsor: Pubkey,  // Funder of the sponsor escrow; default when unsponsored
+    pub sponsor_escrow_lamports: u64, // Billed before the user's own funds, never withdrawable by the user
+    pub discount_bps: u16,            // Off every charge, from the latest claimed promo grant
+    pub locked_sol: u64,              // Lamports locked at subscribe; zero for USDC or older locks
+}
+
+impl UserSubscription {
+    /// Why unsubscribe_batch and emergency_withdraw have to leave this subscription
+    /// to the single-entry instructions, or UNSUBSCRIBE_REASON_NONE when they can
+    /// cancel it for `user`
+    pub fn batch_unsubscribe_skip_reason(&self, user: &Pubkey) -> u8 {
+        if self.user != *user {
+            UNSUBSCRIBE_REASON_INVALID_ACCOUNTS
+        } else if !self.is_active {
+            UNSUBSCRIBE_REASON_NOT_ACTIVE
+        } else if self.sponsor != Pubkey::default() {
+            UNSUBSCRIBE_REASON_SPONSORED
+        } else if self.certificate_asset_id != Pubkey::default() {
+            UNSUBSCRIBE_REASON_COMPRESSED_CERTIFICATE
+        } else {
+            UNSUBSCRIBE_REASON_NONE
+        }
+    }
+
+    /// When a delinquent subscription's access ends and it can be expired
+    pub fn grace_ends_at(&self) -> Result<i64> {
+        self.next_payment_due
+            .checked_add(PAYMENT_GRACE_PERIOD_SECONDS)
+            .ok_or(ErrorCode::ArithmeticOverflow.into())
+    }
+
+    /// The service fee in cents after this subscription's promo discount
+    pub fn discounted_fee(&self, fee_usd: u64) -> Result<u64> {
+        let discount = fee_usd
+            .checked_mul(u64::from(self.discount_bps))
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            / 10000;
+        fee_usd
+            .checked_sub(discount)
+            .ok_or(ErrorCode::ArithmeticUnderflow.into())
+    }
 }
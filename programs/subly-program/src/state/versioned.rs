@@ -0,0 +1,107 @@
+use crate::constants::*;
+use crate::error::ErrorCode;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+/// Accounts that store a layout version as their first field after the discriminator.
+/// Instructions only accept the current version; older accounts go through the
+/// matching migrate_* instruction and newer ones are rejected.
+pub trait Versioned {
+    const CURRENT_VERSION: u8;
+
+    fn version(&self) -> u8;
+
+    /// Whether an account of `len` bytes, discriminator included, was written
+    /// before the version field existed. Such accounts are recognized by size
+    fn is_unversioned_len(_len: usize) -> bool {
+        false
+    }
+
+    /// Copy the fields of an unversioned account, `data` without its
+    /// discriminator, onto self, a zeroed account on the current version
+    fn upgrade_unversioned(&mut self, _data: &[u8]) -> Result<()> {
+        err!(ErrorCode::UnsupportedAccountVersion)
+    }
+
+    /// Called by migration once the data is on the current layout, with the
+    /// length it had before. Fields the old account lacked read as zero unless
+    /// set here
+    fn initialize_appended_fields(&mut self, _previous_len: usize) {}
+
+    fn is_current_version(&self) -> bool {
+        self.version() == Self::CURRENT_VERSION
+    }
+}
+
+macro_rules! impl_versioned {
+    ($account:ty, $version:expr) => {
+        impl Versioned for $account {
+            const CURRENT_VERSION: u8 = $version;
+
+            fn version(&self) -> u8 {
+                self.version
+            }
+        }
+    };
+    ($account:ty, $version:expr, $unversioned:ty) => {
+        impl Versioned for $account {
+            const CURRENT_VERSION: u8 = $version;
+
+            fn version(&self) -> u8 {
+                self.version
+            }
+
+            // These were allocated without room for the discriminator, so a
+            // full-size account is accepted too
+            fn is_unversioned_len(len: usize) -> bool {
+                let space = <$unversioned as anchor_lang::Space>::INIT_SPACE;
+                len == space || len == 8 + space
+            }
+
+            fn upgrade_unversioned(&mut self, data: &[u8]) -> Result<()> {
+                read_unversioned::<$unversioned>(data)?.upgrade(self);
+                Ok(())
+            }
+        }
+    };
+}
+
+fn read_unversioned<L: AnchorDeserialize>(mut data: &[u8]) -> Result<L> {
+    Ok(L::deserialize(&mut data).map_err(|_| ErrorCode::InvalidMigrationAccount)?)
+}
+
+impl Versioned for GlobalState {
+    const CURRENT_VERSION: u8 = GLOBAL_STATE_VERSION;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn is_unversioned_len(len: usize) -> bool {
+        len == 8 + GlobalStateV0::INIT_SPACE
+    }
+
+    fn upgrade_unversioned(&mut self, data: &[u8]) -> Result<()> {
+        read_unversioned::<GlobalStateV0>(data)?.upgrade(self);
+        Ok(())
+    }
+
+    fn initialize_appended_fields(&mut self, previous_len: usize) {
+        self.apply_appended_field_defaults(previous_len);
+    }
+}
+
+impl_versioned!(Provider, PROVIDER_VERSION, ProviderV0);
+impl_versioned!(
+    SubscriptionService,
+    SUBSCRIPTION_SERVICE_VERSION,
+    SubscriptionServiceV0
+);
+impl_versioned!(User, USER_VERSION, UserV0);
+impl_versioned!(
+    UserSubscription,
+    USER_SUBSCRIPTION_VERSION,
+    UserSubscriptionV0
+);
+impl_versioned!(StakeAccount, STAKE_ACCOUNT_VERSION, StakeAccountV0);
+impl_versioned!(WithdrawalAllowlist, WITHDRAWAL_ALLOWLIST_VERSION);
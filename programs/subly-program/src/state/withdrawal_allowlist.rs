@@ -0,0 +1,37 @@
+use crate::{constants::MAX_WITHDRAWAL_DESTINATIONS, error::ErrorCode};
+use anchor_lang::prelude::*;
+
+/// Opt-in list of addresses a user's withdrawals may be sent to
+#[account]
+#[derive(InitSpace)]
+pub struct WithdrawalAllowlist {
+    pub version: u8, // Layout version, see Versioned
+    pub user: Pubkey,
+    pub enabled: bool,
+    #[max_len(MAX_WITHDRAWAL_DESTINATIONS)]
+    pub destinations: Vec<Pubkey>,
+    // Changes made while enabled block withdrawals until then, so a stolen key
+    // cannot add its own address and drain the vault right away
+    pub locked_until: i64,
+    pub bump: u8,
+}
+
+impl WithdrawalAllowlist {
+    /// Whether a withdrawal to `destination` is allowed at `current_time`
+    pub fn check_destination(&self, destination: &Pubkey, current_time: i64) -> Result<()> {
+        // The cooldown also covers disabling, otherwise turning the list off would
+        // be an instant bypass
+        require!(
+            current_time >= self.locked_until,
+            ErrorCode::WithdrawalAllowlistCooldown
+        );
+        if !self.enabled {
+            return Ok(());
+        }
+        require!(
+            self.destinations.contains(destination),
+            ErrorCode::WithdrawalDestinationNotAllowed
+        );
+        Ok(())
+    }
+}
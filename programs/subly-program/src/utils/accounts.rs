@@ -0,0 +1,427 @@
+use crate::{constants::*, error::ErrorCode, state::*, utils::load_token_account};
+use anchor_lang::{
+    prelude::*,
+    system_program::{
+        allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+    },
+};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+/// Create a program-signed PDA account with `space` bytes owned by `owner`.
+/// Anyone can send lamports to a PDA before it exists, which makes a plain
+/// create_account fail, so a pre-funded address is topped up, allocated and assigned instead.
+/// `signer` holds the new account's seeds, plus the payer's when the payer is a PDA.
+pub fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let rent_exempt_lamports = Rent::get()?.minimum_balance(space);
+
+    if account.lamports() == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer,
+            ),
+            rent_exempt_lamports,
+            space as u64,
+            owner,
+        );
+    }
+
+    let top_up = rent_exempt_lamports.saturating_sub(account.lamports());
+    if top_up > 0 {
+        transfer(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer,
+            ),
+            top_up,
+        )?;
+    }
+
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer,
+        ),
+        space as u64,
+    )?;
+
+    assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            Assign {
+                account_to_assign: account.clone(),
+            },
+            signer,
+        ),
+        owner,
+    )
+}
+
+/// Text fields may only change once the account has been resized to the
+/// current maximum layout, so a raised limit cannot overflow an older account
+pub fn require_full_layout(account: &AccountInfo, space: usize) -> Result<()> {
+    require!(
+        account.data_len() >= space,
+        ErrorCode::AccountResizeRequired
+    );
+    Ok(())
+}
+
+/// Lamports every user vault keeps so the system account stays rent exempt.
+/// Deposits top the vault up to this floor and it is never counted in deposited_sol.
+pub fn vault_rent_floor() -> Result<u64> {
+    Ok(Rent::get()?.minimum_balance(0))
+}
+
+/// Vault lamports that can leave the vault without dropping below the rent floor
+pub fn spendable_vault_balance(vault: &AccountInfo) -> Result<u64> {
+    // A vault under the floor has nothing to spend, so saturating to zero is intended
+    Ok(vault.lamports().saturating_sub(vault_rent_floor()?))
+}
+
+/// Amount a treasury withdrawal moves: everything spendable for `u64::MAX`,
+/// otherwise `requested` as long as the rent floor stays behind
+pub fn treasury_withdrawal_amount(requested: u64, spendable: u64) -> Result<u64> {
+    if requested == u64::MAX {
+        require!(spendable > 0, ErrorCode::InsufficientBalance);
+        return Ok(spendable);
+    }
+    require!(requested > 0, ErrorCode::InvalidAmount);
+    require!(requested <= spendable, ErrorCode::InsufficientBalance);
+    Ok(requested)
+}
+
+/// Fails unless billing can pay protocol fees to `fee_recipient`: the wallet is
+/// rent exempt, so a fee below the rent minimum still lands, and its USDC
+/// associated token account exists for GlobalState::usdc_mint. The default
+/// pubkey keeps fees in the treasury and needs neither account
+pub fn require_payable_fee_recipient(
+    global_state: &GlobalState,
+    fee_recipient: Pubkey,
+    wallet: Option<&AccountInfo>,
+    usdc_account: Option<&AccountInfo>,
+) -> Result<()> {
+    if fee_recipient == Pubkey::default() {
+        return Ok(());
+    }
+    let (Some(wallet), Some(usdc_account)) = (wallet, usdc_account) else {
+        return err!(ErrorCode::FeeRecipientAccountMissing);
+    };
+    require_keys_eq!(wallet.key(), fee_recipient, ErrorCode::InvalidFeeRecipient);
+    require!(
+        wallet.lamports() >= Rent::get()?.minimum_balance(wallet.data_len()),
+        ErrorCode::FeeRecipientNotPayable
+    );
+
+    let token_account =
+        load_token_account(usdc_account)?.ok_or(ErrorCode::FeeRecipientNotPayable)?;
+    require_keys_eq!(
+        usdc_account.key(),
+        get_associated_token_address_with_program_id(
+            &fee_recipient,
+            &global_state.usdc_mint,
+            usdc_account.owner
+        ),
+        ErrorCode::InvalidFeeRecipient
+    );
+    require_keys_eq!(
+        token_account.owner,
+        fee_recipient,
+        ErrorCode::InvalidFeeRecipient
+    );
+    require_keys_eq!(
+        token_account.mint,
+        global_state.usdc_mint,
+        ErrorCode::InvalidSettlementMint
+    );
+    Ok(())
+}
+
+/// Validate and deserialize a SubscriptionService passed via remaining accounts.
+/// Returns None when the account is not owned by this program, does not carry the
+/// SubscriptionService discriminator and current layout version, or is not the
+/// canonical PDA for its (provider, service_id) pair.
+pub fn load_subscription_service(account_info: &AccountInfo) -> Option<SubscriptionService> {
+    if account_info.owner != &crate::ID {
+        return None;
+    }
+
+    let data = account_info.try_borrow_data().ok()?;
+    if data.len() < 8 || &data[..8] != SubscriptionService::DISCRIMINATOR {
+        return None;
+    }
+
+    // try_deserialize re-checks the discriminator before decoding the fields
+    let service = SubscriptionService::try_deserialize(&mut &data[..]).ok()?;
+    if !service.is_current_version() {
+        return None;
+    }
+
+    let expected_address = Pubkey::create_program_address(
+        &[
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            service.provider.as_ref(),
+            service.service_id.to_le_bytes().as_ref(),
+            &[service.bump],
+        ],
+        &crate::ID,
+    )
+    .ok()?;
+
+    if expected_address != account_info.key() {
+        return None;
+    }
+
+    Some(service)
+}
+
+/// The user's withdrawal allowlist, or None if they never created one.
+/// The caller constrains the address to the user's allowlist PDA
+pub fn load_withdrawal_allowlist(account_info: &AccountInfo) -> Result<Option<WithdrawalAllowlist>> {
+    if account_info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(
+        *account_info.owner,
+        crate::ID,
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+
+    let data = account_info.try_borrow_data()?;
+    let allowlist = WithdrawalAllowlist::try_deserialize(&mut &data[..])?;
+    require!(
+        allowlist.is_current_version(),
+        ErrorCode::UnsupportedAccountVersion
+    );
+    Ok(Some(allowlist))
+}
+
+/// Whether a batch loop can afford to look at one more remaining account
+pub fn has_compute_for_next_scan() -> bool {
+    anchor_lang::solana_program::compute_units::sol_remaining_compute_units()
+        >= MIN_COMPUTE_UNITS_PER_SCAN
+}
+
+/// Byte offsets of the fixed-size SubscriptionService prefix. Everything after
+/// service_id follows the variable-length name, so it is reached by skipping strings
+pub const SERVICE_VERSION_OFFSET: usize = 8;
+pub const SERVICE_PROVIDER_OFFSET: usize = SERVICE_VERSION_OFFSET + 1;
+pub const SERVICE_ID_OFFSET: usize = SERVICE_PROVIDER_OFFSET + 32;
+pub const SERVICE_NAME_OFFSET: usize = SERVICE_ID_OFFSET + 8;
+
+/// The SubscriptionService fields batch views need, read without allocating
+/// the name, description or URL strings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionServiceSummary {
+    pub provider: Pubkey,
+    pub service_id: u64,
+    pub fee_usd: u64,
+    pub billing_frequency_days: u64,
+    pub is_active: bool,
+    pub bump: u8,
+    pub frozen: bool,
+}
+
+/// Read a SubscriptionServiceSummary from raw account data. Returns None unless the
+/// data carries the SubscriptionService discriminator and current layout version.
+/// Must be kept in step with the field order of SubscriptionService.
+pub fn parse_subscription_service_summary(data: &[u8]) -> Option<SubscriptionServiceSummary> {
+    if data.get(..8)? != SubscriptionService::DISCRIMINATOR
+        || *data.get(SERVICE_VERSION_OFFSET)? != SUBSCRIPTION_SERVICE_VERSION
+    {
+        return None;
+    }
+
+    let mut reader = LayoutReader {
+        data,
+        offset: SERVICE_PROVIDER_OFFSET,
+    };
+    let provider = reader.pubkey()?;
+    let service_id = reader.u64()?;
+    reader.skip_string()?; // name
+    reader.skip_string()?; // description
+    let fee_usd = reader.u64()?;
+    let billing_frequency_days = reader.u64()?;
+    reader.skip_string()?; // image_url
+    reader.skip(8)?; // current_subscribers
+    let is_active = reader.bool()?;
+    reader.skip(8 + 1 + 2)?; // created_at, certificate_mode, royalty_bps
+    reader.skip_string()?; // certificate_metadata_uri
+    let bump = reader.u8()?;
+    let frozen = reader.bool()?;
+    reader.skip(1 + 1)?; // deactivated_by_admin, deactivation_reason
+
+    Some(SubscriptionServiceSummary {
+        provider,
+        service_id,
+        fee_usd,
+        billing_frequency_days,
+        is_active,
+        bump,
+        frozen,
+    })
+}
+
+/// Validate and summarize a SubscriptionService passed via remaining accounts.
+/// Applies the same owner, layout and canonical PDA checks as load_subscription_service.
+pub fn load_subscription_service_summary(
+    account_info: &AccountInfo,
+) -> Option<SubscriptionServiceSummary> {
+    if account_info.owner != &crate::ID {
+        return None;
+    }
+
+    let data = account_info.try_borrow_data().ok()?;
+    let summary = parse_subscription_service_summary(&data)?;
+
+    let expected_address = Pubkey::create_program_address(
+        &[
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            summary.provider.as_ref(),
+            summary.service_id.to_le_bytes().as_ref(),
+            &[summary.bump],
+        ],
+        &crate::ID,
+    )
+    .ok()?;
+
+    if expected_address != account_info.key() {
+        return None;
+    }
+
+    Some(summary)
+}
+
+/// Bounds-checked little-endian reader over borsh-encoded account data
+struct LayoutReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> LayoutReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Pubkey::try_from(self.take(32)?).ok()
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        let len = u32::from_le_bytes(self.take(4)?.try_into().ok()?);
+        self.skip(len as usize)
+    }
+}
+
+/// Validate and deserialize a UserSubscription passed via remaining accounts.
+/// Returns None unless the account is a canonical, current-version UserSubscription PDA
+/// owned by this program.
+pub fn load_user_subscription(account_info: &AccountInfo) -> Option<UserSubscription> {
+    if account_info.owner != &crate::ID {
+        return None;
+    }
+
+    let data = account_info.try_borrow_data().ok()?;
+    if data.len() < 8 || &data[..8] != UserSubscription::DISCRIMINATOR {
+        return None;
+    }
+
+    let subscription = UserSubscription::try_deserialize(&mut &data[..]).ok()?;
+    if !subscription.is_current_version() {
+        return None;
+    }
+
+    let expected_address = Pubkey::create_program_address(
+        &[
+            USER_SUBSCRIPTION_SEED.as_bytes(),
+            subscription.user.as_ref(),
+            subscription.provider.as_ref(),
+            subscription.service_id.to_le_bytes().as_ref(),
+            &[subscription.bump],
+        ],
+        &crate::ID,
+    )
+    .ok()?;
+
+    if expected_address != account_info.key() {
+        return None;
+    }
+
+    Some(subscription)
+}
+
+/// Validate and deserialize a Provider passed via remaining accounts.
+/// Returns None unless the account is a canonical, current-version Provider PDA
+/// owned by this program.
+pub fn load_provider(account_info: &AccountInfo) -> Option<Provider> {
+    if account_info.owner != &crate::ID {
+        return None;
+    }
+
+    let data = account_info.try_borrow_data().ok()?;
+    if data.len() < 8 || &data[..8] != Provider::DISCRIMINATOR {
+        return None;
+    }
+
+    let provider = Provider::try_deserialize(&mut &data[..]).ok()?;
+    if !provider.is_current_version() {
+        return None;
+    }
+
+    let expected_address = Pubkey::create_program_address(
+        &[
+            PROVIDER_SEED.as_bytes(),
+            provider.wallet.as_ref(),
+            &[provider.bump],
+        ],
+        &crate::ID,
+    )
+    .ok()?;
+
+    if expected_address != account_info.key() {
+        return None;
+    }
+
+    Some(provider)
+}
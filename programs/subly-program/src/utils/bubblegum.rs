@@ -0,0 +1,206 @@
+//! Minimal Bubblegum CPI used for compressed certificates.
+//! Instructions are built by hand (Anchor discriminator + borsh args) so the
+//! program does not pull in the Metaplex SDK and its Solana version pins.
+use anchor_lang::{
+    error::ErrorCode,
+    prelude::*,
+    solana_program::{instruction::Instruction, program::invoke_signed, pubkey},
+};
+
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey = pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+pub const SPL_NOOP_PROGRAM_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+pub const SPL_ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+const MINT_V1_DISCRIMINATOR: [u8; 8] = [145, 98, 192, 118, 184, 147, 118, 104];
+const BURN_DISCRIMINATOR: [u8; 8] = [116, 110, 29, 56, 107, 219, 42, 93];
+
+// TreeConfig: discriminator, tree_creator, tree_delegate, total_mint_capacity, num_minted
+const TREE_CONFIG_DELEGATE_OFFSET: usize = 8 + 32;
+const TREE_CONFIG_NUM_MINTED_OFFSET: usize = 8 + 32 + 32 + 8;
+
+#[derive(AnchorSerialize)]
+pub struct BubblegumCollection {
+    pub verified: bool,
+    pub key: Pubkey,
+}
+
+#[derive(AnchorSerialize)]
+pub struct BubblegumCreator {
+    pub address: Pubkey,
+    pub verified: bool,
+    pub share: u8,
+}
+
+/// Bubblegum MetadataArgs, with the fields the certificate never sets fixed to None
+#[derive(AnchorSerialize)]
+pub struct BubblegumMetadataArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub token_standard: Option<u8>, // 0 = NonFungible
+    pub collection: Option<BubblegumCollection>,
+    pub uses: Option<u8>,           // Always None
+    pub token_program_version: u8,  // 0 = Original
+    pub creators: Vec<BubblegumCreator>,
+}
+
+/// Leaf fields the client reads from the indexer to prove the certificate for burning
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CompressedLeafProof {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+/// Tree delegate recorded in a Bubblegum TreeConfig account
+pub fn tree_delegate(tree_config: &AccountInfo) -> Result<Pubkey> {
+    require_keys_eq!(
+        *tree_config.owner,
+        BUBBLEGUM_PROGRAM_ID,
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+    let data = tree_config.try_borrow_data()?;
+    let delegate_bytes = data
+        .get(TREE_CONFIG_DELEGATE_OFFSET..TREE_CONFIG_DELEGATE_OFFSET + 32)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let mut delegate = [0u8; 32];
+    delegate.copy_from_slice(delegate_bytes);
+
+    Ok(Pubkey::new_from_array(delegate))
+}
+
+/// Asset id of the next leaf minted into the tree (derived from the tree's mint counter)
+pub fn next_compressed_asset_id(tree_config: &AccountInfo, merkle_tree: &Pubkey) -> Result<Pubkey> {
+    let data = tree_config.try_borrow_data()?;
+    let num_minted_bytes = data
+        .get(TREE_CONFIG_NUM_MINTED_OFFSET..TREE_CONFIG_NUM_MINTED_OFFSET + 8)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let mut nonce_bytes = [0u8; 8];
+    nonce_bytes.copy_from_slice(num_minted_bytes);
+    let nonce = u64::from_le_bytes(nonce_bytes);
+
+    Ok(Pubkey::find_program_address(
+        &[b"asset", merkle_tree.as_ref(), &nonce.to_le_bytes()],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .0)
+}
+
+/// Mint a compressed NFT to `leaf_owner`; the tree delegate PDA signs with `signer_seeds`
+#[allow(clippy::too_many_arguments)]
+pub fn bubblegum_mint_v1<'info>(
+    bubblegum_program: &AccountInfo<'info>,
+    tree_config: &AccountInfo<'info>,
+    leaf_owner: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    tree_delegate: &AccountInfo<'info>,
+    log_wrapper: &AccountInfo<'info>,
+    compression_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    metadata: BubblegumMetadataArgs,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let mut data = MINT_V1_DISCRIMINATOR.to_vec();
+    metadata
+        .serialize(&mut data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let instruction = Instruction {
+        program_id: BUBBLEGUM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(tree_config.key(), false),
+            AccountMeta::new_readonly(leaf_owner.key(), false),
+            AccountMeta::new_readonly(leaf_owner.key(), false), // leaf delegate
+            AccountMeta::new(merkle_tree.key(), false),
+            AccountMeta::new(payer.key(), true),
+            AccountMeta::new_readonly(tree_delegate.key(), true),
+            AccountMeta::new_readonly(log_wrapper.key(), false),
+            AccountMeta::new_readonly(compression_program.key(), false),
+            AccountMeta::new_readonly(system_program.key(), false),
+        ],
+        data,
+    };
+
+    invoke_signed(
+        &instruction,
+        &[
+            tree_config.clone(),
+            leaf_owner.clone(),
+            merkle_tree.clone(),
+            payer.clone(),
+            tree_delegate.clone(),
+            log_wrapper.clone(),
+            compression_program.clone(),
+            system_program.clone(),
+            bubblegum_program.clone(),
+        ],
+        &[signer_seeds],
+    )?;
+
+    Ok(())
+}
+
+/// Burn a compressed NFT owned by `leaf_owner` (who must sign the outer transaction).
+/// `proof_accounts` are the Merkle proof nodes, passed through as remaining accounts.
+#[allow(clippy::too_many_arguments)]
+pub fn bubblegum_burn<'info>(
+    bubblegum_program: &AccountInfo<'info>,
+    tree_config: &AccountInfo<'info>,
+    leaf_owner: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    log_wrapper: &AccountInfo<'info>,
+    compression_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    proof_accounts: &[AccountInfo<'info>],
+    leaf: &CompressedLeafProof,
+) -> Result<()> {
+    let mut data = BURN_DISCRIMINATOR.to_vec();
+    leaf.serialize(&mut data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(tree_config.key(), false),
+        AccountMeta::new_readonly(leaf_owner.key(), true),
+        AccountMeta::new_readonly(leaf_owner.key(), false), // leaf delegate
+        AccountMeta::new(merkle_tree.key(), false),
+        AccountMeta::new_readonly(log_wrapper.key(), false),
+        AccountMeta::new_readonly(compression_program.key(), false),
+        AccountMeta::new_readonly(system_program.key(), false),
+    ];
+    accounts.extend(
+        proof_accounts
+            .iter()
+            .map(|node| AccountMeta::new_readonly(node.key(), false)),
+    );
+
+    let mut account_infos = vec![
+        tree_config.clone(),
+        leaf_owner.clone(),
+        merkle_tree.clone(),
+        log_wrapper.clone(),
+        compression_program.clone(),
+        system_program.clone(),
+        bubblegum_program.clone(),
+    ];
+    account_infos.extend(proof_accounts.iter().cloned());
+
+    invoke_signed(
+        &Instruction {
+            program_id: BUBBLEGUM_PROGRAM_ID,
+            accounts,
+            data,
+        },
+        &account_infos,
+        &[],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::{constants::*, error::ErrorCode, state::*, utils::has_allowed_scheme};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    metadata::mpl_token_metadata::types::{Creator, DataV2},
+    token_2022::spl_token_2022::{
+        extension::ExtensionType,
+        state::{Account as TokenAccountState, Mint as MintState},
+    },
+};
+
+/// Build the Token Metadata payload for a subscription certificate.
+/// The service name is truncated to the Token Metadata name limit on a char boundary.
+/// The provider receives all royalties. The certificate authority is listed as a
+/// zero-share creator and is verified because it signs the metadata CPI; the
+/// provider starts unverified and can only be verified by its own signature.
+pub fn certificate_metadata(
+    service: &SubscriptionService,
+    certificate_authority: Pubkey,
+) -> DataV2 {
+    DataV2 {
+        name: metadata_name(&service.name),
+        symbol: CERTIFICATE_SYMBOL.to_string(),
+        uri: certificate_metadata_uri(service),
+        seller_fee_basis_points: service.royalty_bps,
+        creators: Some(vec![
+            Creator {
+                address: service.provider,
+                verified: false,
+                share: 100,
+            },
+            Creator {
+                address: certificate_authority,
+                verified: true,
+                share: 0,
+            },
+        ]),
+        collection: None,
+        uses: None,
+    }
+}
+
+/// Metadata JSON URI for a service's certificates, falling back to the protocol default
+pub fn certificate_metadata_uri(service: &SubscriptionService) -> String {
+    if service.certificate_metadata_uri.is_empty() {
+        format!(
+            "{}{}/{}.json",
+            DEFAULT_CERTIFICATE_URI_BASE, service.provider, service.service_id
+        )
+    } else {
+        service.certificate_metadata_uri.clone()
+    }
+}
+
+/// An empty URI selects the protocol default; anything else must be an https or ipfs URL
+pub fn validate_certificate_metadata_uri(uri: &str) -> Result<()> {
+    require!(uri.len() <= MAX_URL_LENGTH, ErrorCode::UrlTooLong);
+    require!(
+        uri.is_empty() || has_allowed_scheme(uri),
+        ErrorCode::InvalidCertificateUri
+    );
+    Ok(())
+}
+
+/// Token Metadata payload for a provider's certificate collection NFT
+pub fn certificate_collection_metadata(provider_name: &str) -> DataV2 {
+    DataV2 {
+        name: metadata_name(provider_name),
+        symbol: CERTIFICATE_SYMBOL.to_string(),
+        uri: String::new(),
+        seller_fee_basis_points: 0,
+        creators: None,
+        collection: None,
+        uses: None,
+    }
+}
+
+/// Size of a certificate mint. Soulbound (Token-2022) mints carry the
+/// NonTransferable and MintCloseAuthority extensions
+pub fn certificate_mint_space(soulbound: bool) -> Result<usize> {
+    let extensions: &[ExtensionType] = if soulbound {
+        &[
+            ExtensionType::NonTransferable,
+            ExtensionType::MintCloseAuthority,
+        ]
+    } else {
+        &[]
+    };
+    Ok(ExtensionType::try_calculate_account_len::<MintState>(extensions)?)
+}
+
+/// Size of a certificate token account. The Associated Token program adds
+/// ImmutableOwner and NonTransferableAccount for soulbound mints
+pub fn certificate_token_account_space(soulbound: bool) -> Result<usize> {
+    let extensions: &[ExtensionType] = if soulbound {
+        &[
+            ExtensionType::ImmutableOwner,
+            ExtensionType::NonTransferableAccount,
+        ]
+    } else {
+        &[]
+    };
+    Ok(ExtensionType::try_calculate_account_len::<TokenAccountState>(extensions)?)
+}
+
+fn metadata_name(name: &str) -> String {
+    let mut name_end = name.len().min(MAX_CERTIFICATE_NAME_LENGTH);
+    while !name.is_char_boundary(name_end) {
+        name_end -= 1;
+    }
+    name[..name_end].to_string()
+}
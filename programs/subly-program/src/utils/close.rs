@@ -0,0 +1,53 @@
+use crate::{error::ErrorCode, state::GlobalState};
+use anchor_lang::prelude::*;
+
+/// Where the lamports of a closed protocol account go
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RentDestination {
+    /// Whoever paid the account's rent, closing it themselves
+    Payer,
+    /// GlobalState::rent_collector, when the authority or anyone else closes it
+    RentCollector,
+}
+
+/// Rent goes back to the account's payer (user or provider) only when they
+/// close it themselves. Cleanup by anyone else goes to the rent collector
+pub fn rent_destination(closer: &Pubkey, payer: &Pubkey) -> RentDestination {
+    if closer == payer {
+        RentDestination::Payer
+    } else {
+        RentDestination::RentCollector
+    }
+}
+
+/// Close `account` on behalf of `closer`, sending its lamports where
+/// rent_destination says. `rent_collector` is only needed when the rent goes
+/// there and must be the configured one. Returns the destination and the
+/// lamports it received
+pub fn close_with_rent_policy<'info, T>(
+    account: &Account<'info, T>,
+    global_state: &GlobalState,
+    closer: &Pubkey,
+    payer: &AccountInfo<'info>,
+    rent_collector: Option<&AccountInfo<'info>>,
+) -> Result<(Pubkey, u64)>
+where
+    T: AccountSerialize + AccountDeserialize + Owner + Clone,
+{
+    let destination = match rent_destination(closer, payer.key) {
+        RentDestination::Payer => payer,
+        RentDestination::RentCollector => {
+            let rent_collector = rent_collector.ok_or(ErrorCode::InvalidRentCollector)?;
+            require_keys_eq!(
+                rent_collector.key(),
+                global_state.rent_collector(),
+                ErrorCode::InvalidRentCollector
+            );
+            rent_collector
+        }
+    };
+
+    let lamports = account.to_account_info().lamports();
+    account.close(destination.clone())?;
+    Ok((destination.key(), lamports))
+}
@@ -0,0 +1,90 @@
+use crate::{constants::PROMO_MESSAGE_DOMAIN, error::ErrorCode};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        ed25519_program,
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+};
+
+/// Byte layout of an ed25519 program instruction carrying one signature:
+/// a count and padding byte, then seven u16 offsets
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_LEN: usize = 14;
+const ED25519_PUBKEY_LEN: usize = 32;
+/// Instruction index meaning "this instruction's own data"
+const ED25519_CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// The message a provider signs off-chain to grant a promo. Clients must build
+/// the same bytes: domain, user, service_id, discount_bps, expiry and nonce,
+/// integers little-endian
+pub fn promo_claim_message(
+    user: &Pubkey,
+    service_id: u64,
+    discount_bps: u16,
+    expiry: i64,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = PROMO_MESSAGE_DOMAIN.to_vec();
+    message.extend_from_slice(user.as_ref());
+    message.extend_from_slice(&service_id.to_le_bytes());
+    message.extend_from_slice(&discount_bps.to_le_bytes());
+    message.extend_from_slice(&expiry.to_le_bytes());
+    message.extend_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+/// Public key and message verified by an ed25519 program instruction, or None
+/// unless it holds exactly one signature whose key, signature and message all
+/// live in its own data. Offsets into other instructions are refused, as they
+/// would let a caller point the check at bytes it did not sign
+pub fn parse_ed25519_instruction(data: &[u8]) -> Option<(Pubkey, &[u8])> {
+    if *data.first()? != 1 {
+        return None;
+    }
+    let offsets = data.get(ED25519_OFFSETS_START..ED25519_OFFSETS_START + ED25519_OFFSETS_LEN)?;
+    let read = |index: usize| u16::from_le_bytes([offsets[index * 2], offsets[index * 2 + 1]]);
+    let signature_instruction = read(1);
+    let pubkey_offset = usize::from(read(2));
+    let pubkey_instruction = read(3);
+    let message_offset = usize::from(read(4));
+    let message_len = usize::from(read(5));
+    let message_instruction = read(6);
+    if signature_instruction != ED25519_CURRENT_INSTRUCTION
+        || pubkey_instruction != ED25519_CURRENT_INSTRUCTION
+        || message_instruction != ED25519_CURRENT_INSTRUCTION
+    {
+        return None;
+    }
+
+    let pubkey = data.get(pubkey_offset..pubkey_offset.checked_add(ED25519_PUBKEY_LEN)?)?;
+    let message = data.get(message_offset..message_offset.checked_add(message_len)?)?;
+    Some((Pubkey::try_from(pubkey).ok()?, message))
+}
+
+/// Require that the instruction right before the current one is an ed25519
+/// program check of `signer`'s signature over `message`. The ed25519 program
+/// fails the whole transaction on a bad signature, so only what it verified is
+/// left to compare
+pub fn verify_preceding_ed25519(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let previous_index = current_index
+        .checked_sub(1)
+        .ok_or(ErrorCode::PromoSignatureMissing)?;
+    let instruction = load_instruction_at_checked(usize::from(previous_index), instructions_sysvar)?;
+    require_keys_eq!(
+        instruction.program_id,
+        ed25519_program::ID,
+        ErrorCode::PromoSignatureMissing
+    );
+
+    let (signed_by, signed_message) =
+        parse_ed25519_instruction(&instruction.data).ok_or(ErrorCode::InvalidPromoSignature)?;
+    require_keys_eq!(signed_by, *signer, ErrorCode::PromoSignerMismatch);
+    require!(signed_message == message, ErrorCode::InvalidPromoSignature);
+    Ok(())
+}
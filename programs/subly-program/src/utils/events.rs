@@ -0,0 +1,36 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{instruction::Instruction, program::invoke_signed},
+    Event,
+};
+
+/// Emit an event through a self-CPI so it is recorded in the transaction's inner
+/// instructions instead of the (truncatable) program logs.
+/// Same wire format as anchor's emit_cpi!, but usable from methods on a
+/// #[event_cpi] accounts struct where no `ctx` binding is in scope.
+pub fn emit_cpi_event<'info, E: Event>(
+    event_authority: &AccountInfo<'info>,
+    event_authority_bump: u8,
+    event: E,
+) -> Result<()> {
+    let data: Vec<u8> = anchor_lang::event::EVENT_IX_TAG_LE
+        .iter()
+        .copied()
+        .chain(event.data())
+        .collect();
+
+    let instruction = Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![AccountMeta::new_readonly(event_authority.key(), true)],
+    );
+
+    // Seed fixed by anchor's #[event_cpi] attribute
+    invoke_signed(
+        &instruction,
+        std::slice::from_ref(event_authority),
+        &[&[b"__event_authority", &[event_authority_bump]]],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Protocol fee split for every payment path. The multiplication is done in u128
+//! so no amount representable in u64 can overflow, whatever the fee rate.
+
+use crate::{constants::MAX_PROTOCOL_FEE_BPS, error::ErrorCode};
+use anchor_lang::prelude::*;
+
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Split `amount` into (protocol fee, provider share) at `fee_bps`, rounding the
+/// fee down. The two parts always add back up to `amount`
+pub fn split_payment(amount: u64, fee_bps: u16) -> Result<(u64, u64)> {
+    require!(
+        fee_bps <= MAX_PROTOCOL_FEE_BPS,
+        ErrorCode::InvalidProtocolFee
+    );
+
+    // fee_bps is below BPS_DENOMINATOR, so the fee never exceeds amount and fits in u64
+    let fee = u64::try_from(amount as u128 * fee_bps as u128 / BPS_DENOMINATOR)
+        .map_err(|_| ErrorCode::ArithmeticOverflow)?;
+    let provider = amount
+        .checked_sub(fee)
+        .ok_or(ErrorCode::ArithmeticUnderflow)?;
+
+    require!(
+        fee.checked_add(provider) == Some(amount),
+        ErrorCode::ArithmeticOverflow
+    );
+    Ok((fee, provider))
+}
@@ -0,0 +1,28 @@
+//! Integer-only formatting for program logs. Floats are never used on-chain:
+//! float formatting is expensive in compute units and not deterministic enough
+//! for auditors to sign off on.
+
+/// `1_500_000_000` lamports -> `"1.500000000"`
+pub fn lamports_to_sol_string(lamports: u64) -> String {
+    format_fixed_point(lamports, 9)
+}
+
+/// `1234` cents -> `"12.34"`
+pub fn cents_to_usd_string(cents: u64) -> String {
+    format_fixed_point(cents, 2)
+}
+
+/// `725` basis points -> `"7.25"` (percent)
+pub fn bps_to_percent_string(bps: u64) -> String {
+    format_fixed_point(bps, 2)
+}
+
+fn format_fixed_point(value: u64, decimals: u32) -> String {
+    let scale = 10u64.pow(decimals);
+    format!(
+        "{}.{:0width$}",
+        value / scale,
+        value % scale,
+        width = decimals as usize
+    )
+}
@@ -0,0 +1,127 @@
+use crate::{
+    constants::GLOBAL_STATE_V1_SPACE,
+    error::ErrorCode,
+    state::{GlobalState, Versioned},
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+/// Whether `data` is on an older version, or on the current one but written
+/// before its last fields were appended
+pub fn needs_migration<T: Space + Versioned>(data: &[u8]) -> bool {
+    data.len() < 8 + T::INIT_SPACE || data[8] != T::CURRENT_VERSION
+}
+
+/// Authority stored in GlobalState data of any supported layout, read in place
+/// because older layouts do not deserialize as the current one
+pub fn stored_global_state_authority(data: &[u8]) -> Result<Pubkey> {
+    let unversioned = GlobalState::is_unversioned_len(data.len());
+    require!(
+        (unversioned || data.len() >= 8 + GLOBAL_STATE_V1_SPACE)
+            && &data[..8] == GlobalState::DISCRIMINATOR,
+        ErrorCode::InvalidMigrationAccount
+    );
+    // Version 0 has no version byte in front of the authority
+    let offset = if unversioned { 8 } else { 9 };
+    Ok(Pubkey::try_from(&data[offset..offset + 32])
+        .map_err(|_| ErrorCode::InvalidMigrationAccount)?)
+}
+
+/// Rewrite account data from an older layout into the current layout of `T`.
+/// Returns the version the data was on and the upgraded bytes, sized for the current layout.
+pub fn upgrade_account_data<T>(data: &[u8]) -> Result<(u8, Vec<u8>)>
+where
+    T: AccountDeserialize + AccountSerialize + Discriminator + Space + Versioned,
+{
+    require!(
+        data.len() >= 8 && &data[..8] == T::DISCRIMINATOR,
+        ErrorCode::InvalidMigrationAccount
+    );
+
+    require!(
+        needs_migration::<T>(data),
+        ErrorCode::AccountAlreadyMigrated
+    );
+
+    // Version 0 predates the version field, so it is recognized by its size
+    let target_len = 8 + T::INIT_SPACE;
+    let unversioned = T::is_unversioned_len(data.len());
+    let from_version = if unversioned {
+        0
+    } else {
+        require!(data.len() > 8, ErrorCode::InvalidMigrationAccount);
+        data[8]
+    };
+
+    let mut upgraded = vec![0u8; target_len];
+    upgraded[..8].copy_from_slice(&data[..8]);
+    match from_version {
+        // v0 -> v1: copied field by field from the old layout once the zeroed
+        // current layout is deserialized below
+        0 if unversioned => {}
+        // Fields appended to the current version start out zeroed
+        version if version == T::CURRENT_VERSION => {
+            upgraded[8..data.len()].copy_from_slice(&data[8..])
+        }
+        _ => return err!(ErrorCode::UnsupportedAccountVersion),
+    }
+    upgraded[8] = T::CURRENT_VERSION;
+
+    let mut account =
+        T::try_deserialize(&mut &upgraded[..]).map_err(|_| ErrorCode::InvalidMigrationAccount)?;
+    require!(
+        account.is_current_version(),
+        ErrorCode::InvalidMigrationAccount
+    );
+    if unversioned {
+        account.upgrade_unversioned(&data[8..])?;
+    }
+
+    // Written over the same bytes, so anything past the serialized fields stays zeroed
+    account.initialize_appended_fields(data.len());
+    account.try_serialize(&mut upgraded.as_mut_slice())?;
+
+    Ok((from_version, upgraded))
+}
+
+/// Upgrade a program account in place, growing it to the current layout.
+/// `payer` funds any rent the larger account needs. Returns the previous version.
+pub fn migrate_account<'info, T>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<u8>
+where
+    T: AccountDeserialize + AccountSerialize + Discriminator + Owner + Space + Versioned,
+{
+    require_keys_eq!(
+        *account.owner,
+        T::owner(),
+        ErrorCode::InvalidMigrationAccount
+    );
+
+    let (from_version, upgraded) = upgrade_account_data::<T>(&account.try_borrow_data()?)?;
+
+    let rent_due = Rent::get()?
+        .minimum_balance(upgraded.len())
+        .saturating_sub(account.lamports());
+    if rent_due > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_due,
+        )?;
+    }
+
+    account.resize(upgraded.len())?;
+    account.try_borrow_mut_data()?.copy_from_slice(&upgraded);
+
+    Ok(from_version)
+}
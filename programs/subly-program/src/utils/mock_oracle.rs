@@ -0,0 +1,64 @@
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+use pyth_sdk_solana::state::{
+    AccountType, PriceInfo, PriceStatus, SolanaPriceAccount, MAGIC, VERSION,
+};
+
+/// Size of a Pyth price account, what init_mock_price_feed allocates
+pub const MOCK_PRICE_FEED_SPACE: usize = std::mem::size_of::<SolanaPriceAccount>();
+
+/// Exponents a mock feed may use: fine enough that any whole-cent price
+/// survives the round trip through get_sol_usd_price_cents unchanged
+pub const MOCK_PRICE_EXPO_RANGE: std::ops::RangeInclusive<i32> = -12..=-2;
+
+/// Bytes of a Trading Pyth price account quoting `price_cents` at `expo`,
+/// published at `publish_time`. Every field the oracle does not read stays zero
+pub fn mock_price_account_data(price_cents: u64, expo: i32, publish_time: i64) -> Result<Vec<u8>> {
+    require!(
+        price_cents > 0 && MOCK_PRICE_EXPO_RANGE.contains(&expo),
+        ErrorCode::InvalidPrice
+    );
+    // cents * 10^-expo / 100, exact since expo <= -2
+    let price = 10_i64
+        .checked_pow(expo.unsigned_abs() - 2)
+        .and_then(|scale| i64::try_from(price_cents).ok()?.checked_mul(scale))
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    let mut data = vec![0; MOCK_PRICE_FEED_SPACE];
+    let mut put =
+        |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, magic),
+        &MAGIC.to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, ver),
+        &VERSION.to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, atype),
+        &(AccountType::Price as u32).to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, size),
+        &(MOCK_PRICE_FEED_SPACE as u32).to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, expo),
+        &expo.to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, timestamp),
+        &publish_time.to_le_bytes(),
+    );
+    let agg = std::mem::offset_of!(SolanaPriceAccount, agg);
+    put(
+        agg + std::mem::offset_of!(PriceInfo, price),
+        &price.to_le_bytes(),
+    );
+    put(
+        agg + std::mem::offset_of!(PriceInfo, status),
+        &[PriceStatus::Trading as u8],
+    );
+    Ok(data)
+}
@@ -0,0 +1,31 @@
+pub mod accounts;
+pub mod bubblegum;
+pub mod certificate;
+pub mod close;
+pub mod ed25519;
+pub mod events;
+pub mod fees;
+pub mod format;
+pub mod migration;
+#[cfg(feature = "mock")]
+pub mod mock_oracle;
+pub mod oracle;
+pub mod staking;
+pub mod text;
+pub mod token;
+
+pub use accounts::*;
+pub use bubblegum::*;
+pub use certificate::*;
+pub use close::*;
+pub use ed25519::*;
+pub use events::*;
+pub use fees::*;
+pub use format::*;
+pub use migration::*;
+#[cfg(feature = "mock")]
+pub use mock_oracle::*;
+pub use oracle::*;
+pub use staking::*;
+pub use text::*;
+pub use token::*;
@@ -0,0 +1,231 @@
+use crate::{constants::*, error::ErrorCode, state::GlobalState, utils::cents_to_usd_string};
+use anchor_lang::{prelude::*, solana_program::native_token::LAMPORTS_PER_SOL};
+use pyth_sdk_solana::state::SolanaPriceAccount;
+use std::ops::RangeInclusive;
+
+/// Read the SOL/USD price in USD cents from a Pyth price account, rejecting
+/// prices older than GlobalState's max price age, outside its sanity range or
+/// too far from the last observed price. While the feed has no fresh price, a
+/// live price override stands in for it
+pub fn get_sol_usd_price_cents(
+    price_feed_account: &AccountInfo,
+    global_state: &GlobalState,
+) -> Result<u64> {
+    let price_cents = sol_usd_price_cents_or_override_at(
+        price_feed_account,
+        global_state,
+        Clock::get()?.unix_timestamp,
+    )?;
+    global_state.check_price_deviation(price_cents)?;
+    Ok(price_cents)
+}
+
+/// The Pyth price as of `current_time` under GlobalState's max price age and
+/// sanity range. Only when Pyth has no fresh price is the authority's price
+/// override served, and only until it expires; a feed that cannot be parsed or
+/// a price outside the sanity range fails as it would without an override
+pub fn sol_usd_price_cents_or_override_at(
+    price_feed_account: &AccountInfo,
+    global_state: &GlobalState,
+    current_time: i64,
+) -> Result<u64> {
+    let error = match sol_usd_price_cents_at(
+        price_feed_account,
+        global_state.max_price_age(),
+        global_state.sol_price_bounds(),
+        current_time,
+    ) {
+        Ok(price_cents) => return Ok(price_cents),
+        Err(Error::AnchorError(error))
+            if error.error_code_number == u32::from(ErrorCode::PriceNotAvailable) =>
+        {
+            Error::AnchorError(error)
+        }
+        Err(error) => return Err(error),
+    };
+
+    let price_cents = global_state.price_override_at(current_time).ok_or(error)?;
+    msg!(
+        "WARNING: Pyth SOL/USD price unavailable, using MANUAL PRICE OVERRIDE of ${} valid until {}",
+        cents_to_usd_string(price_cents),
+        global_state.price_override_valid_until
+    );
+    Ok(price_cents)
+}
+
+/// get_sol_usd_price_cents for instructions that can write GlobalState, which
+/// also make the price the circuit breaker's new reference
+pub fn observe_sol_usd_price_cents(
+    price_feed_account: &AccountInfo,
+    global_state: &mut GlobalState,
+) -> Result<u64> {
+    let price_cents = get_sol_usd_price_cents(price_feed_account, global_state)?;
+    global_state.record_observed_price(price_cents);
+    Ok(price_cents)
+}
+
+/// get_sol_usd_price_cents as of `current_time`, accepting prices at most
+/// `max_age` seconds old and within `bounds` cents
+pub fn sol_usd_price_cents_at(
+    price_feed_account: &AccountInfo,
+    max_age: u64,
+    bounds: RangeInclusive<u64>,
+    current_time: i64,
+) -> Result<u64> {
+    let price_feed = SolanaPriceAccount::account_info_to_feed(price_feed_account)
+        .map_err(|_| ErrorCode::InvalidPriceFeed)?;
+
+    let price = price_feed
+        .get_price_no_older_than(current_time, max_age)
+        .ok_or(ErrorCode::PriceNotAvailable)?;
+
+    let price_cents = pyth_price_to_cents(price.price, price.expo)?;
+
+    require!(
+        price_cents > 0 && bounds.contains(&price_cents),
+        ErrorCode::InvalidPrice
+    );
+
+    Ok(price_cents)
+}
+
+/// Pyth price and exponent -> USD cents, truncating anything below a cent
+pub fn pyth_price_to_cents(price: i64, expo: i32) -> Result<u64> {
+    require!(price > 0, ErrorCode::InvalidPrice);
+
+    // Pyth SOL/USD typically has an exponent of -8, i.e. units of 10^-8 USD
+    if expo >= 0 {
+        Ok((price as u64)
+            .checked_mul(10_u64.checked_pow(expo as u32).ok_or(ErrorCode::ArithmeticOverflow)?)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .checked_mul(100)
+            .ok_or(ErrorCode::ArithmeticOverflow)?)
+    } else {
+        let divisor = 10_u64
+            .checked_pow(expo.unsigned_abs())
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok((price as u64)
+            .checked_mul(100)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .checked_div(divisor)
+            .ok_or(ErrorCode::ArithmeticOverflow)?)
+    }
+}
+
+/// USD cents -> SOL lamports at `sol_usd_cents` per SOL, widened to u128 so the
+/// intermediate product cannot overflow
+pub fn convert_usd_to_sol_lamports(usd_cents: u64, sol_usd_cents: u64) -> Result<u64> {
+    require_convertible_usd(usd_cents)?;
+    let lamports = (usd_cents as u128)
+        .checked_mul(LAMPORTS_PER_SOL as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(sol_usd_cents as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    Ok(u64::try_from(lamports).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+}
+
+/// Lamports locked when subscribing: `lock_periods` billing periods of the fee
+/// at the given price. Fails instead of wrapping when the lock is unrepresentable
+pub fn subscription_lock_lamports(
+    fee_usd_cents: u64,
+    sol_usd_cents: u64,
+    lock_periods: u64,
+) -> Result<u64> {
+    require_fee_within_ceiling(fee_usd_cents)?;
+    Ok(convert_usd_to_sol_lamports(fee_usd_cents, sol_usd_cents)?
+        .checked_mul(lock_periods)
+        .ok_or(ErrorCode::ArithmeticOverflow)?)
+}
+
+/// Lamports released when a SOL-locked subscription from before locks were
+/// recorded ends. Those all locked SUBSCRIPTION_LOCK_PERIODS, recomputed at
+/// today's price, so it is capped at what the user still has locked: a price
+/// drop since subscribing must not release another subscription's collateral
+pub fn subscription_unlock_lamports(
+    fee_usd_cents: u64,
+    sol_usd_cents: u64,
+    locked_lamports: u64,
+) -> Result<u64> {
+    Ok(
+        subscription_lock_lamports(fee_usd_cents, sol_usd_cents, SUBSCRIPTION_LOCK_PERIODS)?
+            .min(locked_lamports),
+    )
+}
+
+/// USD cents -> settlement token base units for a USD-pegged token. No price is
+/// involved: one token is one dollar
+pub fn convert_usd_to_token_amount(usd_cents: u64, decimals: u8) -> Result<u64> {
+    require_convertible_usd(usd_cents)?;
+    let scale = 10_u128
+        .checked_pow(decimals as u32)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let amount = (usd_cents as u128)
+        .checked_mul(scale)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(100)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    Ok(u64::try_from(amount).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+}
+
+/// Settlement token base units locked when a subscription is covered in USDC
+pub fn subscription_lock_token_amount(
+    fee_usd_cents: u64,
+    decimals: u8,
+    lock_periods: u64,
+) -> Result<u64> {
+    require_fee_within_ceiling(fee_usd_cents)?;
+    Ok(convert_usd_to_token_amount(fee_usd_cents, decimals)?
+        .checked_mul(lock_periods)
+        .ok_or(ErrorCode::ArithmeticOverflow)?)
+}
+
+/// Service fees must be positive and within both the configured cap
+/// (GlobalState::service_fee_cap) and MAX_SERVICE_FEE_USD_CENTS
+pub fn validate_service_fee(fee_usd_cents: u64, max_fee_usd_cents: u64) -> Result<()> {
+    require!(
+        fee_usd_cents > 0 && fee_usd_cents <= max_fee_usd_cents,
+        ErrorCode::InvalidFeeAmount
+    );
+    require_fee_within_ceiling(fee_usd_cents)
+}
+
+/// Defensive bound for helpers taking a single period's fee. Registration
+/// already enforces it, so this only trips on accounts that bypassed it
+fn require_fee_within_ceiling(fee_usd_cents: u64) -> Result<()> {
+    require!(
+        fee_usd_cents <= MAX_SERVICE_FEE_USD_CENTS,
+        ErrorCode::InvalidFeeAmount
+    );
+    Ok(())
+}
+
+/// Defensive bound for helpers converting any USD amount, see MAX_CONVERTIBLE_USD_CENTS
+fn require_convertible_usd(usd_cents: u64) -> Result<()> {
+    require!(
+        usd_cents <= MAX_CONVERTIBLE_USD_CENTS,
+        ErrorCode::InvalidFeeAmount
+    );
+    Ok(())
+}
+
+/// SOL lamports -> settlement token base units at `sol_usd_cents` per SOL
+pub fn convert_sol_to_token_amount(
+    sol_lamports: u64,
+    sol_usd_cents: u64,
+    decimals: u8,
+) -> Result<u64> {
+    let scale = 10_u128
+        .checked_pow(decimals as u32)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let amount = (sol_lamports as u128)
+        .checked_mul(sol_usd_cents as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_mul(scale) // cents -> base units * 100
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(100 * LAMPORTS_PER_SOL as u128) // cents per dollar * LAMPORTS_PER_SOL
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    Ok(u64::try_from(amount).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+}
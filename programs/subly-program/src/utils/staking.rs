@@ -0,0 +1,55 @@
+//! JitoSOL estimates and yield accrual. Rates are in basis points and every
+//! product is widened to u128, so only results that do not fit in u64 fail.
+
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+
+const BPS_DENOMINATOR: u128 = 10_000;
+const SECONDS_PER_YEAR: u128 = 31_536_000;
+const MONTHS_PER_YEAR: u128 = 12;
+
+/// JitoSOL credited for staking `lamports`: a conservative 98% of the SOL, as
+/// the pool mints slightly less than 1:1
+pub fn estimate_staked_jito_sol(lamports: u64) -> u64 {
+    // 98/100 of a u64 always fits back into a u64
+    (lamports as u128 * 98 / 100) as u64
+}
+
+/// Lamports expected back for redeeming `jito_sol_amount` at `jito_apy_bps`,
+/// rounded down
+pub fn estimate_unstaked_lamports(jito_sol_amount: u64, jito_apy_bps: u16) -> Result<u64> {
+    let lamports =
+        jito_sol_amount as u128 * (BPS_DENOMINATOR + jito_apy_bps as u128) / BPS_DENOMINATOR;
+
+    Ok(u64::try_from(lamports).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+}
+
+/// JitoSOL to redeem so that `estimate_unstaked_lamports` covers `lamports`,
+/// rounded up. Never more than `lamports`, since the multiplier is at least 1
+pub fn jito_sol_needed_for(lamports: u64, jito_apy_bps: u16) -> u64 {
+    (lamports as u128 * BPS_DENOMINATOR).div_ceil(BPS_DENOMINATOR + jito_apy_bps as u128) as u64
+}
+
+/// One month of yield on `deposited_lamports` at `jito_apy_bps`, rounded down
+pub fn monthly_yield_lamports(deposited_lamports: u64, jito_apy_bps: u16) -> u64 {
+    // At most 6.55x the deposit spread over 12 months, so it fits in u64
+    (deposited_lamports as u128 * jito_apy_bps as u128 / BPS_DENOMINATOR / MONTHS_PER_YEAR) as u64
+}
+
+/// Yield accrued on `staked_lamports` at `apy_bps` over `elapsed_seconds`, rounded down
+pub fn accrued_yield_lamports(
+    staked_lamports: u64,
+    apy_bps: u64,
+    elapsed_seconds: i64,
+) -> Result<u64> {
+    require!(elapsed_seconds >= 0, ErrorCode::ArithmeticUnderflow);
+
+    let yield_lamports = (staked_lamports as u128)
+        .checked_mul(apy_bps as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_mul(elapsed_seconds as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        / (BPS_DENOMINATOR * SECONDS_PER_YEAR);
+
+    Ok(u64::try_from(yield_lamports).map_err(|_| ErrorCode::ArithmeticOverflow)?)
+}
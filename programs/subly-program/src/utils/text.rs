@@ -0,0 +1,99 @@
+//! Validation for the provider and service strings that wallets and UIs display.
+//! Length limits alone let control characters, invisible characters and padded
+//! names into accounts, which makes spoofed listings easy to build.
+
+use crate::{constants::*, error::ErrorCode};
+use anchor_lang::prelude::*;
+
+/// Characters that render as nothing (or reorder text) and so let two listings
+/// look identical: zero-width and joiner characters, bidi controls, BOM, soft hyphen
+const INVISIBLE_CHARS: &[char] = &[
+    '\u{00AD}', '\u{180E}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{200E}', '\u{200F}', '\u{202A}',
+    '\u{202B}', '\u{202C}', '\u{202D}', '\u{202E}', '\u{2060}', '\u{2061}', '\u{2062}', '\u{2063}',
+    '\u{2064}', '\u{2066}', '\u{2067}', '\u{2068}', '\u{2069}', '\u{FEFF}',
+];
+
+/// Lookalikes folded onto the ASCII letter they imitate when normalizing names
+const CONFUSABLES: &[(char, char)] = &[
+    ('\u{0131}', 'i'), // Latin dotless i
+    ('\u{0430}', 'a'), // Cyrillic
+    ('\u{0435}', 'e'),
+    ('\u{043E}', 'o'),
+    ('\u{0440}', 'p'),
+    ('\u{0441}', 'c'),
+    ('\u{0443}', 'y'),
+    ('\u{0445}', 'x'),
+    ('\u{0456}', 'i'),
+    ('\u{0458}', 'j'),
+    ('\u{0455}', 's'),
+    ('\u{03BF}', 'o'), // Greek
+    ('\u{03B1}', 'a'),
+    ('\u{03BD}', 'v'),
+];
+
+/// URL schemes accepted for images and metadata
+pub const ALLOWED_URL_SCHEMES: &[&str] = &["https://", "ipfs://"];
+
+/// Names: no control or invisible characters and no leading or trailing whitespace
+pub fn validate_name(name: &str) -> Result<()> {
+    require!(name.len() <= MAX_NAME_LENGTH, ErrorCode::NameTooLong);
+    validate_display_text(name, false)
+}
+
+/// Descriptions follow the name rules but may span lines
+pub fn validate_description(description: &str) -> Result<()> {
+    require!(
+        description.len() <= MAX_DESCRIPTION_LENGTH,
+        ErrorCode::DescriptionTooLong
+    );
+    validate_display_text(description, true)
+}
+
+/// Image URLs are required and must use one of ALLOWED_URL_SCHEMES
+pub fn validate_image_url(url: &str) -> Result<()> {
+    require!(url.len() <= MAX_URL_LENGTH, ErrorCode::UrlTooLong);
+    require!(has_allowed_scheme(url), ErrorCode::InvalidUrlScheme);
+    Ok(())
+}
+
+/// Whether `url` uses an allowed scheme, has something after it and contains
+/// no whitespace, control or invisible characters
+pub fn has_allowed_scheme(url: &str) -> bool {
+    ALLOWED_URL_SCHEMES.iter().any(|scheme| {
+        url.strip_prefix(scheme)
+            .is_some_and(|rest| !rest.is_empty())
+    }) && !url
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || INVISIBLE_CHARS.contains(&c))
+}
+
+fn validate_display_text(text: &str, allow_newlines: bool) -> Result<()> {
+    require!(
+        !text
+            .chars()
+            .any(|c| (c.is_control() && !(allow_newlines && c == '\n'))
+                || INVISIBLE_CHARS.contains(&c)),
+        ErrorCode::InvalidCharacters
+    );
+    require!(text.trim() == text, ErrorCode::UntrimmedText);
+    Ok(())
+}
+
+/// Canonical form used to spot duplicate names: lowercase, confusable letters
+/// folded to ASCII and whitespace runs collapsed to a single space
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .flat_map(char::to_lowercase)
+                .map(|c| {
+                    CONFUSABLES
+                        .iter()
+                        .find(|(confusable, _)| *confusable == c)
+                        .map_or(c, |(_, ascii)| *ascii)
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
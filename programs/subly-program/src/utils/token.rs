@@ -0,0 +1,61 @@
+use crate::error::ErrorCode;
+use anchor_lang::{prelude::*, solana_program::program_option::COption, CheckOwner};
+use anchor_spl::{
+    token_2022::{
+        self,
+        spl_token_2022::{
+            extension::{
+                transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+            },
+            state::Mint,
+        },
+    },
+    token_interface::TokenAccount,
+};
+
+/// Fee the token program withholds when `amount` is transferred with this mint.
+/// Classic SPL mints and Token-2022 mints without the TransferFeeConfig extension charge nothing.
+pub fn transfer_fee_amount(mint_info: &AccountInfo, amount: u64) -> Result<u64> {
+    if mint_info.owner != &token_2022::ID {
+        return Ok(0);
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    let mint = StateWithExtensions::<Mint>::unpack(&data)?;
+    let fee = match mint.get_extension::<TransferFeeConfig>() {
+        Ok(config) => config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        Err(_) => 0,
+    };
+
+    Ok(fee)
+}
+
+/// Token account owned by either token program, or None if the account is closed
+pub fn load_token_account(info: &AccountInfo) -> Result<Option<TokenAccount>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    TokenAccount::check_owner(info.owner)?;
+
+    let data = info.try_borrow_data()?;
+    Ok(Some(TokenAccount::try_deserialize(&mut &data[..])?))
+}
+
+/// Fails unless a freshly minted certificate is a single whole token: supply 1,
+/// zero decimals and a mint authority only the certificate authority PDA can use.
+/// Certificate mints are reused on resubscription, so the authority is locked to
+/// the PDA rather than removed. Accepts classic and Token-2022 mint data
+pub fn require_single_certificate(mint_data: &[u8], certificate_authority: &Pubkey) -> Result<()> {
+    let mint = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    require!(
+        mint.base.supply == 1 && mint.base.decimals == 0,
+        ErrorCode::InvalidCertificateSupply
+    );
+    require!(
+        mint.base.mint_authority == COption::Some(*certificate_authority),
+        ErrorCode::InvalidCertificateMintAuthority
+    );
+    Ok(())
+}
@@ -0,0 +1,479 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::error_code;
+use subly_program::{constants::SOL_VAULT_SEED, error::ErrorCode, state::*};
+
+fn user(deposited_sol: u64, locked_sol: u64, staked_sol: u64) -> User {
+    User {
+        version: User::CURRENT_VERSION,
+        wallet: Pubkey::new_unique(),
+        deposited_sol,
+        locked_sol,
+        staked_sol,
+        created_at: 1_700_000_000,
+        vault_bump: 254,
+        bump: 253,
+        usdc_balance: 0,
+        locked_usdc: 0,
+        withdrawal_delay_secs: 0,
+        pending_withdrawal_delay_secs: 0,
+        withdrawal_delay_effective_at: 0,
+        pending_withdrawal_lamports: 0,
+        withdrawal_unlocks_at: 0,
+        referrer: Pubkey::default(),
+        referred_count: 0,
+        referred_volume: 0,
+    }
+}
+
+fn stake_account(staked_amount: u64, jito_sol_amount: u64) -> StakeAccount {
+    StakeAccount {
+        version: StakeAccount::CURRENT_VERSION,
+        user: Pubkey::new_unique(),
+        staked_amount,
+        jito_sol_amount,
+        stake_date: 1_700_000_000,
+        last_yield_claim: 1_700_000_000,
+        total_yield_earned: 0,
+        is_active: true,
+        bump: 255,
+    }
+}
+
+#[test]
+fn available_sol_excludes_locked_funds() {
+    assert_eq!(user(5_000, 1_500, 0).available_sol().unwrap(), 3_500);
+}
+
+#[test]
+fn available_sol_is_zero_once_payments_eat_into_the_lock() {
+    // SOL billing leaves the lock in place, so this is a normal state
+    assert_eq!(user(1_000, 1_001, 0).available_sol().unwrap(), 0);
+
+    let mut account = user(1_000, 400, 0);
+    account.pending_withdrawal_lamports = 500;
+    account.record_sol_payment(300).unwrap();
+    assert_eq!(account.available_sol().unwrap(), 0);
+    assert_eq!(
+        error_code(account.record_sol_payment(701)),
+        u32::from(ErrorCode::InsufficientBalance)
+    );
+}
+
+#[test]
+fn available_usdc_excludes_locked_usdc() {
+    let mut account = user(0, 0, 0);
+    account.usdc_balance = 120_000_000;
+    account.locked_usdc = 100_000_000;
+    assert_eq!(account.available_usdc().unwrap(), 20_000_000);
+
+    account.release_locked_usdc(100_000_000).unwrap();
+    assert_eq!(account.available_usdc().unwrap(), 120_000_000);
+    assert_eq!(
+        error_code(account.release_locked_usdc(1)),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+
+    account.locked_usdc = 120_000_001;
+    assert_eq!(
+        error_code(account.available_usdc()),
+        u32::from(ErrorCode::LockedExceedsDeposited)
+    );
+}
+
+#[test]
+fn stake_moves_deposited_into_staked() {
+    let mut account = user(5_000, 0, 1_000);
+    account.move_to_staked(2_000).unwrap();
+    assert_eq!(account.deposited_sol, 3_000);
+    assert_eq!(account.staked_sol, 3_000);
+}
+
+#[test]
+fn stake_beyond_deposited_is_an_underflow() {
+    // Previously panicked in stake_sol
+    let mut account = user(1_000, 0, 0);
+    assert_eq!(
+        error_code(account.move_to_staked(1_001)),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+}
+
+#[test]
+fn stake_overflowing_staked_is_an_overflow() {
+    // Previously panicked in stake_sol
+    let mut account = user(1_000, 0, u64::MAX);
+    assert_eq!(
+        error_code(account.move_to_staked(1)),
+        u32::from(ErrorCode::ArithmeticOverflow)
+    );
+}
+
+#[test]
+fn unstake_beyond_staked_is_an_underflow() {
+    // Previously panicked in unstake_sol and withdraw when the APY estimate exceeded the stake
+    let mut account = user(0, 0, 1_000);
+    assert_eq!(
+        error_code(account.move_to_deposited(1_020)),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+}
+
+#[test]
+fn unstake_overflowing_deposited_is_an_overflow() {
+    // Previously panicked in unstake_sol and withdraw
+    let mut account = user(u64::MAX, 0, 1_000);
+    assert_eq!(
+        error_code(account.move_to_deposited(1)),
+        u32::from(ErrorCode::ArithmeticOverflow)
+    );
+}
+
+#[test]
+fn releasing_more_than_locked_is_an_underflow() {
+    // Previously clamped locked_sol to zero in unsubscribe_from_service
+    let mut account = user(10_000, 1_000, 0);
+    assert_eq!(
+        error_code(account.release_locked(1_200)),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+    assert_eq!(account.locked_sol, 1_000);
+}
+
+#[test]
+fn record_unstake_deactivates_an_emptied_stake() {
+    let mut stake = stake_account(1_000, 980);
+    stake.record_unstake(980, 1_000).unwrap();
+    assert_eq!(stake.jito_sol_amount, 0);
+    assert_eq!(stake.staked_amount, 0);
+    assert!(!stake.is_active);
+}
+
+#[test]
+fn record_unstake_beyond_jito_sol_is_an_underflow() {
+    // Previously panicked in unstake_sol and withdraw
+    let mut stake = stake_account(1_000, 980);
+    assert_eq!(
+        error_code(stake.record_unstake(981, 0)),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+}
+
+#[test]
+fn record_unstake_beyond_staked_amount_is_an_underflow() {
+    // Previously panicked in unstake_sol and withdraw
+    let mut stake = stake_account(1_000, 980);
+    assert_eq!(
+        error_code(stake.record_unstake(980, 1_001)),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+}
+
+#[test]
+fn record_yield_overflow_is_an_overflow() {
+    // Previously panicked in claim_yield
+    let mut stake = stake_account(1_000, 980);
+    stake.total_yield_earned = u64::MAX;
+    assert_eq!(
+        error_code(stake.record_yield(1, 1_700_086_400)),
+        u32::from(ErrorCode::ArithmeticOverflow)
+    );
+    assert_eq!(stake.last_yield_claim, 1_700_000_000);
+}
+
+/// A User account as init_if_needed hands it over: zeroed past the discriminator
+fn fresh_user() -> User {
+    User {
+        version: 0,
+        wallet: Pubkey::default(),
+        deposited_sol: 0,
+        locked_sol: 0,
+        staked_sol: 0,
+        created_at: 0,
+        vault_bump: 0,
+        bump: 0,
+        usdc_balance: 0,
+        locked_usdc: 0,
+        withdrawal_delay_secs: 0,
+        pending_withdrawal_delay_secs: 0,
+        withdrawal_delay_effective_at: 0,
+        pending_withdrawal_lamports: 0,
+        withdrawal_unlocks_at: 0,
+        referrer: Pubkey::default(),
+        referred_count: 0,
+        referred_volume: 0,
+    }
+}
+
+#[test]
+fn repeated_initialization_keeps_identity_fields() {
+    let wallet = Pubkey::new_unique();
+    let mut account = fresh_user();
+
+    assert!(account
+        .initialize_or_verify(wallet, 1_700_000_000, 254, 253)
+        .unwrap());
+    account.deposited_sol = 5_000;
+
+    assert!(!account
+        .initialize_or_verify(wallet, 1_800_000_000, 254, 253)
+        .unwrap());
+    assert_eq!(account.wallet, wallet);
+    assert_eq!(account.created_at, 1_700_000_000);
+    assert_eq!(account.vault_bump, 254);
+    assert_eq!(account.bump, 253);
+    assert_eq!(account.deposited_sol, 5_000);
+    assert!(account.is_current_version());
+}
+
+#[test]
+fn initialization_backfills_unset_fields() {
+    let wallet = Pubkey::new_unique();
+    let mut account = fresh_user();
+    account.version = User::CURRENT_VERSION;
+    account.wallet = wallet;
+
+    assert!(!account
+        .initialize_or_verify(wallet, 1_700_000_000, 254, 253)
+        .unwrap());
+    assert_eq!(account.created_at, 1_700_000_000);
+    assert_eq!(account.vault_bump, 254);
+    assert_eq!(account.bump, 253);
+}
+
+#[test]
+fn initialization_rejects_another_wallet() {
+    let mut account = user(1_000, 0, 0);
+    assert_eq!(
+        error_code(account.initialize_or_verify(Pubkey::new_unique(), 1_700_000_000, 254, 253)),
+        u32::from(ErrorCode::UnauthorizedUser)
+    );
+}
+
+#[test]
+fn initialization_rejects_a_mismatched_stored_bump() {
+    let mut account = user(1_000, 0, 0);
+    let wallet = account.wallet;
+    assert_eq!(
+        error_code(account.initialize_or_verify(wallet, 1_700_000_000, 251, 253)),
+        u32::from(ErrorCode::InvalidStoredBump)
+    );
+}
+
+#[test]
+fn vault_bump_comes_from_the_account_once_stored() {
+    let wallet = Pubkey::new_unique();
+    let (_, canonical) = Pubkey::find_program_address(
+        &[SOL_VAULT_SEED.as_bytes(), wallet.as_ref()],
+        &subly_program::ID,
+    );
+    let mut account = fresh_user();
+    assert_eq!(account.vault_bump_for(&wallet), canonical);
+
+    account.vault_bump = 254;
+    assert_eq!(account.vault_bump_for(&wallet), 254);
+}
+
+fn allowlist(enabled: bool, destinations: Vec<Pubkey>, locked_until: i64) -> WithdrawalAllowlist {
+    WithdrawalAllowlist {
+        version: WithdrawalAllowlist::CURRENT_VERSION,
+        user: Pubkey::new_unique(),
+        enabled,
+        destinations,
+        locked_until,
+        bump: 255,
+    }
+}
+
+#[test]
+fn disabled_allowlist_permits_any_destination() {
+    let list = allowlist(false, vec![], 0);
+    assert!(list.check_destination(&Pubkey::new_unique(), 1_700_000_000).is_ok());
+}
+
+#[test]
+fn enabled_allowlist_only_permits_listed_destinations() {
+    let listed = Pubkey::new_unique();
+    let list = allowlist(true, vec![listed], 0);
+    assert!(list.check_destination(&listed, 1_700_000_000).is_ok());
+    assert_eq!(
+        error_code(list.check_destination(&Pubkey::new_unique(), 1_700_000_000)),
+        u32::from(ErrorCode::WithdrawalDestinationNotAllowed)
+    );
+}
+
+#[test]
+fn allowlist_cooldown_blocks_withdrawals_even_when_disabled() {
+    let listed = Pubkey::new_unique();
+    for enabled in [true, false] {
+        let list = allowlist(enabled, vec![listed], 1_700_086_400);
+        assert_eq!(
+            error_code(list.check_destination(&listed, 1_700_086_399)),
+            u32::from(ErrorCode::WithdrawalAllowlistCooldown)
+        );
+        assert!(list.check_destination(&listed, 1_700_086_400).is_ok());
+    }
+}
+
+#[test]
+fn raising_the_withdrawal_delay_applies_immediately() {
+    let mut account = user(1_000, 0, 0);
+    account.set_withdrawal_delay(3_600, 1_700_000_000).unwrap();
+    assert_eq!(account.withdrawal_delay_at(1_700_000_000), 3_600);
+    assert_eq!(account.withdrawal_delay_effective_at, 0);
+}
+
+#[test]
+fn lowering_the_withdrawal_delay_waits_out_the_old_delay() {
+    let mut account = user(1_000, 0, 0);
+    account.set_withdrawal_delay(3_600, 1_700_000_000).unwrap();
+    account.set_withdrawal_delay(0, 1_700_000_100).unwrap();
+    assert_eq!(account.withdrawal_delay_at(1_700_003_699), 3_600);
+    assert_eq!(account.withdrawal_delay_at(1_700_003_700), 0);
+
+    // Raising it again drops the scheduled decrease
+    account.set_withdrawal_delay(7_200, 1_700_000_200).unwrap();
+    assert_eq!(account.withdrawal_delay_at(1_700_003_700), 7_200);
+}
+
+#[test]
+fn withdrawal_delay_is_bounded() {
+    let mut account = user(1_000, 0, 0);
+    for delay in [-1, 30 * 86_400 + 1] {
+        assert_eq!(
+            error_code(account.set_withdrawal_delay(delay, 1_700_000_000)),
+            u32::from(ErrorCode::InvalidWithdrawalDelay)
+        );
+    }
+}
+
+#[test]
+fn pending_withdrawal_is_reserved_until_unlocked() {
+    let mut account = user(1_000, 400, 0);
+    assert_eq!(
+        error_code(account.request_withdrawal(100, 1_700_000_000)),
+        u32::from(ErrorCode::WithdrawalDelayNotSet)
+    );
+
+    account.set_withdrawal_delay(60, 1_700_000_000).unwrap();
+    assert_eq!(
+        error_code(account.request_withdrawal(601, 1_700_000_000)),
+        u32::from(ErrorCode::InsufficientAvailableBalance)
+    );
+    account.request_withdrawal(500, 1_700_000_000).unwrap();
+    assert_eq!(account.available_sol().unwrap(), 100);
+    assert_eq!(
+        error_code(account.request_withdrawal(50, 1_700_000_000)),
+        u32::from(ErrorCode::WithdrawalAlreadyPending)
+    );
+
+    assert_eq!(
+        error_code(account.take_pending_withdrawal(1_700_000_059)),
+        u32::from(ErrorCode::WithdrawalStillLocked)
+    );
+    assert_eq!(account.take_pending_withdrawal(1_700_000_060).unwrap(), 500);
+    assert_eq!(account.available_sol().unwrap(), 600);
+}
+
+#[test]
+fn cancelling_a_withdrawal_releases_the_reservation() {
+    let mut account = user(1_000, 0, 0);
+    account.set_withdrawal_delay(60, 1_700_000_000).unwrap();
+    account.request_withdrawal(700, 1_700_000_000).unwrap();
+    assert_eq!(account.cancel_pending_withdrawal().unwrap(), 700);
+    assert_eq!(account.available_sol().unwrap(), 1_000);
+    assert_eq!(
+        error_code(account.cancel_pending_withdrawal()),
+        u32::from(ErrorCode::NoPendingWithdrawal)
+    );
+}
+
+#[test]
+fn auto_stake_leaves_the_liquid_reserve() {
+    let mut account = user(3_000, 500, 0);
+    assert_eq!(account.stakeable_above_reserve(1_000).unwrap(), 1_500);
+    assert_eq!(account.stakeable_above_reserve(5_000).unwrap(), 0);
+
+    account.set_withdrawal_delay(60, 1_700_000_000).unwrap();
+    account.request_withdrawal(1_000, 1_700_000_000).unwrap();
+    assert_eq!(account.stakeable_above_reserve(1_000).unwrap(), 500);
+}
+
+#[test]
+fn repeated_stakes_accumulate() {
+    let mut stake = stake_account(0, 0);
+    stake.is_active = false;
+    stake.record_stake(1_000, 980, 1_700_000_500).unwrap();
+    assert_eq!(stake.stake_date, 1_700_000_500);
+    assert!(stake.is_active);
+
+    stake.record_stake(2_000, 1_960, 1_700_009_000).unwrap();
+    assert_eq!(stake.staked_amount, 3_000);
+    assert_eq!(stake.jito_sol_amount, 2_940);
+    assert_eq!(stake.stake_date, 1_700_000_500);
+}
+
+#[test]
+fn deposit_headroom_counts_deposited_and_staked_sol() {
+    let account = user(600, 100, 300);
+    assert_eq!(account.deposit_headroom(0).unwrap(), u64::MAX);
+    assert_eq!(account.deposit_headroom(1_000).unwrap(), 100);
+    assert_eq!(account.deposit_headroom(800).unwrap(), 0);
+}
+
+#[test]
+fn unstake_principal_is_pro_rata_to_jito_sol_burned() {
+    let stake = stake_account(1_000, 980);
+    assert_eq!(stake.principal_for(490).unwrap(), 500);
+    assert_eq!(stake.principal_for(980).unwrap(), 1_000);
+    assert_eq!(
+        error_code(stake.principal_for(981)),
+        u32::from(ErrorCode::InsufficientStakedFunds)
+    );
+}
+
+#[test]
+fn unstake_proceeds_credit_what_the_vault_received() {
+    // A fee-charging pool returned less than the principal behind the JitoSOL burned
+    let mut account = user(200, 0, 1_000);
+    account.record_unstake_proceeds(500, 470).unwrap();
+    assert_eq!(account.staked_sol, 500);
+    assert_eq!(account.deposited_sol, 670);
+
+    // Yield above the principal is credited too, rather than underflowing staked_sol
+    account.record_unstake_proceeds(500, 520).unwrap();
+    assert_eq!(account.staked_sol, 0);
+    assert_eq!(account.deposited_sol, 1_190);
+}
+
+#[test]
+fn referrer_is_set_once_and_never_to_self() {
+    let mut account = user(0, 0, 0);
+    assert_eq!(
+        error_code(account.set_referrer(account.wallet)),
+        u32::from(ErrorCode::SelfReferral)
+    );
+    assert_eq!(
+        error_code(account.set_referrer(Pubkey::default())),
+        u32::from(ErrorCode::InvalidReferrer)
+    );
+
+    let referrer = Pubkey::new_unique();
+    account.set_referrer(referrer).unwrap();
+    assert_eq!(
+        error_code(account.set_referrer(Pubkey::new_unique())),
+        u32::from(ErrorCode::ReferrerAlreadySet)
+    );
+    assert_eq!(account.referrer, referrer);
+}
+
+#[test]
+fn referred_users_are_counted_once_and_every_deposit_adds_volume() {
+    let mut referrer = user(0, 0, 0);
+    referrer.record_referred_deposit(1_000, true).unwrap();
+    referrer.record_referred_deposit(500, false).unwrap();
+    referrer.record_referred_deposit(200, true).unwrap();
+    assert_eq!(referrer.referred_count, 2);
+    assert_eq!(referrer.referred_volume, 1_700);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use subly_program::{error::ErrorCode, state::*};
+
+fn global_state(authority: Pubkey) -> GlobalState {
+    let mut state = fresh_global_state();
+    state.authority = authority;
+    state
+}
+
+#[test]
+fn the_current_authority_keeps_control_until_acceptance() {
+    let authority = Pubkey::new_unique();
+    let successor = Pubkey::new_unique();
+    let mut state = global_state(authority);
+
+    assert_eq!(
+        state.propose_authority(successor).unwrap(),
+        Pubkey::default()
+    );
+    assert_eq!(state.authority, authority);
+    state.check_billing_caller(&authority, false).unwrap();
+    assert_eq!(
+        error_code(state.check_billing_caller(&successor, false)),
+        u32::from(ErrorCode::UnauthorizedAuthority)
+    );
+
+    assert_eq!(state.accept_authority(successor).unwrap(), authority);
+    assert_eq!(state.authority, successor);
+    assert_eq!(state.pending_authority, Pubkey::default());
+    state.check_billing_caller(&successor, false).unwrap();
+    assert_eq!(
+        error_code(state.check_billing_caller(&authority, false)),
+        u32::from(ErrorCode::UnauthorizedAuthority)
+    );
+}
+
+#[test]
+fn only_the_proposed_key_can_accept() {
+    let authority = Pubkey::new_unique();
+    let mut state = global_state(authority);
+
+    assert_eq!(
+        error_code(state.accept_authority(Pubkey::new_unique())),
+        u32::from(ErrorCode::NoPendingAuthorityTransfer)
+    );
+
+    state.propose_authority(Pubkey::new_unique()).unwrap();
+    for signer in [authority, Pubkey::new_unique()] {
+        assert_eq!(
+            error_code(state.accept_authority(signer)),
+            u32::from(ErrorCode::UnauthorizedAuthority)
+        );
+    }
+    assert_eq!(state.authority, authority);
+}
+
+#[test]
+fn a_proposal_can_be_replaced_or_cancelled() {
+    let authority = Pubkey::new_unique();
+    let mistyped = Pubkey::new_unique();
+    let intended = Pubkey::new_unique();
+    let mut state = global_state(authority);
+
+    state.propose_authority(mistyped).unwrap();
+    assert_eq!(state.propose_authority(intended).unwrap(), mistyped);
+    assert_eq!(
+        error_code(state.accept_authority(mistyped)),
+        u32::from(ErrorCode::UnauthorizedAuthority)
+    );
+
+    assert_eq!(state.cancel_authority_transfer().unwrap(), intended);
+    assert_eq!(
+        error_code(state.accept_authority(intended)),
+        u32::from(ErrorCode::NoPendingAuthorityTransfer)
+    );
+    assert_eq!(
+        error_code(state.cancel_authority_transfer()),
+        u32::from(ErrorCode::NoPendingAuthorityTransfer)
+    );
+    assert_eq!(state.authority, authority);
+}
+
+#[test]
+fn the_default_key_cannot_be_proposed() {
+    let mut state = global_state(Pubkey::new_unique());
+    assert_eq!(
+        error_code(state.propose_authority(Pubkey::default())),
+        u32::from(ErrorCode::InvalidConfigAddress)
+    );
+}
@@ -0,0 +1,76 @@
+//! unsubscribe_batch sent to the compiled program: a cancelled entry updates the
+//! same state and counters as unsubscribe_from_service, and an entry it cannot
+//! cancel is skipped without failing the batch.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::solana_program::instruction::AccountMeta;
+use common::program::*;
+use subly_program::state::*;
+
+#[tokio::test]
+async fn a_batch_cancels_its_entries_and_updates_the_counters() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+
+    let subscription: UserSubscription =
+        fixture.fetch(&mut context, fixture.user_subscription).await;
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    let service: SubscriptionService = fixture
+        .fetch(&mut context, fixture.subscription_service)
+        .await;
+    let provider: Provider = fixture.fetch(&mut context, fixture.provider_account).await;
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    let attributes: CertificateAttributes = fixture
+        .fetch(&mut context, fixture.certificate_attributes)
+        .await;
+    assert!(subscription.is_active);
+    assert!(subscription.locked_sol > 0);
+
+    // The same entry twice: the second sees the subscription already cancelled
+    // and is skipped, so every counter moves exactly once
+    let mut batch = fixture.unsubscribe_batch();
+    let entry: Vec<AccountMeta> = batch.accounts[batch.accounts.len() - 6..].to_vec();
+    batch.accounts.extend(entry);
+    fixture.run(&mut context, batch, &[&fixture.user]).await;
+
+    let cancelled: UserSubscription = fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert!(!cancelled.is_active);
+    assert!(cancelled.unsubscribed_at.is_some());
+    assert_eq!(cancelled.locked_sol, 0);
+
+    let user_after: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert_eq!(
+        user_after.locked_sol,
+        user.locked_sol - subscription.locked_sol
+    );
+    assert_eq!(user_after.deposited_sol, user.deposited_sol);
+
+    let service_after: SubscriptionService = fixture
+        .fetch(&mut context, fixture.subscription_service)
+        .await;
+    assert_eq!(
+        service_after.current_subscribers,
+        service.current_subscribers - 1
+    );
+    let provider_after: Provider = fixture.fetch(&mut context, fixture.provider_account).await;
+    assert_eq!(
+        provider_after.total_subscribers,
+        provider.total_subscribers - 1
+    );
+    let global_state_after: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(
+        global_state_after.total_active_subscriptions,
+        global_state.total_active_subscriptions - 1
+    );
+
+    // Never billed, so access ends at the cancellation itself
+    let attributes_after: CertificateAttributes = fixture
+        .fetch(&mut context, fixture.certificate_attributes)
+        .await;
+    assert_eq!(attributes_after.paid_through, attributes_after.updated_at);
+    assert!(attributes_after.paid_through < attributes.paid_through);
+}
@@ -0,0 +1,171 @@
+//! A payment that lands in a transaction whose later instruction fails must leave
+//! no trace: billing writes its state before any CPI that could fail, so the
+//! runtime rollback is the only thing standing between a failed transaction and a
+//! half-billed subscriber. Each test sends execute_payment followed by a
+//! withdraw_usdc whose token transfer fails, and compares every account billing
+//! touches byte for byte.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::InstructionError};
+use anchor_spl::{associated_token, token::spl_token};
+use common::program::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    account::Account, instruction::Instruction, signature::Signer, transaction::TransactionError,
+};
+use subly_program::{constants::*, state::*};
+
+/// USDC the user's account claims to hold while its vault is empty
+const PHANTOM_USDC: u64 = 10_000_000;
+
+/// spl_token::error::TokenError::InsufficientFunds
+const TOKEN_INSUFFICIENT_FUNDS: u32 = 1;
+
+/// Every account execute_payment writes or creates, including the PDAs that do not
+/// exist until the first payment of a cycle
+fn billing_accounts(fixture: &Fixture) -> Vec<Pubkey> {
+    vec![
+        fixture.global_state,
+        fixture.user_account,
+        fixture.user_subscription,
+        fixture.subscription_service,
+        fixture.provider_account,
+        fixture.sol_vault,
+        fixture.treasury,
+        fixture.protocol_usdc_treasury,
+        pda(&[
+            PROVIDER_EARNINGS_SEED.as_bytes(),
+            fixture.provider.pubkey().as_ref(),
+        ]),
+        pda(&[
+            PAYMENT_RECORD_SEED.as_bytes(),
+            fixture.user_subscription.as_ref(),
+            &0u64.to_le_bytes(),
+        ]),
+        fixture.certificate_mint,
+        fixture.certificate_token_account,
+        fixture.certificate_attributes,
+    ]
+}
+
+async fn snapshot(
+    context: &mut ProgramTestContext,
+    addresses: &[Pubkey],
+) -> Vec<(Pubkey, Option<Account>)> {
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for &address in addresses {
+        let account = context.banks_client.get_account(address).await.unwrap();
+        accounts.push((address, account));
+    }
+    accounts
+}
+
+/// A withdraw_usdc that passes every program check and then fails inside the SPL
+/// Token transfer: the user's account records USDC its vault does not hold
+async fn failing_usdc_withdrawal(
+    fixture: &Fixture,
+    context: &mut ProgramTestContext,
+) -> Instruction {
+    let user = fixture.user.pubkey();
+    let usdc_vault = fixture.usdc_vault();
+    let user_usdc_account =
+        associated_token::get_associated_token_address(&user, &fixture.usdc_mint);
+    fixture.fund_usdc(context, 0).await;
+    fixture.set_account(
+        context,
+        user_usdc_account,
+        token_account(fixture.usdc_mint, user, 0),
+    );
+    fixture
+        .update(context, fixture.user_account, |account: &mut User| {
+            account.usdc_balance = PHANTOM_USDC;
+        })
+        .await;
+
+    instruction(
+        subly_program::instruction::WithdrawUsdc {
+            amount: PHANTOM_USDC,
+        },
+        subly_program::accounts::WithdrawUsdc {
+            user,
+            user_account: fixture.user_account,
+            global_state: fixture.global_state,
+            sol_vault: fixture.sol_vault,
+            withdrawal_allowlist: pda(&[WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.as_ref()]),
+            usdc_mint: fixture.usdc_mint,
+            usdc_vault,
+            user_usdc_account,
+            token_program: spl_token::ID,
+            event_authority: event_authority(),
+            program: subly_program::ID,
+        },
+    )
+}
+
+#[tokio::test]
+async fn failed_cpi_after_billing_rolls_back_every_billing_account() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    fixture.advance_to_next_payment(&mut context).await;
+    let withdrawal = failing_usdc_withdrawal(&fixture, &mut context).await;
+
+    let addresses = billing_accounts(&fixture);
+    let before = snapshot(&mut context, &addresses).await;
+
+    let authority = context.payer.pubkey();
+    let (error, logs) = fixture
+        .try_run_all(
+            &mut context,
+            &[fixture.execute_payment(authority), withdrawal],
+            &[&fixture.user],
+        )
+        .await
+        .expect_err("the token transfer fails");
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(1, InstructionError::Custom(TOKEN_INSUFFICIENT_FUNDS)),
+        "{logs:#?}"
+    );
+
+    let after = snapshot(&mut context, &addresses).await;
+    for ((address, before), (_, after)) in before.iter().zip(&after) {
+        assert_eq!(before, after, "billing account {address} changed");
+    }
+}
+
+#[tokio::test]
+async fn the_same_payment_alone_changes_billing_state() {
+    // Control for the rollback test: without the failing withdrawal the payment
+    // lands, so unchanged accounts above come from the rollback and not from a
+    // payment that never ran
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    fixture.advance_to_next_payment(&mut context).await;
+    failing_usdc_withdrawal(&fixture, &mut context).await;
+
+    let addresses = billing_accounts(&fixture);
+    let before = snapshot(&mut context, &addresses).await;
+
+    let authority = context.payer.pubkey();
+    fixture
+        .run(&mut context, fixture.execute_payment(authority), &[])
+        .await;
+
+    let after = snapshot(&mut context, &addresses).await;
+    for address in [
+        fixture.global_state,
+        fixture.user_account,
+        fixture.user_subscription,
+        fixture.sol_vault,
+    ] {
+        let index = addresses.iter().position(|&a| a == address).unwrap();
+        assert_ne!(
+            before[index], after[index],
+            "billing account {address} unchanged"
+        );
+    }
+}
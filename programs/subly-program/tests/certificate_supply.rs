@@ -0,0 +1,70 @@
+mod common;
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program_option::COption, program_pack::Pack},
+};
+use anchor_spl::token_2022::spl_token_2022::state::Mint;
+use common::outcome;
+use subly_program::{error::ErrorCode, utils::*};
+
+fn mint_data(supply: u64, decimals: u8, mint_authority: COption<Pubkey>) -> Vec<u8> {
+    let mut data = vec![0u8; Mint::LEN];
+    Mint::pack(
+        Mint {
+            mint_authority,
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: mint_authority,
+        },
+        &mut data,
+    )
+    .unwrap();
+    data
+}
+
+#[test]
+fn only_a_single_whole_pda_controlled_certificate_passes() {
+    let authority = Pubkey::new_unique();
+    let pda = COption::Some(authority);
+    let supply = Some(u32::from(ErrorCode::InvalidCertificateSupply));
+    let mint_authority = Some(u32::from(ErrorCode::InvalidCertificateMintAuthority));
+    let cases = [
+        ("minted once", mint_data(1, 0, pda), None),
+        ("minted twice", mint_data(2, 0, pda), supply),
+        ("nothing minted", mint_data(0, 0, pda), supply),
+        ("fractional", mint_data(1, 6, pda), supply),
+        (
+            "one base unit of a divisible mint",
+            mint_data(1_000_000, 6, pda),
+            supply,
+        ),
+        (
+            "user-held authority",
+            mint_data(1, 0, COption::Some(Pubkey::new_unique())),
+            mint_authority,
+        ),
+        (
+            "authority removed",
+            mint_data(1, 0, COption::None),
+            mint_authority,
+        ),
+    ];
+    for (label, data, expected) in cases {
+        assert_eq!(
+            outcome(require_single_certificate(&data, &authority)),
+            expected,
+            "{label}"
+        );
+    }
+}
+
+#[test]
+fn uninitialized_or_truncated_mints_are_rejected() {
+    let authority = Pubkey::new_unique();
+    assert!(require_single_certificate(&[0u8; Mint::LEN], &authority).is_err());
+
+    let data = mint_data(1, 0, COption::Some(authority));
+    assert!(require_single_certificate(&data[..Mint::LEN - 1], &authority).is_err());
+}
@@ -0,0 +1,31 @@
+//! Helpers shared by the test suites. Each suite uses only some of them, so
+//! unused ones are expected
+#![allow(dead_code)]
+
+#[cfg(feature = "test-sbf")]
+pub mod program;
+
+use anchor_lang::prelude::*;
+use subly_program::state::GlobalState;
+
+/// A GlobalState with every field zero, as a newly allocated account reads
+pub fn fresh_global_state() -> GlobalState {
+    GlobalState::deserialize(&mut &vec![0u8; GlobalState::INIT_SPACE][..]).unwrap()
+}
+
+/// The code of the AnchorError `result` failed with
+pub fn error_code<T: std::fmt::Debug>(result: Result<T>) -> u32 {
+    match result {
+        Err(Error::AnchorError(error)) => error.error_code_number,
+        other => panic!("expected an AnchorError, got {other:?}"),
+    }
+}
+
+/// The code of the AnchorError `result` failed with, None if it passed
+pub fn outcome(result: Result<()>) -> Option<u32> {
+    match result {
+        Ok(()) => None,
+        Err(Error::AnchorError(error)) => Some(error.error_code_number),
+        Err(error) => panic!("unexpected error: {error:?}"),
+    }
+}
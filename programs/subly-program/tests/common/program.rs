@@ -0,0 +1,850 @@
+//! Program-test harness shared by the suites that run the compiled program under
+//! `cargo test-sbf`: one provider service, one funded subscriber and the accounts
+//! billing settles into, with builders for the instructions those suites send.
+//!
+//! Subscribing creates certificate metadata, so the Token Metadata program has to
+//! be available as a fixture:
+//! `solana program dump -u m metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s tests/fixtures/mpl_token_metadata.so`
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{
+        instruction::AccountMeta, program::MAX_RETURN_DATA, program_option::COption,
+        program_pack::Pack, sysvar,
+    },
+    system_program, AccountDeserialize, AccountSerialize, AnchorDeserialize, InstructionData,
+    Space, ToAccountMetas,
+};
+use anchor_spl::{associated_token, metadata, token::spl_token};
+use pyth_sdk_solana::state::{PriceInfo, PriceStatus, SolanaPriceAccount};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    clock::Clock,
+    instruction::Instruction,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use subly_program::{constants::*, state::*};
+
+pub const SERVICE_ID: u64 = 0;
+pub const FEE_USD_CENTS: u64 = 1_599;
+pub const BILLING_FREQUENCY_DAYS: u64 = 30;
+pub const SOL_USD_PRICE: i64 = 150 * 100_000_000; // $150 with Pyth's usual -8 exponent
+pub const DEPOSIT_LAMPORTS: u64 = 10_000_000_000;
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &subly_program::ID).0
+}
+
+pub fn event_authority() -> Pubkey {
+    pda(&[b"__event_authority"])
+}
+
+/// Program instruction from its generated data and accounts structs
+pub fn instruction(data: impl InstructionData, accounts: impl ToAccountMetas) -> Instruction {
+    Instruction::new_with_bytes(
+        subly_program::ID,
+        &data.data(),
+        accounts.to_account_metas(None),
+    )
+}
+
+/// Rent-exempt account holding `data`
+pub fn account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Program-owned account serialized into its full allocated size
+pub fn program_account<T: AccountSerialize>(value: &T, space: usize) -> Account {
+    let mut data = Vec::with_capacity(space);
+    value.try_serialize(&mut data).unwrap();
+    data.resize(space, 0);
+    account(data, subly_program::ID)
+}
+
+/// All-zero value of an account type, for tests to fill in the fields they need
+pub fn zeroed<T: AnchorDeserialize + Space>() -> T {
+    T::deserialize(&mut &vec![0u8; T::INIT_SPACE][..]).unwrap()
+}
+
+pub fn packed<T: Pack>(value: T) -> Account {
+    let mut data = vec![0; T::LEN];
+    T::pack(value, &mut data).unwrap();
+    account(data, spl_token::ID)
+}
+
+pub fn mint_account(supply: u64, decimals: u8) -> Account {
+    packed(spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    })
+}
+
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    packed(spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    })
+}
+
+/// Trading SOL/USD Pyth price account published at `timestamp`
+pub fn price_account(timestamp: i64) -> Account {
+    let size = std::mem::size_of::<SolanaPriceAccount>();
+    let mut data = vec![0; size];
+    let mut put =
+        |offset: usize, bytes: &[u8]| data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, magic),
+        &pyth_sdk_solana::state::MAGIC.to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, ver),
+        &pyth_sdk_solana::state::VERSION.to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, atype),
+        &3u32.to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, size),
+        &(size as u32).to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, expo),
+        &(-8i32).to_le_bytes(),
+    );
+    put(
+        std::mem::offset_of!(SolanaPriceAccount, timestamp),
+        &timestamp.to_le_bytes(),
+    );
+    let agg = std::mem::offset_of!(SolanaPriceAccount, agg);
+    put(
+        agg + std::mem::offset_of!(PriceInfo, price),
+        &SOL_USD_PRICE.to_le_bytes(),
+    );
+    put(
+        agg + std::mem::offset_of!(PriceInfo, conf),
+        &100_000u64.to_le_bytes(),
+    );
+    put(
+        agg + std::mem::offset_of!(PriceInfo, status),
+        &[PriceStatus::Trading as u8],
+    );
+    account(data, Pubkey::new_unique())
+}
+
+/// Certificate metadata or master edition address under Token Metadata
+pub fn metadata_address(mint: &Pubkey, edition: bool) -> Pubkey {
+    let mut seeds = vec![b"metadata".as_ref(), metadata::ID.as_ref(), mint.as_ref()];
+    if edition {
+        seeds.push(b"edition");
+    }
+    Pubkey::find_program_address(&seeds, &metadata::ID).0
+}
+
+/// Failed transaction with the logs it left
+pub type Failure = (TransactionError, Vec<String>);
+
+/// Compute units consumed and logs of a transaction that landed
+pub type Landed = (u64, Vec<String>);
+
+/// Addresses of the protocol, one provider service and one subscriber
+pub struct Fixture {
+    pub user: Keypair,
+    pub provider: Keypair,
+    pub price_feed: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub jito_sol_mint: Pubkey,
+    pub jito_stake_pool: Pubkey,
+    pub stake_pool_program: Pubkey,
+    pub global_state: Pubkey,
+    pub treasury: Pubkey,
+    pub protocol_usdc_treasury: Pubkey,
+    pub provider_account: Pubkey,
+    pub subscription_service: Pubkey,
+    pub user_account: Pubkey,
+    pub sol_vault: Pubkey,
+    pub user_subscription: Pubkey,
+    pub certificate_mint: Pubkey,
+    pub certificate_token_account: Pubkey,
+    pub certificate_attributes: Pubkey,
+    pub certificate_authority: Pubkey,
+    pub rent_sponsor: Pubkey,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let user = Keypair::new();
+        let provider = Keypair::new();
+        let usdc_mint = Pubkey::new_unique();
+        let user_key = user.pubkey();
+        let provider_key = provider.pubkey();
+        let service_id = SERVICE_ID.to_le_bytes();
+        let treasury = pda(&[TREASURY_SEED.as_bytes()]);
+        let certificate_mint = pda(&[
+            CERTIFICATE_SEED.as_bytes(),
+            user_key.as_ref(),
+            provider_key.as_ref(),
+            &service_id,
+        ]);
+
+        Self {
+            price_feed: Pubkey::new_unique(),
+            usdc_mint,
+            jito_sol_mint: Pubkey::new_unique(),
+            jito_stake_pool: Pubkey::new_unique(),
+            stake_pool_program: Pubkey::new_unique(),
+            global_state: pda(&[GLOBAL_STATE_SEED.as_bytes()]),
+            treasury,
+            protocol_usdc_treasury: associated_token::get_associated_token_address(
+                &treasury, &usdc_mint,
+            ),
+            provider_account: pda(&[PROVIDER_SEED.as_bytes(), provider_key.as_ref()]),
+            subscription_service: pda(&[
+                SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+                provider_key.as_ref(),
+                &service_id,
+            ]),
+            user_account: pda(&[USER_SEED.as_bytes(), user_key.as_ref()]),
+            sol_vault: pda(&[SOL_VAULT_SEED.as_bytes(), user_key.as_ref()]),
+            user_subscription: pda(&[
+                USER_SUBSCRIPTION_SEED.as_bytes(),
+                user_key.as_ref(),
+                provider_key.as_ref(),
+                &service_id,
+            ]),
+            certificate_mint,
+            certificate_token_account: associated_token::get_associated_token_address(
+                &user_key,
+                &certificate_mint,
+            ),
+            certificate_attributes: pda(&[
+                CERTIFICATE_ATTRIBUTES_SEED.as_bytes(),
+                certificate_mint.as_ref(),
+            ]),
+            certificate_authority: pda(&[CERTIFICATE_AUTHORITY_SEED.as_bytes()]),
+            rent_sponsor: pda(&[RENT_SPONSOR_SEED.as_bytes()]),
+            user,
+            provider,
+        }
+    }
+
+    /// Program test with the provider, its service, the oracle and the USDC
+    /// accounts billing settles into already in place
+    pub async fn start(&self) -> ProgramTestContext {
+        let mut program_test = ProgramTest::new("subly_program", subly_program::ID, None);
+        program_test.prefer_bpf(true);
+        program_test.add_program("mpl_token_metadata", metadata::ID, None);
+
+        let provider = self.provider.pubkey();
+        let (provider_bump, service_bump) = (
+            Pubkey::find_program_address(
+                &[PROVIDER_SEED.as_bytes(), provider.as_ref()],
+                &subly_program::ID,
+            )
+            .1,
+            Pubkey::find_program_address(
+                &[
+                    SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+                    provider.as_ref(),
+                    &SERVICE_ID.to_le_bytes(),
+                ],
+                &subly_program::ID,
+            )
+            .1,
+        );
+        program_test.add_account(
+            self.provider_account,
+            program_account(
+                &Provider {
+                    version: Provider::CURRENT_VERSION,
+                    wallet: provider,
+                    name: "Provider".to_string(),
+                    description: "Program test fixture".to_string(),
+                    total_subscribers: 0,
+                    is_verified: true,
+                    created_at: 0,
+                    bump: provider_bump,
+                    last_service_registered_at: 0,
+                    services_registered_in_window: 0,
+                    is_banned: false,
+                    verified_at: 0,
+                },
+                8 + Provider::INIT_SPACE,
+            ),
+        );
+        program_test.add_account(
+            self.subscription_service,
+            program_account(
+                &SubscriptionService {
+                    version: SubscriptionService::CURRENT_VERSION,
+                    provider,
+                    service_id: SERVICE_ID,
+                    name: "Streaming".to_string(),
+                    description: "Program test fixture".to_string(),
+                    fee_usd: FEE_USD_CENTS,
+                    billing_frequency_days: BILLING_FREQUENCY_DAYS,
+                    image_url: "https://example.com/image.png".to_string(),
+                    current_subscribers: 0,
+                    is_active: true,
+                    created_at: 0,
+                    certificate_mode: CERTIFICATE_MODE_TOKEN,
+                    royalty_bps: 0,
+                    certificate_metadata_uri: "https://example.com/certificate.json".to_string(),
+                    bump: service_bump,
+                    frozen: false,
+                    deactivated_by_admin: false,
+                    deactivation_reason: 0,
+                },
+                8 + SubscriptionService::INIT_SPACE,
+            ),
+        );
+
+        program_test.add_account(self.price_feed, price_account(0));
+        program_test.add_account(self.usdc_mint, mint_account(1_000_000_000_000, 6));
+        program_test.add_account(self.jito_sol_mint, mint_account(0, 9));
+        // Enough USDC to cover every payout billing credits
+        program_test.add_account(
+            self.protocol_usdc_treasury,
+            token_account(self.usdc_mint, self.treasury, 1_000_000_000_000),
+        );
+        program_test.add_account(
+            self.user.pubkey(),
+            Account::new(100_000_000_000, 0, &system_program::ID),
+        );
+        program_test.add_account(
+            provider,
+            Account::new(10_000_000_000, 0, &system_program::ID),
+        );
+
+        let mut context = program_test.start_with_context().await;
+        let now = self.clock(&mut context).await.unix_timestamp;
+        self.publish_price(&mut context, now);
+        context
+    }
+
+    pub async fn clock(&self, context: &mut ProgramTestContext) -> Clock {
+        context.banks_client.get_sysvar::<Clock>().await.unwrap()
+    }
+
+    pub fn publish_price(&self, context: &mut ProgramTestContext, timestamp: i64) {
+        self.set_account(context, self.price_feed, price_account(timestamp));
+    }
+
+    /// Move the clock past the first payment's due date and refresh the oracle
+    pub async fn advance_to_next_payment(&self, context: &mut ProgramTestContext) {
+        let mut clock = self.clock(context).await;
+        clock.unix_timestamp += (BILLING_FREQUENCY_DAYS * 86_400) as i64 + 1;
+        context.set_sysvar(&clock);
+        self.publish_price(context, clock.unix_timestamp);
+    }
+
+    /// Mark the subscription delinquent on its due date and move the clock to
+    /// the end of its grace period, when it can expire
+    pub async fn lapse(&self, context: &mut ProgramTestContext) {
+        self.update(
+            context,
+            self.user_subscription,
+            |subscription: &mut UserSubscription| {
+                subscription.failed_payment_attempts = 1;
+                subscription.delinquent_since = Some(subscription.next_payment_due);
+            },
+        )
+        .await;
+        let subscription: UserSubscription = self.fetch(context, self.user_subscription).await;
+        let mut clock = self.clock(context).await;
+        clock.unix_timestamp = subscription.grace_ends_at().unwrap();
+        context.set_sysvar(&clock);
+        self.publish_price(context, clock.unix_timestamp);
+    }
+
+    pub fn set_account(&self, context: &mut ProgramTestContext, address: Pubkey, account: Account) {
+        context.set_account(&address, &AccountSharedData::from(account));
+    }
+
+    pub async fn raw_account(&self, context: &mut ProgramTestContext, address: Pubkey) -> Account {
+        context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap_or_else(|| panic!("account {address} exists"))
+    }
+
+    pub async fn fetch<T: AccountDeserialize>(
+        &self,
+        context: &mut ProgramTestContext,
+        address: Pubkey,
+    ) -> T {
+        let account = self.raw_account(context, address).await;
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    /// Rewrite a program account in place, keeping its size and lamports
+    pub async fn update<T: AccountDeserialize + AccountSerialize>(
+        &self,
+        context: &mut ProgramTestContext,
+        address: Pubkey,
+        change: impl FnOnce(&mut T),
+    ) {
+        let mut account = self.raw_account(context, address).await;
+        let mut value = T::try_deserialize(&mut account.data.as_slice()).unwrap();
+        change(&mut value);
+        let space = account.data.len();
+        account.data.clear();
+        value.try_serialize(&mut account.data).unwrap();
+        account.data.resize(space, 0);
+        self.set_account(context, address, account);
+    }
+
+    /// Send `instruction` alone and return the compute units it consumed
+    pub async fn run(
+        &self,
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> u64 {
+        self.run_logged(context, instruction, signers).await.0
+    }
+
+    /// `run`, also returning the transaction's logs
+    pub async fn run_logged(
+        &self,
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Landed {
+        self.try_run_all(context, &[instruction], signers)
+            .await
+            .unwrap_or_else(|(error, logs)| panic!("{error}: {logs:#?}"))
+    }
+
+    pub async fn try_run(
+        &self,
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<u64, Failure> {
+        let (compute_units, _) = self.try_run_all(context, &[instruction], signers).await?;
+        Ok(compute_units)
+    }
+
+    /// Send `instructions` as one transaction, which lands or fails as a whole
+    pub async fn try_run_all(
+        &self,
+        context: &mut ProgramTestContext,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Landed, Failure> {
+        let blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        let outcome = context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        let metadata = outcome.metadata.expect("transaction metadata");
+        match outcome.result {
+            Ok(()) => Ok((metadata.compute_units_consumed, metadata.log_messages)),
+            Err(error) => Err((error, metadata.log_messages)),
+        }
+    }
+
+    /// Simulate a view instruction and decode what it returned
+    pub async fn view<T: AnchorDeserialize>(
+        &self,
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+    ) -> T {
+        let blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            blockhash,
+        );
+        let simulation = context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await
+            .unwrap();
+        let details = simulation.simulation_details.expect("simulation details");
+        match simulation.result {
+            Some(Ok(())) => {}
+            outcome => panic!("{outcome:?}: {:#?}", details.logs),
+        }
+        // The runtime trims trailing zero bytes off return data
+        let mut data = details
+            .return_data
+            .map(|return_data| return_data.data)
+            .unwrap_or_default();
+        data.resize(MAX_RETURN_DATA, 0);
+        T::deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    pub fn initialize(&self, authority: Pubkey) -> Instruction {
+        instruction(
+            subly_program::instruction::Initialize {
+                jito_stake_pool: self.jito_stake_pool,
+                jito_sol_mint: self.jito_sol_mint,
+                spl_stake_pool_program: self.stake_pool_program,
+                sol_usd_price_feed: self.price_feed,
+                usdc_mint: self.usdc_mint,
+            },
+            subly_program::accounts::Initialize {
+                authority,
+                global_state: self.global_state,
+                system_program: system_program::ID,
+            },
+        )
+    }
+
+    fn set_paused_accounts(&self, authority: Pubkey) -> subly_program::accounts::SetPaused {
+        subly_program::accounts::SetPaused {
+            authority,
+            global_state: self.global_state,
+            event_authority: event_authority(),
+            program: subly_program::ID,
+        }
+    }
+
+    pub fn pause_protocol(&self, authority: Pubkey) -> Instruction {
+        instruction(
+            subly_program::instruction::PauseProtocol {},
+            self.set_paused_accounts(authority),
+        )
+    }
+
+    pub fn set_pause_flags(&self, authority: Pubkey, pause_flags: u8) -> Instruction {
+        instruction(
+            subly_program::instruction::SetPauseFlags { pause_flags },
+            self.set_paused_accounts(authority),
+        )
+    }
+
+    pub fn set_emergency_mode(&self, authority: Pubkey, enabled: bool) -> Instruction {
+        instruction(
+            subly_program::instruction::SetEmergencyMode { enabled },
+            self.set_paused_accounts(authority),
+        )
+    }
+
+    pub fn deposit(&self) -> Instruction {
+        instruction(
+            subly_program::instruction::Deposit {
+                amount: DEPOSIT_LAMPORTS,
+                auto_stake: false,
+                referrer: None,
+            },
+            subly_program::accounts::Deposit {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                global_state: self.global_state,
+                sol_vault: self.sol_vault,
+                referrer_account: None,
+                system_program: system_program::ID,
+                stake_account: None,
+                protocol_jito_vault: None,
+                protocol_authority: None,
+                stake_pool_program: None,
+                jito_stake_pool: None,
+                stake_pool_withdraw_authority: None,
+                reserve_stake: None,
+                jito_sol_mint: None,
+                manager_fee_account: None,
+                referrer_pool_tokens: None,
+                token_program: None,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        )
+    }
+
+    pub fn subscribe(&self) -> Instruction {
+        let provider = self.provider.pubkey();
+        let collection_mint = pda(&[CERTIFICATE_COLLECTION_SEED.as_bytes(), provider.as_ref()]);
+
+        instruction(
+            subly_program::instruction::SubscribeToService {
+                provider,
+                service_id: SERVICE_ID,
+            },
+            subly_program::accounts::SubscribeToService {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                user_subscription: self.user_subscription,
+                global_state: self.global_state,
+                sol_usd_price_feed: self.price_feed,
+                usdc_mint: None,
+                certificate_nft_mint: self.certificate_mint,
+                certificate_nft_token_account: self.certificate_token_account,
+                certificate_metadata: metadata_address(&self.certificate_mint, false),
+                certificate_attributes: self.certificate_attributes,
+                certificate_collection_mint: collection_mint,
+                certificate_collection_metadata: metadata_address(&collection_mint, false),
+                certificate_collection_master_edition: metadata_address(&collection_mint, true),
+                certificate_authority: self.certificate_authority,
+                provider_signer: None,
+                rent_sponsor: self.rent_sponsor,
+                token_metadata_program: metadata::ID,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        )
+    }
+
+    pub fn unsubscribe(&self) -> Instruction {
+        instruction(
+            subly_program::instruction::UnsubscribeFromService {
+                provider: self.provider.pubkey(),
+                service_id: SERVICE_ID,
+            },
+            subly_program::accounts::UnsubscribeFromService {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                user_subscription: self.user_subscription,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                global_state: self.global_state,
+                sol_usd_price_feed: self.price_feed,
+                certificate_nft_mint: self.certificate_mint,
+                certificate_nft_token_account: self.certificate_token_account,
+                certificate_attributes: self.certificate_attributes,
+                certificate_authority: self.certificate_authority,
+                rent_sponsor: self.rent_sponsor,
+                sponsor_escrow: pda(&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                ]),
+                sponsor: None,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        )
+    }
+
+    /// expire_subscription of the fixture subscription, sent by `caller`
+    pub fn expire_subscription(&self, caller: Pubkey) -> Instruction {
+        instruction(
+            subly_program::instruction::ExpireSubscription {},
+            subly_program::accounts::ExpireSubscription {
+                caller,
+                user_account: self.user_account,
+                user_subscription: self.user_subscription,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                global_state: self.global_state,
+                sol_usd_price_feed: self.price_feed,
+                sponsor_escrow: pda(&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                ]),
+                sponsor: None,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        )
+    }
+
+    /// Batch unsubscribe of the fixture subscription, passed as the six
+    /// remaining accounts the batch reads per subscription
+    pub fn unsubscribe_batch(&self) -> Instruction {
+        let mut instruction = instruction(
+            subly_program::instruction::UnsubscribeBatch {},
+            subly_program::accounts::UnsubscribeBatch {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                global_state: self.global_state,
+                sol_usd_price_feed: self.price_feed,
+                certificate_authority: self.certificate_authority,
+                rent_sponsor: self.rent_sponsor,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        );
+        instruction.accounts.extend(self.subscription_entry());
+        instruction
+    }
+
+    /// The subscription's accounts as one unsubscribe_batch or emergency_withdraw entry
+    pub fn subscription_entry(&self) -> Vec<AccountMeta> {
+        [
+            self.user_subscription,
+            self.subscription_service,
+            self.provider_account,
+            self.certificate_mint,
+            self.certificate_token_account,
+            self.certificate_attributes,
+        ]
+        .into_iter()
+        .map(|address| AccountMeta::new(address, false))
+        .collect()
+    }
+
+    pub fn execute_payment(&self, authority: Pubkey) -> Instruction {
+        self.billing(authority, None)
+    }
+
+    /// execute_payment offering the user's USDC vault, which pays when it covers the fee
+    pub fn execute_usdc_payment(&self, authority: Pubkey) -> Instruction {
+        self.billing(authority, Some(self.usdc_vault()))
+    }
+
+    fn billing(&self, authority: Pubkey, user_usdc_vault: Option<Pubkey>) -> Instruction {
+        instruction(
+            subly_program::instruction::ExecuteSubscriptionPayment {
+                _user: self.user.pubkey(),
+                _provider: self.provider.pubkey(),
+                _service_id: SERVICE_ID,
+            },
+            subly_program::accounts::ExecuteSubscriptionPayment {
+                authority,
+                global_state: self.global_state,
+                keeper_registration: None,
+                user_account: self.user_account,
+                user_subscription: self.user_subscription,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                user_sol_vault: self.sol_vault,
+                provider_earnings: pda(&[
+                    PROVIDER_EARNINGS_SEED.as_bytes(),
+                    self.provider.pubkey().as_ref(),
+                ]),
+                treasury: self.treasury,
+                protocol_usdc_treasury: self.protocol_usdc_treasury,
+                fee_recipient: None,
+                fee_recipient_usdc_account: None,
+                usdc_mint: self.usdc_mint,
+                user_usdc_vault,
+                sponsor_escrow: pda(&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                ]),
+                certificate_nft_mint: self.certificate_mint,
+                certificate_nft_token_account: self.certificate_token_account,
+                certificate_authority: self.certificate_authority,
+                certificate_attributes: self.certificate_attributes,
+                payment_record: pda(&[
+                    PAYMENT_RECORD_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                    &0u64.to_le_bytes(),
+                ]),
+                sol_usd_price_feed: self.price_feed,
+                token_program: spl_token::ID,
+                certificate_token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        )
+    }
+
+    /// The user's USDC vault: the USDC associated token account of their SOL vault
+    pub fn usdc_vault(&self) -> Pubkey {
+        associated_token::get_associated_token_address(&self.sol_vault, &self.usdc_mint)
+    }
+
+    /// Put `amount` USDC in the user's vault and on their account, as deposit_usdc would
+    pub async fn fund_usdc(&self, context: &mut ProgramTestContext, amount: u64) {
+        self.set_account(
+            context,
+            self.usdc_vault(),
+            token_account(self.usdc_mint, self.sol_vault, amount),
+        );
+        self.update(context, self.user_account, |account: &mut User| {
+            account.usdc_balance = amount;
+        })
+        .await;
+    }
+
+    pub fn withdraw(&self, amount: u64) -> Instruction {
+        let user = self.user.pubkey();
+        instruction(
+            subly_program::instruction::Withdraw { amount },
+            subly_program::accounts::Withdraw {
+                user,
+                user_account: self.user_account,
+                sol_vault: self.sol_vault,
+                destination: None,
+                withdrawal_allowlist: pda(&[WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.as_ref()]),
+                global_state: self.global_state,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        )
+    }
+
+    /// emergency_withdraw passing the user's subscription
+    pub fn emergency_withdraw(&self) -> Instruction {
+        let mut instruction = instruction(
+            subly_program::instruction::EmergencyWithdraw {},
+            subly_program::accounts::EmergencyWithdraw {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                sol_vault: self.sol_vault,
+                global_state: self.global_state,
+                certificate_authority: self.certificate_authority,
+                rent_sponsor: self.rent_sponsor,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            },
+        );
+        instruction.accounts.extend(self.subscription_entry());
+        instruction
+    }
+
+    /// Protocol initialized by the payer and the user funded, ready to subscribe
+    pub async fn funded(&self) -> ProgramTestContext {
+        let mut context = self.start().await;
+        let authority = context.payer.pubkey();
+        self.run(&mut context, self.initialize(authority), &[])
+            .await;
+        self.run(&mut context, self.deposit(), &[&self.user]).await;
+        context
+    }
+
+    /// Funded user with an active subscription
+    pub async fn subscribed(&self) -> ProgramTestContext {
+        let mut context = self.funded().await;
+        self.run(&mut context, self.subscribe(), &[&self.user])
+            .await;
+        context
+    }
+}
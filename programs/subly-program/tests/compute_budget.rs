@@ -0,0 +1,86 @@
+//! Compute-unit ceilings for the instructions every subscriber goes through. Each
+//! test builds representative state, runs the compiled program and fails when an
+//! instruction consumes more than its budget, so growth on the billing path shows
+//! up in review instead of as failed payments once it reaches the 200k default limit.
+//!
+//! Budgets hold for deployed builds, which leave out `verbose-logs`, so the suite
+//! refuses to build with it: `cargo test-sbf` compiles the program with the same
+//! features it tests with.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+#[cfg(feature = "verbose-logs")]
+compile_error!(
+    "compute budgets are measured without `verbose-logs`, run `cargo test-sbf` without it"
+);
+
+mod common;
+
+use common::program::*;
+use solana_sdk::signature::Signer;
+
+/// Ceilings per instruction. Raise one only together with the change that needs
+/// it, and keep each well below the 200k default so a transaction never has to
+/// request more compute
+const EXECUTE_PAYMENT_CU_BUDGET: u64 = 100_000;
+const SUBSCRIBE_CU_BUDGET: u64 = 180_000;
+const WITHDRAW_CU_BUDGET: u64 = 30_000;
+
+fn assert_within_budget(instruction: &str, consumed: u64, budget: u64) {
+    assert!(
+        consumed <= budget,
+        "{instruction} consumed {consumed} CU, over its budget of {budget}"
+    );
+}
+
+#[tokio::test]
+async fn subscribe_stays_within_budget() {
+    let fixture = Fixture::new();
+    let mut context = fixture.funded().await;
+
+    let consumed = fixture
+        .run(&mut context, fixture.subscribe(), &[&fixture.user])
+        .await;
+    assert_within_budget("subscribe_to_service", consumed, SUBSCRIBE_CU_BUDGET);
+}
+
+#[tokio::test]
+async fn execute_payment_stays_within_budget() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    fixture.advance_to_next_payment(&mut context).await;
+
+    // The first payment of a cycle also creates its payment record and the
+    // provider's earnings ledger, the most expensive case billing runs into
+    let authority = context.payer.pubkey();
+    let (consumed, logs) = fixture
+        .run_logged(&mut context, fixture.execute_payment(authority), &[])
+        .await;
+    // A program binary left over from a verbose build would be measured with
+    // its logging cost, and the budget would no longer describe deployments
+    assert!(
+        !logs.iter().any(|log| log.contains("PAYMENT EXECUTED")),
+        "measured a program built with verbose-logs: {logs:#?}"
+    );
+    assert_within_budget(
+        "execute_subscription_payment",
+        consumed,
+        EXECUTE_PAYMENT_CU_BUDGET,
+    );
+}
+
+#[tokio::test]
+async fn withdraw_stays_within_budget() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+
+    let consumed = fixture
+        .run(
+            &mut context,
+            fixture.withdraw(1_000_000_000),
+            &[&fixture.user],
+        )
+        .await;
+    assert_within_budget("withdraw", consumed, WITHDRAW_CU_BUDGET);
+}
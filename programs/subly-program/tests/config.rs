@@ -0,0 +1,383 @@
+mod common;
+
+use anchor_lang::{prelude::*, system_program};
+use common::{error_code, fresh_global_state};
+use subly_program::{
+    constants::*, error::ErrorCode, state::*, utils::require_payable_fee_recipient,
+};
+
+type FieldReader = fn(&GlobalState) -> Pubkey;
+
+/// Each rotatable field paired with a reader for the GlobalState slot it writes
+const FIELDS: &[(u8, FieldReader)] = &[
+    (CONFIG_FIELD_PRICE_FEED, |state| state.sol_usd_price_feed),
+    (CONFIG_FIELD_JITO_STAKE_POOL, |state| state.jito_stake_pool),
+    (CONFIG_FIELD_JITO_SOL_MINT, |state| state.jito_sol_mint),
+    (CONFIG_FIELD_SPL_STAKE_POOL_PROGRAM, |state| {
+        state.spl_stake_pool_program
+    }),
+    (CONFIG_FIELD_USDC_MINT, |state| state.usdc_mint),
+];
+
+#[test]
+fn each_field_updates_only_its_own_address() {
+    for (field, read) in FIELDS {
+        let mut state = fresh_global_state();
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+
+        assert_eq!(
+            state.set_config_address(*field, first).unwrap(),
+            Pubkey::default()
+        );
+        assert_eq!(state.set_config_address(*field, second).unwrap(), first);
+        assert_eq!(read(&state), second);
+
+        let untouched = FIELDS
+            .iter()
+            .filter(|(other, _)| other != field)
+            .all(|(_, read)| read(&state) == Pubkey::default());
+        assert!(untouched, "field {field} wrote another address");
+    }
+}
+
+#[test]
+fn default_addresses_are_rejected() {
+    for (field, read) in FIELDS {
+        let mut state = fresh_global_state();
+        let current = Pubkey::new_unique();
+        state.set_config_address(*field, current).unwrap();
+
+        assert_eq!(
+            error_code(state.set_config_address(*field, Pubkey::default())),
+            u32::from(ErrorCode::InvalidConfigAddress)
+        );
+        assert_eq!(read(&state), current);
+    }
+}
+
+#[test]
+fn other_config_fields_are_not_addresses() {
+    let mut state = fresh_global_state();
+    for field in [CONFIG_FIELD_PROTOCOL_FEE, CONFIG_FIELD_EXECUTION_MODE] {
+        assert_eq!(
+            error_code(state.set_config_address(field, Pubkey::new_unique())),
+            u32::from(ErrorCode::InvalidConfigAddress)
+        );
+    }
+}
+
+#[test]
+fn direct_changes_stop_once_a_timelock_is_set() {
+    let mut state = fresh_global_state();
+    state.require_no_config_timelock().unwrap();
+
+    assert_eq!(state.raise_config_timelock(86400).unwrap(), 0);
+    assert_eq!(
+        error_code(state.require_no_config_timelock()),
+        u32::from(ErrorCode::ConfigTimelocked)
+    );
+}
+
+#[test]
+fn the_timelock_can_only_be_raised_directly() {
+    let mut state = fresh_global_state();
+    state.raise_config_timelock(86400).unwrap();
+    state.raise_config_timelock(2 * 86400).unwrap();
+
+    assert_eq!(
+        error_code(state.raise_config_timelock(86400)),
+        u32::from(ErrorCode::ConfigTimelocked)
+    );
+    assert_eq!(
+        error_code(state.raise_config_timelock(MAX_CONFIG_TIMELOCK_SECS + 1)),
+        u32::from(ErrorCode::InvalidConfigTimelock)
+    );
+    assert_eq!(state.config_timelock_secs, 2 * 86400);
+}
+
+#[test]
+fn a_queued_change_waits_out_the_timelock() {
+    let mut state = fresh_global_state();
+    state.raise_config_timelock(3600).unwrap();
+    let executable_at = state.config_change_executable_at(1_000).unwrap();
+    assert_eq!(executable_at, 4_600);
+
+    let pending = PendingConfigChange {
+        change: ConfigChange::ProtocolFee(200),
+        queued_by: Pubkey::new_unique(),
+        queued_at: 1_000,
+        executable_at,
+        bump: 255,
+    };
+    assert_eq!(
+        error_code(pending.require_executable(executable_at - 1)),
+        u32::from(ErrorCode::ConfigChangeNotExecutable)
+    );
+    pending.require_executable(executable_at).unwrap();
+}
+
+#[test]
+fn each_change_is_keyed_by_the_field_it_replaces() {
+    assert_eq!(
+        ConfigChange::ProtocolFee(0).field(),
+        CONFIG_FIELD_PROTOCOL_FEE
+    );
+    assert_eq!(
+        ConfigChange::ConfigTimelock(0).field(),
+        CONFIG_FIELD_CONFIG_TIMELOCK
+    );
+    assert_eq!(
+        ConfigChange::VerifiedFee(None).field(),
+        CONFIG_FIELD_VERIFIED_FEE
+    );
+    assert_eq!(
+        ConfigChange::FeeRecipient(Pubkey::default()).field(),
+        CONFIG_FIELD_FEE_RECIPIENT
+    );
+    assert_eq!(
+        ConfigChange::MaxPriceAge(0).field(),
+        CONFIG_FIELD_MAX_PRICE_AGE
+    );
+    assert_eq!(
+        ConfigChange::SolPriceBounds {
+            min_cents: 1,
+            max_cents: 1
+        }
+        .field(),
+        CONFIG_FIELD_SOL_PRICE_BOUNDS
+    );
+    assert_eq!(
+        ConfigChange::MaxPriceDeviation(0).field(),
+        CONFIG_FIELD_MAX_PRICE_DEVIATION
+    );
+    for (field, _) in FIELDS {
+        let change = ConfigChange::Address {
+            field: *field,
+            address: Pubkey::new_unique(),
+        };
+        assert_eq!(change.field(), *field);
+        change.validate().unwrap();
+    }
+}
+
+#[test]
+fn changes_that_could_never_apply_are_rejected_when_queued() {
+    for (change, error) in [
+        (
+            ConfigChange::ProtocolFee(MAX_PROTOCOL_FEE_BPS + 1),
+            ErrorCode::InvalidProtocolFee,
+        ),
+        (
+            ConfigChange::Address {
+                field: CONFIG_FIELD_PRICE_FEED,
+                address: Pubkey::default(),
+            },
+            ErrorCode::InvalidConfigAddress,
+        ),
+        (
+            ConfigChange::Address {
+                field: CONFIG_FIELD_KEEPER_ADDED,
+                address: Pubkey::new_unique(),
+            },
+            ErrorCode::InvalidConfigAddress,
+        ),
+        (
+            ConfigChange::ConfigTimelock(-1),
+            ErrorCode::InvalidConfigTimelock,
+        ),
+        (
+            ConfigChange::ConfigTimelock(MAX_CONFIG_TIMELOCK_SECS + 1),
+            ErrorCode::InvalidConfigTimelock,
+        ),
+        (
+            ConfigChange::VerifiedFee(Some(MAX_PROTOCOL_FEE_BPS + 1)),
+            ErrorCode::InvalidProtocolFee,
+        ),
+        (
+            ConfigChange::MaxPriceAge(MAX_PRICE_AGE_LIMIT_SECS + 1),
+            ErrorCode::InvalidMaxPriceAge,
+        ),
+        (
+            ConfigChange::SolPriceBounds {
+                min_cents: 0,
+                max_cents: 100_000,
+            },
+            ErrorCode::InvalidPriceBounds,
+        ),
+        (
+            ConfigChange::SolPriceBounds {
+                min_cents: 2_000,
+                max_cents: 1_000,
+            },
+            ErrorCode::InvalidPriceBounds,
+        ),
+    ] {
+        assert_eq!(error_code(change.validate()), u32::from(error));
+    }
+}
+
+#[test]
+fn max_price_age_falls_back_to_the_default() {
+    let mut global_state = fresh_global_state();
+    // Accounts migrated from before the field read zero
+    assert_eq!(
+        global_state.max_price_age(),
+        u64::from(DEFAULT_MAX_PRICE_AGE_SECS)
+    );
+
+    assert_eq!(global_state.set_max_price_age(5).unwrap(), 0);
+    assert_eq!(global_state.max_price_age(), 5);
+
+    assert_eq!(global_state.set_max_price_age(0).unwrap(), 5);
+    assert_eq!(
+        global_state.max_price_age(),
+        u64::from(DEFAULT_MAX_PRICE_AGE_SECS)
+    );
+}
+
+#[test]
+fn max_price_age_is_bounded() {
+    let mut global_state = fresh_global_state();
+    global_state
+        .set_max_price_age(MAX_PRICE_AGE_LIMIT_SECS)
+        .unwrap();
+    assert_eq!(
+        error_code(
+            global_state
+                .set_max_price_age(MAX_PRICE_AGE_LIMIT_SECS + 1)
+                .map(|_| Pubkey::default())
+        ),
+        u32::from(ErrorCode::InvalidMaxPriceAge)
+    );
+    assert_eq!(global_state.max_price_age_secs, MAX_PRICE_AGE_LIMIT_SECS);
+}
+
+#[test]
+fn sol_price_bounds_fall_back_to_the_defaults() {
+    let mut global_state = fresh_global_state();
+    assert_eq!(
+        global_state.sol_price_bounds(),
+        MIN_SOL_USD_PRICE_CENTS..=MAX_SOL_USD_PRICE_CENTS
+    );
+
+    assert_eq!(
+        global_state.set_sol_price_bounds(500, 500_000).unwrap(),
+        (0, 0)
+    );
+    assert_eq!(global_state.sol_price_bounds(), 500..=500_000);
+}
+
+#[test]
+fn sol_price_bounds_must_be_ordered_and_nonzero() {
+    let mut global_state = fresh_global_state();
+    for (min, max) in [(0, 100_000), (2_000, 1_000)] {
+        assert_eq!(
+            error_code(
+                global_state
+                    .set_sol_price_bounds(min, max)
+                    .map(|_| Pubkey::default())
+            ),
+            u32::from(ErrorCode::InvalidPriceBounds)
+        );
+    }
+    assert_eq!(
+        global_state.set_sol_price_bounds(1, u64::MAX).unwrap(),
+        (0, 0)
+    );
+    assert_eq!(global_state.sol_price_bounds(), 1..=u64::MAX);
+}
+
+#[test]
+fn lock_periods_fall_back_to_the_default() {
+    let mut global_state = fresh_global_state();
+    assert_eq!(global_state.lock_periods(), SUBSCRIPTION_LOCK_PERIODS);
+
+    assert_eq!(global_state.set_lock_periods(3).unwrap(), 0);
+    assert_eq!(global_state.lock_periods(), 3);
+}
+
+#[test]
+fn lock_periods_are_bounded() {
+    let mut global_state = fresh_global_state();
+    for periods in [0, MAX_SUBSCRIPTION_LOCK_PERIODS + 1] {
+        assert_eq!(
+            error_code(
+                global_state
+                    .set_lock_periods(periods)
+                    .map(|_| Pubkey::default())
+            ),
+            u32::from(ErrorCode::InvalidLockPeriods)
+        );
+    }
+    global_state
+        .set_lock_periods(MAX_SUBSCRIPTION_LOCK_PERIODS)
+        .unwrap();
+    assert_eq!(
+        global_state.lock_periods(),
+        u64::from(MAX_SUBSCRIPTION_LOCK_PERIODS)
+    );
+}
+
+#[test]
+fn fees_stay_in_the_treasury_until_a_fee_recipient_is_set() {
+    let mut global_state = fresh_global_state();
+    assert_eq!(global_state.fee_recipient(), None);
+
+    let cold_wallet = Pubkey::new_unique();
+    global_state.fee_recipient = cold_wallet;
+    assert_eq!(global_state.fee_recipient(), Some(cold_wallet));
+
+    global_state.fee_recipient = Pubkey::default();
+    assert_eq!(global_state.fee_recipient(), None);
+}
+
+#[test]
+fn a_fee_recipient_needs_its_own_accounts() {
+    let global_state = fresh_global_state();
+    require_payable_fee_recipient(&global_state, Pubkey::default(), None, None).unwrap();
+
+    let cold_wallet = Pubkey::new_unique();
+    assert_eq!(
+        error_code(require_payable_fee_recipient(
+            &global_state,
+            cold_wallet,
+            None,
+            None
+        )),
+        u32::from(ErrorCode::FeeRecipientAccountMissing)
+    );
+
+    let other_wallet = Pubkey::new_unique();
+    let (mut lamports, mut usdc_lamports) = (1_000_000_000, 0);
+    let (mut data, mut usdc_data) = (vec![], vec![]);
+    let wallet = AccountInfo::new(
+        &other_wallet,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &system_program::ID,
+        false,
+        0,
+    );
+    let usdc_account = AccountInfo::new(
+        &other_wallet,
+        false,
+        false,
+        &mut usdc_lamports,
+        &mut usdc_data,
+        &system_program::ID,
+        false,
+        0,
+    );
+    assert_eq!(
+        error_code(require_payable_fee_recipient(
+            &global_state,
+            cold_wallet,
+            Some(&wallet),
+            Some(&usdc_account)
+        )),
+        u32::from(ErrorCode::InvalidFeeRecipient)
+    );
+}
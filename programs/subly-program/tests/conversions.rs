@@ -0,0 +1,198 @@
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use proptest::prelude::*;
+use subly_program::{constants::*, utils::*};
+
+const USDC_DECIMALS: u8 = 6;
+
+// Realistic ranges: SOL at $10 - $5,000, fees from a cent to $10,000 and
+// balances up to a billion SOL
+fn price_cents() -> impl Strategy<Value = u64> {
+    1_000..=500_000_u64
+}
+
+fn fee_cents() -> impl Strategy<Value = u64> {
+    1..=MAX_SERVICE_FEE_USD_CENTS
+}
+
+fn lamports() -> impl Strategy<Value = u64> {
+    0..=1_000_000_000 * LAMPORTS_PER_SOL
+}
+
+fn apy_bps() -> impl Strategy<Value = u16> {
+    0..=10_000_u16
+}
+
+fn ordered(a: u64, b: u64) -> (u64, u64) {
+    (a.min(b), a.max(b))
+}
+
+proptest! {
+    #[test]
+    fn usd_to_sol_never_fails_in_range(fee in fee_cents(), price in price_cents()) {
+        prop_assert!(convert_usd_to_sol_lamports(fee, price).is_ok());
+        prop_assert!(subscription_lock_lamports(fee, price, SUBSCRIPTION_LOCK_PERIODS).is_ok());
+        prop_assert!(convert_usd_to_token_amount(fee, USDC_DECIMALS).is_ok());
+        prop_assert!(subscription_lock_token_amount(fee, USDC_DECIMALS, SUBSCRIPTION_LOCK_PERIODS).is_ok());
+    }
+
+    #[test]
+    fn usd_to_sol_grows_with_the_fee(a in fee_cents(), b in fee_cents(), price in price_cents()) {
+        let (low, high) = ordered(a, b);
+        prop_assert!(
+            convert_usd_to_sol_lamports(low, price).unwrap()
+                <= convert_usd_to_sol_lamports(high, price).unwrap()
+        );
+        prop_assert!(
+            convert_usd_to_token_amount(low, USDC_DECIMALS).unwrap()
+                <= convert_usd_to_token_amount(high, USDC_DECIMALS).unwrap()
+        );
+    }
+
+    #[test]
+    fn usd_to_sol_shrinks_as_the_price_rises(fee in fee_cents(), a in price_cents(), b in price_cents()) {
+        let (low, high) = ordered(a, b);
+        prop_assert!(
+            convert_usd_to_sol_lamports(fee, low).unwrap()
+                >= convert_usd_to_sol_lamports(fee, high).unwrap()
+        );
+    }
+
+    #[test]
+    fn usd_round_trips_through_sol_within_a_lamport(fee in fee_cents(), price in price_cents()) {
+        let lamports = convert_usd_to_sol_lamports(fee, price).unwrap();
+        let exact = fee as u128 * 10_u128.pow(USDC_DECIMALS as u32) / 100;
+        let back = convert_sol_to_token_amount(lamports, price, USDC_DECIMALS).unwrap() as u128;
+
+        // Both steps round down, losing at most one lamport's worth plus one base unit
+        let lamport_value = price as u128 * 10_u128.pow(USDC_DECIMALS as u32)
+            / (100 * LAMPORTS_PER_SOL as u128);
+        prop_assert!(back <= exact);
+        prop_assert!(exact - back <= lamport_value + 1, "{} vs {}", back, exact);
+    }
+
+    #[test]
+    fn sol_to_token_is_monotonic(a in lamports(), b in lamports(), x in price_cents(), y in price_cents()) {
+        let (few, many) = ordered(a, b);
+        let (cheap, dear) = ordered(x, y);
+        let at = |lamports, price| convert_sol_to_token_amount(lamports, price, USDC_DECIMALS).unwrap();
+        prop_assert!(at(few, cheap) <= at(many, cheap));
+        prop_assert!(at(few, cheap) <= at(few, dear));
+    }
+
+    #[test]
+    fn lock_is_the_fee_for_every_lock_period(fee in fee_cents(), price in price_cents()) {
+        prop_assert_eq!(
+            subscription_lock_lamports(fee, price, SUBSCRIPTION_LOCK_PERIODS).unwrap(),
+            convert_usd_to_sol_lamports(fee, price).unwrap() * SUBSCRIPTION_LOCK_PERIODS
+        );
+        prop_assert_eq!(
+            subscription_lock_token_amount(fee, USDC_DECIMALS, SUBSCRIPTION_LOCK_PERIODS).unwrap(),
+            convert_usd_to_token_amount(fee, USDC_DECIMALS).unwrap() * SUBSCRIPTION_LOCK_PERIODS
+        );
+    }
+
+    #[test]
+    fn unlock_never_exceeds_the_lock(fee in fee_cents(), locked_at in price_cents(), unlocked_at in price_cents()) {
+        let locked = subscription_lock_lamports(fee, locked_at, SUBSCRIPTION_LOCK_PERIODS).unwrap();
+        let unlocked = subscription_unlock_lamports(fee, unlocked_at, locked).unwrap();
+        prop_assert!(unlocked <= locked);
+        if unlocked_at == locked_at {
+            prop_assert_eq!(unlocked, locked);
+        }
+    }
+
+    #[test]
+    fn fee_and_provider_share_never_exceed_the_payment(fee in fee_cents(), price in price_cents(), fee_bps in 0..=MAX_PROTOCOL_FEE_BPS) {
+        let amount = convert_usd_to_sol_lamports(fee, price).unwrap();
+        let (protocol, provider) = split_payment(amount, fee_bps).unwrap();
+        prop_assert!(protocol as u128 + provider as u128 <= amount as u128);
+    }
+
+    #[test]
+    fn unstake_estimate_covers_what_was_asked(lamports in lamports(), apy in apy_bps()) {
+        let jito_sol = jito_sol_needed_for(lamports, apy);
+        prop_assert!(jito_sol <= lamports);
+
+        // Rounding up the JitoSOL means the estimate covers the request, and
+        // overshoots by no more than the value of one base unit
+        let back = estimate_unstaked_lamports(jito_sol, apy).unwrap();
+        prop_assert!(back >= lamports);
+        prop_assert!(back - lamports <= 2);
+    }
+
+    #[test]
+    fn unstake_estimate_is_monotonic(a in lamports(), b in lamports(), x in apy_bps(), y in apy_bps()) {
+        let (few, many) = ordered(a, b);
+        let (low, high) = (x.min(y), x.max(y));
+        let at = |jito_sol, apy| estimate_unstaked_lamports(jito_sol, apy).unwrap();
+        prop_assert!(at(few, low) <= at(many, low));
+        prop_assert!(at(few, low) <= at(few, high));
+        prop_assert!(at(few, low) >= few);
+    }
+
+    #[test]
+    fn staking_never_credits_more_than_deposited(a in lamports(), b in lamports()) {
+        let (few, many) = ordered(a, b);
+        prop_assert!(estimate_staked_jito_sol(few) <= few);
+        prop_assert!(estimate_staked_jito_sol(few) <= estimate_staked_jito_sol(many));
+    }
+
+    #[test]
+    fn yield_is_monotonic_and_bounded(
+        a in lamports(),
+        b in lamports(),
+        apy in apy_bps(),
+        x in 0..=10 * 31_536_000_i64,
+        y in 0..=10 * 31_536_000_i64,
+    ) {
+        let (few, many) = ordered(a, b);
+        let (short, long) = (x.min(y), x.max(y));
+        let accrued = |staked, elapsed| accrued_yield_lamports(staked, apy as u64, elapsed).unwrap();
+        prop_assert!(accrued(few, short) <= accrued(many, short));
+        prop_assert!(accrued(few, short) <= accrued(few, long));
+
+        // A year's accrual matches twelve monthly estimates, give or take rounding
+        let year = accrued(few, 31_536_000);
+        let monthly = monthly_yield_lamports(few, apy);
+        prop_assert!(monthly * 12 <= year && year - monthly * 12 < 12);
+        prop_assert!(monthly <= monthly_yield_lamports(many, apy));
+    }
+}
+
+#[test]
+fn yield_rejects_a_clock_that_went_backwards() {
+    assert!(accrued_yield_lamports(LAMPORTS_PER_SOL, YIELD_APY_BPS, -1).is_err());
+    assert_eq!(
+        accrued_yield_lamports(u64::MAX, YIELD_APY_BPS, 0).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn yield_matches_the_documented_rate() {
+    // 5% of 100 SOL over a year is 5 SOL
+    assert_eq!(
+        accrued_yield_lamports(100 * LAMPORTS_PER_SOL, YIELD_APY_BPS, 31_536_000).unwrap(),
+        5 * LAMPORTS_PER_SOL
+    );
+}
+
+#[test]
+fn unlock_is_capped_after_a_price_drop() {
+    let locked = subscription_lock_lamports(1_000, 20_000, SUBSCRIPTION_LOCK_PERIODS).unwrap();
+    assert_eq!(
+        subscription_unlock_lamports(1_000, 10_000, locked).unwrap(),
+        locked
+    );
+    assert_eq!(
+        subscription_unlock_lamports(1_000, 40_000, locked).unwrap(),
+        locked / 2
+    );
+}
+
+#[test]
+fn token_conversions_reject_decimals_too_large_to_scale() {
+    // 10^39 does not fit in a u128
+    assert!(convert_usd_to_token_amount(100, 39).is_err());
+    assert!(convert_sol_to_token_amount(LAMPORTS_PER_SOL, 15_000, u8::MAX).is_err());
+}
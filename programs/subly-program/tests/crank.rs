@@ -0,0 +1,99 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use std::collections::BTreeSet;
+use subly_program::{constants::*, error::ErrorCode};
+
+/// The subscription set a thread walks, sorted as the crank requires
+fn subscription_set(len: usize) -> Vec<Pubkey> {
+    let keys: BTreeSet<Pubkey> = (0..len).map(|_| Pubkey::new_unique()).collect();
+    keys.into_iter().collect()
+}
+
+/// What the thread passes in remaining accounts: the next keys after the cursor
+fn next_page(set: &[Pubkey], cursor: Pubkey) -> Vec<Pubkey> {
+    set.iter()
+        .copied()
+        .filter(|key| *key > cursor)
+        .take(MAX_SUBSCRIPTIONS_PER_BATCH)
+        .collect()
+}
+
+#[test]
+fn two_cranks_cover_the_set_exactly_once() {
+    let set = subscription_set(MAX_SUBSCRIPTIONS_PER_BATCH + 5);
+    let mut state = fresh_global_state();
+    let mut scanned: Vec<Pubkey> = Vec::new();
+
+    let first = next_page(&set, state.crank_cursor);
+    state.check_crank_page(&first).unwrap();
+    assert!(state.advance_crank_cursor(&first, first.len()));
+    scanned.extend(&first);
+
+    let second = next_page(&set, state.crank_cursor);
+    state.check_crank_page(&second).unwrap();
+    assert!(!state.advance_crank_cursor(&second, second.len()));
+    scanned.extend(&second);
+
+    assert_eq!(scanned, set);
+    assert_eq!(state.crank_cursor, Pubkey::default());
+
+    // The next pass starts over from the beginning
+    assert_eq!(next_page(&set, state.crank_cursor), first);
+}
+
+#[test]
+fn a_replayed_page_is_rejected() {
+    let set = subscription_set(MAX_SUBSCRIPTIONS_PER_BATCH + 1);
+    let mut state = fresh_global_state();
+    let first = next_page(&set, state.crank_cursor);
+    state.advance_crank_cursor(&first, first.len());
+
+    assert_eq!(
+        error_code(state.check_crank_page(&first)),
+        u32::from(ErrorCode::CrankCursorMismatch)
+    );
+}
+
+#[test]
+fn pages_must_be_sorted_without_duplicates() {
+    let set = subscription_set(3);
+    let state = fresh_global_state();
+
+    for page in [
+        vec![set[1], set[0]],
+        vec![set[0], set[0]],
+        vec![set[0], set[2], set[1]],
+    ] {
+        assert_eq!(
+            error_code(state.check_crank_page(&page)),
+            u32::from(ErrorCode::CrankCursorMismatch)
+        );
+    }
+    state.check_crank_page(&[]).unwrap();
+}
+
+#[test]
+fn a_crank_cut_short_resumes_where_it_stopped() {
+    let set = subscription_set(10);
+    let mut state = fresh_global_state();
+
+    // Compute ran out after four accounts of a short page
+    let page = next_page(&set, state.crank_cursor);
+    assert!(state.advance_crank_cursor(&page, 4));
+    assert_eq!(state.crank_cursor, set[3]);
+    assert_eq!(next_page(&set, state.crank_cursor), set[4..]);
+
+    // Nothing scanned at all leaves the cursor where it was
+    assert!(state.advance_crank_cursor(&set[4..], 0));
+    assert_eq!(state.crank_cursor, set[3]);
+}
+
+#[test]
+fn an_empty_page_ends_the_pass() {
+    let mut state = fresh_global_state();
+    state.crank_cursor = Pubkey::new_unique();
+    assert!(!state.advance_crank_cursor(&[], 0));
+    assert_eq!(state.crank_cursor, Pubkey::default());
+}
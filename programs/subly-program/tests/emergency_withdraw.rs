@@ -0,0 +1,85 @@
+//! emergency_withdraw sent to the compiled program: besides emptying the vault it
+//! cancels the subscriptions passed to it the way an unsubscribe would, so the
+//! service, provider and protocol counters and the certificate stop counting them.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use common::program::*;
+use solana_sdk::signature::Signer;
+use subly_program::state::*;
+
+#[tokio::test]
+async fn emergency_withdraw_cancels_the_subscriptions_it_is_given() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    let authority = context.payer.pubkey();
+    fixture
+        .run(&mut context, fixture.pause_protocol(authority), &[])
+        .await;
+    fixture
+        .run(
+            &mut context,
+            fixture.set_emergency_mode(authority, true),
+            &[],
+        )
+        .await;
+
+    let service: SubscriptionService = fixture
+        .fetch(&mut context, fixture.subscription_service)
+        .await;
+    let provider: Provider = fixture.fetch(&mut context, fixture.provider_account).await;
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    let attributes: CertificateAttributes = fixture
+        .fetch(&mut context, fixture.certificate_attributes)
+        .await;
+
+    // The same entry twice: the second sees the subscription already cancelled
+    // and is left alone, so every counter moves exactly once
+    let mut withdrawal = fixture.emergency_withdraw();
+    withdrawal.accounts.extend(fixture.subscription_entry());
+    fixture
+        .run(&mut context, withdrawal, &[&fixture.user])
+        .await;
+
+    let cancelled: UserSubscription = fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert!(!cancelled.is_active);
+    assert!(cancelled.unsubscribed_at.is_some());
+    assert_eq!(cancelled.locked_sol, 0);
+
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert_eq!((user.deposited_sol, user.locked_sol), (0, 0));
+
+    let service_after: SubscriptionService = fixture
+        .fetch(&mut context, fixture.subscription_service)
+        .await;
+    assert_eq!(
+        service_after.current_subscribers,
+        service.current_subscribers - 1
+    );
+    let provider_after: Provider = fixture.fetch(&mut context, fixture.provider_account).await;
+    assert_eq!(
+        provider_after.total_subscribers,
+        provider.total_subscribers - 1
+    );
+    let global_state_after: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(
+        global_state_after.total_active_subscriptions,
+        global_state.total_active_subscriptions - 1
+    );
+
+    // The certificate is burned and its attributes no longer claim paid access
+    let certificate = context
+        .banks_client
+        .get_account(fixture.certificate_token_account)
+        .await
+        .unwrap();
+    assert!(certificate.is_none(), "certificate token account left open");
+    let attributes_after: CertificateAttributes = fixture
+        .fetch(&mut context, fixture.certificate_attributes)
+        .await;
+    assert_eq!(attributes_after.paid_through, attributes_after.updated_at);
+    assert!(attributes_after.paid_through < attributes.paid_through);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+fn global_state(authority: Pubkey, execution_mode: u8) -> GlobalState {
+    let mut state = fresh_global_state();
+    state.version = GlobalState::CURRENT_VERSION;
+    state.authority = authority;
+    state.execution_mode = execution_mode;
+    state
+}
+
+#[test]
+fn authority_only_mode_rejects_keepers_and_strangers() {
+    let authority = Pubkey::new_unique();
+    let state = global_state(authority, EXECUTION_MODE_AUTHORITY_ONLY);
+    state.check_billing_caller(&authority, false).unwrap();
+    assert_eq!(
+        error_code(state.check_billing_caller(&Pubkey::new_unique(), true)),
+        u32::from(ErrorCode::UnauthorizedAuthority)
+    );
+}
+
+#[test]
+fn allowlist_mode_admits_the_authority_and_registered_keepers() {
+    let authority = Pubkey::new_unique();
+    let state = global_state(authority, EXECUTION_MODE_ALLOWLIST);
+    state.check_billing_caller(&authority, false).unwrap();
+    state.check_billing_caller(&Pubkey::new_unique(), true).unwrap();
+    assert_eq!(
+        error_code(state.check_billing_caller(&Pubkey::new_unique(), false)),
+        u32::from(ErrorCode::KeeperNotRegistered)
+    );
+}
+
+#[test]
+fn permissionless_mode_admits_anyone() {
+    let state = global_state(Pubkey::new_unique(), EXECUTION_MODE_PERMISSIONLESS);
+    state.check_billing_caller(&Pubkey::new_unique(), false).unwrap();
+}
+
+#[test]
+fn unknown_mode_closes_billing() {
+    // A corrupted mode must not fall through to permissionless
+    let authority = Pubkey::new_unique();
+    let state = global_state(authority, EXECUTION_MODE_PERMISSIONLESS + 1);
+    assert_eq!(
+        error_code(state.check_billing_caller(&authority, false)),
+        u32::from(ErrorCode::InvalidExecutionMode)
+    );
+}
+
+#[test]
+fn the_operator_bills_wherever_the_authority_does() {
+    let operator = Pubkey::new_unique();
+    for execution_mode in [EXECUTION_MODE_AUTHORITY_ONLY, EXECUTION_MODE_ALLOWLIST] {
+        let mut state = global_state(Pubkey::new_unique(), execution_mode);
+        state.operator = operator;
+        state.check_billing_caller(&operator, false).unwrap();
+    }
+}
+
+#[test]
+fn an_unset_operator_admits_nobody() {
+    // The default pubkey cannot sign, but it must not count as an operator either
+    let state = global_state(Pubkey::new_unique(), EXECUTION_MODE_AUTHORITY_ONLY);
+    assert_eq!(
+        error_code(state.check_billing_caller(&Pubkey::default(), false)),
+        u32::from(ErrorCode::UnauthorizedAuthority)
+    );
+}
+
+#[test]
+fn the_keeper_registry_caps_at_max_keepers() {
+    let mut state = global_state(Pubkey::new_unique(), EXECUTION_MODE_ALLOWLIST);
+    for _ in 0..MAX_KEEPERS {
+        state.register_keeper().unwrap();
+    }
+    assert_eq!(
+        error_code(state.register_keeper()),
+        u32::from(ErrorCode::KeeperRegistryFull)
+    );
+
+    // Removing one frees a slot right away
+    state.unregister_keeper();
+    state.register_keeper().unwrap();
+    assert_eq!(state.keeper_count, MAX_KEEPERS);
+}
+
+#[test]
+fn removing_an_uncounted_keeper_does_not_underflow() {
+    let mut state = global_state(Pubkey::new_unique(), EXECUTION_MODE_ALLOWLIST);
+    state.unregister_keeper();
+    assert_eq!(state.keeper_count, 0);
+}
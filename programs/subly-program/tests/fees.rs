@@ -0,0 +1,247 @@
+mod common;
+
+use common::{error_code, fresh_global_state};
+use proptest::prelude::*;
+use subly_program::{constants::*, error::ErrorCode, state::*, utils::*};
+
+proptest! {
+    #[test]
+    fn split_adds_back_up_to_the_amount(amount in any::<u64>(), fee_bps in 0..=MAX_PROTOCOL_FEE_BPS) {
+        let (fee, provider) = split_payment(amount, fee_bps).unwrap();
+        prop_assert_eq!(fee as u128 + provider as u128, amount as u128);
+        prop_assert_eq!(fee as u128, amount as u128 * fee_bps as u128 / 10_000);
+        prop_assert!(fee <= amount / 10);
+    }
+
+    #[test]
+    fn fee_grows_with_the_rate(amount in any::<u64>(), low in 0..=MAX_PROTOCOL_FEE_BPS, high in 0..=MAX_PROTOCOL_FEE_BPS) {
+        let (low, high) = (low.min(high), low.max(high));
+        let (low_fee, _) = split_payment(amount, low).unwrap();
+        let (high_fee, _) = split_payment(amount, high).unwrap();
+        prop_assert!(low_fee <= high_fee);
+    }
+
+    #[test]
+    fn rates_above_the_maximum_are_rejected(amount in any::<u64>(), fee_bps in MAX_PROTOCOL_FEE_BPS + 1..=u16::MAX) {
+        prop_assert_eq!(
+            error_code(split_payment(amount, fee_bps)),
+            u32::from(ErrorCode::InvalidProtocolFee)
+        );
+    }
+}
+
+#[test]
+fn split_handles_the_extremes() {
+    let cases = [
+        (0, 0, (0, 0)),
+        (0, MAX_PROTOCOL_FEE_BPS, (0, 0)),
+        (9, DEFAULT_PROTOCOL_FEE_BPS, (0, 9)),
+        (10_000, DEFAULT_PROTOCOL_FEE_BPS, (100, 9_900)),
+        (u64::MAX, 0, (0, u64::MAX)),
+        (
+            u64::MAX,
+            MAX_PROTOCOL_FEE_BPS,
+            (u64::MAX / 10, u64::MAX - u64::MAX / 10),
+        ),
+    ];
+    for (amount, fee_bps, expected) in cases {
+        assert_eq!(
+            split_payment(amount, fee_bps).unwrap(),
+            expected,
+            "{amount} at {fee_bps} bps"
+        );
+    }
+}
+
+/// A realistic clock, well past the grace period of the unset effective_from
+const NOW: i64 = 1_700_000_000;
+
+fn global_state_charging(fee_bps: u16) -> GlobalState {
+    let mut state = fresh_global_state();
+    state.protocol_fee_bps = fee_bps;
+    state
+}
+
+#[test]
+fn protocol_fee_accepts_rates_up_to_the_maximum() {
+    for fee_bps in [0, MAX_PROTOCOL_FEE_BPS] {
+        let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+        assert_eq!(
+            state.set_protocol_fee(fee_bps, NOW).unwrap(),
+            DEFAULT_PROTOCOL_FEE_BPS
+        );
+        assert_eq!(state.protocol_fee_bps, fee_bps);
+    }
+
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    assert_eq!(
+        error_code(state.set_protocol_fee(MAX_PROTOCOL_FEE_BPS + 1, NOW)),
+        u32::from(ErrorCode::InvalidProtocolFee)
+    );
+    assert_eq!(state.protocol_fee_bps, DEFAULT_PROTOCOL_FEE_BPS);
+    assert_eq!(state.protocol_fee_effective_from, 0);
+}
+
+#[test]
+fn payments_already_due_keep_the_old_rate() {
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    // Before any change every payment uses the configured rate
+    assert_eq!(state.protocol_fee_bps_for(0), DEFAULT_PROTOCOL_FEE_BPS);
+
+    state.set_protocol_fee(MAX_PROTOCOL_FEE_BPS, NOW).unwrap();
+    assert_eq!(
+        state.protocol_fee_bps_for(NOW - 1),
+        DEFAULT_PROTOCOL_FEE_BPS
+    );
+    assert_eq!(state.protocol_fee_bps_for(NOW), MAX_PROTOCOL_FEE_BPS);
+    assert_eq!(
+        state.protocol_fee_bps_for(NOW + 4_000),
+        MAX_PROTOCOL_FEE_BPS
+    );
+}
+
+#[test]
+fn a_second_change_waits_until_the_first_settles() {
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    state.set_protocol_fee(MAX_PROTOCOL_FEE_BPS, NOW).unwrap();
+    let settled_at = NOW + PAYMENT_GRACE_PERIOD_SECONDS;
+    assert_eq!(state.protocol_fee_settled_at().unwrap(), settled_at);
+
+    // Refused while payments due before the first change may still be billed,
+    // which keeps their rate intact
+    assert_eq!(
+        error_code(state.set_protocol_fee(0, settled_at - 1)),
+        u32::from(ErrorCode::ProtocolFeeChangePending)
+    );
+    assert_eq!(state.protocol_fee_bps, MAX_PROTOCOL_FEE_BPS);
+    assert_eq!(
+        state.protocol_fee_bps_for(NOW - 1),
+        DEFAULT_PROTOCOL_FEE_BPS
+    );
+
+    // Once it settles the next change replaces the previous rate
+    assert_eq!(
+        state.set_protocol_fee(0, settled_at).unwrap(),
+        MAX_PROTOCOL_FEE_BPS
+    );
+    assert_eq!(
+        state.protocol_fee_bps_for(settled_at - 1),
+        MAX_PROTOCOL_FEE_BPS
+    );
+    assert_eq!(state.protocol_fee_bps_for(settled_at), 0);
+}
+
+#[test]
+fn a_pending_change_can_be_undone() {
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    state.set_protocol_fee(MAX_PROTOCOL_FEE_BPS, NOW).unwrap();
+
+    assert_eq!(
+        state
+            .set_protocol_fee(DEFAULT_PROTOCOL_FEE_BPS, NOW + 1)
+            .unwrap(),
+        MAX_PROTOCOL_FEE_BPS
+    );
+    assert_eq!(state.protocol_fee_bps, DEFAULT_PROTOCOL_FEE_BPS);
+    // Every payment is back on the rate from before the change
+    for due_at in [NOW - 1, NOW, NOW + 1] {
+        assert_eq!(state.protocol_fee_bps_for(due_at), DEFAULT_PROTOCOL_FEE_BPS);
+    }
+
+    // Undoing does not restart the window for other rates
+    assert_eq!(
+        error_code(state.set_protocol_fee(0, NOW + 2)),
+        u32::from(ErrorCode::ProtocolFeeChangePending)
+    );
+}
+
+#[test]
+fn verified_fee_accepts_rates_up_to_the_maximum() {
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    assert_eq!(
+        state.set_verified_fee(Some(MAX_PROTOCOL_FEE_BPS)).unwrap(),
+        None
+    );
+    assert_eq!(
+        error_code(state.set_verified_fee(Some(MAX_PROTOCOL_FEE_BPS + 1))),
+        u32::from(ErrorCode::InvalidProtocolFee)
+    );
+    assert_eq!(state.verified_fee_bps(), Some(MAX_PROTOCOL_FEE_BPS));
+}
+
+#[test]
+fn verified_and_unverified_providers_get_different_splits() {
+    let amount = 1_000_000;
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    // Without a verified rate everyone pays the protocol fee
+    assert_eq!(
+        state.protocol_fee_bps_for_provider(0, true),
+        DEFAULT_PROTOCOL_FEE_BPS
+    );
+
+    state
+        .set_verified_fee(Some(DEFAULT_PROTOCOL_FEE_BPS / 2))
+        .unwrap();
+    let (unverified_fee, unverified_share) =
+        split_payment(amount, state.protocol_fee_bps_for_provider(0, false)).unwrap();
+    let (verified_fee, verified_share) =
+        split_payment(amount, state.protocol_fee_bps_for_provider(0, true)).unwrap();
+
+    assert_eq!(
+        unverified_fee,
+        amount * DEFAULT_PROTOCOL_FEE_BPS as u64 / 10_000
+    );
+    assert_eq!(verified_fee, unverified_fee / 2);
+    assert_eq!(
+        verified_share - unverified_share,
+        unverified_fee - verified_fee
+    );
+}
+
+#[test]
+fn a_verified_fee_of_zero_waives_the_protocol_fee() {
+    let amount = 1_000_000;
+    let mut state = global_state_charging(DEFAULT_PROTOCOL_FEE_BPS);
+    state.set_verified_fee(Some(0)).unwrap();
+    assert_eq!(state.verified_fee_bps(), Some(0));
+
+    let verified_bps = state.protocol_fee_bps_for_provider(0, true);
+    assert_eq!(verified_bps, 0);
+    assert_eq!(split_payment(amount, verified_bps).unwrap(), (0, amount));
+    assert_eq!(
+        state.protocol_fee_bps_for_provider(0, false),
+        DEFAULT_PROTOCOL_FEE_BPS
+    );
+
+    // Clearing it is a separate state from 0: verified providers pay the
+    // protocol fee again
+    assert_eq!(state.set_verified_fee(None).unwrap(), Some(0));
+    assert_eq!(state.verified_fee_bps(), None);
+    assert_eq!(
+        state.protocol_fee_bps_for_provider(0, true),
+        DEFAULT_PROTOCOL_FEE_BPS
+    );
+}
+
+#[test]
+fn treasury_withdrawals_leave_the_rent_floor() {
+    assert_eq!(treasury_withdrawal_amount(400, 1_000).unwrap(), 400);
+    assert_eq!(treasury_withdrawal_amount(1_000, 1_000).unwrap(), 1_000);
+    assert_eq!(
+        error_code(treasury_withdrawal_amount(1_001, 1_000)),
+        u32::from(ErrorCode::InsufficientBalance)
+    );
+    assert_eq!(
+        error_code(treasury_withdrawal_amount(0, 1_000)),
+        u32::from(ErrorCode::InvalidAmount)
+    );
+}
+
+#[test]
+fn u64_max_withdraws_the_whole_spendable_balance() {
+    assert_eq!(treasury_withdrawal_amount(u64::MAX, 1_000).unwrap(), 1_000);
+    assert_eq!(
+        error_code(treasury_withdrawal_amount(u64::MAX, 0)),
+        u32::from(ErrorCode::InsufficientBalance)
+    );
+}
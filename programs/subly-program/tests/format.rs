@@ -0,0 +1,24 @@
+use subly_program::utils::{bps_to_percent_string, cents_to_usd_string, lamports_to_sol_string};
+
+#[test]
+fn formats_lamports_as_sol() {
+    assert_eq!(lamports_to_sol_string(0), "0.000000000");
+    assert_eq!(lamports_to_sol_string(1), "0.000000001");
+    assert_eq!(lamports_to_sol_string(1_500_000_000), "1.500000000");
+    assert_eq!(lamports_to_sol_string(u64::MAX), "18446744073.709551615");
+}
+
+#[test]
+fn formats_cents_as_usd() {
+    assert_eq!(cents_to_usd_string(0), "0.00");
+    assert_eq!(cents_to_usd_string(5), "0.05");
+    assert_eq!(cents_to_usd_string(1599), "15.99");
+    assert_eq!(cents_to_usd_string(15_000), "150.00");
+}
+
+#[test]
+fn formats_bps_as_percent() {
+    assert_eq!(bps_to_percent_string(700), "7.00");
+    assert_eq!(bps_to_percent_string(725), "7.25");
+    assert_eq!(bps_to_percent_string(10_000), "100.00");
+}
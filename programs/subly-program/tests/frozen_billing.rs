@@ -0,0 +1,123 @@
+//! Billing of a frozen service, sent to the compiled program. The payment is
+//! refused before the keeper pays for any of the records billing creates, and
+//! the scan reports the subscription as skipped rather than due.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::instruction::{AccountMeta, InstructionError},
+};
+use common::program::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{instruction::Instruction, signature::Signer, transaction::TransactionError};
+use subly_program::{constants::*, error::ErrorCode, state::*, CrankPaymentsResult};
+
+async fn frozen_and_due(fixture: &Fixture) -> ProgramTestContext {
+    let mut context = fixture.subscribed().await;
+    fixture.advance_to_next_payment(&mut context).await;
+    fixture
+        .update(
+            &mut context,
+            fixture.subscription_service,
+            |service: &mut SubscriptionService| service.frozen = true,
+        )
+        .await;
+    context
+}
+
+/// crank_payments over the fixture's subscription, with `trailing` after it
+fn crank(fixture: &Fixture, authority: Pubkey, trailing: &[Pubkey]) -> Instruction {
+    let mut instruction = instruction(
+        subly_program::instruction::CrankPayments {},
+        subly_program::accounts::CrankPayments {
+            authority,
+            global_state: fixture.global_state,
+            keeper_registration: None,
+            sol_usd_price_feed: fixture.price_feed,
+            event_authority: event_authority(),
+            program: subly_program::ID,
+        },
+    );
+    instruction.accounts.extend(
+        std::iter::once(&fixture.user_subscription)
+            .chain(trailing)
+            .map(|address| AccountMeta::new_readonly(*address, false)),
+    );
+    instruction
+}
+
+#[tokio::test]
+async fn a_frozen_service_is_refused_before_billing_creates_records() {
+    let fixture = Fixture::new();
+    let mut context = frozen_and_due(&fixture).await;
+    let before: UserSubscription = fixture.fetch(&mut context, fixture.user_subscription).await;
+
+    let authority = context.payer.pubkey();
+    match fixture
+        .try_run(&mut context, fixture.execute_payment(authority), &[])
+        .await
+    {
+        Err((TransactionError::InstructionError(0, InstructionError::Custom(code)), _))
+            if code == u32::from(ErrorCode::ServiceFrozen) => {}
+        other => panic!("expected ServiceFrozen, got {other:?}"),
+    }
+
+    let payment_record = pda(&[
+        PAYMENT_RECORD_SEED.as_bytes(),
+        fixture.user_subscription.as_ref(),
+        &0u64.to_le_bytes(),
+    ]);
+    let provider_earnings = pda(&[
+        PROVIDER_EARNINGS_SEED.as_bytes(),
+        fixture.provider.pubkey().as_ref(),
+    ]);
+    for address in [payment_record, provider_earnings] {
+        let account = context.banks_client.get_account(address).await.unwrap();
+        assert!(account.is_none(), "{address} was created");
+    }
+
+    let after: UserSubscription = fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert_eq!(after.total_payments_made, before.total_payments_made);
+    assert_eq!(after.delinquent_since, None);
+}
+
+#[tokio::test]
+async fn the_scan_skips_subscriptions_of_a_frozen_service() {
+    let fixture = Fixture::new();
+    let mut context = frozen_and_due(&fixture).await;
+    let authority = context.payer.pubkey();
+
+    // Without the service the scan cannot tell, and reports the subscription due
+    let result: CrankPaymentsResult = fixture
+        .view(&mut context, crank(&fixture, authority, &[]))
+        .await;
+    assert_eq!((result.scanned, result.due), (1, 1));
+
+    let result: CrankPaymentsResult = fixture
+        .view(
+            &mut context,
+            crank(&fixture, authority, &[fixture.subscription_service]),
+        )
+        .await;
+    assert_eq!((result.scanned, result.due), (1, 0));
+
+    // Once unfrozen the same page is due again
+    fixture
+        .update(
+            &mut context,
+            fixture.subscription_service,
+            |service: &mut SubscriptionService| service.frozen = false,
+        )
+        .await;
+    let result: CrankPaymentsResult = fixture
+        .view(
+            &mut context,
+            crank(&fixture, authority, &[fixture.subscription_service]),
+        )
+        .await;
+    assert_eq!((result.scanned, result.due), (1, 1));
+}
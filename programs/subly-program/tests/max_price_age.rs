@@ -0,0 +1,136 @@
+//! set_max_price_age sent to the compiled program: the age it stores decides
+//! whether subscribing accepts a price, and out-of-range or unauthorized
+//! changes leave GlobalState untouched.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::InstructionError};
+use common::program::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use subly_program::{constants::*, error::ErrorCode, state::GlobalState};
+
+/// Age of the price the tests publish, between the ages they set
+const PRICE_AGE_SECS: i64 = 600;
+
+fn set_max_price_age(fixture: &Fixture, authority: Pubkey, max_price_age_secs: u32) -> Instruction {
+    instruction(
+        subly_program::instruction::SetMaxPriceAge { max_price_age_secs },
+        subly_program::accounts::SetMaxPriceAge {
+            authority,
+            global_state: fixture.global_state,
+            event_authority: event_authority(),
+            program: subly_program::ID,
+        },
+    )
+}
+
+fn assert_error(name: &str, outcome: Result<u64, Failure>, expected: ErrorCode) {
+    match outcome {
+        Err((TransactionError::InstructionError(0, InstructionError::Custom(code)), _))
+            if code == u32::from(expected) => {}
+        Err((error, logs)) => {
+            panic!("{name} failed with {error} instead of {expected:?}: {logs:#?}")
+        }
+        Ok(_) => panic!("{name} succeeded"),
+    }
+}
+
+/// Funded user facing a price published PRICE_AGE_SECS ago, with the max price
+/// age set to `max_price_age_secs`
+async fn with_stale_price(fixture: &Fixture, max_price_age_secs: u32) -> ProgramTestContext {
+    let mut context = fixture.funded().await;
+    let authority = context.payer.pubkey();
+    fixture
+        .run(
+            &mut context,
+            set_max_price_age(fixture, authority, max_price_age_secs),
+            &[],
+        )
+        .await;
+    let now = fixture.clock(&mut context).await.unix_timestamp;
+    fixture.publish_price(&mut context, now - PRICE_AGE_SECS);
+    context
+}
+
+#[tokio::test]
+async fn a_price_older_than_the_max_age_is_refused() {
+    let fixture = Fixture::new();
+    let mut context = with_stale_price(&fixture, 60).await;
+
+    let outcome = fixture
+        .try_run(&mut context, fixture.subscribe(), &[&fixture.user])
+        .await;
+    assert_error("subscribe", outcome, ErrorCode::PriceNotAvailable);
+}
+
+#[tokio::test]
+async fn the_same_price_is_accepted_under_a_longer_max_age() {
+    let fixture = Fixture::new();
+    let mut context = with_stale_price(&fixture, 3_600).await;
+
+    fixture
+        .run(&mut context, fixture.subscribe(), &[&fixture.user])
+        .await;
+}
+
+#[tokio::test]
+async fn zero_restores_the_default_max_age() {
+    assert!(i64::from(DEFAULT_MAX_PRICE_AGE_SECS) < PRICE_AGE_SECS);
+    let fixture = Fixture::new();
+    let mut context = with_stale_price(&fixture, 0).await;
+
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(global_state.max_price_age_secs, 0);
+    let outcome = fixture
+        .try_run(&mut context, fixture.subscribe(), &[&fixture.user])
+        .await;
+    assert_error("subscribe", outcome, ErrorCode::PriceNotAvailable);
+}
+
+#[tokio::test]
+async fn rejected_changes_leave_the_max_age_alone() {
+    let fixture = Fixture::new();
+    let mut context = fixture.funded().await;
+    let authority = context.payer.pubkey();
+    fixture
+        .run(
+            &mut context,
+            set_max_price_age(&fixture, authority, 60),
+            &[],
+        )
+        .await;
+
+    let outcome = fixture
+        .try_run(
+            &mut context,
+            set_max_price_age(&fixture, authority, MAX_PRICE_AGE_LIMIT_SECS + 1),
+            &[],
+        )
+        .await;
+    assert_error("set_max_price_age", outcome, ErrorCode::InvalidMaxPriceAge);
+
+    let stranger = Keypair::new();
+    let outcome = fixture
+        .try_run(
+            &mut context,
+            set_max_price_age(&fixture, stranger.pubkey(), 3_600),
+            &[&stranger],
+        )
+        .await;
+    assert_error(
+        "set_max_price_age",
+        outcome,
+        ErrorCode::UnauthorizedAuthority,
+    );
+
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(global_state.max_price_age_secs, 60);
+}
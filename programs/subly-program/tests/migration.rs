@@ -0,0 +1,604 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use subly_program::{
+    constants::*,
+    error::ErrorCode,
+    state::*,
+    utils::{needs_migration, stored_global_state_authority, upgrade_account_data},
+};
+
+// Fixtures for version 0 are written field by field in the layouts the
+// accounts had before the version field, each field holding a distinct value so
+// a shifted offset shows up. Accounts other than GlobalState were allocated
+// INIT_SPACE bytes without room for the discriminator
+
+fn push_string(data: &mut Vec<u8>, value: &str, max_len: usize) {
+    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    data.extend_from_slice(value.as_bytes());
+    data.resize(data.len() + max_len - value.len(), 0);
+}
+
+/// Pad the fields written into `data` out to the account's allocated size
+fn allocated(mut data: Vec<u8>, len: usize) -> Vec<u8> {
+    assert!(data.len() <= len);
+    data.resize(len, 0);
+    data
+}
+
+/// A User as written before the version field existed
+fn legacy_user_data() -> Vec<u8> {
+    let mut data = User::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[1; 32]); // wallet
+    data.extend_from_slice(&5_000_000_000u64.to_le_bytes()); // deposited_sol
+    data.extend_from_slice(&1_000_000_000u64.to_le_bytes()); // locked_sol
+    data.extend_from_slice(&2_000_000_000u64.to_le_bytes()); // staked_sol
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // created_at
+    data.push(253); // bump
+    assert_eq!(data.len(), 8 + UserV0::INIT_SPACE);
+    data
+}
+
+/// A Provider as written before the version field existed
+fn legacy_provider_data() -> Vec<u8> {
+    let mut data = Provider::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[1; 32]); // wallet
+    push_string(&mut data, "Acme", 4); // name
+    push_string(&mut data, "Streaming", 9); // description
+    data.extend_from_slice(&21u64.to_le_bytes()); // total_subscribers
+    data.push(1); // is_verified
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // created_at
+    data.push(252); // bump
+    allocated(data, ProviderV0::INIT_SPACE)
+}
+
+/// A SubscriptionService as written before the version field existed
+fn legacy_subscription_service_data() -> Vec<u8> {
+    let mut data = SubscriptionService::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[2; 32]); // provider
+    data.extend_from_slice(&7u64.to_le_bytes()); // service_id
+    push_string(&mut data, "Music", 5); // name
+    push_string(&mut data, "Songs", 5); // description
+    data.extend_from_slice(&999u64.to_le_bytes()); // fee_usd
+    data.extend_from_slice(&30u64.to_le_bytes()); // billing_frequency_days
+    push_string(&mut data, "https://x.io/a.png", 18); // image_url
+    data.extend_from_slice(&4u64.to_le_bytes()); // current_subscribers
+    data.push(1); // is_active
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // created_at
+    data.push(251); // bumps
+    allocated(data, SubscriptionServiceV0::INIT_SPACE)
+}
+
+/// A UserSubscription as written before the version field existed, once paid
+fn legacy_user_subscription_data() -> Vec<u8> {
+    let mut data = UserSubscription::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[3; 32]); // user
+    data.extend_from_slice(&[4; 32]); // provider
+    data.extend_from_slice(&7u64.to_le_bytes()); // service_id
+    data.extend_from_slice(&9u64.to_le_bytes()); // subscription_id
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // subscribed_at
+    data.push(1); // last_payment_at: Some
+    data.extend_from_slice(&1_702_592_000i64.to_le_bytes());
+    data.extend_from_slice(&1_705_184_000i64.to_le_bytes()); // next_payment_due
+    data.extend_from_slice(&2u64.to_le_bytes()); // total_payments_made
+    data.push(1); // is_active
+    data.push(0); // unsubscribed_at: None
+    data.push(250); // bumps
+    allocated(data, UserSubscriptionV0::INIT_SPACE)
+}
+
+/// A StakeAccount as written before the version field existed
+fn legacy_stake_account_data() -> Vec<u8> {
+    let mut data = StakeAccount::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[5; 32]); // user
+    data.extend_from_slice(&3_000_000_000u64.to_le_bytes()); // staked_amount
+    data.extend_from_slice(&2_900_000_000u64.to_le_bytes()); // jito_sol_amount
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // stake_date
+    data.extend_from_slice(&1_700_086_400i64.to_le_bytes()); // last_yield_claim
+    data.extend_from_slice(&12_345u64.to_le_bytes()); // total_yield_earned
+    data.push(1); // is_active
+    data.push(249); // bump
+    assert_eq!(data.len(), 8 + StakeAccountV0::INIT_SPACE);
+    data
+}
+
+/// A GlobalState as written before the version field existed
+fn legacy_global_state_data() -> Vec<u8> {
+    let mut data = GlobalState::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[1; 32]); // authority
+    data.extend_from_slice(&250u16.to_le_bytes()); // protocol_fee_bps
+    data.push(1); // is_paused
+    data.extend_from_slice(&[2; 32]); // jito_stake_pool
+    data.extend_from_slice(&[3; 32]); // jito_sol_mint
+    data.extend_from_slice(&[4; 32]); // spl_stake_pool_program
+    data.extend_from_slice(&[5; 32]); // sol_usd_price_feed
+    data.extend_from_slice(&[6; 32]); // usdc_mint
+    data.extend_from_slice(&11u64.to_le_bytes()); // total_services
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // last_payment_processed
+    data.push(254); // bump
+    assert_eq!(data.len(), 8 + GlobalStateV0::INIT_SPACE);
+    data
+}
+
+#[test]
+fn migrates_legacy_user() {
+    let (from_version, upgraded) = upgrade_account_data::<User>(&legacy_user_data()).unwrap();
+    assert_eq!(from_version, 0);
+    assert_eq!(upgraded.len(), 8 + User::INIT_SPACE);
+
+    let user = User::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert_eq!(user.version, User::CURRENT_VERSION);
+    let wallet = Pubkey::new_from_array([1; 32]);
+    assert_eq!(user.wallet, wallet);
+    assert_eq!(user.deposited_sol, 5_000_000_000);
+    assert_eq!(user.locked_sol, 1_000_000_000);
+    assert_eq!(user.staked_sol, 2_000_000_000);
+    assert_eq!(user.created_at, 1_700_000_000);
+    assert_eq!(user.bump, 253);
+    // Never stored before, so it is derived rather than read from the old bump
+    assert_eq!(
+        user.vault_bump,
+        Pubkey::find_program_address(
+            &[SOL_VAULT_SEED.as_bytes(), wallet.as_ref()],
+            &subly_program::ID
+        )
+        .1
+    );
+    assert_eq!(user.usdc_balance, 0);
+    assert_eq!(user.referrer, Pubkey::default());
+}
+
+#[test]
+fn migrates_legacy_provider() {
+    for data in [
+        legacy_provider_data(),
+        allocated(legacy_provider_data(), 8 + ProviderV0::INIT_SPACE),
+    ] {
+        let (from_version, upgraded) = upgrade_account_data::<Provider>(&data).unwrap();
+        assert_eq!(from_version, 0);
+        assert_eq!(upgraded.len(), 8 + Provider::INIT_SPACE);
+
+        let provider = Provider::try_deserialize(&mut &upgraded[..]).unwrap();
+        assert!(provider.is_current_version());
+        assert_eq!(provider.wallet, Pubkey::new_from_array([1; 32]));
+        assert_eq!(provider.name, "Acme");
+        assert_eq!(provider.description, "Streaming");
+        assert_eq!(provider.total_subscribers, 21);
+        assert!(provider.is_verified);
+        assert_eq!(provider.created_at, 1_700_000_000);
+        assert_eq!(provider.bump, 252);
+        assert!(!provider.is_banned);
+        assert_eq!(provider.verified_at, 0);
+    }
+}
+
+#[test]
+fn migrates_legacy_subscription_service() {
+    let (from_version, upgraded) =
+        upgrade_account_data::<SubscriptionService>(&legacy_subscription_service_data()).unwrap();
+    assert_eq!(from_version, 0);
+
+    let service = SubscriptionService::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert!(service.is_current_version());
+    assert_eq!(service.provider, Pubkey::new_from_array([2; 32]));
+    assert_eq!(service.service_id, 7);
+    assert_eq!(service.name, "Music");
+    assert_eq!(service.description, "Songs");
+    assert_eq!(service.fee_usd, 999);
+    assert_eq!(service.billing_frequency_days, 30);
+    assert_eq!(service.image_url, "https://x.io/a.png");
+    assert_eq!(service.current_subscribers, 4);
+    assert!(service.is_active);
+    assert_eq!(service.created_at, 1_700_000_000);
+    // The old bumps field becomes bump, past the fields inserted before it
+    assert_eq!(service.bump, 251);
+    assert_eq!(service.certificate_mode, 0);
+    assert_eq!(service.royalty_bps, 0);
+    assert_eq!(service.certificate_metadata_uri, "");
+    assert!(!service.frozen);
+}
+
+#[test]
+fn migrates_legacy_user_subscription() {
+    let (from_version, upgraded) =
+        upgrade_account_data::<UserSubscription>(&legacy_user_subscription_data()).unwrap();
+    assert_eq!(from_version, 0);
+
+    let migrated = UserSubscription::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert!(migrated.is_current_version());
+    assert_eq!(migrated.user, Pubkey::new_from_array([3; 32]));
+    assert_eq!(migrated.provider, Pubkey::new_from_array([4; 32]));
+    assert_eq!(migrated.service_id, 7);
+    assert_eq!(migrated.subscription_id, 9);
+    assert_eq!(migrated.subscribed_at, 1_700_000_000);
+    assert_eq!(migrated.last_payment_at, Some(1_702_592_000));
+    assert_eq!(migrated.next_payment_due, 1_705_184_000);
+    assert_eq!(migrated.total_payments_made, 2);
+    assert!(migrated.is_active);
+    assert_eq!(migrated.unsubscribed_at, None);
+    assert_eq!(migrated.bump, 250);
+    assert_eq!(migrated.certificate_asset_id, Pubkey::default());
+    assert_eq!(migrated.delinquent_since, None);
+    assert_eq!(migrated.locked_sol, 0);
+}
+
+#[test]
+fn migrates_legacy_stake_account() {
+    let (from_version, upgraded) =
+        upgrade_account_data::<StakeAccount>(&legacy_stake_account_data()).unwrap();
+    assert_eq!(from_version, 0);
+
+    let stake = StakeAccount::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert!(stake.is_current_version());
+    assert_eq!(stake.user, Pubkey::new_from_array([5; 32]));
+    assert_eq!(stake.staked_amount, 3_000_000_000);
+    assert_eq!(stake.jito_sol_amount, 2_900_000_000);
+    assert_eq!(stake.stake_date, 1_700_000_000);
+    assert_eq!(stake.last_yield_claim, 1_700_086_400);
+    assert_eq!(stake.total_yield_earned, 12_345);
+    assert!(stake.is_active);
+    assert_eq!(stake.bump, 249);
+}
+
+#[test]
+fn migrates_legacy_global_state() {
+    let data = legacy_global_state_data();
+    assert!(needs_migration::<GlobalState>(&data));
+
+    let (from_version, upgraded) = upgrade_account_data::<GlobalState>(&data).unwrap();
+    assert_eq!(from_version, 0);
+    assert_eq!(upgraded.len(), 8 + GlobalState::INIT_SPACE);
+
+    let migrated = GlobalState::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert!(migrated.is_current_version());
+    assert_eq!(migrated.authority, Pubkey::new_from_array([1; 32]));
+    assert_eq!(migrated.protocol_fee_bps, 250);
+    assert!(migrated.is_paused);
+    assert_eq!(migrated.jito_stake_pool, Pubkey::new_from_array([2; 32]));
+    assert_eq!(migrated.jito_sol_mint, Pubkey::new_from_array([3; 32]));
+    assert_eq!(
+        migrated.spl_stake_pool_program,
+        Pubkey::new_from_array([4; 32])
+    );
+    assert_eq!(migrated.sol_usd_price_feed, Pubkey::new_from_array([5; 32]));
+    assert_eq!(migrated.usdc_mint, Pubkey::new_from_array([6; 32]));
+    assert_eq!(migrated.total_services, 11);
+    assert_eq!(migrated.last_payment_processed, 1_700_000_000);
+    assert_eq!(migrated.bump, 254);
+    assert_eq!(
+        migrated.treasury_bump,
+        Pubkey::find_program_address(&[TREASURY_SEED.as_bytes()], &subly_program::ID).1
+    );
+    // Counters the old layout lacked start at zero, limits at initialize's defaults
+    assert_eq!(migrated.total_users, 0);
+    assert_eq!(migrated.total_protocol_fees_lamports, 0);
+    assert_eq!(migrated.min_deposit_lamports, DEFAULT_MIN_DEPOSIT_LAMPORTS);
+    assert_eq!(
+        migrated.max_services_per_window,
+        DEFAULT_MAX_SERVICES_PER_WINDOW
+    );
+    assert_eq!(migrated.service_fee_cap(), MAX_SERVICE_FEE_USD_CENTS);
+    assert_eq!(migrated.reserved, [0; 6]);
+}
+
+#[test]
+fn rejects_current_version() {
+    let (_, upgraded) = upgrade_account_data::<User>(&legacy_user_data()).unwrap();
+    assert_eq!(
+        error_code(upgrade_account_data::<User>(&upgraded)),
+        u32::from(ErrorCode::AccountAlreadyMigrated)
+    );
+}
+
+#[test]
+fn rejects_future_version() {
+    let (_, mut upgraded) = upgrade_account_data::<User>(&legacy_user_data()).unwrap();
+    upgraded[8] = User::CURRENT_VERSION + 1;
+    assert_eq!(
+        error_code(upgrade_account_data::<User>(&upgraded)),
+        u32::from(ErrorCode::UnsupportedAccountVersion)
+    );
+}
+
+#[test]
+fn rejects_other_account_types() {
+    assert_eq!(
+        error_code(upgrade_account_data::<StakeAccount>(&legacy_user_data())),
+        u32::from(ErrorCode::InvalidMigrationAccount)
+    );
+}
+
+/// A GlobalState as version 1 shipped, before any fields were appended
+fn version_1_global_state_data() -> (GlobalState, Vec<u8>) {
+    let mut global_state = fresh_global_state();
+    global_state.version = GlobalState::CURRENT_VERSION;
+    global_state.authority = Pubkey::new_unique();
+    global_state.total_users = 42;
+    global_state.total_deposited_lamports = 7_000_000_000;
+    global_state.bump = 254;
+
+    let mut data = Vec::new();
+    global_state.try_serialize(&mut data).unwrap();
+    data.truncate(8 + GLOBAL_STATE_V1_SPACE);
+    (global_state, data)
+}
+
+/// A GlobalState written field by field in the layout version 1 shipped with,
+/// each field holding a distinct value so a shifted offset shows up
+fn shipped_version_1_global_state_data() -> Vec<u8> {
+    let mut data = GlobalState::DISCRIMINATOR.to_vec();
+    data.push(1); // version
+    data.extend_from_slice(&[1; 32]); // authority
+    data.extend_from_slice(&250u16.to_le_bytes()); // protocol_fee_bps
+    data.push(0); // is_paused
+    data.extend_from_slice(&[2; 32]); // jito_stake_pool
+    data.extend_from_slice(&[3; 32]); // jito_sol_mint
+    data.extend_from_slice(&[4; 32]); // spl_stake_pool_program
+    data.extend_from_slice(&[5; 32]); // sol_usd_price_feed
+    data.extend_from_slice(&[6; 32]); // usdc_mint
+    data.extend_from_slice(&11u64.to_le_bytes()); // total_services
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes()); // last_payment_processed
+    data.extend_from_slice(&12u64.to_le_bytes()); // total_providers
+    data.extend_from_slice(&13u64.to_le_bytes()); // total_users
+    data.extend_from_slice(&14u64.to_le_bytes()); // total_active_subscriptions
+    data.extend_from_slice(&15_000_000_000u64.to_le_bytes()); // total_deposited_lamports
+    data.extend_from_slice(&16_000_000_000u64.to_le_bytes()); // total_staked_lamports
+    data.extend_from_slice(&17_000_000_000u64.to_le_bytes()); // total_volume_lamports
+    data.extend_from_slice(&18_000_000u64.to_le_bytes()); // total_protocol_fees_lamports
+    data.push(1); // soulbound_certificates
+    data.extend_from_slice(&[7; 32]); // certificate_merkle_tree
+    data.extend_from_slice(&[8; 32]); // certificate_collection
+    data.push(1); // sponsor_certificate_rent
+    data.extend_from_slice(&90u64.to_le_bytes()); // payment_record_retention_days
+    data.push(253); // treasury_bump
+    data.push(254); // bump
+    assert_eq!(data.len(), 8 + GLOBAL_STATE_V1_SPACE);
+    data
+}
+
+/// GlobalState's space while its 64 reserved bytes lasted, from their
+/// introduction until fee_recipient left 11 of them
+const RESERVED_LAYOUT_SPACE: usize = 587;
+
+#[test]
+fn grows_a_version_1_global_state_to_the_current_layout() {
+    let (original, data) = version_1_global_state_data();
+
+    let (from_version, upgraded) = upgrade_account_data::<GlobalState>(&data).unwrap();
+    assert_eq!(from_version, GlobalState::CURRENT_VERSION);
+    assert_eq!(upgraded.len(), 8 + GlobalState::INIT_SPACE);
+
+    let migrated = GlobalState::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert!(migrated.is_current_version());
+    assert_eq!(migrated.authority, original.authority);
+    assert_eq!(migrated.total_users, 42);
+    assert_eq!(migrated.total_deposited_lamports, 7_000_000_000);
+    assert_eq!(migrated.bump, 254);
+    // Limits get initialize's defaults rather than a lifting zero
+    assert_eq!(migrated.min_deposit_lamports, DEFAULT_MIN_DEPOSIT_LAMPORTS);
+    assert_eq!(
+        migrated.liquid_reserve_lamports,
+        DEFAULT_LIQUID_RESERVE_LAMPORTS
+    );
+    assert_eq!(
+        migrated.max_services_per_window,
+        DEFAULT_MAX_SERVICES_PER_WINDOW
+    );
+    assert_eq!(
+        migrated.max_service_fee_usd_cents,
+        MAX_SERVICE_FEE_USD_CENTS
+    );
+    // Everything else appended starts out zeroed
+    assert_eq!(migrated.execution_mode, EXECUTION_MODE_AUTHORITY_ONLY);
+    assert_eq!(migrated.config_timelock_secs, 0);
+    assert_eq!(migrated.max_price_age_secs, 0);
+    assert_eq!(
+        migrated.sol_price_bounds(),
+        MIN_SOL_USD_PRICE_CENTS..=MAX_SOL_USD_PRICE_CENTS
+    );
+    assert_eq!(migrated.lock_periods(), SUBSCRIPTION_LOCK_PERIODS);
+    assert_eq!(migrated.fee_recipient(), None);
+    assert_eq!(migrated.rent_collector(), migrated.authority);
+    assert_eq!(migrated.verified_fee_bps(), None);
+    assert_eq!(migrated.max_price_deviation_bps, 0);
+    assert_eq!(migrated.price_override_at(0), None);
+    assert_eq!(migrated.total_protocol_fees_usdc, 0);
+    assert_eq!(migrated.reserved, [0; 6]);
+
+    assert!(!needs_migration::<GlobalState>(&upgraded));
+    assert_eq!(
+        error_code(upgrade_account_data::<GlobalState>(&upgraded)),
+        u32::from(ErrorCode::AccountAlreadyMigrated)
+    );
+}
+
+#[test]
+fn every_field_appended_to_a_shipped_version_1_global_state_reads_its_default() {
+    let data = shipped_version_1_global_state_data();
+
+    let (from_version, upgraded) = upgrade_account_data::<GlobalState>(&data).unwrap();
+    assert_eq!(from_version, 1);
+    let migrated = GlobalState::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert!(migrated.is_current_version());
+
+    // What version 1 stored comes through unchanged
+    assert_eq!(migrated.authority, Pubkey::new_from_array([1; 32]));
+    assert_eq!(migrated.protocol_fee_bps, 250);
+    assert!(!migrated.is_paused);
+    assert_eq!(migrated.jito_stake_pool, Pubkey::new_from_array([2; 32]));
+    assert_eq!(migrated.jito_sol_mint, Pubkey::new_from_array([3; 32]));
+    assert_eq!(
+        migrated.spl_stake_pool_program,
+        Pubkey::new_from_array([4; 32])
+    );
+    assert_eq!(migrated.sol_usd_price_feed, Pubkey::new_from_array([5; 32]));
+    assert_eq!(migrated.usdc_mint, Pubkey::new_from_array([6; 32]));
+    assert_eq!(migrated.total_services, 11);
+    assert_eq!(migrated.last_payment_processed, 1_700_000_000);
+    assert_eq!(migrated.total_providers, 12);
+    assert_eq!(migrated.total_users, 13);
+    assert_eq!(migrated.total_active_subscriptions, 14);
+    assert_eq!(migrated.total_deposited_lamports, 15_000_000_000);
+    assert_eq!(migrated.total_staked_lamports, 16_000_000_000);
+    assert_eq!(migrated.total_volume_lamports, 17_000_000_000);
+    assert_eq!(migrated.total_protocol_fees_lamports, 18_000_000);
+    assert!(migrated.soulbound_certificates);
+    assert_eq!(
+        migrated.certificate_merkle_tree,
+        Pubkey::new_from_array([7; 32])
+    );
+    assert_eq!(
+        migrated.certificate_collection,
+        Pubkey::new_from_array([8; 32])
+    );
+    assert!(migrated.sponsor_certificate_rent);
+    assert_eq!(migrated.payment_record_retention_days, 90);
+    assert_eq!(migrated.treasury_bump, 253);
+    assert_eq!(migrated.bump, 254);
+
+    // Limits get initialize's defaults
+    assert_eq!(migrated.min_deposit_lamports, DEFAULT_MIN_DEPOSIT_LAMPORTS);
+    assert_eq!(
+        migrated.liquid_reserve_lamports,
+        DEFAULT_LIQUID_RESERVE_LAMPORTS
+    );
+    assert_eq!(migrated.max_deposit_per_user_lamports, 0);
+    assert_eq!(
+        migrated.max_services_per_window,
+        DEFAULT_MAX_SERVICES_PER_WINDOW
+    );
+    assert_eq!(migrated.service_fee_cap(), MAX_SERVICE_FEE_USD_CENTS);
+
+    // Everything else reads as off, unset or the built-in default
+    assert_eq!(migrated.execution_mode, EXECUTION_MODE_AUTHORITY_ONLY);
+    assert_eq!(migrated.total_pending_payouts_usdc, 0);
+    assert!(!migrated.require_verified_providers);
+    assert_eq!(migrated.crank_cursor, Pubkey::default());
+    assert_eq!((migrated.paused_at, migrated.unpaused_at), (0, 0));
+    assert_eq!(migrated.pending_authority, Pubkey::default());
+    assert_eq!(migrated.protocol_fee_bps_for(0), 250);
+    assert_eq!(
+        migrated.protocol_fee_settled_at().unwrap(),
+        PAYMENT_GRACE_PERIOD_SECONDS
+    );
+    assert_eq!(migrated.pause_flags, 0);
+    assert!(migrated.require_not_paused().is_ok());
+    assert_eq!(migrated.operator, Pubkey::default());
+    assert_eq!(migrated.keeper_count, 0);
+    assert_eq!(migrated.config_timelock_secs, 0);
+    assert!(migrated.require_no_config_timelock().is_ok());
+    assert!(!migrated.emergency_mode);
+    assert_eq!(
+        migrated.max_price_age(),
+        u64::from(DEFAULT_MAX_PRICE_AGE_SECS)
+    );
+    assert_eq!(
+        migrated.sol_price_bounds(),
+        MIN_SOL_USD_PRICE_CENTS..=MAX_SOL_USD_PRICE_CENTS
+    );
+    assert_eq!(migrated.lock_periods(), SUBSCRIPTION_LOCK_PERIODS);
+    assert_eq!(migrated.fee_recipient(), None);
+    assert_eq!(migrated.rent_collector(), migrated.authority);
+    assert_eq!(migrated.verified_fee_bps(), None);
+    assert_eq!(migrated.protocol_fee_bps_for_provider(0, true), 250);
+    assert_eq!(migrated.max_price_deviation_bps, 0);
+    assert_eq!(migrated.last_observed_price_cents, 0);
+    assert!(migrated.check_price_deviation(15_000).is_ok());
+    assert_eq!(migrated.price_override_at(0), None);
+    assert_eq!(migrated.total_protocol_fees_usdc, 0);
+    assert_eq!(migrated.reserved, [0; 6]);
+}
+
+#[test]
+fn keeps_appended_values_a_global_state_already_had() {
+    let mut global_state = fresh_global_state();
+    global_state.version = GlobalState::CURRENT_VERSION;
+    global_state.min_deposit_lamports = 1;
+    global_state.liquid_reserve_lamports = 0;
+    global_state.max_services_per_window = 0;
+
+    // Written before the reserved bytes were appended
+    let mut data = Vec::new();
+    global_state.try_serialize(&mut data).unwrap();
+    data.truncate(8 + RESERVED_LAYOUT_SPACE - 64);
+    assert!(needs_migration::<GlobalState>(&data));
+
+    let (_, upgraded) = upgrade_account_data::<GlobalState>(&data).unwrap();
+    let migrated = GlobalState::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert_eq!(migrated.min_deposit_lamports, 1);
+    assert_eq!(migrated.liquid_reserve_lamports, 0);
+    assert_eq!(migrated.max_services_per_window, 0);
+}
+
+#[test]
+fn grows_a_global_state_whose_reserved_bytes_ran_out() {
+    let mut global_state = fresh_global_state();
+    global_state.version = GlobalState::CURRENT_VERSION;
+    global_state.authority = Pubkey::new_unique();
+    global_state.lock_periods = 4;
+    global_state.fee_recipient = Pubkey::new_unique();
+
+    // Written with 11 reserved bytes, too few for rent_collector
+    let mut data = Vec::new();
+    global_state.try_serialize(&mut data).unwrap();
+    data.truncate(8 + RESERVED_LAYOUT_SPACE);
+    assert!(needs_migration::<GlobalState>(&data));
+
+    let (_, upgraded) = upgrade_account_data::<GlobalState>(&data).unwrap();
+    assert_eq!(upgraded.len(), data.len() + 64);
+    let migrated = GlobalState::try_deserialize(&mut &upgraded[..]).unwrap();
+    assert_eq!(migrated.lock_periods(), 4);
+    assert_eq!(migrated.fee_recipient, global_state.fee_recipient);
+    assert_eq!(migrated.rent_collector(), global_state.authority);
+    assert_eq!(migrated.verified_fee_bps(), None);
+    assert_eq!(migrated.price_override_at(0), None);
+    assert_eq!(migrated.total_protocol_fees_usdc, 0);
+    assert_eq!(migrated.reserved, [0; 6]);
+}
+
+#[test]
+fn loads_a_global_state_written_before_the_price_fields_without_migrating() {
+    let mut global_state = fresh_global_state();
+    global_state.version = GlobalState::CURRENT_VERSION;
+    global_state.authority = Pubkey::new_unique();
+    global_state.rent_collector = Pubkey::new_unique();
+    global_state.set_verified_fee(Some(150)).unwrap();
+
+    // The breaker and override fields came out of the reserve, so an account
+    // written before them has the same length and reads them as zero
+    let mut data = Vec::new();
+    global_state.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), 8 + GlobalState::INIT_SPACE);
+    assert!(!needs_migration::<GlobalState>(&data));
+
+    let loaded = GlobalState::try_deserialize(&mut &data[..]).unwrap();
+    assert_eq!(loaded.rent_collector(), global_state.rent_collector);
+    assert_eq!(loaded.verified_fee_bps(), Some(150));
+    assert_eq!(loaded.max_price_deviation_bps, 0);
+    assert_eq!(loaded.last_observed_price_cents, 0);
+    assert!(loaded.check_price_deviation(15_000).is_ok());
+    assert_eq!(loaded.price_override_at(0), None);
+}
+
+#[test]
+fn reads_the_global_state_authority_from_every_layout() {
+    let (original, data) = version_1_global_state_data();
+    assert_eq!(
+        stored_global_state_authority(&data).unwrap(),
+        original.authority
+    );
+
+    assert_eq!(
+        stored_global_state_authority(&legacy_global_state_data()).unwrap(),
+        Pubkey::new_from_array([1; 32])
+    );
+
+    let (_, upgraded) = upgrade_account_data::<GlobalState>(&data).unwrap();
+    assert_eq!(
+        stored_global_state_authority(&upgraded).unwrap(),
+        original.authority
+    );
+
+    assert!(stored_global_state_authority(&legacy_user_data()).is_err());
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{error_code, fresh_global_state};
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+fn global_state() -> GlobalState {
+    let mut global_state = fresh_global_state();
+    global_state.min_deposit_lamports = DEFAULT_MIN_DEPOSIT_LAMPORTS;
+    global_state
+}
+
+#[test]
+fn first_deposit_must_reach_the_minimum() {
+    let global_state = global_state();
+    assert_eq!(
+        error_code(global_state.require_min_deposit(DEFAULT_MIN_DEPOSIT_LAMPORTS - 1, true)),
+        u32::from(ErrorCode::DepositTooSmall)
+    );
+    assert_eq!(
+        error_code(global_state.require_min_deposit(1, true)),
+        u32::from(ErrorCode::DepositTooSmall)
+    );
+    global_state
+        .require_min_deposit(DEFAULT_MIN_DEPOSIT_LAMPORTS, true)
+        .unwrap();
+}
+
+#[test]
+fn top_ups_may_be_any_size() {
+    let global_state = global_state();
+    global_state.require_min_deposit(1, false).unwrap();
+    global_state
+        .require_min_deposit(DEFAULT_MIN_DEPOSIT_LAMPORTS - 1, false)
+        .unwrap();
+}
+
+#[test]
+fn a_zero_minimum_admits_any_first_deposit() {
+    let mut global_state = global_state();
+    global_state.min_deposit_lamports = 0;
+    global_state.require_min_deposit(1, true).unwrap();
+}
+
+#[test]
+fn the_minimum_stays_within_its_bounds() {
+    let mut global_state = global_state();
+    assert_eq!(
+        global_state.set_min_deposit(0).unwrap(),
+        DEFAULT_MIN_DEPOSIT_LAMPORTS
+    );
+    global_state
+        .set_min_deposit(MAX_MIN_DEPOSIT_LAMPORTS)
+        .unwrap();
+    assert_eq!(
+        error_code(
+            global_state
+                .set_min_deposit(MAX_MIN_DEPOSIT_LAMPORTS + 1)
+                .map(|_| ())
+        ),
+        u32::from(ErrorCode::InvalidMinDeposit)
+    );
+    assert_eq!(global_state.min_deposit_lamports, MAX_MIN_DEPOSIT_LAMPORTS);
+}
+
+#[test]
+fn the_minimum_may_not_exceed_the_per_user_cap() {
+    let mut global_state = global_state();
+    global_state.max_deposit_per_user_lamports = 1_000_000_000;
+    assert_eq!(
+        error_code(global_state.set_min_deposit(1_000_000_001).map(|_| ())),
+        u32::from(ErrorCode::InvalidMinDeposit)
+    );
+    global_state.set_min_deposit(1_000_000_000).unwrap();
+    assert_eq!(global_state.min_deposit_lamports, 1_000_000_000);
+}
@@ -0,0 +1,900 @@
+//! End-to-end flows against the `mock` feature's test doubles: the Pyth-layout
+//! feed written by init_mock_price_feed and the mock stake pool program. Each
+//! test drives the real instructions, moving the clock where billing needs it.
+//!
+//! Runs under `cargo test-sbf --features mock` after `anchor build -- --features mock`,
+//! with the Token Metadata fixture described in common/program.rs.
+#![cfg(all(feature = "test-sbf", feature = "mock"))]
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{program_option::COption, program_pack::Pack, stake, sysvar},
+    system_program, AccountDeserialize, AccountSerialize, InstructionData, Space, ToAccountMetas,
+};
+use anchor_spl::{associated_token, metadata, token::spl_token};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+const SERVICE_ID: u64 = 0;
+const FEE_USD_CENTS: u64 = 1_599;
+const BILLING_FREQUENCY_DAYS: u64 = 30;
+const SOL_USD_CENTS: u64 = 15_000; // $150
+const PRICE_EXPO: i32 = -8;
+const DEPOSIT_LAMPORTS: u64 = 10_000_000_000;
+
+fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &subly_program::ID).0
+}
+
+fn event_authority() -> Pubkey {
+    pda(&[b"__event_authority"])
+}
+
+fn account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn program_account<T: AccountSerialize>(value: &T, space: usize) -> Account {
+    let mut data = Vec::with_capacity(space);
+    value.try_serialize(&mut data).unwrap();
+    data.resize(space, 0);
+    account(data, subly_program::ID)
+}
+
+fn packed<T: Pack>(value: T) -> Account {
+    let mut data = vec![0; T::LEN];
+    T::pack(value, &mut data).unwrap();
+    account(data, spl_token::ID)
+}
+
+/// A mock pool's (pool, withdraw authority, protocol JitoSOL vault) for `pool_mint`
+fn mock_pool(pool_mint: &Pubkey) -> (Pubkey, Pubkey, Pubkey) {
+    let pool = Pubkey::find_program_address(
+        &[mock_stake_pool::POOL_SEED.as_bytes(), pool_mint.as_ref()],
+        &mock_stake_pool::ID,
+    )
+    .0;
+    let withdraw_authority = Pubkey::find_program_address(
+        &[
+            pool.as_ref(),
+            mock_stake_pool::WITHDRAW_AUTHORITY_SEED.as_bytes(),
+        ],
+        &mock_stake_pool::ID,
+    )
+    .0;
+    let protocol_jito_vault =
+        associated_token::get_associated_token_address(&pda(&[b"protocol_authority"]), pool_mint);
+    (pool, withdraw_authority, protocol_jito_vault)
+}
+
+/// JitoSOL stand-in, minted only by the mock pool
+fn pool_mint_account(withdraw_authority: Pubkey) -> Account {
+    packed(spl_token::state::Mint {
+        mint_authority: COption::Some(withdraw_authority),
+        supply: 0,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    })
+}
+
+/// `instruction` with every account in `moves` swapped for its replacement
+fn redirect(mut instruction: Instruction, moves: &[(Pubkey, Pubkey)]) -> Instruction {
+    for meta in &mut instruction.accounts {
+        if let Some((_, to)) = moves.iter().find(|(from, _)| *from == meta.pubkey) {
+            meta.pubkey = *to;
+        }
+    }
+    instruction
+}
+
+/// Protocol pointed at the mock feed and mock pool, one provider service and one subscriber
+struct Fixture {
+    user: Keypair,
+    provider: Pubkey,
+    price_feed: Pubkey,
+    usdc_mint: Pubkey,
+    global_state: Pubkey,
+    treasury: Pubkey,
+    protocol_usdc_treasury: Pubkey,
+    provider_account: Pubkey,
+    subscription_service: Pubkey,
+    user_account: Pubkey,
+    sol_vault: Pubkey,
+    stake_account: Pubkey,
+    user_subscription: Pubkey,
+    certificate_mint: Pubkey,
+    certificate_token_account: Pubkey,
+    certificate_attributes: Pubkey,
+    certificate_authority: Pubkey,
+    pool_mint: Pubkey,
+    pool: Pubkey,
+    pool_withdraw_authority: Pubkey,
+    protocol_authority: Pubkey,
+    protocol_jito_vault: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let user = Keypair::new();
+        let provider = Pubkey::new_unique();
+        let usdc_mint = Pubkey::new_unique();
+        let pool_mint = Pubkey::new_unique();
+        let user_key = user.pubkey();
+        let service_id = SERVICE_ID.to_le_bytes();
+        let treasury = pda(&[TREASURY_SEED.as_bytes()]);
+        let certificate_mint = pda(&[
+            CERTIFICATE_SEED.as_bytes(),
+            user_key.as_ref(),
+            provider.as_ref(),
+            &service_id,
+        ]);
+        let (pool, pool_withdraw_authority, protocol_jito_vault) = mock_pool(&pool_mint);
+        let protocol_authority = pda(&[b"protocol_authority"]);
+
+        Self {
+            provider,
+            price_feed: pda(&[MOCK_PRICE_FEED_SEED.as_bytes()]),
+            usdc_mint,
+            global_state: pda(&[GLOBAL_STATE_SEED.as_bytes()]),
+            treasury,
+            protocol_usdc_treasury: associated_token::get_associated_token_address(
+                &treasury, &usdc_mint,
+            ),
+            provider_account: pda(&[PROVIDER_SEED.as_bytes(), provider.as_ref()]),
+            subscription_service: pda(&[
+                SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+                provider.as_ref(),
+                &service_id,
+            ]),
+            user_account: pda(&[USER_SEED.as_bytes(), user_key.as_ref()]),
+            sol_vault: pda(&[SOL_VAULT_SEED.as_bytes(), user_key.as_ref()]),
+            stake_account: pda(&[STAKE_ACCOUNT_SEED.as_bytes(), user_key.as_ref()]),
+            user_subscription: pda(&[
+                USER_SUBSCRIPTION_SEED.as_bytes(),
+                user_key.as_ref(),
+                provider.as_ref(),
+                &service_id,
+            ]),
+            certificate_mint,
+            certificate_token_account: associated_token::get_associated_token_address(
+                &user_key,
+                &certificate_mint,
+            ),
+            certificate_attributes: pda(&[
+                CERTIFICATE_ATTRIBUTES_SEED.as_bytes(),
+                certificate_mint.as_ref(),
+            ]),
+            certificate_authority: pda(&[CERTIFICATE_AUTHORITY_SEED.as_bytes()]),
+            pool_mint,
+            pool,
+            pool_withdraw_authority,
+            protocol_authority,
+            protocol_jito_vault,
+            user,
+        }
+    }
+
+    /// Both programs loaded, the provider's service and the USDC accounts in
+    /// place, the mock feed published and the protocol initialized against the mocks
+    async fn start(&self) -> ProgramTestContext {
+        let mut program_test = ProgramTest::new("subly_program", subly_program::ID, None);
+        program_test.prefer_bpf(true);
+        program_test.add_program("mock_stake_pool", mock_stake_pool::ID, None);
+        program_test.add_program("mpl_token_metadata", metadata::ID, None);
+
+        program_test.add_account(
+            self.provider_account,
+            program_account(
+                &Provider {
+                    version: Provider::CURRENT_VERSION,
+                    wallet: self.provider,
+                    name: "Provider".to_string(),
+                    description: "Mock localnet fixture".to_string(),
+                    total_subscribers: 0,
+                    is_verified: true,
+                    created_at: 0,
+                    bump: Pubkey::find_program_address(
+                        &[PROVIDER_SEED.as_bytes(), self.provider.as_ref()],
+                        &subly_program::ID,
+                    )
+                    .1,
+                    last_service_registered_at: 0,
+                    services_registered_in_window: 0,
+                    is_banned: false,
+                    verified_at: 0,
+                },
+                8 + Provider::INIT_SPACE,
+            ),
+        );
+        program_test.add_account(
+            self.subscription_service,
+            program_account(
+                &SubscriptionService {
+                    version: SubscriptionService::CURRENT_VERSION,
+                    provider: self.provider,
+                    service_id: SERVICE_ID,
+                    name: "Streaming".to_string(),
+                    description: "Mock localnet fixture".to_string(),
+                    fee_usd: FEE_USD_CENTS,
+                    billing_frequency_days: BILLING_FREQUENCY_DAYS,
+                    image_url: "https://example.com/image.png".to_string(),
+                    current_subscribers: 0,
+                    is_active: true,
+                    created_at: 0,
+                    certificate_mode: CERTIFICATE_MODE_TOKEN,
+                    royalty_bps: 0,
+                    certificate_metadata_uri: "https://example.com/certificate.json".to_string(),
+                    bump: Pubkey::find_program_address(
+                        &[
+                            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+                            self.provider.as_ref(),
+                            &SERVICE_ID.to_le_bytes(),
+                        ],
+                        &subly_program::ID,
+                    )
+                    .1,
+                    frozen: false,
+                    deactivated_by_admin: false,
+                    deactivation_reason: 0,
+                },
+                8 + SubscriptionService::INIT_SPACE,
+            ),
+        );
+        program_test.add_account(
+            self.usdc_mint,
+            packed(spl_token::state::Mint {
+                mint_authority: COption::None,
+                supply: 1_000_000_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            }),
+        );
+        program_test.add_account(
+            self.protocol_usdc_treasury,
+            packed(spl_token::state::Account {
+                mint: self.usdc_mint,
+                owner: self.treasury,
+                amount: 1_000_000_000_000,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            }),
+        );
+        program_test.add_account(
+            self.pool_mint,
+            pool_mint_account(self.pool_withdraw_authority),
+        );
+        program_test.add_account(
+            self.user.pubkey(),
+            Account::new(100_000_000_000, 0, &system_program::ID),
+        );
+
+        let mut context = program_test.start_with_context().await;
+        let now = self.clock(&mut context).await.unix_timestamp;
+        let payer = context.payer.pubkey();
+        self.run(&mut context, self.publish_price(payer, now), &[])
+            .await;
+        self.run(
+            &mut context,
+            self.initialize_pool(payer, self.pool_mint),
+            &[],
+        )
+        .await;
+        self.run(&mut context, self.initialize(payer), &[]).await;
+        self.run(&mut context, self.deposit(), &[&self.user]).await;
+        context
+    }
+
+    async fn clock(&self, context: &mut ProgramTestContext) -> Clock {
+        context.banks_client.get_sysvar::<Clock>().await.unwrap()
+    }
+
+    async fn fetch<T: AccountDeserialize>(
+        &self,
+        context: &mut ProgramTestContext,
+        address: Pubkey,
+    ) -> T {
+        let account = context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("account exists");
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn lamports(&self, context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+        context.banks_client.get_balance(address).await.unwrap()
+    }
+
+    async fn token_balance(&self, context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+        let account = context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("token account exists");
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    async fn run(
+        &self,
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) {
+        if let Err((error, logs)) = self.try_run(context, instruction, signers).await {
+            panic!("{error}: {logs:#?}");
+        }
+    }
+
+    async fn try_run(
+        &self,
+        context: &mut ProgramTestContext,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<(), (TransactionError, Option<Vec<String>>)> {
+        let blockhash = context.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        let outcome = context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+            .unwrap();
+        outcome.result.map_err(|error| {
+            (
+                error,
+                outcome.metadata.map(|metadata| metadata.log_messages),
+            )
+        })
+    }
+
+    /// Move the clock past the first payment's due date and republish the feed
+    async fn advance_to_next_payment(&self, context: &mut ProgramTestContext) {
+        let mut clock = self.clock(context).await;
+        clock.unix_timestamp += (BILLING_FREQUENCY_DAYS * 86_400) as i64 + 1;
+        context.set_sysvar(&clock);
+        let payer = context.payer.pubkey();
+        self.run(
+            context,
+            self.publish_price(payer, clock.unix_timestamp),
+            &[],
+        )
+        .await;
+    }
+
+    fn publish_price(&self, payer: Pubkey, publish_time: i64) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::InitMockPriceFeed {
+                price_cents: SOL_USD_CENTS,
+                expo: PRICE_EXPO,
+                publish_time,
+            }
+            .data(),
+            subly_program::accounts::InitMockPriceFeed {
+                payer,
+                price_feed: self.price_feed,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn initialize_pool(&self, payer: Pubkey, pool_mint: Pubkey) -> Instruction {
+        let (pool, withdraw_authority, _) = mock_pool(&pool_mint);
+        Instruction::new_with_bytes(
+            mock_stake_pool::ID,
+            &mock_stake_pool::instruction::InitializePool {}.data(),
+            mock_stake_pool::accounts::InitializePool {
+                payer,
+                pool,
+                withdraw_authority,
+                pool_mint,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn update_config(
+        &self,
+        authority: Pubkey,
+        sol_usd_price_feed: Option<Pubkey>,
+        pool_mint: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::UpdateConfig {
+                sol_usd_price_feed,
+                jito_stake_pool: pool_mint.map(|mint| mock_pool(&mint).0),
+                jito_sol_mint: pool_mint,
+                spl_stake_pool_program: None,
+                usdc_mint: None,
+            }
+            .data(),
+            subly_program::accounts::UpdateConfig {
+                authority,
+                global_state: self.global_state,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn initialize(&self, authority: Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::Initialize {
+                jito_stake_pool: self.pool,
+                jito_sol_mint: self.pool_mint,
+                spl_stake_pool_program: mock_stake_pool::ID,
+                sol_usd_price_feed: self.price_feed,
+                usdc_mint: self.usdc_mint,
+            }
+            .data(),
+            subly_program::accounts::Initialize {
+                authority,
+                global_state: self.global_state,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn deposit(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::Deposit {
+                amount: DEPOSIT_LAMPORTS,
+                auto_stake: false,
+                referrer: None,
+            }
+            .data(),
+            subly_program::accounts::Deposit {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                global_state: self.global_state,
+                sol_vault: self.sol_vault,
+                referrer_account: None,
+                system_program: system_program::ID,
+                stake_account: None,
+                protocol_jito_vault: None,
+                protocol_authority: None,
+                stake_pool_program: None,
+                jito_stake_pool: None,
+                stake_pool_withdraw_authority: None,
+                reserve_stake: None,
+                jito_sol_mint: None,
+                manager_fee_account: None,
+                referrer_pool_tokens: None,
+                token_program: None,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn subscribe(&self) -> Instruction {
+        let metadata_address = |mint: &Pubkey, edition: bool| {
+            let mut seeds = vec![b"metadata".as_ref(), metadata::ID.as_ref(), mint.as_ref()];
+            if edition {
+                seeds.push(b"edition");
+            }
+            Pubkey::find_program_address(&seeds, &metadata::ID).0
+        };
+        let collection_mint = pda(&[
+            CERTIFICATE_COLLECTION_SEED.as_bytes(),
+            self.provider.as_ref(),
+        ]);
+
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::SubscribeToService {
+                provider: self.provider,
+                service_id: SERVICE_ID,
+            }
+            .data(),
+            subly_program::accounts::SubscribeToService {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                user_subscription: self.user_subscription,
+                global_state: self.global_state,
+                sol_usd_price_feed: self.price_feed,
+                usdc_mint: None,
+                certificate_nft_mint: self.certificate_mint,
+                certificate_nft_token_account: self.certificate_token_account,
+                certificate_metadata: metadata_address(&self.certificate_mint, false),
+                certificate_attributes: self.certificate_attributes,
+                certificate_collection_mint: collection_mint,
+                certificate_collection_metadata: metadata_address(&collection_mint, false),
+                certificate_collection_master_edition: metadata_address(&collection_mint, true),
+                certificate_authority: self.certificate_authority,
+                provider_signer: None,
+                rent_sponsor: pda(&[RENT_SPONSOR_SEED.as_bytes()]),
+                token_metadata_program: metadata::ID,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn execute_payment(&self, authority: Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::ExecuteSubscriptionPayment {
+                _user: self.user.pubkey(),
+                _provider: self.provider,
+                _service_id: SERVICE_ID,
+            }
+            .data(),
+            subly_program::accounts::ExecuteSubscriptionPayment {
+                authority,
+                global_state: self.global_state,
+                keeper_registration: None,
+                user_account: self.user_account,
+                user_subscription: self.user_subscription,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                user_sol_vault: self.sol_vault,
+                provider_earnings: pda(&[
+                    PROVIDER_EARNINGS_SEED.as_bytes(),
+                    self.provider.as_ref(),
+                ]),
+                treasury: self.treasury,
+                protocol_usdc_treasury: self.protocol_usdc_treasury,
+                fee_recipient: None,
+                fee_recipient_usdc_account: None,
+                usdc_mint: self.usdc_mint,
+                user_usdc_vault: None,
+                sponsor_escrow: pda(&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                ]),
+                certificate_nft_mint: self.certificate_mint,
+                certificate_nft_token_account: self.certificate_token_account,
+                certificate_authority: self.certificate_authority,
+                certificate_attributes: self.certificate_attributes,
+                payment_record: pda(&[
+                    PAYMENT_RECORD_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                    &0u64.to_le_bytes(),
+                ]),
+                sol_usd_price_feed: self.price_feed,
+                token_program: spl_token::ID,
+                certificate_token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn unsubscribe(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::UnsubscribeFromService {
+                provider: self.provider,
+                service_id: SERVICE_ID,
+            }
+            .data(),
+            subly_program::accounts::UnsubscribeFromService {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                user_subscription: self.user_subscription,
+                subscription_service: self.subscription_service,
+                provider_account: self.provider_account,
+                global_state: self.global_state,
+                sol_usd_price_feed: self.price_feed,
+                certificate_nft_mint: self.certificate_mint,
+                certificate_nft_token_account: self.certificate_token_account,
+                certificate_attributes: self.certificate_attributes,
+                certificate_authority: self.certificate_authority,
+                rent_sponsor: pda(&[RENT_SPONSOR_SEED.as_bytes()]),
+                sponsor_escrow: pda(&[
+                    SPONSOR_ESCROW_SEED.as_bytes(),
+                    self.user_subscription.as_ref(),
+                ]),
+                sponsor: None,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn stake(&self, amount: u64) -> Instruction {
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::StakeSol { amount }.data(),
+            subly_program::accounts::StakeSol {
+                user: self.user.pubkey(),
+                user_account: self.user_account,
+                stake_account: self.stake_account,
+                sol_vault: self.sol_vault,
+                global_state: self.global_state,
+                protocol_jito_vault: self.protocol_jito_vault,
+                protocol_authority: self.protocol_authority,
+                stake_pool_program: mock_stake_pool::ID,
+                jito_stake_pool: self.pool,
+                stake_pool_withdraw_authority: self.pool_withdraw_authority,
+                reserve_stake: self.pool,
+                jito_sol_mint: self.pool_mint,
+                manager_fee_account: self.protocol_jito_vault,
+                referrer_pool_tokens: self.protocol_jito_vault,
+                token_program: spl_token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+                stake_program: stake::program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+
+    fn withdraw_with_unstake(&self, amount: u64) -> Instruction {
+        let user = self.user.pubkey();
+        Instruction::new_with_bytes(
+            subly_program::ID,
+            &subly_program::instruction::WithdrawWithUnstake {
+                amount,
+                jito_apy_bps: 0,
+            }
+            .data(),
+            subly_program::accounts::WithdrawWithUnstake {
+                user,
+                user_account: self.user_account,
+                sol_vault: self.sol_vault,
+                destination: None,
+                withdrawal_allowlist: pda(&[WITHDRAWAL_ALLOWLIST_SEED.as_bytes(), user.as_ref()]),
+                global_state: self.global_state,
+                stake_account: self.stake_account,
+                protocol_jito_vault: self.protocol_jito_vault,
+                protocol_authority: self.protocol_authority,
+                stake_pool_program: mock_stake_pool::ID,
+                jito_stake_pool: self.pool,
+                stake_pool_withdraw_authority: self.pool_withdraw_authority,
+                reserve_stake: self.pool,
+                jito_sol_mint: self.pool_mint,
+                manager_fee_account: self.protocol_jito_vault,
+                clock: sysvar::clock::ID,
+                stake_history: sysvar::stake_history::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                stake_program: stake::program::ID,
+                event_authority: event_authority(),
+                program: subly_program::ID,
+            }
+            .to_account_metas(None),
+        )
+    }
+}
+
+#[tokio::test]
+async fn subscribe_bill_and_unsubscribe_against_the_mock_feed() {
+    let fixture = Fixture::new();
+    let mut context = fixture.start().await;
+
+    fixture
+        .run(&mut context, fixture.subscribe(), &[&fixture.user])
+        .await;
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert!(
+        user.locked_sol > 0,
+        "subscribing locks SOL at the mock price"
+    );
+
+    fixture.advance_to_next_payment(&mut context).await;
+    let authority = context.payer.pubkey();
+    fixture
+        .run(&mut context, fixture.execute_payment(authority), &[])
+        .await;
+    let subscription: UserSubscription =
+        fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert_eq!(subscription.total_payments_made, 1);
+
+    fixture
+        .run(&mut context, fixture.unsubscribe(), &[&fixture.user])
+        .await;
+    let subscription: UserSubscription =
+        fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert!(!subscription.is_active);
+}
+
+#[tokio::test]
+async fn stake_and_withdraw_through_the_mock_pool() {
+    let fixture = Fixture::new();
+    let mut context = fixture.start().await;
+    let stake_lamports = DEPOSIT_LAMPORTS / 2;
+
+    fixture
+        .run(
+            &mut context,
+            fixture.stake(stake_lamports),
+            &[&fixture.user],
+        )
+        .await;
+    // The mock pool mints one pool token per lamport
+    assert_eq!(
+        fixture
+            .token_balance(&mut context, fixture.protocol_jito_vault)
+            .await,
+        stake_lamports
+    );
+
+    // More than the vault's liquid SOL, so part of the stake is redeemed first
+    let wallet_before = fixture.lamports(&mut context, fixture.user.pubkey()).await;
+    let withdraw_lamports = DEPOSIT_LAMPORTS * 9 / 10;
+    fixture
+        .run(
+            &mut context,
+            fixture.withdraw_with_unstake(withdraw_lamports),
+            &[&fixture.user],
+        )
+        .await;
+
+    assert_eq!(
+        fixture.lamports(&mut context, fixture.user.pubkey()).await,
+        wallet_before + withdraw_lamports
+    );
+    let stake_account: StakeAccount = fixture.fetch(&mut context, fixture.stake_account).await;
+    let redeemed = stake_lamports
+        - fixture
+            .token_balance(&mut context, fixture.protocol_jito_vault)
+            .await;
+    assert!(redeemed > 0);
+    assert!(stake_account.staked_amount < stake_lamports);
+}
+
+#[tokio::test]
+async fn subscribe_stake_and_withdraw_follow_a_config_update() {
+    let fixture = Fixture::new();
+    let mut context = fixture.start().await;
+    let authority = context.payer.pubkey();
+
+    // A second feed, a copy of the mock feed at a new address, and a second pool
+    let feed = Pubkey::new_unique();
+    let feed_account = context
+        .banks_client
+        .get_account(fixture.price_feed)
+        .await
+        .unwrap()
+        .unwrap();
+    context.set_account(&feed, &AccountSharedData::from(feed_account));
+    let pool_mint = Pubkey::new_unique();
+    let (pool, withdraw_authority, protocol_jito_vault) = mock_pool(&pool_mint);
+    context.set_account(
+        &pool_mint,
+        &AccountSharedData::from(pool_mint_account(withdraw_authority)),
+    );
+    fixture
+        .run(
+            &mut context,
+            fixture.initialize_pool(authority, pool_mint),
+            &[],
+        )
+        .await;
+
+    fixture
+        .run(
+            &mut context,
+            fixture.update_config(authority, Some(feed), Some(pool_mint)),
+            &[],
+        )
+        .await;
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(global_state.sol_usd_price_feed, feed);
+    assert_eq!(global_state.jito_stake_pool, pool);
+    assert_eq!(global_state.jito_sol_mint, pool_mint);
+
+    // Staking and withdrawing go through the new pool only
+    let stake_lamports = DEPOSIT_LAMPORTS / 4;
+    assert!(fixture
+        .try_run(
+            &mut context,
+            fixture.stake(stake_lamports),
+            &[&fixture.user]
+        )
+        .await
+        .is_err());
+    let to_new_pool = [
+        (fixture.pool, pool),
+        (fixture.pool_mint, pool_mint),
+        (fixture.pool_withdraw_authority, withdraw_authority),
+        (fixture.protocol_jito_vault, protocol_jito_vault),
+    ];
+    fixture
+        .run(
+            &mut context,
+            redirect(fixture.stake(stake_lamports), &to_new_pool),
+            &[&fixture.user],
+        )
+        .await;
+    assert_eq!(
+        fixture
+            .token_balance(&mut context, protocol_jito_vault)
+            .await,
+        stake_lamports
+    );
+
+    // More than the vault's liquid SOL, so part of the new pool's stake is redeemed
+    let withdraw_lamports = DEPOSIT_LAMPORTS * 9 / 10;
+    fixture
+        .run(
+            &mut context,
+            redirect(
+                fixture.withdraw_with_unstake(withdraw_lamports),
+                &to_new_pool,
+            ),
+            &[&fixture.user],
+        )
+        .await;
+    assert!(
+        fixture
+            .token_balance(&mut context, protocol_jito_vault)
+            .await
+            < stake_lamports
+    );
+
+    // The withdrawal emptied the vault; top it up to cover the subscription lock
+    fixture
+        .run(&mut context, fixture.deposit(), &[&fixture.user])
+        .await;
+
+    // The old feed is refused and the new one used
+    let (error, _) = fixture
+        .try_run(&mut context, fixture.subscribe(), &[&fixture.user])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(u32::from(ErrorCode::InvalidPriceFeed))
+        )
+    );
+    fixture
+        .run(
+            &mut context,
+            redirect(fixture.subscribe(), &[(fixture.price_feed, feed)]),
+            &[&fixture.user],
+        )
+        .await;
+}
@@ -0,0 +1,224 @@
+//! The `mock` feature's price feed must parse as a Pyth account and quote
+//! exactly the price it was written with
+#![cfg(feature = "mock")]
+
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use pyth_sdk_solana::state::SolanaPriceAccount;
+use subly_program::{
+    error::ErrorCode,
+    state::GlobalState,
+    utils::{
+        mock_price_account_data, pyth_price_to_cents, sol_usd_price_cents_at,
+        sol_usd_price_cents_or_override_at, MOCK_PRICE_EXPO_RANGE,
+    },
+};
+
+/// (price in cents, publish time) read back through the Pyth SDK
+fn parse(mut data: Vec<u8>) -> (u64, i64) {
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let account = AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    let price = SolanaPriceAccount::account_info_to_feed(&account)
+        .unwrap()
+        .get_price_unchecked();
+    (
+        pyth_price_to_cents(price.price, price.expo).unwrap(),
+        price.publish_time,
+    )
+}
+
+#[test]
+fn mock_feed_round_trips_through_the_pyth_parser() {
+    for expo in MOCK_PRICE_EXPO_RANGE {
+        for price_cents in [1_000, 15_012, 100_000] {
+            let data = mock_price_account_data(price_cents, expo, 1_700_000_000).unwrap();
+            assert_eq!(parse(data), (price_cents, 1_700_000_000), "expo {expo}");
+        }
+    }
+}
+
+#[test]
+fn mock_feed_rejects_unrepresentable_prices() {
+    assert!(mock_price_account_data(0, -8, 0).is_err());
+    assert!(mock_price_account_data(15_000, -1, 0).is_err());
+    assert!(mock_price_account_data(15_000, -13, 0).is_err());
+    assert!(mock_price_account_data(u64::MAX, -12, 0).is_err());
+}
+
+/// A mock price of `price_cents` published at `publish_time`, read at `now`
+/// under `global_state`'s max price age and sanity range
+fn price_at(
+    global_state: &GlobalState,
+    price_cents: u64,
+    publish_time: i64,
+    now: i64,
+) -> Result<u64> {
+    let mut data = mock_price_account_data(price_cents, -8, publish_time).unwrap();
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let account = AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    sol_usd_price_cents_at(
+        &account,
+        global_state.max_price_age(),
+        global_state.sol_price_bounds(),
+        now,
+    )
+}
+
+#[test]
+fn a_tiny_max_price_age_rejects_a_slightly_old_price() {
+    let mut global_state = fresh_global_state();
+    global_state.set_max_price_age(1).unwrap();
+    assert_eq!(
+        error_code(price_at(
+            &global_state,
+            15_000,
+            1_700_000_000,
+            1_700_000_010
+        )),
+        u32::from(ErrorCode::PriceNotAvailable)
+    );
+}
+
+#[test]
+fn a_longer_max_price_age_accepts_the_same_price() {
+    let mut global_state = fresh_global_state();
+    // The default applies while the field is unset
+    assert_eq!(
+        price_at(&global_state, 15_000, 1_700_000_000, 1_700_000_010).unwrap(),
+        15_000
+    );
+    global_state.set_max_price_age(60).unwrap();
+    assert_eq!(
+        price_at(&global_state, 15_000, 1_700_000_000, 1_700_000_010).unwrap(),
+        15_000
+    );
+}
+
+#[test]
+fn prices_outside_the_configured_bounds_are_rejected() {
+    let mut global_state = fresh_global_state();
+    // $1500 is above the default $1000 ceiling
+    assert_eq!(
+        error_code(price_at(&global_state, 150_000, 100, 100)),
+        u32::from(ErrorCode::InvalidPrice)
+    );
+
+    global_state.set_sol_price_bounds(10_000, 200_000).unwrap();
+    assert_eq!(price_at(&global_state, 150_000, 100, 100).unwrap(), 150_000);
+    assert_eq!(
+        error_code(price_at(&global_state, 5_000, 100, 100)),
+        u32::from(ErrorCode::InvalidPrice)
+    );
+
+    // 1 and u64::MAX switch the sanity range off
+    global_state.set_sol_price_bounds(1, u64::MAX).unwrap();
+    assert_eq!(price_at(&global_state, 5_000, 100, 100).unwrap(), 5_000);
+}
+
+/// A mock price published at `publish_time`, read at `now` through the shared
+/// path that falls back to the price override
+fn price_or_override_at(
+    global_state: &GlobalState,
+    price_cents: u64,
+    publish_time: i64,
+    now: i64,
+) -> Result<u64> {
+    let data = mock_price_account_data(price_cents, -8, publish_time).unwrap();
+    feed_or_override_at(global_state, data, now)
+}
+
+/// A price feed account holding `data`, read at `now` through the override path
+fn feed_or_override_at(global_state: &GlobalState, mut data: Vec<u8>, now: i64) -> Result<u64> {
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let account = AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    sol_usd_price_cents_or_override_at(&account, global_state, now)
+}
+
+#[test]
+fn a_fresh_pyth_price_beats_the_override() {
+    let mut global_state = fresh_global_state();
+    global_state.set_price_override(14_000, 1_000, 100).unwrap();
+    assert_eq!(
+        price_or_override_at(&global_state, 15_000, 100, 110).unwrap(),
+        15_000
+    );
+}
+
+#[test]
+fn the_override_serves_only_while_pyth_is_stale_and_it_is_live() {
+    let mut global_state = fresh_global_state();
+    global_state.set_max_price_age(60).unwrap();
+    global_state.set_price_override(14_000, 1_000, 100).unwrap();
+
+    // Published at 100, so stale from 161 on
+    assert_eq!(
+        price_or_override_at(&global_state, 15_000, 100, 500).unwrap(),
+        14_000
+    );
+    // Valid up to and including valid_until
+    assert_eq!(
+        price_or_override_at(&global_state, 15_000, 100, 1_000).unwrap(),
+        14_000
+    );
+    assert_eq!(
+        error_code(price_or_override_at(&global_state, 15_000, 100, 1_001)),
+        u32::from(ErrorCode::PriceNotAvailable)
+    );
+
+    global_state.set_price_override(0, 0, 500).unwrap();
+    assert_eq!(
+        error_code(price_or_override_at(&global_state, 15_000, 100, 500)),
+        u32::from(ErrorCode::PriceNotAvailable)
+    );
+}
+
+#[test]
+fn the_override_does_not_cover_a_bad_feed_or_price() {
+    let mut global_state = fresh_global_state();
+    global_state.set_price_override(14_000, 1_000, 100).unwrap();
+
+    assert_eq!(
+        error_code(feed_or_override_at(&global_state, vec![0; 64], 500)),
+        u32::from(ErrorCode::InvalidPriceFeed)
+    );
+    // $1500 is fresh but above the default $1000 ceiling
+    assert_eq!(
+        error_code(price_or_override_at(&global_state, 150_000, 500, 500)),
+        u32::from(ErrorCode::InvalidPrice)
+    );
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::error_code;
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+fn empty<T: AnchorDeserialize + Space>() -> T {
+    T::deserialize(&mut &vec![0u8; T::INIT_SPACE][..]).unwrap()
+}
+
+#[test]
+fn repeat_reports_update_the_reporters_single_report() {
+    let mut report: Report = empty();
+    report.record(REPORT_REASON_SCAM, 100).unwrap();
+    assert_eq!(report.report_count, 1);
+    assert_eq!(report.first_reported_at, 100);
+
+    report.record(REPORT_REASON_NOT_DELIVERED, 200).unwrap();
+    assert_eq!(report.report_count, 2);
+    assert_eq!(report.reason_code, REPORT_REASON_NOT_DELIVERED);
+    assert_eq!(report.first_reported_at, 100);
+    assert_eq!(report.last_reported_at, 200);
+}
+
+#[test]
+fn unknown_report_reasons_are_rejected() {
+    for reason_code in [0, REPORT_REASON_OTHER + 1, u8::MAX] {
+        let mut report: Report = empty();
+        assert_eq!(
+            error_code(report.record(reason_code, 100)),
+            u32::from(ErrorCode::InvalidReportReason),
+            "reason {reason_code}"
+        );
+        assert_eq!(report.report_count, 0);
+    }
+}
+
+#[test]
+fn frozen_services_hold_settlement_until_all_are_released() {
+    let mut earnings: ProviderEarnings = empty();
+    earnings.credit(5_000_000, 100).unwrap();
+
+    earnings.hold().unwrap();
+    earnings.hold().unwrap();
+    let held = u32::from(ErrorCode::EarningsHeld);
+    assert_eq!(error_code(earnings.take_pending(200)), held);
+
+    // Billing of the provider's other services keeps crediting while held
+    earnings.credit(1_000_000, 300).unwrap();
+
+    earnings.release().unwrap();
+    assert_eq!(error_code(earnings.take_pending(400)), held);
+
+    earnings.release().unwrap();
+    assert_eq!(earnings.take_pending(500).unwrap(), 6_000_000);
+    assert_eq!(
+        error_code(earnings.release()),
+        u32::from(ErrorCode::ArithmeticUnderflow)
+    );
+}
+
+#[test]
+fn ledger_identity_is_set_once() {
+    let mut earnings: ProviderEarnings = empty();
+    let provider = Pubkey::new_unique();
+    earnings.set_provider_if_new(provider, 254);
+    earnings.set_provider_if_new(Pubkey::new_unique(), 1);
+    assert_eq!(earnings.provider, provider);
+    assert_eq!(earnings.bump, 254);
+}
+
+#[test]
+fn admin_deactivation_is_final_for_the_provider() {
+    let mut service: SubscriptionService = empty();
+    service.is_active = true;
+    service.deactivate_by_admin(REPORT_REASON_SCAM).unwrap();
+    assert!(!service.is_active);
+    assert!(service.deactivated_by_admin);
+    assert_eq!(service.deactivation_reason, REPORT_REASON_SCAM);
+
+    let deactivated = u32::from(ErrorCode::ServiceDeactivatedByAdmin);
+    assert_eq!(
+        error_code(service.require_provider_may_set_status(true)),
+        deactivated
+    );
+    assert!(service.require_provider_may_set_status(false).is_ok());
+
+    // A second takedown keeps the original reason
+    assert_eq!(
+        error_code(service.deactivate_by_admin(REPORT_REASON_OTHER)),
+        deactivated
+    );
+    assert_eq!(service.deactivation_reason, REPORT_REASON_SCAM);
+}
+
+#[test]
+fn admin_deactivation_needs_a_known_reason() {
+    for reason_code in [0, REPORT_REASON_OTHER + 1] {
+        let mut service: SubscriptionService = empty();
+        service.is_active = true;
+        assert_eq!(
+            error_code(service.deactivate_by_admin(reason_code)),
+            u32::from(ErrorCode::InvalidReportReason),
+            "reason {reason_code}"
+        );
+        assert!(service.is_active);
+        assert!(service.require_provider_may_set_status(true).is_ok());
+    }
+}
@@ -0,0 +1,187 @@
+use anchor_lang::prelude::*;
+use subly_program::{
+    constants::*,
+    error::ErrorCode,
+    utils::{
+        convert_sol_to_token_amount, convert_usd_to_sol_lamports, convert_usd_to_token_amount,
+        pyth_price_to_cents,
+        subscription_lock_lamports, subscription_lock_token_amount, validate_service_fee,
+    },
+};
+
+const SOL_USD_CENTS: u64 = 15_000; // $150 per SOL
+
+#[test]
+fn converts_large_monthly_fees_without_overflow() {
+    // $500, $2,000 and $10,000 in cents; the old u64 unsubscribe math overflowed near $1,800
+    let cases = [
+        (50_000, 3_333_333_333),
+        (200_000, 13_333_333_333),
+        (1_000_000, 66_666_666_666),
+    ];
+    for (fee_usd_cents, expected_lamports) in cases {
+        assert_eq!(
+            convert_usd_to_sol_lamports(fee_usd_cents, SOL_USD_CENTS).unwrap(),
+            expected_lamports
+        );
+    }
+}
+
+#[test]
+fn converts_at_the_price_bounds() {
+    // $15.99 at $10 and $1000 per SOL
+    assert_eq!(convert_usd_to_sol_lamports(1599, 1_000).unwrap(), 1_599_000_000);
+    assert_eq!(convert_usd_to_sol_lamports(1599, 100_000).unwrap(), 15_990_000);
+}
+
+#[test]
+fn rejects_unrepresentable_results() {
+    assert!(convert_usd_to_sol_lamports(u64::MAX, 1).is_err());
+    assert!(convert_usd_to_sol_lamports(1599, 0).is_err());
+}
+
+#[test]
+fn converts_lamports_to_settlement_tokens() {
+    // 2 SOL at $150 is $300, i.e. 300_000_000 base units of a 6-decimal token
+    assert_eq!(
+        convert_sol_to_token_amount(2_000_000_000, SOL_USD_CENTS, 6).unwrap(),
+        300_000_000
+    );
+}
+
+#[test]
+fn lock_covers_every_lock_period() {
+    assert_eq!(
+        subscription_lock_lamports(1599, SOL_USD_CENTS, SUBSCRIPTION_LOCK_PERIODS).unwrap(),
+        convert_usd_to_sol_lamports(1599, SOL_USD_CENTS).unwrap() * SUBSCRIPTION_LOCK_PERIODS
+    );
+}
+
+fn is_invalid_fee<T: std::fmt::Debug>(result: Result<T>) -> bool {
+    matches!(
+        result,
+        Err(Error::AnchorError(error))
+            if error.error_code_number == u32::from(ErrorCode::InvalidFeeAmount)
+    )
+}
+
+#[test]
+fn adversarial_fee_at_ten_dollar_sol_is_rejected_not_wrapped() {
+    // At $10/SOL this fee converts to a representable amount whose 12x wraps to
+    // about 0.01 SOL under the old bare `* 12`
+    let sol_usd_cents = MIN_SOL_USD_PRICE_CENTS;
+    let fee_usd_cents = 1_537_228_672_810;
+
+    // The helpers refuse it before doing any math
+    assert!(is_invalid_fee(convert_usd_to_sol_lamports(
+        fee_usd_cents,
+        sol_usd_cents
+    )));
+    assert!(is_invalid_fee(subscription_lock_lamports(
+        fee_usd_cents,
+        sol_usd_cents,
+        SUBSCRIPTION_LOCK_PERIODS
+    )));
+
+    // The registration bound rejects such a fee up front
+    assert!(validate_service_fee(fee_usd_cents, MAX_SERVICE_FEE_USD_CENTS).is_err());
+}
+
+#[test]
+fn largest_allowed_fee_locks_at_the_lowest_price() {
+    assert!(validate_service_fee(MAX_SERVICE_FEE_USD_CENTS, MAX_SERVICE_FEE_USD_CENTS).is_ok());
+    assert!(
+        validate_service_fee(MAX_SERVICE_FEE_USD_CENTS + 1, MAX_SERVICE_FEE_USD_CENTS).is_err()
+    );
+    assert!(validate_service_fee(0, MAX_SERVICE_FEE_USD_CENTS).is_err());
+    assert!(subscription_lock_lamports(
+        MAX_SERVICE_FEE_USD_CENTS,
+        MIN_SOL_USD_PRICE_CENTS,
+        u64::from(MAX_SUBSCRIPTION_LOCK_PERIODS)
+    )
+    .is_ok());
+}
+
+#[test]
+fn configured_fee_cap_applies_at_the_bound() {
+    let cap = 50_000; // $500
+    assert!(validate_service_fee(cap, cap).is_ok());
+    assert!(is_invalid_fee(validate_service_fee(cap + 1, cap)));
+
+    // A cap above the ceiling cannot admit fees beyond it
+    assert!(is_invalid_fee(validate_service_fee(
+        MAX_SERVICE_FEE_USD_CENTS + 1,
+        u64::MAX
+    )));
+}
+
+#[test]
+fn helpers_bound_their_inputs() {
+    assert!(subscription_lock_lamports(
+        MAX_SERVICE_FEE_USD_CENTS,
+        MIN_SOL_USD_PRICE_CENTS,
+        u64::from(MAX_SUBSCRIPTION_LOCK_PERIODS)
+    )
+    .is_ok());
+    assert!(is_invalid_fee(subscription_lock_lamports(
+        MAX_SERVICE_FEE_USD_CENTS + 1,
+        SOL_USD_CENTS,
+        SUBSCRIPTION_LOCK_PERIODS
+    )));
+    assert!(subscription_lock_token_amount(
+        MAX_SERVICE_FEE_USD_CENTS,
+        9,
+        u64::from(MAX_SUBSCRIPTION_LOCK_PERIODS)
+    )
+    .is_ok());
+    assert!(is_invalid_fee(subscription_lock_token_amount(
+        MAX_SERVICE_FEE_USD_CENTS + 1,
+        6,
+        SUBSCRIPTION_LOCK_PERIODS
+    )));
+
+    // Sponsorships convert several periods at once, up to MAX_CONVERTIBLE_USD_CENTS
+    assert!(
+        convert_usd_to_sol_lamports(MAX_CONVERTIBLE_USD_CENTS, MIN_SOL_USD_PRICE_CENTS).is_ok()
+    );
+    assert!(is_invalid_fee(convert_usd_to_sol_lamports(
+        MAX_CONVERTIBLE_USD_CENTS + 1,
+        SOL_USD_CENTS
+    )));
+    assert!(convert_usd_to_token_amount(MAX_CONVERTIBLE_USD_CENTS, 9).is_ok());
+    assert!(is_invalid_fee(convert_usd_to_token_amount(
+        MAX_CONVERTIBLE_USD_CENTS + 1,
+        6
+    )));
+}
+
+#[test]
+fn usd_converts_to_usdc_base_units_without_a_price() {
+    // $9.99 with six decimals
+    assert_eq!(convert_usd_to_token_amount(999, 6).unwrap(), 9_990_000);
+    // Two decimals keeps cents as-is; sub-cent precision is floored away
+    assert_eq!(convert_usd_to_token_amount(999, 2).unwrap(), 999);
+    assert_eq!(convert_usd_to_token_amount(999, 0).unwrap(), 9);
+    assert_eq!(
+        subscription_lock_token_amount(999, 6, SUBSCRIPTION_LOCK_PERIODS).unwrap(),
+        9_990_000 * SUBSCRIPTION_LOCK_PERIODS
+    );
+    assert!(subscription_lock_token_amount(
+        MAX_SERVICE_FEE_USD_CENTS,
+        9,
+        u64::from(MAX_SUBSCRIPTION_LOCK_PERIODS)
+    )
+    .is_ok());
+}
+
+#[test]
+fn pyth_prices_convert_to_cents() {
+    // $150.12345678 at Pyth's usual -8 exponent; sub-cent digits are dropped
+    assert_eq!(pyth_price_to_cents(15_012_345_678, -8).unwrap(), 15_012);
+    assert_eq!(pyth_price_to_cents(150, 0).unwrap(), 15_000);
+    assert!(pyth_price_to_cents(0, -8).is_err());
+    assert!(pyth_price_to_cents(-1, -8).is_err());
+    // Exponents beyond u64 fail instead of panicking
+    assert!(pyth_price_to_cents(1, 20).is_err());
+    assert!(pyth_price_to_cents(1, -20).is_err());
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+#[test]
+fn require_not_paused_follows_the_flag() {
+    let mut global_state = fresh_global_state();
+    assert!(global_state.require_not_paused().is_ok());
+
+    global_state.is_paused = true;
+    assert_eq!(
+        error_code(global_state.require_not_paused()),
+        u32::from(ErrorCode::ProtocolPaused)
+    );
+}
+
+#[test]
+fn pausing_and_unpausing_stamp_their_times() {
+    let mut global_state = fresh_global_state();
+
+    assert!(!global_state.apply_pause(true, 100));
+    assert!(global_state.require_not_paused().is_err());
+    assert_eq!((global_state.paused_at, global_state.unpaused_at), (100, 0));
+
+    // Repeating a pause keeps the time it started
+    assert!(global_state.apply_pause(true, 150));
+    assert_eq!(global_state.paused_at, 100);
+
+    assert!(global_state.apply_pause(false, 200));
+    assert!(global_state.require_not_paused().is_ok());
+    assert_eq!(
+        (global_state.paused_at, global_state.unpaused_at),
+        (100, 200)
+    );
+
+    assert!(!global_state.apply_pause(false, 250));
+    assert_eq!(global_state.unpaused_at, 200);
+}
+
+#[test]
+fn an_area_flag_only_halts_its_own_area() {
+    let mut global_state = fresh_global_state();
+    global_state
+        .set_pause_flags(PAUSE_SUBSCRIPTIONS | PAUSE_PAYMENTS)
+        .unwrap();
+
+    for area in [PAUSE_SUBSCRIPTIONS, PAUSE_PAYMENTS] {
+        assert_eq!(
+            error_code(global_state.require_not_paused_for(area)),
+            u32::from(ErrorCode::ProtocolPaused)
+        );
+    }
+    for area in [PAUSE_DEPOSITS, PAUSE_STAKING] {
+        assert!(global_state.require_not_paused_for(area).is_ok());
+    }
+    // Provider and service registration only follow the master switch
+    assert!(global_state.require_not_paused().is_ok());
+}
+
+#[test]
+fn the_master_switch_halts_every_area() {
+    let mut global_state = fresh_global_state();
+    global_state.apply_pause(true, 100);
+
+    for area in [
+        PAUSE_DEPOSITS,
+        PAUSE_SUBSCRIPTIONS,
+        PAUSE_PAYMENTS,
+        PAUSE_STAKING,
+    ] {
+        assert_eq!(
+            error_code(global_state.require_not_paused_for(area)),
+            u32::from(ErrorCode::ProtocolPaused)
+        );
+    }
+}
+
+#[test]
+fn unknown_pause_flags_are_rejected() {
+    let mut global_state = fresh_global_state();
+    assert_eq!(global_state.set_pause_flags(PAUSE_FLAGS_ALL).unwrap(), 0);
+    assert_eq!(
+        error_code(global_state.set_pause_flags(1 << 4).map(|_| ())),
+        u32::from(ErrorCode::InvalidPauseFlags)
+    );
+    assert_eq!(global_state.pause_flags, PAUSE_FLAGS_ALL);
+    assert_eq!(global_state.set_pause_flags(0).unwrap(), PAUSE_FLAGS_ALL);
+}
+
+#[test]
+fn emergency_withdraw_is_rejected_outside_emergency_mode() {
+    let mut global_state = fresh_global_state();
+    assert_eq!(
+        error_code(global_state.require_emergency_mode()),
+        u32::from(ErrorCode::EmergencyModeNotActive)
+    );
+
+    // A pause alone does not open the emergency exit
+    global_state.apply_pause(true, 100);
+    assert_eq!(
+        error_code(global_state.require_emergency_mode()),
+        u32::from(ErrorCode::EmergencyModeNotActive)
+    );
+
+    assert!(!global_state.set_emergency_mode(true).unwrap());
+    assert!(global_state.require_emergency_mode().is_ok());
+}
+
+#[test]
+fn emergency_withdraw_leaves_sponsored_subscriptions_to_unsubscribe() {
+    let user = Pubkey::new_unique();
+    let mut subscription =
+        UserSubscription::deserialize(&mut &vec![0u8; UserSubscription::INIT_SPACE][..]).unwrap();
+    subscription.user = user;
+    assert_eq!(
+        subscription.batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_NOT_ACTIVE
+    );
+
+    subscription.is_active = true;
+    assert_eq!(
+        subscription.batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_NONE
+    );
+
+    // Deactivating would strand the escrow: a resubscribe clears the sponsor
+    subscription.sponsor = Pubkey::new_unique();
+    subscription.sponsor_escrow_lamports = 1_000_000;
+    assert_eq!(
+        subscription.batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_SPONSORED
+    );
+}
+
+#[test]
+fn emergency_mode_needs_a_paused_protocol() {
+    let mut global_state = fresh_global_state();
+    assert_eq!(
+        error_code(global_state.set_emergency_mode(true).map(|_| ())),
+        u32::from(ErrorCode::EmergencyModeRequiresPause)
+    );
+    assert!(!global_state.emergency_mode);
+
+    // Turning it off is always allowed
+    assert!(!global_state.set_emergency_mode(false).unwrap());
+}
+
+#[test]
+fn emergency_withdraw_clears_locked_and_pending_sol() {
+    let mut user = User::deserialize(&mut &vec![0u8; User::INIT_SPACE][..]).unwrap();
+    user.deposited_sol = 5_000_000_000;
+    user.locked_sol = 3_000_000_000;
+    user.pending_withdrawal_lamports = 1_000_000_000;
+    user.withdrawal_unlocks_at = 500;
+    user.staked_sol = 2_000_000_000;
+    user.usdc_balance = 10_000_000;
+
+    // Locked funds are not available to a normal withdrawal
+    assert_eq!(user.available_sol().unwrap(), 1_000_000_000);
+
+    assert_eq!(user.clear_sol_for_emergency_withdraw(), 5_000_000_000);
+    assert_eq!(
+        (user.deposited_sol, user.locked_sol),
+        (0, 0),
+        "deposits and locks are cleared"
+    );
+    assert_eq!(
+        (user.pending_withdrawal_lamports, user.withdrawal_unlocks_at),
+        (0, 0)
+    );
+    assert_eq!(user.available_sol().unwrap(), 0);
+    // Staked SOL and USDC leave through their own withdrawals
+    assert_eq!(user.staked_sol, 2_000_000_000);
+    assert_eq!(user.usdc_balance, 10_000_000);
+}
@@ -0,0 +1,1284 @@
+//! Pause outcome of every program instruction, checked by sending each one to the
+//! compiled program. Closed instructions must fail with ProtocolPaused under the
+//! master switch and under their own area flag; the ways out must still succeed
+//! with the protocol paused.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs. The
+//! instruction list comes from the IDL, so run `anchor build` first.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::InstructionError, sysvar},
+    system_program, Space,
+};
+use anchor_spl::{associated_token, metadata, token::spl_token};
+use common::program::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    program_option::COption,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use std::collections::BTreeSet;
+use subly_program::{
+    constants::*,
+    error::ErrorCode,
+    state::*,
+    utils::{BUBBLEGUM_PROGRAM_ID, SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID},
+    CompressedLeafProof,
+};
+
+/// What a pause does to an instruction, see GlobalState::require_not_paused
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pause {
+    /// Moves value into or through the protocol; refused while paused
+    Closed,
+    /// A way out for users; must keep working while paused
+    Open,
+    /// Authority setters, views and account maintenance; not gated
+    Unaffected,
+}
+
+use Pause::*;
+
+/// Every program instruction with its intended pause outcome
+#[rustfmt::skip]
+const PAUSE_MATRIX: &[(&str, Pause)] = &[
+    // Deposits, staking and yield
+    ("deposit", Closed),
+    ("deposit_wsol", Closed),
+    ("deposit_usdc", Closed),
+    ("stake_sol", Closed),
+    ("unstake_sol", Closed),
+    ("claim_yield", Closed),
+    // Subscriptions and billing
+    ("subscribe_to_service", Closed),
+    ("subscribe_to_service_compressed", Closed),
+    ("sponsor_subscription", Closed),
+    ("claim_promo", Closed),
+    ("process_subscription_payments", Closed),
+    ("execute_subscription_payment", Closed),
+    ("crank_payments", Closed),
+    // Providers and services
+    ("register_provider", Closed),
+    ("update_provider", Closed),
+    ("register_subscription_service", Closed),
+    ("update_subscription_service", Closed),
+    // Ways out
+    ("withdraw", Open),
+    ("withdraw_with_unstake", Open),
+    ("execute_withdrawal", Open),
+    ("request_withdrawal", Open),
+    ("cancel_withdrawal", Open),
+    ("withdraw_usdc", Open),
+    ("withdraw_sol_as_usdc", Open),
+    ("sweep_dust", Open),
+    ("emergency_withdraw", Open),
+    ("set_withdrawal_delay", Open),
+    ("set_withdrawal_allowlist", Open),
+    ("unsubscribe_from_service", Open),
+    ("unsubscribe_batch", Open),
+    ("unsubscribe_from_service_compressed", Open),
+    ("expire_subscription", Open),
+    ("close_payment_record", Open),
+    ("close_user_subscription", Open),
+    ("settle_provider_earnings", Open),
+    // Authority setters
+    ("initialize", Unaffected),
+    ("initialize_treasury", Unaffected),
+    ("set_paused", Unaffected),
+    ("pause_protocol", Unaffected),
+    ("unpause_protocol", Unaffected),
+    ("set_pause_flags", Unaffected),
+    ("set_emergency_mode", Unaffected),
+    ("set_service_status", Unaffected),
+    ("set_soulbound_certificates", Unaffected),
+    ("set_sponsor_certificate_rent", Unaffected),
+    ("fund_rent_sponsor", Unaffected),
+    ("set_certificate_tree", Unaffected),
+    ("set_min_deposit", Unaffected),
+    ("set_liquid_reserve", Unaffected),
+    ("set_max_deposit_per_user", Unaffected),
+    ("set_max_service_fee", Unaffected),
+    ("set_max_price_age", Unaffected),
+    ("set_max_price_deviation", Unaffected),
+    ("reset_price_breaker", Unaffected),
+    ("set_price_override", Unaffected),
+    ("set_sol_price_bounds", Unaffected),
+    ("set_lock_periods", Unaffected),
+    ("set_fee_recipient", Unaffected),
+    ("set_rent_collector", Unaffected),
+    ("set_service_registration_limit", Unaffected),
+    ("set_require_verified_providers", Unaffected),
+    ("set_execution_mode", Unaffected),
+    ("set_operator", Unaffected),
+    ("set_protocol_fee", Unaffected),
+    ("set_verified_fee", Unaffected),
+    ("update_config", Unaffected),
+    ("set_config_timelock", Unaffected),
+    ("queue_config_change", Unaffected),
+    ("execute_config_change", Unaffected),
+    ("cancel_config_change", Unaffected),
+    ("propose_authority_transfer", Unaffected),
+    ("accept_authority_transfer", Unaffected),
+    ("cancel_authority_transfer", Unaffected),
+    ("withdraw_treasury", Unaffected),
+    ("add_keeper", Unaffected),
+    ("remove_keeper", Unaffected),
+    ("set_payment_record_retention", Unaffected),
+    ("set_payment_record_disputed", Unaffected),
+    // Reports and moderation
+    ("report_service", Unaffected),
+    ("freeze_service", Unaffected),
+    ("unfreeze_service", Unaffected),
+    ("admin_deactivate_service", Unaffected),
+    ("set_provider_verified", Unaffected),
+    ("verify_provider", Unaffected),
+    ("revoke_provider_verification", Unaffected),
+    ("set_provider_banned", Unaffected),
+    // Views
+    ("check_subscribable_services", Unaffected),
+    ("get_protocol_stats", Unaffected),
+    ("check_user_subscription", Unaffected),
+    ("get_due_payments", Unaffected),
+    // Account maintenance
+    ("resize_provider", Unaffected),
+    ("resize_service", Unaffected),
+    ("migrate_global_state", Unaffected),
+    ("migrate_provider", Unaffected),
+    ("migrate_subscription_service", Unaffected),
+    ("migrate_user", Unaffected),
+    ("migrate_user_subscription", Unaffected),
+    ("migrate_stake_account", Unaffected),
+    ("backfill_service_registry", Unaffected),
+    // Localnet test doubles
+    ("init_mock_price_feed", Unaffected),
+];
+
+/// Closed instructions that also stop when their own area is paused with
+/// set_pause_flags, and the PAUSE_* flag they check. The rest follow only the
+/// master switch
+#[rustfmt::skip]
+const PAUSE_AREAS: &[(&str, u8)] = &[
+    ("deposit", PAUSE_DEPOSITS),
+    ("deposit_wsol", PAUSE_DEPOSITS),
+    ("deposit_usdc", PAUSE_DEPOSITS),
+    ("subscribe_to_service", PAUSE_SUBSCRIPTIONS),
+    ("subscribe_to_service_compressed", PAUSE_SUBSCRIPTIONS),
+    ("sponsor_subscription", PAUSE_SUBSCRIPTIONS),
+    ("claim_promo", PAUSE_SUBSCRIPTIONS),
+    ("process_subscription_payments", PAUSE_PAYMENTS),
+    ("execute_subscription_payment", PAUSE_PAYMENTS),
+    ("crank_payments", PAUSE_PAYMENTS),
+    ("stake_sol", PAUSE_STAKING),
+    ("deposit", PAUSE_STAKING), // auto_stake
+    ("unstake_sol", PAUSE_STAKING),
+    ("claim_yield", PAUSE_STAKING),
+];
+
+/// Cases that start before the user subscribes
+const UNSUBSCRIBED_CASES: &[&str] = &[
+    "subscribe_to_service",
+    "subscribe_to_service_compressed",
+    "sweep_dust",
+    "unsubscribe_from_service_compressed",
+];
+
+/// Service the compressed cases register next to the fixture's token service
+const COMPRESSED_SERVICE_ID: u64 = 1;
+
+const ONE_SOL: u64 = 1_000_000_000;
+const TEN_USDC: u64 = 10_000_000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Halt {
+    /// pause_protocol, plus emergency mode for emergency_withdraw
+    Protocol,
+    /// set_pause_flags with a single area
+    Area(u8),
+}
+
+/// Instructions compiled in only with a feature, missing from an IDL built without it
+const FEATURE_GATED: &[&str] = &["init_mock_price_feed"];
+
+/// Instruction names in the IDL anchor generates from the #[program] module
+fn program_instructions() -> BTreeSet<String> {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../target/idl/subly_program.json"
+    );
+    let idl = std::fs::read_to_string(path)
+        .unwrap_or_else(|error| panic!("{path}: {error}, run `anchor build` first"));
+    let idl: serde_json::Value = serde_json::from_str(&idl).unwrap();
+    idl["instructions"]
+        .as_array()
+        .expect("IDL instructions")
+        .iter()
+        .map(|instruction| instruction["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn stake_account_address(fixture: &Fixture) -> Pubkey {
+    pda(&[
+        STAKE_ACCOUNT_SEED.as_bytes(),
+        fixture.user.pubkey().as_ref(),
+    ])
+}
+
+fn protocol_authority() -> Pubkey {
+    pda(&[b"protocol_authority"])
+}
+
+fn protocol_jito_vault(fixture: &Fixture) -> Pubkey {
+    associated_token::get_associated_token_address(&protocol_authority(), &fixture.jito_sol_mint)
+}
+
+fn usdc_account_of(fixture: &Fixture, owner: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address(owner, &fixture.usdc_mint)
+}
+
+fn withdrawal_allowlist(fixture: &Fixture) -> Pubkey {
+    pda(&[
+        WITHDRAWAL_ALLOWLIST_SEED.as_bytes(),
+        fixture.user.pubkey().as_ref(),
+    ])
+}
+
+fn service_address(fixture: &Fixture, service_id: u64) -> Pubkey {
+    pda(&[
+        SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+        fixture.provider.pubkey().as_ref(),
+        &service_id.to_le_bytes(),
+    ])
+}
+
+fn subscription_address(fixture: &Fixture, service_id: u64) -> Pubkey {
+    pda(&[
+        USER_SUBSCRIPTION_SEED.as_bytes(),
+        fixture.user.pubkey().as_ref(),
+        fixture.provider.pubkey().as_ref(),
+        &service_id.to_le_bytes(),
+    ])
+}
+
+/// Active stake with nothing left in JitoSOL, so exits never reach the pool
+fn add_stake_account(fixture: &Fixture, context: &mut ProgramTestContext) {
+    let address = stake_account_address(fixture);
+    let mut stake_account: StakeAccount = zeroed();
+    stake_account.version = StakeAccount::CURRENT_VERSION;
+    stake_account.user = fixture.user.pubkey();
+    stake_account.is_active = true;
+    stake_account.bump = Pubkey::find_program_address(
+        &[
+            STAKE_ACCOUNT_SEED.as_bytes(),
+            fixture.user.pubkey().as_ref(),
+        ],
+        &subly_program::ID,
+    )
+    .1;
+    fixture.set_account(
+        context,
+        address,
+        program_account(&stake_account, 8 + StakeAccount::INIT_SPACE),
+    );
+    fixture.set_account(
+        context,
+        protocol_jito_vault(fixture),
+        token_account(fixture.jito_sol_mint, protocol_authority(), 0),
+    );
+}
+
+/// Copy of the fixture service under COMPRESSED_SERVICE_ID issuing compressed
+/// certificates from a configured tree, which is returned
+async fn add_compressed_service(
+    fixture: &Fixture,
+    context: &mut ProgramTestContext,
+    deactivated_by_admin: bool,
+) -> Pubkey {
+    let tree = Pubkey::new_unique();
+    fixture
+        .update(
+            context,
+            fixture.global_state,
+            |global_state: &mut GlobalState| {
+                global_state.certificate_merkle_tree = tree;
+            },
+        )
+        .await;
+
+    let address = service_address(fixture, COMPRESSED_SERVICE_ID);
+    let mut service: SubscriptionService =
+        fixture.fetch(context, fixture.subscription_service).await;
+    service.service_id = COMPRESSED_SERVICE_ID;
+    service.certificate_mode = CERTIFICATE_MODE_COMPRESSED;
+    service.deactivated_by_admin = deactivated_by_admin;
+    service.bump = Pubkey::find_program_address(
+        &[
+            SUBSCRIPTION_SERVICE_SEED.as_bytes(),
+            fixture.provider.pubkey().as_ref(),
+            &COMPRESSED_SERVICE_ID.to_le_bytes(),
+        ],
+        &subly_program::ID,
+    )
+    .1;
+    fixture.set_account(
+        context,
+        address,
+        program_account(&service, 8 + SubscriptionService::INIT_SPACE),
+    );
+    tree
+}
+
+fn asset_id(tree: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"asset", tree.as_ref(), &nonce.to_le_bytes()],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .0
+}
+
+fn tree_config(tree: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[tree.as_ref()], &BUBBLEGUM_PROGRAM_ID).0
+}
+
+fn deposit(fixture: &Fixture, auto_stake: bool) -> Instruction {
+    let user = fixture.user.pubkey();
+    let staking = |address: Pubkey| auto_stake.then_some(address);
+    instruction(
+        subly_program::instruction::Deposit {
+            amount: ONE_SOL,
+            auto_stake,
+            referrer: None,
+        },
+        subly_program::accounts::Deposit {
+            user,
+            user_account: fixture.user_account,
+            global_state: fixture.global_state,
+            sol_vault: fixture.sol_vault,
+            referrer_account: None,
+            system_program: system_program::ID,
+            stake_account: staking(stake_account_address(fixture)),
+            protocol_jito_vault: staking(protocol_jito_vault(fixture)),
+            protocol_authority: staking(protocol_authority()),
+            stake_pool_program: staking(fixture.stake_pool_program),
+            jito_stake_pool: staking(fixture.jito_stake_pool),
+            stake_pool_withdraw_authority: staking(Pubkey::new_unique()),
+            reserve_stake: staking(Pubkey::new_unique()),
+            jito_sol_mint: staking(fixture.jito_sol_mint),
+            manager_fee_account: staking(Pubkey::new_unique()),
+            referrer_pool_tokens: staking(Pubkey::new_unique()),
+            token_program: staking(spl_token::ID),
+            event_authority: event_authority(),
+            program: subly_program::ID,
+        },
+    )
+}
+
+/// Build the case for `name` on `context`, returning the instruction to send
+/// and the signers it needs besides the payer
+async fn prepare(
+    fixture: &Fixture,
+    context: &mut ProgramTestContext,
+    name: &str,
+    halt: Halt,
+) -> (Instruction, Vec<Keypair>) {
+    let user = fixture.user.pubkey();
+    let provider = fixture.provider.pubkey();
+    let payer = context.payer.pubkey();
+    let now = fixture.clock(context).await.unix_timestamp;
+    let as_user = vec![fixture.user.insecure_clone()];
+    let as_provider = vec![fixture.provider.insecure_clone()];
+
+    match name {
+        // Only the area check reaches the staking leg of a deposit
+        "deposit" => {
+            let auto_stake = halt == Halt::Area(PAUSE_STAKING);
+            if auto_stake {
+                add_stake_account(fixture, context);
+            }
+            (deposit(fixture, auto_stake), as_user)
+        }
+        "deposit_wsol" => {
+            let wsol_mint = spl_token::native_mint::ID;
+            let user_wsol_account = Pubkey::new_unique();
+            fixture.set_account(context, wsol_mint, mint_account(0, 9));
+            let mut wsol = packed(spl_token::state::Account {
+                mint: wsol_mint,
+                owner: user,
+                amount: ONE_SOL,
+                state: spl_token::state::AccountState::Initialized,
+                is_native: COption::Some(0),
+                ..Default::default()
+            });
+            wsol.lamports += ONE_SOL;
+            fixture.set_account(context, user_wsol_account, wsol);
+            let instruction = instruction(
+                subly_program::instruction::DepositWsol { amount: ONE_SOL },
+                subly_program::accounts::DepositWsol {
+                    user,
+                    user_account: fixture.user_account,
+                    global_state: fixture.global_state,
+                    sol_vault: fixture.sol_vault,
+                    referrer_account: None,
+                    wsol_mint,
+                    user_wsol_account,
+                    temp_wsol_account: pda(&[WSOL_TEMP_SEED.as_bytes(), user.as_ref()]),
+                    token_program: spl_token::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "deposit_usdc" => {
+            let user_usdc_account = usdc_account_of(fixture, &user);
+            fixture.set_account(
+                context,
+                user_usdc_account,
+                token_account(fixture.usdc_mint, user, TEN_USDC),
+            );
+            let instruction = instruction(
+                subly_program::instruction::DepositUsdc { amount: TEN_USDC },
+                subly_program::accounts::DepositUsdc {
+                    user,
+                    user_account: fixture.user_account,
+                    global_state: fixture.global_state,
+                    sol_vault: fixture.sol_vault,
+                    usdc_mint: fixture.usdc_mint,
+                    user_usdc_account,
+                    usdc_vault: usdc_account_of(fixture, &fixture.sol_vault),
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "stake_sol" => {
+            let instruction = instruction(
+                subly_program::instruction::StakeSol { amount: ONE_SOL },
+                subly_program::accounts::StakeSol {
+                    user,
+                    user_account: fixture.user_account,
+                    stake_account: stake_account_address(fixture),
+                    sol_vault: fixture.sol_vault,
+                    global_state: fixture.global_state,
+                    protocol_jito_vault: protocol_jito_vault(fixture),
+                    protocol_authority: protocol_authority(),
+                    stake_pool_program: fixture.stake_pool_program,
+                    jito_stake_pool: fixture.jito_stake_pool,
+                    stake_pool_withdraw_authority: Pubkey::new_unique(),
+                    reserve_stake: Pubkey::new_unique(),
+                    jito_sol_mint: fixture.jito_sol_mint,
+                    manager_fee_account: Pubkey::new_unique(),
+                    referrer_pool_tokens: Pubkey::new_unique(),
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    stake_program: anchor_lang::solana_program::stake::program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "unstake_sol" => {
+            add_stake_account(fixture, context);
+            let instruction = instruction(
+                subly_program::instruction::UnstakeSol {
+                    jito_sol_amount: 1,
+                    jito_apy_bps: 0,
+                },
+                subly_program::accounts::UnstakeSol {
+                    user,
+                    user_account: fixture.user_account,
+                    stake_account: stake_account_address(fixture),
+                    sol_vault: fixture.sol_vault,
+                    global_state: fixture.global_state,
+                    protocol_jito_vault: protocol_jito_vault(fixture),
+                    protocol_authority: protocol_authority(),
+                    stake_pool_program: fixture.stake_pool_program,
+                    jito_stake_pool: fixture.jito_stake_pool,
+                    stake_pool_withdraw_authority: Pubkey::new_unique(),
+                    reserve_stake: Pubkey::new_unique(),
+                    jito_sol_mint: fixture.jito_sol_mint,
+                    manager_fee_account: Pubkey::new_unique(),
+                    clock: sysvar::clock::ID,
+                    stake_history: sysvar::stake_history::ID,
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    stake_program: anchor_lang::solana_program::stake::program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "claim_yield" => {
+            add_stake_account(fixture, context);
+            let instruction = instruction(
+                subly_program::instruction::ClaimYield {},
+                subly_program::accounts::ClaimYield {
+                    user,
+                    user_account: fixture.user_account,
+                    global_state: fixture.global_state,
+                    stake_account: stake_account_address(fixture),
+                    sol_vault: fixture.sol_vault,
+                    jito_vault: pda(&[JITO_VAULT_SEED.as_bytes()]),
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "subscribe_to_service" => (fixture.subscribe(), as_user),
+        "subscribe_to_service_compressed" => {
+            let tree = add_compressed_service(fixture, context, false).await;
+            let instruction = instruction(
+                subly_program::instruction::SubscribeToServiceCompressed {
+                    provider,
+                    service_id: COMPRESSED_SERVICE_ID,
+                },
+                subly_program::accounts::SubscribeToServiceCompressed {
+                    user,
+                    user_account: fixture.user_account,
+                    subscription_service: service_address(fixture, COMPRESSED_SERVICE_ID),
+                    provider_account: fixture.provider_account,
+                    user_subscription: subscription_address(fixture, COMPRESSED_SERVICE_ID),
+                    global_state: fixture.global_state,
+                    sol_usd_price_feed: fixture.price_feed,
+                    usdc_mint: None,
+                    certificate_authority: fixture.certificate_authority,
+                    tree_config: tree_config(&tree),
+                    merkle_tree: tree,
+                    bubblegum_program: BUBBLEGUM_PROGRAM_ID,
+                    log_wrapper: SPL_NOOP_PROGRAM_ID,
+                    compression_program: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "sponsor_subscription" => {
+            let instruction = instruction(
+                subly_program::instruction::SponsorSubscription {
+                    _user: user,
+                    _provider: provider,
+                    _service_id: SERVICE_ID,
+                    periods: 1,
+                },
+                subly_program::accounts::SponsorSubscription {
+                    sponsor: payer,
+                    global_state: fixture.global_state,
+                    user_subscription: fixture.user_subscription,
+                    subscription_service: fixture.subscription_service,
+                    sponsor_escrow: pda(&[
+                        SPONSOR_ESCROW_SEED.as_bytes(),
+                        fixture.user_subscription.as_ref(),
+                    ]),
+                    sol_usd_price_feed: fixture.price_feed,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, vec![])
+        }
+        "claim_promo" => {
+            let nonce = 0u64;
+            let instruction = instruction(
+                subly_program::instruction::ClaimPromo {
+                    provider,
+                    service_id: SERVICE_ID,
+                    discount_bps: 1_000,
+                    expiry: now + 86_400,
+                    nonce,
+                },
+                subly_program::accounts::ClaimPromo {
+                    user,
+                    provider_account: fixture.provider_account,
+                    global_state: fixture.global_state,
+                    user_subscription: fixture.user_subscription,
+                    promo_claim: pda(&[
+                        PROMO_CLAIM_SEED.as_bytes(),
+                        provider.as_ref(),
+                        &nonce.to_le_bytes(),
+                    ]),
+                    instructions_sysvar: sysvar::instructions::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "process_subscription_payments" => {
+            let instruction = instruction(
+                subly_program::instruction::ProcessSubscriptionPayments {},
+                subly_program::accounts::ProcessSubscriptionPayments {
+                    authority: payer,
+                    global_state: fixture.global_state,
+                    keeper_registration: None,
+                    treasury: fixture.treasury,
+                    sol_usd_price_feed: fixture.price_feed,
+                    usdc_mint: fixture.usdc_mint,
+                    protocol_usdc_treasury: fixture.protocol_usdc_treasury,
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, vec![])
+        }
+        "execute_subscription_payment" => {
+            fixture.advance_to_next_payment(context).await;
+            (fixture.execute_payment(payer), vec![])
+        }
+        "crank_payments" => {
+            let instruction = instruction(
+                subly_program::instruction::CrankPayments {},
+                subly_program::accounts::CrankPayments {
+                    authority: payer,
+                    global_state: fixture.global_state,
+                    keeper_registration: None,
+                    sol_usd_price_feed: fixture.price_feed,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, vec![])
+        }
+        // The payer registers as a second provider
+        "register_provider" => {
+            let provider_nft_mint = Keypair::new();
+            let certificate_collection_mint =
+                pda(&[CERTIFICATE_COLLECTION_SEED.as_bytes(), payer.as_ref()]);
+            let instruction = instruction(
+                subly_program::instruction::RegisterProvider {
+                    name: "Second provider".to_string(),
+                    description: "Registered while paused".to_string(),
+                },
+                subly_program::accounts::RegisterProvider {
+                    provider: payer,
+                    global_state: fixture.global_state,
+                    provider_account: pda(&[PROVIDER_SEED.as_bytes(), payer.as_ref()]),
+                    provider_nft_mint: provider_nft_mint.pubkey(),
+                    provider_nft_token_account: associated_token::get_associated_token_address(
+                        &payer,
+                        &provider_nft_mint.pubkey(),
+                    ),
+                    certificate_collection_mint,
+                    certificate_collection_token_account:
+                        associated_token::get_associated_token_address(
+                            &fixture.certificate_authority,
+                            &certificate_collection_mint,
+                        ),
+                    certificate_collection_metadata: metadata_address(
+                        &certificate_collection_mint,
+                        false,
+                    ),
+                    certificate_collection_master_edition: metadata_address(
+                        &certificate_collection_mint,
+                        true,
+                    ),
+                    certificate_authority: fixture.certificate_authority,
+                    token_metadata_program: metadata::ID,
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    rent: sysvar::rent::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, vec![provider_nft_mint])
+        }
+        "update_provider" => {
+            let instruction = instruction(
+                subly_program::instruction::UpdateProvider {
+                    name: Some("Renamed".to_string()),
+                    description: None,
+                },
+                subly_program::accounts::UpdateProvider {
+                    provider,
+                    global_state: fixture.global_state,
+                    provider_account: fixture.provider_account,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_provider)
+        }
+        // The fixture service was injected, so the next id has to skip it
+        "register_subscription_service" => {
+            let service_id = SERVICE_ID + 1;
+            fixture
+                .update(
+                    context,
+                    fixture.global_state,
+                    |global_state: &mut GlobalState| {
+                        global_state.total_services = service_id;
+                    },
+                )
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::RegisterSubscriptionService {
+                    name: "Music".to_string(),
+                    description: "Registered while paused".to_string(),
+                    fee_usd: FEE_USD_CENTS,
+                    billing_frequency_days: BILLING_FREQUENCY_DAYS,
+                    image_url: "https://example.com/music.png".to_string(),
+                    certificate_metadata_uri: "https://example.com/music.json".to_string(),
+                },
+                subly_program::accounts::RegisterSubscriptionService {
+                    provider,
+                    provider_account: fixture.provider_account,
+                    global_state: fixture.global_state,
+                    subscription_service: service_address(fixture, service_id),
+                    service_registry_page: pda(&[
+                        SERVICE_REGISTRY_SEED.as_bytes(),
+                        &ServiceRegistryPage::page_of(service_id).to_le_bytes(),
+                    ]),
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_provider)
+        }
+        "update_subscription_service" => {
+            let instruction = instruction(
+                subly_program::instruction::UpdateSubscriptionService {
+                    service_id: SERVICE_ID,
+                    name: Some("Renamed".to_string()),
+                    description: None,
+                    fee_usd: None,
+                    billing_frequency_days: None,
+                    image_url: None,
+                    certificate_mode: None,
+                    royalty_bps: None,
+                    certificate_metadata_uri: None,
+                },
+                subly_program::accounts::UpdateSubscriptionService {
+                    provider,
+                    global_state: fixture.global_state,
+                    subscription_service: fixture.subscription_service,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_provider)
+        }
+        "withdraw" => (fixture.withdraw(ONE_SOL), as_user),
+        // The vault covers the amount, so nothing is unstaked first
+        "withdraw_with_unstake" => {
+            add_stake_account(fixture, context);
+            let instruction = instruction(
+                subly_program::instruction::WithdrawWithUnstake {
+                    amount: ONE_SOL,
+                    jito_apy_bps: 0,
+                },
+                subly_program::accounts::WithdrawWithUnstake {
+                    user,
+                    user_account: fixture.user_account,
+                    sol_vault: fixture.sol_vault,
+                    destination: None,
+                    withdrawal_allowlist: withdrawal_allowlist(fixture),
+                    global_state: fixture.global_state,
+                    stake_account: stake_account_address(fixture),
+                    protocol_jito_vault: protocol_jito_vault(fixture),
+                    protocol_authority: protocol_authority(),
+                    stake_pool_program: fixture.stake_pool_program,
+                    jito_stake_pool: fixture.jito_stake_pool,
+                    stake_pool_withdraw_authority: Pubkey::new_unique(),
+                    reserve_stake: Pubkey::new_unique(),
+                    jito_sol_mint: fixture.jito_sol_mint,
+                    manager_fee_account: Pubkey::new_unique(),
+                    clock: sysvar::clock::ID,
+                    stake_history: sysvar::stake_history::ID,
+                    token_program: spl_token::ID,
+                    system_program: system_program::ID,
+                    stake_program: anchor_lang::solana_program::stake::program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "execute_withdrawal" => {
+            fixture
+                .update(context, fixture.user_account, |user_account: &mut User| {
+                    user_account.pending_withdrawal_lamports = ONE_SOL;
+                    user_account.withdrawal_unlocks_at = now;
+                })
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::ExecuteWithdrawal {},
+                subly_program::accounts::Withdraw {
+                    user,
+                    user_account: fixture.user_account,
+                    sol_vault: fixture.sol_vault,
+                    destination: None,
+                    withdrawal_allowlist: withdrawal_allowlist(fixture),
+                    global_state: fixture.global_state,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "request_withdrawal" => {
+            fixture
+                .update(context, fixture.user_account, |user_account: &mut User| {
+                    user_account.withdrawal_delay_secs = 3_600;
+                })
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::RequestWithdrawal { amount: ONE_SOL },
+                subly_program::accounts::RequestWithdrawal {
+                    user,
+                    user_account: fixture.user_account,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "cancel_withdrawal" => {
+            fixture
+                .update(context, fixture.user_account, |user_account: &mut User| {
+                    user_account.pending_withdrawal_lamports = ONE_SOL;
+                    user_account.withdrawal_unlocks_at = now + 3_600;
+                })
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::CancelWithdrawal {},
+                subly_program::accounts::CancelWithdrawal {
+                    user,
+                    user_account: fixture.user_account,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "withdraw_usdc" => {
+            let usdc_vault = usdc_account_of(fixture, &fixture.sol_vault);
+            let user_usdc_account = usdc_account_of(fixture, &user);
+            fixture.set_account(
+                context,
+                usdc_vault,
+                token_account(fixture.usdc_mint, fixture.sol_vault, TEN_USDC),
+            );
+            fixture.set_account(
+                context,
+                user_usdc_account,
+                token_account(fixture.usdc_mint, user, 0),
+            );
+            fixture
+                .update(context, fixture.user_account, |user_account: &mut User| {
+                    user_account.usdc_balance = TEN_USDC;
+                })
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::WithdrawUsdc { amount: TEN_USDC },
+                subly_program::accounts::WithdrawUsdc {
+                    user,
+                    user_account: fixture.user_account,
+                    global_state: fixture.global_state,
+                    sol_vault: fixture.sol_vault,
+                    withdrawal_allowlist: withdrawal_allowlist(fixture),
+                    usdc_mint: fixture.usdc_mint,
+                    usdc_vault,
+                    user_usdc_account,
+                    token_program: spl_token::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "withdraw_sol_as_usdc" => {
+            let user_usdc_vault = usdc_account_of(fixture, &fixture.sol_vault);
+            fixture.set_account(
+                context,
+                user_usdc_vault,
+                token_account(fixture.usdc_mint, fixture.sol_vault, TEN_USDC),
+            );
+            fixture
+                .update(context, fixture.user_account, |user_account: &mut User| {
+                    user_account.usdc_balance = TEN_USDC;
+                })
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::WithdrawSolAsUsdc {
+                    lamports: ONE_SOL / 100,
+                    min_usdc_out: 0,
+                },
+                subly_program::accounts::WithdrawSolAsUsdc {
+                    user,
+                    user_account: fixture.user_account,
+                    global_state: fixture.global_state,
+                    sol_vault: fixture.sol_vault,
+                    withdrawal_allowlist: withdrawal_allowlist(fixture),
+                    usdc_mint: fixture.usdc_mint,
+                    user_usdc_vault,
+                    user_usdc_account: usdc_account_of(fixture, &user),
+                    sol_usd_price_feed: fixture.price_feed,
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        // What is left in the vault after the deposit is written down to dust
+        "sweep_dust" => {
+            fixture
+                .update(context, fixture.user_account, |user_account: &mut User| {
+                    user_account.deposited_sol = 0;
+                })
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::SweepDust {},
+                subly_program::accounts::SweepDust {
+                    user,
+                    user_account: fixture.user_account,
+                    global_state: fixture.global_state,
+                    sol_vault: fixture.sol_vault,
+                    stake_account: None,
+                    protocol_jito_vault: None,
+                    protocol_authority: None,
+                    stake_pool_program: None,
+                    jito_stake_pool: None,
+                    stake_pool_withdraw_authority: None,
+                    reserve_stake: None,
+                    jito_sol_mint: None,
+                    manager_fee_account: None,
+                    clock: None,
+                    stake_history: None,
+                    stake_program: None,
+                    token_program: None,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "emergency_withdraw" => (fixture.emergency_withdraw(), as_user),
+        "set_withdrawal_delay" => {
+            let instruction = instruction(
+                subly_program::instruction::SetWithdrawalDelay { delay_secs: 3_600 },
+                subly_program::accounts::SetWithdrawalDelay {
+                    user,
+                    user_account: fixture.user_account,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "set_withdrawal_allowlist" => {
+            let instruction = instruction(
+                subly_program::instruction::SetWithdrawalAllowlist {
+                    enabled: true,
+                    destinations: vec![user],
+                },
+                subly_program::accounts::SetWithdrawalAllowlist {
+                    user,
+                    user_account: fixture.user_account,
+                    withdrawal_allowlist: withdrawal_allowlist(fixture),
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "unsubscribe_from_service" => (fixture.unsubscribe(), as_user),
+        "unsubscribe_batch" => (fixture.unsubscribe_batch(), as_user),
+        "expire_subscription" => {
+            fixture.lapse(context).await;
+            (fixture.expire_subscription(payer), vec![])
+        }
+        // A service the authority ended leaves the leaf in place, so no burn
+        // reaches Bubblegum
+        "unsubscribe_from_service_compressed" => {
+            let tree = add_compressed_service(fixture, context, true).await;
+            let nonce = 0;
+            let subscription = subscription_address(fixture, COMPRESSED_SERVICE_ID);
+            let mut user_subscription: UserSubscription = zeroed();
+            user_subscription.version = UserSubscription::CURRENT_VERSION;
+            user_subscription.user = user;
+            user_subscription.provider = provider;
+            user_subscription.service_id = COMPRESSED_SERVICE_ID;
+            user_subscription.subscribed_at = now;
+            user_subscription.next_payment_due = now + (BILLING_FREQUENCY_DAYS * 86_400) as i64;
+            user_subscription.is_active = true;
+            user_subscription.certificate_asset_id = asset_id(&tree, nonce);
+            user_subscription.bump = Pubkey::find_program_address(
+                &[
+                    USER_SUBSCRIPTION_SEED.as_bytes(),
+                    user.as_ref(),
+                    provider.as_ref(),
+                    &COMPRESSED_SERVICE_ID.to_le_bytes(),
+                ],
+                &subly_program::ID,
+            )
+            .1;
+            fixture.set_account(
+                context,
+                subscription,
+                program_account(&user_subscription, 8 + UserSubscription::INIT_SPACE),
+            );
+            let service = service_address(fixture, COMPRESSED_SERVICE_ID);
+            fixture
+                .update(context, service, |service: &mut SubscriptionService| {
+                    service.current_subscribers = 1;
+                })
+                .await;
+            fixture
+                .update(
+                    context,
+                    fixture.provider_account,
+                    |provider: &mut Provider| {
+                        provider.total_subscribers = 1;
+                    },
+                )
+                .await;
+
+            let instruction = instruction(
+                subly_program::instruction::UnsubscribeFromServiceCompressed {
+                    _provider: provider,
+                    _service_id: COMPRESSED_SERVICE_ID,
+                    leaf: CompressedLeafProof {
+                        root: [0; 32],
+                        data_hash: [0; 32],
+                        creator_hash: [0; 32],
+                        nonce,
+                        index: 0,
+                    },
+                },
+                subly_program::accounts::UnsubscribeFromServiceCompressed {
+                    user,
+                    user_account: fixture.user_account,
+                    user_subscription: subscription,
+                    subscription_service: service,
+                    provider_account: fixture.provider_account,
+                    global_state: fixture.global_state,
+                    sol_usd_price_feed: fixture.price_feed,
+                    tree_config: tree_config(&tree),
+                    merkle_tree: tree,
+                    bubblegum_program: BUBBLEGUM_PROGRAM_ID,
+                    log_wrapper: SPL_NOOP_PROGRAM_ID,
+                    compression_program: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+                    sponsor_escrow: pda(&[SPONSOR_ESCROW_SEED.as_bytes(), subscription.as_ref()]),
+                    sponsor: None,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "close_payment_record" => {
+            let payment_record = Pubkey::new_unique();
+            let mut record: PaymentRecord = zeroed();
+            record.user = user;
+            record.provider = provider;
+            fixture.set_account(
+                context,
+                payment_record,
+                program_account(&record, 8 + PaymentRecord::INIT_SPACE),
+            );
+            let instruction = instruction(
+                subly_program::instruction::ClosePaymentRecord {},
+                subly_program::accounts::ClosePaymentRecord {
+                    closer: user,
+                    global_state: fixture.global_state,
+                    payment_record,
+                    user,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "close_user_subscription" => {
+            fixture
+                .run(context, fixture.unsubscribe(), &[&fixture.user])
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::CloseUserSubscription {},
+                subly_program::accounts::CloseUserSubscription {
+                    closer: user,
+                    global_state: fixture.global_state,
+                    user_subscription: fixture.user_subscription,
+                    user,
+                    rent_collector: None,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, as_user)
+        }
+        "settle_provider_earnings" => {
+            let seeds = [PROVIDER_EARNINGS_SEED.as_bytes(), provider.as_ref()];
+            let (provider_earnings, bump) =
+                Pubkey::find_program_address(&seeds, &subly_program::ID);
+            let mut earnings: ProviderEarnings = zeroed();
+            earnings.provider = provider;
+            earnings.pending_usdc = TEN_USDC;
+            earnings.bump = bump;
+            fixture.set_account(
+                context,
+                provider_earnings,
+                program_account(&earnings, 8 + ProviderEarnings::INIT_SPACE),
+            );
+            fixture
+                .update(
+                    context,
+                    fixture.global_state,
+                    |global_state: &mut GlobalState| {
+                        global_state.total_pending_payouts_usdc = TEN_USDC;
+                    },
+                )
+                .await;
+            let instruction = instruction(
+                subly_program::instruction::SettleProviderEarnings {},
+                subly_program::accounts::SettleProviderEarnings {
+                    payer,
+                    global_state: fixture.global_state,
+                    provider,
+                    provider_earnings,
+                    provider_usdc_account: usdc_account_of(fixture, &provider),
+                    treasury: fixture.treasury,
+                    protocol_usdc_treasury: fixture.protocol_usdc_treasury,
+                    usdc_mint: fixture.usdc_mint,
+                    token_program: spl_token::ID,
+                    associated_token_program: associated_token::ID,
+                    system_program: system_program::ID,
+                    event_authority: event_authority(),
+                    program: subly_program::ID,
+                },
+            );
+            (instruction, vec![])
+        }
+        _ => panic!("no pause case for {name}"),
+    }
+}
+
+/// Send `name` once `halt` is in force, on state where it would otherwise succeed
+async fn send_halted(name: &str, halt: Halt) -> Result<u64, Failure> {
+    let fixture = Fixture::new();
+    let mut context = if UNSUBSCRIBED_CASES.contains(&name) {
+        fixture.funded().await
+    } else {
+        fixture.subscribed().await
+    };
+    let (instruction, signers) = prepare(&fixture, &mut context, name, halt).await;
+
+    let authority = context.payer.pubkey();
+    match halt {
+        Halt::Protocol => {
+            fixture
+                .run(&mut context, fixture.pause_protocol(authority), &[])
+                .await;
+            if name == "emergency_withdraw" {
+                fixture
+                    .run(
+                        &mut context,
+                        fixture.set_emergency_mode(authority, true),
+                        &[],
+                    )
+                    .await;
+            }
+        }
+        Halt::Area(flag) => {
+            fixture
+                .run(&mut context, fixture.set_pause_flags(authority, flag), &[])
+                .await;
+        }
+    }
+
+    let signers: Vec<&Keypair> = signers.iter().collect();
+    fixture.try_run(&mut context, instruction, &signers).await
+}
+
+fn assert_protocol_paused(name: &str, outcome: Result<u64, Failure>) {
+    match outcome {
+        Err((TransactionError::InstructionError(0, InstructionError::Custom(code)), _))
+            if code == u32::from(ErrorCode::ProtocolPaused) => {}
+        Err((error, logs)) => {
+            panic!("{name} failed with {error} instead of ProtocolPaused: {logs:#?}")
+        }
+        Ok(_) => panic!("{name} ran while paused"),
+    }
+}
+
+#[test]
+fn every_instruction_has_an_intended_pause_outcome() {
+    let classified: BTreeSet<String> = PAUSE_MATRIX
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    assert_eq!(classified.len(), PAUSE_MATRIX.len(), "duplicate entries");
+
+    let instructions = program_instructions();
+    let unclassified: Vec<_> = instructions.difference(&classified).collect();
+    assert!(
+        unclassified.is_empty(),
+        "no pause outcome for {unclassified:?}"
+    );
+    for name in classified.difference(&instructions) {
+        assert!(
+            FEATURE_GATED.contains(&name.as_str()),
+            "{name} is not a program instruction"
+        );
+    }
+
+    for (name, _) in PAUSE_AREAS {
+        assert!(
+            PAUSE_MATRIX.contains(&(*name, Closed)),
+            "{name} must be Closed in the matrix"
+        );
+    }
+}
+
+#[tokio::test]
+async fn closed_instructions_refuse_to_run_while_paused() {
+    for (name, _) in PAUSE_MATRIX.iter().filter(|(_, pause)| *pause == Closed) {
+        assert_protocol_paused(name, send_halted(name, Halt::Protocol).await);
+    }
+}
+
+#[tokio::test]
+async fn area_flags_halt_their_instructions() {
+    for (name, flag) in PAUSE_AREAS {
+        assert_protocol_paused(name, send_halted(name, Halt::Area(*flag)).await);
+    }
+}
+
+#[tokio::test]
+async fn ways_out_keep_working_while_paused() {
+    for (name, _) in PAUSE_MATRIX.iter().filter(|(_, pause)| *pause == Open) {
+        if let Err((error, logs)) = send_halted(name, Halt::Protocol).await {
+            panic!("{name} must keep working while paused, failed with {error}: {logs:#?}");
+        }
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::{error_code, fresh_global_state};
+use subly_program::{error::ErrorCode, state::*};
+
+const PRICE_CENTS: u64 = 15_000; // $150
+
+fn armed_global_state(max_deviation_bps: u16) -> GlobalState {
+    let mut global_state = fresh_global_state();
+    global_state.max_price_deviation_bps = max_deviation_bps;
+    global_state.record_observed_price(PRICE_CENTS);
+    global_state
+}
+
+#[test]
+fn moves_within_the_threshold_pass() {
+    let global_state = armed_global_state(1_000);
+    for price in [PRICE_CENTS, PRICE_CENTS * 11 / 10, PRICE_CENTS * 9 / 10] {
+        global_state.check_price_deviation(price).unwrap();
+    }
+}
+
+#[test]
+fn a_fifty_percent_jump_halts_pricing() {
+    let mut global_state = armed_global_state(1_000);
+    for price in [PRICE_CENTS * 3 / 2, PRICE_CENTS / 2, PRICE_CENTS * 10] {
+        assert_eq!(
+            error_code(global_state.check_price_deviation(price)),
+            u32::from(ErrorCode::PriceDeviationTooLarge)
+        );
+    }
+    // The refused price never becomes the reference, so the breaker stays tripped
+    assert_eq!(global_state.last_observed_price_cents, PRICE_CENTS);
+
+    assert_eq!(global_state.reset_price_breaker(), PRICE_CENTS);
+    global_state
+        .check_price_deviation(PRICE_CENTS * 3 / 2)
+        .unwrap();
+}
+
+#[test]
+fn breaker_is_off_without_a_threshold_or_a_reference() {
+    let global_state = armed_global_state(0);
+    global_state
+        .check_price_deviation(PRICE_CENTS * 10)
+        .unwrap();
+
+    let mut global_state = armed_global_state(1_000);
+    global_state.reset_price_breaker();
+    global_state
+        .check_price_deviation(PRICE_CENTS * 10)
+        .unwrap();
+}
+
+#[test]
+fn only_arming_the_breaker_skips_the_config_timelock() {
+    let mut global_state = fresh_global_state();
+    global_state.raise_config_timelock(86400).unwrap();
+    global_state
+        .require_direct_price_deviation_change(1_000)
+        .unwrap();
+
+    global_state.max_price_deviation_bps = 1_000;
+    for max_bps in [0, 500, 2_000] {
+        assert_eq!(
+            error_code(global_state.require_direct_price_deviation_change(max_bps)),
+            u32::from(ErrorCode::ConfigTimelocked)
+        );
+    }
+}
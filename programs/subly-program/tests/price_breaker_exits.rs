@@ -0,0 +1,67 @@
+//! Ways out sent to the compiled program with the price circuit breaker tripped.
+//! A subscription from before locks were recorded recomputes its unlock from the
+//! oracle, and must still be cancellable without moving the breaker's reference.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use common::{outcome, program::*};
+use subly_program::{error::ErrorCode, state::*};
+
+/// A third of the fixture's $150 price, far outside the breaker's threshold
+const STALE_REFERENCE_CENTS: u64 = 5_000;
+
+#[tokio::test]
+async fn a_tripped_breaker_does_not_keep_legacy_subscriptions_locked() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    fixture
+        .update(
+            &mut context,
+            fixture.global_state,
+            |global_state: &mut GlobalState| {
+                global_state.max_price_deviation_bps = 1_000;
+                global_state.last_observed_price_cents = STALE_REFERENCE_CENTS;
+            },
+        )
+        .await;
+    // Subscribed before the lock was recorded on the subscription
+    fixture
+        .update(
+            &mut context,
+            fixture.user_subscription,
+            |subscription: &mut UserSubscription| {
+                subscription.locked_sol = 0;
+                subscription.locked_usdc = 0;
+            },
+        )
+        .await;
+
+    // Pricing instructions would refuse the oracle's price
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(
+        outcome(global_state.check_price_deviation(SOL_USD_PRICE as u64 / 1_000_000)),
+        Some(u32::from(ErrorCode::PriceDeviationTooLarge))
+    );
+
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert!(user.locked_sol > 0);
+
+    fixture
+        .run(&mut context, fixture.unsubscribe(), &[&fixture.user])
+        .await;
+
+    let cancelled: UserSubscription = fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert!(!cancelled.is_active);
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert_eq!(user.locked_sol, 0);
+
+    // The breaker stays tripped on the reference it had
+    let global_state: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    assert_eq!(
+        global_state.last_observed_price_cents,
+        STALE_REFERENCE_CENTS
+    );
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use subly_program::{
+    constants::*, error::ErrorCode, state::*, utils::sol_usd_price_cents_or_override_at,
+};
+
+const NOW: i64 = 1_700_000_000;
+const OVERRIDE_CENTS: u64 = 14_000; // $140
+
+/// Price read through an account that is no Pyth feed at all
+fn price_without_feed(global_state: &GlobalState, now: i64) -> Result<u64> {
+    let key = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut lamports = 0;
+    let mut data = vec![];
+    let account = AccountInfo::new(
+        &key,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+    sol_usd_price_cents_or_override_at(&account, global_state, now)
+}
+
+#[test]
+fn a_live_override_does_not_stand_in_for_a_wrong_feed() {
+    let mut global_state = fresh_global_state();
+    global_state
+        .set_price_override(OVERRIDE_CENTS, NOW + 3_600, NOW)
+        .unwrap();
+    assert_eq!(global_state.price_override_at(NOW), Some(OVERRIDE_CENTS));
+    // Only a stale Pyth price is covered, see mock_oracle.rs
+    assert_eq!(
+        error_code(price_without_feed(&global_state, NOW)),
+        u32::from(ErrorCode::InvalidPriceFeed)
+    );
+}
+
+#[test]
+fn an_expired_override_refuses_to_serve() {
+    let mut global_state = fresh_global_state();
+    global_state
+        .set_price_override(OVERRIDE_CENTS, NOW + 3_600, NOW)
+        .unwrap();
+    assert_eq!(global_state.price_override_at(NOW + 3_601), None);
+    assert_eq!(
+        error_code(price_without_feed(&global_state, NOW + 3_601)),
+        u32::from(ErrorCode::InvalidPriceFeed)
+    );
+}
+
+#[test]
+fn the_override_can_be_cleared_at_any_time() {
+    let mut global_state = fresh_global_state();
+    global_state
+        .set_price_override(OVERRIDE_CENTS, NOW + 3_600, NOW)
+        .unwrap();
+    assert_eq!(
+        global_state.set_price_override(0, 0, NOW + 60).unwrap(),
+        (OVERRIDE_CENTS, NOW + 3_600)
+    );
+    assert_eq!(global_state.price_override_valid_until, 0);
+    assert_eq!(global_state.price_override_at(NOW + 60), None);
+
+    // Clearing an override that was never set is fine too
+    global_state.set_price_override(0, 0, NOW).unwrap();
+}
+
+#[test]
+fn overrides_are_bounded_in_price_and_time() {
+    let mut global_state = fresh_global_state();
+    for price_cents in [MIN_SOL_USD_PRICE_CENTS - 1, MAX_SOL_USD_PRICE_CENTS + 1] {
+        assert_eq!(
+            error_code(global_state.set_price_override(price_cents, NOW + 60, NOW)),
+            u32::from(ErrorCode::InvalidPrice),
+            "price {price_cents}"
+        );
+    }
+    for valid_until in [NOW - 1, NOW, NOW + MAX_PRICE_OVERRIDE_SECS + 1] {
+        assert_eq!(
+            error_code(global_state.set_price_override(OVERRIDE_CENTS, valid_until, NOW)),
+            u32::from(ErrorCode::InvalidPriceOverride),
+            "valid until {valid_until}"
+        );
+    }
+    assert_eq!(global_state.price_override_at(NOW), None);
+
+    global_state
+        .set_price_override(OVERRIDE_CENTS, NOW + MAX_PRICE_OVERRIDE_SECS, NOW)
+        .unwrap();
+    assert_eq!(global_state.price_override_at(NOW), Some(OVERRIDE_CENTS));
+}
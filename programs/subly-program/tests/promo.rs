@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use subly_program::{
+    constants::PROMO_MESSAGE_DOMAIN,
+    state::UserSubscription,
+    utils::{parse_ed25519_instruction, promo_claim_message},
+};
+
+/// ed25519 program data for one signature, laid out the way web3.js builds it:
+/// offsets, then public key, signature and message
+fn ed25519_data(pubkey: &Pubkey, message: &[u8], instruction_index: u16) -> Vec<u8> {
+    let pubkey_offset: u16 = 16;
+    let signature_offset: u16 = pubkey_offset + 32;
+    let message_offset: u16 = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for value in [
+        signature_offset,
+        instruction_index,
+        pubkey_offset,
+        instruction_index,
+        message_offset,
+        message.len() as u16,
+        instruction_index,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(&[7; 64]); // Signature bytes are checked by the ed25519 program
+    data.extend_from_slice(message);
+    data
+}
+
+#[test]
+fn promo_message_covers_every_grant_field() {
+    let user = Pubkey::new_unique();
+    let message = promo_claim_message(&user, 3, 2_500, 1_700_000_000, 42);
+    assert_eq!(message.len(), PROMO_MESSAGE_DOMAIN.len() + 32 + 8 + 2 + 8 + 8);
+    assert!(message.starts_with(PROMO_MESSAGE_DOMAIN));
+    assert_ne!(message, promo_claim_message(&user, 3, 2_500, 1_700_000_000, 43));
+    assert_ne!(message, promo_claim_message(&user, 3, 5_000, 1_700_000_000, 42));
+}
+
+#[test]
+fn parses_a_self_contained_signature_check() {
+    let signer = Pubkey::new_unique();
+    let message = promo_claim_message(&Pubkey::new_unique(), 1, 1_000, 1_700_000_000, 1);
+    let data = ed25519_data(&signer, &message, u16::MAX);
+
+    let (parsed_signer, parsed_message) = parse_ed25519_instruction(&data).unwrap();
+    assert_eq!(parsed_signer, signer);
+    assert_eq!(parsed_message, &message[..]);
+}
+
+#[test]
+fn rejects_offsets_into_other_instructions() {
+    // Pointing at another instruction's data would verify bytes the check never covered
+    let message = promo_claim_message(&Pubkey::new_unique(), 1, 1_000, 1_700_000_000, 1);
+    let data = ed25519_data(&Pubkey::new_unique(), &message, 0);
+    assert!(parse_ed25519_instruction(&data).is_none());
+}
+
+#[test]
+fn rejects_multiple_or_truncated_signatures() {
+    let message = promo_claim_message(&Pubkey::new_unique(), 1, 1_000, 1_700_000_000, 1);
+    let mut data = ed25519_data(&Pubkey::new_unique(), &message, u16::MAX);
+    data[0] = 2;
+    assert!(parse_ed25519_instruction(&data).is_none());
+
+    let data = ed25519_data(&Pubkey::new_unique(), &message, u16::MAX);
+    assert!(parse_ed25519_instruction(&data[..data.len() - 1]).is_none());
+}
+
+#[test]
+fn discount_reduces_the_fee() {
+    let mut subscription = UserSubscription {
+        version: 1,
+        user: Pubkey::new_unique(),
+        provider: Pubkey::new_unique(),
+        service_id: 1,
+        subscription_id: 1,
+        subscribed_at: 1_700_000_000,
+        last_payment_at: None,
+        next_payment_due: 1_702_592_000,
+        total_payments_made: 0,
+        is_active: true,
+        unsubscribed_at: None,
+        certificate_asset_id: Pubkey::default(),
+        failed_payment_attempts: 0,
+        delinquent_since: None,
+        bump: 255,
+        locked_usdc: 0,
+        sponsor: Pubkey::default(),
+        sponsor_escrow_lamports: 0,
+        discount_bps: 0,
+        locked_sol: 0,
+    };
+    assert_eq!(subscription.discounted_fee(1_599).unwrap(), 1_599);
+
+    subscription.discount_bps = 2_500;
+    assert_eq!(subscription.discounted_fee(1_599).unwrap(), 1_200);
+
+    subscription.discount_bps = 10_000;
+    assert_eq!(subscription.discounted_fee(1_599).unwrap(), 0);
+}
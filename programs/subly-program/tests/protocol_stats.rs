@@ -0,0 +1,218 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::fresh_global_state;
+use subly_program::state::*;
+
+const SOL: u64 = 1_000_000_000;
+
+fn fresh_user() -> User {
+    User::deserialize(&mut &vec![0u8; User::INIT_SPACE][..]).unwrap()
+}
+
+/// A protocol with its users and open subscriptions, driven through the same
+/// User and GlobalState bookkeeping the instructions perform
+struct Protocol {
+    global_state: GlobalState,
+    users: Vec<User>,
+    subscriptions: u64,
+}
+
+impl Protocol {
+    fn new(users: usize) -> Self {
+        Self {
+            global_state: fresh_global_state(),
+            users: (0..users).map(|_| fresh_user()).collect(),
+            subscriptions: 0,
+        }
+    }
+
+    /// deposit_sol
+    fn deposit(&mut self, user: usize, lamports: u64) {
+        let account = &mut self.users[user];
+        if account.wallet == Pubkey::default() {
+            account.wallet = Pubkey::new_unique();
+            self.global_state.record_user_joined().unwrap();
+        }
+        account.deposited_sol += lamports;
+        self.global_state.record_deposit(lamports).unwrap();
+    }
+
+    /// stake_sol
+    fn stake(&mut self, user: usize, lamports: u64) {
+        self.users[user].move_to_staked(lamports).unwrap();
+        self.global_state.record_stake(lamports).unwrap();
+    }
+
+    /// withdraw_with_unstake: the principal leaves, the measured proceeds arrive
+    fn unstake(&mut self, user: usize, principal: u64, lamports_received: u64) {
+        self.users[user]
+            .record_unstake_proceeds(principal, lamports_received)
+            .unwrap();
+        self.global_state
+            .record_unstake(principal, lamports_received)
+            .unwrap();
+    }
+
+    /// subscribe_to_service
+    fn subscribe(&mut self, user: usize, lock: u64) {
+        self.users[user].locked_sol += lock;
+        self.global_state.record_subscription_opened().unwrap();
+        self.subscriptions += 1;
+    }
+
+    /// execute_subscription_payment, paid from the SOL vault
+    fn pay(&mut self, user: usize, payment: u64, protocol_fee: u64) {
+        self.users[user].record_sol_payment(payment).unwrap();
+        self.global_state
+            .record_payment(payment, payment, protocol_fee)
+            .unwrap();
+    }
+
+    /// execute_subscription_payment, paid from the user's USDC vault
+    fn pay_usdc(&mut self, user: usize, payment: u64, protocol_fee: u64) {
+        self.users[user].usdc_balance -= payment;
+        self.global_state.record_payment(0, 0, 0).unwrap();
+        self.global_state.record_usdc_payment(protocol_fee).unwrap();
+    }
+
+    /// unsubscribe_from_service
+    fn unsubscribe(&mut self, user: usize, lock: u64) {
+        self.users[user].release_locked(lock).unwrap();
+        self.global_state.record_subscription_closed();
+        self.subscriptions -= 1;
+    }
+
+    /// withdraw of everything available
+    fn withdraw_all(&mut self, user: usize) {
+        let amount = self.users[user].available_sol().unwrap();
+        self.users[user].deposited_sol -= amount;
+        self.global_state.record_withdrawal(amount);
+    }
+
+    fn assert_consistent(&self) {
+        let deposited: u64 = self.users.iter().map(|user| user.deposited_sol).sum();
+        let staked: u64 = self.users.iter().map(|user| user.staked_sol).sum();
+        let joined = self
+            .users
+            .iter()
+            .filter(|user| user.wallet != Pubkey::default())
+            .count() as u64;
+
+        assert_eq!(self.global_state.total_deposited_lamports, deposited);
+        assert_eq!(self.global_state.total_staked_lamports, staked);
+        assert_eq!(self.global_state.total_users, joined);
+        assert_eq!(
+            self.global_state.total_active_subscriptions,
+            self.subscriptions
+        );
+    }
+}
+
+#[test]
+fn counters_follow_a_full_subscription_lifecycle() {
+    let mut protocol = Protocol::new(2);
+    let lock = SOL / 2;
+    let payment = SOL / 20;
+    let fee = payment / 100;
+
+    protocol.deposit(0, 5 * SOL);
+    protocol.deposit(1, 3 * SOL);
+    protocol.deposit(0, SOL);
+    protocol.assert_consistent();
+
+    protocol.stake(0, 2 * SOL);
+    protocol.assert_consistent();
+
+    protocol.subscribe(0, lock);
+    protocol.subscribe(1, lock);
+    protocol.assert_consistent();
+
+    protocol.pay(0, payment, fee);
+    protocol.pay(1, payment, fee);
+    protocol.assert_consistent();
+    assert_eq!(protocol.global_state.total_volume_lamports, 2 * payment);
+    assert_eq!(protocol.global_state.total_protocol_fees_lamports, 2 * fee);
+
+    // Yield arrives with the unstake, so more comes back than was staked
+    protocol.unstake(0, 2 * SOL, 2 * SOL + SOL / 50);
+    protocol.assert_consistent();
+
+    protocol.unsubscribe(0, lock);
+    protocol.unsubscribe(1, lock);
+    protocol.assert_consistent();
+
+    protocol.withdraw_all(0);
+    protocol.withdraw_all(1);
+    protocol.assert_consistent();
+
+    let state = &protocol.global_state;
+    assert_eq!(state.total_deposited_lamports, 0);
+    assert_eq!(state.total_staked_lamports, 0);
+    assert_eq!(state.total_active_subscriptions, 0);
+    // Users are counted once and stay counted after leaving
+    assert_eq!(state.total_users, 2);
+}
+
+#[test]
+fn balances_from_before_the_counters_saturate_at_zero() {
+    // Migrated accounts start their counters at zero while users already hold funds
+    let mut state = fresh_global_state();
+    state.record_withdrawal(SOL);
+    state.record_unstake(SOL, 0).unwrap();
+    state.record_subscription_closed();
+    state.record_payment(SOL, SOL, 0).unwrap();
+
+    assert_eq!(state.total_deposited_lamports, 0);
+    assert_eq!(state.total_staked_lamports, 0);
+    assert_eq!(state.total_active_subscriptions, 0);
+    assert_eq!(state.total_volume_lamports, SOL);
+}
+
+#[test]
+fn withdraws_after_payments_draw_deposits_below_the_lock() {
+    let mut protocol = Protocol::new(1);
+    let lock = SOL;
+    let payment = SOL / 4;
+
+    protocol.deposit(0, SOL + SOL / 2);
+    protocol.subscribe(0, lock);
+    protocol.withdraw_all(0);
+    assert_eq!(protocol.users[0].deposited_sol, lock);
+
+    // Billing pays from deposits, leaving less than the lock
+    protocol.pay(0, payment, 0);
+    protocol.pay(0, payment, 0);
+    assert!(protocol.users[0].deposited_sol < protocol.users[0].locked_sol);
+    protocol.withdraw_all(0);
+    assert_eq!(protocol.users[0].deposited_sol, SOL / 2);
+    protocol.assert_consistent();
+
+    // Unsubscribing frees what is left
+    protocol.unsubscribe(0, lock);
+    protocol.withdraw_all(0);
+    assert_eq!(protocol.users[0].deposited_sol, 0);
+    protocol.assert_consistent();
+}
+
+#[test]
+fn usdc_fees_are_counted_in_usdc() {
+    let mut protocol = Protocol::new(1);
+    let payment = SOL / 20;
+    let fee = payment / 100;
+    let usdc_payment = 15_990_000;
+    let usdc_fee = usdc_payment / 100;
+
+    protocol.deposit(0, SOL);
+    protocol.users[0].usdc_balance = 2 * usdc_payment;
+    protocol.subscribe(0, SOL / 2);
+    protocol.pay(0, payment, fee);
+    protocol.pay_usdc(0, usdc_payment, usdc_fee);
+    protocol.pay_usdc(0, usdc_payment, usdc_fee);
+    protocol.assert_consistent();
+
+    // Lamport totals only move for the SOL payment; the USDC fees have their own
+    assert_eq!(protocol.global_state.total_volume_lamports, payment);
+    assert_eq!(protocol.global_state.total_protocol_fees_lamports, fee);
+    assert_eq!(protocol.global_state.total_protocol_fees_usdc, 2 * usdc_fee);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::{error_code, fresh_global_state};
+use subly_program::{error::ErrorCode, state::*};
+
+fn empty_ledger() -> ProviderEarnings {
+    ProviderEarnings::deserialize(&mut &vec![0u8; ProviderEarnings::INIT_SPACE][..]).unwrap()
+}
+
+#[test]
+fn credits_accumulate_until_settled() {
+    let mut earnings = empty_ledger();
+    earnings.credit(4_990_000, 100).unwrap();
+    earnings.credit(4_990_000, 200).unwrap();
+    assert_eq!(earnings.pending_usdc, 9_980_000);
+    assert_eq!(earnings.total_credited_usdc, 9_980_000);
+    assert_eq!(earnings.last_credited_at, 200);
+
+    assert_eq!(earnings.take_pending(300).unwrap(), 9_980_000);
+    assert_eq!(earnings.pending_usdc, 0);
+    assert_eq!(earnings.total_settled_usdc, 9_980_000);
+    assert_eq!(earnings.last_settled_at, 300);
+
+    earnings.credit(1, 400).unwrap();
+    assert_eq!(earnings.pending_usdc, 1);
+    assert_eq!(earnings.total_credited_usdc, 9_980_001);
+}
+
+#[test]
+fn nothing_pending_cannot_be_settled() {
+    let mut earnings = empty_ledger();
+    assert_eq!(
+        error_code(earnings.take_pending(100)),
+        u32::from(ErrorCode::NoPendingEarnings)
+    );
+
+    earnings.credit(10, 100).unwrap();
+    earnings.take_pending(200).unwrap();
+    assert_eq!(
+        error_code(earnings.take_pending(300)),
+        u32::from(ErrorCode::NoPendingEarnings)
+    );
+    assert_eq!(earnings.last_settled_at, 200);
+}
+
+#[test]
+fn credit_overflow_is_rejected() {
+    let mut earnings = empty_ledger();
+    earnings.credit(u64::MAX, 100).unwrap();
+    assert_eq!(
+        error_code(earnings.credit(1, 200)),
+        u32::from(ErrorCode::ArithmeticOverflow)
+    );
+    assert_eq!(earnings.pending_usdc, u64::MAX);
+}
+
+#[test]
+fn treasury_outflows_leave_pending_payouts_untouched() {
+    let mut global_state = fresh_global_state();
+    assert!(global_state.require_payouts_covered(0, 0).is_ok());
+
+    global_state.total_pending_payouts_usdc = 1_000;
+    assert!(global_state.require_payouts_covered(1_000, 0).is_ok());
+    assert!(global_state.require_payouts_covered(1_500, 500).is_ok());
+
+    let underfunded = u32::from(ErrorCode::PayoutsUnderfunded);
+    assert_eq!(
+        error_code(global_state.require_payouts_covered(999, 0)),
+        underfunded
+    );
+    assert_eq!(
+        error_code(global_state.require_payouts_covered(1_500, 501)),
+        underfunded
+    );
+    assert_eq!(
+        error_code(global_state.require_payouts_covered(100, 200)),
+        underfunded
+    );
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::outcome;
+use subly_program::{error::ErrorCode, state::*};
+
+fn provider(is_verified: bool, is_banned: bool) -> Provider {
+    Provider {
+        version: Provider::CURRENT_VERSION,
+        wallet: Pubkey::new_unique(),
+        name: "Provider".to_string(),
+        description: "Services".to_string(),
+        total_subscribers: 0,
+        is_verified,
+        created_at: 1_700_000_000,
+        bump: 254,
+        last_service_registered_at: 0,
+        services_registered_in_window: 0,
+        is_banned,
+        verified_at: 0,
+    }
+}
+
+#[test]
+fn open_mode_accepts_unverified_providers() {
+    assert!(provider(false, false)
+        .require_in_good_standing(false)
+        .is_ok());
+    assert!(provider(true, false)
+        .require_in_good_standing(false)
+        .is_ok());
+}
+
+#[test]
+fn curated_mode_requires_verification() {
+    assert_eq!(
+        outcome(provider(false, false).require_in_good_standing(true)),
+        Some(u32::from(ErrorCode::ProviderNotVerified))
+    );
+    assert!(provider(true, false).require_in_good_standing(true).is_ok());
+}
+
+#[test]
+fn banned_providers_are_rejected_in_every_mode() {
+    for (is_verified, require_verified) in [(false, false), (true, false), (true, true)] {
+        assert_eq!(
+            outcome(provider(is_verified, true).require_in_good_standing(require_verified)),
+            Some(u32::from(ErrorCode::ProviderBanned))
+        );
+    }
+}
+
+#[test]
+fn migrated_providers_are_in_good_standing() {
+    // Accounts written before the ban flag and verified_at existed are zero-filled by the resize
+    let mut data = vec![0u8; Provider::INIT_SPACE];
+    data[0] = Provider::CURRENT_VERSION;
+    let provider = Provider::deserialize(&mut &data[..]).unwrap();
+    assert!(!provider.is_banned);
+    assert_eq!(provider.verified_at, 0);
+    assert!(provider.require_in_good_standing(false).is_ok());
+}
+
+#[test]
+fn verification_records_when_it_was_granted() {
+    let mut provider = provider(false, false);
+    provider.set_verified(true, 1_700_000_500);
+    assert!(provider.is_verified);
+    assert_eq!(provider.verified_at, 1_700_000_500);
+    assert!(provider.require_in_good_standing(true).is_ok());
+
+    // Re-verifying refreshes the stamp
+    provider.set_verified(true, 1_700_001_000);
+    assert_eq!(provider.verified_at, 1_700_001_000);
+
+    provider.set_verified(false, 1_700_002_000);
+    assert!(!provider.is_verified);
+    assert_eq!(provider.verified_at, 0);
+    assert_eq!(
+        outcome(provider.require_in_good_standing(true)),
+        Some(u32::from(ErrorCode::ProviderNotVerified))
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::outcome;
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+/// Start of an arbitrary registration window
+const WINDOW_START: i64 = 19_700 * SERVICE_REGISTRATION_WINDOW_SECONDS;
+
+fn provider(is_verified: bool) -> Provider {
+    Provider {
+        version: Provider::CURRENT_VERSION,
+        wallet: Pubkey::new_unique(),
+        name: "Provider".to_string(),
+        description: "Services".to_string(),
+        total_subscribers: 0,
+        is_verified,
+        created_at: 1_700_000_000,
+        bump: 254,
+        last_service_registered_at: 0,
+        services_registered_in_window: 0,
+        is_banned: false,
+        verified_at: 0,
+    }
+}
+
+fn register(provider: &mut Provider, now: i64, count: u32) -> Result<()> {
+    for _ in 0..count {
+        provider.record_service_registration(now, DEFAULT_MAX_SERVICES_PER_WINDOW)?;
+    }
+    Ok(())
+}
+
+fn is_rate_limited(result: Result<()>) -> bool {
+    outcome(result) == Some(u32::from(ErrorCode::RateLimited))
+}
+
+#[test]
+fn limit_applies_within_a_window() {
+    let mut provider = provider(false);
+    register(
+        &mut provider,
+        WINDOW_START + 10,
+        DEFAULT_MAX_SERVICES_PER_WINDOW,
+    )
+    .unwrap();
+    assert_eq!(
+        provider.services_registered_in_window,
+        DEFAULT_MAX_SERVICES_PER_WINDOW
+    );
+
+    // Last second of the same window
+    let last_second = WINDOW_START + SERVICE_REGISTRATION_WINDOW_SECONDS - 1;
+    assert!(is_rate_limited(register(&mut provider, last_second, 1)));
+    // A failed attempt leaves the counters untouched
+    assert_eq!(provider.last_service_registered_at, WINDOW_START + 10);
+}
+
+#[test]
+fn count_rolls_over_at_the_window_boundary() {
+    let mut provider = provider(false);
+    let last_second = WINDOW_START + SERVICE_REGISTRATION_WINDOW_SECONDS - 1;
+    register(&mut provider, last_second, DEFAULT_MAX_SERVICES_PER_WINDOW).unwrap();
+
+    // The next second starts a new window, even though it is not 24h later
+    let next_window = WINDOW_START + SERVICE_REGISTRATION_WINDOW_SECONDS;
+    register(&mut provider, next_window, 1).unwrap();
+    assert_eq!(provider.services_registered_in_window, 1);
+    assert_eq!(provider.last_service_registered_at, next_window);
+
+    register(
+        &mut provider,
+        next_window,
+        DEFAULT_MAX_SERVICES_PER_WINDOW - 1,
+    )
+    .unwrap();
+    assert!(is_rate_limited(register(&mut provider, next_window, 1)));
+}
+
+#[test]
+fn count_resets_after_skipped_windows() {
+    let mut provider = provider(false);
+    register(&mut provider, WINDOW_START, DEFAULT_MAX_SERVICES_PER_WINDOW).unwrap();
+
+    register(
+        &mut provider,
+        WINDOW_START + 5 * SERVICE_REGISTRATION_WINDOW_SECONDS,
+        1,
+    )
+    .unwrap();
+    assert_eq!(provider.services_registered_in_window, 1);
+}
+
+#[test]
+fn verified_providers_and_zero_limit_are_unlimited() {
+    let mut verified = provider(true);
+    register(
+        &mut verified,
+        WINDOW_START,
+        DEFAULT_MAX_SERVICES_PER_WINDOW * 3,
+    )
+    .unwrap();
+
+    let mut unverified = provider(false);
+    for _ in 0..DEFAULT_MAX_SERVICES_PER_WINDOW * 3 {
+        unverified
+            .record_service_registration(WINDOW_START, 0)
+            .unwrap();
+    }
+}
+
+#[test]
+fn lowered_limit_applies_to_the_current_window() {
+    let mut provider = provider(false);
+    register(&mut provider, WINDOW_START, 5).unwrap();
+    assert!(is_rate_limited(
+        provider.record_service_registration(WINDOW_START, 5)
+    ));
+    provider
+        .record_service_registration(WINDOW_START, 6)
+        .unwrap();
+}
@@ -0,0 +1,135 @@
+//! get_registry_page simulated against the compiled program: a client that starts
+//! at page 0 and follows next_slot and next_page sees every live service once, in
+//! id order, across more than one registry page.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, Space};
+use common::program::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use subly_program::{constants::*, instructions::RegistryPage, state::*};
+
+/// Fills page 0 and spills onto page 1
+const TOTAL_SERVICES: u64 = SERVICE_REGISTRY_PAGE_CAPACITY + 6;
+
+/// Deactivated, so the walk has to leave it out
+const TOMBSTONED: u64 = 30;
+
+fn registry_page_address(page: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SERVICE_REGISTRY_SEED.as_bytes(), &page.to_le_bytes()],
+        &subly_program::ID,
+    )
+}
+
+fn get_registry_page(fixture: &Fixture, page: u32, start_slot: u8) -> Instruction {
+    instruction(
+        subly_program::instruction::GetRegistryPage { page, start_slot },
+        subly_program::accounts::GetRegistryPage {
+            global_state: fixture.global_state,
+            service_registry_page: registry_page_address(page).0,
+        },
+    )
+}
+
+/// Registry pages listing services 0 to TOTAL_SERVICES - 1, with TOMBSTONED
+/// deactivated. Returns the live services in id order
+async fn with_registry(fixture: &Fixture, context: &mut ProgramTestContext) -> Vec<(u64, Pubkey)> {
+    let mut live = Vec::new();
+    for page in 0..=ServiceRegistryPage::page_of(TOTAL_SERVICES - 1) {
+        let (address, bump) = registry_page_address(page);
+        let mut registry_page: ServiceRegistryPage = zeroed();
+        let first_service_id = u64::from(page) * SERVICE_REGISTRY_PAGE_CAPACITY;
+        let end = TOTAL_SERVICES.min(first_service_id + SERVICE_REGISTRY_PAGE_CAPACITY);
+        for service_id in first_service_id..end {
+            let service = Pubkey::new_unique();
+            let is_active = service_id != TOMBSTONED;
+            registry_page
+                .record(bump, service_id, service, is_active)
+                .unwrap();
+            if is_active {
+                live.push((service_id, service));
+            }
+        }
+        fixture.set_account(
+            context,
+            address,
+            program_account(&registry_page, 8 + ServiceRegistryPage::INIT_SPACE),
+        );
+    }
+    fixture
+        .update(
+            context,
+            fixture.global_state,
+            |global_state: &mut GlobalState| {
+                global_state.total_services = TOTAL_SERVICES;
+            },
+        )
+        .await;
+    live
+}
+
+#[tokio::test]
+async fn walking_the_registry_lists_every_live_service_once() {
+    let fixture = Fixture::new();
+    let mut context = fixture.funded().await;
+    let expected = with_registry(&fixture, &mut context).await;
+
+    let mut listed = Vec::new();
+    let mut calls = 0;
+    let (mut page, mut start_slot) = (0, 0);
+    loop {
+        let view: RegistryPage = fixture
+            .view(&mut context, get_registry_page(&fixture, page, start_slot))
+            .await;
+        calls += 1;
+        assert_eq!(view.page, page);
+        assert!(view.services.len() <= MAX_REGISTRY_SERVICES_PER_VIEW);
+        listed.extend(
+            view.services
+                .iter()
+                .map(|registered| (registered.service_id, registered.service)),
+        );
+        match (view.next_slot, view.next_page) {
+            (Some(next_slot), _) => start_slot = next_slot,
+            (None, Some(next_page)) => (page, start_slot) = (next_page, 0),
+            (None, None) => break,
+        }
+    }
+
+    assert_eq!(listed, expected);
+    // 63 live services on page 0 take three calls, page 1 one more
+    assert_eq!(calls, 4);
+}
+
+#[tokio::test]
+async fn a_page_nothing_was_recorded_on_reads_as_empty() {
+    let fixture = Fixture::new();
+    let mut context = fixture.funded().await;
+    with_registry(&fixture, &mut context).await;
+    // Services on page 2 that were registered before the registry and never backfilled
+    fixture
+        .update(
+            &mut context,
+            fixture.global_state,
+            |global_state: &mut GlobalState| {
+                global_state.total_services = 3 * SERVICE_REGISTRY_PAGE_CAPACITY;
+            },
+        )
+        .await;
+
+    let view: RegistryPage = fixture
+        .view(&mut context, get_registry_page(&fixture, 1, 0))
+        .await;
+    assert_eq!(view.next_page, Some(2));
+
+    let view: RegistryPage = fixture
+        .view(&mut context, get_registry_page(&fixture, 2, 0))
+        .await;
+    assert!(view.services.is_empty());
+    assert_eq!((view.next_slot, view.next_page), (None, None));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::fresh_global_state;
+use subly_program::{state::*, utils::*};
+
+fn global_state() -> GlobalState {
+    let mut global_state = fresh_global_state();
+    global_state.authority = Pubkey::new_unique();
+    global_state
+}
+
+#[test]
+fn payer_closing_their_own_account_gets_the_rent() {
+    let payer = Pubkey::new_unique();
+    assert_eq!(rent_destination(&payer, &payer), RentDestination::Payer);
+}
+
+#[test]
+fn cleanup_by_anyone_else_goes_to_the_rent_collector() {
+    let payer = Pubkey::new_unique();
+    let global_state = global_state();
+    assert_eq!(
+        rent_destination(&global_state.authority, &payer),
+        RentDestination::RentCollector
+    );
+    assert_eq!(
+        rent_destination(&Pubkey::new_unique(), &payer),
+        RentDestination::RentCollector
+    );
+}
+
+#[test]
+fn rent_collector_defaults_to_the_authority() {
+    let mut global_state = global_state();
+    assert_eq!(global_state.rent_collector(), global_state.authority);
+
+    let rent_collector = Pubkey::new_unique();
+    global_state.rent_collector = rent_collector;
+    assert_eq!(global_state.rent_collector(), rent_collector);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::outcome;
+use subly_program::{constants::*, error::ErrorCode, state::*, utils::require_full_layout};
+
+/// Bytes the description field gave up before its limit was raised
+const RAISED_BY: usize = 100;
+
+fn provider(description: String) -> Provider {
+    Provider {
+        version: Provider::CURRENT_VERSION,
+        wallet: Pubkey::new_unique(),
+        name: "Provider".to_string(),
+        description,
+        total_subscribers: 7,
+        is_verified: false,
+        created_at: 1_700_000_000,
+        bump: 254,
+        last_service_registered_at: 0,
+        services_registered_in_window: 0,
+        is_banned: false,
+        verified_at: 0,
+    }
+}
+
+/// Serialize into a buffer of the account's size, failing like the runtime
+/// does when the account is too small
+fn store(data: &mut [u8], account: &Provider) -> bool {
+    let mut writer = &mut data[..];
+    account.try_serialize(&mut writer).is_ok()
+}
+
+fn layout_error(data: &mut [u8]) -> Option<u32> {
+    let key = Pubkey::new_unique();
+    let owner = subly_program::ID;
+    let mut lamports = 0;
+    let info = AccountInfo::new(&key, false, true, &mut lamports, data, &owner, false, 0);
+    outcome(require_full_layout(&info, 8 + Provider::INIT_SPACE))
+}
+
+#[test]
+fn old_size_provider_is_resized_and_stores_a_maximum_description() {
+    let old_space = 8 + Provider::INIT_SPACE - RAISED_BY;
+    let mut data = vec![0u8; old_space];
+    assert!(store(
+        &mut data,
+        &provider("d".repeat(MAX_DESCRIPTION_LENGTH - RAISED_BY))
+    ));
+
+    // The old account cannot hold a description at the new limit
+    let longest = provider("d".repeat(MAX_DESCRIPTION_LENGTH));
+    assert!(!store(&mut data.clone(), &longest));
+    assert_eq!(
+        layout_error(&mut data),
+        Some(u32::from(ErrorCode::AccountResizeRequired))
+    );
+
+    // realloc::zero grows the account with a zeroed tail
+    data.resize(8 + Provider::INIT_SPACE, 0);
+    assert_eq!(layout_error(&mut data), None);
+
+    let resized = Provider::try_deserialize(&mut &data[..]).unwrap();
+    assert_eq!(resized.total_subscribers, 7);
+    assert_eq!(resized.bump, 254);
+
+    assert!(store(&mut data, &longest));
+    let stored = Provider::try_deserialize(&mut &data[..]).unwrap();
+    assert_eq!(stored.description.len(), MAX_DESCRIPTION_LENGTH);
+}
+
+#[test]
+fn service_layout_tracks_text_limits() {
+    let service = SubscriptionService {
+        version: SubscriptionService::CURRENT_VERSION,
+        provider: Pubkey::new_unique(),
+        service_id: 0,
+        name: "n".repeat(MAX_NAME_LENGTH),
+        description: "d".repeat(MAX_DESCRIPTION_LENGTH),
+        fee_usd: 1599,
+        billing_frequency_days: 30,
+        image_url: "u".repeat(MAX_URL_LENGTH),
+        current_subscribers: 0,
+        is_active: true,
+        created_at: 1_700_000_000,
+        certificate_mode: CERTIFICATE_MODE_TOKEN,
+        royalty_bps: 0,
+        certificate_metadata_uri: "c".repeat(MAX_URL_LENGTH),
+        bump: 255,
+        frozen: false,
+        deactivated_by_admin: false,
+        deactivation_reason: 0,
+    };
+    let mut data = Vec::new();
+    service.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), 8 + SubscriptionService::INIT_SPACE);
+}
@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use subly_program::{constants::*, state::*, utils::*};
+
+fn service(name: &str, description: &str, image_url: &str, uri: &str) -> SubscriptionService {
+    SubscriptionService {
+        version: SubscriptionService::CURRENT_VERSION,
+        provider: Pubkey::new_unique(),
+        service_id: 42,
+        name: name.to_string(),
+        description: description.to_string(),
+        fee_usd: 1599,
+        billing_frequency_days: 30,
+        image_url: image_url.to_string(),
+        current_subscribers: 9,
+        is_active: true,
+        created_at: 1_700_000_000,
+        certificate_mode: CERTIFICATE_MODE_COMPRESSED,
+        royalty_bps: 250,
+        certificate_metadata_uri: uri.to_string(),
+        bump: 253,
+        frozen: false,
+        deactivated_by_admin: false,
+        deactivation_reason: 0,
+    }
+}
+
+fn serialize(service: &SubscriptionService) -> Vec<u8> {
+    let mut data = Vec::new();
+    service.try_serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn fixed_offsets_match_the_real_layout() {
+    let service = service("Netflix", "Streaming", "https://a.example/i.png", "");
+    let data = serialize(&service);
+
+    assert_eq!(data[SERVICE_VERSION_OFFSET], service.version);
+    assert_eq!(
+        &data[SERVICE_PROVIDER_OFFSET..SERVICE_PROVIDER_OFFSET + 32],
+        service.provider.as_ref()
+    );
+    assert_eq!(
+        data[SERVICE_ID_OFFSET..SERVICE_ID_OFFSET + 8],
+        service.service_id.to_le_bytes()
+    );
+    assert_eq!(
+        data[SERVICE_NAME_OFFSET..SERVICE_NAME_OFFSET + 4],
+        (service.name.len() as u32).to_le_bytes()
+    );
+}
+
+#[test]
+fn summary_matches_full_deserialization() {
+    let longest_name = "n".repeat(MAX_NAME_LENGTH);
+    let longest_description = "d".repeat(MAX_DESCRIPTION_LENGTH);
+    let longest_url = "u".repeat(MAX_URL_LENGTH);
+    let texts = [
+        ("", "", "", ""),
+        ("Netflix", "Streaming", "https://a.example/i.png", ""),
+        (
+            longest_name.as_str(),
+            longest_description.as_str(),
+            longest_url.as_str(),
+            longest_url.as_str(),
+        ),
+    ];
+    for (name, description, image_url, uri) in texts {
+        let mut data = serialize(&service(name, description, image_url, uri));
+        // Accounts are allocated at the maximum size, so decoding must ignore the tail
+        data.resize(8 + SubscriptionService::INIT_SPACE, 0);
+
+        let full = SubscriptionService::try_deserialize(&mut &data[..]).unwrap();
+        let summary = parse_subscription_service_summary(&data).unwrap();
+        assert_eq!(
+            summary,
+            SubscriptionServiceSummary {
+                provider: full.provider,
+                service_id: full.service_id,
+                fee_usd: full.fee_usd,
+                billing_frequency_days: full.billing_frequency_days,
+                is_active: full.is_active,
+                bump: full.bump,
+                frozen: full.frozen,
+            }
+        );
+    }
+}
+
+#[test]
+fn summary_reports_frozen_services() {
+    let mut frozen = service("Netflix", "Streaming", "", "");
+    frozen.frozen = true;
+    let mut data = serialize(&frozen);
+    data.resize(8 + SubscriptionService::INIT_SPACE, 0);
+    assert!(parse_subscription_service_summary(&data).unwrap().frozen);
+}
+
+#[test]
+fn summary_rejects_other_accounts_and_layouts() {
+    let data = serialize(&service("Netflix", "Streaming", "", ""));
+
+    let mut wrong_discriminator = data.clone();
+    wrong_discriminator[..8].copy_from_slice(User::DISCRIMINATOR);
+    assert_eq!(parse_subscription_service_summary(&wrong_discriminator), None);
+
+    let mut old_version = data.clone();
+    old_version[SERVICE_VERSION_OFFSET] = 0;
+    assert_eq!(parse_subscription_service_summary(&old_version), None);
+
+    let truncated = &data[..data.len() - 1];
+    assert_eq!(parse_subscription_service_summary(truncated), None);
+
+    let mut oversized_name = data;
+    oversized_name[SERVICE_NAME_OFFSET..SERVICE_NAME_OFFSET + 4]
+        .copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(parse_subscription_service_summary(&oversized_name), None);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use anchor_lang::prelude::*;
+use common::error_code;
+use subly_program::{constants::*, error::ErrorCode, state::*};
+
+fn empty_page() -> ServiceRegistryPage {
+    ServiceRegistryPage::deserialize(&mut &vec![0u8; ServiceRegistryPage::INIT_SPACE][..]).unwrap()
+}
+
+#[test]
+fn page_capacity_matches_the_layout() {
+    assert_eq!(
+        empty_page().services.len() as u64,
+        SERVICE_REGISTRY_PAGE_CAPACITY
+    );
+    assert_eq!(ServiceRegistryPage::page_of(0), 0);
+    assert_eq!(
+        ServiceRegistryPage::page_of(SERVICE_REGISTRY_PAGE_CAPACITY - 1),
+        0
+    );
+    assert_eq!(
+        ServiceRegistryPage::page_of(SERVICE_REGISTRY_PAGE_CAPACITY),
+        1
+    );
+    assert_eq!(ServiceRegistryPage::page_of(u64::MAX), u32::MAX);
+}
+
+#[test]
+fn registered_services_are_listed_in_id_order() {
+    let mut page = empty_page();
+    let first = SERVICE_REGISTRY_PAGE_CAPACITY;
+    let services: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    // Backfills may arrive in any order
+    for offset in [2, 0, 1] {
+        page.record(254, first + offset, services[offset as usize], true)
+            .unwrap();
+    }
+
+    assert_eq!(page.page, 1);
+    assert_eq!(page.bump, 254);
+    assert_eq!(page.entries, 3);
+    assert_eq!(
+        page.live_services().collect::<Vec<_>>(),
+        vec![
+            (first, services[0]),
+            (first + 1, services[1]),
+            (first + 2, services[2]),
+        ]
+    );
+    assert!(page.is_live(first + 1));
+    assert!(!page.is_live(first + 3));
+    // Same slot on another page
+    assert!(!page.is_live(1));
+}
+
+#[test]
+fn deactivation_tombstones_and_reactivation_revives() {
+    let mut page = empty_page();
+    let service = Pubkey::new_unique();
+    page.record(255, 5, service, true).unwrap();
+
+    page.record(255, 5, service, false).unwrap();
+    assert!(!page.is_live(5));
+    assert_eq!(page.live_services().count(), 0);
+    // The entry stays, so the slot is not counted twice
+    assert_eq!(page.entries, 1);
+
+    page.record(255, 5, service, true).unwrap();
+    assert!(page.is_live(5));
+    assert_eq!(page.entries, 1);
+}
+
+#[test]
+fn a_slot_cannot_be_taken_by_another_service() {
+    let mut page = empty_page();
+    page.record(255, 7, Pubkey::new_unique(), true).unwrap();
+    assert_eq!(
+        error_code(page.record(255, 7, Pubkey::new_unique(), true)),
+        u32::from(ErrorCode::ServiceRegistrySlotTaken)
+    );
+    assert_eq!(page.entries, 1);
+}
+
+#[test]
+fn a_full_page_is_listed_in_chunks_that_resume_at_the_next_live_slot() {
+    let mut page = empty_page();
+    for service_id in 0..SERVICE_REGISTRY_PAGE_CAPACITY {
+        // Slot 25 is where the second chunk would start, so it is skipped over
+        let is_active = service_id != 25;
+        page.record(255, service_id, Pubkey::new_unique(), is_active)
+            .unwrap();
+    }
+
+    let (first, next_slot) = page.live_services_from(0, MAX_REGISTRY_SERVICES_PER_VIEW);
+    assert_eq!(first.len(), MAX_REGISTRY_SERVICES_PER_VIEW);
+    assert_eq!(next_slot, Some(26));
+
+    let (second, next_slot) = page.live_services_from(26, MAX_REGISTRY_SERVICES_PER_VIEW);
+    assert_eq!(second.len(), MAX_REGISTRY_SERVICES_PER_VIEW);
+    assert_eq!(next_slot, Some(51));
+
+    let (third, next_slot) = page.live_services_from(51, MAX_REGISTRY_SERVICES_PER_VIEW);
+    assert_eq!(third.len(), 13);
+    assert_eq!(next_slot, None);
+
+    let walked: Vec<_> = first.into_iter().chain(second).chain(third).collect();
+    assert_eq!(walked, page.live_services().collect::<Vec<_>>());
+    // Past the last slot there is nothing left
+    assert_eq!(page.live_services_from(u8::MAX, 1), (Vec::new(), None));
+}
@@ -0,0 +1,93 @@
+//! expire_subscription sent to the compiled program: a delinquent subscription
+//! expires for anyone once its grace period is over, taking it off the service
+//! and releasing its lock, and not a second earlier.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::solana_program::instruction::InstructionError;
+use common::program::*;
+use solana_sdk::{signature::Signer, transaction::TransactionError};
+use subly_program::{error::ErrorCode, state::*};
+
+fn assert_error(name: &str, outcome: Result<u64, Failure>, expected: ErrorCode) {
+    match outcome {
+        Err((TransactionError::InstructionError(0, InstructionError::Custom(code)), _))
+            if code == u32::from(expected) => {}
+        Err((error, logs)) => {
+            panic!("{name} failed with {error} instead of {expected:?}: {logs:#?}")
+        }
+        Ok(_) => panic!("{name} succeeded"),
+    }
+}
+
+#[tokio::test]
+async fn a_lapsed_subscription_expires_and_releases_its_lock() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert!(user.locked_sol > 0);
+    let service: SubscriptionService = fixture
+        .fetch(&mut context, fixture.subscription_service)
+        .await;
+
+    fixture.lapse(&mut context).await;
+    let caller = context.payer.pubkey();
+    fixture
+        .run(&mut context, fixture.expire_subscription(caller), &[])
+        .await;
+
+    let expired: UserSubscription = fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert!(!expired.is_active);
+    assert_eq!(expired.locked_sol, 0);
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert_eq!(user.locked_sol, 0);
+    let after: SubscriptionService = fixture
+        .fetch(&mut context, fixture.subscription_service)
+        .await;
+    assert_eq!(after.current_subscribers, service.current_subscribers - 1);
+
+    // Expired is final, a second expiry has nothing left to release
+    assert_error(
+        "second expiry",
+        fixture
+            .try_run(&mut context, fixture.expire_subscription(caller), &[])
+            .await,
+        ErrorCode::SubscriptionNotActive,
+    );
+}
+
+#[tokio::test]
+async fn only_delinquent_subscriptions_past_their_grace_period_expire() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    let caller = context.payer.pubkey();
+
+    fixture.advance_to_next_payment(&mut context).await;
+    assert_error(
+        "expiry of a subscription in good standing",
+        fixture
+            .try_run(&mut context, fixture.expire_subscription(caller), &[])
+            .await,
+        ErrorCode::SubscriptionNotDelinquent,
+    );
+
+    fixture.lapse(&mut context).await;
+    let mut clock = fixture.clock(&mut context).await;
+    clock.unix_timestamp -= 1;
+    context.set_sysvar(&clock);
+    fixture.publish_price(&mut context, clock.unix_timestamp);
+    assert_error(
+        "expiry within the grace period",
+        fixture
+            .try_run(&mut context, fixture.expire_subscription(caller), &[])
+            .await,
+        ErrorCode::GracePeriodNotOver,
+    );
+
+    let subscription: UserSubscription =
+        fixture.fetch(&mut context, fixture.user_subscription).await;
+    assert!(subscription.is_active);
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::outcome;
+use subly_program::{constants::*, error::ErrorCode, utils::*};
+
+#[test]
+fn names_are_validated() {
+    let longest = "n".repeat(MAX_NAME_LENGTH);
+    let too_long = "n".repeat(MAX_NAME_LENGTH + 1);
+    let cases: &[(&str, Option<ErrorCode>)] = &[
+        ("Netflix", None),
+        ("Netflix Premium 4K", None),
+        ("Caf\u{e9} M\u{fc}nchen", None),
+        (&longest, None),
+        (&too_long, Some(ErrorCode::NameTooLong)),
+        ("Netflix\n\n\n", Some(ErrorCode::InvalidCharacters)),
+        ("Net\tflix", Some(ErrorCode::InvalidCharacters)),
+        ("Net\u{7}flix", Some(ErrorCode::InvalidCharacters)),
+        ("Net\u{85}flix", Some(ErrorCode::InvalidCharacters)),
+        ("Net\u{200B}flix", Some(ErrorCode::InvalidCharacters)),
+        ("Netflix\u{202E}", Some(ErrorCode::InvalidCharacters)),
+        ("\u{FEFF}Netflix", Some(ErrorCode::InvalidCharacters)),
+        (" Netflix", Some(ErrorCode::UntrimmedText)),
+        ("Netflix ", Some(ErrorCode::UntrimmedText)),
+        ("Netflix\u{3000}", Some(ErrorCode::UntrimmedText)),
+    ];
+    for (name, expected) in cases {
+        assert_eq!(
+            outcome(validate_name(name)),
+            expected.map(u32::from),
+            "name {name:?}"
+        );
+    }
+}
+
+#[test]
+fn descriptions_are_validated() {
+    let too_long = "d".repeat(MAX_DESCRIPTION_LENGTH + 1);
+    let cases: &[(&str, Option<ErrorCode>)] = &[
+        ("", None),
+        ("Streaming service", None),
+        ("Line one\nLine two", None),
+        (&too_long, Some(ErrorCode::DescriptionTooLong)),
+        ("Line one\r\nLine two", Some(ErrorCode::InvalidCharacters)),
+        ("Hidden\u{200D}text", Some(ErrorCode::InvalidCharacters)),
+        ("Streaming\n", Some(ErrorCode::UntrimmedText)),
+    ];
+    for (description, expected) in cases {
+        assert_eq!(
+            outcome(validate_description(description)),
+            expected.map(u32::from),
+            "description {description:?}"
+        );
+    }
+}
+
+#[test]
+fn urls_are_validated() {
+    let longest = format!("https://{}", "u".repeat(MAX_URL_LENGTH - "https://".len()));
+    let too_long = format!("{longest}u");
+    let cases: &[(&str, Option<ErrorCode>, Option<ErrorCode>)] = &[
+        // (url, as image_url, as certificate metadata URI)
+        ("https://example.com/logo.png", None, None),
+        (
+            "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            None,
+            None,
+        ),
+        (&longest, None, None),
+        (
+            &too_long,
+            Some(ErrorCode::UrlTooLong),
+            Some(ErrorCode::UrlTooLong),
+        ),
+        ("", Some(ErrorCode::InvalidUrlScheme), None),
+        (
+            "https://",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+        (
+            "http://example.com/logo.png",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+        (
+            "javascript:alert(1)",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+        (
+            "HTTPS://example.com",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+        (
+            " https://example.com",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+        (
+            "https://example.com/a b.png",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+        (
+            "https://exam\u{200B}ple.com",
+            Some(ErrorCode::InvalidUrlScheme),
+            Some(ErrorCode::InvalidCertificateUri),
+        ),
+    ];
+    for (url, as_image, as_certificate) in cases {
+        assert_eq!(
+            outcome(validate_image_url(url)),
+            as_image.map(u32::from),
+            "image url {url:?}"
+        );
+        assert_eq!(
+            outcome(validate_certificate_metadata_uri(url)),
+            as_certificate.map(u32::from),
+            "certificate uri {url:?}"
+        );
+    }
+}
+
+#[test]
+fn lookalike_names_normalize_to_the_same_form() {
+    let cases = [
+        ("Netflix", "netflix"),
+        ("NETFLIX", "netflix"),
+        ("Netfl\u{131}x", "netflix"),
+        ("N\u{435}tfl\u{456}x", "netflix"),
+        ("Netflix   Premium", "netflix premium"),
+        ("Netflix\u{a0}Premium", "netflix premium"),
+        ("Disney+", "disney+"),
+    ];
+    for (name, normalized) in cases {
+        assert_eq!(normalize_name(name), normalized, "name {name:?}");
+    }
+    assert_ne!(normalize_name("Netflix"), normalize_name("Netflex"));
+}
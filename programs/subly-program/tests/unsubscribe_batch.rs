@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use subly_program::{constants::*, state::*};
+
+/// Account locks a transaction may hold
+const MAX_TX_ACCOUNT_LOCKS: usize = 64;
+/// Largest compute budget a transaction can request
+const MAX_TX_COMPUTE_UNITS: u64 = 1_400_000;
+/// Return data is capped at 1024 bytes
+const MAX_RETURN_DATA: usize = 1024;
+
+fn subscription(user: Pubkey) -> UserSubscription {
+    UserSubscription {
+        version: UserSubscription::CURRENT_VERSION,
+        user,
+        provider: Pubkey::new_unique(),
+        service_id: 1,
+        subscription_id: 1,
+        subscribed_at: 1_700_000_000,
+        last_payment_at: None,
+        next_payment_due: 1_702_592_000,
+        total_payments_made: 0,
+        is_active: true,
+        unsubscribed_at: None,
+        certificate_asset_id: Pubkey::default(),
+        failed_payment_attempts: 0,
+        delinquent_since: None,
+        bump: 255,
+        locked_usdc: 0,
+        sponsor: Pubkey::default(),
+        sponsor_escrow_lamports: 0,
+        discount_bps: 0,
+        locked_sol: 0,
+    }
+}
+
+/// Accounts unsubscribe_batch names itself: its Accounts struct plus the
+/// event authority and program added by #[event_cpi]
+fn fixed_accounts() -> usize {
+    subly_program::accounts::UnsubscribeBatch {
+        user: Pubkey::new_unique(),
+        user_account: Pubkey::new_unique(),
+        global_state: Pubkey::new_unique(),
+        sol_usd_price_feed: Pubkey::new_unique(),
+        certificate_authority: Pubkey::new_unique(),
+        rent_sponsor: Pubkey::new_unique(),
+        token_program: Pubkey::new_unique(),
+        system_program: Pubkey::new_unique(),
+        event_authority: Pubkey::new_unique(),
+        program: Pubkey::new_unique(),
+    }
+    .to_account_metas(None)
+    .len()
+}
+
+#[test]
+fn active_token_subscriptions_can_be_cancelled() {
+    let user = Pubkey::new_unique();
+    assert_eq!(
+        subscription(user).batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_NONE
+    );
+}
+
+#[test]
+fn entries_the_batch_cannot_cancel_are_skipped_with_a_reason() {
+    let user = Pubkey::new_unique();
+
+    assert_eq!(
+        subscription(Pubkey::new_unique()).batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_INVALID_ACCOUNTS
+    );
+
+    let mut inactive = subscription(user);
+    inactive.is_active = false;
+    assert_eq!(
+        inactive.batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_NOT_ACTIVE
+    );
+
+    let mut sponsored = subscription(user);
+    sponsored.sponsor = Pubkey::new_unique();
+    assert_eq!(
+        sponsored.batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_SPONSORED
+    );
+
+    let mut compressed = subscription(user);
+    compressed.certificate_asset_id = Pubkey::new_unique();
+    assert_eq!(
+        compressed.batch_unsubscribe_skip_reason(&user),
+        UNSUBSCRIBE_REASON_COMPRESSED_CERTIFICATE
+    );
+}
+
+#[test]
+fn batch_size_is_pinned_by_account_and_compute_limits() {
+    assert_eq!(fixed_accounts(), 10);
+    assert_eq!(UNSUBSCRIBE_BATCH_STRIDE, 6);
+    assert_eq!(MAX_UNSUBSCRIBES_PER_BATCH, 9);
+
+    // Fills the account locks without exceeding them
+    let accounts = |entries: usize| fixed_accounts() + entries * UNSUBSCRIBE_BATCH_STRIDE;
+    assert!(accounts(MAX_UNSUBSCRIBES_PER_BATCH) <= MAX_TX_ACCOUNT_LOCKS);
+    assert!(accounts(MAX_UNSUBSCRIBES_PER_BATCH + 1) > MAX_TX_ACCOUNT_LOCKS);
+
+    // Every entry can get its compute budget in one transaction
+    assert!(
+        MAX_UNSUBSCRIBES_PER_BATCH as u64 * MIN_COMPUTE_UNITS_PER_UNSUBSCRIBE
+            <= MAX_TX_COMPUTE_UNITS
+    );
+
+    // Summary: two u32 counters, the unlocked total and one reason per entry
+    let summary_len = 4 + 4 + 8 + 4 + MAX_UNSUBSCRIBES_PER_BATCH;
+    assert!(summary_len <= MAX_RETURN_DATA);
+}
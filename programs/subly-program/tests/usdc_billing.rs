@@ -0,0 +1,57 @@
+//! A payment the user's USDC vault covers, sent to the compiled program: the fee
+//! leaves in USDC and its protocol share is counted in USDC, apart from the
+//! lamport totals SOL payments feed.
+//!
+//! Runs under `cargo test-sbf`, see common::program for the fixtures it needs.
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::token::spl_token;
+use common::program::*;
+use solana_sdk::signature::Signer;
+use subly_program::state::*;
+
+/// FEE_USD_CENTS in USDC base units, 6 decimals
+const USDC_FEE: u64 = FEE_USD_CENTS * 10_000;
+
+#[tokio::test]
+async fn a_usdc_payment_counts_its_protocol_fee_in_usdc() {
+    let fixture = Fixture::new();
+    let mut context = fixture.subscribed().await;
+    fixture.advance_to_next_payment(&mut context).await;
+    fixture.fund_usdc(&mut context, 2 * USDC_FEE).await;
+    let before: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+
+    let authority = context.payer.pubkey();
+    fixture
+        .run(&mut context, fixture.execute_usdc_payment(authority), &[])
+        .await;
+
+    let after: GlobalState = fixture.fetch(&mut context, fixture.global_state).await;
+    let protocol_fee = USDC_FEE * u64::from(before.protocol_fee_bps) / 10_000;
+    assert!(protocol_fee > 0);
+    assert_eq!(
+        after.total_protocol_fees_usdc,
+        before.total_protocol_fees_usdc + protocol_fee
+    );
+    // Nothing was paid in SOL
+    assert_eq!(after.total_volume_lamports, before.total_volume_lamports);
+    assert_eq!(
+        after.total_protocol_fees_lamports,
+        before.total_protocol_fees_lamports
+    );
+
+    let user: User = fixture.fetch(&mut context, fixture.user_account).await;
+    assert_eq!(user.usdc_balance, USDC_FEE);
+    let vault = fixture
+        .raw_account(&mut context, fixture.usdc_vault())
+        .await;
+    assert_eq!(
+        spl_token::state::Account::unpack(&vault.data)
+            .unwrap()
+            .amount,
+        USDC_FEE
+    );
+}